@@ -0,0 +1,175 @@
+//! Adapters that present library containers, such as those
+//! of the C++ standard library, as the nodes that describe them best.
+//!
+//! Without an adapter, a container is shown as the structure
+//! that implements it, which exposes the internals of the library,
+//! such as allocators and raw pointers to buffers, instead of its contents.
+
+use crate::options::GlobFilter;
+use aili_model::state::NodeTypeClass;
+
+/// Describes how the contents of an adapted container
+/// are represented in the state graph.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum ContainerShape {
+    /// Sequence of elements, such as `std::vector`.
+    ///
+    /// The container is represented by a [`NodeTypeClass::Array`] node
+    /// whose items are the elements of the container.
+    Sequence,
+
+    /// Associative container, such as `std::map`.
+    ///
+    /// The container is represented by a [`NodeTypeClass::Struct`] node
+    /// whose members are the values of the container, named by their keys.
+    Map,
+
+    /// Character string, such as `std::string`.
+    ///
+    /// The container is represented by a [`NodeTypeClass::Atom`] node
+    /// whose value is the [`String`](aili_model::state::NodeValue::String).
+    String,
+
+    /// Owning pointer, such as `std::unique_ptr`.
+    ///
+    /// The container is represented by a [`NodeTypeClass::Ref`] node
+    /// that refers to the owned object.
+    Pointer,
+}
+
+impl From<ContainerShape> for NodeTypeClass {
+    fn from(value: ContainerShape) -> Self {
+        match value {
+            ContainerShape::Sequence => Self::Array,
+            ContainerShape::Map => Self::Struct,
+            ContainerShape::String => Self::Atom,
+            ContainerShape::Pointer => Self::Ref,
+        }
+    }
+}
+
+/// Recognizes containers by the names of their types
+/// and assigns them a [`ContainerShape`].
+///
+/// Contents of adapted containers are read using
+/// [GDB's pretty-printers](https://sourceware.org/gdb/current/onlinedocs/gdb.html/Pretty-Printing.html),
+/// so a pretty-printer for the container must be installed in GDB.
+/// Printers for the C++ standard library are usually installed along with it.
+/// Containers that cannot be pretty-printed are shown as if they were not adapted.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ContainerAdapter {
+    types: GlobFilter,
+    shape: ContainerShape,
+}
+
+impl ContainerAdapter {
+    /// Constructs an adapter that assigns a shape
+    /// to all types whose names pass a filter.
+    ///
+    /// Type names are matched without the `const` qualifier.
+    pub fn new(types: GlobFilter, shape: ContainerShape) -> Self {
+        Self { types, shape }
+    }
+
+    /// Constructs adapters for the containers of the C++ standard library,
+    /// as implemented by both libstdc++ and libc++.
+    ///
+    /// ## Adapted Types
+    /// | Type              | Shape                                  |
+    /// |-------------------|----------------------------------------|
+    /// | `std::vector`     | [`Sequence`](ContainerShape::Sequence) |
+    /// | `std::map`        | [`Map`](ContainerShape::Map)           |
+    /// | `std::string`     | [`String`](ContainerShape::String)     |
+    /// | `std::unique_ptr` | [`Pointer`](ContainerShape::Pointer)   |
+    pub fn standard_library() -> Vec<Self> {
+        vec![
+            Self::new(
+                GlobFilter::new(["std::vector<*", "std::__1::vector<*"]),
+                ContainerShape::Sequence,
+            ),
+            Self::new(
+                GlobFilter::new(["std::map<*", "std::__1::map<*"]),
+                ContainerShape::Map,
+            ),
+            Self::new(
+                GlobFilter::new([
+                    "std::string",
+                    "std::basic_string<*",
+                    "std::__cxx11::string",
+                    "std::__cxx11::basic_string<*",
+                    "std::__1::string",
+                    "std::__1::basic_string<*",
+                ]),
+                ContainerShape::String,
+            ),
+            Self::new(
+                GlobFilter::new(["std::unique_ptr<*", "std::__1::unique_ptr<*"]),
+                ContainerShape::Pointer,
+            ),
+        ]
+    }
+
+    /// Gets the shape that the adapter assigns to containers.
+    pub fn shape(&self) -> ContainerShape {
+        self.shape
+    }
+
+    /// Checks whether the adapter applies to a type.
+    ///
+    /// Pointers, references, and arrays are never adapted,
+    /// even if their names start like the name of an adapted type.
+    pub fn matches(&self, type_name: &str) -> bool {
+        let is_derived_type = type_name.ends_with(['*', '&', ']']);
+        !is_derived_type && self.types.matches(type_name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn shape_of(type_name: &str) -> Option<ContainerShape> {
+        ContainerAdapter::standard_library()
+            .into_iter()
+            .find(|adapter| adapter.matches(type_name))
+            .map(|adapter| adapter.shape())
+    }
+
+    #[test]
+    fn standard_library_containers() {
+        assert_eq!(
+            shape_of("std::vector<int, std::allocator<int> >"),
+            Some(ContainerShape::Sequence)
+        );
+        assert_eq!(
+            shape_of("std::__1::vector<int, std::__1::allocator<int> >"),
+            Some(ContainerShape::Sequence)
+        );
+        assert_eq!(
+            shape_of(
+                "std::map<int, int, std::less<int>, std::allocator<std::pair<int const, int> > >"
+            ),
+            Some(ContainerShape::Map)
+        );
+        assert_eq!(shape_of("std::string"), Some(ContainerShape::String));
+        assert_eq!(
+            shape_of(
+                "std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> >"
+            ),
+            Some(ContainerShape::String)
+        );
+        assert_eq!(
+            shape_of("std::unique_ptr<int, std::default_delete<int> >"),
+            Some(ContainerShape::Pointer)
+        );
+    }
+
+    #[test]
+    fn other_types_are_not_adapted() {
+        assert_eq!(shape_of("int"), None);
+        assert_eq!(shape_of("std::vector<int, std::allocator<int> > *"), None);
+        assert_eq!(shape_of("std::vector<int, std::allocator<int> > [2]"), None);
+        assert_eq!(shape_of("my::vector<int>"), None);
+        assert_eq!(shape_of("std::pair<int, int>"), None);
+    }
+}
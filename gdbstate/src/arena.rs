@@ -0,0 +1,260 @@
+//! Storage of the nodes that represent variable objects.
+//!
+//! Variable objects are identified by their names, which are strings,
+//! so relations between nodes refer to variable nodes by their
+//! [`VariableIndex`] instead, which is cheaper to store and compare.
+//! [`GdbStateNodeId`](crate::state::GdbStateNodeId) remains the handle
+//! through which nodes are reached from the outside.
+
+use crate::{gdbmi::types::VariableObject, state::GdbStateNodeForVariable};
+use std::collections::HashMap;
+
+/// Stable reference to a node in a [`VariableArena`].
+///
+/// Indices are cheap to copy and compare, unlike [`VariableObject`]s,
+/// so they are used to keep track of relations between nodes.
+/// An index remains valid until its node is removed,
+/// after which it does not refer to any node, even if the slot
+/// in the arena is reused by another node.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct VariableIndex {
+    slot: u32,
+    generation: u32,
+}
+
+/// Slot of a [`VariableArena`].
+#[derive(Debug)]
+struct Slot {
+    /// Incremented each time the slot is vacated.
+    generation: u32,
+
+    /// The node that occupies the slot, if any,
+    /// and the variable object it represents.
+    entry: Option<(VariableObject, GdbStateNodeForVariable)>,
+}
+
+/// Arena of nodes that represent variable objects.
+///
+/// Nodes are stored in a list of slots and can be accessed
+/// either by their [`VariableIndex`] or by the variable object
+/// they represent. Slots of removed nodes are reused.
+#[derive(Debug, Default)]
+pub(crate) struct VariableArena {
+    slots: Vec<Slot>,
+    vacant: Vec<u32>,
+    indices: HashMap<VariableObject, VariableIndex>,
+}
+
+impl VariableArena {
+    /// Constructs an empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the number of nodes in the arena.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Checks whether a variable object has a node.
+    pub fn contains_key(&self, object: &VariableObject) -> bool {
+        self.indices.contains_key(object)
+    }
+
+    /// Gets the index of the node of a variable object.
+    pub fn index_of(&self, object: &VariableObject) -> Option<VariableIndex> {
+        self.indices.get(object).copied()
+    }
+
+    /// Gets the variable object represented by the node at an index.
+    pub fn object_at(&self, index: VariableIndex) -> Option<&VariableObject> {
+        self.entry_at(index).map(|(object, _)| object)
+    }
+
+    /// Gets the node of a variable object.
+    pub fn get(&self, object: &VariableObject) -> Option<&GdbStateNodeForVariable> {
+        self.get_at(self.index_of(object)?)
+    }
+
+    /// Gets the node of a variable object.
+    pub fn get_mut(&mut self, object: &VariableObject) -> Option<&mut GdbStateNodeForVariable> {
+        self.get_at_mut(self.index_of(object)?)
+    }
+
+    /// Gets the node at an index.
+    pub fn get_at(&self, index: VariableIndex) -> Option<&GdbStateNodeForVariable> {
+        self.entry_at(index).map(|(_, node)| node)
+    }
+
+    /// Gets the node at an index.
+    pub fn get_at_mut(&mut self, index: VariableIndex) -> Option<&mut GdbStateNodeForVariable> {
+        let slot = self.slots.get_mut(index.slot as usize)?;
+        if slot.generation != index.generation {
+            return None;
+        }
+        slot.entry.as_mut().map(|(_, node)| node)
+    }
+
+    /// Inserts the node of a variable object,
+    /// replacing the previous node of the same object, if any.
+    pub fn insert(
+        &mut self,
+        object: VariableObject,
+        node: GdbStateNodeForVariable,
+    ) -> &mut GdbStateNodeForVariable {
+        let index = match self.indices.get(&object).copied() {
+            Some(index) => index,
+            None => {
+                let index = self.allocate();
+                self.indices.insert(object.clone(), index);
+                index
+            }
+        };
+        let slot = &mut self.slots[index.slot as usize];
+        &mut slot.entry.insert((object, node)).1
+    }
+
+    /// Removes the node of a variable object.
+    ///
+    /// Its index becomes invalid.
+    pub fn remove(&mut self, object: &VariableObject) -> Option<GdbStateNodeForVariable> {
+        let index = self.indices.remove(object)?;
+        let slot = &mut self.slots[index.slot as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        self.vacant.push(index.slot);
+        slot.entry.take().map(|(_, node)| node)
+    }
+
+    /// Iterates over the nodes and the variable objects they represent.
+    pub fn iter(&self) -> impl Iterator<Item = (&VariableObject, &GdbStateNodeForVariable)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| slot.entry.as_ref())
+            .map(|(object, node)| (object, node))
+    }
+
+    /// Iterates over the nodes.
+    pub fn values(&self) -> impl Iterator<Item = &GdbStateNodeForVariable> {
+        self.iter().map(|(_, node)| node)
+    }
+
+    fn entry_at(&self, index: VariableIndex) -> Option<&(VariableObject, GdbStateNodeForVariable)> {
+        let slot = self.slots.get(index.slot as usize)?;
+        if slot.generation != index.generation {
+            return None;
+        }
+        slot.entry.as_ref()
+    }
+
+    /// Finds a vacant slot, or creates a new one.
+    fn allocate(&mut self) -> VariableIndex {
+        if let Some(slot) = self.vacant.pop() {
+            return VariableIndex {
+                slot,
+                generation: self.slots[slot as usize].generation,
+            };
+        }
+        self.slots.push(Slot {
+            generation: 0,
+            entry: None,
+        });
+        VariableIndex {
+            slot: (self.slots.len() - 1) as u32,
+            generation: 0,
+        }
+    }
+}
+
+/// Lookup of the variable objects that [`VariableIndex`]es refer to.
+///
+/// Implemented by [`VariableArena`] itself, and by maps
+/// that remember the variable objects of some of its indices,
+/// since an index never refers to any other variable object
+/// than the one it was allocated for.
+pub(crate) trait VariableNames {
+    /// Gets the variable object that an index refers to.
+    fn object_at(&self, index: VariableIndex) -> Option<&VariableObject>;
+}
+
+impl VariableNames for VariableArena {
+    fn object_at(&self, index: VariableIndex) -> Option<&VariableObject> {
+        VariableArena::object_at(self, index)
+    }
+}
+
+impl VariableNames for HashMap<VariableIndex, VariableObject> {
+    fn object_at(&self, index: VariableIndex) -> Option<&VariableObject> {
+        self.get(&index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::GdbStateNode;
+    use aili_model::state::NodeTypeClass;
+
+    fn variable(name: &str) -> VariableObject {
+        VariableObject(name.to_owned())
+    }
+
+    fn node(type_class: NodeTypeClass) -> GdbStateNodeForVariable {
+        GdbStateNodeForVariable::new(GdbStateNode::new(type_class), None)
+    }
+
+    #[test]
+    fn vacated_slot_is_reused() {
+        let mut arena = VariableArena::new();
+        arena.insert(variable("var1"), node(NodeTypeClass::Atom));
+        arena.insert(variable("var2"), node(NodeTypeClass::Atom));
+        let removed_index = arena.index_of(&variable("var1")).unwrap();
+        assert!(arena.remove(&variable("var1")).is_some());
+        arena.insert(variable("var3"), node(NodeTypeClass::Ref));
+        let new_index = arena.index_of(&variable("var3")).unwrap();
+        assert_eq!(new_index.slot, removed_index.slot);
+        assert_eq!(arena.slots.len(), 2);
+        assert_eq!(arena.len(), 2);
+    }
+
+    #[test]
+    fn stale_index_does_not_reach_new_occupant() {
+        let mut arena = VariableArena::new();
+        arena.insert(variable("var1"), node(NodeTypeClass::Atom));
+        let stale_index = arena.index_of(&variable("var1")).unwrap();
+        arena.remove(&variable("var1"));
+        assert!(arena.get_at(stale_index).is_none());
+        arena.insert(variable("var2"), node(NodeTypeClass::Ref));
+        assert!(arena.get_at(stale_index).is_none());
+        assert!(arena.get_at_mut(stale_index).is_none());
+        assert!(arena.object_at(stale_index).is_none());
+        let new_index = arena.index_of(&variable("var2")).unwrap();
+        assert_ne!(new_index, stale_index);
+        assert_eq!(arena.object_at(new_index), Some(&variable("var2")));
+        assert_eq!(
+            arena.get_at(new_index).unwrap().type_class,
+            NodeTypeClass::Ref
+        );
+    }
+
+    #[test]
+    fn replacing_node_keeps_its_index() {
+        let mut arena = VariableArena::new();
+        arena.insert(variable("var1"), node(NodeTypeClass::Atom));
+        let index = arena.index_of(&variable("var1")).unwrap();
+        arena.insert(variable("var1"), node(NodeTypeClass::Ref));
+        assert_eq!(arena.index_of(&variable("var1")), Some(index));
+        assert_eq!(arena.get_at(index).unwrap().type_class, NodeTypeClass::Ref);
+        assert_eq!(arena.len(), 1);
+    }
+
+    #[test]
+    fn removed_node_is_not_iterated() {
+        let mut arena = VariableArena::new();
+        arena.insert(variable("var1"), node(NodeTypeClass::Atom));
+        arena.insert(variable("var2"), node(NodeTypeClass::Atom));
+        arena.remove(&variable("var1"));
+        assert!(!arena.contains_key(&variable("var1")));
+        let objects: Vec<_> = arena.iter().map(|(object, _)| object.clone()).collect();
+        assert_eq!(objects, [variable("var2")]);
+    }
+}
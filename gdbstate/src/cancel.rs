@@ -0,0 +1,36 @@
+//! Cancellation of long-running operations on a state graph.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Shared flag that requests an operation to stop early.
+///
+/// Clones of a token share the same flag, so one clone can be
+/// passed to an operation, such as [`GdbStateGraph::update_cancellable`](crate::state::GdbStateGraph::update_cancellable),
+/// while another is kept by whoever decides that the operation
+/// is no longer needed, for example because the user
+/// has stepped the debuggee again.
+///
+/// Once cancelled, a token remains cancelled,
+/// so a new token should be created for each operation.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Constructs a token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests all operations that observe the token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Checks whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
@@ -1,9 +1,19 @@
 //! Construction of a [`GdbStateGraph`] using a [`GdbMiSession`].
 
 use crate::{
-    gdbmi::{result::Result, session::GdbMiSession, types::*},
+    adapters::ContainerShape,
+    arena::{VariableArena, VariableIndex},
+    cancel::CancellationToken,
+    gdbmi::{
+        result::{BadResponse, Error, Result},
+        session::GdbMiSession,
+        types::*,
+    },
+    heap::{self, HEAP_FUNCTION_SOURCE, HeapBlock},
     hints::PointerLengthHintKey,
+    options::GraphOptions,
     state::*,
+    warning::GraphWarning,
 };
 use aili_model::state::*;
 use aili_style::{
@@ -15,20 +25,57 @@ use aili_style::{
 use derive_more::{Debug, Deref, DerefMut};
 use regex::Regex;
 use std::{
-    collections::{BTreeMap, HashMap, VecDeque},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
     sync::LazyLock,
 };
 
+/// Python source of the `$_aili_layout` GDB convenience function.
+///
+/// The function takes a structure or union value and returns a string
+/// that starts with `struct` or `union`, followed by space-separated
+/// descriptions of bitfield members in the format `name:offset:width`.
+/// Offsets and widths are in bits.
+const LAYOUT_FUNCTION_SOURCE: &str = "\
+class AiliLayout(gdb.Function):
+    def __init__(self):
+        super().__init__('_aili_layout')
+    def invoke(self, value):
+        t = value.type.strip_typedefs()
+        kind = 'union' if t.code == gdb.TYPE_CODE_UNION else 'struct'
+        bitfields = ['%s:%d:%d' % (f.name, f.bitpos, f.bitsize) for f in t.fields() if f.name and f.bitsize]
+        return ' '.join([kind] + bitfields)
+AiliLayout()
+";
+
 impl GdbStateGraph {
     /// Constructs a state graph that only consists of the root node
     pub fn empty() -> Self {
         Self {
             root_node: GdbStateNode::new(NodeTypeClass::Root),
             stack_trace: Vec::new(),
-            variables: HashMap::new(),
+            variables: VariableArena::new(),
             length_nodes: HashMap::new(),
+            bitfield_nodes: HashMap::new(),
+            truncation_markers: HashMap::new(),
+            type_layouts: HashMap::new(),
+            layout_queries_enabled: false,
+            heap: None,
+            heap_queries_enabled: false,
+            threads: None,
+            registers: None,
+            register_names: Vec::new(),
+            watch_expression_nodes: None,
+            function_result: None,
+            pending_return_value: None,
             address_mapping: BTreeMap::new(),
-            resolved_length_hints: HashMap::new(),
+            resolved_hints: HashMap::new(),
+            hidden_union_members: HashMap::new(),
+            shared_subtrees: HashMap::new(),
+            watchpoints: None,
+            options: GraphOptions::default(),
+            needs_rebuild: false,
+            warning_handler: None,
         }
     }
 
@@ -48,15 +95,64 @@ impl GdbStateGraph {
     pub async fn new_with_hints(
         gdb: &mut impl GdbMiSession,
         pointer_hints: &CascadeStyle<PointerLengthHintKey>,
+    ) -> Result<Self> {
+        Self::new_with_options(gdb, pointer_hints, GraphOptions::default()).await
+    }
+
+    /// Constructs a new state graph using a provided GDB session,
+    /// a hint sheet to help deduce what each block of allocated memory is,
+    /// and options that control which variables are included.
+    ///
+    /// This function sends commands to GDB and awaits responses
+    /// asynchronously.
+    pub async fn new_with_options(
+        gdb: &mut impl GdbMiSession,
+        pointer_hints: &CascadeStyle<PointerLengthHintKey>,
+        options: GraphOptions,
+    ) -> Result<Self> {
+        Self::new_cancellable(gdb, pointer_hints, options, &CancellationToken::new()).await
+    }
+
+    /// Constructs a new state graph like [`GdbStateGraph::new_with_options`],
+    /// but stops early if a cancellation token is cancelled.
+    ///
+    /// The token is checked between commands sent to GDB.
+    /// If it is cancelled, construction fails with [`Error::Cancelled`]
+    /// and the variable objects created so far are erased from the session.
+    pub async fn new_cancellable(
+        gdb: &mut impl GdbMiSession,
+        pointer_hints: &CascadeStyle<PointerLengthHintKey>,
+        options: GraphOptions,
+        cancellation: &CancellationToken,
     ) -> Result<Self> {
         let mut graph = Self::empty();
-        let mut writer = GdbStateGraphWriter::new(&mut graph, gdb, pointer_hints);
-        writer.update_stack_trace().await?;
-        writer.resolve_length_hints_from(&GdbStateNodeId::Root);
-        writer.resolve_deferred_dereferences().await?;
+        graph.options = options;
+        graph.construct(gdb, pointer_hints, cancellation).await?;
         Ok(graph)
     }
 
+    /// Populates an empty state graph.
+    ///
+    /// If construction fails, the variable objects created so far
+    /// are erased from the session, but the graph is not emptied.
+    async fn construct(
+        &mut self,
+        gdb: &mut impl GdbMiSession,
+        pointer_hints: &CascadeStyle<PointerLengthHintKey>,
+        cancellation: &CancellationToken,
+    ) -> Result<()> {
+        let result = GdbStateGraphWriter::new(self, gdb, pointer_hints, cancellation)
+            .construct_graph()
+            .await;
+        if let Err(error) = result {
+            // The graph will be discarded,
+            // so nobody else can clean up after it
+            let _ = self.drop_variable_objects(gdb).await;
+            return Err(error);
+        }
+        Ok(())
+    }
+
     /// Updates an existing state graph using a provided GDB session.
     ///
     /// It is assumed that it is the same session that was passed
@@ -79,18 +175,189 @@ impl GdbStateGraph {
         gdb: &mut impl GdbMiSession,
         pointer_hints: &CascadeStyle<PointerLengthHintKey>,
     ) -> Result<()> {
-        let mut writer = GdbStateGraphWriter::new(self, gdb, pointer_hints);
-        writer.update_variable_objects().await?;
-        writer.update_stack_trace().await?;
-        writer.resolve_length_hints_from(&GdbStateNodeId::Root);
-        writer.resolve_deferred_dereferences().await?;
+        self.update_cancellable(gdb, pointer_hints, &CancellationToken::new())
+            .await
+    }
+
+    /// Updates an existing state graph like [`GdbStateGraph::update_with_hints`],
+    /// but stops early if a cancellation token is cancelled.
+    ///
+    /// The token is checked between commands sent to GDB.
+    /// If it is cancelled, the update fails with [`Error::Cancelled`]
+    /// and the graph is left incomplete. Changes that GDB has already reported
+    /// cannot be reported again, so the next update rebuilds the graph
+    /// as if by [`GdbStateGraph::resynchronize`].
+    pub async fn update_cancellable(
+        &mut self,
+        gdb: &mut impl GdbMiSession,
+        pointer_hints: &CascadeStyle<PointerLengthHintKey>,
+        cancellation: &CancellationToken,
+    ) -> Result<()> {
+        if self.needs_rebuild {
+            return self
+                .resynchronize_cancellable(gdb, pointer_hints, cancellation)
+                .await;
+        }
+        let result = GdbStateGraphWriter::new(self, gdb, pointer_hints, cancellation)
+            .update_graph()
+            .await;
+        if let Err(Error::Cancelled) = result {
+            self.needs_rebuild = true;
+        }
+        result
+    }
+
+    /// Brings an existing state graph in line with the current state
+    /// of the debuggee after execution has moved to an arbitrary point.
+    ///
+    /// Unlike [`GdbStateGraph::update`], this does not assume
+    /// that the stack has only grown or shrunk since the last update,
+    /// so it is suitable after reverse execution, or after jumping
+    /// between checkpoints of a replay, such as one made by `rr`.
+    /// The whole graph is rebuilt, so it always reflects a consistent
+    /// snapshot of the program state.
+    pub async fn resynchronize(&mut self, gdb: &mut impl GdbMiSession) -> Result<()> {
+        self.resynchronize_with_hints(gdb, &CascadeStyle::empty())
+            .await
+    }
+
+    /// Brings an existing state graph in line with the current state
+    /// of the debuggee after execution has moved to an arbitrary point,
+    /// using a hint sheet to help deduce what each block of allocated memory is.
+    ///
+    /// See [`GdbStateGraph::resynchronize`] for details.
+    pub async fn resynchronize_with_hints(
+        &mut self,
+        gdb: &mut impl GdbMiSession,
+        pointer_hints: &CascadeStyle<PointerLengthHintKey>,
+    ) -> Result<()> {
+        self.resynchronize_cancellable(gdb, pointer_hints, &CancellationToken::new())
+            .await
+    }
+
+    /// Rebuilds an existing state graph like [`GdbStateGraph::resynchronize_with_hints`],
+    /// but stops early if a cancellation token is cancelled.
+    ///
+    /// If the token is cancelled, the rebuild fails with [`Error::Cancelled`]
+    /// and the graph is left empty until the next update rebuilds it again.
+    pub async fn resynchronize_cancellable(
+        &mut self,
+        gdb: &mut impl GdbMiSession,
+        pointer_hints: &CascadeStyle<PointerLengthHintKey>,
+        cancellation: &CancellationToken,
+    ) -> Result<()> {
+        let watchpoints_enabled = self.watchpoints.is_some();
+        self.drop_variable_objects(gdb).await?;
+        // The new graph keeps the options and the warning handler of the old one
+        *self = Self {
+            options: std::mem::take(&mut self.options),
+            warning_handler: self.warning_handler.take(),
+            ..Self::empty()
+        };
+        if let Err(error) = self.construct(gdb, pointer_hints, cancellation).await {
+            // Do not keep handles to deleted variable objects around,
+            // but keep everything the next update needs to try again
+            *self = Self {
+                options: std::mem::take(&mut self.options),
+                warning_handler: self.warning_handler.take(),
+                watchpoints: watchpoints_enabled.then(WatchpointState::default),
+                needs_rebuild: true,
+                ..Self::empty()
+            };
+            return Err(error);
+        }
+        if watchpoints_enabled {
+            self.enable_watchpoints(gdb).await?;
+        }
+        Ok(())
+    }
+
+    /// Installs software watchpoints on all variables in the state graph,
+    /// including variables that appear in later updates.
+    ///
+    /// While watchpoints are enabled, [`GdbStateGraph::update`] only
+    /// reevaluates variables whose watchpoints have been reported
+    /// by [`GdbStateGraph::record_watchpoint_hit`] since the previous update,
+    /// instead of reevaluating every variable. This makes updates of large
+    /// programs faster, but it makes execution of the debuggee slower,
+    /// since GDB has to check the watched memory after each instruction.
+    ///
+    /// The caller is responsible for reporting all watchpoint hits
+    /// and all watchpoints that go out of scope, as they are announced
+    /// by GDB in [`AsyncExecRecord`](crate::gdbmi::raw_output::AsyncExecRecord)s.
+    /// Changes that have not been reported are not reflected by updates.
+    ///
+    /// GDB stops the debuggee each time a watched variable is written
+    /// to or goes out of scope, so continuing and stepping stop earlier
+    /// than they would without watchpoints. Passing each [`StopReason`]
+    /// to [`GdbStateGraph::record_stop`] reports the hit and tells
+    /// whether the debuggee only stopped because of a watchpoint,
+    /// in which case a continue command should be repeated.
+    pub async fn enable_watchpoints(&mut self, gdb: &mut impl GdbMiSession) -> Result<()> {
+        if self.watchpoints.is_some() {
+            return Ok(());
+        }
+        // Hardware watchpoints are scarce, we may need thousands of them
+        gdb.gdb_set("can-use-hw-watchpoints", "0").await?;
+        self.watchpoints = Some(WatchpointState::default());
+        GdbStateGraphWriter::new(self, gdb, &CascadeStyle::empty(), &CancellationToken::new())
+            .sync_watchpoints()
+            .await
+    }
+
+    /// Removes all watchpoints installed by [`GdbStateGraph::enable_watchpoints`]
+    /// and returns to reevaluating all variables on every update.
+    pub async fn disable_watchpoints(&mut self, gdb: &mut impl GdbMiSession) -> Result<()> {
+        let Some(watchpoints) = self.watchpoints.take() else {
+            return Ok(());
+        };
+        for number in watchpoints.by_number.into_keys() {
+            // Watchpoints that went out of scope have already been deleted by GDB
+            let _ = gdb.break_delete(number).await;
+        }
         Ok(())
     }
 
+    /// Informs the state graph that GDB has reported a watchpoint
+    /// being triggered or going out of scope.
+    ///
+    /// The number of the watchpoint can be obtained from GDB output
+    /// with [`AsyncExecRecord::watchpoint_number`](crate::gdbmi::raw_output::AsyncExecRecord::watchpoint_number).
+    /// Watchpoints that were not installed by the state graph are ignored.
+    pub fn record_watchpoint_hit(&mut self, number: usize) {
+        if let Some(watchpoints) = &mut self.watchpoints
+            && watchpoints.by_number.contains_key(&number)
+        {
+            watchpoints.pending_hits.push(number);
+        }
+    }
+
+    /// Informs the state graph of the reason why the debuggee has stopped.
+    ///
+    /// Hits of watchpoints installed by [`GdbStateGraph::enable_watchpoints`]
+    /// are recorded as if by [`GdbStateGraph::record_watchpoint_hit`].
+    ///
+    /// ## Return Value
+    /// True if the debuggee has stopped because of a watchpoint
+    /// installed by the state graph, false otherwise.
+    pub fn record_stop(&mut self, reason: &StopReason) -> bool {
+        let (StopReason::WatchpointTrigger { number } | StopReason::WatchpointScope { number }) =
+            *reason
+        else {
+            return false;
+        };
+        let installed = self
+            .watchpoints
+            .as_ref()
+            .is_some_and(|watchpoints| watchpoints.by_number.contains_key(&number));
+        self.record_watchpoint_hit(number);
+        installed
+    }
+
     /// Erases all variable objects associated with this state graph
     /// from the provided GDB session.
     pub async fn drop_variable_objects(&self, gdb: &mut impl GdbMiSession) -> Result<()> {
-        for (variable_handle, node) in &self.variables {
+        for (variable_handle, node) in self.variables.iter() {
             // Only top level nodes need to be deleted,
             // the rest will be cleaned up by GDB recursively
             if node.is_top_level() {
@@ -98,6 +365,10 @@ impl GdbStateGraph {
                 let _ = gdb.var_delete(variable_handle).await;
             }
         }
+        for number in self.watchpoints.iter().flat_map(|w| w.by_number.keys()) {
+            // Watchpoints that went out of scope have already been deleted by GDB
+            let _ = gdb.break_delete(*number).await;
+        }
         Ok(())
     }
 }
@@ -118,10 +389,25 @@ struct GdbStateGraphWriter<'a, T: GdbMiSession> {
     /// deduce the length of arrays.
     pointer_hint_sheet: &'a CascadeStyle<PointerLengthHintKey>,
 
+    /// Token that requests the operation to stop early.
+    cancellation: &'a CancellationToken,
+
     /// References to [`NodeTypeClass::Ref`] nodes whose
     /// [`EdgeLabel::Deref`] should be evaluated later.
     deferred_pointers: VecDeque<VariableObject>,
 
+    /// Pointers in [`GdbStateGraphWriter::deferred_pointers`],
+    /// so that each pointer is queued at most once.
+    queued_pointers: HashSet<VariableObject>,
+
+    /// Number of the thread that is currently selected in GDB,
+    /// if it is known.
+    active_thread: Option<usize>,
+
+    /// True if a stack frame other than the top one
+    /// may have been selected in GDB.
+    frame_selected: bool,
+
     /// Cloned stylesheet resolution variable pools
     /// at each [`NodeTypeClass::Ref`] node.
     stylesheet_snapshots: HashMap<
@@ -138,49 +424,200 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         graph: &'a mut GdbStateGraph,
         gdb: &'a mut T,
         pointer_hints: &'a CascadeStyle<PointerLengthHintKey>,
+        cancellation: &'a CancellationToken,
     ) -> Self {
         Self {
             pointer_hint_sheet: pointer_hints,
+            cancellation,
             graph,
             gdb,
             deferred_pointers: VecDeque::new(),
+            queued_pointers: HashSet::new(),
+            active_thread: None,
+            frame_selected: false,
             stylesheet_snapshots: HashMap::new(),
         }
     }
 
+    /// Populates a new state graph.
+    async fn construct_graph(&mut self) -> Result<()> {
+        self.configure_string_printing().await?;
+        self.configure_layout_queries().await;
+        self.configure_heap_queries().await;
+        self.configure_register_queries().await?;
+        self.populate_global_variables().await?;
+        self.update_stacks().await?;
+        self.resolve_length_hints_from(&GdbStateNodeId::Root);
+        self.resolve_deferred_dereferences().await?;
+        self.apply_union_variants();
+        self.share_read_only_subtrees();
+        self.check_cancelled()?;
+        self.update_heap_blocks().await;
+        self.update_registers().await?;
+        self.update_watch_expressions().await?;
+        self.restore_top_frame().await?;
+        Ok(())
+    }
+
+    /// Brings an existing state graph up to date.
+    async fn update_graph(&mut self) -> Result<()> {
+        // Members are hidden again once the hints have been resolved
+        self.restore_union_members();
+        // Same goes for shared subtrees once all objects have been updated
+        self.restore_shared_subtrees();
+        if self.watchpoints.is_some() {
+            self.update_watched_variable_objects().await?;
+        } else {
+            self.update_variable_objects().await?;
+        }
+        self.update_stacks().await?;
+        self.update_string_variables().await?;
+        self.check_cancelled()?;
+        self.update_adapted_containers().await?;
+        self.resolve_length_hints_from(&GdbStateNodeId::Root);
+        self.resolve_deferred_dereferences().await?;
+        self.apply_union_variants();
+        self.share_read_only_subtrees();
+        self.check_cancelled()?;
+        self.update_heap_blocks().await;
+        self.sync_watchpoints().await?;
+        self.update_registers().await?;
+        self.update_watch_expressions().await?;
+        self.update_function_result();
+        self.restore_top_frame().await?;
+        Ok(())
+    }
+
+    /// Fails with [`Error::Cancelled`] if the operation
+    /// has been cancelled.
+    fn check_cancelled(&self) -> Result<()> {
+        if self.cancellation.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
     async fn update_variable_objects(&mut self) -> Result<()> {
         let changelist = self.gdb.var_update(PrintValues::SimpleValues).await?;
         for change in &changelist {
+            self.check_cancelled()?;
             self.update_variable_object(change).await?;
         }
         Ok(())
     }
 
+    async fn update_watched_variable_objects(&mut self) -> Result<()> {
+        let Some(watchpoints) = &mut self.watchpoints else {
+            return Ok(());
+        };
+        let mut hits = std::mem::take(&mut watchpoints.pending_hits);
+        hits.sort_unstable();
+        hits.dedup();
+        for number in hits {
+            let Some(object) = self
+                .watchpoints
+                .as_ref()
+                .and_then(|w| w.by_number.get(&number))
+                .cloned()
+            else {
+                continue;
+            };
+            let changelist = self
+                .gdb
+                .var_update_object(PrintValues::SimpleValues, &object)
+                .await?;
+            for change in &changelist {
+                self.check_cancelled()?;
+                self.update_variable_object(change).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Installs watchpoints on top-level variable objects that do not have one
+    /// and removes watchpoints of variable objects that no longer exist.
+    ///
+    /// Does nothing if watchpoints are disabled.
+    async fn sync_watchpoints(&mut self) -> Result<()> {
+        let Some(watchpoints) = &self.watchpoints else {
+            return Ok(());
+        };
+        let stale_watchpoints = watchpoints
+            .by_number
+            .iter()
+            .filter(|(_, object)| !self.variables.contains_key(object))
+            .map(|(number, _)| *number)
+            .collect::<Vec<_>>();
+        let unwatched_objects = self
+            .variables
+            .iter()
+            .filter(|(object, node)| {
+                node.is_top_level() && !watchpoints.by_object.contains_key(object)
+            })
+            .map(|(object, node)| (object.clone(), node.parent.clone()))
+            .collect::<Vec<_>>();
+        for number in stale_watchpoints {
+            // Watchpoints that went out of scope have already been deleted by GDB
+            let _ = self.gdb.break_delete(number).await;
+            if let Some(watchpoints) = &mut self.watchpoints
+                && let Some(object) = watchpoints.by_number.remove(&number)
+            {
+                watchpoints.by_object.remove(&object);
+            }
+        }
+        for (object, parent) in unwatched_objects {
+            // Local variables must be watched in the context of their frame
+            if let Some(parent) = parent {
+                self.select_frame_node(&parent).await?;
+            }
+            let expression = self.gdb.var_info_path_expression(&object).await?;
+            let watchpoint = self.gdb.break_watch(&expression).await?;
+            if let Some(watchpoints) = &mut self.watchpoints {
+                watchpoints
+                    .by_number
+                    .insert(watchpoint.number, object.clone());
+                watchpoints.by_object.insert(object, watchpoint.number);
+            }
+        }
+        self.restore_top_frame().await?;
+        Ok(())
+    }
+
     async fn update_variable_object(&mut self, var_object: &VariableObjectUpdate) -> Result<()> {
-        if var_object.dynamic {
-            // TODO: Warn
-            // Dynamic variable objects should never be returned by GDB unless explicitly enabled
+        if var_object.dynamic
+            && self
+                .variables
+                .get(&var_object.object)
+                .is_none_or(|variable| variable.container_shape.is_none())
+        {
+            // Dynamic variable objects should never be returned by GDB
+            // unless explicitly enabled for adapted containers
+            self.warn(GraphWarning::IgnoredDynamicVariableObject(
+                var_object.object.clone(),
+            ));
         }
-        if var_object.new_type_name.is_some() {
-            // TODO: Warn
+        if let Some(type_name) = &var_object.new_type_name {
+            self.warn(GraphWarning::UnexpectedTypeChange {
+                object: var_object.object.clone(),
+                type_name: type_name.clone(),
+            });
         }
         if var_object.in_scope != InScope::True {
             self.variable_object_out_of_scope(&var_object.object)
                 .await?;
-        } else if let Some(variable) = self.variables.get_mut(&var_object.object) {
+        } else if let Some(variable) = self.variables.get_mut(&var_object.object)
+            && !variable.is_string
+            && variable.container_shape.is_none()
+        {
             // Otherwise, the value must have changed, so reevaluate it
+            // (unless it is a string or an adapted container,
+            // those are reevaluated separately)
             let new_value = var_object.value.as_deref().and_then(Self::parse_node_value);
             variable.value = new_value;
             // If the variable is a pointer, update its dereference
             if variable.type_class == NodeTypeClass::Ref {
-                if let Some(GdbStateNodeId::VarObject(old_deref_id)) =
-                    variable.remove_successor(&EdgeLabel::Deref)
-                {
-                    let dropped_last_ref = self.free_dereference(&var_object.object, &old_deref_id);
-                    if dropped_last_ref {
-                        self.remove_variables_recursive(&old_deref_id);
-                    }
-                }
+                self.unlink_dereference(&var_object.object);
                 // Resolve the dereference later
                 self.add_deferred_dereference(var_object.object.clone());
             }
@@ -190,18 +627,502 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         Ok(())
     }
 
+    /// Sets up GDB so that it prints strings in a format
+    /// that can be decoded, if strings are enabled.
+    async fn configure_string_printing(&mut self) -> Result<()> {
+        let Some(max_length) = self.options.strings else {
+            return Ok(());
+        };
+        self.gdb
+            .gdb_set("print elements", &max_length.to_string())
+            .await?;
+        // Repeated characters would otherwise be printed outside the quotes
+        self.gdb.gdb_set("print repeats", "unlimited").await?;
+        // Character arrays should end at the first null character
+        self.gdb.gdb_set("print null-stop", "on").await?;
+        Ok(())
+    }
+
+    /// Defines the convenience function that reports
+    /// the layouts of structures and unions.
+    ///
+    /// The function is implemented in Python, so layout queries
+    /// remain disabled if GDB has been built without Python support.
+    async fn configure_layout_queries(&mut self) {
+        let command = format!("python exec({LAYOUT_FUNCTION_SOURCE:?})");
+        self.layout_queries_enabled = self.gdb.interpreter_exec_console(&command).await.is_ok();
+    }
+
+    /// Defines the convenience function that enumerates heap blocks,
+    /// if heap blocks have been requested.
+    ///
+    /// The function is implemented in Python, so heap blocks
+    /// are not listed if GDB has been built without Python support.
+    async fn configure_heap_queries(&mut self) {
+        if self.options.heap_blocks {
+            let command = format!("python exec({HEAP_FUNCTION_SOURCE:?})");
+            self.heap_queries_enabled = self.gdb.interpreter_exec_console(&command).await.is_ok();
+        }
+    }
+
+    /// Lists heap blocks that are not reachable from any pointer in the graph.
+    ///
+    /// Pointers must be resolved before the blocks are listed,
+    /// since pointers stored in dereferenced objects count as well.
+    async fn update_heap_blocks(&mut self) {
+        if !self.heap_queries_enabled {
+            return;
+        }
+        // Heap cannot be walked if the program is not running,
+        // in which case there are no blocks to list
+        let blocks = self
+            .gdb
+            .data_evaluate_expression("$_aili_heap()")
+            .await
+            .ok()
+            .and_then(|value| Self::parse_string_value(&value, usize::MAX))
+            .map(|description| heap::parse_heap_blocks(&description))
+            .unwrap_or_default();
+        let pointers = self.variables.values().filter_map(|variable| {
+            match (variable.type_class, &variable.value) {
+                (NodeTypeClass::Ref, Some(NodeValue::Uint(address))) => Some(*address),
+                _ => None,
+            }
+        });
+        let blocks = heap::unreachable_blocks(blocks, pointers);
+        let heap_edge = EdgeLabel::Custom(HEAP_EDGE_LABEL.to_owned());
+        if self.heap.is_none() {
+            self.root_node.successors.push((heap_edge, NodeKey::Heap));
+        }
+        self.heap = Some(Self::create_heap_nodes(&blocks));
+    }
+
+    /// Creates the nodes that list heap blocks.
+    fn create_heap_nodes(blocks: &[HeapBlock]) -> HeapNodes {
+        let mut list = GdbStateNode::new(NodeTypeClass::Array);
+        list.successors
+            .push((EdgeLabel::Length, NodeKey::HeapLength));
+        let mut length = GdbStateNode::new(NodeTypeClass::Atom);
+        length.value = Some(NodeValue::Uint(blocks.len() as u64));
+        let blocks = blocks
+            .iter()
+            .enumerate()
+            .map(|(index, block)| {
+                list.successors
+                    .push((EdgeLabel::Index(index), NodeKey::HeapBlock(index)));
+                let mut block_node = GdbStateNode::new(NodeTypeClass::Struct);
+                block_node
+                    .add_named_successor("address".to_owned(), NodeKey::HeapBlockAddress(index));
+                block_node.add_named_successor("size".to_owned(), NodeKey::HeapBlockSize(index));
+                let mut address = GdbStateNode::new(NodeTypeClass::Atom);
+                address.value = Some(NodeValue::Uint(block.address));
+                let mut size = GdbStateNode::new(NodeTypeClass::Atom);
+                size.value = Some(NodeValue::Uint(block.size));
+                HeapBlockNodes {
+                    block: block_node,
+                    address,
+                    size,
+                }
+            })
+            .collect();
+        HeapNodes {
+            list,
+            length,
+            blocks,
+        }
+    }
+
+    /// Finds the numbers of the CPU registers that should be included,
+    /// if registers have been requested.
+    async fn configure_register_queries(&mut self) -> Result<()> {
+        let graph = &mut *self.graph;
+        let Some(filter) = &graph.options.registers else {
+            return Ok(());
+        };
+        let names = self.gdb.data_list_register_names().await?;
+        // Numbers that do not belong to any register have empty names
+        graph.register_names = names
+            .into_iter()
+            .enumerate()
+            .filter(|(_, name)| !name.is_empty() && filter.matches(name))
+            .collect();
+        Ok(())
+    }
+
+    /// Reads the values of CPU registers and attaches them
+    /// to the top-most frame of the selected thread.
+    async fn update_registers(&mut self) -> Result<()> {
+        if self.register_names.is_empty() {
+            return Ok(());
+        }
+        let thread = self.threads.as_ref().and_then(|threads| threads.selected);
+        let top_frame = self
+            .stack(thread)
+            .len()
+            .checked_sub(1)
+            .map(|frame_index| frame_id(thread, frame_index));
+        let registers_edge = EdgeLabel::Custom(REGISTERS_EDGE_LABEL.to_owned());
+        // Detach the registers from the frame that used to be on top
+        let previous_frame = self.registers.take().and_then(|registers| registers.frame);
+        if let Some(frame) = previous_frame.and_then(|frame| self.graph.get_mut(&frame)) {
+            frame.remove_successor(&registers_edge);
+        }
+        // Registers cannot be read if the program is not running
+        let Some(top_frame) = top_frame else {
+            return Ok(());
+        };
+        self.select_top_frame().await?;
+        let numbers = self
+            .register_names
+            .iter()
+            .map(|(number, _)| *number)
+            .collect::<Vec<_>>();
+        let values = self
+            .gdb
+            .data_list_register_values(RegisterFormat::Hexadecimal, &numbers)
+            .await?;
+        let mut registers = RegisterNodes {
+            list: GdbStateNode::new(NodeTypeClass::Struct),
+            values: BTreeMap::new(),
+            frame: Some(top_frame.clone()),
+        };
+        for register in values {
+            let Some(name) = self
+                .register_names
+                .iter()
+                .find(|(number, _)| *number == register.number)
+                .map(|(_, name)| name.clone())
+            else {
+                continue;
+            };
+            // Vector registers are printed as structures, so they have no value
+            let Some(value) = Self::parse_node_value(&register.value) else {
+                continue;
+            };
+            let mut node = GdbStateNode::new(NodeTypeClass::Atom);
+            node.value = Some(value);
+            registers
+                .list
+                .add_named_successor(name, NodeKey::Register(register.number));
+            registers.values.insert(register.number, node);
+        }
+        self.registers = Some(registers);
+        if let Some(frame) = self.graph.get_mut(&top_frame) {
+            frame.successors.push((registers_edge, NodeKey::Registers));
+        }
+        Ok(())
+    }
+
+    /// Evaluates watch expressions in the top-most frame
+    /// of the selected thread and attaches their values to the root.
+    async fn update_watch_expressions(&mut self) -> Result<()> {
+        let watch_edge = EdgeLabel::Custom(WATCH_EDGE_LABEL.to_owned());
+        if self.watch_expression_nodes.take().is_some() {
+            self.root_node.remove_successor(&watch_edge);
+        }
+        if self.options.watch_expressions.is_empty() {
+            return Ok(());
+        }
+        let mut watch_expressions = WatchExpressionNodes {
+            list: GdbStateNode::new(NodeTypeClass::Struct),
+            values: BTreeMap::new(),
+        };
+        let thread = self.threads.as_ref().and_then(|threads| threads.selected);
+        // Expressions cannot be evaluated if the program is not running
+        if !self.stack(thread).is_empty() {
+            self.select_top_frame().await?;
+            for (index, expression) in self
+                .options
+                .watch_expressions
+                .clone()
+                .into_iter()
+                .enumerate()
+            {
+                // Expressions that refer to variables that are not in scope fail
+                let value = match self.gdb.data_evaluate_expression(&expression).await {
+                    Ok(value) => value,
+                    Err(Error::ErrorResponse(_)) => continue,
+                    Err(error) => return Err(error),
+                };
+                // Pointers are printed with their type, as in `(int *) 0x4052a0`
+                let value = match value.strip_prefix('(') {
+                    Some(cast) => cast.split_once(") ").map_or(value.as_str(), |(_, v)| v),
+                    None => &value,
+                };
+                let Some(value) = Self::parse_node_value(value) else {
+                    continue;
+                };
+                let mut node = GdbStateNode::new(NodeTypeClass::Atom);
+                node.value = Some(value);
+                watch_expressions
+                    .list
+                    .add_named_successor(expression, NodeKey::WatchExpression(index));
+                watch_expressions.values.insert(index, node);
+            }
+        }
+        self.watch_expression_nodes = Some(watch_expressions);
+        self.root_node
+            .successors
+            .push((watch_edge, NodeKey::WatchExpressions));
+        Ok(())
+    }
+
+    /// Attaches the value that a function has returned, if it has been
+    /// [reported](GdbStateGraph::report_stop) since the last update,
+    /// to the top-most frame of the selected thread.
+    fn update_function_result(&mut self) {
+        let previous_frame = self.function_result.take().map(|result| result.frame);
+        if let Some(frame) = previous_frame.and_then(|frame| self.graph.get_mut(&frame)) {
+            frame.remove_successor(&EdgeLabel::Result);
+        }
+        let Some(return_value) = self.pending_return_value.take() else {
+            return;
+        };
+        let thread = self.threads.as_ref().and_then(|threads| threads.selected);
+        let Some(frame_index) = self.stack(thread).len().checked_sub(1) else {
+            return;
+        };
+        // Structures are printed as their members, so they have no value
+        let Some(value) = Self::parse_node_value(&return_value) else {
+            return;
+        };
+        let frame = frame_id(thread, frame_index);
+        let mut node = GdbStateNode::new(NodeTypeClass::Atom);
+        node.value = Some(value);
+        self.function_result = Some(FunctionResultNode {
+            node,
+            frame: frame.clone(),
+        });
+        if let Some(frame) = self.graph.get_mut(&frame) {
+            frame
+                .successors
+                .push((EdgeLabel::Result, NodeKey::FunctionResult));
+        }
+    }
+
+    /// Gets the layout of the type of a container node.
+    ///
+    /// The frame that the variable belongs to must be selected.
+    async fn type_layout(&mut self, var_object: &VariableObject) -> TypeLayout {
+        let type_name = self
+            .variables
+            .get(var_object)
+            .and_then(|node| node.type_name.clone())
+            .unwrap_or_default();
+        if let Some(layout) = self.type_layouts.get(&type_name) {
+            return layout.clone();
+        }
+        let mut layout = TypeLayout {
+            // Without Python, we can at least recognize unions
+            // that are not hidden behind a typedef
+            is_union: type_name.starts_with("union "),
+            bitfields: HashMap::new(),
+        };
+        // Children of adapted containers do not have path expressions
+        if self.layout_queries_enabled
+            && let Ok(expression) = self.gdb.var_info_path_expression(var_object).await
+        {
+            let description = self
+                .gdb
+                .data_evaluate_expression(&format!("$_aili_layout({expression})"))
+                .await
+                .ok()
+                .and_then(|value| Self::parse_string_value(&value, usize::MAX));
+            if let Some(description) = description {
+                layout = Self::parse_type_layout(&description);
+            }
+        }
+        // Anonymous types all have the same name, so they cannot be cached
+        if !type_name.contains('{') {
+            self.type_layouts.insert(type_name, layout.clone());
+        }
+        layout
+    }
+
+    /// Parses a layout description returned by the `$_aili_layout`
+    /// convenience function.
+    ///
+    /// Malformed bitfield entries are skipped, since the description
+    /// may be cut short if it exceeds GDB's `print elements` limit.
+    fn parse_type_layout(description: &str) -> TypeLayout {
+        let mut words = description.split_whitespace();
+        let is_union = words.next() == Some("union");
+        let bitfields = words
+            .filter_map(|word| {
+                let mut parts = word.split(':');
+                let name = parts.next()?;
+                let offset = parts.next()?.parse().ok()?;
+                let width = parts.next()?.parse().ok()?;
+                Some((name.to_owned(), (offset, width)))
+            })
+            .collect();
+        TypeLayout {
+            is_union,
+            bitfields,
+        }
+    }
+
+    /// Inserts the bit offset and width pseudo-nodes
+    /// under a node that represents a bitfield.
+    fn add_bitfield_nodes(&mut self, var_object: &VariableObject, offset: u64, width: u64) {
+        let mut offset_node = GdbStateNode::new(NodeTypeClass::Atom);
+        offset_node.value = Some(NodeValue::Uint(offset));
+        let mut width_node = GdbStateNode::new(NodeTypeClass::Atom);
+        width_node.value = Some(NodeValue::Uint(width));
+        let Some(index) = self.variables.index_of(var_object) else {
+            return;
+        };
+        self.bitfield_nodes.insert(
+            index,
+            BitfieldNodes {
+                offset: offset_node,
+                width: width_node,
+            },
+        );
+        let Some(node) = self.variables.get_at_mut(index) else {
+            return;
+        };
+        node.successors.push((
+            EdgeLabel::Custom(BIT_OFFSET_EDGE_LABEL.to_owned()),
+            NodeKey::BitOffset(index),
+        ));
+        node.successors.push((
+            EdgeLabel::Custom(BIT_WIDTH_EDGE_LABEL.to_owned()),
+            NodeKey::BitWidth(index),
+        ));
+    }
+
+    /// Reevaluates all nodes that hold strings.
+    ///
+    /// This is done on every update, since GDB does not report
+    /// changes in the characters of a character array
+    /// whose elements have not been listed.
+    async fn update_string_variables(&mut self) -> Result<()> {
+        let string_objects = self
+            .variables
+            .iter()
+            .filter(|(_, node)| node.is_string)
+            .map(|(object, _)| object.clone())
+            .collect::<Vec<_>>();
+        for object in string_objects {
+            self.select_frame_of_variable(&object).await?;
+            self.read_string_variable(&object).await?;
+        }
+        Ok(())
+    }
+
+    /// Decodes the string held by a node and stores it as the node's value.
+    ///
+    /// The frame that the variable belongs to must be selected.
+    async fn read_string_variable(&mut self, var_object: &VariableObject) -> Result<()> {
+        let max_length = self.options.strings.unwrap_or_default();
+        // Children of adapted containers do not have path expressions,
+        // and invalid pointers cannot be evaluated, they just do not get a value
+        let value = match self.gdb.var_info_path_expression(var_object).await {
+            Ok(expression) => self.gdb.data_evaluate_expression(&expression).await.ok(),
+            Err(_) => None,
+        }
+        .and_then(|value| Self::parse_string_value(&value, max_length));
+        if let Some(variable) = self.variables.get_mut(var_object) {
+            variable.value = value.map(NodeValue::String);
+        }
+        Ok(())
+    }
+
+    /// Selects the stack frame that a variable node belongs to.
+    ///
+    /// Does nothing if the variable does not belong to a frame.
+    async fn select_frame_of_variable(&mut self, var_object: &VariableObject) -> Result<()> {
+        let mut parent = self
+            .variables
+            .get(var_object)
+            .and_then(|v| v.parent.clone());
+        while let Some(GdbStateNodeId::VarObject(object)) = parent {
+            parent = self.variables.get(&object).and_then(|v| v.parent.clone());
+        }
+        if let Some(parent) = parent {
+            self.select_frame_node(&parent).await?;
+        }
+        Ok(())
+    }
+
+    /// Selects the thread and the stack frame that a frame node represents.
+    ///
+    /// Does nothing if the node is not a frame.
+    async fn select_frame_node(&mut self, frame: &GdbStateNodeId) -> Result<()> {
+        let Some((thread, frame_index)) = frame_location(frame) else {
+            return Ok(());
+        };
+        let level = self.stack(thread).len() - frame_index - 1;
+        self.select_thread(thread).await?;
+        self.gdb.stack_select_frame(level).await?;
+        self.frame_selected = true;
+        Ok(())
+    }
+
+    /// Selects the top frame of the thread that was selected
+    /// before the graph started updating.
+    async fn select_top_frame(&mut self) -> Result<()> {
+        self.select_thread(None).await?;
+        self.gdb.stack_select_frame(0).await?;
+        self.frame_selected = false;
+        Ok(())
+    }
+
+    /// Selects the thread that was selected before the graph
+    /// started updating, and its top frame if another frame
+    /// has been selected since, so that GDB is left
+    /// the way it was when the debuggee stopped.
+    async fn restore_top_frame(&mut self) -> Result<()> {
+        if self.frame_selected {
+            self.select_top_frame().await
+        } else {
+            self.select_thread(None).await
+        }
+    }
+
+    /// Selects a thread in GDB, or the thread that was selected
+    /// before the graph started updating if `thread` is [`None`].
+    ///
+    /// Does nothing if the graph does not include all threads.
+    async fn select_thread(&mut self, thread: Option<usize>) -> Result<()> {
+        let Some(thread) = thread.or(self.threads.as_ref().and_then(|t| t.selected)) else {
+            return Ok(());
+        };
+        if self.active_thread != Some(thread) {
+            self.gdb.thread_select(thread).await?;
+            self.active_thread = Some(thread);
+        }
+        Ok(())
+    }
+
     fn add_deferred_dereference(&mut self, var_object: VariableObject) {
-        self.deferred_pointers.push_back(var_object);
+        // A pointer that changes several times before it is resolved
+        // is only resolved once, with its latest value
+        if self.queued_pointers.insert(var_object.clone()) {
+            self.deferred_pointers.push_back(var_object);
+        }
     }
 
     async fn resolve_deferred_dereferences(&mut self) -> Result<()> {
         while let Some(ref_object) = self.deferred_pointers.pop_front() {
+            self.queued_pointers.remove(&ref_object);
+            self.check_cancelled()?;
+            // The pointer may have been truncated before its value changed
+            self.unmark_truncated(&ref_object);
+            // Pointers have a single dereference edge,
+            // so if it is already linked, it is relinked
+            self.unlink_dereference(&ref_object);
+            let type_hint = self.treat_as_hint(&ref_object);
             // Get the pointer node, bail if it has been removed
             let Some(node) = self.variables.get_mut(&ref_object) else {
                 continue;
             };
-            // Get the pointer's type name so we can cast properly
-            let pointer_type_name = node.type_name.clone();
+            // Get the pointer's type name so we can cast properly,
+            // unless the hint sheet says what it points to
+            let pointer_type_name = type_hint
+                .map(|type_name| pointer_to(&type_name))
+                .or_else(|| node.type_name.clone());
             // If it's a null pointer, it should not appear in the state graph
             let Some(NodeValue::Uint(address)) = node.value else {
                 continue;
@@ -209,6 +1130,12 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
             if address == 0 {
                 continue;
             }
+            // Objects that are already in the graph are linked regardless of limits
+            let deref_depth = self.deref_depth(&ref_object) + 1;
+            if !self.address_mapping.contains_key(&address) && self.exceeds_limits(deref_depth) {
+                self.mark_truncated(&ref_object);
+                continue;
+            }
             let can_access_target_address = self
                 .gdb
                 .data_evaluate_expression(&format!("*(char*){address}"))
@@ -222,8 +1149,9 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
             };
             // Get the length of the array if it exists
             let length_hint = self
-                .resolved_length_hints
+                .resolved_hints
                 .get(&ref_object)
+                .and_then(|hints| hints.length.as_ref())
                 .and_then(|hint| {
                     let context = EvaluationContext::from_graph(
                         self.graph,
@@ -238,7 +1166,12 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
                 });
             // TODO: Some errors can be ignored here
             let deref_var_object = self
-                .get_or_create_dereference_variable_node(address, &type_name, length_hint)
+                .get_or_create_dereference_variable_node(
+                    address,
+                    &type_name,
+                    length_hint,
+                    deref_depth,
+                )
                 .await?;
             self.link_dereference_relation(&ref_object, &deref_var_object);
             // Resolve the hint sheet from that node
@@ -249,6 +1182,7 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
                 resolver.push_edge(&EdgeLabel::Deref);
                 self.resolve_length_hints_from_snapshot(
                     &GdbStateNodeId::VarObject(deref_var_object),
+                    Some(&GdbStateNodeId::VarObject(ref_object.clone())),
                     variable_pool,
                     resolver,
                 );
@@ -257,6 +1191,56 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         Ok(())
     }
 
+    /// Gets the number of pointers that have been followed
+    /// from a variable to reach a node.
+    fn deref_depth(&self, var_object: &VariableObject) -> usize {
+        let mut node = self.variables.get(var_object);
+        while let Some(GdbStateNodeId::VarObject(parent)) = node.and_then(|n| n.parent.as_ref()) {
+            node = self.variables.get(parent);
+        }
+        node.map(|n| n.deref_depth).unwrap_or_default()
+    }
+
+    /// Checks whether a new node at a given dereference depth
+    /// would exceed the limits of the graph.
+    fn exceeds_limits(&self, deref_depth: usize) -> bool {
+        let limits = &self.options.limits;
+        limits.max_deref_depth.is_some_and(|max| deref_depth > max)
+            || limits
+                .max_nodes
+                .is_some_and(|max| self.variables.len() >= max)
+    }
+
+    /// Marks a node as having successors omitted due to a limit.
+    fn mark_truncated(&mut self, var_object: &VariableObject) {
+        let Some(index) = self.variables.index_of(var_object) else {
+            return;
+        };
+        if self.truncation_markers.contains_key(&index) {
+            return;
+        }
+        let Some(node) = self.variables.get_at_mut(index) else {
+            return;
+        };
+        node.successors.push((
+            EdgeLabel::Custom(TRUNCATED_EDGE_LABEL.to_owned()),
+            NodeKey::Truncated(index),
+        ));
+        let mut marker = GdbStateNode::new(NodeTypeClass::Atom);
+        marker.value = Some(NodeValue::Bool(true));
+        self.truncation_markers.insert(index, marker);
+    }
+
+    /// Removes the mark set by [`GdbStateGraphWriter::mark_truncated`].
+    fn unmark_truncated(&mut self, var_object: &VariableObject) {
+        if let Some(index) = self.variables.index_of(var_object)
+            && self.truncation_markers.remove(&index).is_some()
+            && let Some(node) = self.variables.get_at_mut(index)
+        {
+            node.remove_successor_by_key(NodeKey::Truncated(index));
+        }
+    }
+
     /// Unlinks a variable node from a pointer node that refers to it,
     /// updating reference counts.
     ///
@@ -273,19 +1257,40 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         &mut self,
         referer_handle: &VariableObject,
         dereference_handle: &VariableObject,
+    ) -> bool {
+        let Some(referer) = self.variables.index_of(referer_handle) else {
+            self.warn(GraphWarning::DanglingReferer {
+                referer: referer_handle.clone(),
+                target: dereference_handle.clone(),
+            });
+            return false;
+        };
+        self.free_dereference_at(referer_handle, referer, dereference_handle)
+    }
+
+    /// Same as [`free_dereference`](Self::free_dereference),
+    /// but the pointer node is identified by its index in the arena,
+    /// so it can be used after the pointer node has been removed.
+    #[must_use]
+    fn free_dereference_at(
+        &mut self,
+        referer_handle: &VariableObject,
+        referer: VariableIndex,
+        dereference_handle: &VariableObject,
     ) -> bool {
         let Some(dereference_node) = self.variables.get_mut(dereference_handle) else {
-            // TODO: Warn
+            self.warn(GraphWarning::DanglingReferer {
+                referer: referer_handle.clone(),
+                target: dereference_handle.clone(),
+            });
             return false;
         };
-        let Some(referer_index) = dereference_node
-            .referers
-            .iter()
-            .enumerate()
-            .find(|(_, r)| *r == referer_handle)
-            .map(|(i, _)| i)
+        let Some(referer_index) = dereference_node.referers.iter().position(|r| *r == referer)
         else {
-            // TODO: Warn
+            self.warn(GraphWarning::DanglingReferer {
+                referer: referer_handle.clone(),
+                target: dereference_handle.clone(),
+            });
             return false;
         };
         // Remove the expiring referer from the list of referers
@@ -297,15 +1302,18 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
 
     async fn variable_object_out_of_scope(&mut self, var_object: &VariableObject) -> Result<()> {
         // The variable has gone out of scope, so we destroy it
+        let index = self.variables.index_of(var_object);
         let parent_node = self.remove_variables_recursive(var_object);
         // Remove the reference to it from its parent frame
-        if let Some(GdbStateNodeId::Frame(frame_index)) = parent_node {
-            if let Some(frame) = self.stack_trace.get_mut(frame_index) {
-                frame.remove_successor_by_id(&GdbStateNodeId::VarObject(var_object.clone()));
+        if let Some(parent) = parent_node.filter(|id| frame_location(id).is_some()) {
+            if let Some(frame) = self.get_mut(&parent)
+                && let Some(index) = index
+            {
+                frame.remove_successor_by_key(NodeKey::VarObject(index));
             }
         } else {
             // Only local variables can go out of scope
-            // TODO: warn
+            self.warn(GraphWarning::UnexpectedOutOfScope(var_object.clone()));
         }
         self.gdb.var_delete(var_object).await?;
         Ok(())
@@ -340,6 +1348,7 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         &mut self,
         handle: &VariableObject,
     ) -> Option<(Option<GdbStateNodeId>, Vec<VariableObject>)> {
+        let index = self.variables.index_of(handle)?;
         let node = self.variables.remove(handle)?;
         // Keep track of what children need to be removed as well
         let mut to_remove = Vec::new();
@@ -348,55 +1357,184 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
             self.address_mapping.remove(&address);
         }
         // If the node has a length hint, remove it from that map
-        self.resolved_length_hints.remove(handle);
+        self.resolved_hints.remove(handle);
+        // If the node is a bitfield, remove its pseudo-nodes
+        self.bitfield_nodes.remove(&index);
+        self.truncation_markers.remove(&index);
         // Unlink dangling references
         for referer in node.referers {
-            if let Some(referer_node) = self.variables.get_mut(&referer) {
+            if let Some(referer_node) = self.variables.get_at_mut(referer) {
                 referer_node.remove_successor(&EdgeLabel::Deref);
             } else {
-                // TODO: Warn
-                // Referers should be kept up-to-date
+                // Referers are removed from the list before they are removed
+                // from the arena, so the index should never be stale
+                self.warn(GraphWarning::StaleReferer(handle.clone()));
             }
         }
         // Remove all child nodes
-        for (edge_label, next_object) in node.node.successors {
+        for (edge_label, next_key) in node.node.successors {
             match edge_label {
                 // These edges are what one would reasonably expect here
                 EdgeLabel::Named(_, _) | EdgeLabel::Index(_) | EdgeLabel::Length => {
-                    match next_object {
-                        GdbStateNodeId::Root | GdbStateNodeId::Frame(_) => {
-                            // TODO: Warn
-                        }
-                        GdbStateNodeId::VarObject(v) => {
-                            to_remove.push(v);
+                    match next_key {
+                        NodeKey::VarObject(v) => {
+                            if let Some(child) = self.variables.object_at(v) {
+                                to_remove.push(child.clone());
+                            }
                         }
-                        GdbStateNodeId::Length(v) => {
+                        NodeKey::Length(v) => {
                             self.length_nodes.remove(&v);
                         }
+                        _ => self.warn_unexpected_successor(handle, index, edge_label, next_key),
                     }
                 }
                 // Dereference edges have their own freeing mechanism
                 EdgeLabel::Deref => {
-                    if let GdbStateNodeId::VarObject(dereference) = next_object {
-                        let dropped_last_ref = self.free_dereference(handle, &dereference);
+                    if let NodeKey::VarObject(v) = next_key {
+                        let Some(dereference) = self.variables.object_at(v).cloned() else {
+                            continue;
+                        };
+                        let dropped_last_ref =
+                            self.free_dereference_at(handle, index, &dereference);
                         if dropped_last_ref {
                             to_remove.push(dereference);
                         }
                     } else {
-                        // TODO: Warn, only variable nodes should
+                        // Only variable nodes can be dereferenced
+                        self.warn_unexpected_successor(handle, index, edge_label, next_key);
                     }
                 }
+                // Bitfield and truncation pseudo-nodes
+                // have been removed with the node itself
+                EdgeLabel::Custom(_) => {}
                 // These edges cannot go from a variable node,
                 // so we emit a warning if it ever happens
                 EdgeLabel::Main | EdgeLabel::Next | EdgeLabel::Result => {
-                    // TODO: Warn
+                    self.warn_unexpected_successor(handle, index, edge_label, next_key);
                 }
             }
         }
         Some((node.parent, to_remove))
     }
 
-    async fn update_stack_trace(&mut self) -> Result<()> {
+    /// Warns about a successor of a variable node
+    /// that has already been removed from the arena.
+    ///
+    /// The pseudo-nodes of the removed node are named after its handle,
+    /// since its index no longer leads to it.
+    fn warn_unexpected_successor(
+        &mut self,
+        handle: &VariableObject,
+        index: VariableIndex,
+        edge: EdgeLabel,
+        successor: NodeKey,
+    ) {
+        let removed = HashMap::from([(index, handle.clone())]);
+        let Some(successor) = successor.to_id(&removed).or_else(|| self.id_of(successor)) else {
+            return;
+        };
+        self.warn(GraphWarning::UnexpectedSuccessor {
+            node: GdbStateNodeId::VarObject(handle.clone()),
+            edge,
+            successor,
+        });
+    }
+
+    /// Updates the stack of the selected thread,
+    /// or the stacks of all threads if the graph includes them.
+    async fn update_stacks(&mut self) -> Result<()> {
+        if self.options.threads {
+            self.update_threads().await
+        } else {
+            self.update_stack_trace(None).await
+        }
+    }
+
+    /// Updates the stacks of all threads
+    /// and forgets threads that have exited.
+    async fn update_threads(&mut self) -> Result<()> {
+        let thread_list = self.gdb.thread_info().await?;
+        self.active_thread = thread_list.current_thread_id;
+        let threads = self.threads.get_or_insert_with(ThreadNodes::new);
+        threads.selected = thread_list.current_thread_id;
+        threads
+            .stacks
+            .retain(|id, _| thread_list.threads.iter().any(|thread| thread.id == *id));
+        // Frames of running threads cannot be listed,
+        // so their stacks are left as they were
+        let stopped_threads = thread_list
+            .threads
+            .iter()
+            .filter(|thread| !thread.running)
+            .map(|thread| thread.id);
+        for id in stopped_threads {
+            self.check_cancelled()?;
+            self.select_thread(Some(id)).await?;
+            self.update_stack_trace(Some(id)).await?;
+        }
+        self.link_thread_stacks();
+        Ok(())
+    }
+
+    /// Lists the stacks of all threads in the array of threads
+    /// and links the stack of the selected thread to the root.
+    fn link_thread_stacks(&mut self) {
+        let graph = &mut *self.graph;
+        let Some(threads) = &mut graph.threads else {
+            return;
+        };
+        let bottom_frames = threads
+            .stacks
+            .iter()
+            .filter(|(_, stack)| !stack.is_empty())
+            .map(|(id, _)| NodeKey::ThreadFrame(*id, 0))
+            .collect::<Vec<_>>();
+        threads.list.successors = std::iter::once((EdgeLabel::Length, NodeKey::ThreadsLength))
+            .chain(
+                bottom_frames
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .map(|(index, frame)| (EdgeLabel::Index(index), frame)),
+            )
+            .collect();
+        threads.length.value = Some(NodeValue::Uint(bottom_frames.len() as u64));
+        let threads_edge = EdgeLabel::Custom(THREADS_EDGE_LABEL.to_owned());
+        if !graph
+            .root_node
+            .successors
+            .iter()
+            .any(|(edge, _)| *edge == threads_edge)
+        {
+            graph
+                .root_node
+                .successors
+                .push((threads_edge, NodeKey::Threads));
+        }
+        // The main edge leads to the stack of the selected thread
+        let main = threads
+            .selected
+            .map(|id| NodeKey::ThreadFrame(id, 0))
+            .filter(|frame| bottom_frames.contains(frame));
+        let current_main = graph
+            .root_node
+            .successors
+            .iter()
+            .find(|(edge, _)| *edge == EdgeLabel::Main)
+            .map(|(_, frame)| *frame);
+        if current_main != main {
+            graph.root_node.remove_successor(&EdgeLabel::Main);
+            if let Some(main) = main {
+                graph.root_node.successors.push((EdgeLabel::Main, main));
+            }
+        }
+    }
+
+    /// Updates the stack of a thread, or of the selected thread
+    /// if `thread` is [`None`].
+    ///
+    /// The thread must be selected.
+    async fn update_stack_trace(&mut self, thread: Option<usize>) -> Result<()> {
         let stack_trace = self.gdb.stack_list_frames().await?;
         // There is no way to tell if the top stack frame has
         // returned and then the same function was called
@@ -406,8 +1544,8 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         // Traverse the stack from the bottom up and update
         // everything after the first frame that does not match
         // the cached state
-        let update_index = self
-            .stack_trace
+        let cached_stack_trace = self.stack(thread);
+        let update_index = cached_stack_trace
             .iter()
             // Reverse the trace from GDB, it lists frames starting from the top
             .zip(stack_trace.iter().rev())
@@ -418,23 +1556,68 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
             .map(|(i, _)| i)
             // If all available frames match, at least the frames that are only
             // cached but no longer reported by GDB (or vice versa) must be updated
-            .unwrap_or(self.stack_trace.len().min(stack_trace.len()));
+            .unwrap_or(cached_stack_trace.len().min(stack_trace.len()));
         // Drop all cached frames starting at the first different frame
-        self.drop_stack_frames_after(update_index);
+        self.drop_stack_frames_after(thread, update_index);
+        // Frames that are kept may have moved on to other lines
+        for (frame_node, frame) in self
+            .stack_mut(thread)
+            .iter_mut()
+            .zip(stack_trace.iter().rev())
+        {
+            frame_node.source_location = source_location(frame);
+        }
         // New variables may have come into scope at the topmost unchanged frame
         if update_index > 0 {
             self.gdb
                 .stack_select_frame(stack_trace[stack_trace.len() - update_index].level)
                 .await?;
-            self.update_local_variables(update_index - 1).await?;
+            self.update_local_variables(thread, update_index - 1)
+                .await?;
         }
         // Create new frames starting at the first different frame
         let frames_to_push = stack_trace.into_iter().rev().skip(update_index);
-        self.push_stack_frames(frames_to_push).await?;
+        self.push_stack_frames(thread, frames_to_push).await?;
         Ok(())
     }
 
-    async fn update_local_variables(&mut self, frame_index: usize) -> Result<()> {
+    /// Gets the stack frames of a thread, or of the selected thread
+    /// if `thread` is [`None`].
+    fn stack(&self, thread: Option<usize>) -> &[GdbStateNode] {
+        match thread {
+            None => self.stack_trace.as_slice(),
+            Some(thread) => self
+                .threads
+                .as_ref()
+                .and_then(|threads| threads.stacks.get(&thread))
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Gets the stack frames of a thread, or of the selected thread
+    /// if `thread` is [`None`], for modification.
+    ///
+    /// Threads must have been listed before the stack of a thread is modified.
+    fn stack_mut(&mut self, thread: Option<usize>) -> &mut Vec<GdbStateNode> {
+        match thread {
+            None => &mut self.graph.stack_trace,
+            Some(thread) => self
+                .graph
+                .threads
+                .as_mut()
+                .expect("Threads should be listed before their stacks are modified")
+                .stacks
+                .entry(thread)
+                .or_default(),
+        }
+    }
+
+    async fn update_local_variables(
+        &mut self,
+        thread: Option<usize>,
+        frame_index: usize,
+    ) -> Result<()> {
         let mut locals = self
             .gdb
             .stack_list_variables(PrintValues::NoValues, false)
@@ -442,6 +1625,7 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         // Sort the output by name so that variables of the same name end up together
         locals.sort_by(|a, b| a.name.cmp(&b.name));
         let mut locals = locals.into_iter().peekable();
+        let mut missing_locals = Vec::new();
         // Go through all local variables
         while let Some(local) = locals.next() {
             let name = local.name;
@@ -454,128 +1638,176 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
             }
             // We can only get one variable value, assume it is the one
             // with largest discriminator (the most recently declared one)
-            let edge_id = EdgeLabel::Named(name.clone(), overloads);
+            let edge_id = EdgeLabel::Named(name.as_str().into(), overloads);
             // Check that the parent (the stack frame node) knows about the variable
-            let has_the_variable = self.stack_trace[frame_index]
+            let has_the_variable = self.stack(thread)[frame_index]
                 .successors
                 .iter()
                 .any(|(e, _)| *e == edge_id);
-            // If the stack frame does not know about the variable, create it now
+            // If the stack frame does not know about the variable, create it
             if !has_the_variable {
-                self.create_local_variable(frame_index, &name, edge_id)
-                    .await?;
+                missing_locals.push((name, edge_id));
             }
             // TODO: Check that the stack knows about all shadowed variables as well,
             // and warn if it does not (they are not reachable from our current point)
         }
+        self.create_local_variables(thread, frame_index, missing_locals)
+            .await
+    }
+
+    /// Creates variable objects for local variables of a frame
+    /// in one batch and adds them to the frame.
+    async fn create_local_variables(
+        &mut self,
+        thread: Option<usize>,
+        frame_index: usize,
+        locals: Vec<(String, EdgeLabel)>,
+    ) -> Result<()> {
+        let names = locals
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        let var_objects = self
+            .gdb
+            .var_create_batch(VariableObjectFrameContext::CurrentFrame, &names)
+            .await;
+        let mut var_objects = var_objects.into_iter();
+        for (name, edge_label) in locals {
+            let var_object = var_objects
+                .next()
+                .unwrap_or_else(|| Err(BadResponse::MissingResultRecord.into()));
+            let result = match var_object {
+                Ok(var_object) => {
+                    self.create_local_variable(thread, frame_index, &name, edge_label, var_object)
+                        .await
+                }
+                Err(error) => Err(error),
+            };
+            if let Err(error) = result {
+                // The rest of the batch has been created as well,
+                // but it will not be added to the graph
+                for var_object in var_objects.flatten() {
+                    let _ = self.gdb.var_delete(&var_object.object).await;
+                }
+                return Err(error);
+            }
+        }
         Ok(())
     }
 
     async fn create_local_variable(
         &mut self,
+        thread: Option<usize>,
         frame_index: usize,
         name: &str,
         edge_label: EdgeLabel,
+        var_object: VariableObjectData,
     ) -> Result<()> {
-        let var_object = self
-            .gdb
-            .var_create(VariableObjectFrameContext::CurrentFrame, name)
-            .await?;
         let handle = self
-            .create_variable_tree(var_object, Some(GdbStateNodeId::Frame(frame_index)))
+            .create_variable_tree(var_object, Some(frame_id(thread, frame_index)))
             .await?;
-        let id = GdbStateNodeId::VarObject(handle.clone());
-        self.stack_trace[frame_index]
-            .successors
-            .push((edge_label, id));
+        if let Some(index) = self.variables.index_of(&handle) {
+            self.stack_mut(thread)[frame_index]
+                .successors
+                .push((edge_label, NodeKey::VarObject(index)));
+        }
         self.add_variable_to_address_map(name, handle, false)
             .await?;
         Ok(())
     }
 
-    fn drop_stack_frames_after(&mut self, update_index: usize) {
+    fn drop_stack_frames_after(&mut self, thread: Option<usize>, update_index: usize) {
         // Drop frames until there is the requested amount
-        while self.stack_trace.len() > update_index {
-            self.pop_stack_frame();
+        while self.stack(thread).len() > update_index {
+            self.pop_stack_frame(thread);
         }
         // Unlink the reference in the preceding node
+        // (stacks of threads are linked to the root separately)
         if update_index == 0 {
-            self.root_node.remove_successor(&EdgeLabel::Main);
+            if thread.is_none() {
+                self.root_node.remove_successor(&EdgeLabel::Main);
+            }
         } else {
-            self.stack_trace[update_index - 1].remove_successor(&EdgeLabel::Next);
+            self.stack_mut(thread)[update_index - 1].remove_successor(&EdgeLabel::Next);
         }
     }
 
     /// Panics if the stack is empty
-    fn pop_stack_frame(&mut self) {
+    fn pop_stack_frame(&mut self, thread: Option<usize>) {
         // Variable objects should be invalidated by GDB,
         // so we do not remove those manually
-        self.stack_trace.pop().unwrap();
+        self.stack_mut(thread).pop().unwrap();
     }
 
     async fn push_stack_frames(
         &mut self,
+        thread: Option<usize>,
         new_frames: impl IntoIterator<Item = StackFrame>,
     ) -> Result<()> {
         for frame in new_frames {
-            self.push_stack_frame(frame).await?;
+            self.check_cancelled()?;
+            self.push_stack_frame(thread, frame).await?;
         }
         Ok(())
     }
 
-    async fn push_stack_frame(&mut self, frame: StackFrame) -> Result<()> {
+    async fn push_stack_frame(&mut self, thread: Option<usize>, frame: StackFrame) -> Result<()> {
         // Get the expected index of the frame
-        let frame_index = self.stack_trace.len();
+        let frame_index = self.stack(thread).len();
         // Create the node and add it to the trace
         let mut frame_node = GdbStateNode::new(NodeTypeClass::Frame);
+        frame_node.source_location = source_location(&frame);
         frame_node.type_name = Some(frame.func);
-        self.stack_trace.push(frame_node);
+        self.stack_mut(thread).push(frame_node);
         // Link the frame to the previous one or to the root node
+        // (stacks of threads are linked to the root separately)
         if frame_index == 0 {
-            self.root_node
-                .successors
-                .push((EdgeLabel::Main, GdbStateNodeId::Frame(0)));
+            if thread.is_none() {
+                self.root_node
+                    .successors
+                    .push((EdgeLabel::Main, NodeKey::Frame(0)));
+            }
         } else {
-            self.stack_trace[frame_index - 1]
+            self.stack_mut(thread)[frame_index - 1]
                 .successors
-                .push((EdgeLabel::Next, GdbStateNodeId::Frame(frame_index)));
+                .push((EdgeLabel::Next, frame_key(thread, frame_index)));
         }
         // Populate all local variables
         self.gdb.stack_select_frame(frame.level).await?;
-        self.update_local_variables(frame_index).await?;
+        self.update_local_variables(thread, frame_index).await?;
         Ok(())
     }
 
     fn resolve_length_hints_from(&mut self, origin: &GdbStateNodeId) {
         let variable_pool = VariablePool::default();
         let resolver = SelectorResolver::new(self.pointer_hint_sheet.selector_machine());
-        self.resolve_length_hints_from_snapshot(origin, variable_pool, resolver);
+        self.resolve_length_hints_from_snapshot(origin, None, variable_pool, resolver);
     }
 
+    /// Resolves the hint sheet from a node,
+    /// which has been reached from the node `referer` by a dereference edge,
+    /// unless it is the root.
     fn resolve_length_hints_from_snapshot(
         &mut self,
         origin: &GdbStateNodeId,
+        referer: Option<&GdbStateNodeId>,
         mut variable_pool: VariablePool<&'a str, GdbStateNodeId>,
         mut resolver: SelectorResolver<'a, GdbStateNodeId>,
     ) {
-        let mut resolved_hints = std::mem::take(&mut self.resolved_length_hints);
+        let mut resolved_hints = std::mem::take(&mut self.resolved_hints);
         let mut snapshots = std::mem::take(&mut self.stylesheet_snapshots);
         // If running from root, there is no preceding edge
-        // Otherwise assume the entry point is after a dereference edge
-        let preceding_edge = if *origin == GdbStateNodeId::Root {
-            None
-        } else {
-            Some(EdgeLabel::Deref)
-        };
+        // Otherwise the entry point is after a dereference edge
+        let previous = referer.map(|referer| (referer, &EdgeLabel::Deref));
         self.resolve_length_hints_with_resolver_from(
             origin,
             &mut resolver,
             &mut variable_pool,
             &mut resolved_hints,
             &mut snapshots,
-            preceding_edge.as_ref(),
+            previous,
         );
-        self.resolved_length_hints = resolved_hints;
+        self.resolved_hints = resolved_hints;
         self.stylesheet_snapshots = snapshots;
     }
 
@@ -584,7 +1816,7 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         origin: &GdbStateNodeId,
         resolver: &mut SelectorResolver<'a, GdbStateNodeId>,
         variable_pool: &mut VariablePool<&'a str, GdbStateNodeId>,
-        resolved_hints: &mut HashMap<VariableObject, PropertyValue<GdbStateNodeId>>,
+        resolved_hints: &mut HashMap<VariableObject, ResolvedHints>,
         snapshots: &mut HashMap<
             VariableObject,
             (
@@ -592,21 +1824,27 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
                 SelectorResolver<'a, GdbStateNodeId>,
             ),
         >,
-        previous_edge: Option<&EdgeLabel>,
+        previous: Option<(&GdbStateNodeId, &EdgeLabel)>,
     ) {
+        let previous_node = previous.map(|(node, _)| node.clone());
+        let previous_edge = previous.map(|(_, edge_label)| edge_label);
         let context = EvaluationContext::from_graph(self.graph, origin.clone())
             .with_variables(variable_pool)
+            .with_optional_parent(previous_node.clone())
             .with_optional_preceding_edge(previous_edge);
         let matched_rules = resolver.resolve_node(origin.clone(), &context);
+        let origin_type_class = self.graph.get(origin).map(|node| node.node_type_class());
         for (rule_index, caret) in matched_rules {
             let rule = self.pointer_hint_sheet.rule_at(rule_index);
             if caret == SelectionCaret::PrecedingEdge || rule.extra_label.is_some() {
-                // TODO: Warn, this kind of rules should not appear here
+                // This kind of rules should not appear here
+                self.warn(GraphWarning::UnsupportedHintRule(rule_index));
                 continue;
             }
             for property in &rule.properties {
                 let context = EvaluationContext::from_graph(self.graph, origin.clone())
                     .with_variables(variable_pool)
+                    .with_optional_parent(previous_node.clone())
                     .with_optional_preceding_edge(previous_edge);
                 match &property.key {
                     StyleKey::Variable(name) => {
@@ -617,64 +1855,393 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
                         // If it is a variable node, resolve the
                         if let GdbStateNodeId::VarObject(var_object) = origin {
                             let length_value = evaluate(&property.value, &context);
-                            resolved_hints.insert(var_object.clone(), length_value);
+                            resolved_hints.entry(var_object.clone()).or_default().length =
+                                Some(length_value);
+                        } else {
+                            // Only variables should be assigned lengths
+                            self.warn(GraphWarning::MisplacedLengthHint(origin.clone()));
+                        }
+                    }
+                    StyleKey::Property(PointerLengthHintKey::TreatAs) => {
+                        if let GdbStateNodeId::VarObject(var_object) = origin
+                            && origin_type_class == Some(NodeTypeClass::Ref)
+                        {
+                            let type_value = evaluate(&property.value, &context);
+                            resolved_hints
+                                .entry(var_object.clone())
+                                .or_default()
+                                .treat_as = Some(type_value);
+                        } else {
+                            self.warn(GraphWarning::MisplacedTypeHint(origin.clone()));
+                        }
+                    }
+                    StyleKey::Property(PointerLengthHintKey::UnionVariant) => {
+                        if let GdbStateNodeId::VarObject(var_object) = origin
+                            // Unions are only told apart from structures
+                            // if GDB can report the layouts of types
+                            && matches!(
+                                origin_type_class,
+                                Some(NodeTypeClass::Union | NodeTypeClass::Struct)
+                            )
+                        {
+                            let variant_value = evaluate(&property.value, &context);
+                            resolved_hints
+                                .entry(var_object.clone())
+                                .or_default()
+                                .union_variant = Some(variant_value);
+                        } else {
+                            self.warn(GraphWarning::MisplacedUnionVariantHint(origin.clone()));
+                        }
+                    }
+                    StyleKey::Property(PointerLengthHintKey::Variant) => {
+                        if let GdbStateNodeId::VarObject(var_object) = origin
+                            && matches!(
+                                origin_type_class,
+                                Some(NodeTypeClass::Union | NodeTypeClass::Struct)
+                            )
+                        {
+                            let variant_value = evaluate(&property.value, &context);
+                            resolved_hints
+                                .entry(var_object.clone())
+                                .or_default()
+                                .variant = Some(variant_value);
+                        } else {
+                            self.warn(GraphWarning::MisplacedVariantHint(origin.clone()));
+                        }
+                    }
+                }
+            }
+        }
+        // Return early if we know no selectors can match past this point
+        if !resolver.has_edges_to_resolve() {
+            return;
+        }
+        // Save a snapshot so we can return to it later
+        if self
+            .graph
+            .get(origin)
+            .is_some_and(|n| n.node_type_class() == NodeTypeClass::Ref)
+            && let GdbStateNodeId::VarObject(var_object) = origin
+        {
+            snapshots.insert(
+                var_object.clone(),
+                (variable_pool.snapshot(), resolver.snapshot()),
+            );
+        }
+        for (edge_label, successor) in self
+            .graph
+            .key_of(origin)
+            .and_then(|key| self.graph.node(key))
+            .into_iter()
+            .flat_map(|n| &n.successors)
+        {
+            if *edge_label == EdgeLabel::Deref {
+                // Do not resolve past a dereference edge,
+                // each heap-allocated object will be the root of its own resolution
+                continue;
+            }
+            let Some(successor) = self.graph.id_of(*successor) else {
+                continue;
+            };
+
+            variable_pool.push();
+            resolver.push_edge(edge_label);
+            self.resolve_length_hints_with_resolver_from(
+                &successor,
+                resolver,
+                variable_pool,
+                resolved_hints,
+                snapshots,
+                Some((origin, edge_label)),
+            );
+            resolver.pop_edge();
+            variable_pool.pop();
+        }
+    }
+
+    /// Gets the type that the hint sheet says a pointer points to.
+    fn treat_as_hint(&self, var_object: &VariableObject) -> Option<String> {
+        let hint = self.resolved_hints.get(var_object)?.treat_as.clone()?;
+        let context = EvaluationContext::from_graph(
+            self.graph,
+            GdbStateNodeId::VarObject(var_object.clone()),
+        );
+        hint_string(unwrap_node_value(hint, &context))
+    }
+
+    /// Hides all members of unions except those
+    /// that the hint sheet has selected.
+    fn apply_union_variants(&mut self) {
+        let hints = self
+            .resolved_hints
+            .iter()
+            .flat_map(|(var_object, hints)| {
+                [
+                    hints
+                        .union_variant
+                        .clone()
+                        .map(|hint| (var_object.clone(), hint, false)),
+                    hints
+                        .variant
+                        .clone()
+                        .map(|hint| (var_object.clone(), hint, true)),
+                ]
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        for (var_object, hint, is_tagged_union) in hints {
+            let id = GdbStateNodeId::VarObject(var_object.clone());
+            let context = EvaluationContext::from_graph(self.graph, id.clone());
+            let Some(variant) = hint_string(unwrap_node_value(hint, &context)) else {
+                continue;
+            };
+            if !is_tagged_union {
+                self.select_union_variant(var_object, &variant);
+                continue;
+            }
+            let unions = self.unions_of_tagged_union(&id);
+            if unions.is_empty() {
+                self.warn(GraphWarning::MisplacedVariantHint(id));
+            }
+            for union in unions {
+                self.select_union_variant(union, &variant);
+            }
+        }
+    }
+
+    /// Finds the unions whose active member is selected
+    /// by a [`Variant`](PointerLengthHintKey::Variant) hint
+    /// assigned to a node.
+    ///
+    /// That is the node itself if it is a union,
+    /// or all its members that are unions otherwise.
+    fn unions_of_tagged_union(&self, id: &GdbStateNodeId) -> Vec<VariableObject> {
+        let Some(node) = self.key_of(id).and_then(|key| self.node(key)) else {
+            return Vec::new();
+        };
+        if node.type_class == NodeTypeClass::Union {
+            return match id {
+                GdbStateNodeId::VarObject(var_object) => vec![var_object.clone()],
+                _ => Vec::new(),
+            };
+        }
+        node.successors
+            .iter()
+            .filter_map(|(_, successor)| match successor {
+                NodeKey::VarObject(index)
+                    if self
+                        .variables
+                        .get_at(*index)
+                        .is_some_and(|member| member.type_class == NodeTypeClass::Union) =>
+                {
+                    self.variables.object_at(*index).cloned()
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Hides all members of a union except one.
+    fn select_union_variant(&mut self, var_object: VariableObject, variant: &str) {
+        let Some(node) = self.variables.get_mut(&var_object) else {
+            return;
+        };
+        let is_variant =
+            |edge: &EdgeLabel| matches!(edge, EdgeLabel::Named(name, _) if name == variant);
+        if !node.successors.iter().any(|(edge, _)| is_variant(edge)) {
+            self.warn(GraphWarning::UnknownUnionVariant {
+                node: GdbStateNodeId::VarObject(var_object),
+                variant: variant.to_owned(),
+            });
+            return;
+        }
+        let all_members = node.successors.clone();
+        node.successors.retain(|(edge, _)| is_variant(edge));
+        // Keep the full list if the union has already been narrowed down
+        self.hidden_union_members
+            .entry(var_object)
+            .or_insert(all_members);
+    }
+
+    /// Shows all members of unions that have been hidden
+    /// by [`GdbStateGraphWriter::apply_union_variants`],
+    /// so they are updated like any other nodes.
+    fn restore_union_members(&mut self) {
+        for (var_object, all_members) in std::mem::take(&mut self.hidden_union_members) {
+            if let Some(node) = self.variables.get_mut(&var_object) {
+                node.successors = all_members;
+            }
+        }
+    }
+
+    /// Redirects pointers to read-only objects that are identical
+    /// to another such object to that other object,
+    /// if [`GraphOptions::with_shared_subtrees`] is enabled.
+    fn share_read_only_subtrees(&mut self) {
+        if !self.options.shared_subtrees {
+            return;
+        }
+        // Objects are visited by address, so the one with
+        // the lowest address becomes the shared one
+        let targets = self
+            .address_mapping
+            .values()
+            .filter(|var_object| self.is_read_only_target(var_object))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut shared_targets = HashMap::<u64, Vec<VariableIndex>>::new();
+        for target in targets {
+            let Some(index) = self.variables.index_of(&target) else {
+                continue;
+            };
+            let key = NodeKey::VarObject(index);
+            let mut hasher = DefaultHasher::new();
+            self.hash_subtree(key, &mut hasher);
+            let candidates = shared_targets.entry(hasher.finish()).or_default();
+            // Hashes may collide, so the subtrees still need to be compared
+            let Some(shared_target) = candidates
+                .iter()
+                .find(|candidate| self.subtrees_equal(NodeKey::VarObject(**candidate), key))
+                .copied()
+            else {
+                candidates.push(index);
+                continue;
+            };
+            self.redirect_referers(&target, shared_target);
+        }
+    }
+
+    /// Checks whether an object is only reachable
+    /// through pointers to `const`.
+    fn is_read_only_target(&self, var_object: &VariableObject) -> bool {
+        let Some(node) = self.variables.get(var_object) else {
+            return false;
+        };
+        // Only objects that were created by dereferencing a pointer
+        // have no parent, variables themselves are never merged
+        node.parent.is_none()
+            && !node.referers.is_empty()
+            && node.referers.iter().all(|referer| {
+                self.variables
+                    .get_at(*referer)
+                    .is_some_and(|referer| referer.points_to_const)
+            })
+    }
+
+    /// Feeds the types, values, and edges of a subtree into a hasher,
+    /// without following [`EdgeLabel::Deref`] edges.
+    fn hash_subtree(&self, key: NodeKey, hasher: &mut DefaultHasher) {
+        let Some(node) = self.node(key) else {
+            return;
+        };
+        // Node values do not implement Hash because they may be floats
+        format!(
+            "{:?} {:?} {:?}",
+            node.type_class, node.type_name, node.value
+        )
+        .hash(hasher);
+        for (edge, successor) in &node.successors {
+            edge.hash(hasher);
+            if *edge != EdgeLabel::Deref {
+                self.hash_subtree(*successor, hasher);
+            }
+        }
+    }
+
+    /// Checks whether two subtrees have the same types, values, and edges.
+    ///
+    /// [`EdgeLabel::Deref`] edges are equal if they lead to the same node.
+    fn subtrees_equal(&self, a: NodeKey, b: NodeKey) -> bool {
+        let (Some(a), Some(b)) = (self.node(a), self.node(b)) else {
+            return false;
+        };
+        a.type_class == b.type_class
+            && a.type_name == b.type_name
+            && a.value == b.value
+            && a.successors.len() == b.successors.len()
+            && a.successors.iter().zip(&b.successors).all(
+                |((a_edge, a_successor), (b_edge, b_successor))| {
+                    a_edge == b_edge
+                        && if *a_edge == EdgeLabel::Deref {
+                            a_successor == b_successor
                         } else {
-                            // TODO: Warn, only variables should be assigned lengths
+                            self.subtrees_equal(*a_successor, *b_successor)
                         }
-                    }
+                },
+            )
+    }
+
+    /// Redirects all pointers to an object to another object
+    /// and marks the other object as shared.
+    fn redirect_referers(&mut self, target: &VariableObject, shared_target: VariableIndex) {
+        let Some(node) = self.variables.get(target) else {
+            return;
+        };
+        let referers = node
+            .referers
+            .iter()
+            .filter_map(|referer| self.variables.object_at(*referer))
+            .cloned()
+            .collect::<Vec<_>>();
+        for referer in referers {
+            let Some(referer_node) = self.variables.get_mut(&referer) else {
+                continue;
+            };
+            for (edge, successor) in &mut referer_node.successors {
+                if *edge == EdgeLabel::Deref {
+                    *successor = NodeKey::VarObject(shared_target);
                 }
             }
+            self.shared_subtrees.insert(referer, target.clone());
         }
-        // Return early if we know no selectors can match past this point
-        if !resolver.has_edges_to_resolve() {
-            return;
-        }
-        // Save a snapshot so we can return to it later
-        if self
-            .graph
-            .get(origin)
-            .is_some_and(|n| n.type_class == NodeTypeClass::Ref)
-            && let GdbStateNodeId::VarObject(var_object) = origin
-        {
-            snapshots.insert(
-                var_object.clone(),
-                (variable_pool.snapshot(), resolver.snapshot()),
-            );
+        if let Some(node) = self.variables.get_at_mut(shared_target) {
+            node.shared = true;
         }
-        for (edge_label, successor) in self
-            .graph
-            .get(origin)
-            .into_iter()
-            .flat_map(|n| &n.successors)
-        {
-            if *edge_label == EdgeLabel::Deref {
-                // Do not resolve past a dereference edge,
-                // each heap-allocated object will be the root of its own resolution
+    }
+
+    /// Points all pointers that have been redirected
+    /// by [`GdbStateGraphWriter::share_read_only_subtrees`]
+    /// back at their own targets, so they are updated like any other nodes.
+    fn restore_shared_subtrees(&mut self) {
+        for (referer, target) in std::mem::take(&mut self.shared_subtrees) {
+            let Some(target) = self.variables.index_of(&target) else {
+                continue;
+            };
+            let Some(referer_node) = self.variables.get_mut(&referer) else {
                 continue;
+            };
+            let mut shared_targets = Vec::new();
+            for (edge, successor) in &mut referer_node.successors {
+                if *edge == EdgeLabel::Deref {
+                    shared_targets.push(std::mem::replace(successor, NodeKey::VarObject(target)));
+                }
+            }
+            for shared_target in shared_targets {
+                let NodeKey::VarObject(shared_target) = shared_target else {
+                    continue;
+                };
+                if let Some(node) = self.variables.get_at_mut(shared_target) {
+                    node.shared = false;
+                }
             }
-
-            variable_pool.push();
-            resolver.push_edge(edge_label);
-            self.resolve_length_hints_with_resolver_from(
-                successor,
-                resolver,
-                variable_pool,
-                resolved_hints,
-                snapshots,
-                Some(edge_label),
-            );
-            resolver.pop_edge();
-            variable_pool.pop();
         }
     }
 
-    #[expect(unused)]
     async fn populate_global_variables(&mut self) -> Result<()> {
+        let Some(filter) = self.options.globals.clone() else {
+            return Ok(());
+        };
         // Get all global variables across all files
         let query_result = self.gdb.symbol_info_variables().await?;
+        let mut included_names = HashSet::new();
         for file in query_result {
             for symbol in &file.symbols {
-                self.create_global_variable(symbol).await?;
+                // Static variables in different files may share a name,
+                // we cannot tell them apart by the edge label, so keep the first one
+                if filter.matches(&symbol.name) && included_names.insert(symbol.name.clone()) {
+                    self.check_cancelled()?;
+                    self.create_global_variable(symbol).await?;
+                }
             }
         }
         Ok(())
@@ -684,9 +2251,11 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         let edge_name = variable_symbol.name.clone();
         // Create the node
         let handle = self.read_global_variable_node(variable_symbol).await?;
-        let id = GdbStateNodeId::VarObject(handle.clone());
         // Insert the node into root
-        self.root_node.add_named_successor(edge_name, id);
+        if let Some(index) = self.variables.index_of(&handle) {
+            self.root_node
+                .add_named_successor(edge_name, NodeKey::VarObject(index));
+        }
         // Add the variable to address map
         self.add_variable_to_address_map(&variable_symbol.name, handle, true)
             .await?;
@@ -712,7 +2281,10 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
             self.address_mapping.insert(address, var_object);
             // TODO: Handle the case if the variable already exists
         } else {
-            // TODO: Warn
+            self.warn(GraphWarning::UnparsableValue {
+                object: var_object,
+                value: address,
+            });
         }
         Ok(())
     }
@@ -744,6 +2316,7 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
             parent_node: parent,
             node_data: var_object,
             successor_id: None,
+            bitfield: None,
         }];
         while let Some(requested_node) = to_construct.pop() {
             // If this is the first node (the one passed to the function as argument),
@@ -765,8 +2338,10 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         requested_node: DeferredVariableTree,
     ) -> Result<Vec<DeferredVariableTree>> {
         if requested_node.node_data.dynamic {
-            // TODO: Warn
             // Dynamic variable objects should never be returned by GDB unless explicitly enabled
+            self.warn(GraphWarning::IgnoredDynamicVariableObject(
+                requested_node.node_data.object.clone(),
+            ));
         }
         let has_children = requested_node.node_data.numchild > 0;
         let is_container = requested_node
@@ -774,14 +2349,57 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
             .value
             .as_deref()
             .is_none_or(Self::is_value_of_container);
+        let is_string = self.options.strings.is_some()
+            && requested_node
+                .node_data
+                .type_name
+                .as_deref()
+                .is_some_and(Self::is_string_type_name);
+        let container_shape = requested_node
+            .node_data
+            .type_name
+            .clone()
+            .map(Self::preprocess_type_name)
+            .and_then(|type_name| self.options.container_shape(&type_name));
+        // Array types are the only ones whose names end with a bracket
+        let truncated_length = self
+            .options
+            .limits
+            .max_array_elements
+            .filter(|max| requested_node.node_data.numchild > *max)
+            .filter(|_| {
+                requested_node
+                    .node_data
+                    .type_name
+                    .as_deref()
+                    .is_some_and(|type_name| type_name.ends_with(']'))
+            })
+            .map(|_| requested_node.node_data.numchild);
         let var_object_handle = requested_node.node_data.object.clone();
         self.create_variable_node(requested_node.node_data, requested_node.parent_node.clone());
+        if let Some((offset, width)) = requested_node.bitfield {
+            self.add_bitfield_nodes(&var_object_handle, offset, width);
+        }
         let mut deferred = Vec::new();
-        if has_children {
+        let adapted_children = match container_shape {
+            Some(shape) => self.adapt_container(&var_object_handle, shape).await?,
+            None => None,
+        };
+        if let Some(adapted_children) = adapted_children {
+            // Adapted containers are populated by their pretty-printers
+            deferred = adapted_children;
+        } else if is_string {
+            // Strings are atoms, their characters do not get nodes of their own
+            self.variables
+                .get_mut(&var_object_handle)
+                .expect("The node was just created")
+                .is_string = true;
+            self.read_string_variable(&var_object_handle).await?;
+        } else if has_children {
             if is_container {
                 // If there are children, now is the time to resolve them
                 deferred = self
-                    .after_create_container_variable_node(&var_object_handle)
+                    .after_create_container_variable_node(&var_object_handle, truncated_length)
                     .await?;
             } else {
                 self.after_create_non_atom_variable_node(&var_object_handle);
@@ -791,7 +2409,11 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         if let (Some(successor_id), Some(parent_id)) =
             (requested_node.successor_id, requested_node.parent_node)
         {
-            let node_id = GdbStateNodeId::VarObject(var_object_handle);
+            let node_id = NodeKey::VarObject(
+                self.variables
+                    .index_of(&var_object_handle)
+                    .expect("The node was just created"),
+            );
             let parent_node = self.get_mut(&parent_id).expect("The node was just created");
             match successor_id {
                 ContainerChildId::Named(name) => parent_node.add_named_successor(name, node_id),
@@ -825,42 +2447,79 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
             .gdb
             .var_list_children(var_object, PrintValues::SimpleValues)
             .await?;
+        // Pseudo-children are independent of one another,
+        // so they are all resolved in one batch
+        let pseudo_children = primary_children
+            .children
+            .iter()
+            .filter(|child| child.variable_object.type_name.is_none())
+            .map(|child| &child.variable_object.object)
+            .collect::<Vec<_>>();
+        let mut nested_children = self
+            .gdb
+            .var_list_children_batch(&pseudo_children, PrintValues::SimpleValues)
+            .await
+            .into_iter();
         let mut children = Vec::new();
         for child in primary_children.children {
             if child.variable_object.type_name.is_some() {
                 // Proper children are returned directly
                 children.push(child);
             } else {
-                // Pseudo-children are resolved first
-                let nested_children = self
-                    .gdb
-                    .var_list_children(&child.variable_object.object, PrintValues::SimpleValues)
-                    .await?;
+                let nested_children = nested_children
+                    .next()
+                    .unwrap_or_else(|| Err(BadResponse::MissingResultRecord.into()))?;
                 children.extend(nested_children.children);
             }
         }
         Ok(children)
     }
 
+    /// Creates the children of a container node.
+    ///
+    /// `truncated_length` is the full length of the container
+    /// if it is an array that should be truncated.
     async fn after_create_container_variable_node(
         &mut self,
         var_object: &VariableObject,
+        truncated_length: Option<usize>,
     ) -> Result<Vec<DeferredVariableTree>> {
-        let children = self
-            .list_children_with_resolved_pseudo_children(var_object)
-            .await?;
-        let container_kind = ContainerKind::deduce_from_children(&children)
+        let children = match (truncated_length, self.options.limits.max_array_elements) {
+            (Some(_), Some(max)) => {
+                // Arrays do not have pseudo-children
+                self.gdb
+                    .var_list_children_range(var_object, PrintValues::SimpleValues, 0, max)
+                    .await?
+                    .children
+            }
+            _ => {
+                self.list_children_with_resolved_pseudo_children(var_object)
+                    .await?
+            }
+        };
+        let mut container_kind = ContainerKind::deduce_from_children(&children)
             .expect("We have just verified that the node has children; type must be deducible");
+        // Members of structures and unions are named the same way,
+        // so they can only be told apart by their types
+        let layout = if container_kind == ContainerKind::Struct {
+            self.type_layout(var_object).await
+        } else {
+            TypeLayout::default()
+        };
+        if layout.is_union {
+            container_kind = ContainerKind::Union;
+        }
         let node = self
             .variables
             .get_mut(var_object)
             .expect("The node was just created");
         node.type_class = container_kind.into();
         match container_kind {
-            ContainerKind::Struct => Ok(children
+            ContainerKind::Struct | ContainerKind::Union => Ok(children
                 .into_iter()
                 .map(|child| DeferredVariableTree {
                     parent_node: Some(GdbStateNodeId::VarObject(var_object.clone())),
+                    bitfield: layout.bitfields.get(&child.exp).copied(),
                     node_data: child.variable_object,
                     successor_id: Some(ContainerChildId::Named(child.exp)),
                 })
@@ -877,7 +2536,10 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
                         // `ContainerKind::deduce_from_children` ensures that all
                         // children have numeric names, but the name may be too long
                         // to store in our variables
-                        // TODO: warn
+                        self.warn(GraphWarning::UnparsableValue {
+                            object: child.variable_object.object,
+                            value: child.exp,
+                        });
                         continue;
                     };
                     length = length.max(index + 1);
@@ -885,44 +2547,350 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
                         parent_node: Some(GdbStateNodeId::VarObject(var_object.clone())),
                         node_data: child.variable_object,
                         successor_id: Some(ContainerChildId::Index(index)),
+                        bitfield: None,
                     });
                 }
-                // Insert the length node
-                let mut length_node = GdbStateNode::new(NodeTypeClass::Atom);
-                length_node.value = Some(NodeValue::Uint(length as u64));
-                self.length_nodes.insert(var_object.clone(), length_node);
-                self.variables
-                    .get_mut(var_object)
-                    .expect("The node was just created")
-                    .successors
-                    .push((
-                        EdgeLabel::Length,
-                        GdbStateNodeId::Length(var_object.clone()),
-                    ));
+                if let Some(full_length) = truncated_length {
+                    length = length.max(full_length);
+                    self.mark_truncated(var_object);
+                }
+                self.add_length_node(var_object, length);
                 Ok(deferred)
             }
             ContainerKind::Pointer => unreachable!(),
         }
     }
 
+    /// Inserts the [`EdgeLabel::Length`] pseudo-node under an array node.
+    fn add_length_node(&mut self, var_object: &VariableObject, length: usize) {
+        let mut length_node = GdbStateNode::new(NodeTypeClass::Atom);
+        length_node.value = Some(NodeValue::Uint(length as u64));
+        let index = self
+            .variables
+            .index_of(var_object)
+            .expect("The node was just created");
+        self.length_nodes.insert(index, length_node);
+        self.variables
+            .get_at_mut(index)
+            .expect("The node was just created")
+            .successors
+            .push((EdgeLabel::Length, NodeKey::Length(index)));
+    }
+
+    /// Makes a container node present its contents,
+    /// as read by its pretty-printer, in the given shape.
+    ///
+    /// Returns [`None`] if the container cannot be pretty-printed,
+    /// in which case it should be treated like any other variable.
+    async fn adapt_container(
+        &mut self,
+        var_object: &VariableObject,
+        shape: ContainerShape,
+    ) -> Result<Option<Vec<DeferredVariableTree>>> {
+        let has_visualizer = self
+            .gdb
+            .var_set_visualizer(var_object, "gdb.default_visualizer")
+            .await
+            .is_ok();
+        if !has_visualizer {
+            return Ok(None);
+        }
+        self.variables
+            .get_mut(var_object)
+            .expect("The node was just created")
+            .container_shape = Some(shape);
+        self.populate_adapted_container(var_object, shape)
+            .await
+            .map(Some)
+    }
+
+    /// Reads the contents of an adapted container
+    /// and arranges its node in the given shape.
+    ///
+    /// Returns requests to create the nodes of the container's elements.
+    async fn populate_adapted_container(
+        &mut self,
+        var_object: &VariableObject,
+        shape: ContainerShape,
+    ) -> Result<Vec<DeferredVariableTree>> {
+        let parent_node = Some(GdbStateNodeId::VarObject(var_object.clone()));
+        if shape == ContainerShape::String {
+            let value = self.gdb.var_evaluate_expression(var_object).await?;
+            let node = self
+                .variables
+                .get_mut(var_object)
+                .expect("The container node must exist");
+            node.type_class = shape.into();
+            node.value = Self::parse_string_value(&value, usize::MAX).map(NodeValue::String);
+            return Ok(Vec::new());
+        }
+        let children = self
+            .gdb
+            .var_list_children(var_object, PrintValues::SimpleValues)
+            .await?
+            .children;
+        let node = self
+            .variables
+            .get_mut(var_object)
+            .expect("The container node must exist");
+        node.type_class = shape.into();
+        node.value = None;
+        match shape {
+            ContainerShape::Sequence => {
+                // Array nodes do not have types
+                node.type_name = None;
+                self.add_length_node(var_object, children.len());
+                let max_elements = self.options.limits.max_array_elements.unwrap_or(usize::MAX);
+                if children.len() > max_elements {
+                    self.mark_truncated(var_object);
+                }
+                Ok(children
+                    .into_iter()
+                    .take(max_elements)
+                    .enumerate()
+                    .map(|(index, child)| DeferredVariableTree {
+                        parent_node: parent_node.clone(),
+                        node_data: child.variable_object,
+                        successor_id: Some(ContainerChildId::Index(index)),
+                        bitfield: None,
+                    })
+                    .collect())
+            }
+            ContainerShape::Map => {
+                // Pretty-printers list keys and values of maps alternately
+                let mut deferred = Vec::new();
+                let mut children = children.into_iter();
+                while let (Some(key), Some(value)) = (children.next(), children.next()) {
+                    // Keys that are not elementary values are named by their position
+                    let name = match key.variable_object.value {
+                        Some(key_value) => {
+                            Self::parse_string_value(&key_value, usize::MAX).unwrap_or(key_value)
+                        }
+                        None => key.exp,
+                    };
+                    deferred.push(DeferredVariableTree {
+                        parent_node: parent_node.clone(),
+                        node_data: value.variable_object,
+                        successor_id: Some(ContainerChildId::Named(name)),
+                        bitfield: None,
+                    });
+                }
+                Ok(deferred)
+            }
+            ContainerShape::Pointer => {
+                // The only child of an owning pointer is the raw pointer
+                if let Some(pointer) = children.into_iter().next() {
+                    node.value = pointer
+                        .variable_object
+                        .value
+                        .as_deref()
+                        .and_then(Self::parse_node_value);
+                    // The pointer's type is needed to dereference it
+                    node.type_name = pointer.variable_object.type_name;
+                    self.add_deferred_dereference(var_object.clone());
+                }
+                Ok(Vec::new())
+            }
+            ContainerShape::String => unreachable!(),
+        }
+    }
+
+    /// Reads the contents of all adapted containers again.
+    ///
+    /// This is done on every update, since the elements
+    /// of a container may have been replaced entirely.
+    /// Sequences whose elements have only been added, removed,
+    /// or written to keep the nodes of the remaining elements.
+    async fn update_adapted_containers(&mut self) -> Result<()> {
+        let containers = self
+            .variables
+            .iter()
+            .filter_map(|(object, node)| Some((object.clone(), node.container_shape?)))
+            .collect::<Vec<_>>();
+        for (object, shape) in containers {
+            // Nested containers may have been recreated with their parents
+            if !self.variables.contains_key(&object) {
+                continue;
+            }
+            self.select_frame_of_variable(&object).await?;
+            let reused = if shape == ContainerShape::Sequence {
+                self.update_adapted_sequence(&object).await?
+            } else {
+                None
+            };
+            let mut to_construct = match reused {
+                Some(to_construct) => to_construct,
+                None => {
+                    self.remove_adapted_container_contents(&object);
+                    self.populate_adapted_container(&object, shape).await?
+                }
+            };
+            while let Some(requested_node) = to_construct.pop() {
+                let deferred = self.create_variable_tree_segment(requested_node).await?;
+                to_construct.extend(deferred);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the elements of an adapted sequence again,
+    /// keeping the nodes of elements that are still present.
+    ///
+    /// The elements are listed again, which takes time proportional
+    /// to the length of the sequence, but GDB reports changes in their
+    /// values individually, so writing to one element of a long sequence
+    /// only updates that element's node. Only elements past the previous end
+    /// of the sequence are created, and only elements past
+    /// its new end are removed.
+    ///
+    /// Returns [`None`] if the elements that are still present
+    /// are not represented by the same variable objects as before,
+    /// in which case the sequence must be populated from scratch.
+    async fn update_adapted_sequence(
+        &mut self,
+        var_object: &VariableObject,
+    ) -> Result<Option<Vec<DeferredVariableTree>>> {
+        let Some(node) = self.variables.get(var_object) else {
+            return Ok(Some(Vec::new()));
+        };
+        let mut elements = node
+            .successors
+            .iter()
+            .filter_map(|(edge, successor)| match (edge, successor) {
+                (EdgeLabel::Index(index), NodeKey::VarObject(element)) => {
+                    Some((*index, self.variables.object_at(*element)?.clone()))
+                }
+                _ => None,
+            })
+            .collect::<BTreeMap<_, _>>();
+        let children = self
+            .gdb
+            .var_list_children(var_object, PrintValues::SimpleValues)
+            .await?
+            .children;
+        let length = children.len();
+        let max_elements = self.options.limits.max_array_elements.unwrap_or(usize::MAX);
+        let mut to_construct = Vec::new();
+        for (index, child) in children.into_iter().take(max_elements).enumerate() {
+            match elements.remove(&index) {
+                Some(element) if element == child.variable_object.object => {}
+                Some(_) => return Ok(None),
+                None => to_construct.push(DeferredVariableTree {
+                    parent_node: Some(GdbStateNodeId::VarObject(var_object.clone())),
+                    node_data: child.variable_object,
+                    successor_id: Some(ContainerChildId::Index(index)),
+                    bitfield: None,
+                }),
+            }
+        }
+        // Whatever is left is past the new end of the sequence
+        if let Some(node) = self.variables.get_mut(var_object) {
+            node.successors.retain(|(edge, _)| {
+                !matches!(edge, EdgeLabel::Index(index) if elements.contains_key(index))
+            });
+        }
+        for element in elements.into_values() {
+            self.remove_variables_recursive(&element);
+        }
+        if let Some(length_node) = self
+            .variables
+            .index_of(var_object)
+            .and_then(|index| self.length_nodes.get_mut(&index))
+        {
+            length_node.value = Some(NodeValue::Uint(length as u64));
+        }
+        if length > max_elements {
+            self.mark_truncated(var_object);
+        } else {
+            self.unmark_truncated(var_object);
+        }
+        Ok(Some(to_construct))
+    }
+
+    /// Removes all nodes that represent the contents
+    /// of an adapted container, keeping the container's node.
+    fn remove_adapted_container_contents(&mut self, var_object: &VariableObject) {
+        let Some(node) = self.variables.get_mut(var_object) else {
+            return;
+        };
+        let successors = std::mem::take(&mut node.successors);
+        for (edge_label, successor) in successors {
+            match (edge_label, successor) {
+                (EdgeLabel::Deref, NodeKey::VarObject(dereference)) => {
+                    let Some(dereference) = self.variables.object_at(dereference).cloned() else {
+                        continue;
+                    };
+                    let dropped_last_ref = self.free_dereference(var_object, &dereference);
+                    if dropped_last_ref {
+                        self.remove_variables_recursive(&dereference);
+                    }
+                }
+                (_, NodeKey::VarObject(child)) => {
+                    if let Some(child) = self.variables.object_at(child).cloned() {
+                        self.remove_variables_recursive(&child);
+                    }
+                }
+                (_, NodeKey::Length(index)) => {
+                    self.length_nodes.remove(&index);
+                }
+                (_, NodeKey::Truncated(index)) => {
+                    self.truncation_markers.remove(&index);
+                }
+                (edge, successor) => {
+                    // Containers should not have any other successors
+                    if let Some(successor) = self.id_of(successor) {
+                        self.warn(GraphWarning::UnexpectedSuccessor {
+                            node: GdbStateNodeId::VarObject(var_object.clone()),
+                            edge,
+                            successor,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the dereference edge of a pointer, if it has one,
+    /// and removes its target if the pointer was the last thing
+    /// that kept it in the graph.
+    fn unlink_dereference(&mut self, referer_handle: &VariableObject) {
+        let Some(referer) = self.variables.get_mut(referer_handle) else {
+            return;
+        };
+        if let Some(NodeKey::VarObject(old_deref_index)) =
+            referer.remove_successor(&EdgeLabel::Deref)
+            && let Some(old_deref_id) = self.variables.object_at(old_deref_index).cloned()
+        {
+            let dropped_last_ref = self.free_dereference(referer_handle, &old_deref_id);
+            if dropped_last_ref {
+                self.remove_variables_recursive(&old_deref_id);
+            }
+        }
+    }
+
     fn link_dereference_relation(
         &mut self,
         referer_handle: &VariableObject,
         dereference_handle: &VariableObject,
     ) {
+        let referer = self
+            .variables
+            .index_of(referer_handle)
+            .expect("Attempted to link dereference to nonexistent node");
+        let dereference = self
+            .variables
+            .index_of(dereference_handle)
+            .expect("Attempted to link referer to nonexistent node");
         self.variables
-            .get_mut(referer_handle)
+            .get_at_mut(referer)
             .expect("Attempted to link dereference to nonexistent node")
             .successors
-            .push((
-                EdgeLabel::Deref,
-                GdbStateNodeId::VarObject(dereference_handle.clone()),
-            ));
+            .push((EdgeLabel::Deref, NodeKey::VarObject(dereference)));
         self.variables
-            .get_mut(dereference_handle)
+            .get_at_mut(dereference)
             .expect("Attempted to link referer to nonexistent node")
             .referers
-            .push(referer_handle.clone());
+            .push(referer);
     }
 
     async fn get_or_create_dereference_variable_node(
@@ -930,6 +2898,7 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         address: u64,
         pointer_type_name: &str,
         array_length: Option<u64>,
+        deref_depth: usize,
     ) -> Result<VariableObject> {
         // If the node already exists, return it right away
         if let Some(var_object) = self.address_mapping.get(&address) {
@@ -945,10 +2914,12 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
             .await?;
         let var_object = self.create_variable_tree(deref_var_object, None).await?;
         self.address_mapping.insert(address, var_object.clone());
-        self.variables
+        let node = self
+            .variables
             .get_mut(&var_object)
-            .expect("The variable node was just created")
-            .address = Some(address);
+            .expect("The variable node was just created");
+        node.address = Some(address);
+        node.deref_depth = deref_depth;
         Ok(var_object)
     }
 
@@ -957,12 +2928,18 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         var_object: VariableObjectData,
         parent: Option<GdbStateNodeId>,
     ) {
+        let type_name = var_object
+            .type_name
+            .expect("Pseudo-child variable object encountered in unexpected context");
+        let points_to_const = points_to_const(&type_name);
+        let handle = var_object.object.clone();
         let node = self.new_variable_node(var_object.object, NodeTypeClass::Atom, parent);
-        node.type_name =
-            Some(Self::preprocess_type_name(var_object.type_name.expect(
-                "Pseudo-child variable object encountered in unexpected context",
-            )));
+        node.type_name = Some(Self::preprocess_type_name(type_name));
         node.value = var_object.value.as_deref().and_then(Self::parse_node_value);
+        self.variables
+            .get_mut(&handle)
+            .expect("The node was just created")
+            .points_to_const = points_to_const;
     }
 
     fn new_variable_node(
@@ -971,13 +2948,10 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         type_class: NodeTypeClass,
         parent: Option<GdbStateNodeId>,
     ) -> &mut GdbStateNode {
-        self.variables
-            .entry(id)
-            .insert_entry(GdbStateNodeForVariable::new(
-                GdbStateNode::new(type_class),
-                parent,
-            ))
-            .into_mut()
+        self.variables.insert(
+            id,
+            GdbStateNodeForVariable::new(GdbStateNode::new(type_class), parent),
+        )
     }
 
     fn parse_node_value(mut s: &str) -> Option<NodeValue> {
@@ -998,12 +2972,75 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
         } else if let Some(h) = s.strip_prefix("0x") {
             // Parse it as hexadecimal
             u64::from_str_radix(h, 16).ok().map(NodeValue::Uint)
+        } else if let Ok(f) = Self::strip_nan_payload(s).parse() {
+            // Parse it as floating point
+            Some(NodeValue::Float(f))
         } else {
             // It's probably a struct or array, so do not include a value
             None
         }
     }
 
+    /// Removes the payload that GDB prints after not-a-number
+    /// floating point values, such as in `-nan(0x8000000000000)`.
+    fn strip_nan_payload(s: &str) -> &str {
+        match s.split_once('(') {
+            Some((nan, _)) if nan.ends_with("nan") => nan,
+            _ => s,
+        }
+    }
+
+    /// Decodes the first string literal in a value printed by GDB,
+    /// such as `0x4005d4 "hello"` for a character pointer
+    /// or `"hello"` for a character array.
+    fn parse_string_value(s: &str, max_length: usize) -> Option<String> {
+        let (_, literal) = s.split_once('"')?;
+        let mut bytes = Vec::new();
+        let mut chars = literal.chars();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => match chars.next()? {
+                    'n' => bytes.push(b'\n'),
+                    't' => bytes.push(b'\t'),
+                    'r' => bytes.push(b'\r'),
+                    'a' => bytes.push(0x07),
+                    'b' => bytes.push(0x08),
+                    'f' => bytes.push(0x0c),
+                    'v' => bytes.push(0x0b),
+                    'e' => bytes.push(0x1b),
+                    c @ '0'..='7' => {
+                        // Octal escapes have up to three digits
+                        let mut code = c.to_digit(8).unwrap();
+                        for _ in 0..2 {
+                            let Some(digit) = chars.clone().next().and_then(|c| c.to_digit(8))
+                            else {
+                                break;
+                            };
+                            code = code * 8 + digit;
+                            chars.next();
+                        }
+                        bytes.push(code as u8);
+                    }
+                    c => bytes.extend(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                },
+                c => bytes.extend(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        Some(
+            String::from_utf8_lossy(&bytes)
+                .chars()
+                .take(max_length)
+                .collect(),
+        )
+    }
+
+    fn is_string_type_name(type_name: &str) -> bool {
+        static STRING_TYPE_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^(?:const )?char (?:\*|\[\d+\])$").unwrap());
+        STRING_TYPE_REGEX.is_match(type_name)
+    }
+
     fn preprocess_type_name(mut name: String) -> String {
         // Const keyword should not be apart of the type name
         name = name
@@ -1031,16 +3068,18 @@ impl<'a, T: GdbMiSession> GdbStateGraphWriter<'a, T> {
 }
 
 impl GdbStateNode {
-    fn new(type_class: NodeTypeClass) -> Self {
+    pub(crate) fn new(type_class: NodeTypeClass) -> Self {
         Self {
             type_class,
             type_name: None,
             successors: Vec::new(),
             value: None,
+            source_location: None,
+            shared: false,
         }
     }
 
-    fn add_named_successor(&mut self, name: String, successor: GdbStateNodeId) {
+    fn add_named_successor(&mut self, name: String, successor: NodeKey) {
         let existing_nodes_with_same_name = self
             .successors
             .iter()
@@ -1052,11 +3091,11 @@ impl GdbStateNode {
                 }
             })
             .count();
-        let new_edge_label = EdgeLabel::Named(name, existing_nodes_with_same_name);
+        let new_edge_label = EdgeLabel::Named(name.into(), existing_nodes_with_same_name);
         self.successors.push((new_edge_label, successor));
     }
 
-    fn remove_successor(&mut self, id: &EdgeLabel) -> Option<GdbStateNodeId> {
+    fn remove_successor(&mut self, id: &EdgeLabel) -> Option<NodeKey> {
         let index = self
             .successors
             .iter()
@@ -1066,17 +3105,115 @@ impl GdbStateNode {
         Some(self.successors.swap_remove(index).1)
     }
 
-    fn remove_successor_by_id(&mut self, id: &GdbStateNodeId) -> Option<EdgeLabel> {
+    fn remove_successor_by_key(&mut self, key: NodeKey) -> Option<EdgeLabel> {
         let index = self
             .successors
             .iter()
             .enumerate()
-            .find(|(_, (_, v))| v == id)?
+            .find(|(_, (_, v))| *v == key)?
             .0;
         Some(self.successors.swap_remove(index).0)
     }
 }
 
+impl ThreadNodes {
+    fn new() -> Self {
+        Self {
+            list: GdbStateNode::new(NodeTypeClass::Array),
+            length: GdbStateNode::new(NodeTypeClass::Atom),
+            stacks: BTreeMap::new(),
+            selected: None,
+        }
+    }
+}
+
+/// Interprets the value of a hint as a string.
+fn hint_string(value: PropertyValue<GdbStateNodeId>) -> Option<String> {
+    match value {
+        PropertyValue::String(value) | PropertyValue::Value(NodeValue::String(value)) => {
+            Some(value)
+        }
+        _ => None,
+    }
+}
+
+/// Gets the name of the type of pointers to a type.
+///
+/// Pointers to arrays need parentheses, as in `char (*)[16]`.
+fn pointer_to(type_name: &str) -> String {
+    match type_name.find('[') {
+        Some(index) => format!(
+            "{} (*){}",
+            type_name[..index].trim_end(),
+            &type_name[index..]
+        ),
+        None => format!("{type_name} *"),
+    }
+}
+
+/// Checks whether a type is a pointer to a `const`-qualified type.
+///
+/// Qualifiers of the pointer itself follow its last asterisk,
+/// while qualifiers of the pointee either precede the base type
+/// or follow the asterisk before it, so in `const char **`,
+/// the pointee is not `const`, but in `char *const *`, it is.
+fn points_to_const(type_name: &str) -> bool {
+    let Some((pointee, pointer_qualifiers)) = type_name.rsplit_once('*') else {
+        return false;
+    };
+    // Pointers to arrays and functions have more after the asterisk
+    let is_plain_pointer = pointer_qualifiers
+        .split_whitespace()
+        .all(|word| matches!(word, "const" | "volatile" | "restrict"));
+    let pointee_qualifiers = pointee
+        .rsplit_once('*')
+        .map_or(pointee, |(_, qualifiers)| qualifiers);
+    is_plain_pointer
+        && pointee_qualifiers
+            .split_whitespace()
+            .any(|word| word == "const")
+}
+
+/// Gets the identifier of a frame of a thread, or of the selected thread
+/// if `thread` is [`None`].
+fn frame_id(thread: Option<usize>, frame_index: usize) -> GdbStateNodeId {
+    match thread {
+        None => GdbStateNodeId::Frame(frame_index),
+        Some(thread) => GdbStateNodeId::ThreadFrame(thread, frame_index),
+    }
+}
+
+/// Same as [`frame_id`], but gets the key of the frame
+/// that its predecessors refer to it by.
+fn frame_key(thread: Option<usize>, frame_index: usize) -> NodeKey {
+    match thread {
+        None => NodeKey::Frame(frame_index),
+        Some(thread) => NodeKey::ThreadFrame(thread, frame_index),
+    }
+}
+
+/// Gets the location in the source code that a stack frame is executing,
+/// if the function has debugging information.
+fn source_location(frame: &StackFrame) -> Option<SourceLocation> {
+    Some(SourceLocation {
+        file: frame.file.clone()?,
+        line: frame.line?,
+    })
+}
+
+/// Gets the thread and the index of the frame
+/// that a frame node represents, if it is a frame node.
+///
+/// The thread is [`None`] for frames of the selected thread
+/// in graphs that do not include all threads.
+fn frame_location(id: &GdbStateNodeId) -> Option<(Option<usize>, usize)> {
+    match id {
+        GdbStateNodeId::Frame(frame_index) => Some((None, *frame_index)),
+        GdbStateNodeId::ThreadFrame(thread, frame_index) => Some((Some(*thread), *frame_index)),
+        _ => None,
+    }
+}
+
 /// Information necessary to construct a variable tree.
 struct DeferredVariableTree {
     /// ID of the parent node under which the tree is placed,
@@ -1091,6 +3228,10 @@ struct DeferredVariableTree {
     /// if it should be inserted into the parent.
     /// [`None`] if it should not be inserted.
     successor_id: Option<ContainerChildId>,
+
+    /// Offset and width of the member in bits,
+    /// if it is a bitfield.
+    bitfield: Option<(u64, u64)>,
 }
 
 /// Name or index of a child of a container node.
@@ -1105,6 +3246,11 @@ enum ContainerKind {
     /// Structure type, corresponds to [`NodeTypeClass::Struct`].
     Struct,
 
+    /// Union type, corresponds to [`NodeTypeClass::Union`].
+    ///
+    /// Cannot be deduced from children, see [`ContainerKind::deduce_from_children`].
+    Union,
+
     /// Plain array type, corresponds to [`NodeTypeClass::Array`].
     Array,
 
@@ -1121,6 +3267,9 @@ impl ContainerKind {
     /// - If all children's names are decimal numbers,
     ///   the parent is a [`ContainerKind::Array`].
     /// - Otherwise, the parent is a [`ContainerKind::Struct`].
+    ///
+    /// Unions have the same children as structures,
+    /// so they are also reported as [`ContainerKind::Struct`].
     fn deduce_from_children<'a>(
         children: impl IntoIterator<Item = &'a ChildVariableObject>,
     ) -> Option<Self> {
@@ -1150,8 +3299,27 @@ impl From<ContainerKind> for NodeTypeClass {
     fn from(value: ContainerKind) -> Self {
         match value {
             ContainerKind::Struct => Self::Struct,
+            ContainerKind::Union => Self::Union,
             ContainerKind::Array => Self::Array,
             ContainerKind::Pointer => Self::Ref,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn const_qualification_of_pointee() {
+        assert!(points_to_const("const char *"));
+        assert!(points_to_const("char const *"));
+        assert!(points_to_const("const char * const"));
+        assert!(points_to_const("char * const *"));
+        assert!(!points_to_const("char *"));
+        assert!(!points_to_const("char * const"));
+        assert!(!points_to_const("const char **"));
+        assert!(!points_to_const("const char"));
+        assert!(!points_to_const("int (*)(const char *)"));
+    }
+}
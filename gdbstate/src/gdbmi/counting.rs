@@ -0,0 +1,119 @@
+//! Instrumentation that counts the commands sent to GDB.
+
+use super::{raw_output::ResultRecord, result::Result, stream::GdbMiStream};
+use derive_more::{Deref, DerefMut};
+use std::collections::BTreeMap;
+
+/// Numbers of commands sent to GDB, by operation.
+///
+/// Operations are identified by the first word of the command,
+/// such as `-var-create`.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct CommandCounts(BTreeMap<String, usize>);
+
+impl CommandCounts {
+    /// Constructs an empty count.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts a command.
+    pub fn record(&mut self, command: &str) {
+        let operation = command.split_whitespace().next().unwrap_or_default();
+        *self.0.entry(operation.to_owned()).or_default() += 1;
+    }
+
+    /// Gets the number of commands of one operation.
+    pub fn get(&self, operation: &str) -> usize {
+        self.0.get(operation).copied().unwrap_or_default()
+    }
+
+    /// Gets the total number of commands.
+    pub fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+
+    /// Iterates over operations and the numbers of commands
+    /// of each operation, in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.0
+            .iter()
+            .map(|(operation, count)| (operation.as_str(), *count))
+    }
+}
+
+/// [`GdbMiStream`] that forwards commands to another stream
+/// and counts them.
+///
+/// This is intended for measuring how much traffic
+/// the construction and updates of a state graph generate.
+/// The wrapper dereferences to the wrapped stream,
+/// so the stream can be used as usual.
+#[derive(Debug, Deref, DerefMut)]
+pub struct CountingStream<T> {
+    #[deref]
+    #[deref_mut]
+    inner: T,
+    counts: CommandCounts,
+}
+
+impl<T> CountingStream<T> {
+    /// Wraps a stream.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            counts: CommandCounts::new(),
+        }
+    }
+
+    /// Gets the numbers of commands sent since the stream was wrapped
+    /// or since the last call to [`CountingStream::take_counts`].
+    pub fn counts(&self) -> &CommandCounts {
+        &self.counts
+    }
+
+    /// Gets the numbers of commands sent so far and resets them.
+    pub fn take_counts(&mut self) -> CommandCounts {
+        std::mem::take(&mut self.counts)
+    }
+
+    /// Unwraps the stream.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: GdbMiStream> GdbMiStream for CountingStream<T> {
+    async fn send_command(&mut self, command: &str) -> Result<ResultRecord> {
+        self.counts.record(command);
+        self.inner.send_command(command).await
+    }
+
+    async fn send_commands(&mut self, commands: &[String]) -> Vec<Result<ResultRecord>> {
+        for command in commands {
+            self.counts.record(command);
+        }
+        self.inner.send_commands(commands).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn count_commands_by_operation() {
+        let mut counts = CommandCounts::new();
+        counts.record("-var-create - * \"x\"");
+        counts.record("-var-update --all-values *");
+        counts.record("-var-create - * \"y\"");
+        assert_eq!(counts.get("-var-create"), 2);
+        assert_eq!(counts.get("-var-update"), 1);
+        assert_eq!(counts.get("-stack-list-frames"), 0);
+        assert_eq!(counts.total(), 3);
+        assert_eq!(
+            counts.iter().collect::<Vec<_>>(),
+            [("-var-create", 2), ("-var-update", 1)]
+        );
+    }
+}
@@ -13,7 +13,8 @@ pub struct ParseError;
 /// [GDB/MI](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI.html)
 /// output.
 ///
-/// Result records (`^`) and asynchronous execution records (`*`) are supported.
+/// Result records (`^`), asynchronous execution records (`*`),
+/// and asynchronous notifications (`=`) are supported.
 pub fn parse_gdbmi_record(input: &str) -> Result<Record, ParseError> {
     let lexer = parser::Token::lexer(input);
     let mut parser = parser::Parser::new();
@@ -42,6 +43,8 @@ pomelo! {
     %type exec_record    AsyncExecRecord;
     %type exec_record1   AsyncExecRecord;
     %type exec_class     AsyncExecClass;
+    %type notify_record  AsyncNotifyRecord;
+    %type notify_record1 AsyncNotifyRecord;
     %type result_record  ResultRecord;
     %type result_record1 ResultRecord;
     %type result_class   ResultClass;
@@ -128,6 +131,7 @@ pomelo! {
     // Only result records are parsed, other kinds are not needed for testing
     record ::= result_record(r)                              { Record::Result(r) }
     record ::= exec_record(r)                                { Record::AsyncExec(r) }
+    record ::= notify_record(r)                              { Record::AsyncNotify(r) }
 
     // Result record
     result_record ::= result_record1;
@@ -141,6 +145,11 @@ pomelo! {
     exec_record1 ::= Asterisk exec_class(c)                  { AsyncExecRecord { async_exec_class: c, results: ResultTuple::default() } }
     exec_class ::= Unquoted(s)                               { s.parse().map_err(|_| ParseError)? }
 
+    // Async-notify record
+    notify_record ::= notify_record1;
+    notify_record ::= notify_record1(mut r) Comma results(e) { r.results = e; r }
+    notify_record1 ::= Equals Unquoted(c)                    { AsyncNotifyRecord { async_class: c.to_owned(), results: ResultTuple::default() } }
+
     // Parameters returned together with a successful result record
     results ::= result(e)                                    { ResultTuple(vec![e]) }
     results ::= results(mut r) Comma result(e)               { r.0.push(e); r }
@@ -251,6 +260,7 @@ impl std::str::FromStr for AsyncExecClass {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::gdbmi::types::{SessionEvent, StopReason};
 
     #[test]
     fn result_record_with_no_data() {
@@ -412,6 +422,118 @@ mod test {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn watchpoint_trigger_record() {
+        let result = parse_gdbmi_record(
+            r#"*stopped,reason="watchpoint-trigger",wpt={number="2",exp="x"},value={old="1",new="2"}"#,
+        )
+        .expect("Input should have parsed successfully");
+        let Record::AsyncExec(record) = result else {
+            panic!("Record should have been an async record");
+        };
+        assert_eq!(record.watchpoint_number(), Some(2));
+    }
+
+    #[test]
+    fn watchpoint_scope_record() {
+        let result = parse_gdbmi_record(r#"*stopped,reason="watchpoint-scope",wpnum="3""#)
+            .expect("Input should have parsed successfully");
+        let Record::AsyncExec(record) = result else {
+            panic!("Record should have been an async record");
+        };
+        assert_eq!(record.watchpoint_number(), Some(3));
+    }
+
+    #[test]
+    fn async_notify_record() {
+        let result = parse_gdbmi_record(r#"=thread-created,id="1",group-id="i1""#)
+            .expect("Input should have parsed successfully");
+        let expected = AsyncNotifyRecord {
+            async_class: "thread-created".to_owned(),
+            results: ResultTuple(vec![
+                ResultEntry {
+                    key: "id".to_owned(),
+                    value: Value::Const("1".to_owned()),
+                },
+                ResultEntry {
+                    key: "group-id".to_owned(),
+                    value: Value::Const("i1".to_owned()),
+                },
+            ]),
+        }
+        .into();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn breakpoint_hit_event() {
+        let result = parse_gdbmi_record(
+            r#"*stopped,reason="breakpoint-hit",disp="keep",bkptno="1",thread-id="1",stopped-threads="all""#,
+        )
+        .expect("Input should have parsed successfully");
+        assert_eq!(
+            result.session_event(),
+            Some(SessionEvent::Stopped {
+                reason: StopReason::BreakpointHit { number: 1 },
+                thread_id: Some("1".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn function_finished_event() {
+        let result = parse_gdbmi_record(
+            r#"*stopped,reason="function-finished",frame={level="0",func="main"},thread-id="1",gdb-result-var="$1",return-value="42""#,
+        )
+        .expect("Input should have parsed successfully");
+        assert_eq!(
+            result.session_event(),
+            Some(SessionEvent::Stopped {
+                reason: StopReason::FunctionFinished {
+                    return_value: Some("42".to_owned()),
+                },
+                thread_id: Some("1".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn exit_event() {
+        let result = parse_gdbmi_record(r#"*stopped,reason="exited",exit-code="010""#)
+            .expect("Input should have parsed successfully");
+        assert_eq!(
+            result.session_event(),
+            Some(SessionEvent::Stopped {
+                reason: StopReason::Exited { exit_code: Some(8) },
+                thread_id: None,
+            })
+        );
+    }
+
+    #[test]
+    fn running_and_thread_events() {
+        let result = parse_gdbmi_record(r#"*running,thread-id="all""#)
+            .expect("Input should have parsed successfully");
+        assert_eq!(
+            result.session_event(),
+            Some(SessionEvent::Running {
+                thread_id: Some("all".to_owned()),
+            })
+        );
+        let result = parse_gdbmi_record(r#"=thread-created,id="2",group-id="i1""#)
+            .expect("Input should have parsed successfully");
+        assert_eq!(
+            result.session_event(),
+            Some(SessionEvent::ThreadCreated {
+                id: "2".to_owned(),
+                group_id: Some("i1".to_owned()),
+            })
+        );
+        let result = parse_gdbmi_record(r#"=library-loaded,id="libc.so.6""#)
+            .expect("Input should have parsed successfully");
+        assert_eq!(result.session_event(), None);
+    }
+
     #[test]
     fn char_result_record() {
         let result = parse_gdbmi_record("^done,value=\"-16 '\\360'\"\n")
@@ -0,0 +1,765 @@
+//! Scripted stand-in for a GDB session.
+
+use super::stream::{GdbMiEventSource, StringGdbMiStream};
+use std::collections::{HashMap, VecDeque};
+
+/// [`StringGdbMiStream`] that responds to commands from a script
+/// instead of forwarding them to GDB.
+///
+/// Like any other stream, the mock implements [`GdbMiSession`](super::session::GdbMiSession),
+/// so a [`GdbStateGraph`](crate::state::GdbStateGraph) can be constructed
+/// and updated from it deterministically, without a debugger
+/// or a compiled program. All futures it returns are ready immediately.
+///
+/// Responses are raw [result records](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Result-Records.html),
+/// such as `^done,depth="1"`. Commands are matched exactly,
+/// except that runs of whitespace are treated as a single space.
+/// A command can be given multiple responses, which are used up in order,
+/// and the last of which is repeated once all others have been used.
+/// Commands without a response receive an error record,
+/// the same way GDB responds to commands it does not know.
+///
+/// The mock is also a [`GdbMiEventSource`] that reads
+/// scripted lines of async output, such as `*stopped` records,
+/// so execution can be driven with a
+/// [`DebugController`](super::session::DebugController).
+///
+/// ```
+/// use aili_gdbstate::gdbmi::{mock::MockGdbMiSession, session::GdbMiSession};
+/// use std::task::{Context, Poll, Waker};
+///
+/// let mut gdb = MockGdbMiSession::new().with_response("-stack-info-depth", r#"^done,depth="2""#);
+/// let depth = std::pin::pin!(gdb.stack_info_depth()).poll(&mut Context::from_waker(Waker::noop()));
+/// assert!(matches!(depth, Poll::Ready(Ok(2))));
+/// assert_eq!(gdb.history(), ["-stack-info-depth"]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MockGdbMiSession {
+    responses: HashMap<String, VecDeque<String>>,
+    events: VecDeque<String>,
+    history: Vec<String>,
+}
+
+impl MockGdbMiSession {
+    /// Constructs a mock that has no responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a response to a command.
+    ///
+    /// See [`MockGdbMiSession::add_response`].
+    pub fn with_response(mut self, command: &str, response: impl Into<String>) -> Self {
+        self.add_response(command, response);
+        self
+    }
+
+    /// Adds a response to a command.
+    ///
+    /// If the command already has responses, the new response
+    /// is used after all of them.
+    pub fn add_response(&mut self, command: &str, response: impl Into<String>) {
+        self.responses
+            .entry(normalize_command(command))
+            .or_default()
+            .push_back(response.into());
+    }
+
+    /// Adds a line of async output.
+    ///
+    /// See [`MockGdbMiSession::add_event`].
+    pub fn with_event(mut self, line: impl Into<String>) -> Self {
+        self.add_event(line);
+        self
+    }
+
+    /// Adds a line of async output, such as `*stopped,reason="end-stepping-range"`.
+    ///
+    /// Lines are read in the order they were added,
+    /// regardless of what commands have been sent.
+    pub fn add_event(&mut self, line: impl Into<String>) {
+        self.events.push_back(line.into());
+    }
+
+    /// Gets all commands that have been sent to the mock, in order.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Gets all commands that have been sent to the mock
+    /// and forgets them, so that the commands sent
+    /// by the next operation can be told apart.
+    pub fn take_history(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.history)
+    }
+
+    /// Finds the response to a command and uses it up,
+    /// unless it is the last one.
+    fn respond(&mut self, command: &str) -> String {
+        match self.responses.get_mut(&normalize_command(command)) {
+            Some(responses) if responses.len() > 1 => responses.pop_front().unwrap(),
+            Some(responses) => responses.front().cloned().unwrap_or_default(),
+            None => format!(
+                "^error,msg={:?}",
+                format!("Undefined mock command: \"{command}\"")
+            ),
+        }
+    }
+}
+
+impl StringGdbMiStream for MockGdbMiSession {
+    async fn send_command(&mut self, command: &str) -> std::io::Result<String> {
+        self.history.push(command.to_owned());
+        Ok(self.respond(command))
+    }
+}
+
+impl GdbMiEventSource for MockGdbMiSession {
+    async fn next_line(&mut self) -> Option<std::io::Result<String>> {
+        self.events.pop_front().map(Ok)
+    }
+}
+
+/// Collapses runs of whitespace in a command into single spaces.
+fn normalize_command(command: &str) -> String {
+    command.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Resolves a future that the mock has made ready immediately.
+#[cfg(test)]
+pub(crate) fn ready<F: Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, Waker};
+    match std::pin::pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("Future should have been ready"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        gdbmi::{
+            result::{BadResponse, Error},
+            session::{DebugController, GdbMiSession, connect_remote_target},
+            stream::GdbMiStream,
+            types::{BreakpointLocation, RemoteTarget, StopReason, VariableObject},
+        },
+        options::GraphOptions,
+        state::{GdbStateGraph, GdbStateNodeId},
+    };
+    use aili_model::state::*;
+    use aili_style::cascade::CascadeStyle;
+
+    #[test]
+    fn responses_are_used_up_in_order() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response("-stack-info-depth", r#"^done,depth="1""#)
+            .with_response("-stack-info-depth", r#"^done,depth="2""#);
+        let responses = (0..3)
+            .map(|_| {
+                ready(StringGdbMiStream::send_command(
+                    &mut gdb,
+                    "-stack-info-depth",
+                ))
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            responses,
+            [
+                r#"^done,depth="1""#,
+                r#"^done,depth="2""#,
+                r#"^done,depth="2""#
+            ]
+        );
+    }
+
+    #[test]
+    fn whitespace_in_commands_is_collapsed() {
+        let mut gdb = MockGdbMiSession::new().with_response("-stack-list-variables 0", "^done");
+        let response = ready(StringGdbMiStream::send_command(
+            &mut gdb,
+            "-stack-list-variables  0",
+        ))
+        .unwrap();
+        assert_eq!(response, "^done");
+        assert_eq!(gdb.history(), ["-stack-list-variables  0"]);
+    }
+
+    #[test]
+    fn batch_responses_are_parsed_in_order() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response("-stack-info-depth", r#"^done,depth="1""#)
+            .with_response("-stack-list-frames", "^done,stack=[]");
+        let commands = ["-stack-list-frames", "-exec-run", "-stack-info-depth"].map(str::to_owned);
+        let results = ready(GdbMiStream::send_commands(&mut gdb, &commands));
+        let first_key = |i: usize| results[i].as_ref().unwrap().results.0[0].key.as_str();
+        assert_eq!(results.len(), 3);
+        assert_eq!(first_key(0), "stack");
+        assert_eq!(first_key(1), "msg");
+        assert_eq!(first_key(2), "depth");
+        assert_eq!(gdb.history(), commands);
+    }
+
+    #[test]
+    fn unknown_commands_fail() {
+        let mut gdb = MockGdbMiSession::new();
+        let response = ready(StringGdbMiStream::send_command(&mut gdb, "-exec-run")).unwrap();
+        assert!(response.starts_with("^error"));
+    }
+
+    #[test]
+    fn graph_from_scripted_session() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response(
+                "-stack-list-frames",
+                r#"^done,stack=[frame={level="0",addr="0x0000000000401126",func="main",file="main.c",line="5",arch="i386:x86-64"}]"#,
+            )
+            .with_response(
+                "-stack-list-frames",
+                r#"^done,stack=[frame={level="0",addr="0x0000000000401106",func="f",file="main.c",line="2",arch="i386:x86-64"},frame={level="1",addr="0x0000000000401130",func="main",file="main.c",line="6",arch="i386:x86-64"}]"#,
+            )
+            .with_response("-stack-select-frame 0", "^done")
+            .with_response("-stack-select-frame 1", "^done")
+            .with_response("-stack-list-variables 0", "^done,variables=[]")
+            .with_response("-var-update 2 *", "^done,changelist=[]");
+        let mut graph = ready(GdbStateGraph::new(&mut gdb)).unwrap();
+        let main = graph.get_at_root(&[EdgeLabel::Main]).unwrap();
+        assert_eq!(main.node_type_id(), Some("main"));
+        assert_eq!(
+            main.source_location().map(|location| location.line),
+            Some(5)
+        );
+        assert!(
+            graph
+                .get_at_root(&[EdgeLabel::Main, EdgeLabel::Next])
+                .is_none()
+        );
+        ready(graph.update(&mut gdb)).unwrap();
+        let main = graph.get_at_root(&[EdgeLabel::Main]).unwrap();
+        assert_eq!(
+            main.source_location().map(|location| location.line),
+            Some(6)
+        );
+        let f = graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Next])
+            .unwrap();
+        assert_eq!(f.node_type_id(), Some("f"));
+    }
+
+    #[test]
+    fn watchpoints_limit_updates_to_watched_objects() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response(
+                "-stack-list-frames",
+                r#"^done,stack=[frame={level="0",addr="0x0000000000401126",func="main",file="main.c",line="5",arch="i386:x86-64"}]"#,
+            )
+            .with_response("-stack-select-frame 0", "^done")
+            .with_response("-stack-list-variables 0", r#"^done,variables=[{name="a"},{name="b"}]"#)
+            .with_response(r#"-var-create - * "a""#, r#"^done,name="var1",numchild="0",value="1",type="int""#)
+            .with_response(r#"-var-create - * "b""#, r#"^done,name="var2",numchild="0",value="2",type="int""#)
+            .with_response(r#"-data-evaluate-expression "&a""#, r#"^done,value="(int *) 0x7fffffffe010""#)
+            .with_response(r#"-data-evaluate-expression "&b""#, r#"^done,value="(int *) 0x7fffffffe014""#);
+        let mut graph = ready(GdbStateGraph::new(&mut gdb)).unwrap();
+        gdb.add_response("-gdb-set can-use-hw-watchpoints 0", "^done");
+        gdb.add_response(
+            r#"-var-info-path-expression "var1""#,
+            r#"^done,path_expr="a""#,
+        );
+        gdb.add_response(
+            r#"-var-info-path-expression "var2""#,
+            r#"^done,path_expr="b""#,
+        );
+        gdb.add_response(r#"-break-watch "a""#, r#"^done,wpt={number="2",exp="a"}"#);
+        gdb.add_response(r#"-break-watch "b""#, r#"^done,wpt={number="3",exp="b"}"#);
+        gdb.add_response(
+            r#"-var-update 2 "var1""#,
+            r#"^done,changelist=[{name="var1",value="3",in_scope="true",type_changed="false",has_more="0"}]"#,
+        );
+        ready(graph.enable_watchpoints(&mut gdb)).unwrap();
+        // The top frame is selected again once the watchpoints are in place
+        assert_eq!(
+            gdb.take_history().last().map(String::as_str),
+            Some("-stack-select-frame 0")
+        );
+        assert!(graph.record_stop(&StopReason::WatchpointTrigger { number: 2 }));
+        assert!(!graph.record_stop(&StopReason::EndSteppingRange));
+        ready(graph.update(&mut gdb)).unwrap();
+        let history = gdb.take_history();
+        assert!(history.contains(&r#"-var-update 2 "var1""#.to_owned()));
+        assert!(
+            !history.iter().any(
+                |command| command.starts_with("-var-update") && !command.ends_with(r#""var1""#)
+            )
+        );
+        let main = graph.get_at_root(&[EdgeLabel::Main]).unwrap();
+        let value_of = |name: &str| {
+            let variable = main
+                .get_successor(&EdgeLabel::Named(name.into(), 0))
+                .unwrap();
+            graph.get(&variable).unwrap().value()
+        };
+        assert_eq!(value_of("a"), Some(NodeValue::Uint(3)));
+        assert_eq!(value_of("b"), Some(NodeValue::Uint(2)));
+    }
+
+    #[test]
+    fn pointer_relinked_twice_in_one_update_has_one_target() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response(
+                "-stack-list-frames",
+                r#"^done,stack=[frame={level="0",addr="0x0000000000401126",func="main",file="main.c",line="5",arch="i386:x86-64"}]"#,
+            )
+            .with_response("-stack-select-frame 0", "^done")
+            .with_response("-stack-list-variables 0", r#"^done,variables=[{name="a"},{name="b"},{name="p"}]"#)
+            .with_response(r#"-var-create - * "a""#, r#"^done,name="var1",numchild="0",value="1",type="int""#)
+            .with_response(r#"-var-create - * "b""#, r#"^done,name="var2",numchild="0",value="2",type="int""#)
+            .with_response(r#"-var-create - * "p""#, r#"^done,name="var3",numchild="1",value="0x7fffffffe010",type="int *""#)
+            .with_response(r#"-data-evaluate-expression "&a""#, r#"^done,value="(int *) 0x7fffffffe010""#)
+            .with_response(r#"-data-evaluate-expression "&b""#, r#"^done,value="(int *) 0x7fffffffe014""#)
+            .with_response(r#"-data-evaluate-expression "&p""#, r#"^done,value="(int **) 0x7fffffffe018""#)
+            .with_response(r#"-data-evaluate-expression "*(char*)140737488347152""#, r#"^done,value="1 '\\001'""#)
+            .with_response(r#"-data-evaluate-expression "*(char*)140737488347156""#, r#"^done,value="2 '\\002'""#)
+            .with_response(r#"-var-create - * "*(int *)140737488347152""#, r#"^done,name="var4",numchild="0",value="1",type="int""#)
+            .with_response(r#"-var-create - * "*(int *)140737488347156""#, r#"^done,name="var5",numchild="0",value="2",type="int""#)
+            .with_response(
+                "-var-update 2 *",
+                r#"^done,changelist=[{name="var3",value="0x7fffffffe014",in_scope="true",type_changed="false",has_more="0"},{name="var3",value="0x7fffffffe010",in_scope="true",type_changed="false",has_more="0"}]"#,
+            );
+        let mut graph = ready(GdbStateGraph::new(&mut gdb)).unwrap();
+        ready(graph.update(&mut gdb)).unwrap();
+        // The pointer is resolved once, with its latest value
+        assert!(
+            !gdb.history()
+                .contains(&r#"-var-create - * "*(int *)140737488347156""#.to_owned())
+        );
+        let main = graph.get_at_root(&[EdgeLabel::Main]).unwrap();
+        let variable = |name: &str| {
+            main.get_successor(&EdgeLabel::Named(name.into(), 0))
+                .unwrap()
+        };
+        let pointer = graph.get(&variable("p")).unwrap();
+        let deref_edges = pointer
+            .successors()
+            .filter(|(edge, _)| **edge == EdgeLabel::Deref)
+            .map(|(_, target)| target)
+            .collect::<Vec<_>>();
+        let target = GdbStateNodeId::VarObject(VariableObject("var4".to_owned()));
+        assert_eq!(deref_edges, std::slice::from_ref(&target));
+        let target = graph.get(&target).unwrap();
+        assert_eq!(target.value(), Some(NodeValue::Uint(1)));
+    }
+
+    #[test]
+    fn top_frame_is_selected_after_strings_are_read() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response("-gdb-set print elements 16", "^done")
+            .with_response("-gdb-set print repeats unlimited", "^done")
+            .with_response("-gdb-set print null-stop on", "^done")
+            .with_response(
+                "-stack-list-frames",
+                r#"^done,stack=[frame={level="0",addr="0x0000000000401106",func="f",file="main.c",line="2",arch="i386:x86-64"},frame={level="1",addr="0x0000000000401130",func="main",file="main.c",line="6",arch="i386:x86-64"}]"#,
+            )
+            .with_response("-stack-select-frame 0", "^done")
+            .with_response("-stack-select-frame 1", "^done")
+            // The caller is populated first, the string is its local variable
+            .with_response("-stack-list-variables 0", r#"^done,variables=[{name="s"}]"#)
+            .with_response("-stack-list-variables 0", "^done,variables=[]")
+            .with_response(r#"-var-create - * "s""#, r#"^done,name="var1",numchild="1",value="0x402004 \"hi\"",type="char *""#)
+            .with_response(r#"-data-evaluate-expression "&s""#, r#"^done,value="(char **) 0x7fffffffe010""#)
+            .with_response(r#"-var-info-path-expression "var1""#, r#"^done,path_expr="s""#)
+            .with_response(r#"-data-evaluate-expression "s""#, r#"^done,value="0x402004 \"hi\"""#)
+            .with_response("-var-update 2 *", "^done,changelist=[]");
+        let options = GraphOptions::new().with_strings(16);
+        let mut graph = ready(GdbStateGraph::new_with_options(
+            &mut gdb,
+            &CascadeStyle::empty(),
+            options,
+        ))
+        .unwrap();
+        gdb.take_history();
+        ready(graph.update(&mut gdb)).unwrap();
+        let history = gdb.take_history();
+        // The string is read again in the frame of the caller,
+        // and the top frame is selected once it has been
+        assert!(history.contains(&"-stack-select-frame 1".to_owned()));
+        assert_eq!(
+            history.last().map(String::as_str),
+            Some("-stack-select-frame 0")
+        );
+        let string = graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("s".into(), 0)])
+            .unwrap();
+        assert_eq!(string.value(), Some(NodeValue::String("hi".to_owned())));
+    }
+
+    /// Scripts a session with a single `std::vector` variable
+    /// whose elements have the given values.
+    fn vector_session(values: &[u64]) -> MockGdbMiSession {
+        MockGdbMiSession::new()
+            .with_response(
+                "-stack-list-frames",
+                r#"^done,stack=[frame={level="0",addr="0x0000000000401126",func="main",file="main.cpp",line="5",arch="i386:x86-64"}]"#,
+            )
+            .with_response("-stack-select-frame 0", "^done")
+            .with_response("-stack-list-variables 0", r#"^done,variables=[{name="v"}]"#)
+            .with_response(r#"-var-create - * "v""#, r#"^done,name="var1",numchild="1",value="{...}",type="std::vector<int, std::allocator<int> >""#)
+            .with_response(r#"-data-evaluate-expression "&v""#, r#"^done,value="(std::vector<int> *) 0x7fffffffe010""#)
+            .with_response(
+                r#"-var-set-visualizer "var1" gdb.default_visualizer"#,
+                "^done",
+            )
+            .with_response(r#"-var-list-children 2 "var1""#, vector_children(values))
+    }
+
+    /// Formats the response to listing the children of a `std::vector`.
+    fn vector_children(values: &[u64]) -> String {
+        let children = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                format!(
+                    r#"child={{name="var1.[{i}]",exp="[{i}]",numchild="0",value="{value}",type="int"}}"#
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            r#"^done,numchild="{}",children=[{}]"#,
+            values.len(),
+            children.join(",")
+        )
+    }
+
+    #[test]
+    fn writing_to_element_of_adapted_sequence_keeps_other_elements() {
+        let mut gdb = vector_session(&[1, 2, 3])
+            // The contents are listed again on update
+            .with_response(r#"-var-list-children 2 "var1""#, vector_children(&[1, 5, 3]))
+            .with_response(
+                "-var-update 2 *",
+                r#"^done,changelist=[{name="var1.[1]",value="5",in_scope="true",type_changed="false",has_more="0"}]"#,
+            );
+        let options = GraphOptions::new().with_standard_container_adapters();
+        let mut graph = ready(GdbStateGraph::new_with_options(
+            &mut gdb,
+            &CascadeStyle::empty(),
+            options,
+        ))
+        .unwrap();
+        let element = |i: usize| VariableObject(format!("var1.[{i}]"));
+        let indices = (0..3)
+            .map(|i| graph.variables.index_of(&element(i)))
+            .collect::<Vec<_>>();
+        gdb.take_history();
+        ready(graph.update(&mut gdb)).unwrap();
+        let history = gdb.take_history();
+        assert!(history.contains(&r#"-var-list-children 2 "var1""#.to_owned()));
+        // No variable objects are created or deleted
+        assert!(!history.iter().any(
+            |command| command.starts_with("-var-create") || command.starts_with("-var-delete")
+        ));
+        // The nodes of all elements are kept
+        for (i, index) in indices.into_iter().enumerate() {
+            assert!(index.is_some());
+            assert_eq!(graph.variables.index_of(&element(i)), index);
+        }
+        let item = |i: usize| {
+            graph
+                .get_at_root(&[
+                    EdgeLabel::Main,
+                    EdgeLabel::Named("v".into(), 0),
+                    EdgeLabel::Index(i),
+                ])
+                .unwrap()
+                .value()
+        };
+        assert_eq!(item(0), Some(NodeValue::Uint(1)));
+        assert_eq!(item(1), Some(NodeValue::Uint(5)));
+        assert_eq!(item(2), Some(NodeValue::Uint(3)));
+    }
+
+    #[test]
+    fn shrinking_adapted_sequence_removes_elements_past_its_end() {
+        let mut gdb = vector_session(&[1, 2, 3])
+            .with_response(r#"-var-list-children 2 "var1""#, vector_children(&[1]))
+            .with_response("-var-update 2 *", "^done,changelist=[]");
+        let options = GraphOptions::new().with_standard_container_adapters();
+        let mut graph = ready(GdbStateGraph::new_with_options(
+            &mut gdb,
+            &CascadeStyle::empty(),
+            options,
+        ))
+        .unwrap();
+        let element = |i: usize| VariableObject(format!("var1.[{i}]"));
+        let first = graph.variables.index_of(&element(0));
+        ready(graph.update(&mut gdb)).unwrap();
+        assert!(first.is_some());
+        assert_eq!(graph.variables.index_of(&element(0)), first);
+        assert!(!graph.variables.contains_key(&element(1)));
+        assert!(!graph.variables.contains_key(&element(2)));
+        let vector = graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("v".into(), 0)])
+            .unwrap();
+        assert_eq!(
+            vector
+                .successors()
+                .filter(|(edge, _)| matches!(edge, EdgeLabel::Index(_)))
+                .count(),
+            1
+        );
+        let length = vector.get_successor(&EdgeLabel::Length).unwrap();
+        assert_eq!(
+            graph.get(&length).unwrap().value(),
+            Some(NodeValue::Uint(1))
+        );
+    }
+
+    /// Scripts a session with a single variable of an adapted type
+    /// whose pretty-printer has been installed.
+    fn adapted_session(type_name: &str) -> MockGdbMiSession {
+        MockGdbMiSession::new()
+            .with_response(
+                "-stack-list-frames",
+                r#"^done,stack=[frame={level="0",addr="0x0000000000401126",func="main",file="main.cpp",line="5",arch="i386:x86-64"}]"#,
+            )
+            .with_response("-stack-select-frame 0", "^done")
+            .with_response("-stack-list-variables 0", r#"^done,variables=[{name="c"}]"#)
+            .with_response(r#"-var-create - * "c""#, format!(r#"^done,name="var1",numchild="1",value="{{...}}",type="{type_name}""#))
+            .with_response(r#"-data-evaluate-expression "&c""#, r#"^done,value="(void *) 0x7fffffffe010""#)
+            .with_response(
+                r#"-var-set-visualizer "var1" gdb.default_visualizer"#,
+                "^done",
+            )
+            .with_response("-var-update 2 *", "^done,changelist=[]")
+    }
+
+    /// Constructs a graph with the standard container adapters.
+    fn adapted_graph(gdb: &mut MockGdbMiSession) -> GdbStateGraph {
+        let options = GraphOptions::new().with_standard_container_adapters();
+        ready(GdbStateGraph::new_with_options(
+            gdb,
+            &CascadeStyle::empty(),
+            options,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn adapted_map_is_named_by_keys() {
+        let mut gdb = adapted_session("std::map<std::string, int>")
+            .with_response(
+                r#"-var-list-children 2 "var1""#,
+                r#"^done,numchild="4",children=[child={name="var1.[0]",exp="[0]",numchild="0",value="\"one\"",type="std::string"},child={name="var1.[1]",exp="[1]",numchild="0",value="1",type="int"},child={name="var1.[2]",exp="[2]",numchild="0",value="\"two\"",type="std::string"},child={name="var1.[3]",exp="[3]",numchild="0",value="2",type="int"}]"#,
+            );
+        let graph = adapted_graph(&mut gdb);
+        let map = graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("c".into(), 0)])
+            .unwrap();
+        assert_eq!(map.node_type_class(), NodeTypeClass::Struct);
+        assert_eq!(map.value(), None);
+        let value_of = |key: &str| {
+            let value = map.get_successor(&EdgeLabel::Named(key.into(), 0)).unwrap();
+            graph.get(&value).unwrap().value()
+        };
+        assert_eq!(value_of("one"), Some(NodeValue::Uint(1)));
+        assert_eq!(value_of("two"), Some(NodeValue::Uint(2)));
+        // Only the values get nodes, keys only name them
+        assert_eq!(map.successors().count(), 2);
+    }
+
+    #[test]
+    fn adapted_string_is_atom() {
+        let mut gdb = adapted_session("std::string").with_response(
+            r#"-var-evaluate-expression "var1""#,
+            r#"^done,value="\"hello\"""#,
+        );
+        let graph = adapted_graph(&mut gdb);
+        let string = graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("c".into(), 0)])
+            .unwrap();
+        assert_eq!(string.node_type_class(), NodeTypeClass::Atom);
+        assert_eq!(string.value(), Some(NodeValue::String("hello".to_owned())));
+        assert_eq!(string.successors().count(), 0);
+        // The characters are not listed
+        assert!(
+            !gdb.history()
+                .contains(&r#"-var-list-children 2 "var1""#.to_owned())
+        );
+    }
+
+    #[test]
+    fn adapted_pointer_refers_to_owned_object() {
+        let mut gdb = adapted_session("std::unique_ptr<int, std::default_delete<int> >")
+            .with_response(
+                r#"-var-list-children 2 "var1""#,
+                r#"^done,numchild="1",children=[child={name="var1.get()",exp="get()",numchild="1",value="0x4052a0",type="int *"}]"#,
+            )
+            .with_response(r#"-data-evaluate-expression "*(char*)4215456""#, r#"^done,value="7 '\\a'""#)
+            .with_response(r#"-var-create - * "*(int *)4215456""#, r#"^done,name="var2",numchild="0",value="7",type="int""#);
+        let graph = adapted_graph(&mut gdb);
+        let pointer = graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("c".into(), 0)])
+            .unwrap();
+        assert_eq!(pointer.node_type_class(), NodeTypeClass::Ref);
+        assert_eq!(pointer.value(), Some(NodeValue::Uint(0x4052a0)));
+        // The raw pointer does not get a node, it is followed directly
+        let deref_edges = pointer
+            .successors()
+            .map(|(edge, _)| edge.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(deref_edges, [EdgeLabel::Deref]);
+        let target = graph
+            .get_at_root(&[
+                EdgeLabel::Main,
+                EdgeLabel::Named("c".into(), 0),
+                EdgeLabel::Deref,
+            ])
+            .unwrap();
+        assert_eq!(target.value(), Some(NodeValue::Uint(7)));
+    }
+
+    #[test]
+    fn controller_waits_for_stop() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response(
+                r#"-break-insert -t "main.c:6""#,
+                r#"^done,bkpt={number="2",type="breakpoint",disp="del",enabled="y",addr="0x0000000000401130",func="main",file="main.c",fullname="/tmp/main.c",line="6",thread-groups=["i1"],times="0"}"#,
+            )
+            .with_response("-exec-continue", "^running")
+            .with_response("-exec-next", "^running")
+            .with_response("-exec-finish", "^running")
+            .with_event(r#"*running,thread-id="all""#)
+            .with_event(r#"=breakpoint-deleted,id="2""#)
+            .with_event(r#"*stopped,reason="breakpoint-hit",disp="del",bkptno="2",thread-id="1""#)
+            .with_event(r#"*running,thread-id="all""#)
+            .with_event(r#"*stopped,reason="end-stepping-range",thread-id="1""#);
+        let mut controller = DebugController::new(&mut gdb);
+        let location = BreakpointLocation::FileLine {
+            file: "main.c".to_owned(),
+            line: 6,
+        };
+        let reason = ready(controller.run_to(&location)).unwrap();
+        assert_eq!(reason, StopReason::BreakpointHit { number: 2 });
+        let reason = ready(controller.next()).unwrap();
+        assert_eq!(reason, StopReason::EndSteppingRange);
+        // There is no more output, so the debuggee never stops
+        assert!(ready(controller.finish()).is_err());
+        assert_eq!(
+            gdb.history(),
+            [
+                r#"-break-insert -t "main.c:6""#,
+                "-exec-continue",
+                "-exec-next",
+                "-exec-finish",
+            ]
+        );
+    }
+
+    #[test]
+    fn remote_target_is_connected_reset_and_downloaded() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response(r#"-file-exec-and-symbols "firmware.elf""#, "^done")
+            .with_response(
+                "-target-select extended-remote localhost:3333",
+                "^connected",
+            )
+            .with_response(r#"-interpreter-exec console "monitor reset halt""#, "^done")
+            .with_response("-target-download", "^done");
+        let target = RemoteTarget::ExtendedRemote("localhost:3333".to_owned());
+        ready(connect_remote_target(
+            &mut gdb,
+            "firmware.elf",
+            &target,
+            Some("reset halt"),
+            true,
+        ))
+        .unwrap();
+        assert_eq!(
+            gdb.history(),
+            [
+                r#"-file-exec-and-symbols "firmware.elf""#,
+                "-target-select extended-remote localhost:3333",
+                r#"-interpreter-exec console "monitor reset halt""#,
+                "-target-download",
+            ]
+        );
+    }
+
+    #[test]
+    fn remote_target_is_only_reset_and_downloaded_on_request() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response(r#"-file-exec-and-symbols "firmware.elf""#, "^done")
+            .with_response("-target-select remote /dev/ttyACM0", "^connected");
+        let target = RemoteTarget::Remote("/dev/ttyACM0".to_owned());
+        ready(connect_remote_target(
+            &mut gdb,
+            "firmware.elf",
+            &target,
+            None,
+            false,
+        ))
+        .unwrap();
+        assert_eq!(
+            gdb.history(),
+            [
+                r#"-file-exec-and-symbols "firmware.elf""#,
+                "-target-select remote /dev/ttyACM0",
+            ]
+        );
+    }
+
+    #[test]
+    fn remote_target_is_not_prepared_if_it_cannot_be_connected() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response(r#"-file-exec-and-symbols "firmware.elf""#, "^done")
+            .with_response(
+                "-target-select extended-remote localhost:3333",
+                r#"^error,msg="localhost:3333: Connection timed out.""#,
+            );
+        let target = RemoteTarget::ExtendedRemote("localhost:3333".to_owned());
+        let result = ready(connect_remote_target(
+            &mut gdb,
+            "firmware.elf",
+            &target,
+            Some("reset halt"),
+            true,
+        ));
+        assert!(matches!(
+            result,
+            Err(Error::ErrorResponse(response))
+                if response.msg.as_deref() == Some("localhost:3333: Connection timed out.")
+        ));
+        assert_eq!(gdb.history().len(), 2);
+    }
+
+    #[test]
+    fn target_select_expects_connected_record() {
+        let mut gdb =
+            MockGdbMiSession::new().with_response("-target-select remote localhost:1234", "^done");
+        let target = RemoteTarget::Remote("localhost:1234".to_owned());
+        let result = ready(gdb.target_select(&target));
+        assert!(matches!(
+            result,
+            Err(Error::BadResponse(BadResponse::UnexpectedResultClass(class))) if class == "done"
+        ));
+    }
+
+    #[test]
+    fn monitor_and_download_report_errors() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response(
+                r#"-interpreter-exec console "monitor reset halt""#,
+                r#"^error,msg="\"monitor\" command not supported by this target.""#,
+            )
+            .with_response("-target-download", r#"^error,msg="Load failed""#);
+        assert!(matches!(
+            ready(gdb.monitor("reset halt")),
+            Err(Error::ErrorResponse(_))
+        ));
+        assert!(matches!(
+            ready(gdb.target_download()),
+            Err(Error::ErrorResponse(response)) if response.msg.as_deref() == Some("Load failed")
+        ));
+    }
+}
@@ -1,7 +1,9 @@
 //! Interoperability with the
 //! [GDB Machine Interface](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI.html)
 
+pub mod counting;
 pub mod grammar;
+pub mod mock;
 mod parsing;
 pub mod raw_output;
 pub mod result;
@@ -23,6 +23,10 @@ impl Value {
         self.into_const().ok_or(BadResponse::BadValueType)
     }
 
+    pub fn string_list(self) -> Result<Vec<String>> {
+        self.list()?.into_iter().map(Self::string).collect()
+    }
+
     pub fn decimal<T>(self) -> Result<T>
     where
         T: std::str::FromStr,
@@ -67,6 +71,22 @@ impl Value {
         self.tuple()?.stack_frame()
     }
 
+    pub fn thread_info_list(self) -> Result<Vec<ThreadInfo>> {
+        self.list()?.into_iter().map(Self::thread_info).collect()
+    }
+
+    pub fn thread_info(self) -> Result<ThreadInfo> {
+        self.tuple()?.thread_info()
+    }
+
+    pub fn register_value_list(self) -> Result<Vec<RegisterValue>> {
+        self.list()?.into_iter().map(Self::register_value).collect()
+    }
+
+    pub fn register_value(self) -> Result<RegisterValue> {
+        self.tuple()?.register_value()
+    }
+
     pub fn local_variable_list(self) -> Result<Vec<LocalVariable>> {
         self.list()?.into_iter().map(Self::local_variable).collect()
     }
@@ -243,6 +263,44 @@ impl ResultTuple {
         })
     }
 
+    pub fn thread_info(mut self) -> Result<ThreadInfo> {
+        Ok(ThreadInfo {
+            id: self.take("id")?.decimal()?,
+            target_id: self.take("target-id")?.string()?,
+            name: self.take_optional("name").map(Value::string).transpose()?,
+            running: self
+                .take_optional("state")
+                .map(Value::string)
+                .transpose()?
+                .is_some_and(|state| state == "running"),
+        })
+    }
+
+    pub fn thread_list(mut self) -> Result<ThreadList> {
+        Ok(ThreadList {
+            threads: self.take("threads")?.thread_info_list()?,
+            current_thread_id: self
+                .take_optional("current-thread-id")
+                .map(Value::decimal)
+                .transpose()?,
+        })
+    }
+
+    pub fn breakpoint(mut self) -> Result<Breakpoint> {
+        Ok(Breakpoint {
+            number: self.take("number")?.decimal()?,
+            file: self.take_optional("file").map(Value::string).transpose()?,
+            line: self.take_optional("line").map(Value::decimal).transpose()?,
+        })
+    }
+
+    pub fn register_value(mut self) -> Result<RegisterValue> {
+        Ok(RegisterValue {
+            number: self.take("number")?.decimal()?,
+            value: self.take("value")?.string()?,
+        })
+    }
+
     pub fn child_list(mut self) -> Result<ChildList> {
         Ok(ChildList {
             numchild: self.take("numchild")?.decimal()?,
@@ -255,3 +313,140 @@ impl ResultTuple {
         })
     }
 }
+
+impl AsyncExecRecord {
+    /// Gets the number of the watchpoint that caused the debuggee to stop,
+    /// if the record reports such a stop.
+    ///
+    /// This includes both watchpoints that have been triggered
+    /// and watchpoints that have gone out of scope.
+    pub fn watchpoint_number(&self) -> Option<usize> {
+        if self.async_exec_class != AsyncExecClass::Stopped {
+            return None;
+        }
+        let mut results = self.results.clone();
+        let reason = results.take_optional("reason")?.into_const()?;
+        let number = match reason.as_str() {
+            "watchpoint-scope" => results.take_optional("wpnum")?,
+            "watchpoint-trigger" => results
+                .take_optional("wpt")?
+                .into_tuple()?
+                .take("number")
+                .ok()?,
+            "read-watchpoint-trigger" => results
+                .take_optional("hw-rwpt")?
+                .into_tuple()?
+                .take("number")
+                .ok()?,
+            "access-watchpoint-trigger" => results
+                .take_optional("hw-awpt")?
+                .into_tuple()?
+                .take("number")
+                .ok()?,
+            _ => return None,
+        };
+        number.decimal().ok()
+    }
+}
+
+impl Record {
+    /// Interprets the record as a [`SessionEvent`].
+    ///
+    /// Returns [`None`] for result records
+    /// and for notifications that do not describe a [`SessionEvent`].
+    pub fn session_event(&self) -> Option<SessionEvent> {
+        match self {
+            Self::Result(_) => None,
+            Self::AsyncExec(record) => Some(record.session_event()),
+            Self::AsyncNotify(record) => record.session_event(),
+        }
+    }
+}
+
+impl AsyncExecRecord {
+    /// Interprets the record as a [`SessionEvent`].
+    pub fn session_event(&self) -> SessionEvent {
+        let mut results = self.results.clone();
+        let thread_id = results
+            .take_optional("thread-id")
+            .and_then(Value::into_const);
+        match self.async_exec_class {
+            AsyncExecClass::Running => SessionEvent::Running { thread_id },
+            AsyncExecClass::Stopped => SessionEvent::Stopped {
+                reason: self.stop_reason(),
+                thread_id,
+            },
+        }
+    }
+
+    /// Gets the reason why the debuggee has stopped.
+    ///
+    /// Returns [`StopReason::Unknown`] if the record
+    /// does not report a reason or is not a `*stopped` record.
+    pub fn stop_reason(&self) -> StopReason {
+        if self.async_exec_class != AsyncExecClass::Stopped {
+            return StopReason::Unknown;
+        }
+        let mut results = self.results.clone();
+        let Some(reason) = results.take_optional("reason").and_then(Value::into_const) else {
+            return StopReason::Unknown;
+        };
+        let watchpoint_number = self.watchpoint_number();
+        match reason.as_str() {
+            "breakpoint-hit" => match results.take_optional("bkptno").map(Value::decimal) {
+                Some(Ok(number)) => StopReason::BreakpointHit { number },
+                _ => StopReason::Other(reason),
+            },
+            "watchpoint-scope" => match watchpoint_number {
+                Some(number) => StopReason::WatchpointScope { number },
+                None => StopReason::Other(reason),
+            },
+            "watchpoint-trigger" | "read-watchpoint-trigger" | "access-watchpoint-trigger" => {
+                match watchpoint_number {
+                    Some(number) => StopReason::WatchpointTrigger { number },
+                    None => StopReason::Other(reason),
+                }
+            }
+            "end-stepping-range" => StopReason::EndSteppingRange,
+            "function-finished" => StopReason::FunctionFinished {
+                return_value: results
+                    .take_optional("return-value")
+                    .and_then(Value::into_const),
+            },
+            "signal-received" => StopReason::SignalReceived {
+                signal_name: results
+                    .take_optional("signal-name")
+                    .and_then(Value::into_const),
+            },
+            // GDB reports exit codes in octal
+            "exited" => StopReason::Exited {
+                exit_code: results
+                    .take_optional("exit-code")
+                    .and_then(Value::into_const)
+                    .and_then(|code| u32::from_str_radix(&code, 8).ok()),
+            },
+            "exited-normally" => StopReason::Exited { exit_code: Some(0) },
+            "exited-signalled" => StopReason::Exited { exit_code: None },
+            _ => StopReason::Other(reason),
+        }
+    }
+}
+
+impl AsyncNotifyRecord {
+    /// Interprets the record as a [`SessionEvent`].
+    ///
+    /// Returns [`None`] for notifications that do not describe
+    /// a [`SessionEvent`].
+    pub fn session_event(&self) -> Option<SessionEvent> {
+        let mut results = self.results.clone();
+        match self.async_class.as_str() {
+            "thread-created" => Some(SessionEvent::ThreadCreated {
+                id: results.take_optional("id")?.into_const()?,
+                group_id: results
+                    .take_optional("group-id")
+                    .and_then(Value::into_const),
+            }),
+            _ => None,
+        }
+    }
+}
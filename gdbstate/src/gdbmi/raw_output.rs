@@ -44,7 +44,8 @@ pub enum AsyncExecClass {
 
 /// A record in the output of GDB.
 ///
-/// Currently, only [`ResultRecord`] and [`AsyncExecRecord`] are supported.
+/// Currently, only [`ResultRecord`], [`AsyncExecRecord`],
+/// and [`AsyncNotifyRecord`] are supported.
 #[derive(Clone, PartialEq, Eq, Debug, From)]
 pub enum Record {
     /// Result record.
@@ -52,6 +53,9 @@ pub enum Record {
 
     /// Async record.
     AsyncExec(AsyncExecRecord),
+
+    /// Async notification.
+    AsyncNotify(AsyncNotifyRecord),
 }
 
 /// Full [async record](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Async-Records.html).
@@ -64,6 +68,20 @@ pub struct AsyncExecRecord {
     pub results: ResultTuple,
 }
 
+/// Full [async notification](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Async-Records.html),
+/// such as `=thread-created`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AsyncNotifyRecord {
+    /// Class of the record.
+    ///
+    /// GDB reports many kinds of notifications,
+    /// so the class is kept as the name that GDB uses for it.
+    pub async_class: String,
+
+    /// Payload data.
+    pub results: ResultTuple,
+}
+
 /// Full [result record](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Result-Records.html).
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct ResultRecord {
@@ -21,6 +21,12 @@ pub enum Error {
     /// or no response at all.
     #[display("gdb returned unexpected response: {_0}")]
     BadResponse(BadResponse),
+
+    /// The operation has been cancelled
+    /// with a [`CancellationToken`](crate::cancel::CancellationToken).
+    #[display("operation was cancelled")]
+    #[from(ignore)]
+    Cancelled,
 }
 
 /// Describes an error in processing a response returned by GDB.
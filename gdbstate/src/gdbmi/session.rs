@@ -2,9 +2,10 @@
 //! that directly exposes commands known to GDB.
 
 use super::{
+    grammar::parse_gdbmi_record,
     raw_output::*,
     result::{BadResponse, ErrorResponse, Result},
-    stream::GdbMiStream,
+    stream::{GdbMiEventSource, GdbMiStream},
     types::*,
 };
 
@@ -46,6 +47,16 @@ pub trait GdbMiSession {
         bounds: std::ops::Range<usize>,
     ) -> impl Future<Output = Result<Vec<StackFrame>>>;
 
+    /// Exposes the
+    /// [`-thread-info`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Thread-Commands.html#The-_002dthread_002dinfo-Command)
+    /// command.
+    fn thread_info(&mut self) -> impl Future<Output = Result<ThreadList>>;
+
+    /// Exposes the
+    /// [`-thread-select`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Thread-Commands.html#The-_002dthread_002dselect-Command)
+    /// command.
+    fn thread_select(&mut self, thread_id: usize) -> impl Future<Output = Result<()>>;
+
     /// Exposes the
     /// [`-stack-list-variables`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Stack-Manipulation.html#The-_002dstack_002dlist_002dvariables-Command)
     /// command.
@@ -64,6 +75,18 @@ pub trait GdbMiSession {
         expression: &str,
     ) -> impl Future<Output = Result<VariableObjectData>>;
 
+    /// Exposes the
+    /// [`-var-create`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Variable-Objects.html#The-_002dvar_002dcreate-Command)
+    /// command for multiple expressions in the same frame,
+    /// sent as one batch with [`GdbMiStream::send_commands`].
+    ///
+    /// Results are in the order of the expressions.
+    fn var_create_batch(
+        &mut self,
+        frame: VariableObjectFrameContext,
+        expressions: &[&str],
+    ) -> impl Future<Output = Vec<Result<VariableObjectData>>>;
+
     /// Exposes the
     /// [`-var-delete`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Variable-Objects.html#The-_002dvar_002ddelete-Command)
     /// command.
@@ -86,6 +109,42 @@ pub trait GdbMiSession {
         print_values: PrintValues,
     ) -> impl Future<Output = Result<ChildList>>;
 
+    /// Exposes the
+    /// [`-var-list-children`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Variable-Objects.html#The-_002dvar_002dlist_002dchildren-Command)
+    /// command for multiple variable objects,
+    /// sent as one batch with [`GdbMiStream::send_commands`].
+    ///
+    /// Results are in the order of the variable objects.
+    fn var_list_children_batch(
+        &mut self,
+        objects: &[&VariableObject],
+        print_values: PrintValues,
+    ) -> impl Future<Output = Vec<Result<ChildList>>>;
+
+    /// Exposes the
+    /// [`-var-list-children`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Variable-Objects.html#The-_002dvar_002dlist_002dchildren-Command)
+    /// command, restricted to children with indices
+    /// from `from` (inclusive) to `to` (exclusive).
+    fn var_list_children_range(
+        &mut self,
+        object: &VariableObject,
+        print_values: PrintValues,
+        from: usize,
+        to: usize,
+    ) -> impl Future<Output = Result<ChildList>>;
+
+    /// Exposes the
+    /// [`-var-set-visualizer`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Variable-Objects.html#The-_002dvar_002dset_002dvisualizer-Command)
+    /// command.
+    ///
+    /// Pass `gdb.default_visualizer` to use the pretty-printer
+    /// that GDB would select for the variable by default.
+    fn var_set_visualizer(
+        &mut self,
+        object: &VariableObject,
+        visualizer: &str,
+    ) -> impl Future<Output = Result<()>>;
+
     /// Exposes the
     /// [`-var-update`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Variable-Objects.html#The-_002dvar_002dupdate-Command)
     /// command.
@@ -94,6 +153,49 @@ pub trait GdbMiSession {
         print_values: PrintValues,
     ) -> impl Future<Output = Result<Vec<VariableObjectUpdate>>>;
 
+    /// Exposes the
+    /// [`-var-update`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Variable-Objects.html#The-_002dvar_002dupdate-Command)
+    /// command for a single variable object and its children.
+    fn var_update_object(
+        &mut self,
+        print_values: PrintValues,
+        object: &VariableObject,
+    ) -> impl Future<Output = Result<Vec<VariableObjectUpdate>>>;
+
+    /// Exposes the
+    /// [`-var-info-path-expression`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Variable-Objects.html#The-_002dvar_002dinfo_002dpath_002dexpression-Command)
+    /// command.
+    fn var_info_path_expression(
+        &mut self,
+        object: &VariableObject,
+    ) -> impl Future<Output = Result<String>>;
+
+    /// Exposes the
+    /// [`-break-insert`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Breakpoint-Commands.html#The-_002dbreak_002dinsert-Command)
+    /// command.
+    ///
+    /// Temporary breakpoints are deleted when they are hit.
+    fn break_insert(
+        &mut self,
+        location: &BreakpointLocation,
+        temporary: bool,
+    ) -> impl Future<Output = Result<Breakpoint>>;
+
+    /// Exposes the
+    /// [`-break-watch`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Breakpoint-Commands.html#The-_002dbreak_002dwatch-Command)
+    /// command.
+    fn break_watch(&mut self, expression: &str) -> impl Future<Output = Result<Watchpoint>>;
+
+    /// Exposes the
+    /// [`-break-delete`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Breakpoint-Commands.html#The-_002dbreak_002ddelete-Command)
+    /// command.
+    fn break_delete(&mut self, number: usize) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-gdb-set`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Miscellaneous-Commands.html#The-_002dgdb_002dset-Command)
+    /// command.
+    fn gdb_set(&mut self, variable: &str, value: &str) -> impl Future<Output = Result<()>>;
+
     /// Exposes the
     /// [`-data-evaluate-expression`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Data-Manipulation.html#The-_002ddata_002devaluate_002dexpression-Command)
     /// command.
@@ -101,6 +203,121 @@ pub trait GdbMiSession {
         &mut self,
         expression: &str,
     ) -> impl Future<Output = Result<String>>;
+
+    /// Exposes the
+    /// [`-data-list-register-names`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Data-Manipulation.html#The-_002ddata_002dlist_002dregister_002dnames-Command)
+    /// command.
+    ///
+    /// Names are listed in order of the numbers of the registers.
+    /// Numbers that do not belong to any register have an empty name.
+    fn data_list_register_names(&mut self) -> impl Future<Output = Result<Vec<String>>>;
+
+    /// Exposes the
+    /// [`-data-list-register-values`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Data-Manipulation.html#The-_002ddata_002dlist_002dregister_002dvalues-Command)
+    /// command.
+    ///
+    /// Values are read from the selected stack frame.
+    fn data_list_register_values(
+        &mut self,
+        format: RegisterFormat,
+        registers: &[usize],
+    ) -> impl Future<Output = Result<Vec<RegisterValue>>>;
+
+    /// Exposes the
+    /// [`-exec-continue`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Program-Execution.html#The-_002dexec_002dcontinue-Command)
+    /// command.
+    ///
+    /// The command completes as soon as the debuggee starts running,
+    /// not when it stops again. See [`DebugController`]
+    /// for a way to wait until it stops.
+    fn exec_continue(&mut self) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-exec-step`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Program-Execution.html#The-_002dexec_002dstep-Command)
+    /// command.
+    ///
+    /// The command completes as soon as the debuggee starts running.
+    fn exec_step(&mut self) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-exec-next`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Program-Execution.html#The-_002dexec_002dnext-Command)
+    /// command.
+    ///
+    /// The command completes as soon as the debuggee starts running.
+    fn exec_next(&mut self) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-exec-finish`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Program-Execution.html#The-_002dexec_002dfinish-Command)
+    /// command.
+    ///
+    /// The command completes as soon as the debuggee starts running.
+    fn exec_finish(&mut self) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-exec-continue --reverse`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Program-Execution.html#The-_002dexec_002dcontinue-Command)
+    /// command.
+    ///
+    /// Reverse execution is only available if the target supports it,
+    /// for example when replaying a recording made by `rr`
+    /// or when process record is enabled.
+    fn exec_reverse_continue(&mut self) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-exec-step --reverse`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Program-Execution.html#The-_002dexec_002dstep-Command)
+    /// command.
+    ///
+    /// Reverse execution is only available if the target supports it.
+    fn exec_reverse_step(&mut self) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-exec-next --reverse`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Program-Execution.html#The-_002dexec_002dnext-Command)
+    /// command.
+    ///
+    /// Reverse execution is only available if the target supports it.
+    fn exec_reverse_next(&mut self) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-exec-finish --reverse`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Program-Execution.html#The-_002dexec_002dfinish-Command)
+    /// command.
+    ///
+    /// Reverse execution is only available if the target supports it.
+    fn exec_reverse_finish(&mut self) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-file-exec-and-symbols`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-File-Commands.html#The-_002dfile_002dexec_002dand_002dsymbols-Command)
+    /// command.
+    fn file_exec_and_symbols(&mut self, path: &str) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-target-select`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Target-Manipulation.html#The-_002dtarget_002dselect-Command)
+    /// command.
+    fn target_select(&mut self, target: &RemoteTarget) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-target-disconnect`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Target-Manipulation.html#The-_002dtarget_002ddisconnect-Command)
+    /// command.
+    fn target_disconnect(&mut self) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-target-download`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Target-Manipulation.html#The-_002dtarget_002ddownload-Command)
+    /// command.
+    fn target_download(&mut self) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`monitor`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/Connecting.html#index-monitor)
+    /// command through
+    /// [`-interpreter-exec`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Miscellaneous-Commands.html#The-_002dinterpreter_002dexec-Command).
+    ///
+    /// The command is passed to the remote stub verbatim,
+    /// so the available commands depend on the stub.
+    fn monitor(&mut self, command: &str) -> impl Future<Output = Result<()>>;
+
+    /// Exposes the
+    /// [`-interpreter-exec console`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Miscellaneous-Commands.html#The-_002dinterpreter_002dexec-Command)
+    /// command.
+    ///
+    /// Output that the command prints to the console is not captured.
+    fn interpreter_exec_console(&mut self, command: &str) -> impl Future<Output = Result<()>>;
 }
 
 impl<T: GdbMiStream> GdbMiSession for T {
@@ -166,6 +383,21 @@ impl<T: GdbMiStream> GdbMiSession for T {
             .stack_trace()?)
     }
 
+    async fn thread_info(&mut self) -> Result<ThreadList> {
+        Ok(self
+            .send_command("-thread-info")
+            .await?
+            .must_be_done_or_running()?
+            .thread_list()?)
+    }
+
+    async fn thread_select(&mut self, thread_id: usize) -> Result<()> {
+        self.send_command_fmt(format_args!("-thread-select {thread_id}"))
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
     async fn stack_list_variables(
         &mut self,
         print_values: PrintValues,
@@ -198,6 +430,22 @@ impl<T: GdbMiStream> GdbMiSession for T {
             .var_object()?)
     }
 
+    async fn var_create_batch(
+        &mut self,
+        frame: VariableObjectFrameContext,
+        expressions: &[&str],
+    ) -> Vec<Result<VariableObjectData>> {
+        let commands = expressions
+            .iter()
+            .map(|expression| format!("-var-create - {frame} {expression:?}"))
+            .collect::<Vec<_>>();
+        self.send_commands(&commands)
+            .await
+            .into_iter()
+            .map(|record| Ok(record?.must_be_done_or_running()?.var_object()?))
+            .collect()
+    }
+
     async fn var_delete(&mut self, object: &VariableObject) -> Result<()> {
         self.send_command_fmt(format_args!("-var-delete \"{}\"", object.0))
             .await?
@@ -229,6 +477,53 @@ impl<T: GdbMiStream> GdbMiSession for T {
             .child_list()?)
     }
 
+    async fn var_list_children_batch(
+        &mut self,
+        objects: &[&VariableObject],
+        print_values: PrintValues,
+    ) -> Vec<Result<ChildList>> {
+        let commands = objects
+            .iter()
+            .map(|object| format!("-var-list-children {print_values} \"{}\"", object.0))
+            .collect::<Vec<_>>();
+        self.send_commands(&commands)
+            .await
+            .into_iter()
+            .map(|record| Ok(record?.must_be_done_or_running()?.child_list()?))
+            .collect()
+    }
+
+    async fn var_list_children_range(
+        &mut self,
+        object: &VariableObject,
+        print_values: PrintValues,
+        from: usize,
+        to: usize,
+    ) -> Result<ChildList> {
+        Ok(self
+            .send_command_fmt(format_args!(
+                "-var-list-children {print_values} \"{}\" {from} {to}",
+                object.0
+            ))
+            .await?
+            .must_be_done_or_running()?
+            .child_list()?)
+    }
+
+    async fn var_set_visualizer(
+        &mut self,
+        object: &VariableObject,
+        visualizer: &str,
+    ) -> Result<()> {
+        self.send_command_fmt(format_args!(
+            "-var-set-visualizer \"{}\" {visualizer}",
+            object.0
+        ))
+        .await?
+        .must_be_done_or_running()?;
+        Ok(())
+    }
+
     async fn var_update(&mut self, print_values: PrintValues) -> Result<Vec<VariableObjectUpdate>> {
         Ok(self
             .send_command_fmt(format_args!("-var-update {print_values} *"))
@@ -238,6 +533,73 @@ impl<T: GdbMiStream> GdbMiSession for T {
             .varobj_changelist()?)
     }
 
+    async fn var_update_object(
+        &mut self,
+        print_values: PrintValues,
+        object: &VariableObject,
+    ) -> Result<Vec<VariableObjectUpdate>> {
+        Ok(self
+            .send_command_fmt(format_args!("-var-update {print_values} \"{}\"", object.0))
+            .await?
+            .must_be_done_or_running()?
+            .take("changelist")?
+            .varobj_changelist()?)
+    }
+
+    async fn var_info_path_expression(&mut self, object: &VariableObject) -> Result<String> {
+        Ok(self
+            .send_command_fmt(format_args!("-var-info-path-expression \"{}\"", object.0))
+            .await?
+            .must_be_done_or_running()?
+            .take("path_expr")?
+            .string()?)
+    }
+
+    async fn break_insert(
+        &mut self,
+        location: &BreakpointLocation,
+        temporary: bool,
+    ) -> Result<Breakpoint> {
+        let temporary = if temporary { "-t " } else { "" };
+        Ok(self
+            .send_command_fmt(format_args!(
+                "-break-insert {temporary}{:?}",
+                location.to_string()
+            ))
+            .await?
+            .must_be_done_or_running()?
+            .take("bkpt")?
+            .tuple()?
+            .breakpoint()?)
+    }
+
+    async fn break_watch(&mut self, expression: &str) -> Result<Watchpoint> {
+        let mut watchpoint = self
+            .send_command_fmt(format_args!("-break-watch {expression:?}"))
+            .await?
+            .must_be_done_or_running()?
+            .take("wpt")?
+            .tuple()?;
+        Ok(Watchpoint {
+            number: watchpoint.take("number")?.decimal()?,
+            exp: watchpoint.take("exp")?.string()?,
+        })
+    }
+
+    async fn break_delete(&mut self, number: usize) -> Result<()> {
+        self.send_command_fmt(format_args!("-break-delete {number}"))
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn gdb_set(&mut self, variable: &str, value: &str) -> Result<()> {
+        self.send_command_fmt(format_args!("-gdb-set {variable} {value}"))
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
     async fn data_evaluate_expression(&mut self, expression: &str) -> Result<String> {
         Ok(self
             .send_command_fmt(format_args!("-data-evaluate-expression {expression:?}"))
@@ -246,6 +608,316 @@ impl<T: GdbMiStream> GdbMiSession for T {
             .take("value")?
             .string()?)
     }
+
+    async fn data_list_register_names(&mut self) -> Result<Vec<String>> {
+        Ok(self
+            .send_command("-data-list-register-names")
+            .await?
+            .must_be_done_or_running()?
+            .take("register-names")?
+            .string_list()?)
+    }
+
+    async fn data_list_register_values(
+        &mut self,
+        format: RegisterFormat,
+        registers: &[usize],
+    ) -> Result<Vec<RegisterValue>> {
+        let registers = registers
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(self
+            .send_command_fmt(format_args!(
+                "-data-list-register-values {format} {registers}"
+            ))
+            .await?
+            .must_be_done_or_running()?
+            .take("register-values")?
+            .register_value_list()?)
+    }
+
+    async fn exec_continue(&mut self) -> Result<()> {
+        self.send_command("-exec-continue")
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn exec_step(&mut self) -> Result<()> {
+        self.send_command("-exec-step")
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn exec_next(&mut self) -> Result<()> {
+        self.send_command("-exec-next")
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn exec_finish(&mut self) -> Result<()> {
+        self.send_command("-exec-finish")
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn exec_reverse_continue(&mut self) -> Result<()> {
+        self.send_command("-exec-continue --reverse")
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn exec_reverse_step(&mut self) -> Result<()> {
+        self.send_command("-exec-step --reverse")
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn exec_reverse_next(&mut self) -> Result<()> {
+        self.send_command("-exec-next --reverse")
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn exec_reverse_finish(&mut self) -> Result<()> {
+        self.send_command("-exec-finish --reverse")
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn file_exec_and_symbols(&mut self, path: &str) -> Result<()> {
+        self.send_command_fmt(format_args!("-file-exec-and-symbols {path:?}"))
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn target_select(&mut self, target: &RemoteTarget) -> Result<()> {
+        self.send_command_fmt(format_args!("-target-select {target}"))
+            .await?
+            .must_be_connected()?;
+        Ok(())
+    }
+
+    async fn target_disconnect(&mut self) -> Result<()> {
+        self.send_command("-target-disconnect")
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn target_download(&mut self) -> Result<()> {
+        self.send_command("-target-download")
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    async fn monitor(&mut self, command: &str) -> Result<()> {
+        self.interpreter_exec_console(&format!("monitor {command}"))
+            .await
+    }
+
+    async fn interpreter_exec_console(&mut self, command: &str) -> Result<()> {
+        self.send_command_fmt(format_args!("-interpreter-exec console {command:?}"))
+            .await?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+}
+
+/// Connects a session to a remote target, such as a `gdbserver`
+/// or a debug probe attached to a microcontroller,
+/// and prepares it to run a program.
+///
+/// The executable is loaded into GDB to provide debug symbols.
+/// If `reset_command` is provided, it is sent to the remote stub
+/// as a [`monitor`](GdbMiSession::monitor) command after connecting,
+/// which can be used to reset the board (for example, OpenOCD
+/// understands `reset halt`). If `download` is set,
+/// the executable is then written to the target's memory.
+pub async fn connect_remote_target(
+    gdb: &mut impl GdbMiSession,
+    executable_path: &str,
+    target: &RemoteTarget,
+    reset_command: Option<&str>,
+    download: bool,
+) -> Result<()> {
+    gdb.file_exec_and_symbols(executable_path).await?;
+    gdb.target_select(target).await?;
+    if let Some(reset_command) = reset_command {
+        gdb.monitor(reset_command).await?;
+    }
+    if download {
+        gdb.target_download().await?;
+    }
+    Ok(())
+}
+
+/// Stream of [`SessionEvent`]s that GDB reports asynchronously.
+///
+/// This spares the embedder from parsing the async output of GDB
+/// to find out when the state graph should be updated.
+///
+/// ```no_run
+/// # use aili_gdbstate::gdbmi::{session::SessionEvents, stream::GdbMiEventSource};
+/// async fn wait_for_stop(source: impl GdbMiEventSource) {
+///     let mut events = SessionEvents::new(source);
+///     while let Some(Ok(event)) = events.next().await {
+///         if event.requires_update() {
+///             // Update the state graph here
+///             return;
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SessionEvents<T> {
+    source: T,
+}
+
+impl<T: GdbMiEventSource> SessionEvents<T> {
+    /// Constructs a stream that reads events from a source of GDB output.
+    pub fn new(source: T) -> Self {
+        Self { source }
+    }
+
+    /// Waits for the next event.
+    ///
+    /// Lines of output that are not async records,
+    /// or that do not describe a [`SessionEvent`], are skipped.
+    /// Returns [`None`] when the source has no more output.
+    pub async fn next(&mut self) -> Option<std::io::Result<SessionEvent>> {
+        loop {
+            let line = match self.source.next_line().await? {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error)),
+            };
+            let event = parse_gdbmi_record(&line)
+                .ok()
+                .and_then(|record| record.session_event());
+            if let Some(event) = event {
+                return Some(Ok(event));
+            }
+        }
+    }
+
+    /// Unwraps the source of GDB output.
+    pub fn into_inner(self) -> T {
+        self.source
+    }
+}
+
+/// Drives the execution of the debuggee with breakpoints and stepping.
+///
+/// Execution commands return as soon as the debuggee starts running,
+/// so the controller waits for the `*stopped` record that follows
+/// and reports why the debuggee stopped. Once a method returns,
+/// the debuggee is paused and the state graph can be updated,
+/// unless it has exited.
+///
+/// The controller reads commands and events through the same object,
+/// which must implement both [`GdbMiStream`] and [`GdbMiEventSource`].
+///
+/// ```no_run
+/// # use aili_gdbstate::gdbmi::{session::DebugController, stream::*};
+/// async fn step_through(mut gdb: impl GdbMiStream + GdbMiEventSource) {
+///     let mut controller = DebugController::new(&mut gdb);
+///     controller.run_to_line(4).await.unwrap();
+///     controller.next().await.unwrap();
+///     controller.finish().await.unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DebugController<'a, T> {
+    gdb: &'a mut T,
+}
+
+impl<'a, T: GdbMiStream + GdbMiEventSource> DebugController<'a, T> {
+    /// Constructs a controller of a paused debuggee.
+    pub fn new(gdb: &'a mut T) -> Self {
+        Self { gdb }
+    }
+
+    /// Provides access to the underlying session,
+    /// for example to update the state graph.
+    pub fn session(&mut self) -> &mut T {
+        self.gdb
+    }
+
+    /// Inserts a breakpoint that stays until it is deleted.
+    pub async fn insert_breakpoint(&mut self, location: &BreakpointLocation) -> Result<Breakpoint> {
+        self.gdb.break_insert(location, false).await
+    }
+
+    /// Deletes a breakpoint.
+    pub async fn delete_breakpoint(&mut self, breakpoint: &Breakpoint) -> Result<()> {
+        self.gdb.break_delete(breakpoint.number).await
+    }
+
+    /// Continues execution until a line in the current source file is reached,
+    /// or until the debuggee stops for another reason.
+    pub async fn run_to_line(&mut self, line: usize) -> Result<StopReason> {
+        self.run_to(&BreakpointLocation::Line(line)).await
+    }
+
+    /// Continues execution until a location is reached,
+    /// or until the debuggee stops for another reason.
+    pub async fn run_to(&mut self, location: &BreakpointLocation) -> Result<StopReason> {
+        self.gdb.break_insert(location, true).await?;
+        self.resume().await
+    }
+
+    /// Continues execution until the debuggee stops.
+    pub async fn resume(&mut self) -> Result<StopReason> {
+        self.gdb.exec_continue().await?;
+        self.wait_for_stop().await
+    }
+
+    /// Executes one source line, entering function calls.
+    pub async fn step(&mut self) -> Result<StopReason> {
+        self.gdb.exec_step().await?;
+        self.wait_for_stop().await
+    }
+
+    /// Executes one source line, stepping over function calls.
+    pub async fn next(&mut self) -> Result<StopReason> {
+        self.gdb.exec_next().await?;
+        self.wait_for_stop().await
+    }
+
+    /// Continues execution until the current function returns.
+    pub async fn finish(&mut self) -> Result<StopReason> {
+        self.gdb.exec_finish().await?;
+        self.wait_for_stop().await
+    }
+
+    /// Reads events until the debuggee stops.
+    ///
+    /// Fails with [`std::io::ErrorKind::UnexpectedEof`]
+    /// if the output ends before the debuggee stops.
+    async fn wait_for_stop(&mut self) -> Result<StopReason> {
+        loop {
+            let Some(line) = self.gdb.next_line().await else {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            };
+            let event = parse_gdbmi_record(&line?)
+                .ok()
+                .and_then(|record| record.session_event());
+            if let Some(SessionEvent::Stopped { reason, .. }) = event {
+                return Ok(reason);
+            }
+        }
+    }
 }
 
 impl ResultRecord {
@@ -259,4 +931,15 @@ impl ResultRecord {
         }
         Ok(self.results)
     }
+
+    pub fn must_be_connected(mut self) -> Result<ResultTuple> {
+        if self.result_class == ResultClass::Error {
+            let msg = self.results.take("msg").and_then(Value::string).ok();
+            return Err(ErrorResponse { msg }.into());
+        }
+        if self.result_class != ResultClass::Connected {
+            return Err(BadResponse::UnexpectedResultClass(self.result_class.to_string()).into());
+        }
+        Ok(self.results)
+    }
 }
@@ -5,6 +5,7 @@ use super::{
     raw_output::{Record, ResultRecord},
     result::{BadResponse, Result},
 };
+use std::collections::HashMap;
 
 /// Low level interface to GDB that communicates using literal strings.
 pub trait StringGdbMiStream {
@@ -26,6 +27,26 @@ pub trait StringGdbMiStream {
     ) -> impl Future<Output = std::io::Result<String>> {
         async move { self.send_command(&std::fmt::format(args)).await }
     }
+
+    /// Sends a batch of independent MI commands to GDB.
+    ///
+    /// The returned strings are the result records that respond
+    /// to the commands, in the order of the commands.
+    /// See [`GdbMiStream::send_commands`] for how the commands may be sent.
+    ///
+    /// The default implementation sends the commands one by one.
+    fn send_commands(
+        &mut self,
+        commands: &[String],
+    ) -> impl Future<Output = Vec<std::io::Result<String>>> {
+        async move {
+            let mut results = Vec::with_capacity(commands.len());
+            for command in commands {
+                results.push(self.send_command(command).await);
+            }
+            results
+        }
+    }
 }
 
 /// Low level interface to GDB that responds with parsed result records.
@@ -48,22 +69,134 @@ pub trait GdbMiStream {
     ) -> impl Future<Output = Result<ResultRecord>> {
         async move { self.send_command(&std::fmt::format(args)).await }
     }
+
+    /// Sends a batch of independent MI commands to GDB.
+    ///
+    /// The responses are returned in the order of the commands,
+    /// and the result of each command is independent of the others,
+    /// so one command failing does not prevent the others from being sent.
+    /// The commands must not depend on the results of one another,
+    /// because they may be issued before any of them has completed.
+    ///
+    /// The default implementation sends the commands one by one.
+    /// Streams that can have multiple commands in flight should override it,
+    /// send each command with a unique [token](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Input-Syntax.html),
+    /// and match the responses to the commands using [`order_by_token`].
+    fn send_commands(
+        &mut self,
+        commands: &[String],
+    ) -> impl Future<Output = Vec<Result<ResultRecord>>> {
+        async move {
+            let mut results = Vec::with_capacity(commands.len());
+            for command in commands {
+                results.push(self.send_command(command).await);
+            }
+            results
+        }
+    }
+}
+
+/// Low level interface to the output that GDB produces on its own,
+/// rather than in response to a command.
+///
+/// This includes [async records](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Async-Records.html)
+/// that report when the debuggee starts or stops.
+/// The lines are not required to be async records,
+/// other lines are skipped by readers of the source.
+pub trait GdbMiEventSource {
+    /// Waits for the next line of output.
+    ///
+    /// Returns [`None`] when there will be no more output,
+    /// for example because GDB has exited.
+    fn next_line(&mut self) -> impl Future<Output = Option<std::io::Result<String>>>;
+}
+
+/// Matches the responses to a batch of commands
+/// to the tokens the commands were sent with.
+///
+/// `tokens` are the tokens of the commands in the order
+/// the commands were sent. The returned results are in the same order.
+/// Responses may arrive in any order; those whose token is not expected
+/// are ignored. Commands that have not received a response
+/// fail with [`BadResponse::MissingResultRecord`].
+pub fn order_by_token<'a>(
+    tokens: impl IntoIterator<Item = &'a str>,
+    responses: impl IntoIterator<Item = ResultRecord>,
+) -> Vec<Result<ResultRecord>> {
+    let mut responses = responses
+        .into_iter()
+        .filter_map(|record| Some((record.token.clone()?, record)))
+        .collect::<HashMap<_, _>>();
+    tokens
+        .into_iter()
+        .map(|token| {
+            responses
+                .remove(token)
+                .ok_or_else(|| BadResponse::MissingResultRecord.into())
+        })
+        .collect()
 }
 
 impl<T: StringGdbMiStream> GdbMiStream for T {
     async fn send_command(&mut self, command: &str) -> Result<ResultRecord> {
         let output = StringGdbMiStream::send_command(self, command).await?;
-        match parse_gdbmi_record(&output) {
-            Ok(Record::Result(r)) => Ok(r),
-            _ => Err(BadResponse::SyntaxError(output).into()),
-        }
+        parse_result_record(output)
     }
 
     async fn send_command_fmt(&mut self, args: std::fmt::Arguments<'_>) -> Result<ResultRecord> {
         let output = StringGdbMiStream::send_command_fmt(self, args).await?;
-        match parse_gdbmi_record(&output) {
-            Ok(Record::Result(r)) => Ok(r),
-            _ => Err(BadResponse::SyntaxError(output).into()),
+        parse_result_record(output)
+    }
+
+    async fn send_commands(&mut self, commands: &[String]) -> Vec<Result<ResultRecord>> {
+        StringGdbMiStream::send_commands(self, commands)
+            .await
+            .into_iter()
+            .map(|output| parse_result_record(output?))
+            .collect()
+    }
+}
+
+/// Parses the result record that responds to a command.
+fn parse_result_record(output: String) -> Result<ResultRecord> {
+    match parse_gdbmi_record(&output) {
+        Ok(Record::Result(r)) => Ok(r),
+        _ => Err(BadResponse::SyntaxError(output).into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gdbmi::{
+        raw_output::{ResultClass, ResultTuple},
+        result::Error,
+    };
+
+    fn record_with_token(token: &str) -> ResultRecord {
+        ResultRecord {
+            token: Some(token.to_owned()),
+            result_class: ResultClass::Done,
+            results: ResultTuple::default(),
         }
     }
+
+    #[test]
+    fn responses_are_matched_to_commands() {
+        let results = order_by_token(
+            ["1", "2", "3"],
+            [
+                record_with_token("3"),
+                record_with_token("4"),
+                record_with_token("1"),
+            ],
+        );
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().token.as_deref(), Some("1"));
+        assert!(matches!(
+            results[1],
+            Err(Error::BadResponse(BadResponse::MissingResultRecord))
+        ));
+        assert_eq!(results[2].as_ref().unwrap().token.as_deref(), Some("3"));
+    }
 }
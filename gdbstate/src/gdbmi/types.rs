@@ -63,6 +63,73 @@ pub struct StackFrame {
     pub arch: String,
 }
 
+/// Description of a thread in the response to the
+/// [`-thread-info`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Thread-Commands.html#The-_002dthread_002dinfo-Command)
+/// command.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ThreadInfo {
+    /// Number that GDB uses to identify the thread.
+    pub id: usize,
+
+    /// Identifier of the thread in the target, such as `Thread 0x7ffff7d8a740 (LWP 1234)`.
+    pub target_id: String,
+
+    /// Name of the thread, if it has one.
+    pub name: Option<String>,
+
+    /// True if the thread is currently running.
+    ///
+    /// Stack frames of running threads cannot be listed.
+    pub running: bool,
+}
+
+/// Response to the
+/// [`-thread-info`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Thread-Commands.html#The-_002dthread_002dinfo-Command)
+/// command.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ThreadList {
+    /// All threads of the debuggee.
+    pub threads: Vec<ThreadInfo>,
+
+    /// Number of the thread that is currently selected, if any.
+    pub current_thread_id: Option<usize>,
+}
+
+/// Value of a register in the response to the
+/// [`-data-list-register-values`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Data-Manipulation.html#The-_002ddata_002dlist_002dregister_002dvalues-Command)
+/// command.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RegisterValue {
+    /// Number that GDB uses to identify the register.
+    pub number: usize,
+
+    /// Value of the register, formatted as requested by a [`RegisterFormat`].
+    pub value: String,
+}
+
+/// Used by the
+/// [`-data-list-register-values`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Data-Manipulation.html#The-_002ddata_002dlist_002dregister_002dvalues-Command)
+/// command, specifies how values of registers are formatted.
+#[derive(Display)]
+pub enum RegisterFormat {
+    /// Values are formatted as hexadecimal numbers.
+    #[display("x")]
+    Hexadecimal,
+
+    /// Values are formatted as signed decimal numbers.
+    #[display("d")]
+    Decimal,
+
+    /// Values are formatted in their natural format,
+    /// which depends on the type of the register.
+    #[display("N")]
+    Natural,
+
+    /// Raw contents of the registers are returned.
+    #[display("r")]
+    Raw,
+}
+
 /// Description of a local variable in responses to some
 /// [stack manipulation commands](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Stack-Manipulation.html).
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -248,3 +315,172 @@ pub enum VariableObjectFrameContext {
     #[display("@")]
     Floating,
 }
+
+/// Remote target that GDB can connect to.
+///
+/// The connection string depends on how the remote stub is reachable.
+/// Usually, it is `host:port` for a TCP connection, such as with
+/// [`gdbserver`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/Server.html),
+/// or a path to a serial device.
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+pub enum RemoteTarget {
+    /// Target that runs a single program and stops when it exits.
+    #[display("remote {_0}")]
+    Remote(String),
+
+    /// Target that can run and attach to any number of programs.
+    #[display("extended-remote {_0}")]
+    ExtendedRemote(String),
+}
+
+/// Location in the debuggee's code where a breakpoint can be inserted.
+///
+/// Locations are formatted as
+/// [linespecs](https://sourceware.org/gdb/current/onlinedocs/gdb.html/Linespec-Locations.html).
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+pub enum BreakpointLocation {
+    /// Line in the source file that is currently being executed.
+    #[display("{_0}")]
+    Line(usize),
+
+    /// Line in a named source file.
+    #[display("{file}:{line}")]
+    FileLine {
+        /// Name of the source file.
+        file: String,
+
+        /// Line in the source file.
+        line: usize,
+    },
+
+    /// Start of a function.
+    #[display("{_0}")]
+    Function(String),
+}
+
+/// Breakpoint created by
+/// [`-break-insert`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Breakpoint-Commands.html#The-_002dbreak_002dinsert-Command).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Breakpoint {
+    /// Number that identifies the breakpoint.
+    pub number: usize,
+
+    /// Source file where the breakpoint is, if known.
+    pub file: Option<String>,
+
+    /// Line in the source file where the breakpoint is, if known.
+    pub line: Option<usize>,
+}
+
+/// Watchpoint created by
+/// [`-break-watch`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Breakpoint-Commands.html#The-_002dbreak_002dwatch-Command).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Watchpoint {
+    /// Number that identifies the watchpoint.
+    pub number: usize,
+
+    /// The watched expression.
+    pub exp: String,
+}
+
+/// Change in the state of the debuggee that GDB reports asynchronously.
+///
+/// Events are extracted from async records
+/// with [`Record::session_event`](super::raw_output::Record::session_event).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SessionEvent {
+    /// The debuggee has started executing (`*running`).
+    ///
+    /// The state graph cannot be updated until the debuggee stops again.
+    Running {
+        /// Identifier of the thread that is running,
+        /// or `all` if all threads are running.
+        thread_id: Option<String>,
+    },
+
+    /// The debuggee has stopped executing (`*stopped`).
+    Stopped {
+        /// Why the debuggee has stopped.
+        reason: StopReason,
+
+        /// Identifier of the thread that has caused the stop.
+        thread_id: Option<String>,
+    },
+
+    /// A new thread has been created in the debuggee (`=thread-created`).
+    ThreadCreated {
+        /// Identifier of the new thread.
+        id: String,
+
+        /// Identifier of the thread group (inferior) that the thread belongs to.
+        group_id: Option<String>,
+    },
+}
+
+impl SessionEvent {
+    /// Checks whether the state graph should be updated after the event.
+    ///
+    /// This is the case if the debuggee has stopped
+    /// and is still alive, so its state can be inspected.
+    pub fn requires_update(&self) -> bool {
+        match self {
+            Self::Stopped { reason, .. } => !matches!(reason, StopReason::Exited { .. }),
+            _ => false,
+        }
+    }
+}
+
+/// Reason why the debuggee has stopped, as reported in a
+/// [`*stopped`](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Async-Records.html)
+/// record.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum StopReason {
+    /// A breakpoint has been hit.
+    BreakpointHit {
+        /// Number of the breakpoint.
+        number: usize,
+    },
+
+    /// A watched expression has been accessed or has changed.
+    WatchpointTrigger {
+        /// Number of the watchpoint.
+        number: usize,
+    },
+
+    /// A watched expression has gone out of scope.
+    WatchpointScope {
+        /// Number of the watchpoint.
+        number: usize,
+    },
+
+    /// A step command has finished.
+    EndSteppingRange,
+
+    /// A `-exec-finish` command has finished.
+    FunctionFinished {
+        /// Value that the function has returned, as printed by GDB,
+        /// or [`None`] if the function does not return anything.
+        return_value: Option<String>,
+    },
+
+    /// The debuggee has received a signal.
+    SignalReceived {
+        /// Name of the signal, such as `SIGSEGV`.
+        signal_name: Option<String>,
+    },
+
+    /// The debuggee has exited.
+    Exited {
+        /// Exit code of the debuggee, if it has exited normally.
+        exit_code: Option<u32>,
+    },
+
+    /// Any other reason, as named by GDB.
+    Other(String),
+
+    /// GDB has not reported a reason.
+    ///
+    /// This happens, for example, when the debuggee
+    /// is interrupted, or when a remote target connects.
+    Unknown,
+}
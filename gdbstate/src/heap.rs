@@ -0,0 +1,188 @@
+//! Enumeration of blocks allocated on the heap
+//! that are not reachable from the state graph.
+
+use std::collections::BTreeSet;
+
+/// Python source of the `$_aili_heap` GDB convenience function.
+///
+/// The function walks the chunks of the main heap of the glibc allocator
+/// and returns a string of space-separated descriptions of the chunks
+/// that are in use, in the format `address:size`. Addresses point
+/// to the start of the usable memory of each chunk. Both numbers are decimal.
+///
+/// Chunks freed into the tcache or fastbins remain marked as in use,
+/// so the function follows the free lists and leaves their chunks out,
+/// as well as the allocator's own `tcache_perthread_struct`,
+/// which occupies the first chunk of the heap.
+/// Fastbins can only be found if GDB has the debugging
+/// information of glibc, since `main_arena` is not exported.
+pub(crate) const HEAP_FUNCTION_SOURCE: &str = "\
+class AiliHeap(gdb.Function):
+    def __init__(self):
+        super().__init__('_aili_heap')
+    def invoke(self):
+        mappings = gdb.execute('info proc mappings', to_string=True).splitlines()
+        heap = [line.split() for line in mappings if line.endswith('[heap]')]
+        if not heap:
+            return ''
+        start, end = int(heap[0][0], 16), int(heap[0][1], 16)
+        word = gdb.lookup_type('void').pointer().sizeof
+        order = 'little' if 'little' in gdb.execute('show endian', to_string=True) else 'big'
+        inferior = gdb.selected_inferior()
+        def read_word(address):
+            return int.from_bytes(inferior.read_memory(address, word).tobytes(), order)
+        def chunk_size(chunk):
+            return read_word(chunk + word)
+        def is_block(address):
+            return start < address < end and address % (2 * word) == 0
+        def free_list(block, link_offset, mangled):
+            blocks = set()
+            while block:
+                if not is_block(block) or block in blocks:
+                    return None
+                blocks.add(block)
+                link = read_word(block)
+                if mangled:
+                    link ^= block >> 12
+                block = link + link_offset if link else 0
+            return blocks
+        freed = set()
+        def follow_free_list(block, link_offset):
+            # Links are mangled with their own address since glibc 2.32,
+            # lists read the wrong way run off the heap before they end
+            for mangled in (True, False):
+                blocks = free_list(block, link_offset, mangled)
+                if blocks is not None:
+                    freed.update(blocks)
+                    return
+        # The tcache is allocated first, its size depends on the width of its counters
+        tcache_chunk = None
+        first_size = chunk_size(start) & ~7
+        for counters in (128, 64):
+            struct_size = 2 * word + counters + 64 * word
+            if first_size in [-(-struct_size // align) * align for align in (2 * word, 16)]:
+                tcache_chunk = start
+                for i in range(64):
+                    follow_free_list(read_word(start + 2 * word + counters + i * word), 0)
+                break
+        try:
+            fastbins = gdb.parse_and_eval('main_arena')['fastbinsY']
+            for i in range(fastbins.type.range()[1] + 1):
+                chunk = int(fastbins[i])
+                if chunk:
+                    follow_free_list(chunk + 2 * word, 2 * word)
+        except gdb.error:
+            pass
+        blocks = []
+        chunk = start
+        while chunk + 2 * word <= end:
+            size = chunk_size(chunk) & ~7
+            following = chunk + size
+            if size == 0 or following + 2 * word > end:
+                break
+            block = chunk + 2 * word
+            in_use = chunk_size(following) & 1
+            if in_use and chunk != tcache_chunk and block not in freed:
+                blocks.append('%d:%d' % (block, size - word))
+            chunk = following
+        return ' '.join(blocks)
+AiliHeap()
+";
+
+/// Block of memory allocated on the heap.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct HeapBlock {
+    /// Address of the first usable byte of the block.
+    pub address: u64,
+
+    /// Number of usable bytes in the block.
+    pub size: u64,
+}
+
+/// Parses a description of heap blocks returned
+/// by the `$_aili_heap` convenience function.
+///
+/// Malformed descriptions of blocks are skipped.
+pub(crate) fn parse_heap_blocks(description: &str) -> Vec<HeapBlock> {
+    description
+        .split_whitespace()
+        .filter_map(|block| {
+            let (address, size) = block.split_once(':')?;
+            Some(HeapBlock {
+                address: address.parse().ok()?,
+                size: size.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Selects the blocks that none of the provided pointers point into.
+pub(crate) fn unreachable_blocks(
+    blocks: impl IntoIterator<Item = HeapBlock>,
+    pointers: impl IntoIterator<Item = u64>,
+) -> Vec<HeapBlock> {
+    let pointers = BTreeSet::from_iter(pointers);
+    blocks
+        .into_iter()
+        .filter(|block| {
+            let end = block.address.saturating_add(block.size);
+            pointers.range(block.address..end).next().is_none()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_blocks() {
+        assert_eq!(
+            parse_heap_blocks("4210704:24 4210736:1032"),
+            vec![
+                HeapBlock {
+                    address: 4210704,
+                    size: 24,
+                },
+                HeapBlock {
+                    address: 4210736,
+                    size: 1032,
+                },
+            ]
+        );
+        assert_eq!(parse_heap_blocks(""), vec![]);
+        assert_eq!(
+            parse_heap_blocks("42 x:1 16:8"),
+            vec![HeapBlock {
+                address: 16,
+                size: 8,
+            }]
+        );
+    }
+
+    #[test]
+    fn blocks_with_pointers_into_them_are_reachable() {
+        let blocks = [
+            HeapBlock {
+                address: 16,
+                size: 8,
+            },
+            HeapBlock {
+                address: 32,
+                size: 8,
+            },
+            HeapBlock {
+                address: 48,
+                size: 8,
+            },
+        ];
+        // A pointer just past the end of a block does not point into it
+        assert_eq!(
+            unreachable_blocks(blocks, [16, 40, 52]),
+            vec![HeapBlock {
+                address: 32,
+                size: 8,
+            }]
+        );
+    }
+}
@@ -1,15 +1,47 @@
 //! User-provided hints to help deduce whether each pointer
-//! points to an array or a single object.
+//! points to an array or a single object,
+//! and how opaque buffers and tagged unions should be read.
 
 use aili_style::stylesheet::RawPropertyKey;
 use derive_more::{Debug, Display, Error};
 
-/// [`aili_style::stylesheet::PropertyKey`] to a length hint sheet.
+/// [`aili_style::stylesheet::PropertyKey`] to a hint sheet.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub enum PointerLengthHintKey {
     /// Indicates the length of an array pointed to by a pointer.
     #[debug("length")]
     Length,
+
+    /// Indicates the type of the object pointed to by a pointer,
+    /// such as `int[4]` for an `unsigned char *` that points to a buffer
+    /// of raw bytes.
+    ///
+    /// The value must be a string that names a type.
+    /// If a [length](PointerLengthHintKey::Length) is also provided,
+    /// the pointer points to an array of objects of this type.
+    #[debug("treat-as")]
+    TreatAs,
+
+    /// Selects the only member of a union that is included in the graph,
+    /// such as the member that the tag of a tagged union says is active.
+    ///
+    /// The value must be a string that names the member.
+    /// All members are included if the value is unset.
+    /// Unions can only be told apart from structures if GDB is built
+    /// with Python, so the hint is accepted on structures as well.
+    #[debug("union-variant")]
+    UnionVariant,
+
+    /// Selects the only member of each union in a tagged union structure
+    /// that is included in the graph.
+    ///
+    /// Unlike [`UnionVariant`](PointerLengthHintKey::UnionVariant),
+    /// the hint is assigned to the structure that holds both the tag
+    /// and the union, so the tag can be selected directly,
+    /// as in `variant: @("tag") == 1 ? "as_int" : "as_ptr"`.
+    /// The value must be a string that names the member.
+    #[debug("variant")]
+    Variant,
 }
 
 /// Error type emited when an unrecognized key is passed
@@ -23,14 +55,14 @@ pub enum BadHintKey {
 
     /// Quoted key was passed.
     ///
-    /// Quoted keys cannot be used in length hint sheets.
+    /// Quoted keys cannot be used in hint sheets.
     #[display("quoted keys are not allowed: {_0:?}")]
     #[error(ignore)]
     Quoted(String),
 
     /// Fragment key was passed.
     ///
-    /// Fragment keys cannot be used in length hint sheets.
+    /// Fragment keys cannot be used in hint sheets.
     #[display("fragment keys are not allowed: {_0}/{_1:?}")]
     #[error(ignore)]
     Fragment(String, String),
@@ -42,6 +74,9 @@ impl TryFrom<RawPropertyKey> for PointerLengthHintKey {
         match value {
             RawPropertyKey::Property(p) => match p.as_str() {
                 "length" => Ok(Self::Length),
+                "treat-as" => Ok(Self::TreatAs),
+                "union-variant" => Ok(Self::UnionVariant),
+                "variant" => Ok(Self::Variant),
                 _ => Err(BadHintKey::InvalidKey(p)),
             },
             RawPropertyKey::QuotedProperty(p) => Err(BadHintKey::Quoted(p)),
@@ -1,6 +1,13 @@
 #![doc = include_str!("../README.md")]
 
+pub mod adapters;
+mod arena;
+pub mod cancel;
 mod construct;
 pub mod gdbmi;
+mod heap;
 pub mod hints;
+pub mod options;
+pub mod snapshot;
 pub mod state;
+pub mod warning;
@@ -0,0 +1,369 @@
+//! Options that control which parts of the program state
+//! are included in a [`GdbStateGraph`](crate::state::GdbStateGraph).
+
+use crate::adapters::{ContainerAdapter, ContainerShape};
+
+/// Options for constructing a [`GdbStateGraph`](crate::state::GdbStateGraph).
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct GraphOptions {
+    /// Filter of global variables that should be included in the graph.
+    pub(crate) globals: Option<GlobFilter>,
+
+    /// Maximum length of strings decoded from character pointers
+    /// and arrays, or [`None`] if they should not be decoded.
+    pub(crate) strings: Option<usize>,
+
+    /// Adapters that present library containers by their contents,
+    /// in order of precedence.
+    pub(crate) container_adapters: Vec<ContainerAdapter>,
+
+    /// True if heap blocks that are not reachable
+    /// from any visible pointer should be listed.
+    pub(crate) heap_blocks: bool,
+
+    /// Limits on the size of the graph.
+    pub(crate) limits: GraphLimits,
+
+    /// True if the stacks of all threads should be included,
+    /// not only the stack of the selected thread.
+    pub(crate) threads: bool,
+
+    /// Filter of CPU registers that should be included in the graph.
+    pub(crate) registers: Option<GlobFilter>,
+
+    /// Expressions whose values should be included in the graph.
+    pub(crate) watch_expressions: Vec<String>,
+
+    /// True if identical read-only objects should share a node.
+    pub(crate) shared_subtrees: bool,
+}
+
+impl GraphOptions {
+    /// Constructs the default options.
+    ///
+    /// By default, only the stack trace and variables
+    /// reachable from it are included in the graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Includes global and static variables whose names
+    /// match a filter in the graph.
+    ///
+    /// Included variables are attached to the root node
+    /// by [named edges](aili_model::state::EdgeLabel::Named).
+    pub fn with_globals(mut self, filter: GlobFilter) -> Self {
+        self.globals = Some(filter);
+        self
+    }
+
+    /// Decodes C strings that character pointers and character arrays
+    /// hold and stores them as [`NodeValue::String`](aili_model::state::NodeValue::String)
+    /// values of the respective nodes.
+    ///
+    /// Nodes of type `char *` and `char [N]` become atoms whose value
+    /// is the string, instead of references to characters or arrays
+    /// of characters. Strings end at the first null character,
+    /// and at most `max_length` characters are decoded.
+    ///
+    /// A `max_length` of zero disables decoding, since GDB
+    /// would read it as no limit at all.
+    pub fn with_strings(mut self, max_length: usize) -> Self {
+        self.strings = (max_length > 0).then_some(max_length);
+        self
+    }
+
+    /// Presents containers recognized by an adapter by their contents,
+    /// rather than by their internal structure.
+    ///
+    /// If multiple adapters recognize the same type,
+    /// the one that has been added first is used.
+    pub fn with_container_adapter(mut self, adapter: ContainerAdapter) -> Self {
+        self.container_adapters.push(adapter);
+        self
+    }
+
+    /// Presents the containers of the C++ standard library
+    /// by their contents.
+    ///
+    /// See [`ContainerAdapter::standard_library`].
+    pub fn with_standard_container_adapters(mut self) -> Self {
+        self.container_adapters
+            .extend(ContainerAdapter::standard_library());
+        self
+    }
+
+    /// Lists blocks allocated on the heap that no pointer
+    /// in the graph points into, such as leaked memory.
+    ///
+    /// The blocks are attached to the root node by a
+    /// [custom edge](aili_model::state::EdgeLabel::Custom)
+    /// named [`HEAP_EDGE_LABEL`](crate::state::HEAP_EDGE_LABEL).
+    /// Heap blocks can only be listed in programs that use
+    /// the glibc allocator, and GDB must be built with Python.
+    pub fn with_heap_blocks(mut self) -> Self {
+        self.heap_blocks = true;
+        self
+    }
+
+    /// Includes the stacks of all threads of the debuggee in the graph.
+    ///
+    /// Threads are listed, in the order of their numbers in GDB,
+    /// by an array that is attached to the root node by a
+    /// [custom edge](aili_model::state::EdgeLabel::Custom)
+    /// named [`THREADS_EDGE_LABEL`](crate::state::THREADS_EDGE_LABEL).
+    /// Each item of the array is the bottom-most frame of a thread.
+    /// The [`Main`](aili_model::state::EdgeLabel::Main) edge still leads
+    /// to the stack of the thread that is selected in GDB.
+    pub fn with_threads(mut self) -> Self {
+        self.threads = true;
+        self
+    }
+
+    /// Includes the values of CPU registers whose names
+    /// match a filter in the graph.
+    ///
+    /// Registers are members of a structure that is attached
+    /// to the top-most frame of the selected thread by a
+    /// [custom edge](aili_model::state::EdgeLabel::Custom)
+    /// named [`REGISTERS_EDGE_LABEL`](crate::state::REGISTERS_EDGE_LABEL).
+    /// Registers whose values are not numbers, such as vector registers,
+    /// are left out.
+    pub fn with_registers(mut self, filter: GlobFilter) -> Self {
+        self.registers = Some(filter);
+        self
+    }
+
+    /// Includes the value of an expression, such as `head->next->value`
+    /// or `sizeof(buf)`, in the graph.
+    ///
+    /// Expressions are evaluated by GDB in the top-most frame
+    /// of the selected thread whenever the graph is updated.
+    /// Their values are members of a structure that is attached
+    /// to the root node by a
+    /// [custom edge](aili_model::state::EdgeLabel::Custom)
+    /// named [`WATCH_EDGE_LABEL`](crate::state::WATCH_EDGE_LABEL).
+    /// Each member is named after its expression.
+    /// Expressions that cannot be evaluated, such as those that refer
+    /// to variables that are not in scope, and expressions whose values
+    /// are not numbers, such as structures, are left out.
+    ///
+    /// Expressions can also be added to and removed from an existing graph
+    /// with [`GdbStateGraph::add_watch_expression`](crate::state::GdbStateGraph::add_watch_expression)
+    /// and [`GdbStateGraph::remove_watch_expression`](crate::state::GdbStateGraph::remove_watch_expression).
+    pub fn with_watch_expression(mut self, expression: impl Into<String>) -> Self {
+        self.watch_expressions.push(expression.into());
+        self
+    }
+
+    /// Merges identical read-only objects that pointers point to
+    /// into a single node, so that repeated constant data, such as
+    /// copies of the same string literal or lookup table,
+    /// is only included in the graph once.
+    ///
+    /// An object is considered read-only if all pointers that point to it
+    /// are pointers to `const`. Objects are identical if they have
+    /// the same types and values, and so do all their members and items.
+    /// Pointers to the merged objects are redirected to the one
+    /// with the lowest address, whose node is then
+    /// [shared](aili_model::state::ProgramStateNode::is_shared).
+    pub fn with_shared_subtrees(mut self) -> Self {
+        self.shared_subtrees = true;
+        self
+    }
+
+    /// Limits how much of the program state is included in the graph,
+    /// so that large data structures do not take too long to read.
+    pub fn with_limits(mut self, limits: GraphLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Finds the shape that an adapter assigns to a type, if any.
+    pub(crate) fn container_shape(&self, type_name: &str) -> Option<ContainerShape> {
+        self.container_adapters
+            .iter()
+            .find(|adapter| adapter.matches(type_name))
+            .map(ContainerAdapter::shape)
+    }
+}
+
+/// Limits on the size of a [`GdbStateGraph`](crate::state::GdbStateGraph).
+///
+/// Nodes that have successors missing because of a limit
+/// are marked by a [custom edge](aili_model::state::EdgeLabel::Custom)
+/// named [`TRUNCATED_EDGE_LABEL`](crate::state::TRUNCATED_EDGE_LABEL),
+/// so stylesheets can indicate that the graph is incomplete.
+///
+/// By default, the graph is not limited.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct GraphLimits {
+    /// Maximum number of pointers that may be followed
+    /// from a variable to reach a node.
+    pub(crate) max_deref_depth: Option<usize>,
+
+    /// Number of variable nodes after which pointers are no longer followed.
+    pub(crate) max_nodes: Option<usize>,
+
+    /// Maximum number of items of an array that are included.
+    pub(crate) max_array_elements: Option<usize>,
+}
+
+impl GraphLimits {
+    /// Constructs limits that do not limit anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of pointers that may be followed from a variable.
+    ///
+    /// Objects that are only reachable through a longer chain
+    /// of pointers, such as distant items of linked lists,
+    /// are not included in the graph.
+    pub fn with_max_deref_depth(mut self, max_deref_depth: usize) -> Self {
+        self.max_deref_depth = Some(max_deref_depth);
+        self
+    }
+
+    /// Stops following pointers once the graph has
+    /// a given number of nodes that represent objects.
+    ///
+    /// Variables that are in scope and their members are always included,
+    /// so the graph may still end up with more nodes than the limit.
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Limits the number of items of an array that are included.
+    ///
+    /// Only the first items of longer arrays are included.
+    /// The [length](aili_model::state::EdgeLabel::Length)
+    /// of the array is still reported in full.
+    pub fn with_max_array_elements(mut self, max_array_elements: usize) -> Self {
+        self.max_array_elements = Some(max_array_elements);
+        self
+    }
+}
+
+/// Filter that matches names against a set of glob patterns.
+///
+/// A pattern may contain wildcards `*`, which matches any sequence
+/// of characters, and `?`, which matches any single character.
+/// All other characters only match themselves.
+/// A name passes the filter if it matches any of the patterns.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct GlobFilter {
+    patterns: Vec<String>,
+}
+
+impl GlobFilter {
+    /// Constructs a filter that matches any of the provided patterns.
+    pub fn new(patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            patterns: patterns.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Constructs a filter that matches all names.
+    pub fn all() -> Self {
+        Self::new(["*"])
+    }
+
+    /// Checks whether a name passes the filter.
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.chars().collect::<Vec<_>>();
+        self.patterns.iter().any(|pattern| {
+            let pattern = pattern.chars().collect::<Vec<_>>();
+            glob_matches(&pattern, &name)
+        })
+    }
+}
+
+/// Matches a name against a single glob pattern.
+fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+    // Position in the pattern just after the last star,
+    // and the position in the name where the star's match ends
+    let mut backtrack = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, n));
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+            }
+            Some(c) if *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => {
+                // Let the last star match one more character
+                let Some((star_p, star_n)) = backtrack else {
+                    return false;
+                };
+                p = star_p;
+                n = star_n + 1;
+                backtrack = Some((star_p, n));
+            }
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zero_string_length_disables_strings() {
+        assert_eq!(GraphOptions::new().with_strings(0).strings, None);
+        assert_eq!(GraphOptions::new().with_strings(16).strings, Some(16));
+    }
+
+    #[test]
+    fn exact_match() {
+        let filter = GlobFilter::new(["counter"]);
+        assert!(filter.matches("counter"));
+        assert!(!filter.matches("counters"));
+        assert!(!filter.matches("count"));
+    }
+
+    #[test]
+    fn star_wildcard() {
+        let filter = GlobFilter::new(["g_*"]);
+        assert!(filter.matches("g_"));
+        assert!(filter.matches("g_list"));
+        assert!(!filter.matches("list"));
+        let filter = GlobFilter::new(["*_count*"]);
+        assert!(filter.matches("node_count"));
+        assert!(filter.matches("edge_count_max"));
+        assert!(!filter.matches("counter"));
+    }
+
+    #[test]
+    fn question_mark_wildcard() {
+        let filter = GlobFilter::new(["x?"]);
+        assert!(filter.matches("x1"));
+        assert!(!filter.matches("x"));
+        assert!(!filter.matches("x12"));
+    }
+
+    #[test]
+    fn multiple_patterns() {
+        let filter = GlobFilter::new(["head", "tail"]);
+        assert!(filter.matches("head"));
+        assert!(filter.matches("tail"));
+        assert!(!filter.matches("body"));
+    }
+
+    #[test]
+    fn match_all() {
+        assert!(GlobFilter::all().matches(""));
+        assert!(GlobFilter::all().matches("anything"));
+        assert!(!GlobFilter::default().matches("anything"));
+    }
+}
@@ -0,0 +1,241 @@
+//! Immutable copies of a [`GdbStateGraph`].
+
+use crate::{
+    arena::VariableIndex,
+    gdbmi::types::VariableObject,
+    state::{GdbStateGraph, GdbStateNode, GdbStateNodeId, GdbStateNodeRef, NodeKey},
+};
+use aili_model::{capability::CapabilitySet, state::*};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Immutable copy of a [`GdbStateGraph`] at one point in time.
+///
+/// The graph is updated in place as the debuggee runs,
+/// so anything that needs to look at its past states, such as
+/// a timeline of a debugging session or multiple views of different
+/// steps, keeps snapshots instead. Snapshots share nodes
+/// that have not changed between them, so keeping many of them
+/// costs little more than the nodes that have changed.
+///
+/// Only the nodes that are reachable from the root are copied.
+/// Snapshots are cheap to clone and can be sent between threads.
+#[derive(Clone, Debug)]
+pub struct GdbStateGraphSnapshot {
+    nodes: HashMap<GdbStateNodeId, Arc<GdbStateNode>>,
+    variable_names: Arc<HashMap<VariableIndex, VariableObject>>,
+    address_mapping: Arc<BTreeMap<u64, VariableObject>>,
+    capabilities: CapabilitySet,
+}
+
+impl GdbStateGraph {
+    /// Copies the current state of the graph.
+    pub fn snapshot(&self) -> GdbStateGraphSnapshot {
+        self.snapshot_sharing_with(None)
+    }
+
+    /// Copies the current state of the graph,
+    /// sharing the nodes that have not changed
+    /// since a previous snapshot was taken.
+    ///
+    /// The previous snapshot should have been taken from the same graph,
+    /// usually before its last update. Nodes of other graphs
+    /// are only shared if they happen to be identical.
+    pub fn snapshot_sharing_with(
+        &self,
+        previous: Option<&GdbStateGraphSnapshot>,
+    ) -> GdbStateGraphSnapshot {
+        let mut nodes = HashMap::new();
+        // Nodes refer to variable nodes by their indices in the arena,
+        // so the snapshot remembers which variable objects they stand for
+        let mut variable_names = HashMap::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![NodeKey::Root];
+        while let Some(key) = pending.pop() {
+            if !visited.insert(key) {
+                continue;
+            }
+            let (Some(node), Some(id)) = (self.node(key), self.id_of(key)) else {
+                continue;
+            };
+            if let Some(index) = key.variable_index()
+                && let Some(var_object) = self.variables.object_at(index)
+            {
+                variable_names.insert(index, var_object.clone());
+            }
+            pending.extend(node.successors.iter().map(|(_, successor)| *successor));
+            let node = previous
+                .and_then(|previous| previous.nodes.get(&id))
+                .filter(|previous_node| ***previous_node == *node)
+                .cloned()
+                .unwrap_or_else(|| Arc::new(node.clone()));
+            nodes.insert(id, node);
+        }
+        let variable_names = previous
+            .map(|previous| &previous.variable_names)
+            .filter(|previous_names| ***previous_names == variable_names)
+            .cloned()
+            .unwrap_or_else(|| Arc::new(variable_names));
+        let address_mapping = previous
+            .map(|previous| &previous.address_mapping)
+            .filter(|previous_mapping| ***previous_mapping == self.address_mapping)
+            .cloned()
+            .unwrap_or_else(|| Arc::new(self.address_mapping.clone()));
+        GdbStateGraphSnapshot {
+            nodes,
+            variable_names,
+            address_mapping,
+            capabilities: self.capabilities(),
+        }
+    }
+}
+
+impl GdbStateGraphSnapshot {
+    /// Finds the node that represents the object
+    /// that starts at a given address in the debuggee's memory.
+    ///
+    /// See [`GdbStateGraph::node_at_address`].
+    pub fn node_at_address(&self, address: u64) -> Option<GdbStateNodeId> {
+        self.address_mapping
+            .get(&address)
+            .map(|var_object| GdbStateNodeId::VarObject(var_object.clone()))
+    }
+
+    /// Gets the number of nodes in the snapshot.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Checks whether a node is shared with another snapshot,
+    /// which means that it has not changed between them.
+    pub fn shares_node_with(&self, other: &Self, id: &GdbStateNodeId) -> bool {
+        match (self.nodes.get(id), other.nodes.get(id)) {
+            (Some(node), Some(other_node)) => Arc::ptr_eq(node, other_node),
+            _ => false,
+        }
+    }
+}
+
+impl ProgramStateGraph for GdbStateGraphSnapshot {
+    type NodeId = GdbStateNodeId;
+    type NodeRef<'a>
+        = GdbStateNodeRef<'a>
+    where
+        Self: 'a;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        let node = self.nodes.get(id)?;
+        Some(GdbStateNodeRef::new(node, &*self.variable_names))
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        self.capabilities.clone()
+    }
+}
+
+impl RootedProgramStateGraph for GdbStateGraphSnapshot {
+    fn root(&self) -> Self::NodeId {
+        GdbStateNodeId::Root
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::GdbStateNodeForVariable;
+
+    fn frame(value: i64) -> GdbStateNode {
+        GdbStateNode {
+            type_class: NodeTypeClass::Frame,
+            type_name: Some("main".to_owned()),
+            successors: Vec::new(),
+            value: Some(NodeValue::Int(value)),
+            source_location: None,
+            shared: false,
+        }
+    }
+
+    fn graph_with_frames(values: &[i64]) -> GdbStateGraph {
+        let mut graph = GdbStateGraph::empty();
+        graph.stack_trace = values.iter().copied().map(frame).collect();
+        if !values.is_empty() {
+            graph
+                .root_node
+                .successors
+                .push((EdgeLabel::Main, NodeKey::Frame(0)));
+        }
+        for index in 1..values.len() {
+            graph.stack_trace[index - 1]
+                .successors
+                .push((EdgeLabel::Next, NodeKey::Frame(index)));
+        }
+        graph
+    }
+
+    #[test]
+    fn snapshot_is_a_copy() {
+        let mut graph = graph_with_frames(&[1, 2]);
+        let snapshot = graph.snapshot();
+        graph.stack_trace[1].value = Some(NodeValue::Int(3));
+        assert_eq!(snapshot.node_count(), 3);
+        let frame = snapshot.get(&GdbStateNodeId::Frame(1)).unwrap();
+        assert_eq!(frame.value(), Some(NodeValue::Int(2)));
+        assert_eq!(
+            snapshot
+                .get(&snapshot.root())
+                .unwrap()
+                .get_successor(&EdgeLabel::Main),
+            Some(GdbStateNodeId::Frame(0))
+        );
+    }
+
+    #[test]
+    fn unchanged_nodes_are_shared() {
+        let mut graph = graph_with_frames(&[1, 2]);
+        let first = graph.snapshot();
+        graph.stack_trace[1].value = Some(NodeValue::Int(3));
+        let second = graph.snapshot_sharing_with(Some(&first));
+        assert!(second.shares_node_with(&first, &GdbStateNodeId::Root));
+        assert!(second.shares_node_with(&first, &GdbStateNodeId::Frame(0)));
+        assert!(!second.shares_node_with(&first, &GdbStateNodeId::Frame(1)));
+        assert!(
+            !graph
+                .snapshot()
+                .shares_node_with(&first, &GdbStateNodeId::Root)
+        );
+    }
+
+    #[test]
+    fn unreachable_nodes_are_left_out() {
+        let mut graph = graph_with_frames(&[1, 2]);
+        graph.stack_trace[0].successors.clear();
+        let snapshot = graph.snapshot();
+        assert_eq!(snapshot.node_count(), 2);
+        assert!(snapshot.get(&GdbStateNodeId::Frame(1)).is_none());
+    }
+
+    #[test]
+    fn variable_successors_outlive_their_nodes_in_the_graph() {
+        let mut graph = graph_with_frames(&[1]);
+        let var_object = VariableObject("var1".to_owned());
+        let mut variable = GdbStateNode::new(NodeTypeClass::Atom);
+        variable.value = Some(NodeValue::Int(2));
+        graph.variables.insert(
+            var_object.clone(),
+            GdbStateNodeForVariable::new(variable, Some(GdbStateNodeId::Frame(0))),
+        );
+        let index = graph.variables.index_of(&var_object).unwrap();
+        graph.stack_trace[0]
+            .successors
+            .push((EdgeLabel::Named("x".into(), 0), NodeKey::VarObject(index)));
+        let snapshot = graph.snapshot();
+        graph.variables.remove(&var_object);
+        let variable_id = snapshot
+            .get(&GdbStateNodeId::Frame(0))
+            .unwrap()
+            .get_successor(&EdgeLabel::Named("x".into(), 0));
+        assert_eq!(variable_id, Some(GdbStateNodeId::VarObject(var_object)));
+        let variable = snapshot.get(&variable_id.unwrap()).unwrap();
+        assert_eq!(variable.value(), Some(NodeValue::Int(2)));
+    }
+}
@@ -1,10 +1,19 @@
 //! Implementation of [`ProgramStateGraph`] backed by a GDB session.
 
-use crate::gdbmi::types::VariableObject;
-use aili_model::state::*;
+use crate::{
+    adapters::ContainerShape,
+    arena::{VariableArena, VariableIndex, VariableNames},
+    gdbmi::types::{StopReason, VariableObject},
+    options::GraphOptions,
+    warning::{GraphWarning, WarningHandler},
+};
+use aili_model::{
+    capability::{self, CapabilitySet},
+    state::*,
+};
 use aili_style::values::PropertyValue;
 use derive_more::{Debug, Deref, DerefMut};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// Identifiers of state nodes used by [`GdbStateGraph`].
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
@@ -17,6 +26,50 @@ pub enum GdbStateNodeId {
     #[debug("frame({_0})")]
     Frame(usize),
 
+    /// Identifier of a stack frame node of a specific thread.
+    ///
+    /// Used instead of [`GdbStateNodeId::Frame`]
+    /// if the graph includes all threads.
+    /// The first number identifies the thread in GDB,
+    /// the second one is the index of the frame, like in [`GdbStateNodeId::Frame`].
+    #[debug("thread({_0}) frame({_1})")]
+    ThreadFrame(usize, usize),
+
+    /// Identifier of the node that lists the stacks of all threads.
+    #[debug("threads")]
+    Threads,
+
+    /// Identifier of the [`EdgeLabel::Length`] pseudo-node
+    /// associated with the [`GdbStateNodeId::Threads`] node.
+    #[debug("threads len")]
+    ThreadsLength,
+
+    /// Identifier of the node that holds the values of CPU registers.
+    #[debug("registers")]
+    Registers,
+
+    /// Identifier of a node that holds the value of a CPU register.
+    ///
+    /// The number identifies the register in GDB.
+    #[debug("register({_0})")]
+    Register(usize),
+
+    /// Identifier of the node that holds the values of watch expressions.
+    #[debug("watch")]
+    WatchExpressions,
+
+    /// Identifier of a node that holds the value of a watch expression.
+    ///
+    /// The number is the index of the expression
+    /// in [`GdbStateGraph::watch_expressions`].
+    #[debug("watch({_0})")]
+    WatchExpression(usize),
+
+    /// Identifier of the node that holds the value
+    /// that a function has returned to its caller.
+    #[debug("ret")]
+    FunctionResult,
+
     /// Identifier of a node backed by a
     /// [GDB/MI variable object](https://sourceware.org/gdb/current/onlinedocs/gdb.html/GDB_002fMI-Variable-Objects.html).
     #[debug("var({:?})", _0.0)]
@@ -26,32 +79,264 @@ pub enum GdbStateNodeId {
     /// associated with a [`GdbStateNodeId::VarObject`] node.
     #[debug("var({:?}) len", _0.0)]
     Length(VariableObject),
+
+    /// Identifier of the `bit-offset` pseudo-node
+    /// associated with a [`GdbStateNodeId::VarObject`] node
+    /// that represents a bitfield.
+    #[debug("var({:?}) bit-offset", _0.0)]
+    BitOffset(VariableObject),
+
+    /// Identifier of the `bit-width` pseudo-node
+    /// associated with a [`GdbStateNodeId::VarObject`] node
+    /// that represents a bitfield.
+    #[debug("var({:?}) bit-width", _0.0)]
+    BitWidth(VariableObject),
+
+    /// Identifier of the node that lists heap blocks
+    /// that are not reachable from any visible pointer.
+    #[debug("heap")]
+    Heap,
+
+    /// Identifier of the [`EdgeLabel::Length`] pseudo-node
+    /// associated with the [`GdbStateNodeId::Heap`] node.
+    #[debug("heap len")]
+    HeapLength,
+
+    /// Identifier of a node that represents a heap block
+    /// listed by the [`GdbStateNodeId::Heap`] node.
+    #[debug("heap[{_0}]")]
+    HeapBlock(usize),
+
+    /// Identifier of the pseudo-node that holds the address
+    /// of a [`GdbStateNodeId::HeapBlock`] node.
+    #[debug("heap[{_0}] address")]
+    HeapBlockAddress(usize),
+
+    /// Identifier of the pseudo-node that holds the size
+    /// of a [`GdbStateNodeId::HeapBlock`] node.
+    #[debug("heap[{_0}] size")]
+    HeapBlockSize(usize),
+
+    /// Identifier of the `truncated` pseudo-node
+    /// associated with a [`GdbStateNodeId::VarObject`] node
+    /// whose successors have been omitted due to a limit.
+    #[debug("var({:?}) truncated", _0.0)]
+    Truncated(VariableObject),
 }
 
+/// Reference from a node of a [`GdbStateGraph`] to one of its successors.
+///
+/// Mirrors [`GdbStateNodeId`], except that nodes of variable objects
+/// and their pseudo-nodes are referred to by the [`VariableIndex`]
+/// of the variable node, which is cheaper to copy, compare, and hash
+/// than the name of the variable object.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum NodeKey {
+    Root,
+    Frame(usize),
+    ThreadFrame(usize, usize),
+    Threads,
+    ThreadsLength,
+    Registers,
+    Register(usize),
+    WatchExpressions,
+    WatchExpression(usize),
+    FunctionResult,
+    VarObject(VariableIndex),
+    Length(VariableIndex),
+    BitOffset(VariableIndex),
+    BitWidth(VariableIndex),
+    Heap,
+    HeapLength,
+    HeapBlock(usize),
+    HeapBlockAddress(usize),
+    HeapBlockSize(usize),
+    Truncated(VariableIndex),
+}
+
+impl NodeKey {
+    /// Finds the key of the node that an identifier refers to.
+    ///
+    /// Returns [`None`] if the identifier refers
+    /// to a variable object that has no node.
+    pub fn from_id(id: &GdbStateNodeId, variables: &VariableArena) -> Option<Self> {
+        Some(match id {
+            GdbStateNodeId::Root => Self::Root,
+            GdbStateNodeId::Frame(i) => Self::Frame(*i),
+            GdbStateNodeId::ThreadFrame(t, i) => Self::ThreadFrame(*t, *i),
+            GdbStateNodeId::Threads => Self::Threads,
+            GdbStateNodeId::ThreadsLength => Self::ThreadsLength,
+            GdbStateNodeId::Registers => Self::Registers,
+            GdbStateNodeId::Register(n) => Self::Register(*n),
+            GdbStateNodeId::WatchExpressions => Self::WatchExpressions,
+            GdbStateNodeId::WatchExpression(i) => Self::WatchExpression(*i),
+            GdbStateNodeId::FunctionResult => Self::FunctionResult,
+            GdbStateNodeId::VarObject(v) => Self::VarObject(variables.index_of(v)?),
+            GdbStateNodeId::Length(v) => Self::Length(variables.index_of(v)?),
+            GdbStateNodeId::BitOffset(v) => Self::BitOffset(variables.index_of(v)?),
+            GdbStateNodeId::BitWidth(v) => Self::BitWidth(variables.index_of(v)?),
+            GdbStateNodeId::Heap => Self::Heap,
+            GdbStateNodeId::HeapLength => Self::HeapLength,
+            GdbStateNodeId::HeapBlock(i) => Self::HeapBlock(*i),
+            GdbStateNodeId::HeapBlockAddress(i) => Self::HeapBlockAddress(*i),
+            GdbStateNodeId::HeapBlockSize(i) => Self::HeapBlockSize(*i),
+            GdbStateNodeId::Truncated(v) => Self::Truncated(variables.index_of(v)?),
+        })
+    }
+
+    /// Gets the identifier of the node that the key refers to.
+    ///
+    /// Returns [`None`] if the key refers to a variable node
+    /// whose variable object is not known.
+    pub fn to_id(self, variables: &(impl VariableNames + ?Sized)) -> Option<GdbStateNodeId> {
+        let object = |index| variables.object_at(index).cloned();
+        Some(match self {
+            Self::Root => GdbStateNodeId::Root,
+            Self::Frame(i) => GdbStateNodeId::Frame(i),
+            Self::ThreadFrame(t, i) => GdbStateNodeId::ThreadFrame(t, i),
+            Self::Threads => GdbStateNodeId::Threads,
+            Self::ThreadsLength => GdbStateNodeId::ThreadsLength,
+            Self::Registers => GdbStateNodeId::Registers,
+            Self::Register(n) => GdbStateNodeId::Register(n),
+            Self::WatchExpressions => GdbStateNodeId::WatchExpressions,
+            Self::WatchExpression(i) => GdbStateNodeId::WatchExpression(i),
+            Self::FunctionResult => GdbStateNodeId::FunctionResult,
+            Self::VarObject(v) => GdbStateNodeId::VarObject(object(v)?),
+            Self::Length(v) => GdbStateNodeId::Length(object(v)?),
+            Self::BitOffset(v) => GdbStateNodeId::BitOffset(object(v)?),
+            Self::BitWidth(v) => GdbStateNodeId::BitWidth(object(v)?),
+            Self::Heap => GdbStateNodeId::Heap,
+            Self::HeapLength => GdbStateNodeId::HeapLength,
+            Self::HeapBlock(i) => GdbStateNodeId::HeapBlock(i),
+            Self::HeapBlockAddress(i) => GdbStateNodeId::HeapBlockAddress(i),
+            Self::HeapBlockSize(i) => GdbStateNodeId::HeapBlockSize(i),
+            Self::Truncated(v) => GdbStateNodeId::Truncated(object(v)?),
+        })
+    }
+
+    /// Gets the index of the variable node that the key refers to,
+    /// or that the pseudo-node it refers to belongs to.
+    pub fn variable_index(self) -> Option<VariableIndex> {
+        match self {
+            Self::VarObject(v)
+            | Self::Length(v)
+            | Self::BitOffset(v)
+            | Self::BitWidth(v)
+            | Self::Truncated(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Name of the [`EdgeLabel::Custom`] edge that leads from a bitfield
+/// to a numeric node that holds the offset of the bitfield,
+/// in bits, from the start of the structure that contains it.
+pub const BIT_OFFSET_EDGE_LABEL: &str = "bit-offset";
+
+/// Name of the [`EdgeLabel::Custom`] edge that leads from a bitfield
+/// to a numeric node that holds the width of the bitfield in bits.
+pub const BIT_WIDTH_EDGE_LABEL: &str = "bit-width";
+
+/// Name of the [`EdgeLabel::Custom`] edge that leads from a node
+/// to a [`NodeValue::Bool`] node if some successors of the node
+/// have been omitted due to [`GraphLimits`](crate::options::GraphLimits).
+///
+/// This includes arrays whose items have been omitted,
+/// and pointers that have not been followed.
+pub const TRUNCATED_EDGE_LABEL: &str = "truncated";
+
+/// Name of the [`EdgeLabel::Custom`] edge that leads from the root
+/// to an array of heap blocks that are not reachable from any visible pointer.
+///
+/// Each heap block is a structure with members named `address` and `size`,
+/// which hold the address of the block and the number of bytes in it.
+pub const HEAP_EDGE_LABEL: &str = "heap";
+
+/// Name of the [`EdgeLabel::Custom`] edge that leads from the root
+/// to an array that lists the stacks of all threads.
+///
+/// Each item of the array is the bottom-most frame of one thread.
+pub const THREADS_EDGE_LABEL: &str = "threads";
+
+/// Name of the [`EdgeLabel::Custom`] edge that leads from the top-most
+/// frame of the selected thread to a structure that holds the values
+/// of CPU registers.
+///
+/// Members of the structure are named after the registers.
+pub const REGISTERS_EDGE_LABEL: &str = "registers";
+
+/// Name of the [`EdgeLabel::Custom`] edge that leads from the root
+/// to a structure that holds the values of watch expressions.
+///
+/// Members of the structure are named after the expressions.
+pub const WATCH_EDGE_LABEL: &str = "watch";
+
 /// Implementation of a [`ProgramStateGraph`] backed by a GDB session.
 #[derive(Debug)]
 pub struct GdbStateGraph {
     pub(crate) root_node: GdbStateNode,
     pub(crate) stack_trace: Vec<GdbStateNode>,
-    pub(crate) variables: HashMap<VariableObject, GdbStateNodeForVariable>,
-    pub(crate) length_nodes: HashMap<VariableObject, GdbStateNode>,
+    pub(crate) variables: VariableArena,
+    pub(crate) length_nodes: HashMap<VariableIndex, GdbStateNode>,
+    pub(crate) bitfield_nodes: HashMap<VariableIndex, BitfieldNodes>,
+    pub(crate) truncation_markers: HashMap<VariableIndex, GdbStateNode>,
+    pub(crate) type_layouts: HashMap<String, TypeLayout>,
+    pub(crate) layout_queries_enabled: bool,
+    pub(crate) heap: Option<HeapNodes>,
+    pub(crate) heap_queries_enabled: bool,
+    pub(crate) threads: Option<ThreadNodes>,
+    pub(crate) registers: Option<RegisterNodes>,
+    pub(crate) register_names: Vec<(usize, String)>,
+    pub(crate) watch_expression_nodes: Option<WatchExpressionNodes>,
+    pub(crate) function_result: Option<FunctionResultNode>,
+    pub(crate) pending_return_value: Option<String>,
     pub(crate) address_mapping: BTreeMap<u64, VariableObject>,
-    pub(crate) resolved_length_hints: HashMap<VariableObject, PropertyValue<GdbStateNodeId>>,
+    pub(crate) resolved_hints: HashMap<VariableObject, ResolvedHints>,
+    pub(crate) hidden_union_members: HashMap<VariableObject, Vec<(EdgeLabel, NodeKey)>>,
+    pub(crate) shared_subtrees: HashMap<VariableObject, VariableObject>,
+    pub(crate) watchpoints: Option<WatchpointState>,
+    pub(crate) options: GraphOptions,
+    pub(crate) needs_rebuild: bool,
+    #[debug(skip)]
+    pub(crate) warning_handler: Option<WarningHandler>,
 }
 
 impl ProgramStateGraph for GdbStateGraph {
     type NodeId = GdbStateNodeId;
     type NodeRef<'a>
-        = &'a GdbStateNode
+        = GdbStateNodeRef<'a>
     where
         Self: 'a;
     fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
-        match id {
-            GdbStateNodeId::Root => Some(&self.root_node),
-            GdbStateNodeId::Frame(i) => self.stack_trace.get(*i),
-            GdbStateNodeId::VarObject(v) => self.variables.get(v).map(|v| &v.node),
-            GdbStateNodeId::Length(v) => self.length_nodes.get(v),
+        let node = self.node(self.key_of(id)?)?;
+        Some(GdbStateNodeRef::new(node, &self.variables))
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        let mut capabilities = CapabilitySet::from_iter([
+            capability::GDB,
+            capability::ARRAYS,
+            capability::POINTERS,
+            capability::FLOAT_VALUES,
+        ]);
+        if self.options.strings.is_some() {
+            capabilities.insert(capability::STRING_VALUES);
+        }
+        if self.heap_queries_enabled {
+            capabilities.insert(capability::HEAP_BLOCKS);
+        }
+        if self.options.threads {
+            capabilities.insert(capability::THREADS);
+        }
+        if self.options.registers.is_some() {
+            capabilities.insert(capability::REGISTERS);
+        }
+        if !self.options.watch_expressions.is_empty() {
+            capabilities.insert(capability::WATCH_EXPRESSIONS);
         }
+        if self.options.shared_subtrees {
+            capabilities.insert(capability::SHARED_NODES);
+        }
+        capabilities
     }
 }
 
@@ -62,55 +347,314 @@ impl RootedProgramStateGraph for GdbStateGraph {
 }
 
 impl GdbStateGraph {
+    /// Finds the node that represents the object
+    /// that starts at a given address in the debuggee's memory.
+    ///
+    /// Only variables and objects that pointers point to
+    /// are known by their addresses. Members of structures
+    /// and items of arrays are not, so an address that points
+    /// into the middle of an object does not find anything.
+    pub fn node_at_address(&self, address: u64) -> Option<GdbStateNodeId> {
+        self.address_mapping
+            .get(&address)
+            .map(|var_object| GdbStateNodeId::VarObject(var_object.clone()))
+    }
+
+    /// Gets the address of the object that a node represents.
+    ///
+    /// This is the reverse of [`GdbStateGraph::node_at_address`],
+    /// so addresses are only known for the same nodes.
+    pub fn address_of(&self, id: &GdbStateNodeId) -> Option<u64> {
+        match id {
+            GdbStateNodeId::VarObject(var_object) => self.variables.get(var_object)?.address,
+            _ => None,
+        }
+    }
+
+    /// Counts the nodes and edges of the graph.
+    ///
+    /// Only the nodes that are reachable from the root are counted.
+    pub fn stats(&self) -> GraphStats {
+        let mut stats = GraphStats::default();
+        let mut visited = HashSet::new();
+        let mut pending = vec![NodeKey::Root];
+        while let Some(key) = pending.pop() {
+            if !visited.insert(key) {
+                continue;
+            }
+            let Some(node) = self.node(key) else {
+                continue;
+            };
+            *stats.nodes.entry(node.type_class).or_default() += 1;
+            *stats.edges.entry(node.type_class).or_default() += node.successors.len();
+            pending.extend(node.successors.iter().map(|(_, successor)| *successor));
+        }
+        stats.heap_bytes = self
+            .heap
+            .iter()
+            .flat_map(|heap| &heap.blocks)
+            .filter_map(|block| match block.size.value {
+                Some(NodeValue::Uint(size)) => Some(size),
+                _ => None,
+            })
+            .sum();
+        // Pointers are truncated when their targets exceed a limit
+        stats.deferred_dereferences = self
+            .truncation_markers
+            .keys()
+            .filter(|index| {
+                self.variables
+                    .get_at(**index)
+                    .is_some_and(|node| node.type_class == NodeTypeClass::Ref)
+            })
+            .count();
+        stats
+    }
+
+    /// Gets the expressions whose values are included in the graph.
+    ///
+    /// See [`GraphOptions::with_watch_expression`].
+    pub fn watch_expressions(&self) -> &[String] {
+        &self.options.watch_expressions
+    }
+
+    /// Includes the value of an expression in the graph.
+    ///
+    /// The value is read by the next [update](GdbStateGraph::update)
+    /// and on every update after it.
+    /// See [`GraphOptions::with_watch_expression`].
+    pub fn add_watch_expression(&mut self, expression: impl Into<String>) {
+        self.options.watch_expressions.push(expression.into());
+    }
+
+    /// Stops including the value of an expression in the graph.
+    ///
+    /// The value is removed by the next [update](GdbStateGraph::update).
+    /// Returns false if the expression was not watched.
+    pub fn remove_watch_expression(&mut self, expression: &str) -> bool {
+        let expressions = &mut self.options.watch_expressions;
+        let Some(index) = expressions.iter().position(|e| e == expression) else {
+            return false;
+        };
+        expressions.remove(index);
+        true
+    }
+
+    /// Informs the graph of why the debuggee has last stopped.
+    ///
+    /// If a function has just [finished](StopReason::FunctionFinished),
+    /// the next [update](GdbStateGraph::update) attaches the value it has returned
+    /// to the top-most frame of the selected thread, which is the caller,
+    /// by an [`EdgeLabel::Result`] edge. The value stays there
+    /// until the graph is updated again. Only values that are numbers
+    /// are included, so values of structures are left out.
+    pub fn report_stop(&mut self, reason: &StopReason) {
+        self.pending_return_value = match reason {
+            StopReason::FunctionFinished { return_value } => return_value.clone(),
+            _ => None,
+        };
+    }
+
+    /// Sets a callback that receives all [`GraphWarning`]s
+    /// emitted while the graph is constructed or updated.
+    ///
+    /// Warnings are also emitted through [`tracing`] regardless
+    /// of whether a handler is set.
+    pub fn set_warning_handler(&mut self, handler: impl Fn(&GraphWarning) + Send + Sync + 'static) {
+        self.warning_handler = Some(Box::new(handler));
+    }
+
+    /// Reports an inconsistency in the graph.
+    pub(crate) fn warn(&self, warning: GraphWarning) {
+        tracing::warn!("{warning}");
+        if let Some(handler) = &self.warning_handler {
+            handler(&warning);
+        }
+    }
+
     /// Get a mutable reference to a state node by its ID.
     pub(crate) fn get_mut(&mut self, id: &GdbStateNodeId) -> Option<&mut GdbStateNode> {
-        match id {
-            GdbStateNodeId::Root => Some(&mut self.root_node),
-            GdbStateNodeId::Frame(i) => self.stack_trace.get_mut(*i),
-            GdbStateNodeId::VarObject(v) => self.variables.get_mut(v).map(|v| &mut v.node),
-            GdbStateNodeId::Length(v) => self.length_nodes.get_mut(v),
+        self.node_mut(self.key_of(id)?)
+    }
+
+    /// Finds the key of the node that an identifier refers to.
+    pub(crate) fn key_of(&self, id: &GdbStateNodeId) -> Option<NodeKey> {
+        NodeKey::from_id(id, &self.variables)
+    }
+
+    /// Gets the identifier of the node that a key refers to.
+    pub(crate) fn id_of(&self, key: NodeKey) -> Option<GdbStateNodeId> {
+        key.to_id(&self.variables)
+    }
+
+    /// Get a reference to a state node by its key.
+    pub(crate) fn node(&self, key: NodeKey) -> Option<&GdbStateNode> {
+        match key {
+            NodeKey::Root => Some(&self.root_node),
+            NodeKey::Frame(i) => self.stack_trace.get(i),
+            NodeKey::ThreadFrame(t, i) => self.threads.as_ref()?.stacks.get(&t)?.get(i),
+            NodeKey::Threads => self.threads.as_ref().map(|t| &t.list),
+            NodeKey::ThreadsLength => self.threads.as_ref().map(|t| &t.length),
+            NodeKey::Registers => self.registers.as_ref().map(|r| &r.list),
+            NodeKey::Register(n) => self.registers.as_ref()?.values.get(&n),
+            NodeKey::WatchExpressions => self.watch_expression_nodes.as_ref().map(|w| &w.list),
+            NodeKey::WatchExpression(i) => self.watch_expression_nodes.as_ref()?.values.get(&i),
+            NodeKey::FunctionResult => self.function_result.as_ref().map(|r| &r.node),
+            NodeKey::VarObject(v) => self.variables.get_at(v).map(|v| &v.node),
+            NodeKey::Length(v) => self.length_nodes.get(&v),
+            NodeKey::BitOffset(v) => self.bitfield_nodes.get(&v).map(|b| &b.offset),
+            NodeKey::BitWidth(v) => self.bitfield_nodes.get(&v).map(|b| &b.width),
+            NodeKey::Heap => self.heap.as_ref().map(|h| &h.list),
+            NodeKey::HeapLength => self.heap.as_ref().map(|h| &h.length),
+            NodeKey::HeapBlock(i) => self.heap_block(i).map(|b| &b.block),
+            NodeKey::HeapBlockAddress(i) => self.heap_block(i).map(|b| &b.address),
+            NodeKey::HeapBlockSize(i) => self.heap_block(i).map(|b| &b.size),
+            NodeKey::Truncated(v) => self.truncation_markers.get(&v),
         }
     }
+
+    /// Get a mutable reference to a state node by its key.
+    pub(crate) fn node_mut(&mut self, key: NodeKey) -> Option<&mut GdbStateNode> {
+        match key {
+            NodeKey::Root => Some(&mut self.root_node),
+            NodeKey::Frame(i) => self.stack_trace.get_mut(i),
+            NodeKey::ThreadFrame(t, i) => self.threads.as_mut()?.stacks.get_mut(&t)?.get_mut(i),
+            NodeKey::Threads => self.threads.as_mut().map(|t| &mut t.list),
+            NodeKey::ThreadsLength => self.threads.as_mut().map(|t| &mut t.length),
+            NodeKey::Registers => self.registers.as_mut().map(|r| &mut r.list),
+            NodeKey::Register(n) => self.registers.as_mut()?.values.get_mut(&n),
+            NodeKey::WatchExpressions => self.watch_expression_nodes.as_mut().map(|w| &mut w.list),
+            NodeKey::WatchExpression(i) => self.watch_expression_nodes.as_mut()?.values.get_mut(&i),
+            NodeKey::FunctionResult => self.function_result.as_mut().map(|r| &mut r.node),
+            NodeKey::VarObject(v) => self.variables.get_at_mut(v).map(|v| &mut v.node),
+            NodeKey::Length(v) => self.length_nodes.get_mut(&v),
+            NodeKey::BitOffset(v) => self.bitfield_nodes.get_mut(&v).map(|b| &mut b.offset),
+            NodeKey::BitWidth(v) => self.bitfield_nodes.get_mut(&v).map(|b| &mut b.width),
+            NodeKey::Heap => self.heap.as_mut().map(|h| &mut h.list),
+            NodeKey::HeapLength => self.heap.as_mut().map(|h| &mut h.length),
+            NodeKey::HeapBlock(i) => self.heap_block_mut(i).map(|b| &mut b.block),
+            NodeKey::HeapBlockAddress(i) => self.heap_block_mut(i).map(|b| &mut b.address),
+            NodeKey::HeapBlockSize(i) => self.heap_block_mut(i).map(|b| &mut b.size),
+            NodeKey::Truncated(v) => self.truncation_markers.get_mut(&v),
+        }
+    }
+
+    fn heap_block(&self, index: usize) -> Option<&HeapBlockNodes> {
+        self.heap.as_ref()?.blocks.get(index)
+    }
+
+    fn heap_block_mut(&mut self, index: usize) -> Option<&mut HeapBlockNodes> {
+        self.heap.as_mut()?.blocks.get_mut(index)
+    }
+}
+
+/// Size of a [`GdbStateGraph`], as reported by [`GdbStateGraph::stats`].
+///
+/// Useful for tuning [`GraphLimits`](crate::options::GraphLimits)
+/// and for telling the user how much of the program state is shown.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct GraphStats {
+    /// Number of nodes of each type class.
+    pub nodes: HashMap<NodeTypeClass, usize>,
+
+    /// Number of outgoing edges of nodes of each type class.
+    pub edges: HashMap<NodeTypeClass, usize>,
+
+    /// Total size, in bytes, of the heap blocks listed in the graph.
+    ///
+    /// See [`GraphOptions::with_heap_blocks`].
+    pub heap_bytes: u64,
+
+    /// Number of pointers whose targets have been left out
+    /// of the graph because of [limits](crate::options::GraphLimits).
+    pub deferred_dereferences: usize,
+}
+
+impl GraphStats {
+    /// Gets the total number of nodes.
+    pub fn node_count(&self) -> usize {
+        self.nodes.values().sum()
+    }
+
+    /// Gets the total number of edges.
+    pub fn edge_count(&self) -> usize {
+        self.edges.values().sum()
+    }
 }
 
 /// Node of a [`GdbStateGraph`].
-#[derive(Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct GdbStateNode {
     pub(crate) type_class: NodeTypeClass,
     pub(crate) type_name: Option<String>,
-    pub(crate) successors: Vec<(EdgeLabel, GdbStateNodeId)>,
+    pub(crate) successors: Vec<(EdgeLabel, NodeKey)>,
     pub(crate) value: Option<NodeValue>,
+    pub(crate) source_location: Option<SourceLocation>,
+    pub(crate) shared: bool,
+}
+
+/// Reference to a node of a [`GdbStateGraph`]
+/// or a [`GdbStateGraphSnapshot`](crate::snapshot::GdbStateGraphSnapshot).
+///
+/// Nodes refer to their successors by keys that are only meaningful
+/// within the graph, so the reference carries what it needs
+/// to turn them into [`GdbStateNodeId`]s.
+#[derive(Clone, Copy, Debug)]
+pub struct GdbStateNodeRef<'a> {
+    node: &'a GdbStateNode,
+    #[debug(skip)]
+    variables: &'a dyn VariableNames,
 }
 
-impl ProgramStateNode for &GdbStateNode {
+impl<'a> GdbStateNodeRef<'a> {
+    pub(crate) fn new(node: &'a GdbStateNode, variables: &'a dyn VariableNames) -> Self {
+        Self { node, variables }
+    }
+}
+
+impl ProgramStateNode for GdbStateNodeRef<'_> {
     type NodeId = GdbStateNodeId;
     type NodeTypeId<'a>
         = &'a str
     where
         Self: 'a;
     fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
-        self.successors
+        self.node
+            .successors
             .iter()
             .find(|(e, _)| *e == *edge)
-            .map(|(_, n)| n)
-            .cloned()
+            .and_then(|(_, n)| n.to_id(self.variables))
     }
     fn node_type_class(&self) -> NodeTypeClass {
-        self.type_class
+        self.node.type_class
     }
     fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
-        match self.type_class {
-            NodeTypeClass::Atom | NodeTypeClass::Struct | NodeTypeClass::Frame => {
-                self.type_name.as_deref()
-            }
-            NodeTypeClass::Ref | NodeTypeClass::Root | NodeTypeClass::Array => None,
+        match self.node.type_class {
+            NodeTypeClass::Atom
+            | NodeTypeClass::Struct
+            | NodeTypeClass::Union
+            | NodeTypeClass::Frame => self.node.type_name.as_deref(),
+            NodeTypeClass::Ref
+            | NodeTypeClass::Root
+            | NodeTypeClass::Array
+            | NodeTypeClass::Custom
+            | NodeTypeClass::Truncated => None,
         }
     }
     fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
-        self.successors.iter().map(|(e, n)| (e, n.clone()))
+        self.node
+            .successors
+            .iter()
+            .filter_map(|(e, n)| Some((e, n.to_id(self.variables)?)))
     }
     fn value(&self) -> Option<NodeValue> {
-        self.value
+        self.node.value.clone()
+    }
+    fn source_location(&self) -> Option<SourceLocation> {
+        self.node.source_location.clone()
+    }
+    fn is_shared(&self) -> bool {
+        self.node.shared
     }
 }
 
@@ -132,7 +676,25 @@ pub(crate) struct GdbStateNodeForVariable {
 
     /// References to [`NodeTypeClass::Ref`] nodes whose
     /// [`EdgeLabel::Deref`] points to this node.
-    pub referers: Vec<VariableObject>,
+    pub referers: Vec<VariableIndex>,
+
+    /// True if the node holds a decoded C string.
+    pub is_string: bool,
+
+    /// True if the variable is a pointer to a `const`-qualified type,
+    /// which means that the object it points to is read-only through it.
+    pub points_to_const: bool,
+
+    /// Number of pointers that have been followed
+    /// from a variable to reach the node.
+    ///
+    /// Only valid for top-level nodes,
+    /// other nodes share the depth of their top-level ancestor.
+    pub deref_depth: usize,
+
+    /// Shape of the node if it is a container
+    /// presented by a [`ContainerAdapter`](crate::adapters::ContainerAdapter).
+    pub container_shape: Option<ContainerShape>,
 }
 
 /// [`GdbStateNode`] with additional data for a node that
@@ -144,6 +706,10 @@ impl GdbStateNodeForVariable {
             parent,
             address: None,
             referers: Vec::new(),
+            is_string: false,
+            points_to_const: false,
+            deref_depth: 0,
+            container_shape: None,
         }
     }
 
@@ -153,3 +719,137 @@ impl GdbStateNodeForVariable {
         !matches!(self.parent, Some(GdbStateNodeId::VarObject(_)))
     }
 }
+
+/// Pseudo-nodes that describe the placement of a bitfield.
+#[derive(Debug)]
+pub(crate) struct BitfieldNodes {
+    /// Node under the [`BIT_OFFSET_EDGE_LABEL`] edge.
+    pub offset: GdbStateNode,
+
+    /// Node under the [`BIT_WIDTH_EDGE_LABEL`] edge.
+    pub width: GdbStateNode,
+}
+
+/// Nodes that list the stacks of all threads.
+#[derive(Debug)]
+pub(crate) struct ThreadNodes {
+    /// Array node under the [`THREADS_EDGE_LABEL`] edge.
+    pub list: GdbStateNode,
+
+    /// Length of the array.
+    pub length: GdbStateNode,
+
+    /// Stack frames of each thread, keyed by the number of the thread in GDB.
+    ///
+    /// Frames are ordered from the bottom-most one,
+    /// like the frames of the selected thread
+    /// if the graph does not include all threads.
+    pub stacks: BTreeMap<usize, Vec<GdbStateNode>>,
+
+    /// Number of the thread that was selected in GDB
+    /// when the graph was last updated.
+    pub selected: Option<usize>,
+}
+
+/// Nodes that hold the values of CPU registers.
+#[derive(Debug)]
+pub(crate) struct RegisterNodes {
+    /// Structure node under the [`REGISTERS_EDGE_LABEL`] edge.
+    pub list: GdbStateNode,
+
+    /// Nodes of the individual registers, keyed by their numbers in GDB.
+    pub values: BTreeMap<usize, GdbStateNode>,
+
+    /// Frame node that the structure is attached to, if any.
+    pub frame: Option<GdbStateNodeId>,
+}
+
+/// Nodes that hold the values of watch expressions.
+#[derive(Debug)]
+pub(crate) struct WatchExpressionNodes {
+    /// Structure node under the [`WATCH_EDGE_LABEL`] edge.
+    pub list: GdbStateNode,
+
+    /// Nodes of the individual expressions,
+    /// keyed by their indices in [`GdbStateGraph::watch_expressions`].
+    pub values: BTreeMap<usize, GdbStateNode>,
+}
+
+/// Values of hints from the hint sheet that have been assigned to a variable node.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ResolvedHints {
+    /// Value of the [`Length`](crate::hints::PointerLengthHintKey::Length) hint.
+    pub length: Option<PropertyValue<GdbStateNodeId>>,
+
+    /// Value of the [`TreatAs`](crate::hints::PointerLengthHintKey::TreatAs) hint.
+    pub treat_as: Option<PropertyValue<GdbStateNodeId>>,
+
+    /// Value of the [`UnionVariant`](crate::hints::PointerLengthHintKey::UnionVariant) hint.
+    pub union_variant: Option<PropertyValue<GdbStateNodeId>>,
+
+    /// Value of the [`Variant`](crate::hints::PointerLengthHintKey::Variant) hint.
+    pub variant: Option<PropertyValue<GdbStateNodeId>>,
+}
+
+/// Node that holds the value that a function has returned.
+#[derive(Debug)]
+pub(crate) struct FunctionResultNode {
+    /// The value node under the [`EdgeLabel::Result`] edge.
+    pub node: GdbStateNode,
+
+    /// Frame node that the value is attached to.
+    pub frame: GdbStateNodeId,
+}
+
+/// Nodes that list heap blocks that are not reachable
+/// from any visible pointer.
+#[derive(Debug)]
+pub(crate) struct HeapNodes {
+    /// Array node under the [`HEAP_EDGE_LABEL`] edge.
+    pub list: GdbStateNode,
+
+    /// Length of the array.
+    pub length: GdbStateNode,
+
+    /// Nodes of the individual blocks, in order of addresses.
+    pub blocks: Vec<HeapBlockNodes>,
+}
+
+/// Nodes that represent a single heap block.
+#[derive(Debug)]
+pub(crate) struct HeapBlockNodes {
+    /// Structure node that represents the block.
+    pub block: GdbStateNode,
+
+    /// Node that holds the address of the block.
+    pub address: GdbStateNode,
+
+    /// Node that holds the size of the block.
+    pub size: GdbStateNode,
+}
+
+/// Layout of a structure or union type.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub(crate) struct TypeLayout {
+    /// True if the type is a union.
+    pub is_union: bool,
+
+    /// Offsets and widths of bitfield members, in bits,
+    /// by the names of the members.
+    pub bitfields: HashMap<String, (u64, u64)>,
+}
+
+/// Watchpoints installed by a [`GdbStateGraph`]
+/// to detect changes in variables.
+#[derive(Debug, Default)]
+pub(crate) struct WatchpointState {
+    /// Watched variable objects by the numbers of their watchpoints.
+    pub by_number: HashMap<usize, VariableObject>,
+
+    /// Numbers of watchpoints by the variable objects they watch.
+    pub by_object: HashMap<VariableObject, usize>,
+
+    /// Numbers of watchpoints that have reported a hit
+    /// since the last update.
+    pub pending_hits: Vec<usize>,
+}
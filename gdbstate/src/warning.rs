@@ -0,0 +1,173 @@
+//! Diagnostics of inconsistencies encountered
+//! while constructing or updating a state graph.
+
+use crate::{gdbmi::types::VariableObject, state::GdbStateNodeId};
+use aili_model::state::EdgeLabel;
+use derive_more::{Debug, Display};
+
+/// Inconsistency that did not prevent a [`GdbStateGraph`](crate::state::GdbStateGraph)
+/// from being constructed or updated, but suggests that the graph
+/// may not reflect the state of the debuggee accurately.
+///
+/// Warnings are emitted through [`tracing`] at the `WARN` level
+/// and passed to the handler set by
+/// [`GdbStateGraph::set_warning_handler`](crate::state::GdbStateGraph::set_warning_handler).
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+pub enum GraphWarning {
+    /// GDB returned a dynamic variable object
+    /// even though dynamic variable objects were not enabled for it.
+    ///
+    /// The object is treated as an ordinary variable object.
+    #[display("GDB returned dynamic variable object {_0:?}, which was not requested")]
+    IgnoredDynamicVariableObject(VariableObject),
+
+    /// GDB reported that the type of a variable object has changed.
+    ///
+    /// The graph keeps the original type of the variable.
+    #[display("GDB reported that the type of {object:?} changed to {type_name}")]
+    UnexpectedTypeChange {
+        /// Variable object whose type has changed.
+        object: VariableObject,
+
+        /// Name of the new type.
+        type_name: String,
+    },
+
+    /// GDB reported that a variable object that does not belong
+    /// to a stack frame has gone out of scope.
+    #[display("GDB reported that non-local variable {_0:?} went out of scope")]
+    UnexpectedOutOfScope(VariableObject),
+
+    /// A variable node was recorded as referenced by a pointer
+    /// that no longer exists or no longer points to it.
+    #[display("{target:?} is not referenced by {referer:?}, which is recorded as its referer")]
+    DanglingReferer {
+        /// The pointer node that was expected to reference the target.
+        referer: VariableObject,
+
+        /// The node that was expected to be referenced.
+        target: VariableObject,
+    },
+
+    /// A variable node that was removed from the graph was recorded
+    /// as referenced by a pointer that had already been removed.
+    ///
+    /// The pointer cannot be unlinked, since it no longer exists.
+    #[display("A referer of removed variable {_0:?} had already been removed")]
+    StaleReferer(VariableObject),
+
+    /// A node has a successor that nodes of its kind should never have.
+    #[display("{node:?} has unexpected successor {successor:?} at edge {edge:?}")]
+    UnexpectedSuccessor {
+        /// The node that has the successor.
+        node: GdbStateNodeId,
+
+        /// Label of the edge that leads to the successor.
+        edge: EdgeLabel,
+
+        /// The unexpected successor.
+        successor: GdbStateNodeId,
+    },
+
+    /// A value returned by GDB could not be parsed.
+    ///
+    /// The value is left out of the graph.
+    #[display("Cannot parse value {value:?} of {object:?}")]
+    UnparsableValue {
+        /// Variable object whose value could not be parsed.
+        object: VariableObject,
+
+        /// The value as returned by GDB.
+        value: String,
+    },
+
+    /// A rule of the pointer length hint stylesheet selects an edge
+    /// or an extra label, which length hints cannot be assigned to.
+    ///
+    /// The rule is ignored.
+    #[display("Pointer length hint rule {_0} does not select a node")]
+    UnsupportedHintRule(usize),
+
+    /// A length hint has been assigned to a node that is not a variable.
+    ///
+    /// The hint is ignored.
+    #[display("Pointer length hint assigned to {_0:?}, which is not a variable")]
+    MisplacedLengthHint(GdbStateNodeId),
+
+    /// A [`TreatAs`](crate::hints::PointerLengthHintKey::TreatAs) hint
+    /// has been assigned to a node that is not a pointer.
+    ///
+    /// The hint is ignored.
+    #[display("Type hint assigned to {_0:?}, which is not a pointer")]
+    MisplacedTypeHint(GdbStateNodeId),
+
+    /// A [`UnionVariant`](crate::hints::PointerLengthHintKey::UnionVariant) hint
+    /// has been assigned to a node that is not a union or a structure.
+    ///
+    /// The hint is ignored.
+    #[display("Union variant hint assigned to {_0:?}, which is not a union")]
+    MisplacedUnionVariantHint(GdbStateNodeId),
+
+    /// A [`UnionVariant`](crate::hints::PointerLengthHintKey::UnionVariant) hint
+    /// has selected a member that the union does not have.
+    ///
+    /// All members of the union are included.
+    #[display("{node:?} has no member {variant:?} selected by a union variant hint")]
+    UnknownUnionVariant {
+        /// The union node.
+        node: GdbStateNodeId,
+
+        /// Name of the selected member.
+        variant: String,
+    },
+
+    /// A [`Variant`](crate::hints::PointerLengthHintKey::Variant) hint
+    /// has been assigned to a node that is neither a union
+    /// nor a structure with a union member.
+    ///
+    /// The hint is ignored.
+    #[display("Variant hint assigned to {_0:?}, which does not hold a union")]
+    MisplacedVariantHint(GdbStateNodeId),
+}
+
+/// Callback that receives [`GraphWarning`]s.
+pub(crate) type WarningHandler = Box<dyn Fn(&GraphWarning) + Send + Sync>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        gdbmi::mock::{MockGdbMiSession, ready},
+        state::GdbStateGraph,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn type_change_is_reported_to_handler() {
+        let mut gdb = MockGdbMiSession::new()
+            .with_response(
+                "-stack-list-frames",
+                r#"^done,stack=[frame={level="0",addr="0x0000000000401126",func="main",file="main.c",line="5",arch="i386:x86-64"}]"#,
+            )
+            .with_response("-stack-select-frame 0", "^done")
+            .with_response("-stack-list-variables 0", "^done,variables=[]")
+            .with_response(
+                "-var-update 2 *",
+                r#"^done,changelist=[{name="var1",in_scope="true",new_type_name="long"}]"#,
+            );
+        let mut graph = ready(GdbStateGraph::new(&mut gdb)).unwrap();
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let handler_warnings = warnings.clone();
+        graph.set_warning_handler(move |warning| {
+            handler_warnings.lock().unwrap().push(warning.clone());
+        });
+        ready(graph.update(&mut gdb)).unwrap();
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            [GraphWarning::UnexpectedTypeChange {
+                object: VariableObject("var1".to_owned()),
+                type_name: "long".to_owned(),
+            }]
+        );
+    }
+}
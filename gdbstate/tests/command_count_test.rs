@@ -0,0 +1,155 @@
+//! Benchmarks of the numbers of GDB/MI commands
+//! that constructing and updating state graphs takes.
+//!
+//! Each scenario is run in two sizes, so it can be checked
+//! how the number of commands grows with the size of the program state.
+
+// Not all utilities are needed by the benchmarks
+#[allow(dead_code)]
+mod utils;
+
+use aili_gdbstate::{
+    gdbmi::counting::CommandCounts, hints::PointerLengthHintKey, state::GdbStateGraph,
+};
+use aili_model::state::EdgeLabel;
+use aili_style::{
+    cascade::CascadeStyle,
+    stylesheet::{
+        StyleClause, StyleKey, StyleRule, Stylesheet,
+        expression::Expression,
+        selector::{EdgeMatcher, Selector, SelectorSegment},
+    },
+};
+use utils::{
+    commands::{
+        assert_command_budget, assert_constant_commands, assert_linear_commands,
+        counting_gdb_from_source,
+    },
+    future::ExpectReady as _,
+};
+
+/// Number of commands that an update takes if nothing has changed:
+/// `-var-update`, `-stack-list-frames`, `-stack-select-frame`,
+/// and `-stack-list-variables`.
+const UNCHANGED_UPDATE_BUDGET: usize = 4;
+
+/// Runs a program to a line, then constructs a state graph
+/// and updates it without resuming the program.
+///
+/// Returns the commands sent for the construction and for the update.
+fn construct_and_update(
+    source: &str,
+    line: usize,
+    hints: &CascadeStyle<PointerLengthHintKey>,
+) -> (CommandCounts, CommandCounts) {
+    let mut gdb = counting_gdb_from_source(source);
+    gdb.run_to_line(line).unwrap();
+    gdb.take_counts();
+    let mut state_graph = GdbStateGraph::new_with_hints(&mut gdb, hints)
+        .expect_ready()
+        .unwrap();
+    let construction = gdb.take_counts();
+    state_graph
+        .update_with_hints(&mut gdb, hints)
+        .expect_ready()
+        .unwrap();
+    let update = gdb.take_counts();
+    (construction, update)
+}
+
+fn locals_source(count: usize) -> String {
+    let declarations = (0..count)
+        .map(|i| format!("int local{i} = {i};"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        r"
+        int main(void) {{
+            {declarations}
+            /* breakpoint */;
+        }}"
+    )
+}
+
+#[test]
+fn many_locals() {
+    let hints = CascadeStyle::empty();
+    let (small_construction, small_update) = construct_and_update(&locals_source(4), 4, &hints);
+    let (large_construction, large_update) = construct_and_update(&locals_source(8), 4, &hints);
+    // Each local takes a variable object and a query for its address
+    assert_linear_commands(&small_construction, &large_construction, 4, 2);
+    assert_constant_commands(&small_update, &large_update);
+    assert_command_budget(&large_update, UNCHANGED_UPDATE_BUDGET);
+}
+
+fn linked_list_source(length: usize) -> String {
+    format!(
+        r"
+        #include <stdlib.h>
+
+        struct node {{
+            struct node* next;
+            int value;
+        }};
+
+        int main(void) {{
+            struct node* head = 0;
+            for (int i = 0; i < {length}; ++i) {{
+                struct node* n = (struct node*)malloc(sizeof(*n));
+                n->next = head;
+                n->value = i;
+                head = n;
+            }}
+            /* breakpoint */;
+        }}"
+    )
+}
+
+#[test]
+fn linked_list() {
+    let hints = CascadeStyle::empty();
+    let (small_construction, small_update) =
+        construct_and_update(&linked_list_source(4), 17, &hints);
+    let (large_construction, large_update) =
+        construct_and_update(&linked_list_source(8), 17, &hints);
+    // Each node takes an access check, a variable object,
+    // and a query for the members of the structure
+    assert_linear_commands(&small_construction, &large_construction, 4, 3);
+    assert_constant_commands(&small_update, &large_update);
+    assert_command_budget(&large_update, UNCHANGED_UPDATE_BUDGET);
+}
+
+fn window_hints(length: u64) -> CascadeStyle<PointerLengthHintKey> {
+    CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::from_path(
+            [
+                SelectorSegment::Match(EdgeLabel::Main.into()),
+                SelectorSegment::Match(EdgeMatcher::Named("window".into())),
+            ]
+            .into(),
+        ),
+        properties: vec![StyleClause {
+            key: StyleKey::Property(PointerLengthHintKey::Length),
+            value: Expression::Int(length),
+        }],
+    }]))
+}
+
+#[test]
+fn array_window() {
+    let source = r"
+        #include <stdlib.h>
+
+        int main(void) {
+            int* window = (int*)malloc(sizeof(int) * 64);
+            for (int i = 0; i < 64; ++i)
+                window[i] = i;
+            /* breakpoint */;
+        }";
+    let (small_construction, small_update) = construct_and_update(source, 8, &window_hints(4));
+    let (large_construction, large_update) = construct_and_update(source, 8, &window_hints(64));
+    // Items of an array of elementary values are all read at once
+    assert_constant_commands(&small_construction, &large_construction);
+    assert_constant_commands(&small_update, &large_update);
+    assert_command_budget(&large_update, UNCHANGED_UPDATE_BUDGET);
+}
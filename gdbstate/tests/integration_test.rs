@@ -1,6 +1,15 @@
 mod utils;
 
-use aili_gdbstate::{hints::PointerLengthHintKey, state::GdbStateGraph};
+use aili_gdbstate::{
+    cancel::CancellationToken,
+    gdbmi::{result::Error, session::DebugController, types::StopReason},
+    hints::PointerLengthHintKey,
+    options::{GlobFilter, GraphLimits, GraphOptions},
+    state::{
+        BIT_OFFSET_EDGE_LABEL, BIT_WIDTH_EDGE_LABEL, GdbStateGraph, HEAP_EDGE_LABEL,
+        REGISTERS_EDGE_LABEL, THREADS_EDGE_LABEL, TRUNCATED_EDGE_LABEL, WATCH_EDGE_LABEL,
+    },
+};
 use aili_model::state::*;
 use aili_style::{
     cascade::CascadeStyle,
@@ -31,7 +40,7 @@ fn basic_function_argument() {
     let mut gdb = gdb_from_source("int main(int argc) {}");
     let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
     let argc = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("argc".to_owned(), 0)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("argc".into(), 0)])
         .unwrap();
     assert_eq!(argc.node_type_class(), NodeTypeClass::Atom);
     assert_eq!(argc.node_type_id(), Some("int"));
@@ -50,13 +59,36 @@ fn basic_local_variable() {
     gdb.run_to_line(4).unwrap();
     let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
     let local = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("local".to_owned(), 0)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("local".into(), 0)])
         .unwrap();
     assert_eq!(local.node_type_class(), NodeTypeClass::Atom);
     assert_eq!(local.node_type_id(), Some("int"));
     assert_eq!(local.value(), Some(NodeValue::Int(42)));
 }
 
+#[test]
+fn floating_point_variables() {
+    let mut gdb = gdb_from_source(
+        r"
+        int main(void) {
+            double d = 2.5;
+            float f = -0.125f;
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(5).unwrap();
+    let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
+    let d = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("d".into(), 0)])
+        .unwrap();
+    assert_eq!(d.node_type_class(), NodeTypeClass::Atom);
+    assert_eq!(d.value(), Some(NodeValue::Float(2.5)));
+    let f = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("f".into(), 0)])
+        .unwrap();
+    assert_eq!(f.value(), Some(NodeValue::Float(-0.125)));
+}
+
 #[test]
 fn no_op_update() {
     let mut gdb = gdb_from_source(
@@ -67,10 +99,9 @@ fn no_op_update() {
     );
     let mut state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
     state_graph.update(&mut gdb).expect_ready().unwrap();
-    let local =
-        state_graph.get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("local".to_owned(), 0)]);
+    let local = state_graph.get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("local".into(), 0)]);
     let another_local_id =
-        state_graph.get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("local".to_owned(), 1)]);
+        state_graph.get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("local".into(), 1)]);
     assert!(local.is_some());
     assert!(another_local_id.is_none());
 }
@@ -107,9 +138,9 @@ fn variable_shadowing() {
     );
     gdb.run_to_line(6).unwrap();
     let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
-    let a0_id = state_graph.get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".to_owned(), 0)]);
+    let a0_id = state_graph.get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".into(), 0)]);
     let a1 = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".to_owned(), 1)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".into(), 1)])
         .unwrap();
     assert_eq!(a1.node_type_class(), NodeTypeClass::Atom);
     assert_eq!(a1.node_type_id(), Some("unsigned int"));
@@ -133,10 +164,10 @@ fn multiple_variables_with_same_name() {
     gdb.run_to_line(6).unwrap();
     state_graph.update(&mut gdb).expect_ready().unwrap();
     let a0 = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".to_owned(), 0)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".into(), 0)])
         .unwrap();
     let a1 = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".to_owned(), 1)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".into(), 1)])
         .unwrap();
     assert_eq!(a0.node_type_class(), NodeTypeClass::Atom);
     assert_eq!(a0.node_type_id(), Some("int"));
@@ -168,15 +199,66 @@ fn variable_out_of_scope() {
     // Variable a#0 should be loaded now, and a#1 should go out of scope
     state_graph.update(&mut gdb).expect_ready().unwrap();
     let a0 = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".to_owned(), 0)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".into(), 0)])
         .unwrap();
-    let a1_id = state_graph.get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".to_owned(), 1)]);
+    let a1_id = state_graph.get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".into(), 1)]);
     assert_eq!(a0.node_type_class(), NodeTypeClass::Atom);
     assert_eq!(a0.node_type_id(), Some("int"));
     assert_eq!(a0.value(), Some(NodeValue::Int(-42)));
     assert!(a1_id.is_none());
 }
 
+#[test]
+fn global_variables() {
+    let mut gdb = gdb_from_source(
+        r"
+        int g_counter = 5;
+        static int g_hidden = 7;
+        int other = 9;
+        int main(void) {
+            g_counter++;
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(7).unwrap();
+    let options = GraphOptions::new().with_globals(GlobFilter::new(["g_*"]));
+    let mut state_graph =
+        GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+            .expect_ready()
+            .unwrap();
+    let counter = state_graph
+        .get_at_root(&[EdgeLabel::Named("g_counter".into(), 0)])
+        .unwrap();
+    assert_eq!(counter.node_type_class(), NodeTypeClass::Atom);
+    assert_eq!(counter.node_type_id(), Some("int"));
+    assert_eq!(counter.value(), Some(NodeValue::Int(6)));
+    let hidden = state_graph
+        .get_at_root(&[EdgeLabel::Named("g_hidden".into(), 0)])
+        .unwrap();
+    assert_eq!(hidden.value(), Some(NodeValue::Int(7)));
+    let other_id = state_graph.get_id_at_root(&[EdgeLabel::Named("other".into(), 0)]);
+    assert!(other_id.is_none());
+    // Globals should survive updates
+    gdb.run_to_line(8).unwrap();
+    state_graph.update(&mut gdb).expect_ready().unwrap();
+    let counter = state_graph
+        .get_at_root(&[EdgeLabel::Named("g_counter".into(), 0)])
+        .unwrap();
+    assert_eq!(counter.value(), Some(NodeValue::Int(6)));
+}
+
+#[test]
+fn global_variables_excluded_by_default() {
+    let mut gdb = gdb_from_source(
+        r"
+        int g_counter = 5;
+        int main(void) {}",
+    );
+    let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
+    let counter_id = state_graph.get_id_at_root(&[EdgeLabel::Named("g_counter".into(), 0)]);
+    assert!(counter_id.is_none());
+}
+
 #[test]
 fn structure_variables() {
     let mut gdb = gdb_from_source(
@@ -193,14 +275,14 @@ fn structure_variables() {
     gdb.run_to_line(8).unwrap();
     let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
     let pair_id = state_graph
-        .get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("p".to_owned(), 0)])
+        .get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("p".into(), 0)])
         .unwrap();
     let pair = state_graph.get(&pair_id).unwrap();
     let first = state_graph
-        .get_at(&pair_id, &[EdgeLabel::Named("first".to_owned(), 0)])
+        .get_at(&pair_id, &[EdgeLabel::Named("first".into(), 0)])
         .unwrap();
     let second = state_graph
-        .get_at(&pair_id, &[EdgeLabel::Named("second".to_owned(), 0)])
+        .get_at(&pair_id, &[EdgeLabel::Named("second".into(), 0)])
         .unwrap();
     assert_eq!(pair.node_type_class(), NodeTypeClass::Struct);
     assert_eq!(pair.node_type_id(), Some("pair"));
@@ -213,6 +295,82 @@ fn structure_variables() {
     assert_eq!(second.value(), Some(NodeValue::Int(0)));
 }
 
+#[test]
+fn union_variables() {
+    let mut gdb = gdb_from_source(
+        r"
+        typedef union {
+            int i;
+            float f;
+        } number;
+        int main(void) {
+            union { int i; char c; } u = {42};
+            number n = {7};
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(10).unwrap();
+    let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
+    let u = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("u".into(), 0)])
+        .unwrap();
+    let n = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("n".into(), 0)])
+        .unwrap();
+    let n_i = state_graph
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("n".into(), 0),
+            EdgeLabel::Named("i".into(), 0),
+        ])
+        .unwrap();
+    assert_eq!(u.node_type_class(), NodeTypeClass::Union);
+    assert_eq!(n.node_type_class(), NodeTypeClass::Union);
+    assert_eq!(n.node_type_id(), Some("number"));
+    assert_eq!(n_i.value(), Some(NodeValue::Int(7)));
+}
+
+#[test]
+fn bitfield_variables() {
+    let mut gdb = gdb_from_source(
+        r"
+        struct flags {
+            unsigned int low : 3;
+            int high : 5;
+            int plain;
+        };
+        int main(void) {
+            struct flags f = {5, -3, 1};
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(9).unwrap();
+    let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
+    let member = |name: &str, path: &[EdgeLabel]| {
+        let full_path = [
+            &[
+                EdgeLabel::Main,
+                EdgeLabel::Named("f".into(), 0),
+                EdgeLabel::Named(name.into(), 0),
+            ],
+            path,
+        ]
+        .concat();
+        state_graph.get_at_root(&full_path).and_then(|n| n.value())
+    };
+    let bit_offset = [EdgeLabel::Custom(BIT_OFFSET_EDGE_LABEL.to_owned())];
+    let bit_width = [EdgeLabel::Custom(BIT_WIDTH_EDGE_LABEL.to_owned())];
+    assert_eq!(member("low", &[]), Some(NodeValue::Uint(5)));
+    assert_eq!(member("low", &bit_offset), Some(NodeValue::Uint(0)));
+    assert_eq!(member("low", &bit_width), Some(NodeValue::Uint(3)));
+    assert_eq!(member("high", &[]), Some(NodeValue::Int(-3)));
+    assert_eq!(member("high", &bit_offset), Some(NodeValue::Uint(3)));
+    assert_eq!(member("high", &bit_width), Some(NodeValue::Uint(5)));
+    assert_eq!(member("plain", &[]), Some(NodeValue::Uint(1)));
+    assert_eq!(member("plain", &bit_offset), None);
+    assert_eq!(member("plain", &bit_width), None);
+}
+
 #[test]
 fn static_array_variables() {
     let mut gdb = gdb_from_source(
@@ -225,7 +383,7 @@ fn static_array_variables() {
     gdb.run_to_line(4).unwrap();
     let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
     let array_id = state_graph
-        .get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("array".to_owned(), 0)])
+        .get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("array".into(), 0)])
         .unwrap();
     let array = state_graph.get(&array_id).unwrap();
     let first = state_graph
@@ -312,24 +470,56 @@ fn update_in_function_call() {
     assert!(function_frame.successors().next().is_none());
 }
 
+#[test]
+fn resynchronize_after_reverse_execution() {
+    let mut gdb = gdb_from_source(
+        r"
+        int f(int x) {
+            int y = x;
+            /* breakpoint */;
+        }
+        int main(void) {
+            f(1);
+            f(2);
+        }",
+    );
+    gdb.start_recording().unwrap();
+    // Construct the graph in the second call
+    gdb.run_to_line(4).unwrap();
+    gdb.run_to_line(4).unwrap();
+    let mut state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
+    // Go back to the first call, the frame of the same function
+    // must not be mistaken for the one that was there before
+    gdb.reverse_to_line(4).unwrap();
+    state_graph.resynchronize(&mut gdb).expect_ready().unwrap();
+    let y = state_graph
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Next,
+            EdgeLabel::Named("y".into(), 0),
+        ])
+        .unwrap();
+    assert_eq!(y.value(), Some(NodeValue::Int(1)));
+}
+
 #[test]
 fn pointer_argument() {
     let mut gdb = gdb_from_source("int main (int argc, const char* const * argv) {}");
     let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
     let argv = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("argv".to_owned(), 0)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("argv".into(), 0)])
         .unwrap();
     let argv0 = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("argv".to_owned(), 0),
+            EdgeLabel::Named("argv".into(), 0),
             EdgeLabel::Deref,
         ])
         .unwrap();
     let argv00 = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("argv".to_owned(), 0),
+            EdgeLabel::Named("argv".into(), 0),
             EdgeLabel::Deref,
             EdgeLabel::Deref,
         ])
@@ -358,7 +548,7 @@ fn pointer_copying() {
     let argv00_id = state_graph
         .get_id_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("argv".to_owned(), 0),
+            EdgeLabel::Named("argv".into(), 0),
             EdgeLabel::Deref,
             EdgeLabel::Deref,
         ])
@@ -366,7 +556,7 @@ fn pointer_copying() {
     let deref_p_id = state_graph
         .get_id_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("p".to_owned(), 0),
+            EdgeLabel::Named("p".into(), 0),
             EdgeLabel::Deref,
         ])
         .unwrap();
@@ -390,10 +580,10 @@ fn pointer_update() {
     gdb.run_to_line(6).unwrap();
     state_graph.update(&mut gdb).expect_ready().unwrap();
     let p = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("p".to_owned(), 0)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("p".into(), 0)])
         .unwrap();
     let q = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("q".to_owned(), 0)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("q".into(), 0)])
         .unwrap();
     // The pointers should be offset by one byte
     match (p.value(), q.value()) {
@@ -427,22 +617,22 @@ fn pointer_to_local() {
     gdb.run_to_line(11).unwrap();
     let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
     let a_id = state_graph
-        .get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".to_owned(), 0)])
+        .get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".into(), 0)])
         .unwrap();
     let z_id = state_graph
-        .get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("z".to_owned(), 0)])
+        .get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("z".into(), 0)])
         .unwrap();
     let p_deref_id = state_graph
         .get_id_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("p".to_owned(), 0),
+            EdgeLabel::Named("p".into(), 0),
             EdgeLabel::Deref,
         ])
         .unwrap();
     let q_deref_id = state_graph
         .get_id_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("q".to_owned(), 0),
+            EdgeLabel::Named("q".into(), 0),
             EdgeLabel::Deref,
         ])
         .unwrap();
@@ -469,7 +659,7 @@ fn dangling_reference_detachment() {
     gdb.run_to_line(9).unwrap();
     state_graph.update(&mut gdb).expect_ready().unwrap();
     let pointer = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("p".to_owned(), 0)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("p".into(), 0)])
         .unwrap();
     assert!(pointer.get_successor(&EdgeLabel::Deref).is_none());
 }
@@ -501,31 +691,31 @@ fn linked_list() {
     let first = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("head".to_owned(), 0),
+            EdgeLabel::Named("head".into(), 0),
             EdgeLabel::Deref,
-            EdgeLabel::Named("value".to_owned(), 0),
+            EdgeLabel::Named("value".into(), 0),
         ])
         .unwrap();
     let second = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("head".to_owned(), 0),
+            EdgeLabel::Named("head".into(), 0),
             EdgeLabel::Deref,
-            EdgeLabel::Named("next".to_owned(), 0),
+            EdgeLabel::Named("next".into(), 0),
             EdgeLabel::Deref,
-            EdgeLabel::Named("value".to_owned(), 0),
+            EdgeLabel::Named("value".into(), 0),
         ])
         .unwrap();
     let third = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("head".to_owned(), 0),
+            EdgeLabel::Named("head".into(), 0),
             EdgeLabel::Deref,
-            EdgeLabel::Named("next".to_owned(), 0),
+            EdgeLabel::Named("next".into(), 0),
             EdgeLabel::Deref,
-            EdgeLabel::Named("next".to_owned(), 0),
+            EdgeLabel::Named("next".into(), 0),
             EdgeLabel::Deref,
-            EdgeLabel::Named("value".to_owned(), 0),
+            EdgeLabel::Named("value".into(), 0),
         ])
         .unwrap();
     assert_eq!(first.value(), Some(NodeValue::Int(41)));
@@ -561,11 +751,11 @@ fn reusing_deallocated_memory() {
 
 #[test]
 fn constant_length_hint() {
-    let hints = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let hints = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::from_path(
             [
                 SelectorSegment::Match(EdgeLabel::Main.into()),
-                SelectorSegment::Match(EdgeMatcher::Named("argv".to_owned())),
+                SelectorSegment::Match(EdgeMatcher::Named("argv".into())),
             ]
             .into(),
         ),
@@ -581,32 +771,32 @@ fn constant_length_hint() {
     let argv_length = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("argv".to_owned(), 0),
+            EdgeLabel::Named("argv".into(), 0),
             EdgeLabel::Deref,
             EdgeLabel::Length,
         ])
         .unwrap();
     let argv_0 = state_graph.get_at_root(&[
         EdgeLabel::Main,
-        EdgeLabel::Named("argv".to_owned(), 0),
+        EdgeLabel::Named("argv".into(), 0),
         EdgeLabel::Deref,
         EdgeLabel::Index(0),
     ]);
     let argv_1 = state_graph.get_at_root(&[
         EdgeLabel::Main,
-        EdgeLabel::Named("argv".to_owned(), 0),
+        EdgeLabel::Named("argv".into(), 0),
         EdgeLabel::Deref,
         EdgeLabel::Index(1),
     ]);
     let argv_2 = state_graph.get_at_root(&[
         EdgeLabel::Main,
-        EdgeLabel::Named("argv".to_owned(), 0),
+        EdgeLabel::Named("argv".into(), 0),
         EdgeLabel::Deref,
         EdgeLabel::Index(2),
     ]);
     let argv_3 = state_graph.get_at_root(&[
         EdgeLabel::Main,
-        EdgeLabel::Named("argv".to_owned(), 0),
+        EdgeLabel::Named("argv".into(), 0),
         EdgeLabel::Deref,
         EdgeLabel::Index(3),
     ]);
@@ -619,11 +809,11 @@ fn constant_length_hint() {
 
 #[test]
 fn length_hint_from_variable() {
-    let hints = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let hints = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::from_path(
             [
                 SelectorSegment::Match(EdgeLabel::Main.into()),
-                SelectorSegment::Match(EdgeMatcher::Named("argv".to_owned())),
+                SelectorSegment::Match(EdgeMatcher::Named("argv".into())),
             ]
             .into(),
         ),
@@ -645,32 +835,32 @@ fn length_hint_from_variable() {
     let argv_length = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("argv".to_owned(), 0),
+            EdgeLabel::Named("argv".into(), 0),
             EdgeLabel::Deref,
             EdgeLabel::Length,
         ])
         .unwrap();
     let argv_0 = state_graph.get_at_root(&[
         EdgeLabel::Main,
-        EdgeLabel::Named("argv".to_owned(), 0),
+        EdgeLabel::Named("argv".into(), 0),
         EdgeLabel::Deref,
         EdgeLabel::Index(0),
     ]);
     let argv_1 = state_graph.get_at_root(&[
         EdgeLabel::Main,
-        EdgeLabel::Named("argv".to_owned(), 0),
+        EdgeLabel::Named("argv".into(), 0),
         EdgeLabel::Deref,
         EdgeLabel::Index(1),
     ]);
     let argv_2 = state_graph.get_at_root(&[
         EdgeLabel::Main,
-        EdgeLabel::Named("argv".to_owned(), 0),
+        EdgeLabel::Named("argv".into(), 0),
         EdgeLabel::Deref,
         EdgeLabel::Index(2),
     ]);
     let argv_3 = state_graph.get_at_root(&[
         EdgeLabel::Main,
-        EdgeLabel::Named("argv".to_owned(), 0),
+        EdgeLabel::Named("argv".into(), 0),
         EdgeLabel::Deref,
         EdgeLabel::Index(3),
     ]);
@@ -683,14 +873,13 @@ fn length_hint_from_variable() {
 
 #[test]
 fn variable_length_hint() {
-    let hints = CascadeStyle::from(Stylesheet(vec![
+    let hints = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
             properties: vec![StyleClause {
                 key: StyleKey::Variable("--argc".to_owned()),
                 value: Expression::Select(
-                    LimitedSelector::from_path([EdgeLabel::Named("argc".to_owned(), 0).into()])
-                        .into(),
+                    LimitedSelector::from_path([EdgeLabel::Named("argc".into(), 0).into()]).into(),
                 ),
             }],
         },
@@ -698,7 +887,7 @@ fn variable_length_hint() {
             selector: Selector::from_path(
                 [
                     SelectorSegment::Match(EdgeLabel::Main.into()),
-                    SelectorSegment::Match(EdgeMatcher::Named("argv".to_owned())),
+                    SelectorSegment::Match(EdgeMatcher::Named("argv".into())),
                 ]
                 .into(),
             ),
@@ -713,12 +902,12 @@ fn variable_length_hint() {
         .expect_ready()
         .unwrap();
     let argc = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("argc".to_owned(), 0)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("argc".into(), 0)])
         .unwrap();
     let argv_length = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("argv".to_owned(), 0),
+            EdgeLabel::Named("argv".into(), 0),
             EdgeLabel::Deref,
             EdgeLabel::Length,
         ])
@@ -726,16 +915,75 @@ fn variable_length_hint() {
     assert_eq!(argc.value(), argv_length.value());
 }
 
+#[test]
+fn length_hint_from_sibling_member() {
+    // .many(*) "data" {
+    //   length: @(.. "count");
+    // }
+    let hints = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::from_path(
+            [
+                SelectorSegment::AnyNumberOfTimes(
+                    [SelectorSegment::Match(EdgeMatcher::Any)].into(),
+                ),
+                SelectorSegment::Match(EdgeMatcher::Named("data".into())),
+            ]
+            .into(),
+        ),
+        properties: vec![StyleClause {
+            key: StyleKey::Property(PointerLengthHintKey::Length),
+            value: Expression::Select(
+                LimitedSelector::from_path([
+                    LimitedEdgeMatcher::Parent,
+                    EdgeLabel::Named("count".into(), 0).into(),
+                ])
+                .into(),
+            ),
+        }],
+    }]));
+    let mut gdb = gdb_from_source(
+        r"
+        struct vector {
+            int *data;
+            unsigned long count;
+        };
+        int main(void) {
+            int first[2] = { 1, 2 };
+            int second[3] = { 3, 4, 5 };
+            struct vector a = { first, 2 };
+            struct vector b = { second, 3 };
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(11).unwrap();
+    let state_graph = GdbStateGraph::new_with_hints(&mut gdb, &hints)
+        .expect_ready()
+        .unwrap();
+    let length_of = |name: &str| {
+        state_graph
+            .get_at_root(&[
+                EdgeLabel::Main,
+                EdgeLabel::Named(name.into(), 0),
+                EdgeLabel::Named("data".into(), 0),
+                EdgeLabel::Deref,
+                EdgeLabel::Length,
+            ])
+            .unwrap()
+            .value()
+    };
+    assert_eq!(length_of("a"), Some(NodeValue::Uint(2)));
+    assert_eq!(length_of("b"), Some(NodeValue::Uint(3)));
+}
+
 #[test]
 fn resize_array_with_length_hint() {
-    let hints = CascadeStyle::from(Stylesheet(vec![
+    let hints = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
             properties: vec![StyleClause {
                 key: StyleKey::Variable("--len".to_owned()),
                 value: Expression::Select(
-                    LimitedSelector::from_path([EdgeLabel::Named("len".to_owned(), 0).into()])
-                        .into(),
+                    LimitedSelector::from_path([EdgeLabel::Named("len".into(), 0).into()]).into(),
                 ),
             }],
         },
@@ -743,7 +991,7 @@ fn resize_array_with_length_hint() {
             selector: Selector::from_path(
                 [
                     SelectorSegment::Match(EdgeLabel::Main.into()),
-                    SelectorSegment::Match(EdgeMatcher::Named("p".to_owned())),
+                    SelectorSegment::Match(EdgeMatcher::Named("p".into())),
                 ]
                 .into(),
             ),
@@ -778,14 +1026,14 @@ fn resize_array_with_length_hint() {
     let p_length = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("p".to_owned(), 0),
+            EdgeLabel::Named("p".into(), 0),
             EdgeLabel::Deref,
             EdgeLabel::Length,
         ])
         .unwrap();
     let p_3 = state_graph.get_at_root(&[
         EdgeLabel::Main,
-        EdgeLabel::Named("p".to_owned(), 0),
+        EdgeLabel::Named("p".into(), 0),
         EdgeLabel::Deref,
         EdgeLabel::Index(3),
     ]);
@@ -793,6 +1041,228 @@ fn resize_array_with_length_hint() {
     assert!(p_3.is_some());
 }
 
+#[test]
+fn treat_as_hint() {
+    let hints = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::from_path(
+            [
+                SelectorSegment::Match(EdgeLabel::Main.into()),
+                SelectorSegment::Match(EdgeMatcher::Named("buffer".into())),
+            ]
+            .into(),
+        ),
+        properties: vec![StyleClause {
+            key: StyleKey::Property(PointerLengthHintKey::TreatAs),
+            value: Expression::String("int[3]".to_owned()),
+        }],
+    }]));
+    let mut gdb = gdb_from_source(
+        r"
+        int main(void) {
+            int numbers[3] = { 1, 2, 3 };
+            unsigned char* buffer = (unsigned char*)numbers;
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(5).unwrap();
+    let state_graph = GdbStateGraph::new_with_hints(&mut gdb, &hints)
+        .expect_ready()
+        .unwrap();
+    let buffer_length = state_graph
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("buffer".into(), 0),
+            EdgeLabel::Deref,
+            EdgeLabel::Length,
+        ])
+        .unwrap();
+    assert_eq!(buffer_length.value(), Some(NodeValue::Uint(3)));
+    let buffer_2 = state_graph
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("buffer".into(), 0),
+            EdgeLabel::Deref,
+            EdgeLabel::Index(2),
+        ])
+        .unwrap();
+    assert_eq!(buffer_2.value(), Some(NodeValue::Uint(3)));
+}
+
+#[test]
+fn union_variant_hint() {
+    // main "v" {
+    //   --kind: @("kind");
+    // }
+    // main "v" "u" {
+    //   union-variant: --kind == 1 ? "f" : "i";
+    // }
+    let hints = CascadeStyle::from(Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::Match(EdgeLabel::Main.into()),
+                    SelectorSegment::Match(EdgeMatcher::Named("v".into())),
+                ]
+                .into(),
+            ),
+            properties: vec![StyleClause {
+                key: StyleKey::Variable("--kind".to_owned()),
+                value: Expression::Select(
+                    LimitedSelector::from_path([EdgeLabel::Named("kind".into(), 0).into()]).into(),
+                ),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::Match(EdgeLabel::Main.into()),
+                    SelectorSegment::Match(EdgeMatcher::Named("v".into())),
+                    SelectorSegment::Match(EdgeMatcher::Named("u".into())),
+                ]
+                .into(),
+            ),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(PointerLengthHintKey::UnionVariant),
+                value: Expression::Conditional(
+                    Expression::BinaryOperator(
+                        Expression::Variable("--kind".to_owned()).into(),
+                        BinaryOperator::Eq,
+                        Expression::Int(1).into(),
+                    )
+                    .into(),
+                    Expression::String("f".to_owned()).into(),
+                    Expression::String("i".to_owned()).into(),
+                ),
+            }],
+        },
+    ]));
+    let mut gdb = gdb_from_source(
+        r"
+        struct value {
+            int kind;
+            union {
+                int i;
+                float f;
+            } u;
+        };
+        int main(void) {
+            struct value v = { 0, { .i = 5 } };
+            /* breakpoint 1 */;
+            v.kind = 1;
+            v.u.f = 0.5f;
+            /* breakpoint 2 */;
+        }",
+    );
+    gdb.run_to_line(11).unwrap();
+    let mut state_graph = GdbStateGraph::new_with_hints(&mut gdb, &hints)
+        .expect_ready()
+        .unwrap();
+    let member_names = |state_graph: &GdbStateGraph| {
+        state_graph
+            .get_at_root(&[
+                EdgeLabel::Main,
+                EdgeLabel::Named("v".into(), 0),
+                EdgeLabel::Named("u".into(), 0),
+            ])
+            .unwrap()
+            .successors()
+            .map(|(edge, _)| edge.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        member_names(&state_graph),
+        [EdgeLabel::Named("i".into(), 0)]
+    );
+    gdb.run_to_line(14).unwrap();
+    state_graph
+        .update_with_hints(&mut gdb, &hints)
+        .expect_ready()
+        .unwrap();
+    assert_eq!(
+        member_names(&state_graph),
+        [EdgeLabel::Named("f".into(), 0)]
+    );
+}
+
+#[test]
+fn tagged_union_variant_hint() {
+    // main "v" {
+    //   variant: @("tag") == 1 ? "as_int" : "as_ptr";
+    // }
+    let hints = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::from_path(
+            [
+                SelectorSegment::Match(EdgeLabel::Main.into()),
+                SelectorSegment::Match(EdgeMatcher::Named("v".into())),
+            ]
+            .into(),
+        ),
+        properties: vec![StyleClause {
+            key: StyleKey::Property(PointerLengthHintKey::Variant),
+            value: Expression::Conditional(
+                Expression::BinaryOperator(
+                    Expression::Select(
+                        LimitedSelector::from_path([EdgeLabel::Named("tag".into(), 0).into()])
+                            .into(),
+                    )
+                    .into(),
+                    BinaryOperator::Eq,
+                    Expression::Int(1).into(),
+                )
+                .into(),
+                Expression::String("as_int".to_owned()).into(),
+                Expression::String("as_ptr".to_owned()).into(),
+            ),
+        }],
+    }]));
+    let mut gdb = gdb_from_source(
+        r"
+        struct value {
+            int tag;
+            union {
+                int as_int;
+                int *as_ptr;
+            } as;
+        };
+        int main(void) {
+            struct value v = { 1, { .as_int = 5 } };
+            /* breakpoint 1 */;
+            v.tag = 2;
+            v.as.as_ptr = &v.tag;
+            /* breakpoint 2 */;
+        }",
+    );
+    gdb.run_to_line(11).unwrap();
+    let mut state_graph = GdbStateGraph::new_with_hints(&mut gdb, &hints)
+        .expect_ready()
+        .unwrap();
+    let member_names = |state_graph: &GdbStateGraph| {
+        state_graph
+            .get_at_root(&[
+                EdgeLabel::Main,
+                EdgeLabel::Named("v".into(), 0),
+                EdgeLabel::Named("as".into(), 0),
+            ])
+            .unwrap()
+            .successors()
+            .map(|(edge, _)| edge.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        member_names(&state_graph),
+        [EdgeLabel::Named("as_int".into(), 0)]
+    );
+    gdb.run_to_line(14).unwrap();
+    state_graph
+        .update_with_hints(&mut gdb, &hints)
+        .expect_ready()
+        .unwrap();
+    assert_eq!(
+        member_names(&state_graph),
+        [EdgeLabel::Named("as_ptr".into(), 0)]
+    );
+}
+
 #[test]
 fn variable_shadowing_in_length_hints() {
     // main {
@@ -808,26 +1278,22 @@ fn variable_shadowing_in_length_hints() {
     // main *:s "p" {
     //   length: --a + --b;
     // }
-    let hints = CascadeStyle::from(Stylesheet(vec![
+    let hints = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
             properties: vec![
                 StyleClause {
                     key: StyleKey::Variable("--a".to_owned()),
                     value: Expression::Select(
-                        LimitedSelector::from_path([
-                            EdgeLabel::Named("defaultA".to_owned(), 0).into()
-                        ])
-                        .into(),
+                        LimitedSelector::from_path([EdgeLabel::Named("defaultA".into(), 0).into()])
+                            .into(),
                     ),
                 },
                 StyleClause {
                     key: StyleKey::Variable("--b".to_owned()),
                     value: Expression::Select(
-                        LimitedSelector::from_path([
-                            EdgeLabel::Named("defaultB".to_owned(), 0).into()
-                        ])
-                        .into(),
+                        LimitedSelector::from_path([EdgeLabel::Named("defaultB".into(), 0).into()])
+                            .into(),
                     ),
                 },
             ],
@@ -836,15 +1302,14 @@ fn variable_shadowing_in_length_hints() {
             selector: Selector::from_path(
                 [
                     SelectorSegment::Match(EdgeLabel::Main.into()),
-                    SelectorSegment::Match(EdgeMatcher::Named("sA".to_owned())),
+                    SelectorSegment::Match(EdgeMatcher::Named("sA".into())),
                 ]
                 .into(),
             ),
             properties: vec![StyleClause {
                 key: StyleKey::Variable("--a".to_owned()),
                 value: Expression::Select(
-                    LimitedSelector::from_path([EdgeLabel::Named("extra".to_owned(), 0).into()])
-                        .into(),
+                    LimitedSelector::from_path([EdgeLabel::Named("extra".into(), 0).into()]).into(),
                 ),
             }],
         },
@@ -852,15 +1317,14 @@ fn variable_shadowing_in_length_hints() {
             selector: Selector::from_path(
                 [
                     SelectorSegment::Match(EdgeLabel::Main.into()),
-                    SelectorSegment::Match(EdgeMatcher::Named("sB".to_owned())),
+                    SelectorSegment::Match(EdgeMatcher::Named("sB".into())),
                 ]
                 .into(),
             ),
             properties: vec![StyleClause {
                 key: StyleKey::Variable("--b".to_owned()),
                 value: Expression::Select(
-                    LimitedSelector::from_path([EdgeLabel::Named("extra".to_owned(), 0).into()])
-                        .into(),
+                    LimitedSelector::from_path([EdgeLabel::Named("extra".into(), 0).into()]).into(),
                 ),
             }],
         },
@@ -878,7 +1342,7 @@ fn variable_shadowing_in_length_hints() {
                         BinaryOperator::Eq,
                         Expression::String("s".to_owned()).into(),
                     )),
-                    SelectorSegment::Match(EdgeMatcher::Named("p".to_owned())),
+                    SelectorSegment::Match(EdgeMatcher::Named("p".into())),
                 ]
                 .into(),
             ),
@@ -918,8 +1382,8 @@ fn variable_shadowing_in_length_hints() {
     let sa_p_length = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("sA".to_owned(), 0),
-            EdgeLabel::Named("p".to_owned(), 0),
+            EdgeLabel::Named("sA".into(), 0),
+            EdgeLabel::Named("p".into(), 0),
             EdgeLabel::Deref,
             EdgeLabel::Length,
         ])
@@ -927,8 +1391,8 @@ fn variable_shadowing_in_length_hints() {
     let sb_p_length = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("sB".to_owned(), 0),
-            EdgeLabel::Named("p".to_owned(), 0),
+            EdgeLabel::Named("sB".into(), 0),
+            EdgeLabel::Named("p".into(), 0),
             EdgeLabel::Deref,
             EdgeLabel::Length,
         ])
@@ -939,11 +1403,11 @@ fn variable_shadowing_in_length_hints() {
 
 #[test]
 fn triangle_array_length_hints() {
-    let hints = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let hints = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::from_path(
             [
                 SelectorSegment::Match(EdgeLabel::Main.into()),
-                SelectorSegment::Match(EdgeMatcher::Named("arr".to_owned())),
+                SelectorSegment::Match(EdgeMatcher::Named("arr".into())),
                 SelectorSegment::Match(EdgeMatcher::AnyIndex),
             ]
             .into(),
@@ -977,7 +1441,7 @@ fn triangle_array_length_hints() {
         let length = state_graph
             .get_at_root(&[
                 EdgeLabel::Main,
-                EdgeLabel::Named("arr".to_owned(), 0),
+                EdgeLabel::Named("arr".into(), 0),
                 EdgeLabel::Index(i),
                 EdgeLabel::Deref,
                 EdgeLabel::Length,
@@ -992,12 +1456,12 @@ fn char_array() {
     let mut gdb = gdb_from_source(r#"int main(void) { const char s[] = "abc"; }"#);
     let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
     let array = state_graph
-        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("s".to_owned(), 0)])
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("s".into(), 0)])
         .unwrap();
     let length = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("s".to_owned(), 0),
+            EdgeLabel::Named("s".into(), 0),
             EdgeLabel::Length,
         ])
         .unwrap();
@@ -1005,6 +1469,136 @@ fn char_array() {
     assert!(length.value() == Some(NodeValue::Uint(4)));
 }
 
+#[test]
+fn string_values() {
+    let mut gdb = gdb_from_source(
+        r#"
+        int main(void) {
+            char buffer[16] = "abc";
+            const char *text = "hello\tworld";
+            char *null = 0;
+            /* breakpoint 1 */ buffer[1] = 'x';
+            /* breakpoint 2 */;
+        }"#,
+    );
+    gdb.run_to_line(6).unwrap();
+    let options = GraphOptions::new().with_strings(64);
+    let mut state_graph =
+        GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+            .expect_ready()
+            .unwrap();
+    let buffer = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("buffer".into(), 0)])
+        .unwrap();
+    assert_eq!(buffer.node_type_class(), NodeTypeClass::Atom);
+    assert_eq!(buffer.value(), Some(NodeValue::String("abc".to_owned())));
+    assert_eq!(buffer.successors().count(), 0);
+    let text = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("text".into(), 0)])
+        .unwrap();
+    assert_eq!(text.node_type_class(), NodeTypeClass::Atom);
+    assert_eq!(
+        text.value(),
+        Some(NodeValue::String("hello\tworld".to_owned()))
+    );
+    let null = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("null".into(), 0)])
+        .unwrap();
+    assert_eq!(null.value(), None);
+    gdb.run_to_line(7).unwrap();
+    state_graph.update(&mut gdb).expect_ready().unwrap();
+    let buffer = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("buffer".into(), 0)])
+        .unwrap();
+    assert_eq!(buffer.value(), Some(NodeValue::String("axc".to_owned())));
+}
+
+#[test]
+fn string_values_are_truncated() {
+    let mut gdb = gdb_from_source(
+        r#"
+        int main(void) {
+            const char *text = "abcdefgh";
+            /* breakpoint */;
+        }"#,
+    );
+    gdb.run_to_line(4).unwrap();
+    let options = GraphOptions::new().with_strings(4);
+    let state_graph = GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+        .expect_ready()
+        .unwrap();
+    let text = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("text".into(), 0)])
+        .unwrap();
+    assert_eq!(text.value(), Some(NodeValue::String("abcd".to_owned())));
+}
+
+#[test]
+fn identical_read_only_objects_share_a_node() {
+    let mut gdb = gdb_from_source(
+        r"
+        struct pair { int a; int b; };
+        static const struct pair x = { 1, 2 };
+        static const struct pair y = { 1, 2 };
+        static const struct pair z = { 1, 3 };
+        static struct pair w = { 1, 2 };
+        int main(void) {
+            const struct pair *p = &x, *q = &y, *r = &z;
+            struct pair *s = &w;
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(11).unwrap();
+    let options = GraphOptions::new().with_shared_subtrees();
+    let state_graph = GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+        .expect_ready()
+        .unwrap();
+    let target = |name: &str| {
+        state_graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named(name.into(), 0)])
+            .unwrap()
+            .get_successor(&EdgeLabel::Deref)
+            .unwrap()
+    };
+    assert_eq!(target("p"), target("q"));
+    assert_ne!(target("p"), target("r"));
+    assert_ne!(target("p"), target("s"));
+    assert!(state_graph.get(&target("p")).unwrap().is_shared());
+    assert!(!state_graph.get(&target("r")).unwrap().is_shared());
+    assert!(!state_graph.get(&target("s")).unwrap().is_shared());
+}
+
+#[test]
+fn objects_behind_pointers_to_mutable_pointers_are_not_shared() {
+    let mut gdb = gdb_from_source(
+        r"
+        struct pair { int a; int b; };
+        static const struct pair x = { 1, 2 };
+        static const struct pair *a = &x, *b = &x;
+        static const struct pair *const c = &x, *const d = &x;
+        int main(void) {
+            const struct pair **p = &a, **q = &b;
+            const struct pair *const *r = &c, *const *s = &d;
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(10).unwrap();
+    let options = GraphOptions::new().with_shared_subtrees();
+    let state_graph = GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+        .expect_ready()
+        .unwrap();
+    let target = |name: &str| {
+        state_graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named(name.into(), 0)])
+            .unwrap()
+            .get_successor(&EdgeLabel::Deref)
+            .unwrap()
+    };
+    // The pointers that p and q point to are not const themselves
+    assert_ne!(target("p"), target("q"));
+    assert_eq!(target("r"), target("s"));
+}
+
 #[test]
 fn pointer_to_array() {
     let mut gdb = gdb_from_source(
@@ -1021,14 +1615,14 @@ fn pointer_to_array() {
     let array = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("arr".to_owned(), 0),
+            EdgeLabel::Named("arr".into(), 0),
             EdgeLabel::Deref,
         ])
         .unwrap();
     let length = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("arr".to_owned(), 0),
+            EdgeLabel::Named("arr".into(), 0),
             EdgeLabel::Deref,
             EdgeLabel::Length,
         ])
@@ -1045,7 +1639,7 @@ fn length_hint_on_heap() {
     // :array "ptr" {
     //   length: --len;
     // }
-    let hints = CascadeStyle::from(Stylesheet(vec![
+    let hints = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::from_path(
                 [
@@ -1065,8 +1659,7 @@ fn length_hint_on_heap() {
             properties: vec![StyleClause {
                 key: StyleKey::Variable("--len".to_owned()),
                 value: Expression::Select(
-                    LimitedSelector::from_path([EdgeLabel::Named("len".to_owned(), 0).into()])
-                        .into(),
+                    LimitedSelector::from_path([EdgeLabel::Named("len".into(), 0).into()]).into(),
                 ),
             }],
         },
@@ -1083,7 +1676,7 @@ fn length_hint_on_heap() {
                         BinaryOperator::Eq,
                         Expression::String("array".to_owned()).into(),
                     )),
-                    SelectorSegment::Match(EdgeMatcher::Named("ptr".to_owned())),
+                    SelectorSegment::Match(EdgeMatcher::Named("ptr".into())),
                 ]
                 .into(),
             ),
@@ -1116,18 +1709,18 @@ fn length_hint_on_heap() {
     let array = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("a".to_owned(), 0),
+            EdgeLabel::Named("a".into(), 0),
             EdgeLabel::Deref,
-            EdgeLabel::Named("ptr".to_owned(), 0),
+            EdgeLabel::Named("ptr".into(), 0),
             EdgeLabel::Deref,
         ])
         .unwrap();
     let length = state_graph
         .get_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("a".to_owned(), 0),
+            EdgeLabel::Named("a".into(), 0),
             EdgeLabel::Deref,
-            EdgeLabel::Named("ptr".to_owned(), 0),
+            EdgeLabel::Named("ptr".into(), 0),
             EdgeLabel::Deref,
             EdgeLabel::Length,
         ])
@@ -1144,14 +1737,13 @@ fn length_hint_across_heap_boundary() {
     // main "a" .alt(, ref []) {
     //   length: --size;
     // }
-    let hints = CascadeStyle::from(Stylesheet(vec![
+    let hints = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
             properties: vec![StyleClause {
                 key: StyleKey::Variable("--size".to_owned()),
                 value: Expression::Select(
-                    LimitedSelector::from_path([EdgeLabel::Named("size".to_owned(), 0).into()])
-                        .into(),
+                    LimitedSelector::from_path([EdgeLabel::Named("size".into(), 0).into()]).into(),
                 ),
             }],
         },
@@ -1159,7 +1751,7 @@ fn length_hint_across_heap_boundary() {
             selector: Selector::from_path(
                 [
                     SelectorSegment::Match(EdgeLabel::Main.into()),
-                    SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
+                    SelectorSegment::Match(EdgeMatcher::Named("a".into())),
                     SelectorSegment::Branch(vec![
                         [].into(),
                         [
@@ -1196,7 +1788,7 @@ fn length_hint_across_heap_boundary() {
     let array_id = state_graph
         .get_id_at_root(&[
             EdgeLabel::Main,
-            EdgeLabel::Named("a".to_owned(), 0),
+            EdgeLabel::Named("a".into(), 0),
             EdgeLabel::Deref,
         ])
         .unwrap();
@@ -1218,3 +1810,521 @@ fn length_hint_across_heap_boundary() {
         assert!(inner_length.value() == Some(NodeValue::Uint(3)));
     }
 }
+
+#[test]
+fn unreachable_heap_blocks() {
+    let mut gdb = gdb_from_source(
+        r"
+        #include <stdint.h>
+        #include <stdlib.h>
+
+        int main(void) {
+            int* kept = (int*)malloc(16);
+            int* lost = (int*)malloc(64);
+            uintptr_t lost_address = (uintptr_t)lost;
+            lost = 0;
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(10).unwrap();
+    let options = GraphOptions::new().with_heap_blocks();
+    let state_graph = GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+        .expect_ready()
+        .unwrap();
+    let value_at = |name: &str| {
+        state_graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named(name.into(), 0)])
+            .unwrap()
+            .value()
+            .unwrap()
+    };
+    let kept_address = value_at("kept");
+    let lost_address = value_at("lost_address");
+    let heap_edge = EdgeLabel::Custom(HEAP_EDGE_LABEL.to_owned());
+    let heap = state_graph
+        .get_at_root(std::slice::from_ref(&heap_edge))
+        .unwrap();
+    assert_eq!(heap.node_type_class(), NodeTypeClass::Array);
+    let Some(NodeValue::Uint(length)) = state_graph
+        .get_at_root(&[heap_edge.clone(), EdgeLabel::Length])
+        .unwrap()
+        .value()
+    else {
+        panic!("Heap length should be an unsigned integer");
+    };
+    let block_addresses = (0..length as usize)
+        .map(|i| {
+            state_graph
+                .get_at_root(&[
+                    heap_edge.clone(),
+                    EdgeLabel::Index(i),
+                    EdgeLabel::Named("address".into(), 0),
+                ])
+                .unwrap()
+                .value()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert!(block_addresses.contains(&lost_address));
+    assert!(!block_addresses.contains(&kept_address));
+}
+
+#[test]
+fn freed_heap_blocks_are_not_listed() {
+    let mut gdb = gdb_from_source(
+        r"
+        #include <stdint.h>
+        #include <stdlib.h>
+
+        int main(void) {
+            int* kept = (int*)malloc(16);
+            int* freed = (int*)malloc(16);
+            uintptr_t freed_address = (uintptr_t)freed;
+            free(freed);
+            freed = 0;
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(11).unwrap();
+    let options = GraphOptions::new().with_heap_blocks();
+    let state_graph = GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+        .expect_ready()
+        .unwrap();
+    let address_of = |name: &str| {
+        let Some(NodeValue::Uint(address)) = state_graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named(name.into(), 0)])
+            .unwrap()
+            .value()
+        else {
+            panic!("Address should be an unsigned integer");
+        };
+        address
+    };
+    let kept_address = address_of("kept");
+    let freed_address = address_of("freed_address");
+    let heap_edge = EdgeLabel::Custom(HEAP_EDGE_LABEL.to_owned());
+    let Some(NodeValue::Uint(length)) = state_graph
+        .get_at_root(&[heap_edge.clone(), EdgeLabel::Length])
+        .unwrap()
+        .value()
+    else {
+        panic!("Heap length should be an unsigned integer");
+    };
+    let block_addresses = (0..length as usize)
+        .map(|i| {
+            let Some(NodeValue::Uint(address)) = state_graph
+                .get_at_root(&[
+                    heap_edge.clone(),
+                    EdgeLabel::Index(i),
+                    EdgeLabel::Named("address".into(), 0),
+                ])
+                .unwrap()
+                .value()
+            else {
+                panic!("Block address should be an unsigned integer");
+            };
+            address
+        })
+        .collect::<Vec<_>>();
+    // The freed block went into the tcache, whose own header
+    // is the only block allocated before the kept block
+    assert!(!block_addresses.contains(&freed_address));
+    assert!(
+        block_addresses
+            .iter()
+            .all(|address| *address > kept_address)
+    );
+}
+
+#[test]
+fn stacks_of_all_threads() {
+    let mut gdb = gdb_from_source(
+        r"
+        #include <pthread.h>
+        volatile int ready = 0;
+        void* worker(void* arg) {
+            int worker_local = 7;
+            ready = 1;
+            for (;;) {}
+            return arg;
+        }
+        int main(void) {
+            pthread_t thread;
+            pthread_create(&thread, 0, worker, 0);
+            while (!ready) {}
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(14).unwrap();
+    let options = GraphOptions::new().with_threads();
+    let state_graph = GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+        .expect_ready()
+        .unwrap();
+    // The selected thread is still reachable as usual
+    let main = state_graph.get_at_root(&[EdgeLabel::Main]).unwrap();
+    assert_eq!(main.node_type_id(), Some("main"));
+    let threads_edge = EdgeLabel::Custom(THREADS_EDGE_LABEL.to_owned());
+    let Some(NodeValue::Uint(thread_count)) = state_graph
+        .get_at_root(&[threads_edge.clone(), EdgeLabel::Length])
+        .unwrap()
+        .value()
+    else {
+        panic!("Number of threads should be an unsigned integer");
+    };
+    assert_eq!(thread_count, 2);
+    // Find the frame of the worker thread,
+    // which is preceded by frames of the threading library
+    let worker_frame = (0..thread_count as usize)
+        .find_map(|i| {
+            let mut frame = state_graph
+                .get(&state_graph.root())
+                .unwrap()
+                .get_successor(&threads_edge)
+                .and_then(|threads| {
+                    state_graph
+                        .get(&threads)?
+                        .get_successor(&EdgeLabel::Index(i))
+                });
+            while let Some(id) = frame {
+                let node = state_graph.get(&id).unwrap();
+                if node.node_type_id() == Some("worker") {
+                    return Some(id);
+                }
+                frame = node.get_successor(&EdgeLabel::Next);
+            }
+            None
+        })
+        .expect("Worker thread should be listed");
+    let worker_local = state_graph
+        .get_at(&worker_frame, &[EdgeLabel::Named("worker_local".into(), 0)])
+        .unwrap();
+    assert_eq!(worker_local.value(), Some(NodeValue::Int(7)));
+}
+
+#[test]
+fn source_locations_of_frames() {
+    let mut gdb = gdb_from_source(
+        r"
+        void f(void) {
+            /* breakpoint */;
+        }
+        int main(void) {
+            f();
+        }",
+    );
+    gdb.run_to_line(3).unwrap();
+    let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
+    let main_location = state_graph
+        .get_at_root(&[EdgeLabel::Main])
+        .unwrap()
+        .source_location()
+        .unwrap();
+    assert!(main_location.file.ends_with(".c"));
+    assert_eq!(main_location.line, 6);
+    let f_location = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Next])
+        .unwrap()
+        .source_location()
+        .unwrap();
+    assert_eq!(f_location.file, main_location.file);
+    assert_eq!(f_location.line, 3);
+}
+
+#[test]
+fn registers_of_top_frame() {
+    let mut gdb = gdb_from_source(
+        r"
+        void f(void) {
+            /* breakpoint */;
+        }
+        int main(void) {
+            f();
+        }",
+    );
+    gdb.run_to_line(3).unwrap();
+    // Program counter is called differently on different architectures
+    let options = GraphOptions::new().with_registers(GlobFilter::new(["rip", "pc"]));
+    let state_graph = GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+        .expect_ready()
+        .unwrap();
+    let registers_edge = EdgeLabel::Custom(REGISTERS_EDGE_LABEL.to_owned());
+    let main = state_graph.get_at_root(&[EdgeLabel::Main]).unwrap();
+    assert_eq!(main.get_successor(&registers_edge), None);
+    let registers = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Next, registers_edge])
+        .unwrap();
+    assert_eq!(registers.node_type_class(), NodeTypeClass::Struct);
+    let program_counters = registers.successors().collect::<Vec<_>>();
+    assert_eq!(program_counters.len(), 1);
+    let program_counter = state_graph.get(&program_counters[0].1).unwrap();
+    assert!(matches!(program_counter.value(), Some(NodeValue::Uint(pc)) if pc != 0));
+}
+
+#[test]
+fn watch_expressions() {
+    let mut gdb = gdb_from_source(
+        r"
+        int main(void) {
+            int a[4] = { 1, 2, 3, 4 };
+            int x = 5;
+            /* breakpoint */;
+            x = a[2] * 10;
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(5).unwrap();
+    let options = GraphOptions::new()
+        .with_watch_expression("sizeof(a)")
+        .with_watch_expression("x + a[1]")
+        .with_watch_expression("nonexistent");
+    let mut state_graph =
+        GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+            .expect_ready()
+            .unwrap();
+    let watch_edge = EdgeLabel::Custom(WATCH_EDGE_LABEL.to_owned());
+    let watch_value = |state_graph: &GdbStateGraph, expression: &str| {
+        state_graph
+            .get_at_root(&[watch_edge.clone(), EdgeLabel::Named(expression.into(), 0)])
+            .and_then(|node| node.value())
+    };
+    let watch = state_graph
+        .get_at_root(std::slice::from_ref(&watch_edge))
+        .unwrap();
+    assert_eq!(watch.node_type_class(), NodeTypeClass::Struct);
+    assert_eq!(watch.successors().count(), 2);
+    assert_eq!(
+        watch_value(&state_graph, "sizeof(a)"),
+        Some(NodeValue::Uint(16))
+    );
+    assert_eq!(
+        watch_value(&state_graph, "x + a[1]"),
+        Some(NodeValue::Uint(7))
+    );
+    gdb.run_to_line(7).unwrap();
+    state_graph.remove_watch_expression("sizeof(a)");
+    state_graph.add_watch_expression("x");
+    state_graph.update(&mut gdb).expect_ready().unwrap();
+    assert_eq!(watch_value(&state_graph, "sizeof(a)"), None);
+    assert_eq!(
+        watch_value(&state_graph, "x + a[1]"),
+        Some(NodeValue::Uint(32))
+    );
+    assert_eq!(watch_value(&state_graph, "x"), Some(NodeValue::Uint(30)));
+}
+
+#[test]
+fn function_result() {
+    let mut gdb = gdb_from_source(
+        r"
+        int square(int x) {
+            return x * x;
+        }
+        int main(void) {
+            int y = square(7);
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(3).unwrap();
+    let mut state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
+    assert!(
+        state_graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Result])
+            .is_none()
+    );
+    let reason = DebugController::new(&mut gdb)
+        .finish()
+        .expect_ready()
+        .unwrap();
+    assert!(matches!(reason, StopReason::FunctionFinished { .. }));
+    state_graph.report_stop(&reason);
+    state_graph.update(&mut gdb).expect_ready().unwrap();
+    let result = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Result])
+        .unwrap();
+    assert_eq!(result.value(), Some(NodeValue::Uint(49)));
+    // The value is only shown right after the function has returned
+    gdb.run_to_line(7).unwrap();
+    state_graph.update(&mut gdb).expect_ready().unwrap();
+    assert!(
+        state_graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Result])
+            .is_none()
+    );
+}
+
+#[test]
+fn nodes_by_address() {
+    let mut gdb = gdb_from_source(
+        r"
+        int main(void) {
+            int x = 42;
+            int* p = &x;
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(5).unwrap();
+    let state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
+    let x_id = state_graph
+        .get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("x".into(), 0)])
+        .unwrap();
+    let Some(NodeValue::Uint(p_value)) = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("p".into(), 0)])
+        .unwrap()
+        .value()
+    else {
+        panic!("Pointer value should be an unsigned integer");
+    };
+    assert_eq!(state_graph.address_of(&x_id), Some(p_value));
+    assert_eq!(state_graph.node_at_address(p_value), Some(x_id));
+    assert_eq!(state_graph.address_of(&state_graph.root()), None);
+    assert_eq!(state_graph.node_at_address(0), None);
+}
+
+#[test]
+fn deref_depth_limit() {
+    let mut gdb = gdb_from_source(
+        r"
+        #include <stdlib.h>
+
+        struct node {
+            struct node* next;
+        };
+
+        int main(void) {
+            struct node* head = 0;
+            for (int i = 0; i < 3; ++i) {
+                struct node* n = (struct node*)malloc(sizeof(*n));
+                n->next = head;
+                head = n;
+            }
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(15).unwrap();
+    let options = GraphOptions::new().with_limits(GraphLimits::new().with_max_deref_depth(2));
+    let state_graph = GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+        .expect_ready()
+        .unwrap();
+    let next = EdgeLabel::Named("next".into(), 0);
+    let second = [
+        EdgeLabel::Main,
+        EdgeLabel::Named("head".into(), 0),
+        EdgeLabel::Deref,
+        next.clone(),
+        EdgeLabel::Deref,
+    ];
+    assert!(state_graph.get_at_root(&second).is_some());
+    assert!(
+        state_graph
+            .get_at_root(second.iter().chain(&[next.clone(), EdgeLabel::Deref]))
+            .is_none()
+    );
+    let marker = state_graph
+        .get_at_root(second.iter().chain(&[
+            next.clone(),
+            EdgeLabel::Custom(TRUNCATED_EDGE_LABEL.to_owned()),
+        ]))
+        .unwrap();
+    assert_eq!(marker.value(), Some(NodeValue::Bool(true)));
+}
+
+#[test]
+fn graph_stats() {
+    let mut gdb = gdb_from_source(
+        r"
+        #include <stdlib.h>
+
+        int main(void) {
+            int x = 1;
+            int *p = &x;
+            int *q = (int*)malloc(sizeof(int));
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(8).unwrap();
+    let options = GraphOptions::new().with_limits(GraphLimits::new().with_max_deref_depth(0));
+    let state_graph = GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+        .expect_ready()
+        .unwrap();
+    let stats = state_graph.stats();
+    assert_eq!(stats.nodes[&NodeTypeClass::Frame], 1);
+    assert_eq!(stats.nodes[&NodeTypeClass::Ref], 2);
+    // One pointer leads to its target, the other is truncated
+    assert_eq!(stats.edges[&NodeTypeClass::Ref], 2);
+    assert_eq!(stats.deferred_dereferences, 1);
+    assert_eq!(stats.heap_bytes, 0);
+    assert_eq!(stats.node_count(), state_graph.snapshot().node_count());
+}
+
+#[test]
+fn array_element_limit() {
+    let mut gdb = gdb_from_source(
+        r"
+        int main(void) {
+            int data[100] = { 0 };
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(4).unwrap();
+    let options = GraphOptions::new().with_limits(GraphLimits::new().with_max_array_elements(10));
+    let state_graph = GdbStateGraph::new_with_options(&mut gdb, &CascadeStyle::empty(), options)
+        .expect_ready()
+        .unwrap();
+    let data_id = state_graph
+        .get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("data".into(), 0)])
+        .unwrap();
+    let length = state_graph.get_at(&data_id, &[EdgeLabel::Length]).unwrap();
+    assert_eq!(length.value(), Some(NodeValue::Uint(100)));
+    assert!(
+        state_graph
+            .get_at(&data_id, &[EdgeLabel::Index(9)])
+            .is_some()
+    );
+    assert!(
+        state_graph
+            .get_at(&data_id, &[EdgeLabel::Index(10)])
+            .is_none()
+    );
+    let marker = state_graph
+        .get_at(
+            &data_id,
+            &[EdgeLabel::Custom(TRUNCATED_EDGE_LABEL.to_owned())],
+        )
+        .unwrap();
+    assert_eq!(marker.value(), Some(NodeValue::Bool(true)));
+}
+
+#[test]
+fn cancelled_update_is_rebuilt() {
+    let mut gdb = gdb_from_source(
+        r"
+        int main(void) {
+            int x = 1;
+            /* breakpoint */;
+            x = 2;
+            /* breakpoint */;
+        }",
+    );
+    gdb.run_to_line(4).unwrap();
+    let cancelled = CancellationToken::new();
+    cancelled.cancel();
+    let construction = GdbStateGraph::new_cancellable(
+        &mut gdb,
+        &CascadeStyle::empty(),
+        GraphOptions::default(),
+        &cancelled,
+    )
+    .expect_ready();
+    assert!(matches!(construction, Err(Error::Cancelled)));
+    let mut state_graph = GdbStateGraph::new(&mut gdb).expect_ready().unwrap();
+    gdb.run_to_line(6).unwrap();
+    let update = state_graph
+        .update_cancellable(&mut gdb, &CascadeStyle::empty(), &cancelled)
+        .expect_ready();
+    assert!(matches!(update, Err(Error::Cancelled)));
+    state_graph.update(&mut gdb).expect_ready().unwrap();
+    let x = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("x".into(), 0)])
+        .unwrap();
+    assert_eq!(x.value(), Some(NodeValue::Int(2)));
+}
@@ -0,0 +1,52 @@
+//! Assertions on the numbers of commands sent to GDB.
+
+#![allow(dead_code, reason = "Not every test suite counts commands")]
+
+use super::{gdb_from_source, gdbmi::TestGdbMi};
+use aili_gdbstate::gdbmi::counting::{CommandCounts, CountingStream};
+
+/// Compiles a C source and starts a GDB session targeting
+/// the compiled executable that counts the commands it receives.
+pub fn counting_gdb_from_source(source: &str) -> CountingStream<TestGdbMi> {
+    CountingStream::new(gdb_from_source(source))
+}
+
+/// Asserts that at most a given number of commands has been sent.
+#[track_caller]
+pub fn assert_command_budget(counts: &CommandCounts, budget: usize) {
+    assert!(
+        counts.total() <= budget,
+        "{} commands were sent, at most {budget} expected: {counts:?}",
+        counts.total(),
+    );
+}
+
+/// Asserts that two runs of a scenario of different sizes
+/// have sent the same numbers of commands.
+#[track_caller]
+pub fn assert_constant_commands(small: &CommandCounts, large: &CommandCounts) {
+    assert_eq!(
+        small, large,
+        "number of commands depends on the size of the scenario"
+    );
+}
+
+/// Asserts that the number of commands grows at most linearly
+/// with the size of a scenario.
+///
+/// `small` and `large` are the commands sent by two runs of a scenario,
+/// the larger of which has `extra_items` more items than the smaller.
+#[track_caller]
+pub fn assert_linear_commands(
+    small: &CommandCounts,
+    large: &CommandCounts,
+    extra_items: usize,
+    budget_per_item: usize,
+) {
+    let extra_commands = large.total().saturating_sub(small.total());
+    assert!(
+        extra_commands <= extra_items * budget_per_item,
+        "{extra_commands} commands were sent for {extra_items} extra items, \
+        at most {budget_per_item} per item expected: {small:?} vs. {large:?}",
+    );
+}
@@ -1,14 +1,16 @@
 //! Testing implementation of [`GdbMiStream`] that communicates
 //! with the debugger synchronously.
 
-use super::externals::gdb_path;
+use super::{externals::gdb_path, future::ExpectReady as _};
 use aili_gdbstate::gdbmi::{
     grammar::parse_gdbmi_record,
     raw_output::{Record, ResultRecord},
     result::{BadResponse, Result},
-    stream::GdbMiStream,
+    session::DebugController,
+    stream::{GdbMiEventSource, GdbMiStream, order_by_token},
 };
 use std::{
+    collections::VecDeque,
     io::{BufRead, BufReader, Write},
     process::{Child, ChildStdin, ChildStdout, Command, Stdio},
 };
@@ -16,6 +18,7 @@ use std::{
 pub struct TestGdbMi {
     stdin: ChildStdin,
     stdout: BufReader<ChildStdout>,
+    async_lines: VecDeque<String>,
 }
 
 impl TestGdbMi {
@@ -43,6 +46,7 @@ impl TestGdbMi {
         Ok(Self {
             stdin,
             stdout: BufReader::new(stdout),
+            async_lines: VecDeque::new(),
         })
     }
 
@@ -96,10 +100,24 @@ impl TestGdbMi {
     }
 
     pub fn run_to_line(&mut self, line: usize) -> Result<()> {
+        DebugController::new(self)
+            .run_to_line(line)
+            .expect_ready()?;
+        Ok(())
+    }
+
+    pub fn start_recording(&mut self) -> Result<()> {
+        self.send_command("-interpreter-exec console record")?;
+        self.read_output_section_with_result()?
+            .must_be_done_or_running()?;
+        Ok(())
+    }
+
+    pub fn reverse_to_line(&mut self, line: usize) -> Result<()> {
         self.send_command_fmt(format_args!("-break-insert -t {line}"))?;
         self.read_output_section_with_result()?
             .must_be_done_or_running()?;
-        self.send_command("-exec-continue")?;
+        self.send_command("-exec-continue --reverse")?;
         self.read_output_section_with_result()?
             .must_be_done_or_running()?; // GDB will tell us it ran
         self.read_output_section()?; // This output should be generated when it stops
@@ -118,6 +136,51 @@ impl GdbMiStream for TestGdbMi {
         TestGdbMi::send_command_fmt(self, args)?;
         self.read_output_section_with_result()
     }
+    async fn send_commands(&mut self, commands: &[String]) -> Vec<Result<ResultRecord>> {
+        // Write all commands before reading any response,
+        // so GDB can process them back to back
+        let tokens = (0..commands.len())
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        for (token, command) in tokens.iter().zip(commands) {
+            if let Err(error) = TestGdbMi::send_command_fmt(self, format_args!("{token}{command}"))
+            {
+                return commands
+                    .iter()
+                    .map(|_| Err(std::io::Error::from(error.kind()).into()))
+                    .collect();
+            }
+        }
+        let mut responses = Vec::new();
+        for _ in commands {
+            match self.read_output_section_with_result() {
+                Ok(response) => responses.push(response),
+                Err(_) => break,
+            }
+        }
+        order_by_token(tokens.iter().map(String::as_str), responses)
+    }
+}
+
+impl GdbMiEventSource for TestGdbMi {
+    async fn next_line(&mut self) -> Option<std::io::Result<String>> {
+        // Read whole output sections, so the next command
+        // does not see the end of a section that has been read from
+        while self.async_lines.is_empty() {
+            loop {
+                let line = match self.read_output_line() {
+                    Ok(line) if line.is_empty() => return None,
+                    Ok(line) => line,
+                    Err(error) => return Some(Err(error)),
+                };
+                if line.trim() == Self::OUTPUT_SECTION_END {
+                    break;
+                }
+                self.async_lines.push_back(line);
+            }
+        }
+        self.async_lines.pop_front().map(Ok)
+    }
 }
 
 impl Drop for TestGdbMi {
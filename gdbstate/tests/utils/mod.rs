@@ -1,5 +1,6 @@
 //! Common utilities for use in integration tests.
 
+pub mod commands;
 pub mod externals;
 pub mod future;
 pub mod gdbmi;
@@ -0,0 +1,181 @@
+//! Syntax tree of the language.
+
+use std::{collections::HashMap, rc::Rc};
+
+/// Parsed program.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Program {
+    /// Functions declared by the program, by name.
+    pub functions: HashMap<String, Rc<Function>>,
+
+    /// Statements at the top level of the program.
+    pub main: Rc<[Statement]>,
+}
+
+/// Function declared by a program.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Function {
+    /// Name of the function.
+    pub name: String,
+
+    /// Names of the function's parameters.
+    pub parameters: Vec<String>,
+
+    /// Statements that make up the function's body.
+    pub body: Rc<[Statement]>,
+}
+
+/// Statement, annotated with its location in the source.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Statement {
+    /// Line number where the statement starts, starting from 1.
+    pub line: usize,
+
+    /// The statement itself.
+    pub kind: StatementKind,
+}
+
+/// Kinds of statements.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum StatementKind {
+    /// Declaration of a variable, `let name = value;`.
+    ///
+    /// Declaring a variable that already exists overwrites it.
+    Let(String, Rhs),
+
+    /// Assignment to an existing variable, item, or field,
+    /// `place = value;`.
+    ///
+    /// The place is always a [`Variable`](Expression::Variable),
+    /// [`Index`](Expression::Index), or [`Field`](Expression::Field) expression.
+    Assign(Expression, Rhs),
+
+    /// Function call whose result is discarded, `f(args);`.
+    Call(Call),
+
+    /// Conditional statement, `if cond { ... } else { ... }`.
+    If(Expression, Rc<[Statement]>, Rc<[Statement]>),
+
+    /// Loop, `while cond { ... }`.
+    While(Rc<Expression>, Rc<[Statement]>),
+
+    /// Return from a function, `return value;`.
+    Return(Option<Rhs>),
+}
+
+/// Right-hand side of a statement.
+///
+/// Function calls may only appear as an entire right-hand side,
+/// not nested in other expressions. This way, execution can be
+/// paused between any two statements of any function.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Rhs {
+    /// Plain expression.
+    Expression(Expression),
+
+    /// Function call.
+    Call(Call),
+}
+
+/// Function call, `f(args)`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Call {
+    /// Name of the called function.
+    pub function: String,
+
+    /// Expressions that evaluate to the arguments.
+    pub arguments: Vec<Expression>,
+}
+
+/// Expressions that evaluate to a value without side effects,
+/// other than allocation of new objects.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Expression {
+    /// Integer literal.
+    Int(i64),
+
+    /// Boolean literal, `true` or `false`.
+    Bool(bool),
+
+    /// The empty value, `none`.
+    None,
+
+    /// Value of a variable.
+    Variable(String),
+
+    /// Allocates a new array, `[a, b, c]`.
+    Array(Vec<Expression>),
+
+    /// Allocates a new record, `{ x: a, y: b }`.
+    Record(Vec<(String, Expression)>),
+
+    /// Item of an array, `a[i]`.
+    Index(Box<Expression>, Box<Expression>),
+
+    /// Field of a record, `a.x`.
+    Field(Box<Expression>, String),
+
+    /// Length of an array, `len(a)`.
+    Length(Box<Expression>),
+
+    /// Unary operator.
+    Unary(UnaryOperator, Box<Expression>),
+
+    /// Binary operator.
+    Binary(Box<Expression>, BinaryOperator, Box<Expression>),
+}
+
+/// Unary operators.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnaryOperator {
+    /// Arithmetic negation, `-a`.
+    Minus,
+
+    /// Logical negation, `!a`.
+    Not,
+}
+
+/// Binary operators.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BinaryOperator {
+    /// Addition, `a + b`.
+    Plus,
+
+    /// Subtraction, `a - b`.
+    Minus,
+
+    /// Multiplication, `a * b`.
+    Mul,
+
+    /// Integer division, `a / b`.
+    Div,
+
+    /// Remainder after integer division, `a % b`.
+    Mod,
+
+    /// Equality, `a == b`.
+    ///
+    /// Arrays and records are compared by identity.
+    Eq,
+
+    /// Inequality, `a != b`.
+    Ne,
+
+    /// Less than, `a < b`.
+    Lt,
+
+    /// Less than or equal, `a <= b`.
+    Le,
+
+    /// Greater than, `a > b`.
+    Gt,
+
+    /// Greater than or equal, `a >= b`.
+    Ge,
+
+    /// Short-circuiting conjunction, `a && b`.
+    And,
+
+    /// Short-circuiting disjunction, `a || b`.
+    Or,
+}
@@ -0,0 +1,526 @@
+//! Step-by-step execution of programs.
+
+use crate::ast::*;
+use derive_more::{Display, Error};
+use std::rc::Rc;
+
+/// Value stored in a variable, array item, or record field.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Value {
+    /// The empty value.
+    #[default]
+    None,
+
+    /// Integer.
+    Int(i64),
+
+    /// Boolean.
+    Bool(bool),
+
+    /// Reference to an object on the heap, identified by its index.
+    Ref(usize),
+}
+
+/// Object allocated on the heap.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Object {
+    /// Array of values.
+    Array(Vec<Value>),
+
+    /// Record with named fields.
+    Record(Vec<(String, Value)>),
+}
+
+/// Error encountered while executing a program.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[error(ignore)]
+pub enum RuntimeError {
+    /// A variable was used before being declared.
+    #[display("variable {_0:?} is not declared")]
+    UndefinedVariable(String),
+
+    /// A function that does not exist was called.
+    #[display("function {_0:?} is not declared")]
+    UndefinedFunction(String),
+
+    /// A function was called with the wrong number of arguments.
+    #[display("function {_0:?} expects {_1} arguments, but {_2} were provided")]
+    ArityMismatch(String, usize, usize),
+
+    /// An operation was applied to a value of the wrong type.
+    #[display("type mismatch: expected {_0}")]
+    TypeMismatch(&'static str),
+
+    /// An array was indexed outside of its bounds.
+    #[display("index {_0} is out of bounds for length {_1}")]
+    IndexOutOfBounds(i64, usize),
+
+    /// A record does not have the requested field.
+    #[display("record does not have field {_0:?}")]
+    MissingField(String),
+
+    /// Integer division by zero.
+    #[display("division by zero")]
+    DivisionByZero,
+
+    /// Result of integer arithmetic does not fit into 64 bits.
+    #[display("arithmetic overflow")]
+    Overflow,
+
+    /// Too many nested function calls.
+    #[display("maximum call depth of {_0} exceeded")]
+    StackOverflow(usize),
+}
+
+/// Executes a [`Program`] one statement at a time.
+///
+/// Between steps, the interpreter's state can be inspected
+/// as a [`ProgramStateGraph`](aili_model::state::ProgramStateGraph).
+#[derive(Debug)]
+pub struct Interpreter {
+    program: Program,
+    pub(crate) frames: Vec<Frame>,
+    pub(crate) heap: Vec<Object>,
+}
+
+/// Activation record of a function call.
+#[derive(Debug)]
+pub(crate) struct Frame {
+    /// The called function, or [`None`] for the top level of the program.
+    pub(crate) function: Option<Rc<Function>>,
+    pub(crate) variables: Vec<(String, Value)>,
+    blocks: Vec<Block>,
+    return_target: ReturnTarget,
+}
+
+/// Sequence of statements that is being executed.
+#[derive(Debug)]
+struct Block {
+    statements: Rc<[Statement]>,
+    position: usize,
+    /// Condition of the loop, if the block is a loop body.
+    condition: Option<LoopCondition>,
+}
+
+/// Condition that is checked at the end of each iteration of a loop.
+#[derive(Clone, Debug)]
+struct LoopCondition {
+    expression: Rc<Expression>,
+    /// Line of the loop statement.
+    line: usize,
+}
+
+/// Describes what should happen to the value returned by a function.
+#[derive(Debug)]
+enum ReturnTarget {
+    Discard,
+    Define(String),
+    Assign(Expression),
+    Return,
+}
+
+impl Interpreter {
+    /// Maximum number of nested function calls.
+    pub const MAX_CALL_DEPTH: usize = 1000;
+
+    /// Prepares a program for execution.
+    ///
+    /// The interpreter is paused before the first statement.
+    pub fn new(program: Program) -> Self {
+        let main_frame = Frame {
+            function: None,
+            variables: Vec::new(),
+            blocks: vec![Block {
+                statements: program.main.clone(),
+                position: 0,
+                condition: None,
+            }],
+            return_target: ReturnTarget::Discard,
+        };
+        let mut interpreter = Self {
+            program,
+            frames: vec![main_frame],
+            heap: Vec::new(),
+        };
+        interpreter
+            .unwind()
+            .expect("Unwinding an unstarted program cannot fail");
+        interpreter
+    }
+
+    /// Checks whether the program has run to completion.
+    pub fn is_finished(&self) -> bool {
+        self.frames.len() == 1 && self.frames[0].blocks.is_empty()
+    }
+
+    /// Gets the line number of the statement that will be executed next.
+    ///
+    /// If the condition of a loop failed to evaluate,
+    /// this is the line of the loop, since the condition
+    /// is evaluated again by the next step.
+    pub fn current_line(&self) -> Option<usize> {
+        let block = self.frames.last()?.blocks.last()?;
+        match block.statements.get(block.position) {
+            Some(statement) => Some(statement.line),
+            None => block.condition.as_ref().map(|condition| condition.line),
+        }
+    }
+
+    /// Executes a single statement.
+    ///
+    /// Returns `false` if the program had already finished,
+    /// so no statement was executed.
+    pub fn step(&mut self) -> Result<bool, RuntimeError> {
+        // Unwinding only fails if the previous step failed
+        // to evaluate a loop condition, so that is retried here
+        self.unwind()?;
+        let Some(block) = self.frames.last_mut().and_then(|f| f.blocks.last_mut()) else {
+            return Ok(false);
+        };
+        let statements = block.statements.clone();
+        let Some(statement) = statements.get(block.position) else {
+            return Ok(false);
+        };
+        block.position += 1;
+        self.execute(statement)?;
+        self.unwind()?;
+        Ok(true)
+    }
+
+    /// Executes the program until it finishes.
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        while self.step()? {}
+        Ok(())
+    }
+
+    /// Executes the program until the next statement to be executed
+    /// is on a given line, or until the program finishes.
+    ///
+    /// At least one statement is executed if the program has not finished.
+    /// Returns `true` if the program stopped at the line.
+    pub fn run_to_line(&mut self, line: usize) -> Result<bool, RuntimeError> {
+        while self.step()? {
+            if self.current_line() == Some(line) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Leaves all blocks and function calls that have been completed,
+    /// so that the interpreter is ready to execute the next statement.
+    ///
+    /// If a loop condition fails to evaluate, the loop body is left
+    /// at its end, so unwinding can be retried.
+    fn unwind(&mut self) -> Result<(), RuntimeError> {
+        loop {
+            let frame = self.current_frame_mut();
+            if let Some(block) = frame.blocks.last_mut() {
+                if block.position < block.statements.len() {
+                    return Ok(());
+                }
+                if let Some(condition) = block.condition.clone()
+                    && self.evaluate_bool(&condition.expression)?
+                {
+                    self.current_frame_mut().blocks.last_mut().unwrap().position = 0;
+                    continue;
+                }
+                self.current_frame_mut().blocks.pop();
+            } else if self.frames.len() > 1 {
+                // End of a function without an explicit return
+                self.return_from_frame(Value::None)?;
+            } else {
+                // End of the program
+                return Ok(());
+            }
+        }
+    }
+
+    fn execute(&mut self, statement: &Statement) -> Result<(), RuntimeError> {
+        match &statement.kind {
+            StatementKind::Let(name, Rhs::Expression(value)) => {
+                let value = self.evaluate(value)?;
+                self.define_variable(name, value);
+            }
+            StatementKind::Let(name, Rhs::Call(call)) => {
+                self.call(call, ReturnTarget::Define(name.clone()))?;
+            }
+            StatementKind::Assign(place, Rhs::Expression(value)) => {
+                let value = self.evaluate(value)?;
+                self.assign(place, value)?;
+            }
+            StatementKind::Assign(place, Rhs::Call(call)) => {
+                self.call(call, ReturnTarget::Assign(place.clone()))?;
+            }
+            StatementKind::Call(call) => {
+                self.call(call, ReturnTarget::Discard)?;
+            }
+            StatementKind::If(condition, then_branch, else_branch) => {
+                let branch = if self.evaluate_bool(condition)? {
+                    then_branch
+                } else {
+                    else_branch
+                };
+                self.current_frame_mut().blocks.push(Block {
+                    statements: branch.clone(),
+                    position: 0,
+                    condition: None,
+                });
+            }
+            StatementKind::While(condition, body) => {
+                if self.evaluate_bool(condition)? {
+                    self.current_frame_mut().blocks.push(Block {
+                        statements: body.clone(),
+                        position: 0,
+                        condition: Some(LoopCondition {
+                            expression: condition.clone(),
+                            line: statement.line,
+                        }),
+                    });
+                }
+            }
+            StatementKind::Return(None) => {
+                self.return_from_frame(Value::None)?;
+            }
+            StatementKind::Return(Some(Rhs::Expression(value))) => {
+                let value = self.evaluate(value)?;
+                self.return_from_frame(value)?;
+            }
+            StatementKind::Return(Some(Rhs::Call(call))) => {
+                self.call(call, ReturnTarget::Return)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, call: &Call, return_target: ReturnTarget) -> Result<(), RuntimeError> {
+        let Some(function) = self.program.functions.get(&call.function).cloned() else {
+            return Err(RuntimeError::UndefinedFunction(call.function.clone()));
+        };
+        if function.parameters.len() != call.arguments.len() {
+            return Err(RuntimeError::ArityMismatch(
+                call.function.clone(),
+                function.parameters.len(),
+                call.arguments.len(),
+            ));
+        }
+        if self.frames.len() >= Self::MAX_CALL_DEPTH {
+            return Err(RuntimeError::StackOverflow(Self::MAX_CALL_DEPTH));
+        }
+        let arguments = call
+            .arguments
+            .iter()
+            .map(|a| self.evaluate(a))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.frames.push(Frame {
+            variables: function.parameters.iter().cloned().zip(arguments).collect(),
+            blocks: vec![Block {
+                statements: function.body.clone(),
+                position: 0,
+                condition: None,
+            }],
+            function: Some(function),
+            return_target,
+        });
+        Ok(())
+    }
+
+    fn return_from_frame(&mut self, value: Value) -> Result<(), RuntimeError> {
+        if self.frames.len() == 1 {
+            // Returning from the top level ends the program
+            self.current_frame_mut().blocks.clear();
+            return Ok(());
+        }
+        let frame = self.frames.pop().expect("There should be a frame");
+        match frame.return_target {
+            ReturnTarget::Discard => Ok(()),
+            ReturnTarget::Define(name) => {
+                self.define_variable(&name, value);
+                Ok(())
+            }
+            ReturnTarget::Assign(place) => self.assign(&place, value),
+            ReturnTarget::Return => self.return_from_frame(value),
+        }
+    }
+
+    fn current_frame(&self) -> &Frame {
+        self.frames.last().expect("Main frame should always exist")
+    }
+
+    fn current_frame_mut(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("Main frame should always exist")
+    }
+
+    fn define_variable(&mut self, name: &str, value: Value) {
+        let variables = &mut self.current_frame_mut().variables;
+        if let Some((_, existing)) = variables.iter_mut().find(|(n, _)| n == name) {
+            *existing = value;
+        } else {
+            variables.push((name.to_owned(), value));
+        }
+    }
+
+    fn assign(&mut self, place: &Expression, value: Value) -> Result<(), RuntimeError> {
+        let slot = match place {
+            Expression::Variable(name) => self
+                .current_frame_mut()
+                .variables
+                .iter_mut()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v)
+                .ok_or_else(|| RuntimeError::UndefinedVariable(name.clone()))?,
+            Expression::Index(array, index) => {
+                let array = self.evaluate_ref(array)?;
+                let index = self.evaluate_int(index)?;
+                let Object::Array(items) = &mut self.heap[array] else {
+                    return Err(RuntimeError::TypeMismatch("array"));
+                };
+                let length = items.len();
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|i| items.get_mut(i))
+                    .ok_or(RuntimeError::IndexOutOfBounds(index, length))?
+            }
+            Expression::Field(record, field) => {
+                let record = self.evaluate_ref(record)?;
+                let Object::Record(fields) = &mut self.heap[record] else {
+                    return Err(RuntimeError::TypeMismatch("record"));
+                };
+                fields
+                    .iter_mut()
+                    .find(|(n, _)| n == field)
+                    .map(|(_, v)| v)
+                    .ok_or_else(|| RuntimeError::MissingField(field.clone()))?
+            }
+            _ => unreachable!("Parser only accepts places as assignment targets"),
+        };
+        *slot = value;
+        Ok(())
+    }
+
+    fn evaluate_bool(&mut self, expression: &Expression) -> Result<bool, RuntimeError> {
+        match self.evaluate(expression)? {
+            Value::Bool(b) => Ok(b),
+            _ => Err(RuntimeError::TypeMismatch("bool")),
+        }
+    }
+
+    fn evaluate_int(&mut self, expression: &Expression) -> Result<i64, RuntimeError> {
+        match self.evaluate(expression)? {
+            Value::Int(i) => Ok(i),
+            _ => Err(RuntimeError::TypeMismatch("int")),
+        }
+    }
+
+    fn evaluate_ref(&mut self, expression: &Expression) -> Result<usize, RuntimeError> {
+        match self.evaluate(expression)? {
+            Value::Ref(r) => Ok(r),
+            _ => Err(RuntimeError::TypeMismatch("array or record")),
+        }
+    }
+
+    fn evaluate(&mut self, expression: &Expression) -> Result<Value, RuntimeError> {
+        match expression {
+            Expression::Int(i) => Ok(Value::Int(*i)),
+            Expression::Bool(b) => Ok(Value::Bool(*b)),
+            Expression::None => Ok(Value::None),
+            Expression::Variable(name) => self
+                .current_frame()
+                .variables
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| *v)
+                .ok_or_else(|| RuntimeError::UndefinedVariable(name.clone())),
+            Expression::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|i| self.evaluate(i))
+                    .collect::<Result<_, _>>()?;
+                Ok(self.allocate(Object::Array(items)))
+            }
+            Expression::Record(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(n, e)| Ok((n.clone(), self.evaluate(e)?)))
+                    .collect::<Result<_, _>>()?;
+                Ok(self.allocate(Object::Record(fields)))
+            }
+            Expression::Index(array, index) => {
+                let array = self.evaluate_ref(array)?;
+                let index = self.evaluate_int(index)?;
+                let Object::Array(items) = &self.heap[array] else {
+                    return Err(RuntimeError::TypeMismatch("array"));
+                };
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|i| items.get(i))
+                    .copied()
+                    .ok_or(RuntimeError::IndexOutOfBounds(index, items.len()))
+            }
+            Expression::Field(record, field) => {
+                let record = self.evaluate_ref(record)?;
+                let Object::Record(fields) = &self.heap[record] else {
+                    return Err(RuntimeError::TypeMismatch("record"));
+                };
+                fields
+                    .iter()
+                    .find(|(n, _)| n == field)
+                    .map(|(_, v)| *v)
+                    .ok_or_else(|| RuntimeError::MissingField(field.clone()))
+            }
+            Expression::Length(array) => {
+                let array = self.evaluate_ref(array)?;
+                let Object::Array(items) = &self.heap[array] else {
+                    return Err(RuntimeError::TypeMismatch("array"));
+                };
+                Ok(Value::Int(items.len() as i64))
+            }
+            Expression::Unary(UnaryOperator::Minus, operand) => self
+                .evaluate_int(operand)?
+                .checked_neg()
+                .map(Value::Int)
+                .ok_or(RuntimeError::Overflow),
+            Expression::Unary(UnaryOperator::Not, operand) => {
+                Ok(Value::Bool(!self.evaluate_bool(operand)?))
+            }
+            Expression::Binary(lhs, BinaryOperator::And, rhs) => Ok(Value::Bool(
+                self.evaluate_bool(lhs)? && self.evaluate_bool(rhs)?,
+            )),
+            Expression::Binary(lhs, BinaryOperator::Or, rhs) => Ok(Value::Bool(
+                self.evaluate_bool(lhs)? || self.evaluate_bool(rhs)?,
+            )),
+            Expression::Binary(lhs, BinaryOperator::Eq, rhs) => {
+                Ok(Value::Bool(self.evaluate(lhs)? == self.evaluate(rhs)?))
+            }
+            Expression::Binary(lhs, BinaryOperator::Ne, rhs) => {
+                Ok(Value::Bool(self.evaluate(lhs)? != self.evaluate(rhs)?))
+            }
+            Expression::Binary(lhs, operator, rhs) => {
+                let lhs = self.evaluate_int(lhs)?;
+                let rhs = self.evaluate_int(rhs)?;
+                use BinaryOperator::*;
+                let result = match operator {
+                    Plus => lhs.checked_add(rhs).map(Value::Int),
+                    Minus => lhs.checked_sub(rhs).map(Value::Int),
+                    Mul => lhs.checked_mul(rhs).map(Value::Int),
+                    Div | Mod if rhs == 0 => return Err(RuntimeError::DivisionByZero),
+                    Div => lhs.checked_div(rhs).map(Value::Int),
+                    Mod => lhs.checked_rem(rhs).map(Value::Int),
+                    Lt => Some(Value::Bool(lhs < rhs)),
+                    Le => Some(Value::Bool(lhs <= rhs)),
+                    Gt => Some(Value::Bool(lhs > rhs)),
+                    Ge => Some(Value::Bool(lhs >= rhs)),
+                    And | Or | Eq | Ne => unreachable!("Handled separately"),
+                };
+                result.ok_or(RuntimeError::Overflow)
+            }
+        }
+    }
+
+    fn allocate(&mut self, object: Object) -> Value {
+        self.heap.push(object);
+        Value::Ref(self.heap.len() - 1)
+    }
+}
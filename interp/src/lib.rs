@@ -0,0 +1,6 @@
+#![doc = include_str!("../README.md")]
+
+pub mod ast;
+pub mod interpreter;
+pub mod parser;
+pub mod state;
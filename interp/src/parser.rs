@@ -0,0 +1,497 @@
+//! Parser of the language.
+
+use crate::ast::*;
+use derive_more::{Display, Error};
+use std::{collections::HashMap, rc::Rc};
+
+/// Error encountered while parsing a program.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("{error_data} on line {line_number}")]
+pub struct ParseError {
+    /// Description of the error.
+    pub error_data: SyntaxError,
+
+    /// Line number where the error occurred, starting from 1.
+    pub line_number: usize,
+}
+
+/// Kinds of errors encountered while parsing a program.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[error(ignore)]
+pub enum SyntaxError {
+    /// A character that cannot start a token was found.
+    #[display("unexpected character {_0:?}")]
+    UnexpectedCharacter(char),
+
+    /// An integer literal does not fit into 64 bits.
+    #[display("integer literal {_0} is out of range")]
+    IntegerOutOfRange(String),
+
+    /// A token was found where a different one was expected.
+    #[display("unexpected token {_0}")]
+    UnexpectedToken(String),
+
+    /// Input ended in the middle of a construct.
+    #[display("unexpected end of input")]
+    UnexpectedEnd,
+
+    /// The left-hand side of an assignment is not a variable,
+    /// item, or field.
+    #[display("invalid assignment target")]
+    InvalidAssignment,
+
+    /// A function call was nested in an expression.
+    #[display("function call to {_0:?} must be the entire right-hand side of a statement")]
+    NestedCall(String),
+
+    /// Two functions of the same name were declared.
+    #[display("function {_0:?} is declared more than once")]
+    DuplicateFunction(String),
+}
+
+/// Parses the source code of a program.
+pub fn parse_program(source: &str) -> Result<Program, ParseError> {
+    let tokens = tokenize(source)?;
+    Parser {
+        tokens,
+        position: 0,
+        end_line: source.lines().count().max(1),
+    }
+    .program()
+}
+
+/// Lexical token.
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+enum Token {
+    #[display("{_0}")]
+    Int(i64),
+    #[display("{_0:?}")]
+    Identifier(String),
+    #[display("{_0:?}")]
+    Symbol(&'static str),
+}
+
+/// Symbols recognized by the lexer, longest first
+/// so that prefixes do not shadow longer symbols.
+const SYMBOLS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "(", ")", "{", "}", "[", "]", ",", ";", ":", ".", "=", "<",
+    ">", "+", "-", "*", "/", "%", "!",
+];
+
+/// Splits source code into tokens annotated with line numbers.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let error = |error_data| ParseError {
+            error_data,
+            line_number,
+        };
+        // Strip comments
+        let line = line.split("//").next().unwrap_or_default();
+        let mut rest = line.trim_start();
+        while let Some(c) = rest.chars().next() {
+            let token_length;
+            if c.is_ascii_digit() {
+                token_length = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(rest.len());
+                let literal = &rest[..token_length];
+                let value = literal
+                    .parse()
+                    .map_err(|_| error(SyntaxError::IntegerOutOfRange(literal.to_owned())))?;
+                tokens.push((Token::Int(value), line_number));
+            } else if c.is_alphabetic() || c == '_' {
+                token_length = rest
+                    .find(|c: char| !c.is_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                tokens.push((
+                    Token::Identifier(rest[..token_length].to_owned()),
+                    line_number,
+                ));
+            } else if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+                token_length = symbol.len();
+                tokens.push((Token::Symbol(symbol), line_number));
+            } else {
+                return Err(error(SyntaxError::UnexpectedCharacter(c)));
+            }
+            rest = rest[token_length..].trim_start();
+        }
+    }
+    Ok(tokens)
+}
+
+/// Binary operators by precedence, from the loosest.
+const BINARY_OPERATORS: &[&[(&str, BinaryOperator)]] = &[
+    &[("||", BinaryOperator::Or)],
+    &[("&&", BinaryOperator::And)],
+    &[
+        ("==", BinaryOperator::Eq),
+        ("!=", BinaryOperator::Ne),
+        ("<", BinaryOperator::Lt),
+        ("<=", BinaryOperator::Le),
+        (">", BinaryOperator::Gt),
+        (">=", BinaryOperator::Ge),
+    ],
+    &[("+", BinaryOperator::Plus), ("-", BinaryOperator::Minus)],
+    &[
+        ("*", BinaryOperator::Mul),
+        ("/", BinaryOperator::Div),
+        ("%", BinaryOperator::Mod),
+    ],
+];
+
+/// Recursive descent parser.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    end_line: usize,
+}
+
+impl Parser {
+    fn program(mut self) -> Result<Program, ParseError> {
+        let mut functions = HashMap::new();
+        let mut main = Vec::new();
+        while self.peek().is_some() {
+            if self.is_keyword("fn") {
+                let line_number = self.line();
+                let function = self.function()?;
+                if functions.contains_key(&function.name) {
+                    return Err(ParseError {
+                        error_data: SyntaxError::DuplicateFunction(function.name),
+                        line_number,
+                    });
+                }
+                functions.insert(function.name.clone(), Rc::new(function));
+            } else {
+                main.push(self.statement()?);
+            }
+        }
+        Ok(Program {
+            functions,
+            main: main.into(),
+        })
+    }
+
+    fn function(&mut self) -> Result<Function, ParseError> {
+        self.expect_keyword("fn")?;
+        let name = self.identifier()?;
+        self.expect_symbol("(")?;
+        let mut parameters = Vec::new();
+        while !self.is_symbol(")") {
+            parameters.push(self.identifier()?);
+            if !self.is_symbol(")") {
+                self.expect_symbol(",")?;
+            }
+        }
+        self.expect_symbol(")")?;
+        let body = self.block()?;
+        Ok(Function {
+            name,
+            parameters,
+            body,
+        })
+    }
+
+    fn block(&mut self) -> Result<Rc<[Statement]>, ParseError> {
+        self.expect_symbol("{")?;
+        let mut statements = Vec::new();
+        while !self.is_symbol("}") {
+            statements.push(self.statement()?);
+        }
+        self.expect_symbol("}")?;
+        Ok(statements.into())
+    }
+
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        let line = self.line();
+        let kind = if self.is_keyword("let") {
+            self.advance();
+            let name = self.identifier()?;
+            self.expect_symbol("=")?;
+            let rhs = self.rhs()?;
+            self.expect_symbol(";")?;
+            StatementKind::Let(name, rhs)
+        } else if self.is_keyword("if") {
+            self.if_statement()?
+        } else if self.is_keyword("while") {
+            self.advance();
+            let condition = self.expression()?;
+            let body = self.block()?;
+            StatementKind::While(condition.into(), body)
+        } else if self.is_keyword("return") {
+            self.advance();
+            let value = if self.is_symbol(";") {
+                None
+            } else {
+                Some(self.rhs()?)
+            };
+            self.expect_symbol(";")?;
+            StatementKind::Return(value)
+        } else {
+            match self.rhs()? {
+                Rhs::Call(call) => {
+                    self.expect_symbol(";")?;
+                    StatementKind::Call(call)
+                }
+                Rhs::Expression(place) => {
+                    if !matches!(
+                        place,
+                        Expression::Variable(_) | Expression::Index(_, _) | Expression::Field(_, _)
+                    ) {
+                        return Err(self.error(SyntaxError::InvalidAssignment));
+                    }
+                    self.expect_symbol("=")?;
+                    let rhs = self.rhs()?;
+                    self.expect_symbol(";")?;
+                    StatementKind::Assign(place, rhs)
+                }
+            }
+        };
+        Ok(Statement { line, kind })
+    }
+
+    fn if_statement(&mut self) -> Result<StatementKind, ParseError> {
+        self.expect_keyword("if")?;
+        let condition = self.expression()?;
+        let then_branch = self.block()?;
+        let else_branch = if self.is_keyword("else") {
+            self.advance();
+            if self.is_keyword("if") {
+                let line = self.line();
+                let kind = self.if_statement()?;
+                [Statement { line, kind }].into()
+            } else {
+                self.block()?
+            }
+        } else {
+            [].into()
+        };
+        Ok(StatementKind::If(condition, then_branch, else_branch))
+    }
+
+    fn rhs(&mut self) -> Result<Rhs, ParseError> {
+        if let (Some(Token::Identifier(name)), Some(Token::Symbol("("))) =
+            (self.peek(), self.peek_at(1))
+            && !Self::is_reserved(name)
+        {
+            let function = name.clone();
+            self.advance();
+            let arguments = self.arguments()?;
+            // Calls cannot be followed by anything else in the same statement
+            if !self.is_symbol(";") {
+                return Err(self.error(SyntaxError::NestedCall(function)));
+            }
+            Ok(Rhs::Call(Call {
+                function,
+                arguments,
+            }))
+        } else {
+            Ok(Rhs::Expression(self.expression()?))
+        }
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Expression>, ParseError> {
+        self.expect_symbol("(")?;
+        let mut arguments = Vec::new();
+        while !self.is_symbol(")") {
+            arguments.push(self.expression()?);
+            if !self.is_symbol(")") {
+                self.expect_symbol(",")?;
+            }
+        }
+        self.expect_symbol(")")?;
+        Ok(arguments)
+    }
+
+    fn expression(&mut self) -> Result<Expression, ParseError> {
+        self.binary_expression(0)
+    }
+
+    fn binary_expression(&mut self, precedence: usize) -> Result<Expression, ParseError> {
+        let Some(operators) = BINARY_OPERATORS.get(precedence) else {
+            return self.unary_expression();
+        };
+        let mut lhs = self.binary_expression(precedence + 1)?;
+        while let Some((_, operator)) = operators.iter().find(|(s, _)| self.is_symbol(s)) {
+            self.advance();
+            let rhs = self.binary_expression(precedence + 1)?;
+            lhs = Expression::Binary(lhs.into(), *operator, rhs.into());
+        }
+        Ok(lhs)
+    }
+
+    fn unary_expression(&mut self) -> Result<Expression, ParseError> {
+        if self.is_symbol("-") {
+            self.advance();
+            Ok(Expression::Unary(
+                UnaryOperator::Minus,
+                self.unary_expression()?.into(),
+            ))
+        } else if self.is_symbol("!") {
+            self.advance();
+            Ok(Expression::Unary(
+                UnaryOperator::Not,
+                self.unary_expression()?.into(),
+            ))
+        } else {
+            self.postfix_expression()
+        }
+    }
+
+    fn postfix_expression(&mut self) -> Result<Expression, ParseError> {
+        let mut expression = self.primary_expression()?;
+        loop {
+            if self.is_symbol("[") {
+                self.advance();
+                let index = self.expression()?;
+                self.expect_symbol("]")?;
+                expression = Expression::Index(expression.into(), index.into());
+            } else if self.is_symbol(".") {
+                self.advance();
+                let field = self.identifier()?;
+                expression = Expression::Field(expression.into(), field);
+            } else {
+                return Ok(expression);
+            }
+        }
+    }
+
+    fn primary_expression(&mut self) -> Result<Expression, ParseError> {
+        match self.peek().cloned() {
+            Some(Token::Int(i)) => {
+                self.advance();
+                Ok(Expression::Int(i))
+            }
+            Some(Token::Identifier(name)) => {
+                self.advance();
+                match name.as_str() {
+                    "true" => Ok(Expression::Bool(true)),
+                    "false" => Ok(Expression::Bool(false)),
+                    "none" => Ok(Expression::None),
+                    "len" => {
+                        self.expect_symbol("(")?;
+                        let operand = self.expression()?;
+                        self.expect_symbol(")")?;
+                        Ok(Expression::Length(operand.into()))
+                    }
+                    _ if self.is_symbol("(") => Err(self.error(SyntaxError::NestedCall(name))),
+                    _ if Self::is_reserved(&name) => {
+                        Err(self.error(SyntaxError::UnexpectedToken(format!("{name:?}"))))
+                    }
+                    _ => Ok(Expression::Variable(name)),
+                }
+            }
+            Some(Token::Symbol("(")) => {
+                self.advance();
+                let expression = self.expression()?;
+                self.expect_symbol(")")?;
+                Ok(expression)
+            }
+            Some(Token::Symbol("[")) => {
+                self.advance();
+                let mut items = Vec::new();
+                while !self.is_symbol("]") {
+                    items.push(self.expression()?);
+                    if !self.is_symbol("]") {
+                        self.expect_symbol(",")?;
+                    }
+                }
+                self.expect_symbol("]")?;
+                Ok(Expression::Array(items))
+            }
+            Some(Token::Symbol("{")) => {
+                self.advance();
+                let mut fields = Vec::new();
+                while !self.is_symbol("}") {
+                    let name = self.identifier()?;
+                    self.expect_symbol(":")?;
+                    fields.push((name, self.expression()?));
+                    if !self.is_symbol("}") {
+                        self.expect_symbol(",")?;
+                    }
+                }
+                self.expect_symbol("}")?;
+                Ok(Expression::Record(fields))
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Token::Identifier(name)) if !Self::is_reserved(name) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), ParseError> {
+        if self.is_symbol(symbol) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.is_keyword(keyword) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Identifier(s)) if s == keyword)
+    }
+
+    fn is_reserved(name: &str) -> bool {
+        matches!(
+            name,
+            "let" | "fn" | "if" | "else" | "while" | "return" | "true" | "false" | "none" | "len"
+        )
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.position + offset).map(|(t, _)| t)
+    }
+
+    fn advance(&mut self) {
+        self.position += 1;
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map(|(_, line)| *line)
+            .unwrap_or(self.end_line)
+    }
+
+    fn error(&self, error_data: SyntaxError) -> ParseError {
+        ParseError {
+            error_data,
+            line_number: self.line(),
+        }
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(token) => self.error(SyntaxError::UnexpectedToken(token.to_string())),
+            None => self.error(SyntaxError::UnexpectedEnd),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Implementation of [`ProgramStateGraph`] for the [`Interpreter`].
+
+use crate::interpreter::{Interpreter, Object, Value};
+use aili_model::{
+    capability::{self, CapabilitySet},
+    state::*,
+};
+use derive_more::Debug;
+
+/// Identifiers of state nodes of an [`Interpreter`].
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum InterpNodeId {
+    /// Identifier of the root node.
+    #[debug("::")]
+    Root,
+
+    /// Identifier of a stack frame node.
+    #[debug("frame({_0})")]
+    Frame(usize),
+
+    /// Identifier of a node that represents an object on the heap.
+    #[debug("obj({_0})")]
+    Object(usize),
+
+    /// Identifier of the [`EdgeLabel::Length`] pseudo-node
+    /// associated with a [`InterpNodeId::Object`] node.
+    #[debug("obj({_0}) len")]
+    Length(usize),
+
+    /// Identifier of a node that represents a value
+    /// stored in a variable, array item, or record field.
+    ///
+    /// Values are identified by the node that holds them
+    /// and the label of the edge that leads to them.
+    #[debug("{_0:?} -> {_1:?}")]
+    Value(Box<InterpNodeId>, EdgeLabel),
+}
+
+/// Node of the state graph of an [`Interpreter`].
+///
+/// Nodes are constructed on demand from the interpreter's state.
+#[derive(Debug)]
+pub struct InterpNode<'a> {
+    type_class: NodeTypeClass,
+    type_name: Option<&'a str>,
+    successors: Vec<(EdgeLabel, InterpNodeId)>,
+    value: Option<NodeValue>,
+}
+
+impl<'a> InterpNode<'a> {
+    fn new(type_class: NodeTypeClass, type_name: Option<&'a str>) -> Self {
+        Self {
+            type_class,
+            type_name,
+            successors: Vec::new(),
+            value: None,
+        }
+    }
+}
+
+impl ProgramStateNode for InterpNode<'_> {
+    type NodeId = InterpNodeId;
+    type NodeTypeId<'b>
+        = &'b str
+    where
+        Self: 'b;
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
+        self.successors
+            .iter()
+            .find(|(e, _)| *e == *edge)
+            .map(|(_, n)| n)
+            .cloned()
+    }
+    fn node_type_class(&self) -> NodeTypeClass {
+        self.type_class
+    }
+    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
+        self.type_name
+    }
+    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
+        self.successors.iter().map(|(e, n)| (e, n.clone()))
+    }
+    fn value(&self) -> Option<NodeValue> {
+        self.value.clone()
+    }
+}
+
+/// The state graph of an interpreter follows the call stack.
+///
+/// Variables, array items, and record fields that hold integers,
+/// booleans, and the empty value are represented by
+/// [`NodeTypeClass::Atom`] nodes. Those that hold arrays and records
+/// are represented by [`NodeTypeClass::Ref`] nodes that point
+/// to a shared node that represents the referenced object.
+impl ProgramStateGraph for Interpreter {
+    type NodeId = InterpNodeId;
+    type NodeRef<'a>
+        = InterpNode<'a>
+    where
+        Self: 'a;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        match id {
+            InterpNodeId::Root => {
+                let mut node = InterpNode::new(NodeTypeClass::Root, None);
+                node.successors
+                    .push((EdgeLabel::Main, InterpNodeId::Frame(0)));
+                Some(node)
+            }
+            InterpNodeId::Frame(i) => {
+                let frame = self.frames.get(*i)?;
+                let name = frame.function.as_ref().map_or("main", |f| f.name.as_str());
+                let mut node = InterpNode::new(NodeTypeClass::Frame, Some(name));
+                node.successors
+                    .extend(frame.variables.iter().map(|(name, _)| {
+                        let edge = EdgeLabel::Named(name.into(), 0);
+                        (edge.clone(), InterpNodeId::Value(id.clone().into(), edge))
+                    }));
+                if *i + 1 < self.frames.len() {
+                    node.successors
+                        .push((EdgeLabel::Next, InterpNodeId::Frame(i + 1)));
+                }
+                Some(node)
+            }
+            InterpNodeId::Object(i) => match self.heap.get(*i)? {
+                Object::Array(items) => {
+                    let mut node = InterpNode::new(NodeTypeClass::Array, Some("array"));
+                    node.successors
+                        .push((EdgeLabel::Length, InterpNodeId::Length(*i)));
+                    node.successors.extend((0..items.len()).map(|j| {
+                        let edge = EdgeLabel::Index(j);
+                        (edge.clone(), InterpNodeId::Value(id.clone().into(), edge))
+                    }));
+                    Some(node)
+                }
+                Object::Record(fields) => {
+                    let mut node = InterpNode::new(NodeTypeClass::Struct, Some("record"));
+                    node.successors.extend(fields.iter().map(|(name, _)| {
+                        let edge = EdgeLabel::Named(name.into(), 0);
+                        (edge.clone(), InterpNodeId::Value(id.clone().into(), edge))
+                    }));
+                    Some(node)
+                }
+            },
+            InterpNodeId::Length(i) => {
+                let Object::Array(items) = self.heap.get(*i)? else {
+                    return None;
+                };
+                let mut node = InterpNode::new(NodeTypeClass::Atom, None);
+                node.value = Some(NodeValue::Uint(items.len() as u64));
+                Some(node)
+            }
+            InterpNodeId::Value(holder, edge) => Some(value_node(self.get_value(holder, edge)?)),
+        }
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        CapabilitySet::from_iter([capability::ARRAYS, capability::POINTERS])
+    }
+}
+
+impl RootedProgramStateGraph for Interpreter {
+    fn root(&self) -> Self::NodeId {
+        InterpNodeId::Root
+    }
+}
+
+impl Interpreter {
+    /// Looks up a value held by a node under a given edge.
+    fn get_value(&self, holder: &InterpNodeId, edge: &EdgeLabel) -> Option<Value> {
+        match (holder, edge) {
+            (InterpNodeId::Frame(i), EdgeLabel::Named(name, 0)) => self
+                .frames
+                .get(*i)?
+                .variables
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| *v),
+            (InterpNodeId::Object(i), EdgeLabel::Index(j)) => match self.heap.get(*i)? {
+                Object::Array(items) => items.get(*j).copied(),
+                Object::Record(_) => None,
+            },
+            (InterpNodeId::Object(i), EdgeLabel::Named(name, 0)) => match self.heap.get(*i)? {
+                Object::Record(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, v)| *v),
+                Object::Array(_) => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Constructs the node that represents a value.
+fn value_node<'a>(value: Value) -> InterpNode<'a> {
+    match value {
+        Value::None => InterpNode::new(NodeTypeClass::Atom, Some("none")),
+        Value::Int(i) => {
+            let mut node = InterpNode::new(NodeTypeClass::Atom, Some("int"));
+            node.value = Some(NodeValue::Int(i));
+            node
+        }
+        Value::Bool(b) => {
+            let mut node = InterpNode::new(NodeTypeClass::Atom, Some("bool"));
+            node.value = Some(NodeValue::Bool(b));
+            node
+        }
+        Value::Ref(i) => {
+            let mut node = InterpNode::new(NodeTypeClass::Ref, None);
+            node.successors
+                .push((EdgeLabel::Deref, InterpNodeId::Object(i)));
+            node
+        }
+    }
+}
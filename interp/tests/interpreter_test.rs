@@ -0,0 +1,226 @@
+use aili_interp::{
+    interpreter::{Interpreter, RuntimeError},
+    parser::{ParseError, SyntaxError, parse_program},
+};
+use aili_model::state::*;
+
+/// Parses a program and prepares it for execution.
+fn interpreter_from_source(source: &str) -> Interpreter {
+    Interpreter::new(parse_program(source).expect("Program should have parsed"))
+}
+
+#[test]
+fn empty_program_is_finished() {
+    let interpreter = interpreter_from_source("");
+    assert!(interpreter.is_finished());
+    let main = interpreter.get_at_root(&[EdgeLabel::Main]).unwrap();
+    assert_eq!(main.node_type_class(), NodeTypeClass::Frame);
+    assert_eq!(main.node_type_id(), Some("main"));
+}
+
+#[test]
+fn variables_are_updated_by_steps() {
+    let mut interpreter = interpreter_from_source(
+        r"
+        let a = 1;
+        a = a + 41;
+        ",
+    );
+    assert_eq!(interpreter.current_line(), Some(2));
+    interpreter.step().unwrap();
+    let a_path = [EdgeLabel::Main, EdgeLabel::Named("a".into(), 0)];
+    let a = interpreter.get_at_root(&a_path).unwrap();
+    assert_eq!(a.node_type_class(), NodeTypeClass::Atom);
+    assert_eq!(a.node_type_id(), Some("int"));
+    assert_eq!(a.value(), Some(NodeValue::Int(1)));
+    interpreter.step().unwrap();
+    assert!(interpreter.is_finished());
+    let a = interpreter.get_at_root(&a_path).unwrap();
+    assert_eq!(a.value(), Some(NodeValue::Int(42)));
+}
+
+#[test]
+fn function_call_creates_frame() {
+    let mut interpreter = interpreter_from_source(
+        r"
+        fn square(x) {
+            let y = x * x;
+            return y;
+        }
+        let result = square(7);
+        ",
+    );
+    interpreter.step().unwrap();
+    assert_eq!(interpreter.current_line(), Some(3));
+    let square = interpreter
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Next])
+        .unwrap();
+    assert_eq!(square.node_type_class(), NodeTypeClass::Frame);
+    assert_eq!(square.node_type_id(), Some("square"));
+    interpreter.run().unwrap();
+    assert!(
+        interpreter
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Next])
+            .is_none()
+    );
+    let result = interpreter
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("result".into(), 0)])
+        .unwrap();
+    assert_eq!(result.value(), Some(NodeValue::Int(49)));
+}
+
+#[test]
+fn recursive_function() {
+    let mut interpreter = interpreter_from_source(
+        r"
+        fn factorial(n) {
+            if n <= 1 {
+                return 1;
+            }
+            let rest = factorial(n - 1);
+            return n * rest;
+        }
+        let result = factorial(10);
+        ",
+    );
+    interpreter.run().unwrap();
+    let result = interpreter
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("result".into(), 0)])
+        .unwrap();
+    assert_eq!(result.value(), Some(NodeValue::Int(3628800)));
+}
+
+#[test]
+fn loop_over_shared_array() {
+    let mut interpreter = interpreter_from_source(
+        r"
+        let a = [1, 2, 3];
+        let b = a;
+        let i = 0;
+        while i < len(b) {
+            b[i] = b[i] * 10;
+            i = i + 1;
+        }
+        ",
+    );
+    interpreter.run().unwrap();
+    let a_ref = interpreter
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".into(), 0)])
+        .unwrap();
+    assert_eq!(a_ref.node_type_class(), NodeTypeClass::Ref);
+    let a_id = interpreter
+        .get_id_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("a".into(), 0),
+            EdgeLabel::Deref,
+        ])
+        .unwrap();
+    let b_id = interpreter
+        .get_id_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("b".into(), 0),
+            EdgeLabel::Deref,
+        ])
+        .unwrap();
+    assert_eq!(a_id, b_id);
+    let array = interpreter.get(&a_id).unwrap();
+    assert_eq!(array.node_type_class(), NodeTypeClass::Array);
+    let length = interpreter
+        .get(&array.get_successor(&EdgeLabel::Length).unwrap())
+        .unwrap();
+    assert_eq!(length.value(), Some(NodeValue::Uint(3)));
+    let last = interpreter
+        .get(&array.get_successor(&EdgeLabel::Index(2)).unwrap())
+        .unwrap();
+    assert_eq!(last.value(), Some(NodeValue::Int(30)));
+}
+
+#[test]
+fn record_fields() {
+    let mut interpreter = interpreter_from_source(
+        r"
+        let p = { x: 1, y: true };
+        p.x = 5;
+        ",
+    );
+    interpreter.run().unwrap();
+    let p = interpreter
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("p".into(), 0),
+            EdgeLabel::Deref,
+        ])
+        .unwrap();
+    assert_eq!(p.node_type_class(), NodeTypeClass::Struct);
+    let x = interpreter.get(&p.get_successor(&EdgeLabel::Named("x".into(), 0)).unwrap());
+    assert_eq!(x.unwrap().value(), Some(NodeValue::Int(5)));
+    let y = interpreter.get(&p.get_successor(&EdgeLabel::Named("y".into(), 0)).unwrap());
+    assert_eq!(y.unwrap().value(), Some(NodeValue::Bool(true)));
+}
+
+#[test]
+fn run_to_line_stops_before_statement() {
+    let mut interpreter = interpreter_from_source(
+        r"
+        let i = 0;
+        while i < 3 {
+            i = i + 1;
+        }
+        ",
+    );
+    assert_eq!(interpreter.run_to_line(4), Ok(true));
+    assert_eq!(interpreter.run_to_line(4), Ok(true));
+    assert_eq!(interpreter.run_to_line(4), Ok(true));
+    assert_eq!(interpreter.run_to_line(4), Ok(false));
+    assert!(interpreter.is_finished());
+}
+
+#[test]
+fn runtime_error() {
+    let mut interpreter = interpreter_from_source(
+        r"
+        let a = [1];
+        let b = a[1];
+        ",
+    );
+    interpreter.step().unwrap();
+    assert_eq!(
+        interpreter.step(),
+        Err(RuntimeError::IndexOutOfBounds(1, 1))
+    );
+}
+
+#[test]
+fn loop_condition_error_is_repeated() {
+    let mut interpreter = interpreter_from_source(
+        r"
+        let x = 0;
+        while x < 2 {
+            x = none;
+        }
+        let y = 1;
+        ",
+    );
+    interpreter.step().unwrap();
+    interpreter.step().unwrap();
+    assert_eq!(interpreter.step(), Err(RuntimeError::TypeMismatch("int")));
+    assert!(!interpreter.is_finished());
+    assert_eq!(interpreter.current_line(), Some(3));
+    assert_eq!(interpreter.step(), Err(RuntimeError::TypeMismatch("int")));
+    assert!(!interpreter.is_finished());
+}
+
+#[test]
+fn nested_call_is_rejected() {
+    let source = r"
+        fn f(x) { return x; }
+        let a = f(1) + 1;
+        ";
+    assert_eq!(
+        parse_program(source),
+        Err(ParseError {
+            error_data: SyntaxError::NestedCall("f".to_owned()),
+            line_number: 3,
+        })
+    );
+}
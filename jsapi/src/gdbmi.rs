@@ -22,6 +22,7 @@ pub fn parse_gdbmi_record(record: &str) -> Option<GdbMiRecord> {
             let results = match &record {
                 Record::Result(r) => &r.results,
                 Record::AsyncExec(r) => &r.results,
+                Record::AsyncNotify(r) => &r.results,
             };
             Some(GdbMiRecord {
                 results: result_tuple_to_js(results).into(),
@@ -5,11 +5,15 @@
 use crate::stylesheet::LengthHintSheet;
 use aili_gdbstate::{
     gdbmi::stream::StringGdbMiStream,
-    state::{GdbStateGraph as GdbStateGraphImpl, GdbStateNode, GdbStateNodeId},
+    state::{GdbStateGraph as GdbStateGraphImpl, GdbStateNodeId, GdbStateNodeRef},
 };
-use aili_model::state::{ProgramStateGraph, RootedProgramStateGraph};
-use js_sys::Reflect;
+use aili_model::{
+    capability::CapabilitySet,
+    state::{ProgramStateGraph, RootedProgramStateGraph},
+};
+use js_sys::{Promise, Reflect};
 use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
 
 #[wasm_bindgen(typescript_custom_section)]
 const TYPESCRIPT_INTERFACES: &str = r"
@@ -22,6 +26,10 @@ const TYPESCRIPT_INTERFACES: &str = r"
          * 
          * Returns the result record corresponding to the passed command.
          * 
+         * Multiple commands may be in flight at once, so each returned promise
+         * must resolve to the result record of its own command, for example
+         * by sending the commands to GDB with distinct tokens.
+         * 
          * @throws The command is invalid or the session could not execute it.
          */
         sendMiCommand(command: string): Promise<string>;
@@ -37,21 +45,48 @@ extern "C" {
     /// Sends a GDB/MI command to the session.
     #[wasm_bindgen(method, js_name = "sendMiCommand", catch)]
     pub async fn send_mi_command(this: &GdbMi, command: &str) -> Result<JsValue, JsValue>;
+
+    /// Sends a GDB/MI command to the session without waiting for the response.
+    #[wasm_bindgen(method, js_name = "sendMiCommand", catch)]
+    fn start_mi_command(this: &GdbMi, command: &str) -> Result<Promise, JsValue>;
 }
 
 // Implement the trait for reference so that we can use it mutably
 // Wasm-bindgen would not give us `&mut` to a JS object
 impl StringGdbMiStream for &GdbMi {
     async fn send_command(&mut self, command: &str) -> std::io::Result<String> {
-        match self.send_mi_command(command).await {
-            Ok(output) => output.as_string().ok_or_else(|| {
-                std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Command did not return a string",
-                )
-            }),
-            Err(err) => Err(std::io::Error::other(js_error_description(&err))),
+        response_string(self.send_mi_command(command).await)
+    }
+
+    async fn send_commands(&mut self, commands: &[String]) -> Vec<std::io::Result<String>> {
+        // Send all commands before awaiting any response,
+        // the session matches the responses to the commands
+        let pending = commands
+            .iter()
+            .map(|command| self.start_mi_command(command))
+            .collect::<Vec<_>>();
+        let mut results = Vec::with_capacity(pending.len());
+        for promise in pending {
+            let output = match promise {
+                Ok(promise) => JsFuture::from(promise).await,
+                Err(err) => Err(err),
+            };
+            results.push(response_string(output));
         }
+        results
+    }
+}
+
+/// Converts the outcome of a GDB/MI command to the result record string.
+fn response_string(output: Result<JsValue, JsValue>) -> std::io::Result<String> {
+    match output {
+        Ok(output) => output.as_string().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Command did not return a string",
+            )
+        }),
+        Err(err) => Err(std::io::Error::other(js_error_description(&err))),
     }
 }
 
@@ -102,6 +137,20 @@ impl GdbStateGraph {
             .map_err(|e| JsError::new(&format!("{e}")))
     }
 
+    /// Rebuilds the state graph using the provided GDB/MI session
+    /// after execution has moved to an arbitrary point,
+    /// such as after reverse execution.
+    pub async fn resynchronize(
+        &mut self,
+        mut gdb_mi: &GdbMi,
+        hint_sheet: &LengthHintSheet,
+    ) -> Result<(), JsError> {
+        self.0
+            .resynchronize_with_hints(&mut gdb_mi, &hint_sheet.0)
+            .await
+            .map_err(|e| JsError::new(&format!("{e}")))
+    }
+
     /// Cleans up state that was required by the state graph from the provided GDB/MI session.
     #[wasm_bindgen(js_name = "cleanUp")]
     pub async fn clean_up(&self, mut gdb_mi: &GdbMi) -> Result<(), JsError> {
@@ -114,10 +163,13 @@ impl GdbStateGraph {
 
 impl ProgramStateGraph for GdbStateGraph {
     type NodeId = GdbStateNodeId;
-    type NodeRef<'a> = &'a GdbStateNode;
+    type NodeRef<'a> = GdbStateNodeRef<'a>;
     fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
         self.0.get(id)
     }
+    fn capabilities(&self) -> CapabilitySet {
+        self.0.capabilities()
+    }
 }
 
 impl RootedProgramStateGraph for GdbStateGraph {
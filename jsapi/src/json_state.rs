@@ -0,0 +1,227 @@
+//! State graphs serialized to JSON.
+
+#![cfg(feature = "json")]
+
+#[cfg(feature = "gdbstate")]
+use crate::gdbstate::GdbStateGraph;
+use crate::state::StateGraph;
+use aili_model::{capability::CapabilitySet, state::*};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Implementation of [`RootedProgramStateGraph`] deserialized from JSON,
+/// so that synthetic states can be visualized without a debugger.
+///
+/// The graph is a list of nodes. Edges refer to their target nodes
+/// by their index in the list, and the first node is the root.
+///
+/// ```json
+/// {
+///     "capabilities": ["string-values"],
+///     "nodes": [
+///         { "typeClass": "Root", "edges": [{ "label": "Main", "node": 1 }] },
+///         { "typeClass": "Frame", "typeName": "main" }
+///     ]
+/// }
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JsonStateGraph {
+    /// Capabilities that the graph declares,
+    /// which stylesheets can require.
+    #[serde(default)]
+    capabilities: CapabilitySet,
+
+    /// Nodes of the graph, starting with the root.
+    nodes: Vec<JsonStateNode>,
+}
+
+#[wasm_bindgen]
+impl JsonStateGraph {
+    /// Deserializes a state graph from JSON.
+    ///
+    /// Fails if the graph is malformed, if it has no nodes,
+    /// or if an edge leads to a node that does not exist.
+    #[wasm_bindgen(js_name = "fromJson")]
+    pub fn from_json(source: &str) -> Result<Self, JsError> {
+        let graph: Self = serde_json::from_str(source)?;
+        if graph.nodes.is_empty() {
+            return Err(JsError::new("State graph cannot be empty"));
+        }
+        let node_count = graph.nodes.len();
+        if let Some(edge) = graph
+            .nodes
+            .iter()
+            .flat_map(|node| &node.edges)
+            .find(|edge| edge.node >= node_count)
+        {
+            return Err(JsError::new(&format!(
+                "Edge {:?} leads to node {}, but the graph only has {node_count} nodes",
+                edge.label, edge.node
+            )));
+        }
+        Ok(graph)
+    }
+
+    /// Serializes the state graph to JSON.
+    #[wasm_bindgen(js_name = "toJson")]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl JsonStateGraph {
+    /// Copies all nodes that are reachable from the root of another state graph.
+    pub fn from_graph<G: RootedProgramStateGraph>(graph: &G) -> Self {
+        let mut indices = HashMap::from([(graph.root(), 0)]);
+        let mut pending = vec![graph.root()];
+        let mut nodes = Vec::new();
+        // Nodes are numbered in the order they are discovered,
+        // so the root is always first
+        let mut visited = 0;
+        while visited < pending.len() {
+            let id = pending[visited].clone();
+            visited += 1;
+            let Some(node) = graph.get(&id) else {
+                // Keep the indices of the other nodes intact
+                nodes.push(JsonStateNode::missing());
+                continue;
+            };
+            let edges = node
+                .successors()
+                .map(|(label, successor)| {
+                    let node = *indices.entry(successor.clone()).or_insert_with(|| {
+                        pending.push(successor);
+                        pending.len() - 1
+                    });
+                    JsonStateEdge {
+                        label: label.clone(),
+                        node,
+                    }
+                })
+                .collect();
+            nodes.push(JsonStateNode {
+                type_class: node.node_type_class(),
+                type_name: node.node_type_id().map(|id| id.type_name().to_owned()),
+                class_name: node.custom_type_class().map(str::to_owned),
+                value: node.value(),
+                source_location: node.source_location(),
+                edges,
+            });
+        }
+        Self {
+            capabilities: graph.capabilities(),
+            nodes,
+        }
+    }
+}
+
+impl ProgramStateGraph for JsonStateGraph {
+    type NodeId = usize;
+    type NodeRef<'a> = &'a JsonStateNode;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        self.nodes.get(*id)
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        self.capabilities.clone()
+    }
+}
+
+impl RootedProgramStateGraph for JsonStateGraph {
+    fn root(&self) -> Self::NodeId {
+        0
+    }
+}
+
+#[wasm_bindgen]
+impl StateGraph {
+    /// Serializes the state graph to JSON
+    /// that can be loaded by `JsonStateGraph.fromJson`.
+    #[wasm_bindgen(js_name = "toJson")]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        JsonStateGraph::from_graph(self).to_json()
+    }
+}
+
+#[cfg(feature = "gdbstate")]
+#[wasm_bindgen]
+impl GdbStateGraph {
+    /// Serializes a snapshot of the state graph to JSON
+    /// that can be loaded by `JsonStateGraph.fromJson`.
+    #[wasm_bindgen(js_name = "toJson")]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        JsonStateGraph::from_graph(self).to_json()
+    }
+}
+
+/// A node of [`JsonStateGraph`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonStateNode {
+    type_class: NodeTypeClass,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    type_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    class_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<NodeValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_location: Option<SourceLocation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    edges: Vec<JsonStateEdge>,
+}
+
+impl JsonStateNode {
+    /// Placeholder for a node that a graph refers to,
+    /// but does not contain.
+    fn missing() -> Self {
+        Self {
+            type_class: NodeTypeClass::Atom,
+            type_name: None,
+            class_name: None,
+            value: None,
+            source_location: None,
+            edges: Vec::new(),
+        }
+    }
+}
+
+impl ProgramStateNode for &JsonStateNode {
+    type NodeId = usize;
+    type NodeTypeId<'a>
+        = &'a str
+    where
+        Self: 'a;
+    fn value(&self) -> Option<NodeValue> {
+        self.value.clone()
+    }
+    fn node_type_class(&self) -> NodeTypeClass {
+        self.type_class
+    }
+    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
+        self.type_name.as_deref()
+    }
+    fn custom_type_class(&self) -> Option<&str> {
+        self.class_name.as_deref()
+    }
+    fn source_location(&self) -> Option<SourceLocation> {
+        self.source_location.clone()
+    }
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
+        self.edges.iter().find(|e| e.label == *edge).map(|e| e.node)
+    }
+    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
+        self.edges.iter().map(|e| (&e.label, e.node))
+    }
+}
+
+/// An outgoing edge of a [`JsonStateNode`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct JsonStateEdge {
+    /// Label of the edge.
+    label: EdgeLabel,
+
+    /// Index of the node that the edge leads to.
+    node: usize,
+}
@@ -2,6 +2,7 @@
 
 mod gdbmi;
 mod gdbstate;
+mod json_state;
 mod log;
 mod state;
 mod state_description;
@@ -28,6 +28,8 @@ impl state::RootedProgramStateGraph for StateGraph {
 pub struct StateNode {
     pub(crate) type_class: state::NodeTypeClass,
     pub(crate) type_name: Option<String>,
+    pub(crate) class_name: Option<String>,
+    pub(crate) source_location: Option<state::SourceLocation>,
     pub(crate) value: Option<state::NodeValue>,
     pub(crate) successors: HashMap<state::EdgeLabel, usize>,
 }
@@ -39,7 +41,7 @@ impl state::ProgramStateNode for &StateNode {
     where
         Self: 'a;
     fn value(&self) -> Option<state::NodeValue> {
-        self.value
+        self.value.clone()
     }
     fn node_type_class(&self) -> state::NodeTypeClass {
         self.type_class
@@ -47,6 +49,12 @@ impl state::ProgramStateNode for &StateNode {
     fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
         self.type_name.as_deref()
     }
+    fn custom_type_class(&self) -> Option<&str> {
+        self.class_name.as_deref()
+    }
+    fn source_location(&self) -> Option<state::SourceLocation> {
+        self.source_location.clone()
+    }
     fn get_successor(&self, edge: &state::EdgeLabel) -> Option<Self::NodeId> {
         self.successors.get(edge).cloned()
     }
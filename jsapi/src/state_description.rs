@@ -20,6 +20,20 @@ const TYPESCRIPT_INTERFACES: &str = r"
          * General category of the node's type.
          */
         typeKind: NodeTypeClass;
+        /**
+         * Name of the node's custom type class,
+         * if its type kind is {@link NodeTypeClass.Custom}.
+         */
+        className?: string;
+        /**
+         * Name of the source file that the node is located in, if any.
+         */
+        sourceFile?: string;
+        /**
+         * Line of the source file that the node is located at, if any.
+         * Only used if {@link sourceFile} is present.
+         */
+        sourceLine?: number;
         /**
          * Numeric value of the node, if any.
          */
@@ -67,6 +81,10 @@ pub enum NodeTypeClass {
     ///
     /// See [`aili_model::state::NodeTypeClass::Struct`].
     Struct,
+    /// Union node.
+    ///
+    /// See [`aili_model::state::NodeTypeClass::Union`].
+    Union,
     /// Array node.
     ///
     /// See [`aili_model::state::NodeTypeClass::Array`].
@@ -75,6 +93,14 @@ pub enum NodeTypeClass {
     ///
     /// See [`aili_model::state::NodeTypeClass::Ref`].
     Ref,
+    /// Node of a custom type class.
+    ///
+    /// See [`aili_model::state::NodeTypeClass::Custom`].
+    Custom,
+    /// Node that stands in for a part of the graph that has been left out.
+    ///
+    /// See [`aili_model::state::NodeTypeClass::Truncated`].
+    Truncated,
 }
 
 impl From<NodeTypeClass> for state::NodeTypeClass {
@@ -85,8 +111,11 @@ impl From<NodeTypeClass> for state::NodeTypeClass {
             Frame => Self::Frame,
             Atom => Self::Atom,
             Struct => Self::Struct,
+            Union => Self::Union,
             Array => Self::Array,
             Ref => Self::Ref,
+            Custom => Self::Custom,
+            Truncated => Self::Truncated,
         }
     }
 }
@@ -144,7 +173,7 @@ impl EdgeLabel {
     /// See [`aili_model::state::EdgeLabel::Named`].
     pub fn named(name: &str, discriminator: Option<usize>) -> Self {
         Self(state::EdgeLabel::Named(
-            name.to_owned(),
+            name.into(),
             discriminator.unwrap_or_default(),
         ))
     }
@@ -155,6 +184,13 @@ impl EdgeLabel {
     pub fn index(index: usize) -> Self {
         Self(state::EdgeLabel::Index(index))
     }
+
+    /// Edge that indicates a domain-specific relation.
+    ///
+    /// See [`aili_model::state::EdgeLabel::Custom`].
+    pub fn custom(name: &str) -> Self {
+        Self(state::EdgeLabel::Custom(name.to_owned()))
+    }
 }
 
 #[wasm_bindgen]
@@ -179,6 +215,24 @@ extern "C" {
     #[wasm_bindgen(method, getter, js_name = "typeKind")]
     pub fn type_kind(this: &StateNodeDescription) -> NodeTypeClass;
 
+    /// Gets the name of the custom type class of the node.
+    ///
+    /// Maps to [`aili_model::state::ProgramStateNode::custom_type_class`].
+    #[wasm_bindgen(method, getter, js_name = "className")]
+    pub fn class_name(this: &StateNodeDescription) -> Option<String>;
+
+    /// Gets the name of the source file that the node is located in.
+    ///
+    /// Maps to [`aili_model::state::ProgramStateNode::source_location`].
+    #[wasm_bindgen(method, getter, js_name = "sourceFile")]
+    pub fn source_file(this: &StateNodeDescription) -> Option<String>;
+
+    /// Gets the line of the source file that the node is located at.
+    ///
+    /// Maps to [`aili_model::state::ProgramStateNode::source_location`].
+    #[wasm_bindgen(method, getter, js_name = "sourceLine")]
+    pub fn source_line(this: &StateNodeDescription) -> Option<u32>;
+
     /// Gets the value of a node.
     ///
     /// Maps to [`aili_model::state::ProgramStateNode::value`].
@@ -243,6 +297,11 @@ impl StateNode {
         StateNode {
             type_class: description.type_kind().into(),
             type_name: description.type_name(),
+            class_name: description.class_name(),
+            source_location: description.source_file().map(|file| state::SourceLocation {
+                file,
+                line: description.source_line().unwrap_or_default().into(),
+            }),
             value: description.value().map(state::NodeValue::Int),
             successors: HashMap::new(),
         }
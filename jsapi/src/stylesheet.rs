@@ -1,8 +1,17 @@
 //! Simple interface to [`aili_style::stylesheet`].
 
-use aili_parser::{ParseError, parse_stylesheet};
+#[cfg(feature = "parser")]
+use crate::log::Severity;
+use aili_model::capability::CapabilitySet;
+#[cfg(feature = "parser")]
+use aili_parser::{
+    ParseError, ParseOptions, SyntaxLevel,
+    diagnostics::{self, Diagnostic},
+    parse_stylesheet_with_options,
+};
 use aili_style::{cascade::CascadeStyle, stylesheet};
 use aili_translate::property::PropertyKey;
+#[cfg(feature = "parser")]
 use js_sys::Function;
 use wasm_bindgen::prelude::*;
 
@@ -14,29 +23,75 @@ macro_rules! declare_stylesheet {
     ( $( #[ $attr:meta ] )* $name:ident ( $key:ty )) => {
         $( #[ $attr ] )*
         #[wasm_bindgen]
-        pub struct $name(pub(crate) CascadeStyle<$key>);
+        pub struct $name(pub(crate) CascadeStyle<$key>, pub(crate) CapabilitySet);
 
         #[wasm_bindgen]
         impl $name {
             /// Constructs an empty stylesheet.
             pub fn empty() -> Self {
-                Self(CascadeStyle::empty())
+                Self(CascadeStyle::empty(), CapabilitySet::new())
             }
 
-            /// Parses and compiles a stylesheet source using [`aili_parser`].
-            pub fn parse(source: &str, error_handler: Option<Function>) -> Result<Self, JsError> {
+            /// Parses and compiles a stylesheet source using `aili_parser`.
+            ///
+            /// Experimental syntax is only accepted if `experimental` is true.
+            /// Names listed in `custom_edge_labels` are recognized
+            /// as custom edge labels in selectors.
+            #[cfg(feature = "parser")]
+            pub fn parse(
+                source: &str,
+                error_handler: Option<Function>,
+                experimental: Option<bool>,
+                custom_edge_labels: Option<Vec<String>>,
+            ) -> Result<Self, JsError> {
                 let on_error = |err| {
                     if let Some(f) = &error_handler {
                         f.call1(&JsValue::NULL, &StylesheetParseError(err).into())
                             .expect("Uncaught exception thrown by callback passed to parse");
                     }
                 };
-                parse_stylesheet(source, on_error)
-                    .map(stylesheet::Stylesheet::map_key)
-                    .map(CascadeStyle::from)
-                    .map(Self)
+                let options = parse_options(experimental, custom_edge_labels);
+                parse_stylesheet_with_options(source, options, on_error)
+                    .map(Self::compile)
                     .map_err(JsError::from)
             }
+
+            /// Compiles a stylesheet that has been serialized to JSON,
+            /// such as one that has been parsed ahead of time.
+            #[cfg(feature = "json")]
+            #[wasm_bindgen(js_name = "fromJson")]
+            pub fn from_json(source: &str) -> Result<Self, JsError> {
+                serde_json::from_str(source)
+                    .map(Self::compile)
+                    .map_err(JsError::from)
+            }
+
+            /// Capabilities that the stylesheet requires,
+            /// as declared by `@requires` directives.
+            #[wasm_bindgen(getter)]
+            pub fn requirements(&self) -> Vec<String> {
+                self.1.iter().map(str::to_owned).collect()
+            }
+        }
+
+        impl $name {
+            /// Compiles a stylesheet with raw property keys.
+            #[allow(dead_code, reason = "Unused if stylesheets can only be constructed empty")]
+            fn compile(stylesheet: stylesheet::Stylesheet) -> Self {
+                let stylesheet: stylesheet::Stylesheet<$key> = stylesheet.map_key();
+                let requirements = stylesheet.requirements.clone();
+                Self(stylesheet.into(), requirements)
+            }
+
+            /// Verifies that a state graph provides all capabilities
+            /// that the stylesheet requires.
+            #[allow(dead_code, reason = "Not every stylesheet type is checked against a graph")]
+            pub(crate) fn check_requirements(
+                &self,
+                graph: &impl aili_model::state::ProgramStateGraph,
+            ) -> Result<(), stylesheet::MissingCapabilities> {
+                stylesheet::MissingCapabilities::check(&self.1, &graph.capabilities())
+            }
         }
     };
 }
@@ -59,9 +114,11 @@ declare_stylesheet! {
 /// encounters a recoverable syntax error.
 ///
 /// See [`aili_parser::ParseError`].
+#[cfg(feature = "parser")]
 #[wasm_bindgen]
 pub struct StylesheetParseError(ParseError);
 
+#[cfg(feature = "parser")]
 #[wasm_bindgen]
 impl StylesheetParseError {
     /// Message that describes the error.
@@ -70,3 +127,81 @@ impl StylesheetParseError {
         self.0.to_string()
     }
 }
+
+/// Parses a stylesheet source and collects problems in it,
+/// so they can be highlighted in an editor.
+///
+/// Accepts the same options as `Stylesheet.parse`.
+/// See [`aili_parser::diagnostics::lint_stylesheet`].
+#[cfg(feature = "parser")]
+#[wasm_bindgen(js_name = "lintStylesheet")]
+pub fn lint_stylesheet(
+    source: &str,
+    experimental: Option<bool>,
+    custom_edge_labels: Option<Vec<String>>,
+) -> Vec<StylesheetDiagnostic> {
+    let options = parse_options(experimental, custom_edge_labels);
+    diagnostics::lint_stylesheet(source, options)
+        .into_iter()
+        .map(StylesheetDiagnostic::from)
+        .collect()
+}
+
+/// Problem found in a stylesheet source by [`lint_stylesheet`].
+///
+/// See [`aili_parser::diagnostics::Diagnostic`].
+#[cfg(feature = "parser")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct StylesheetDiagnostic {
+    /// One-based number of the line where the problem occurred.
+    pub line: usize,
+
+    /// One-based number of the column where the problem occurred,
+    /// counted in characters.
+    pub column: usize,
+
+    /// How serious the problem is.
+    pub severity: Severity,
+
+    /// Stable identifier of the kind of problem,
+    /// such as `unexpected-token`.
+    pub code: String,
+
+    /// Human-readable description of the problem.
+    pub message: String,
+}
+
+#[cfg(feature = "parser")]
+impl From<Diagnostic> for StylesheetDiagnostic {
+    fn from(value: Diagnostic) -> Self {
+        let severity = match value.severity {
+            diagnostics::Severity::Error => Severity::Error,
+            diagnostics::Severity::Warning => Severity::Warning,
+        };
+        Self {
+            line: value.line_number,
+            column: value.column_number,
+            severity,
+            code: value.code.to_owned(),
+            message: value.message,
+        }
+    }
+}
+
+/// Constructs parser options from the arguments of the parsing functions.
+#[cfg(feature = "parser")]
+fn parse_options(
+    experimental: Option<bool>,
+    custom_edge_labels: Option<Vec<String>>,
+) -> ParseOptions {
+    let syntax_level = if experimental.unwrap_or(false) {
+        SyntaxLevel::Experimental
+    } else {
+        SyntaxLevel::Stable
+    };
+    ParseOptions {
+        syntax_level,
+        custom_edge_labels: custom_edge_labels.into_iter().flatten().collect(),
+        ..ParseOptions::default()
+    }
+}
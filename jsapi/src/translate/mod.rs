@@ -1,5 +1,6 @@
 //! Stylesheet resolution and updating of the visualization tree.
 
+mod patch;
 mod property_map;
 
 use crate::{
@@ -10,7 +11,8 @@ use crate::{
 };
 use aili_model::state::{ProgramStateGraph, RootedProgramStateGraph};
 use aili_style::selectable::Selectable;
-use aili_translate::forward::{VisTreeWriter, VisTreeWriterWarning};
+use aili_translate::forward::{VisTreeWriter, VisTreeWriterWarning, WarningSeverity};
+use patch::{PatchTree, RenderTarget, VisPatchList};
 use property_map::PropertyMapSnapshot;
 use wasm_bindgen::prelude::*;
 
@@ -20,9 +22,7 @@ use wasm_bindgen::prelude::*;
 /// so the bindings need to distinguish different types.
 macro_rules! declare_renderer {
     ( $name:ident ( $state:ty ) ) => {
-        /// Program state renderer that renders into a given [`VisTree`].
-        #[wasm_bindgen]
-        pub struct $name(VisTreeWriter<'static, <$state as ProgramStateGraph>::NodeId, VisTree>);
+        declare_renderer!($name($state) -> VisTree);
 
         #[wasm_bindgen]
         impl $name {
@@ -31,12 +31,67 @@ macro_rules! declare_renderer {
             pub fn new(tree: VisTree) -> Self {
                 Self(VisTreeWriter::new(tree))
             }
+        }
+    };
+    ( $name:ident ( $state:ty ) -> PatchTree ) => {
+        declare_renderer!(@common $name($state) -> PatchTree);
+
+        #[wasm_bindgen]
+        impl $name {
+            /// Constructs a new renderer that records changes
+            /// to the visualization as patches.
+            #[wasm_bindgen(constructor)]
+            pub fn new() -> Self {
+                Self(VisTreeWriter::new(PatchTree::default()))
+            }
+
+            /// Removes and returns the patches recorded since the last call.
+            ///
+            /// Patches made by each call to `applyStylesheet`
+            /// are followed by an `endBatch` marker. The visualization
+            /// is only consistent at the end of a batch.
+            #[wasm_bindgen(js_name = "takePatches")]
+            pub fn take_patches(&mut self) -> VisPatchList {
+                self.0.vis_tree_mut().take_patches()
+            }
+
+            /// Constructs a single batch of patches that builds
+            /// the current visualization from scratch.
+            ///
+            /// Patches that have not been taken yet are not affected,
+            /// and should be discarded if the snapshot is used.
+            pub fn snapshot(&self) -> VisPatchList {
+                self.0.vis_tree().snapshot()
+            }
+        }
 
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+    ( $name:ident ( $state:ty ) -> $tree:ty ) => {
+        declare_renderer!(@common $name($state) -> $tree);
+    };
+    ( @common $name:ident ( $state:ty ) -> $tree:ty ) => {
+        /// Program state renderer that renders into a visualization tree.
+        #[wasm_bindgen]
+        pub struct $name(VisTreeWriter<'static, <$state as ProgramStateGraph>::NodeId, $tree>);
+
+        #[wasm_bindgen]
+        impl $name {
             /// Sets the logger to which log messages from the renderer should be sent.
             #[wasm_bindgen(setter, js_name = "logger")]
             pub fn set_logger(&mut self, logger: Option<Logger>) {
                 self.0.set_warning_handler(logger.map(|logger| {
-                    let handler = move |w| logger.log(Severity::Warning, &format!("{w}"));
+                    let handler = move |w: VisTreeWriterWarning<_>| {
+                        let severity = match w.severity() {
+                            WarningSeverity::Warning => Severity::Warning,
+                            WarningSeverity::Error => Severity::Error,
+                        };
+                        logger.log(severity, &format!("{w}"));
+                    };
                     let boxed: Box<
                         dyn FnMut(VisTreeWriterWarning<<$state as ProgramStateGraph>::NodeId>),
                     > = Box::new(handler);
@@ -64,19 +119,63 @@ macro_rules! declare_renderer {
             }
 
             /// Resolves a [`Stylesheet`] over a state graph and renders the result.
+            ///
+            /// Fails without rendering anything if the stylesheet requires
+            /// capabilities that the state graph does not provide.
             #[wasm_bindgen(js_name = "applyStylesheet")]
-            pub fn apply_stylesheet(&mut self, stylesheet: &Stylesheet, graph: &$state) {
+            pub fn apply_stylesheet(
+                &mut self,
+                stylesheet: &Stylesheet,
+                graph: &$state,
+            ) -> Result<(), JsError> {
+                stylesheet.check_requirements(graph)?;
                 let mapping = aili_translate::cascade::apply_stylesheet(&stylesheet.0, graph);
-                self.0.update_root(Some(Selectable::node(graph.root())));
+                self.0.update_roots(vec![Selectable::node(graph.root())]);
                 self.0.update(mapping);
+                self.0.vis_tree_mut().end_update();
+                Ok(())
+            }
+
+            /// Resolves a [`Stylesheet`] over a state graph and checks the result
+            /// for structure violations without rendering it.
+            ///
+            /// Returns human-readable descriptions of all violations.
+            /// Missing capabilities that the stylesheet requires
+            /// are reported as a violation.
+            #[wasm_bindgen(js_name = "validateStylesheet")]
+            pub fn validate_stylesheet(
+                &self,
+                stylesheet: &Stylesheet,
+                graph: &$state,
+            ) -> Vec<String> {
+                let mapping = aili_translate::cascade::apply_stylesheet(&stylesheet.0, graph);
+                stylesheet
+                    .check_requirements(graph)
+                    .err()
+                    .map(|missing| format!("{missing}"))
+                    .into_iter()
+                    .chain(
+                        self.0
+                            .validate(&mapping)
+                            .into_iter()
+                            .map(|violation| format!("{violation}")),
+                    )
+                    .collect()
             }
         }
     };
 }
 
 declare_renderer!(VisTreeRenderer(StateGraph));
+declare_renderer!(PatchVisTreeRenderer(StateGraph) -> PatchTree);
+#[cfg(feature = "json")]
+declare_renderer!(JsonVisTreeRenderer(crate::json_state::JsonStateGraph));
+#[cfg(feature = "json")]
+declare_renderer!(JsonPatchVisTreeRenderer(crate::json_state::JsonStateGraph) -> PatchTree);
 #[cfg(feature = "gdbstate")]
 declare_renderer!(GdbVisTreeRenderer(crate::gdbstate::GdbStateGraph));
+#[cfg(feature = "gdbstate")]
+declare_renderer!(GdbPatchVisTreeRenderer(crate::gdbstate::GdbStateGraph) -> PatchTree);
 
 /// Resolves a [`Stylesheet`] over a [`StateGraph`] and renders
 /// the result into a [`VisTreeRenderer`].
@@ -85,6 +184,6 @@ pub fn apply_stylesheet(
     stylesheet: &Stylesheet,
     graph: &StateGraph,
     renderer: &mut VisTreeRenderer,
-) {
-    renderer.apply_stylesheet(stylesheet, graph);
+) -> Result<(), JsError> {
+    renderer.apply_stylesheet(stylesheet, graph)
 }
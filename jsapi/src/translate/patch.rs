@@ -0,0 +1,270 @@
+//! Visualization trees that are sent to Javascript
+//! as a stream of patches.
+
+use aili_model::vis::{InvalidHandle, VisTree};
+use aili_translate::mutation::{
+    Mutation, MutationRecorder, PinSide, RecordedConnectorRef, RecordedElementRef,
+};
+use js_sys::{Array, JsString, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPESCRIPT_INTERFACES: &str = r#"
+    /**
+     * Single change to a visualization tree.
+     *
+     * Elements and connectors are identified by numbers
+     * that are assigned in order of creation and never reused.
+     * Elements and connectors have separate sequences of identifiers.
+     */
+    type VisPatch =
+        | { type: "createElement", id: number, tagName: string }
+        | { type: "createConnector", id: number }
+        | { type: "setRoot", element: number | undefined }
+        | { type: "setRoots", elements: number[] }
+        | { type: "setParent", element: number, parent: number | undefined }
+        | { type: "setLayer", element: number, layer: number }
+        | { type: "setElementAttribute", element: number, name: string, value: string | undefined }
+        | { type: "setConnectorAttribute", connector: number, name: string, value: string | undefined }
+        | { type: "setPinAttribute", connector: number, pin: "start" | "end", name: string, value: string | undefined }
+        | { type: "setPinTarget", connector: number, pin: "start" | "end", target: number | undefined }
+        | { type: "setPinTargetConnector", connector: number, pin: "start" | "end", targetConnector: number }
+        | { type: "removeElement", element: number }
+        | { type: "removeConnector", connector: number }
+        | { type: "endBatch" };
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// List of patches, as seen by Javascript.
+    #[wasm_bindgen(typescript_type = "VisPatch[]")]
+    pub type VisPatchList;
+}
+
+/// Target of a renderer.
+pub trait RenderTarget: VisTree {
+    /// Notifies the target that the renderer has finished an update.
+    fn end_update(&mut self) {}
+}
+
+impl RenderTarget for crate::vis::VisTree {}
+
+/// [`VisTree`] that records changes as patches
+/// that Javascript can apply to its own tree.
+///
+/// Changes made by each update of the renderer
+/// form a batch, so Javascript can apply them all at once.
+#[derive(Debug, Default)]
+pub struct PatchTree {
+    /// Recorder of changes that have not been assigned to a batch yet.
+    recorder: MutationRecorder,
+
+    /// Batches that have not been taken by Javascript yet.
+    batches: Vec<Vec<Mutation>>,
+}
+
+impl PatchTree {
+    /// Removes all finished batches and converts them to Javascript objects.
+    ///
+    /// Each batch is followed by an `endBatch` marker.
+    pub fn take_patches(&mut self) -> VisPatchList {
+        let end_batch = Object::new();
+        set_field(&end_batch, "type", "endBatch");
+        std::mem::take(&mut self.batches)
+            .iter()
+            .flat_map(|batch| {
+                batch
+                    .iter()
+                    .map(patch_to_js)
+                    .chain(std::iter::once(end_batch.clone()))
+            })
+            .collect::<Array>()
+            .unchecked_into()
+    }
+
+    /// Constructs a batch that builds the current state
+    /// of the tree from scratch and converts it to Javascript objects.
+    pub fn snapshot(&self) -> VisPatchList {
+        let end_batch = Object::new();
+        set_field(&end_batch, "type", "endBatch");
+        self.recorder
+            .snapshot()
+            .iter()
+            .map(patch_to_js)
+            .chain(std::iter::once(end_batch))
+            .collect::<Array>()
+            .unchecked_into()
+    }
+}
+
+impl RenderTarget for PatchTree {
+    fn end_update(&mut self) {
+        let batch = self.recorder.take_mutations();
+        self.batches.push(batch);
+    }
+}
+
+impl VisTree for PatchTree {
+    type ElementHandle = usize;
+    type ConnectorHandle = usize;
+    type ElementRef<'a> = RecordedElementRef<'a>;
+    type ConnectorRef<'a> = RecordedConnectorRef<'a>;
+
+    fn set_root(&mut self, handle: Option<&Self::ElementHandle>) -> Result<(), InvalidHandle> {
+        self.recorder.set_root(handle)
+    }
+
+    fn set_roots(&mut self, handles: &[Self::ElementHandle]) -> Result<(), InvalidHandle> {
+        self.recorder.set_roots(handles)
+    }
+
+    fn add_element(&mut self, tag_name: &str) -> Self::ElementHandle {
+        self.recorder.add_element(tag_name)
+    }
+
+    fn add_connector(&mut self) -> Self::ConnectorHandle {
+        self.recorder.add_connector()
+    }
+
+    fn get_element(
+        &mut self,
+        handle: &Self::ElementHandle,
+    ) -> Result<Self::ElementRef<'_>, InvalidHandle> {
+        self.recorder.get_element(handle)
+    }
+
+    fn get_connector(
+        &mut self,
+        handle: &Self::ConnectorHandle,
+    ) -> Result<Self::ConnectorRef<'_>, InvalidHandle> {
+        self.recorder.get_connector(handle)
+    }
+
+    fn remove_element(&mut self, handle: &Self::ElementHandle) -> Result<(), InvalidHandle> {
+        self.recorder.remove_element(handle)
+    }
+
+    fn remove_connector(&mut self, handle: &Self::ConnectorHandle) -> Result<(), InvalidHandle> {
+        self.recorder.remove_connector(handle)
+    }
+}
+
+/// Converts a mutation to a plain Javascript object.
+///
+/// Plain objects are used instead of exported Rust types,
+/// so that Javascript does not have to free them.
+fn patch_to_js(mutation: &Mutation) -> Object {
+    let patch = Object::new();
+    match mutation {
+        Mutation::CreateElement { id, tag_name } => {
+            set_field(&patch, "type", "createElement");
+            set_field(&patch, "id", *id);
+            set_field(&patch, "tagName", tag_name.as_str());
+        }
+        Mutation::CreateConnector { id } => {
+            set_field(&patch, "type", "createConnector");
+            set_field(&patch, "id", *id);
+        }
+        Mutation::SetRoot { element } => {
+            set_field(&patch, "type", "setRoot");
+            set_field(&patch, "element", *element);
+        }
+        Mutation::SetRoots { elements } => {
+            set_field(&patch, "type", "setRoots");
+            set_field(
+                &patch,
+                "elements",
+                elements
+                    .iter()
+                    .copied()
+                    .map(JsValue::from)
+                    .collect::<Array>(),
+            );
+        }
+        Mutation::SetParent { element, parent } => {
+            set_field(&patch, "type", "setParent");
+            set_field(&patch, "element", *element);
+            set_field(&patch, "parent", *parent);
+        }
+        Mutation::SetLayer { element, layer } => {
+            set_field(&patch, "type", "setLayer");
+            set_field(&patch, "element", *element);
+            set_field(&patch, "layer", *layer);
+        }
+        Mutation::SetElementAttribute {
+            element,
+            name,
+            value,
+        } => {
+            set_field(&patch, "type", "setElementAttribute");
+            set_field(&patch, "element", *element);
+            set_field(&patch, "name", name.as_str());
+            set_field(&patch, "value", value.as_deref());
+        }
+        Mutation::SetConnectorAttribute {
+            connector,
+            name,
+            value,
+        } => {
+            set_field(&patch, "type", "setConnectorAttribute");
+            set_field(&patch, "connector", *connector);
+            set_field(&patch, "name", name.as_str());
+            set_field(&patch, "value", value.as_deref());
+        }
+        Mutation::SetPinAttribute {
+            connector,
+            pin,
+            name,
+            value,
+        } => {
+            set_field(&patch, "type", "setPinAttribute");
+            set_field(&patch, "connector", *connector);
+            set_field(&patch, "pin", pin_name(*pin));
+            set_field(&patch, "name", name.as_str());
+            set_field(&patch, "value", value.as_deref());
+        }
+        Mutation::SetPinTarget {
+            connector,
+            pin,
+            target,
+        } => {
+            set_field(&patch, "type", "setPinTarget");
+            set_field(&patch, "connector", *connector);
+            set_field(&patch, "pin", pin_name(*pin));
+            set_field(&patch, "target", *target);
+        }
+        Mutation::SetPinTargetConnector {
+            connector,
+            pin,
+            target_connector,
+        } => {
+            set_field(&patch, "type", "setPinTargetConnector");
+            set_field(&patch, "connector", *connector);
+            set_field(&patch, "pin", pin_name(*pin));
+            set_field(&patch, "targetConnector", *target_connector);
+        }
+        Mutation::RemoveElement { element } => {
+            set_field(&patch, "type", "removeElement");
+            set_field(&patch, "element", *element);
+        }
+        Mutation::RemoveConnector { connector } => {
+            set_field(&patch, "type", "removeConnector");
+            set_field(&patch, "connector", *connector);
+        }
+    }
+    patch
+}
+
+/// Gets the name of a connector pin as seen by Javascript.
+fn pin_name(pin: PinSide) -> &'static str {
+    match pin {
+        PinSide::Start => "start",
+        PinSide::End => "end",
+    }
+}
+
+/// Sets a field of a plain Javascript object.
+fn set_field(object: &Object, name: &str, value: impl Into<JsValue>) {
+    Reflect::set(object, &JsString::from(name), &value.into())
+        .expect("Types of all objects are verified, this should never fail");
+}
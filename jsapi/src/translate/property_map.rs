@@ -12,6 +12,10 @@ pub enum PropertyKeyType {
     Display = "display",
     Parent = "parent",
     Target = "target",
+    StableKey = "stable-key",
+    ExtraIndex = "extra-index",
+    Detach = "detach",
+    Layer = "layer",
     Attribute = "attr",
 }
 
@@ -116,13 +120,37 @@ impl PropertyMapSnapshot {
                 format!("{target:?}"),
             ));
         }
+        if let Some(stable_key) = &props.stable_key {
+            properties.push(PropertyMapEntry::from_key_value(
+                PropertyKeyType::StableKey,
+                stable_key.clone(),
+            ));
+        }
+        if let Some(extra_index) = &props.extra_index {
+            properties.push(PropertyMapEntry::from_key_value(
+                PropertyKeyType::ExtraIndex,
+                extra_index.to_string(),
+            ));
+        }
+        if props.detached {
+            properties.push(PropertyMapEntry::from_key_value(
+                PropertyKeyType::Detach,
+                "true".to_owned(),
+            ));
+        }
+        if let Some(layer) = &props.layer {
+            properties.push(PropertyMapEntry::from_key_value(
+                PropertyKeyType::Layer,
+                layer.to_string(),
+            ));
+        }
         for (attr, value) in &props.attributes {
-            properties.push(PropertyMapEntry::attribute(attr.clone(), value.clone()));
+            properties.push(PropertyMapEntry::attribute(attr.to_string(), value.clone()));
         }
         for (fragment, attrs) in &props.fragment_attributes {
             for (attr, value) in attrs {
                 properties.push(PropertyMapEntry::fragment_attribute(
-                    attr.clone(),
+                    attr.to_string(),
                     value.clone(),
                     (*fragment).into(),
                 ));
@@ -42,6 +42,12 @@ const TYPESCRIPT_INTERFACES: &str = r"
          * @throws when the update would create a cycle in the tree.
          */
         set parent(parent: VisElement | undefined);
+        /**
+         * Updates the layer of the element.
+         * Elements in higher layers are drawn above elements in lower layers,
+         * regardless of their position in the tree.
+         */
+        set layer(layer: number);
     }
     /**
      * Connector that can connect two {@link VisElement}s.
@@ -69,9 +75,10 @@ const TYPESCRIPT_INTERFACES: &str = r"
          */
         readonly attributes: AttributeMap;
         /**
-         * Updates what element the pin is attached to.
+         * Updates what element the pin is attached to,
+         * or attaches it to the midpoint of another connector.
          */
-        set target(target: VisElement | undefined);
+        set target(target: VisElement | VisConnector | undefined);
     }
 ";
 
@@ -127,11 +134,19 @@ extern "C" {
     #[wasm_bindgen(method, setter, js_name = "target")]
     pub fn attach_to(this: &VisPin, target: Option<&VisElement>);
 
+    /// Attaches a pin to the midpoint of another connector.
+    #[wasm_bindgen(method, setter, js_name = "target")]
+    pub fn attach_to_connector(this: &VisPin, target: Option<&VisConnector>);
+
     /// Updates an element's parent element.
     ///
     /// Fails if the operation would create a loop.
     #[wasm_bindgen(method, setter, js_name = "parent", catch)]
     pub fn insert_into(this: &VisElement, parent: Option<&VisElement>) -> Result<(), JsValue>;
+
+    /// Updates an element's layer.
+    #[wasm_bindgen(method, setter, js_name = "layer")]
+    pub fn set_layer(this: &VisElement, layer: i32);
 }
 
 /// Updates an attribute in an attribute map.
@@ -204,6 +219,10 @@ impl vis::VisElement for VisElement {
         VisElement::insert_into(self, parent)
             .map_err(|_| vis::ParentAssignmentError::StructureViolation)
     }
+
+    fn set_layer(&mut self, layer: i32) {
+        VisElement::set_layer(self, layer);
+    }
 }
 
 impl vis::AttributeMap for VisConnector {
@@ -218,6 +237,7 @@ impl vis::AttributeMap for VisConnector {
 
 impl vis::VisConnector for VisConnector {
     type Handle = VisElement;
+    type ConnectorHandle = VisConnector;
     type PinRef<'a> = VisPin;
 
     fn start_mut(&mut self) -> Self::PinRef<'_> {
@@ -241,9 +261,18 @@ impl vis::AttributeMap for VisPin {
 
 impl vis::VisPin for VisPin {
     type Handle = VisElement;
+    type ConnectorHandle = VisConnector;
 
     fn attach_to(&mut self, target: Option<&Self::Handle>) -> Result<(), vis::InvalidHandle> {
         VisPin::attach_to(self, target);
         Ok(())
     }
+
+    fn attach_to_connector(
+        &mut self,
+        target: Option<&Self::ConnectorHandle>,
+    ) -> Result<(), vis::InvalidHandle> {
+        VisPin::attach_to_connector(self, target);
+        Ok(())
+    }
 }
@@ -0,0 +1,257 @@
+//! Program state graphs assembled node by node.
+//!
+//! [`StateGraphBuilder`] constructs a [`StateGraph`], a simple in-memory
+//! [`RootedProgramStateGraph`] whose nodes are identified by consecutive
+//! integers, with the root at zero. Unlike the fixture graphs
+//! used in tests, nodes must be added before they are connected,
+//! and edges are validated as they are added.
+//!
+//! ```
+//! use aili_model::{builder::StateGraphBuilder, state::*};
+//!
+//! let mut builder = StateGraphBuilder::new();
+//! let frame = builder.add_node(NodeTypeClass::Frame, None);
+//! let variable = builder.add_node(NodeTypeClass::Atom, Some(NodeValue::Int(5)));
+//! builder.add_edge(builder.root(), EdgeLabel::Main, frame).unwrap();
+//! builder.add_edge(frame, EdgeLabel::Named("a".into(), 0), variable).unwrap();
+//! // Each edge that leaves a node must have a distinct label
+//! assert!(builder.add_edge(builder.root(), EdgeLabel::Main, variable).is_err());
+//!
+//! let graph = builder.build();
+//! let frame = graph.get(&graph.root()).unwrap().get_successor(&EdgeLabel::Main);
+//! assert_eq!(frame, Some(1));
+//! ```
+
+use crate::{
+    capability::CapabilitySet,
+    state::{
+        EdgeLabel, NodeTypeClass, NodeValue, ProgramStateGraph, ProgramStateNode,
+        RootedProgramStateGraph, SourceLocation,
+    },
+};
+use derive_more::{Display, Error};
+
+/// Error type of [`StateGraphBuilder::add_edge`].
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+pub enum StateGraphError {
+    /// An edge refers to a node that has not been added.
+    #[display("node {_0} does not exist")]
+    MissingNode(#[error(not(source))] usize),
+
+    /// A node already has an edge with the same label.
+    #[display("node {from} already has an edge labeled {label:?}")]
+    DuplicateEdge {
+        /// Source node of the edges.
+        from: usize,
+
+        /// Label shared by the edges.
+        #[error(not(source))]
+        label: EdgeLabel,
+    },
+}
+
+/// Incrementally constructs a [`StateGraph`].
+///
+/// The builder starts with a single node of the
+/// [root](NodeTypeClass::Root) class.
+#[derive(Clone, Debug, Default)]
+pub struct StateGraphBuilder {
+    graph: StateGraph,
+}
+
+impl StateGraphBuilder {
+    /// Constructs a builder of a graph that only has a root node.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the identifier of the root node.
+    pub fn root(&self) -> usize {
+        0
+    }
+
+    /// Adds a node to the graph and returns its identifier.
+    ///
+    /// Nodes are identified by consecutive integers
+    /// in the order in which they are added.
+    pub fn add_node(&mut self, type_class: NodeTypeClass, value: Option<NodeValue>) -> usize {
+        self.graph.nodes.push(StateNode {
+            type_class,
+            value,
+            ..Default::default()
+        });
+        self.graph.nodes.len() - 1
+    }
+
+    /// Adds an edge between two nodes.
+    ///
+    /// Fails if either node has not been added, or if the source node
+    /// already has an edge with the same label, in which case
+    /// the graph is left unchanged.
+    pub fn add_edge(
+        &mut self,
+        from: usize,
+        edge_label: EdgeLabel,
+        to: usize,
+    ) -> Result<(), StateGraphError> {
+        if to >= self.graph.nodes.len() {
+            return Err(StateGraphError::MissingNode(to));
+        }
+        let node = self
+            .graph
+            .nodes
+            .get_mut(from)
+            .ok_or(StateGraphError::MissingNode(from))?;
+        if node
+            .successors
+            .iter()
+            .any(|(label, _)| *label == edge_label)
+        {
+            return Err(StateGraphError::DuplicateEdge {
+                from,
+                label: edge_label,
+            });
+        }
+        node.successors.push((edge_label, to));
+        Ok(())
+    }
+
+    /// Gets a node that has already been added,
+    /// so that its remaining details can be set.
+    pub fn node_mut(&mut self, id: usize) -> Option<&mut StateNode> {
+        self.graph.nodes.get_mut(id)
+    }
+
+    /// Sets the capabilities that the graph declares.
+    pub fn with_capabilities(mut self, capabilities: CapabilitySet) -> Self {
+        self.graph.capabilities = capabilities;
+        self
+    }
+
+    /// Finishes the construction of the graph.
+    pub fn build(self) -> StateGraph {
+        self.graph
+    }
+}
+
+/// Program state graph constructed by a [`StateGraphBuilder`].
+#[derive(Clone, Debug)]
+pub struct StateGraph {
+    nodes: Vec<StateNode>,
+    capabilities: CapabilitySet,
+}
+
+impl Default for StateGraph {
+    fn default() -> Self {
+        let root = StateNode {
+            type_class: NodeTypeClass::Root,
+            ..Default::default()
+        };
+        Self {
+            nodes: vec![root],
+            capabilities: CapabilitySet::new(),
+        }
+    }
+}
+
+impl ProgramStateGraph for StateGraph {
+    type NodeId = usize;
+    type NodeRef<'a> = &'a StateNode;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        self.nodes.get(*id)
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        self.capabilities.clone()
+    }
+}
+
+impl RootedProgramStateGraph for StateGraph {
+    fn root(&self) -> Self::NodeId {
+        0
+    }
+}
+
+/// Node of [`StateGraph`].
+///
+/// Successors are kept in a list, so they are always
+/// traversed in the order in which they are added.
+#[derive(Clone, Debug)]
+pub struct StateNode {
+    successors: Vec<(EdgeLabel, usize)>,
+    value: Option<NodeValue>,
+    type_class: NodeTypeClass,
+    type_id: Option<String>,
+    custom_type_class: Option<String>,
+    source_location: Option<SourceLocation>,
+}
+
+impl StateNode {
+    /// Sets the value of the node.
+    pub fn set_value(&mut self, value: Option<NodeValue>) {
+        self.value = value;
+    }
+
+    /// Makes the node's type class [custom](NodeTypeClass::Custom).
+    pub fn set_custom_type_class(&mut self, name: impl Into<String>) {
+        self.type_class = NodeTypeClass::Custom;
+        self.custom_type_class = Some(name.into());
+    }
+
+    /// Sets the type ID of the node.
+    pub fn set_type_id(&mut self, type_id: Option<String>) {
+        self.type_id = type_id;
+    }
+
+    /// Sets the location in the source code
+    /// that the node is associated with.
+    pub fn set_source_location(&mut self, source_location: Option<SourceLocation>) {
+        self.source_location = source_location;
+    }
+}
+
+impl Default for StateNode {
+    fn default() -> Self {
+        Self {
+            successors: Vec::new(),
+            value: None,
+            type_class: NodeTypeClass::Atom,
+            type_id: None,
+            custom_type_class: None,
+            source_location: None,
+        }
+    }
+}
+
+impl ProgramStateNode for &StateNode {
+    type NodeId = usize;
+    type NodeTypeId<'a>
+        = &'a str
+    where
+        Self: 'a;
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
+        self.successors
+            .iter()
+            .find(|(label, _)| label == edge)
+            .map(|(_, target)| *target)
+    }
+    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
+        self.successors
+            .iter()
+            .map(|(label, target)| (label, *target))
+    }
+    fn node_type_class(&self) -> NodeTypeClass {
+        self.type_class
+    }
+    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
+        self.type_id.as_deref()
+    }
+    fn custom_type_class(&self) -> Option<&str> {
+        self.custom_type_class.as_deref()
+    }
+    fn value(&self) -> Option<NodeValue> {
+        self.value.clone()
+    }
+    fn source_location(&self) -> Option<SourceLocation> {
+        self.source_location.clone()
+    }
+}
@@ -0,0 +1,105 @@
+//! Features that a program state graph may provide
+//! and that stylesheets may depend on.
+
+use std::collections::BTreeSet;
+
+/// Capability of graphs constructed by the GDB backend.
+pub const GDB: &str = "gdb";
+
+/// Capability of graphs constructed by the Python backend.
+pub const PYTHON: &str = "python";
+
+/// Capability of graphs that contain [`Array`](crate::state::NodeTypeClass::Array) nodes.
+pub const ARRAYS: &str = "arrays";
+
+/// Capability of graphs that contain [`Ref`](crate::state::NodeTypeClass::Ref) nodes.
+pub const POINTERS: &str = "pointers";
+
+/// Capability of graphs whose nodes may have
+/// [`Float`](crate::state::NodeValue::Float) values.
+pub const FLOAT_VALUES: &str = "float-values";
+
+/// Capability of graphs whose nodes may have
+/// [`String`](crate::state::NodeValue::String) values.
+pub const STRING_VALUES: &str = "string-values";
+
+/// Capability of graphs that list blocks of heap memory
+/// that are not reachable from any visible pointer.
+pub const HEAP_BLOCKS: &str = "heap-blocks";
+
+/// Capability of graphs that show the stacks of all threads,
+/// not only of the thread that is selected.
+pub const THREADS: &str = "threads";
+
+/// Capability of graphs that show the values of CPU registers.
+pub const REGISTERS: &str = "registers";
+
+/// Capability of graphs that show the values
+/// of expressions that the user has asked to watch.
+pub const WATCH_EXPRESSIONS: &str = "watch-expressions";
+
+/// Capability of graphs that merge identical read-only objects
+/// into [shared](crate::state::ProgramStateNode::is_shared) nodes.
+pub const SHARED_NODES: &str = "shared-nodes";
+
+/// Set of named capabilities.
+///
+/// Capabilities are identified by their names. Names of capabilities
+/// that are recognized by the Aili backends are provided as constants
+/// in this module, but any name may be used.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapabilitySet(BTreeSet<String>);
+
+impl CapabilitySet {
+    /// Constructs an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a capability to the set.
+    pub fn with(mut self, capability: impl Into<String>) -> Self {
+        self.insert(capability);
+        self
+    }
+
+    /// Adds a capability to the set.
+    pub fn insert(&mut self, capability: impl Into<String>) {
+        self.0.insert(capability.into());
+    }
+
+    /// Checks whether the set contains a capability.
+    pub fn contains(&self, capability: &str) -> bool {
+        self.0.contains(capability)
+    }
+
+    /// Checks whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over the names of capabilities in the set,
+    /// in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    /// Iterates over the capabilities in this set
+    /// that are missing from another set, in alphabetical order.
+    pub fn missing_from<'a>(&'a self, provided: &'a Self) -> impl Iterator<Item = &'a str> {
+        self.iter()
+            .filter(|capability| !provided.contains(capability))
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for CapabilitySet {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        Self(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<S: Into<String>> Extend<S> for CapabilitySet {
+    fn extend<T: IntoIterator<Item = S>>(&mut self, iter: T) {
+        self.0.extend(iter.into_iter().map(Into::into));
+    }
+}
@@ -0,0 +1,237 @@
+//! State graphs cut off at a distance from the root.
+//!
+//! Enormous data structures, such as long linked lists,
+//! make every traversal of the graph slow, even if only
+//! their beginnings are ever shown. [`DepthLimitedGraph`]
+//! cuts the graph off at a fixed distance from the root
+//! and replaces everything beyond it with
+//! [`Truncated`](NodeTypeClass::Truncated) leaves,
+//! so stylesheets can still indicate where the graph continues.
+//!
+//! ```
+//! use aili_model::{builder::StateGraphBuilder, depth::*, state::*};
+//!
+//! let mut builder = StateGraphBuilder::new();
+//! let frame = builder.add_node(NodeTypeClass::Frame, None);
+//! let pointer = builder.add_node(NodeTypeClass::Ref, None);
+//! let target = builder.add_node(NodeTypeClass::Atom, Some(NodeValue::Int(5)));
+//! builder.add_edge(builder.root(), EdgeLabel::Main, frame).unwrap();
+//! builder.add_edge(frame, EdgeLabel::Named("p".into(), 0), pointer).unwrap();
+//! builder.add_edge(pointer, EdgeLabel::Deref, target).unwrap();
+//! let graph = builder.build();
+//!
+//! let limited = DepthLimitedGraph::new(&graph, 2);
+//! let pointer = limited.get_id_at_root(&[EdgeLabel::Main, EdgeLabel::Named("p".into(), 0)]);
+//! assert_eq!(pointer, Some(DepthLimitedNodeId::Node(2)));
+//! let target = limited.get_at(&pointer.unwrap(), &[EdgeLabel::Deref]).unwrap();
+//! assert_eq!(target.node_type_class(), NodeTypeClass::Truncated);
+//! assert_eq!(target.successors().count(), 0);
+//!
+//! // Only pointers count towards the depth
+//! let limited = DepthLimitedGraph::new(&graph, 0).with_only_dereferences_counted();
+//! let target = limited.get_at_root(&[
+//!     EdgeLabel::Main,
+//!     EdgeLabel::Named("p".into(), 0),
+//!     EdgeLabel::Deref,
+//! ]);
+//! assert_eq!(target.unwrap().node_type_class(), NodeTypeClass::Truncated);
+//! ```
+
+use crate::{capability::CapabilitySet, state::*};
+use derive_more::Debug;
+use std::collections::{HashMap, VecDeque};
+
+/// Identifier of a node of a [`DepthLimitedGraph`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DepthLimitedNodeId<T> {
+    /// Node of the underlying graph that is within the limit.
+    #[debug("{_0:?}")]
+    Node(T),
+
+    /// Leaf that stands in for a node of the underlying graph
+    /// that is beyond the limit.
+    #[debug("truncated({_0:?})")]
+    Truncated(T),
+}
+
+/// View of a state graph that only includes the nodes
+/// that are at most a given number of edges away from the root.
+///
+/// Edges that lead beyond the limit lead to
+/// [`Truncated`](NodeTypeClass::Truncated) leaves instead,
+/// one for each node beyond the limit that is reached that way.
+///
+/// The distances of nodes are measured when the view is constructed,
+/// along the shortest paths from the root, so a node that is reachable
+/// both within and beyond the limit is included.
+#[derive(Debug)]
+#[debug("DepthLimitedGraph")]
+pub struct DepthLimitedGraph<'g, G: ProgramStateGraph> {
+    graph: &'g G,
+    max_depth: usize,
+    only_dereferences: bool,
+    depths: HashMap<G::NodeId, usize>,
+}
+
+impl<'g, G: RootedProgramStateGraph> DepthLimitedGraph<'g, G> {
+    /// Wraps a state graph, cutting it off after
+    /// a given number of edges from the root.
+    pub fn new(graph: &'g G, max_depth: usize) -> Self {
+        let mut limited = Self {
+            graph,
+            max_depth,
+            only_dereferences: false,
+            depths: HashMap::new(),
+        };
+        limited.measure_depths();
+        limited
+    }
+
+    /// Only counts [`Deref`](EdgeLabel::Deref) edges towards the depth,
+    /// so the limit is on the number of pointers followed from a variable.
+    pub fn with_only_dereferences_counted(mut self) -> Self {
+        self.only_dereferences = true;
+        self.measure_depths();
+        self
+    }
+
+    /// Gets the underlying graph.
+    pub fn inner(&self) -> &'g G {
+        self.graph
+    }
+
+    /// Gets the number of nodes of the underlying graph that are within the limit.
+    pub fn node_count(&self) -> usize {
+        self.depths.len()
+    }
+
+    /// Finds the distances of all nodes within the limit from the root.
+    fn measure_depths(&mut self) {
+        self.depths.clear();
+        let root = self.graph.root();
+        self.depths.insert(root.clone(), 0);
+        // Edges that do not count towards the depth are explored first,
+        // so that each node is first reached along its shortest path
+        let mut pending = VecDeque::from([(root, 0)]);
+        while let Some((id, depth)) = pending.pop_front() {
+            if self.depths.get(&id).is_some_and(|known| *known < depth) {
+                continue;
+            }
+            let Some(node) = self.graph.get(&id) else {
+                continue;
+            };
+            for (edge, successor) in node.successors() {
+                let counts = !self.only_dereferences || *edge == EdgeLabel::Deref;
+                let successor_depth = depth + usize::from(counts);
+                if successor_depth > self.max_depth
+                    || self
+                        .depths
+                        .get(&successor)
+                        .is_some_and(|known| *known <= successor_depth)
+                {
+                    continue;
+                }
+                self.depths.insert(successor.clone(), successor_depth);
+                if counts {
+                    pending.push_back((successor, successor_depth));
+                } else {
+                    pending.push_front((successor, successor_depth));
+                }
+            }
+        }
+    }
+}
+
+impl<'g, G: ProgramStateGraph> ProgramStateGraph for DepthLimitedGraph<'g, G> {
+    type NodeId = DepthLimitedNodeId<G::NodeId>;
+    type NodeRef<'a>
+        = DepthLimitedNodeRef<'a, 'g, G>
+    where
+        Self: 'a;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        match id {
+            DepthLimitedNodeId::Node(id) if self.depths.contains_key(id) => {
+                Some(DepthLimitedNodeRef {
+                    node: Some(self.graph.get(id)?),
+                    limited: self,
+                })
+            }
+            DepthLimitedNodeId::Truncated(id) if !self.depths.contains_key(id) => {
+                self.graph.get(id)?;
+                Some(DepthLimitedNodeRef {
+                    node: None,
+                    limited: self,
+                })
+            }
+            _ => None,
+        }
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        self.graph.capabilities()
+    }
+}
+
+impl<G: RootedProgramStateGraph> RootedProgramStateGraph for DepthLimitedGraph<'_, G> {
+    fn root(&self) -> Self::NodeId {
+        DepthLimitedNodeId::Node(self.graph.root())
+    }
+}
+
+/// Reference to a node of a [`DepthLimitedGraph`].
+#[derive(Debug)]
+#[debug("DepthLimitedNodeRef")]
+pub struct DepthLimitedNodeRef<'a, 'g, G: ProgramStateGraph> {
+    /// The node of the underlying graph,
+    /// or [`None`] if this is a truncated leaf.
+    node: Option<G::NodeRef<'a>>,
+    limited: &'a DepthLimitedGraph<'g, G>,
+}
+
+impl<G: ProgramStateGraph> DepthLimitedNodeRef<'_, '_, G> {
+    /// Wraps the identifier of a successor.
+    fn successor_id(&self, id: G::NodeId) -> DepthLimitedNodeId<G::NodeId> {
+        if self.limited.depths.contains_key(&id) {
+            DepthLimitedNodeId::Node(id)
+        } else {
+            DepthLimitedNodeId::Truncated(id)
+        }
+    }
+}
+
+impl<'a, G: ProgramStateGraph> ProgramStateNode for DepthLimitedNodeRef<'a, '_, G> {
+    type NodeId = DepthLimitedNodeId<G::NodeId>;
+    type NodeTypeId<'b>
+        = <G::NodeRef<'a> as ProgramStateNode>::NodeTypeId<'b>
+    where
+        Self: 'b;
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
+        let successor = self.node.as_ref()?.get_successor(edge)?;
+        Some(self.successor_id(successor))
+    }
+    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
+        self.node.iter().flat_map(|node| {
+            node.successors()
+                .map(|(edge, successor)| (edge, self.successor_id(successor)))
+        })
+    }
+    fn node_type_class(&self) -> NodeTypeClass {
+        self.node
+            .as_ref()
+            .map_or(NodeTypeClass::Truncated, |node| node.node_type_class())
+    }
+    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
+        self.node.as_ref()?.node_type_id()
+    }
+    fn custom_type_class(&self) -> Option<&str> {
+        self.node.as_ref()?.custom_type_class()
+    }
+    fn value(&self) -> Option<NodeValue> {
+        self.node.as_ref()?.value()
+    }
+    fn source_location(&self) -> Option<SourceLocation> {
+        self.node.as_ref()?.source_location()
+    }
+    fn is_shared(&self) -> bool {
+        self.node.as_ref().is_some_and(|node| node.is_shared())
+    }
+}
@@ -0,0 +1,293 @@
+//! Type-erased program state graphs.
+//!
+//! Functions that process state graphs are generic over the type
+//! of the graph, so graphs of different types cannot be stored
+//! or passed around behind a single interface directly.
+//! [`DynStateGraph`] wraps any [`RootedProgramStateGraph`]
+//! and hides its type, so the graph that is processed
+//! can be chosen at runtime.
+
+use crate::{capability::CapabilitySet, state::*};
+use derive_more::Debug;
+use std::{
+    any::{Any, TypeId},
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+/// Identifier of a node of a [`DynStateGraph`].
+///
+/// Wraps the identifier of the node in the underlying graph.
+/// Identifiers are only equal if they wrap identifiers
+/// of the same type that are equal.
+#[derive(Clone, Debug)]
+#[debug("{_0:?}")]
+pub struct DynNodeId(Rc<dyn ErasedNodeId>);
+
+impl DynNodeId {
+    /// Wraps an identifier of a node of a concrete graph.
+    pub fn new<T: NodeId + 'static>(id: T) -> Self {
+        Self(Rc::new(id))
+    }
+
+    /// Gets the wrapped identifier if it is of the specified type.
+    pub fn downcast_ref<T: NodeId + 'static>(&self) -> Option<&T> {
+        self.0.as_any().downcast_ref()
+    }
+}
+
+impl PartialEq for DynNodeId {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.dyn_eq(other.0.as_ref())
+    }
+}
+
+impl Eq for DynNodeId {}
+
+impl Hash for DynNodeId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.dyn_hash(state);
+    }
+}
+
+/// Object-safe counterpart of [`NodeId`].
+trait ErasedNodeId: std::fmt::Debug {
+    fn as_any(&self) -> &dyn Any;
+    fn dyn_eq(&self, other: &dyn ErasedNodeId) -> bool;
+    fn dyn_hash(&self, state: &mut dyn Hasher);
+}
+
+impl<T: NodeId + 'static> ErasedNodeId for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn dyn_eq(&self, other: &dyn ErasedNodeId) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        TypeId::of::<T>().hash(&mut state);
+        self.hash(&mut state);
+    }
+}
+
+/// Object-safe counterpart of [`ProgramStateNode`].
+///
+/// Implemented for all state nodes whose identifiers
+/// are [`'static`](https://doc.rust-lang.org/rust-by-example/scope/lifetime/static_lifetime.html#trait-bound).
+/// Nodes of a [`DynStateGraph`] are accessed through this trait.
+pub trait DynProgramStateNode {
+    /// See [`ProgramStateNode::get_successor`].
+    fn dyn_get_successor(&self, edge: &EdgeLabel) -> Option<DynNodeId>;
+
+    /// See [`ProgramStateNode::successors`].
+    fn dyn_successors(&self) -> Box<dyn Iterator<Item = (&EdgeLabel, DynNodeId)> + '_>;
+
+    /// See [`ProgramStateNode::node_type_class`].
+    fn dyn_node_type_class(&self) -> NodeTypeClass;
+
+    /// Gets the name of the node's type,
+    /// see [`ProgramStateNode::node_type_id`].
+    fn dyn_node_type_name(&self) -> Option<String>;
+
+    /// See [`ProgramStateNode::custom_type_class`].
+    fn dyn_custom_type_class(&self) -> Option<&str>;
+
+    /// See [`ProgramStateNode::value`].
+    fn dyn_value(&self) -> Option<NodeValue>;
+
+    /// See [`ProgramStateNode::source_location`].
+    fn dyn_source_location(&self) -> Option<SourceLocation>;
+
+    /// See [`ProgramStateNode::is_shared`].
+    fn dyn_is_shared(&self) -> bool;
+}
+
+impl<T: ProgramStateNode> DynProgramStateNode for T
+where
+    T::NodeId: 'static,
+{
+    fn dyn_get_successor(&self, edge: &EdgeLabel) -> Option<DynNodeId> {
+        self.get_successor(edge).map(DynNodeId::new)
+    }
+    fn dyn_successors(&self) -> Box<dyn Iterator<Item = (&EdgeLabel, DynNodeId)> + '_> {
+        Box::new(
+            self.successors()
+                .map(|(edge, id)| (edge, DynNodeId::new(id))),
+        )
+    }
+    fn dyn_node_type_class(&self) -> NodeTypeClass {
+        self.node_type_class()
+    }
+    fn dyn_node_type_name(&self) -> Option<String> {
+        self.node_type_id().map(|id| id.type_name().to_owned())
+    }
+    fn dyn_custom_type_class(&self) -> Option<&str> {
+        self.custom_type_class()
+    }
+    fn dyn_value(&self) -> Option<NodeValue> {
+        self.value()
+    }
+    fn dyn_source_location(&self) -> Option<SourceLocation> {
+        self.source_location()
+    }
+    fn dyn_is_shared(&self) -> bool {
+        self.is_shared()
+    }
+}
+
+/// Reference to a node of a [`DynStateGraph`].
+pub type DynNodeRef<'a> = Box<dyn DynProgramStateNode + 'a>;
+
+impl ProgramStateNode for DynNodeRef<'_> {
+    type NodeId = DynNodeId;
+    type NodeTypeId<'a>
+        = String
+    where
+        Self: 'a;
+    // The methods must be called on the boxed node explicitly,
+    // the box itself also implements the object-safe trait
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
+        (**self).dyn_get_successor(edge)
+    }
+    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
+        (**self).dyn_successors()
+    }
+    fn node_type_class(&self) -> NodeTypeClass {
+        (**self).dyn_node_type_class()
+    }
+    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
+        (**self).dyn_node_type_name()
+    }
+    fn custom_type_class(&self) -> Option<&str> {
+        (**self).dyn_custom_type_class()
+    }
+    fn value(&self) -> Option<NodeValue> {
+        (**self).dyn_value()
+    }
+    fn source_location(&self) -> Option<SourceLocation> {
+        (**self).dyn_source_location()
+    }
+    fn is_shared(&self) -> bool {
+        (**self).dyn_is_shared()
+    }
+}
+
+/// Object-safe counterpart of [`RootedProgramStateGraph`].
+///
+/// Implemented for all rooted state graphs whose node identifiers
+/// are [`'static`](https://doc.rust-lang.org/rust-by-example/scope/lifetime/static_lifetime.html#trait-bound).
+pub trait DynProgramStateGraph {
+    /// See [`ProgramStateGraph::get`].
+    ///
+    /// Returns [`None`] if the identifier is not
+    /// of the type that the graph uses.
+    fn dyn_get(&self, id: &DynNodeId) -> Option<DynNodeRef<'_>>;
+
+    /// See [`RootedProgramStateGraph::root`].
+    fn dyn_root(&self) -> DynNodeId;
+
+    /// See [`ProgramStateGraph::capabilities`].
+    fn dyn_capabilities(&self) -> CapabilitySet;
+}
+
+impl<T: RootedProgramStateGraph> DynProgramStateGraph for T
+where
+    T::NodeId: 'static,
+{
+    fn dyn_get(&self, id: &DynNodeId) -> Option<DynNodeRef<'_>> {
+        let node = self.get(id.downcast_ref()?)?;
+        Some(Box::new(node))
+    }
+    fn dyn_root(&self) -> DynNodeId {
+        DynNodeId::new(self.root())
+    }
+    fn dyn_capabilities(&self) -> CapabilitySet {
+        self.capabilities()
+    }
+}
+
+/// State graph of a type that is only known at runtime.
+///
+/// Any [`RootedProgramStateGraph`] can be wrapped, either by value
+/// or by reference, and the wrapper can then be passed to all functions
+/// that expect a state graph. Nodes of the wrapper are identified
+/// by [`DynNodeId`]s that wrap the identifiers of the underlying graph.
+///
+/// ```
+/// # use aili_model::{erased::DynStateGraph, state::*};
+/// fn count_root_successors(graph: &impl RootedProgramStateGraph) -> usize {
+///     graph.get(&graph.root()).unwrap().successors().count()
+/// }
+///
+/// fn count_root_successors_of_any(graphs: &[DynStateGraph]) -> Vec<usize> {
+///     graphs.iter().map(count_root_successors).collect()
+/// }
+/// ```
+#[derive(Debug)]
+#[debug("DynStateGraph")]
+pub struct DynStateGraph<'g>(Box<dyn DynProgramStateGraph + 'g>);
+
+impl<'g> DynStateGraph<'g> {
+    /// Wraps a state graph, taking ownership of it.
+    pub fn new(graph: impl DynProgramStateGraph + 'g) -> Self {
+        Self(Box::new(graph))
+    }
+
+    /// Wraps a reference to a state graph.
+    pub fn from_ref<T: RootedProgramStateGraph>(graph: &'g T) -> Self
+    where
+        T::NodeId: 'static,
+    {
+        Self::new(GraphRef(graph))
+    }
+}
+
+impl<'g> From<Box<dyn DynProgramStateGraph + 'g>> for DynStateGraph<'g> {
+    fn from(value: Box<dyn DynProgramStateGraph + 'g>) -> Self {
+        Self(value)
+    }
+}
+
+impl ProgramStateGraph for DynStateGraph<'_> {
+    type NodeId = DynNodeId;
+    type NodeRef<'a>
+        = DynNodeRef<'a>
+    where
+        Self: 'a;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        self.0.dyn_get(id)
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        self.0.dyn_capabilities()
+    }
+}
+
+impl RootedProgramStateGraph for DynStateGraph<'_> {
+    fn root(&self) -> Self::NodeId {
+        self.0.dyn_root()
+    }
+}
+
+/// Reference to a state graph that forwards
+/// to the referenced graph.
+struct GraphRef<'g, T>(&'g T);
+
+impl<T: ProgramStateGraph> ProgramStateGraph for GraphRef<'_, T> {
+    type NodeId = T::NodeId;
+    type NodeRef<'a>
+        = T::NodeRef<'a>
+    where
+        Self: 'a;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        self.0.get(id)
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        self.0.capabilities()
+    }
+}
+
+impl<T: RootedProgramStateGraph> RootedProgramStateGraph for GraphRef<'_, T> {
+    fn root(&self) -> Self::NodeId {
+        self.0.root()
+    }
+}
@@ -0,0 +1,188 @@
+//! State graphs with some of their nodes and edges left out.
+//!
+//! Stylesheets can hide entities from the visualization,
+//! but the stylesheet resolver still has to traverse them.
+//! [`FilteredGraph`] removes them from the graph itself,
+//! so everything that processes the graph skips them entirely.
+//!
+//! ```
+//! use aili_model::{builder::StateGraphBuilder, filter::FilteredGraph, state::*};
+//!
+//! let mut builder = StateGraphBuilder::new();
+//! let frame = builder.add_node(NodeTypeClass::Frame, None);
+//! let caller = builder.add_node(NodeTypeClass::Frame, None);
+//! let internal = builder.add_node(NodeTypeClass::Struct, None);
+//! builder.node_mut(internal).unwrap().set_type_id(Some("std::_Impl".to_owned()));
+//! builder.add_edge(builder.root(), EdgeLabel::Main, frame).unwrap();
+//! builder.add_edge(frame, EdgeLabel::Next, caller).unwrap();
+//! builder.add_edge(frame, EdgeLabel::Named("v".into(), 0), internal).unwrap();
+//! let graph = builder.build();
+//!
+//! // Hide all frames but the top one, and the internals of the standard library
+//! let filtered = FilteredGraph::new(&graph)
+//!     .with_hidden_edges(|_, _, edge| *edge == EdgeLabel::Next)
+//!     .with_hidden_nodes(|graph, id| {
+//!         graph.get(id).is_some_and(|node| {
+//!             node.node_type_id()
+//!                 .is_some_and(|type_id| type_id.type_name().starts_with("std::"))
+//!         })
+//!     });
+//! let frame = filtered.get_at_root(&[EdgeLabel::Main]).unwrap();
+//! assert_eq!(frame.successors().count(), 0);
+//! assert!(filtered.get(&internal).is_none());
+//! ```
+
+use crate::{capability::CapabilitySet, state::*};
+use derive_more::Debug;
+
+/// Predicate that decides whether a node is hidden.
+type NodePredicate<'g, G> = Box<dyn Fn(&G, &<G as ProgramStateGraph>::NodeId) -> bool + 'g>;
+
+/// Predicate that decides whether an edge is hidden,
+/// given the node that the edge starts at.
+type EdgePredicate<'g, G> =
+    Box<dyn Fn(&G, &<G as ProgramStateGraph>::NodeId, &EdgeLabel) -> bool + 'g>;
+
+/// View of a state graph that hides the nodes and edges
+/// that match a predicate.
+///
+/// Edges that lead to hidden nodes are hidden as well, so hidden nodes
+/// are not reachable from the rest of the graph. Nodes that are only
+/// reachable through hidden edges are not hidden themselves,
+/// but since nothing leads to them, traversals never reach them either.
+/// If the root node is hidden, the whole graph is.
+#[derive(Debug)]
+#[debug("FilteredGraph")]
+pub struct FilteredGraph<'g, G: ProgramStateGraph> {
+    graph: &'g G,
+    hidden_nodes: Vec<NodePredicate<'g, G>>,
+    hidden_edges: Vec<EdgePredicate<'g, G>>,
+}
+
+impl<'g, G: ProgramStateGraph> FilteredGraph<'g, G> {
+    /// Wraps a state graph without hiding anything.
+    pub fn new(graph: &'g G) -> Self {
+        Self {
+            graph,
+            hidden_nodes: Vec::new(),
+            hidden_edges: Vec::new(),
+        }
+    }
+
+    /// Hides all nodes that match a predicate.
+    ///
+    /// The predicate receives the underlying graph
+    /// and the identifier of the node.
+    pub fn with_hidden_nodes(mut self, predicate: impl Fn(&G, &G::NodeId) -> bool + 'g) -> Self {
+        self.hidden_nodes.push(Box::new(predicate));
+        self
+    }
+
+    /// Hides all edges that match a predicate.
+    ///
+    /// The predicate receives the underlying graph,
+    /// the identifier of the node that the edge starts at,
+    /// and the label of the edge.
+    pub fn with_hidden_edges(
+        mut self,
+        predicate: impl Fn(&G, &G::NodeId, &EdgeLabel) -> bool + 'g,
+    ) -> Self {
+        self.hidden_edges.push(Box::new(predicate));
+        self
+    }
+
+    /// Gets the underlying graph.
+    pub fn inner(&self) -> &'g G {
+        self.graph
+    }
+
+    /// Checks whether a node is hidden.
+    fn is_node_hidden(&self, id: &G::NodeId) -> bool {
+        self.hidden_nodes
+            .iter()
+            .any(|predicate| predicate(self.graph, id))
+    }
+
+    /// Checks whether an edge is visible, including the node it leads to.
+    fn is_edge_visible(&self, source: &G::NodeId, edge: &EdgeLabel, target: &G::NodeId) -> bool {
+        !self
+            .hidden_edges
+            .iter()
+            .any(|predicate| predicate(self.graph, source, edge))
+            && !self.is_node_hidden(target)
+    }
+}
+
+impl<'g, G: ProgramStateGraph> ProgramStateGraph for FilteredGraph<'g, G> {
+    type NodeId = G::NodeId;
+    type NodeRef<'a>
+        = FilteredNodeRef<'a, 'g, G>
+    where
+        Self: 'a;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        if self.is_node_hidden(id) {
+            return None;
+        }
+        Some(FilteredNodeRef {
+            node: self.graph.get(id)?,
+            id: id.clone(),
+            filter: self,
+        })
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        self.graph.capabilities()
+    }
+}
+
+impl<G: RootedProgramStateGraph> RootedProgramStateGraph for FilteredGraph<'_, G> {
+    fn root(&self) -> Self::NodeId {
+        self.graph.root()
+    }
+}
+
+/// Reference to a node of a [`FilteredGraph`].
+///
+/// Only lists the successors that are not hidden.
+#[derive(Debug)]
+#[debug("FilteredNodeRef")]
+pub struct FilteredNodeRef<'a, 'g, G: ProgramStateGraph> {
+    node: G::NodeRef<'a>,
+    id: G::NodeId,
+    filter: &'a FilteredGraph<'g, G>,
+}
+
+impl<'a, G: ProgramStateGraph> ProgramStateNode for FilteredNodeRef<'a, '_, G> {
+    type NodeId = G::NodeId;
+    type NodeTypeId<'b>
+        = <G::NodeRef<'a> as ProgramStateNode>::NodeTypeId<'b>
+    where
+        Self: 'b;
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
+        self.node
+            .get_successor(edge)
+            .filter(|successor| self.filter.is_edge_visible(&self.id, edge, successor))
+    }
+    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
+        self.node
+            .successors()
+            .filter(|(edge, successor)| self.filter.is_edge_visible(&self.id, edge, successor))
+    }
+    fn node_type_class(&self) -> NodeTypeClass {
+        self.node.node_type_class()
+    }
+    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
+        self.node.node_type_id()
+    }
+    fn custom_type_class(&self) -> Option<&str> {
+        self.node.custom_type_class()
+    }
+    fn value(&self) -> Option<NodeValue> {
+        self.node.value()
+    }
+    fn source_location(&self) -> Option<SourceLocation> {
+        self.node.source_location()
+    }
+    fn is_shared(&self) -> bool {
+        self.node.is_shared()
+    }
+}
@@ -0,0 +1,426 @@
+//! Small program state graphs for tests, demos, and reproductions.
+//!
+//! [`FixtureGraph`] is a simple in-memory [`RootedProgramStateGraph`]
+//! whose nodes are identified by consecutive integers, with the root at zero.
+//! Graphs are easiest to write with the [`graph!`](crate::graph) macro.
+//!
+//! ```
+//! use aili_model::{graph, state::*};
+//!
+//! let graph = graph! {
+//!     0 -main-> 1 [class = frame];
+//!     1 -"a"-> 2 [val = 5];
+//!     1 -"a"#1-> 3 [class = array, type = "int[1]"];
+//!     3 -[0]-> 4 [val = "x"];
+//!     3 -len-> 5 [val = 1];
+//! };
+//! let frame = graph.get(&graph.root()).unwrap().get_successor(&EdgeLabel::Main);
+//! assert_eq!(frame, Some(1));
+//! assert_eq!(graph.get(&2).unwrap().value(), Some(NodeValue::Int(5)));
+//! ```
+
+use crate::state::{
+    EdgeLabel, NodeTypeClass, NodeValue, ProgramStateGraph, ProgramStateNode,
+    RootedProgramStateGraph, SourceLocation,
+};
+
+/// Program state graph that is constructed by hand.
+///
+/// Nodes are created as they are referenced, so a graph
+/// with an edge that leads to node 5 has at least six nodes.
+/// Nodes are [atoms](NodeTypeClass::Atom) unless specified otherwise,
+/// except for the root, which is of the [root](NodeTypeClass::Root) class.
+#[derive(Clone, Debug)]
+pub struct FixtureGraph {
+    nodes: Vec<FixtureNode>,
+}
+
+impl FixtureGraph {
+    /// Constructs a graph that only has a root node.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets a node by its index, creating it and all nodes
+    /// with lower indices if it does not exist.
+    pub fn node_mut(&mut self, id: usize) -> &mut FixtureNode {
+        if id >= self.nodes.len() {
+            self.nodes.resize_with(id + 1, FixtureNode::default);
+        }
+        &mut self.nodes[id]
+    }
+
+    /// Adds an edge between two nodes.
+    ///
+    /// If the source node already has an edge with the same label,
+    /// it is redirected to the new target.
+    pub fn add_edge(&mut self, from: usize, edge_label: EdgeLabel, to: usize) {
+        self.node_mut(to);
+        let successors = &mut self.node_mut(from).successors;
+        match successors
+            .iter_mut()
+            .find(|(label, _)| *label == edge_label)
+        {
+            Some((_, target)) => *target = to,
+            None => successors.push((edge_label, to)),
+        }
+    }
+
+    /// Removes an edge from the graph.
+    ///
+    /// Nodes that were only reachable through the edge
+    /// stay in the graph, but they become unreachable.
+    pub fn remove_edge(&mut self, from: usize, edge_label: &EdgeLabel) {
+        if let Some(node) = self.nodes.get_mut(from) {
+            node.successors.retain(|(label, _)| label != edge_label);
+        }
+    }
+
+    /// Adds an edge between two nodes.
+    pub fn with_edge(mut self, from: usize, edge_label: EdgeLabel, to: usize) -> Self {
+        self.add_edge(from, edge_label, to);
+        self
+    }
+
+    /// Removes an edge from the graph.
+    pub fn without_edge(mut self, from: usize, edge_label: &EdgeLabel) -> Self {
+        self.remove_edge(from, edge_label);
+        self
+    }
+
+    /// Sets the value of a node.
+    pub fn with_value(mut self, id: usize, value: impl IntoNodeValue) -> Self {
+        self.node_mut(id).set_value(Some(value.into_node_value()));
+        self
+    }
+
+    /// Sets the type class of a node.
+    pub fn with_type_class(mut self, id: usize, type_class: NodeTypeClass) -> Self {
+        self.node_mut(id).set_type_class(type_class);
+        self
+    }
+}
+
+impl Default for FixtureGraph {
+    fn default() -> Self {
+        let root = FixtureNode {
+            type_class: NodeTypeClass::Root,
+            ..Default::default()
+        };
+        Self { nodes: vec![root] }
+    }
+}
+
+impl ProgramStateGraph for FixtureGraph {
+    type NodeId = usize;
+    type NodeRef<'a> = &'a FixtureNode;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        self.nodes.get(*id)
+    }
+}
+
+impl RootedProgramStateGraph for FixtureGraph {
+    fn root(&self) -> Self::NodeId {
+        0
+    }
+}
+
+/// Node of [`FixtureGraph`].
+///
+/// Successors are kept in a list, so they are always
+/// traversed in the order in which they are declared.
+#[derive(Clone, Debug)]
+pub struct FixtureNode {
+    successors: Vec<(EdgeLabel, usize)>,
+    value: Option<NodeValue>,
+    type_class: NodeTypeClass,
+    type_id: Option<String>,
+    custom_type_class: Option<String>,
+    source_location: Option<SourceLocation>,
+}
+
+impl FixtureNode {
+    /// Sets the value of the node.
+    pub fn set_value(&mut self, value: Option<NodeValue>) {
+        self.value = value;
+    }
+
+    /// Sets the type class of the node.
+    ///
+    /// Custom type classes should be set with
+    /// [`set_custom_type_class`](FixtureNode::set_custom_type_class).
+    pub fn set_type_class(&mut self, type_class: NodeTypeClass) {
+        self.type_class = type_class;
+        self.custom_type_class = None;
+    }
+
+    /// Makes the node's type class [custom](NodeTypeClass::Custom).
+    pub fn set_custom_type_class(&mut self, name: impl Into<String>) {
+        self.type_class = NodeTypeClass::Custom;
+        self.custom_type_class = Some(name.into());
+    }
+
+    /// Sets the type ID of the node.
+    pub fn set_type_id(&mut self, type_id: Option<String>) {
+        self.type_id = type_id;
+    }
+
+    /// Sets the location in the source code
+    /// that the node is associated with.
+    pub fn set_source_location(&mut self, source_location: Option<SourceLocation>) {
+        self.source_location = source_location;
+    }
+}
+
+impl Default for FixtureNode {
+    fn default() -> Self {
+        Self {
+            successors: Vec::new(),
+            value: None,
+            type_class: NodeTypeClass::Atom,
+            type_id: None,
+            custom_type_class: None,
+            source_location: None,
+        }
+    }
+}
+
+impl ProgramStateNode for &FixtureNode {
+    type NodeId = usize;
+    type NodeTypeId<'a>
+        = &'a str
+    where
+        Self: 'a;
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
+        self.successors
+            .iter()
+            .find(|(label, _)| label == edge)
+            .map(|(_, target)| *target)
+    }
+    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
+        self.successors
+            .iter()
+            .map(|(label, target)| (label, *target))
+    }
+    fn node_type_class(&self) -> NodeTypeClass {
+        self.type_class
+    }
+    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
+        self.type_id.as_deref()
+    }
+    fn custom_type_class(&self) -> Option<&str> {
+        self.custom_type_class.as_deref()
+    }
+    fn value(&self) -> Option<NodeValue> {
+        self.value.clone()
+    }
+    fn source_location(&self) -> Option<SourceLocation> {
+        self.source_location.clone()
+    }
+}
+
+/// Conversion to a [`NodeValue`] that accepts
+/// unsuffixed integer literals.
+pub trait IntoNodeValue {
+    /// Converts the value to a [`NodeValue`].
+    fn into_node_value(self) -> NodeValue;
+}
+
+macro_rules! into_node_value {
+    ( $( $ty:ty => $variant:ident ),* ) => {
+        $(
+            impl IntoNodeValue for $ty {
+                fn into_node_value(self) -> NodeValue {
+                    NodeValue::$variant(self.into())
+                }
+            }
+        )*
+    };
+}
+
+into_node_value!(
+    bool => Bool,
+    i32 => Int,
+    i64 => Int,
+    u32 => Uint,
+    u64 => Uint,
+    f64 => Float,
+    &str => String,
+    String => String
+);
+
+impl IntoNodeValue for NodeValue {
+    fn into_node_value(self) -> NodeValue {
+        self
+    }
+}
+
+/// Constructs a [`FixtureGraph`]
+/// from a list of statements.
+///
+/// Each statement is terminated by a semicolon and either declares
+/// an edge, `<from> -<label>-> <to>`, or just mentions a node, `<node>`.
+/// Edges are added in the order in which they are declared.
+///
+/// Edge labels are written as follows:
+///
+/// | Syntax         | Edge label                  |
+/// |----------------|-----------------------------|
+/// | `main`         | [`EdgeLabel::Main`]         |
+/// | `next`         | [`EdgeLabel::Next`]         |
+/// | `ret`          | [`EdgeLabel::Result`]       |
+/// | `ref`          | [`EdgeLabel::Deref`]        |
+/// | `len`          | [`EdgeLabel::Length`]       |
+/// | `[i]`          | [`EdgeLabel::Index`]        |
+/// | `"name"`       | [`EdgeLabel::Named`]        |
+/// | `"name"#i`     | [`EdgeLabel::Named`] with a discriminator |
+/// | `@"name"`      | [`EdgeLabel::Custom`]       |
+///
+/// A statement may end with attributes in square brackets
+/// that apply to the last node it mentions:
+///
+/// - `val = <value>` sets the value of the node.
+///   Anything that implements [`IntoNodeValue`] is accepted,
+///   expressions other than literals must be parenthesized.
+/// - `class = <class>` sets the type class of the node,
+///   written the way selectors write it, such as `struct`.
+///   A string literal sets a custom type class.
+/// - `type = "<name>"` sets the type ID of the node.
+///
+/// [`EdgeLabel::Main`]: crate::state::EdgeLabel::Main
+/// [`EdgeLabel::Next`]: crate::state::EdgeLabel::Next
+/// [`EdgeLabel::Result`]: crate::state::EdgeLabel::Result
+/// [`EdgeLabel::Deref`]: crate::state::EdgeLabel::Deref
+/// [`EdgeLabel::Length`]: crate::state::EdgeLabel::Length
+/// [`EdgeLabel::Index`]: crate::state::EdgeLabel::Index
+/// [`EdgeLabel::Named`]: crate::state::EdgeLabel::Named
+/// [`EdgeLabel::Custom`]: crate::state::EdgeLabel::Custom
+///
+/// See the [module-level documentation](crate::fixture) for an example.
+#[macro_export]
+macro_rules! graph {
+    ( $( $statements:tt )* ) => {{
+        let mut graph = $crate::fixture::FixtureGraph::new();
+        $crate::__graph_statements!(graph; $( $statements )*);
+        graph
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __graph_statements {
+    ( $graph:ident; ) => {};
+    (
+        $graph:ident;
+        $from:literal - @ $name:literal -> $to:literal
+        $( [ $( $key:ident = $value:tt ),* $(,)? ] )? ;
+        $( $rest:tt )*
+    ) => {
+        $graph.add_edge($from, $crate::state::EdgeLabel::Custom($name.to_owned()), $to);
+        $( $( $crate::__graph_node_attribute!($graph, $to, $key = $value); )* )?
+        $crate::__graph_statements!($graph; $( $rest )*);
+    };
+    (
+        $graph:ident;
+        $from:literal - $label:tt $( # $discriminator:literal )? -> $to:literal
+        $( [ $( $key:ident = $value:tt ),* $(,)? ] )? ;
+        $( $rest:tt )*
+    ) => {
+        $graph.add_edge(
+            $from,
+            $crate::__graph_edge_label!($label $( # $discriminator )?),
+            $to,
+        );
+        $( $( $crate::__graph_node_attribute!($graph, $to, $key = $value); )* )?
+        $crate::__graph_statements!($graph; $( $rest )*);
+    };
+    (
+        $graph:ident;
+        $node:literal $( [ $( $key:ident = $value:tt ),* $(,)? ] )? ;
+        $( $rest:tt )*
+    ) => {
+        $graph.node_mut($node);
+        $( $( $crate::__graph_node_attribute!($graph, $node, $key = $value); )* )?
+        $crate::__graph_statements!($graph; $( $rest )*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __graph_edge_label {
+    ( main ) => {
+        $crate::state::EdgeLabel::Main
+    };
+    ( next ) => {
+        $crate::state::EdgeLabel::Next
+    };
+    ( ret ) => {
+        $crate::state::EdgeLabel::Result
+    };
+    ( ref ) => {
+        $crate::state::EdgeLabel::Deref
+    };
+    ( len ) => {
+        $crate::state::EdgeLabel::Length
+    };
+    ( [ $index:expr ] ) => {
+        $crate::state::EdgeLabel::Index($index)
+    };
+    ( $name:literal ) => {
+        $crate::state::EdgeLabel::Named($crate::name::Name::from($name), 0)
+    };
+    ( $name:literal # $discriminator:literal ) => {
+        $crate::state::EdgeLabel::Named($crate::name::Name::from($name), $discriminator)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __graph_node_attribute {
+    ( $graph:ident, $node:expr, val = $value:tt ) => {
+        $graph
+            .node_mut($node)
+            .set_value(Some($crate::fixture::IntoNodeValue::into_node_value(
+                $value,
+            )))
+    };
+    ( $graph:ident, $node:expr, class = $class:literal ) => {
+        $graph.node_mut($node).set_custom_type_class($class)
+    };
+    ( $graph:ident, $node:expr, class = $class:tt ) => {
+        $graph
+            .node_mut($node)
+            .set_type_class($crate::__graph_type_class!($class))
+    };
+    ( $graph:ident, $node:expr, type = $type_id:literal ) => {
+        $graph
+            .node_mut($node)
+            .set_type_id(Some($type_id.to_owned()))
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __graph_type_class {
+    ( root ) => {
+        $crate::state::NodeTypeClass::Root
+    };
+    ( frame ) => {
+        $crate::state::NodeTypeClass::Frame
+    };
+    ( atom ) => {
+        $crate::state::NodeTypeClass::Atom
+    };
+    ( struct ) => {
+        $crate::state::NodeTypeClass::Struct
+    };
+    ( union ) => {
+        $crate::state::NodeTypeClass::Union
+    };
+    ( array ) => {
+        $crate::state::NodeTypeClass::Array
+    };
+    ( ref ) => {
+        $crate::state::NodeTypeClass::Ref
+    };
+}
@@ -1,5 +1,13 @@
 //! Defines the abstract models that form the interface between
 //! the main modules of Aili.
 
+pub mod builder;
+pub mod capability;
+pub mod depth;
+pub mod erased;
+pub mod filter;
+#[cfg(feature = "fixture")]
+pub mod fixture;
+pub mod name;
 pub mod state;
 pub mod vis;
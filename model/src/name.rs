@@ -0,0 +1,198 @@
+//! Interned strings for names that recur throughout graphs and stylesheets.
+//!
+//! Names of variables, edges, and attributes tend to repeat many times
+//! in a single graph, and again in every update. [`Name`] is a string
+//! that is shared by all its copies, so that cloning it does not allocate.
+//! Names that are created from the same text share the same allocation
+//! for as long as any copy of them is in use, so backends can
+//! [intern](Name::intern) names they use often up front.
+//!
+//! ```
+//! use aili_model::name::Name;
+//!
+//! let next = Name::intern("next");
+//! let same = Name::from("next".to_owned());
+//! assert_eq!(next, same);
+//! assert_eq!(next, "next");
+//! assert!(next.shares_text_with(&same));
+//! ```
+
+use derive_more::{Debug, Display};
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    ops::Deref,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+/// Table of all names that are in use.
+static INTERNED_NAMES: LazyLock<Mutex<NameTable>> = LazyLock::new(Default::default);
+
+/// Table of interned names that forgets names that are no longer used.
+#[derive(Default)]
+struct NameTable {
+    /// The interned names.
+    names: HashSet<Arc<str>>,
+
+    /// Number of names at which the table
+    /// should be pruned of unused names.
+    prune_at: usize,
+}
+
+impl NameTable {
+    /// Minimum value of [`NameTable::prune_at`].
+    const MIN_PRUNE_AT: usize = 1024;
+
+    fn intern(&mut self, text: &str) -> Arc<str> {
+        if let Some(name) = self.names.get(text) {
+            return name.clone();
+        }
+        // Pruning only once the table has doubled in size
+        // keeps the cost of interning a name constant on average
+        if self.names.len() >= self.prune_at {
+            self.prune();
+        }
+        let name = Arc::<str>::from(text);
+        self.names.insert(name.clone());
+        name
+    }
+
+    /// Removes names whose only copy is the one in the table.
+    fn prune(&mut self) {
+        // Copies of a name can only be made from an existing copy,
+        // or from the table while it is locked, so a name that is
+        // not in use cannot come back into use while it is being removed
+        self.names.retain(|name| Arc::strong_count(name) > 1);
+        self.prune_at = (self.names.len() * 2).max(Self::MIN_PRUNE_AT);
+    }
+}
+
+/// Interned string.
+///
+/// Behaves like an immutable [`String`] and compares, hashes,
+/// and orders the same way, but copies of the same name
+/// share a single allocation.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[debug("{_0:?}")]
+#[display("{_0}")]
+pub struct Name(Arc<str>);
+
+impl Name {
+    /// Gets the name with the specified text,
+    /// adding it to the table of interned names if it is not there yet.
+    ///
+    /// Names are removed from the table from time to time
+    /// once all their copies have been dropped, so the table
+    /// does not grow with every name that has ever been used.
+    pub fn intern(text: &str) -> Self {
+        let mut names = INTERNED_NAMES
+            .lock()
+            .expect("Name table should not be poisoned");
+        Self(names.intern(text))
+    }
+
+    /// Gets the text of the name.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Checks whether two names share the same allocation,
+    /// which is the case if both have been interned
+    /// while a copy of one of them was in use.
+    pub fn shares_text_with(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Name {
+    fn from(value: &str) -> Self {
+        Self::intern(value)
+    }
+}
+
+impl From<String> for Name {
+    fn from(value: String) -> Self {
+        Self::intern(&value)
+    }
+}
+
+impl From<&String> for Name {
+    fn from(value: &String) -> Self {
+        Self::intern(value)
+    }
+}
+
+impl From<Name> for String {
+    fn from(value: Name) -> Self {
+        value.0.as_ref().to_owned()
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        *self.0 == *other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialEq<String> for Name {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialEq<Name> for str {
+    fn eq(&self, other: &Name) -> bool {
+        *self == *other.0
+    }
+}
+
+impl PartialEq<Name> for String {
+    fn eq(&self, other: &Name) -> bool {
+        **self == *other.0
+    }
+}
+
+impl PartialEq<Name> for &str {
+    fn eq(&self, other: &Name) -> bool {
+        **self == *other.0
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Name {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Name {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
@@ -3,6 +3,7 @@
 //! Represents the internal state of a debuggee
 //! with a language-independent graph structure.
 
+use crate::{capability::CapabilitySet, name::Name};
 use derive_more::{Debug, From};
 
 /// Unique identifier of a program state node.
@@ -42,8 +43,9 @@ where
     }
 }
 
-/// Enumerates elementary arithmetic values for nodes.
-#[derive(Clone, Copy, Eq, Debug, From)]
+/// Enumerates elementary values for nodes.
+#[derive(Clone, Debug, From)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NodeValue {
     /// Boolean value.
     #[debug("{}", if *_0 { "true" } else  { "false" })]
@@ -56,6 +58,14 @@ pub enum NodeValue {
     /// Unsigned integer value.
     #[debug("{_0}")]
     Uint(u64),
+
+    /// Floating point value.
+    #[debug("{_0}")]
+    Float(f64),
+
+    /// Character string value, such as a decoded C string.
+    #[debug("{_0:?}")]
+    String(String),
 }
 
 impl PartialEq for NodeValue {
@@ -64,6 +74,8 @@ impl PartialEq for NodeValue {
     }
 }
 
+impl Eq for NodeValue {}
+
 impl PartialOrd for NodeValue {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
@@ -71,31 +83,106 @@ impl PartialOrd for NodeValue {
 }
 
 impl Ord for NodeValue {
+    /// Compares two values.
+    ///
+    /// Numeric values are ordered by their numeric value.
+    /// `true == 1` and `false == 0`. Integers are compared
+    /// with [`Float`](NodeValue::Float)s exactly, without rounding them first,
+    /// and NaN is ordered after all other numeric values.
+    /// Strings are ordered lexicographically
+    /// and they are ordered after all numeric values.
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match (*self, *other) {
-            (Self::Bool(left), Self::Bool(right)) => left.cmp(&right),
-            (Self::Bool(left), Self::Int(right)) => i64::from(left).cmp(&right),
-            (Self::Bool(left), Self::Uint(right)) => u64::from(left).cmp(&right),
-            (Self::Int(left), Self::Bool(right)) => left.cmp(&right.into()),
-            (Self::Int(left), Self::Int(right)) => left.cmp(&right),
-            (Self::Int(left), Self::Uint(right)) => u64::try_from(left)
-                .map(|left| left.cmp(&right))
+        match (self, other) {
+            (Self::String(left), Self::String(right)) => left.cmp(right),
+            (Self::String(_), _) => std::cmp::Ordering::Greater,
+            (_, Self::String(_)) => std::cmp::Ordering::Less,
+            (Self::Float(left), Self::Float(right)) => cmp_floats(*left, *right),
+            (Self::Float(left), _) => cmp_integer_to_float(other.as_integer(), *left).reverse(),
+            (_, Self::Float(right)) => cmp_integer_to_float(self.as_integer(), *right),
+            (Self::Bool(left), Self::Bool(right)) => left.cmp(right),
+            (Self::Bool(left), Self::Int(right)) => i64::from(*left).cmp(right),
+            (Self::Bool(left), Self::Uint(right)) => u64::from(*left).cmp(right),
+            (Self::Int(left), Self::Bool(right)) => left.cmp(&(*right).into()),
+            (Self::Int(left), Self::Int(right)) => left.cmp(right),
+            (Self::Int(left), Self::Uint(right)) => u64::try_from(*left)
+                .map(|left| left.cmp(right))
                 .unwrap_or(std::cmp::Ordering::Less),
-            (Self::Uint(left), Self::Bool(right)) => left.cmp(&right.into()),
-            (Self::Uint(left), Self::Int(right)) => u64::try_from(right)
+            (Self::Uint(left), Self::Bool(right)) => left.cmp(&(*right).into()),
+            (Self::Uint(left), Self::Int(right)) => u64::try_from(*right)
                 .map(|right| left.cmp(&right))
                 .unwrap_or(std::cmp::Ordering::Greater),
-            (Self::Uint(left), Self::Uint(right)) => left.cmp(&right),
+            (Self::Uint(left), Self::Uint(right)) => left.cmp(right),
         }
     }
 }
 
+impl NodeValue {
+    /// Converts an integer or boolean value to an integer
+    /// wide enough to hold any of them.
+    ///
+    /// Must not be called on floats and strings.
+    fn as_integer(&self) -> i128 {
+        match self {
+            Self::Bool(b) => (*b).into(),
+            Self::Int(i) => (*i).into(),
+            Self::Uint(u) => (*u).into(),
+            Self::Float(_) | Self::String(_) => unreachable!("value is not an integer"),
+        }
+    }
+}
+
+/// Totally orders floats so that they are consistent with [`NodeValue`] equality.
+fn cmp_floats(left: f64, right: f64) -> std::cmp::Ordering {
+    // Partial comparison treats positive and negative zero as equal,
+    // which is what we want. It only fails if NaN is involved
+    left.partial_cmp(&right)
+        .unwrap_or_else(|| left.is_nan().cmp(&right.is_nan()))
+}
+
+/// Compares an integer with a float exactly,
+/// so that the ordering stays transitive even for integers
+/// that floats cannot represent.
+fn cmp_integer_to_float(left: i128, right: f64) -> std::cmp::Ordering {
+    // Rounds up to 2^127, which is larger than every i128
+    const BOUND: f64 = i128::MAX as f64;
+    if right.is_nan() || right >= BOUND {
+        std::cmp::Ordering::Less
+    } else if right < -BOUND {
+        std::cmp::Ordering::Greater
+    } else {
+        // Truncation is exact within the bounds, only the fraction is lost
+        let whole = right.trunc();
+        left.cmp(&(whole as i128))
+            .then_with(|| cmp_floats(whole, right))
+    }
+}
+
+/// Location in the source code of a program.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceLocation {
+    /// Name of the source file, as reported by the debugger.
+    pub file: String,
+
+    /// One-based number of the line in the file.
+    pub line: u64,
+}
+
 /// Types of program state edges.
 ///
 /// Each type has specific semantics which determine what kinds
 /// of [`NodeTypeClass`] they can connect, but these are not enforced.
 /// Nontheless, implementations should adhere to them.
+///
+/// An edge is identified by its source node and its label,
+/// so no two edges that leave the same node may share a label.
+/// Parallel edges between the same pair of nodes are permitted,
+/// but they must be told apart by their labels, for example
+/// by [`EdgeLabel::Named`] discriminators. Edges with duplicate
+/// labels would be indistinguishable to consumers of the graph,
+/// which would see them as a single edge.
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EdgeLabel {
     /// Identifies the entry point (the bottom-most stack frame).
     ///
@@ -123,7 +210,8 @@ pub enum EdgeLabel {
     /// [`NodeTypeClass::Frame`]
     ///
     /// ## Permitted Targets
-    /// [`NodeTypeClass::Atom`], [`NodeTypeClass::Struct`], [`NodeTypeClass::Array`], [`NodeTypeClass::Ref`]
+    /// [`NodeTypeClass::Atom`], [`NodeTypeClass::Struct`], [`NodeTypeClass::Union`], [`NodeTypeClass::Array`],
+    /// [`NodeTypeClass::Ref`], [`NodeTypeClass::Custom`]
     #[debug("ret")]
     Result,
 
@@ -133,7 +221,8 @@ pub enum EdgeLabel {
     /// [`NodeTypeClass::Ref`]
     ///
     /// ## Permitted Targets
-    /// [`NodeTypeClass::Atom`], [`NodeTypeClass::Struct`], [`NodeTypeClass::Array`], [`NodeTypeClass::Ref`]
+    /// [`NodeTypeClass::Atom`], [`NodeTypeClass::Struct`], [`NodeTypeClass::Union`], [`NodeTypeClass::Array`],
+    /// [`NodeTypeClass::Ref`], [`NodeTypeClass::Custom`]
     #[debug("ref")]
     Deref,
 
@@ -143,7 +232,8 @@ pub enum EdgeLabel {
     /// [`NodeTypeClass::Array`]
     ///
     /// ## Permitted Targets
-    /// [`NodeTypeClass::Atom`], [`NodeTypeClass::Struct`], [`NodeTypeClass::Array`], [`NodeTypeClass::Ref`]
+    /// [`NodeTypeClass::Atom`], [`NodeTypeClass::Struct`], [`NodeTypeClass::Union`], [`NodeTypeClass::Array`],
+    /// [`NodeTypeClass::Ref`], [`NodeTypeClass::Custom`]
     #[debug("[{_0}]")]
     Index(usize),
 
@@ -156,12 +246,14 @@ pub enum EdgeLabel {
     /// Indices should always be sequential.
     ///
     /// ## Permitted Sources
-    /// [`NodeTypeClass::Root`], [`NodeTypeClass::Frame`], [`NodeTypeClass::Struct`]
+    /// [`NodeTypeClass::Root`], [`NodeTypeClass::Frame`], [`NodeTypeClass::Struct`],
+    /// [`NodeTypeClass::Union`], [`NodeTypeClass::Custom`]
     ///
     /// ## Permitted Targets
-    /// [`NodeTypeClass::Atom`], [`NodeTypeClass::Struct`], [`NodeTypeClass::Array`], [`NodeTypeClass::Ref`]
+    /// [`NodeTypeClass::Atom`], [`NodeTypeClass::Struct`], [`NodeTypeClass::Union`], [`NodeTypeClass::Array`],
+    /// [`NodeTypeClass::Ref`], [`NodeTypeClass::Custom`]
     #[debug("{_0:?}#{_1}")]
-    Named(String, usize),
+    Named(Name, usize),
 
     /// Indicates a variable that stores the length of a sequence.
     ///
@@ -172,6 +264,60 @@ pub enum EdgeLabel {
     /// [`NodeTypeClass::Atom`]
     #[debug("len")]
     Length,
+
+    /// Identifies a domain-specific relation that none
+    /// of the other labels describe, such as a thread or a channel.
+    ///
+    /// Custom labels are distinguished by their names.
+    /// Implementations that use them should document
+    /// which names they emit.
+    ///
+    /// ## Permitted Sources
+    /// Any [`NodeTypeClass`]
+    ///
+    /// Custom edges are permitted in addition to the outgoing edges
+    /// listed for each [`NodeTypeClass`]. This includes
+    /// [`NodeTypeClass::Custom`], whose other outgoing edges
+    /// are limited to [`EdgeLabel::Named`].
+    ///
+    /// ## Permitted Targets
+    /// Any [`NodeTypeClass`] except [`NodeTypeClass::Root`]
+    #[debug("{_0}")]
+    Custom(String),
+}
+
+impl EdgeLabel {
+    /// Checks whether edges with this label may start
+    /// at nodes of a type class, as documented for each label.
+    pub fn permits_source(&self, type_class: NodeTypeClass) -> bool {
+        use NodeTypeClass::*;
+        match self {
+            Self::Main => type_class == Root,
+            Self::Next | Self::Result => type_class == Frame,
+            Self::Deref => type_class == Ref,
+            Self::Index(_) | Self::Length => type_class == Array,
+            Self::Named(_, _) => matches!(type_class, Root | Frame | Struct | Union | Custom),
+            Self::Custom(_) => true,
+        }
+    }
+
+    /// Checks whether edges with this label may lead
+    /// to nodes of a type class, as documented for each label.
+    ///
+    /// Edges with any label may lead to [`Truncated`](NodeTypeClass::Truncated)
+    /// nodes, since they stand in for nodes of any type class.
+    pub fn permits_target(&self, type_class: NodeTypeClass) -> bool {
+        use NodeTypeClass::*;
+        match (self, type_class) {
+            (_, Truncated) => true,
+            (Self::Main | Self::Next, _) => type_class == Frame,
+            (Self::Length, _) => type_class == Atom,
+            (Self::Custom(_), _) => type_class != Root,
+            (Self::Result | Self::Deref | Self::Index(_) | Self::Named(_, _), _) => {
+                !matches!(type_class, Root | Frame)
+            }
+        }
+    }
 }
 
 /// Categories of types of program state nodes.
@@ -185,7 +331,11 @@ pub enum EdgeLabel {
 ///
 /// Nodes of some types may be characterized with a [`NodeValue`],
 /// usualy a numeric one.
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+///
+/// [`EdgeLabel::Custom`] edges are not listed among the permitted
+/// edges of each type, as their semantics are domain-specific.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NodeTypeClass {
     /// Type of the node that represents the program's global scope.
     ///
@@ -275,6 +425,30 @@ pub enum NodeTypeClass {
     #[debug("struct")]
     Struct,
 
+    /// Type of nodes that represent values whose members
+    /// share the same storage, such as C unions.
+    ///
+    /// Parametrized by the type of the value.
+    ///
+    /// ## Properties
+    /// | Property | Usage | Notes                          |
+    /// |----------|-------|--------------------------------|
+    /// | Value    | No    |                                |
+    /// | Type ID  | Yes   | Distinguishes different unions |
+    ///
+    /// ## Permitted Incoming Edges
+    /// | Edge label                                                            | Multiplicity |
+    /// |-----------------------------------------------------------------------|--------------|
+    /// | [`EdgeLabel::Named`] or [`EdgeLabel::Index`] or [`EdgeLabel::Result`] | 0..1         |
+    /// | [`EdgeLabel::Deref`]                                                  | *            |
+    ///
+    /// ## Permitted Outgoing Edges
+    /// | Edge label           | Multiplicity | Semantics                                |
+    /// |----------------------|--------------|------------------------------------------|
+    /// | [`EdgeLabel::Named`] | *            | Alternative interpretations of the value |
+    #[debug("union")]
+    Union,
+
     /// Type of nodes that represent sequence (array) values.
     ///
     /// ## Properties
@@ -317,6 +491,45 @@ pub enum NodeTypeClass {
     /// | [`EdgeLabel::Deref`] | 1            | The value being referenced |
     #[debug("ref")]
     Ref,
+
+    /// Type of nodes that represent domain-specific entities
+    /// that do not fit any of the other categories,
+    /// such as closures, channels, or tasks.
+    ///
+    /// The specific class of the node is identified by name
+    /// by [`ProgramStateNode::custom_type_class`].
+    ///
+    /// ## Properties
+    /// | Property | Usage    |
+    /// |----------|----------|
+    /// | Value    | Optional |
+    /// | Type ID  | Optional |
+    ///
+    /// ## Permitted Incoming Edges
+    /// [`EdgeLabel::Result`], [`EdgeLabel::Deref`], [`EdgeLabel::Index`], [`EdgeLabel::Named`]
+    ///
+    /// ## Permitted Outgoing Edges
+    /// [`EdgeLabel::Named`]
+    #[debug("custom")]
+    Custom,
+
+    /// Type of nodes that stand in for parts of the graph
+    /// that have been left out, such as by a
+    /// [`DepthLimitedGraph`](crate::depth::DepthLimitedGraph).
+    ///
+    /// ## Properties
+    /// | Property | Usage |
+    /// |----------|-------|
+    /// | Value    | No    |
+    /// | Type ID  | No    |
+    ///
+    /// ## Permitted Incoming Edges
+    /// Any.
+    ///
+    /// ## Permitted Outgoing Edges
+    /// None.
+    #[debug("truncated")]
+    Truncated,
 }
 
 /// Node in the program state graph.
@@ -334,7 +547,10 @@ pub trait ProgramStateNode {
     fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId>;
 
     /// Iterates through the list of successors and the edges
-    /// that lead to them. Edge labels are unique.
+    /// that lead to them.
+    ///
+    /// Edge labels are unique, but multiple edges may lead
+    /// to the same successor. See [`EdgeLabel`] for details.
     fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)>;
 
     /// Gets the categorical type of the node.
@@ -343,8 +559,38 @@ pub trait ProgramStateNode {
     /// Gets the specific type ID of the node.
     fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>>;
 
+    /// Gets the name of the node's domain-specific type class
+    /// if its [`node_type_class`](ProgramStateNode::node_type_class)
+    /// is [`NodeTypeClass::Custom`].
+    ///
+    /// The default implementation returns [`None`],
+    /// which is appropriate for state graphs that do not use custom classes.
+    fn custom_type_class(&self) -> Option<&str> {
+        None
+    }
+
     /// Gets the value of the node, if any.
     fn value(&self) -> Option<NodeValue>;
+
+    /// Gets the location in the source code that the node is associated with.
+    ///
+    /// Nodes of type [`NodeTypeClass::Frame`] are located
+    /// at the line that the function is currently executing.
+    ///
+    /// The default implementation returns [`None`],
+    /// which is appropriate for state graphs that do not track source locations.
+    fn source_location(&self) -> Option<SourceLocation> {
+        None
+    }
+
+    /// Checks whether the node stands in for several identical
+    /// objects of the program that have been merged into one node.
+    ///
+    /// The default implementation returns `false`,
+    /// which is appropriate for state graphs that never merge nodes.
+    fn is_shared(&self) -> bool {
+        false
+    }
 }
 
 /// Container for a program state graph.
@@ -360,6 +606,17 @@ pub trait ProgramStateGraph {
     /// Get a reference to a state node by its ID.
     fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>>;
 
+    /// Gets the [capabilities](crate::capability) that the graph provides.
+    ///
+    /// Stylesheets may declare capabilities they require,
+    /// so they can be rejected before they are applied to a graph
+    /// they were not written for.
+    ///
+    /// The default implementation returns an empty set.
+    fn capabilities(&self) -> CapabilitySet {
+        CapabilitySet::new()
+    }
+
     /// Get the ID of a state node by its path from a reference node.
     fn get_id_at<'b>(
         &self,
@@ -4,6 +4,8 @@
 
 use derive_more::{Display, Error, From};
 
+pub mod memory;
+
 /// Error type that indicates the use of an invalid handle
 /// to access a visualization entity.
 #[derive(Clone, Copy, Debug, Display, Default, Error)]
@@ -42,6 +44,16 @@ pub trait VisElement: AttributeMap {
 
     /// Updates the parent element of this element.
     fn insert_into(&mut self, parent: Option<&Self::Handle>) -> Result<(), ParentAssignmentError>;
+
+    /// Moves this element to a different layer.
+    ///
+    /// Elements in higher layers are drawn above elements in lower layers,
+    /// regardless of their position in the tree. Elements are in layer zero
+    /// until they are moved. Trees that do not support layers
+    /// draw all elements in the same layer.
+    fn set_layer(&mut self, layer: i32) {
+        let _ = layer;
+    }
 }
 
 /// Visualization tree connector.
@@ -49,8 +61,11 @@ pub trait VisConnector: AttributeMap {
     /// Type of handles to elements (not connectors).
     type Handle: VisHandle;
 
+    /// Type of handles to connectors.
+    type ConnectorHandle: VisHandle;
+
     /// Type of references to connector pins
-    type PinRef<'a>: VisPin<Handle = Self::Handle> + 'a
+    type PinRef<'a>: VisPin<Handle = Self::Handle, ConnectorHandle = Self::ConnectorHandle> + 'a
     where
         Self: 'a;
 
@@ -66,8 +81,21 @@ pub trait VisPin: AttributeMap {
     /// Type of handles to elements (not connectors or pins).
     type Handle: VisHandle;
 
+    /// Type of handles to connectors.
+    type ConnectorHandle: VisHandle;
+
     /// Updates the target element of this pin.
+    ///
+    /// Replaces the target connector if there is one.
     fn attach_to(&mut self, target: Option<&Self::Handle>) -> Result<(), InvalidHandle>;
+
+    /// Attaches this pin to the midpoint of another connector.
+    ///
+    /// Replaces the target element if there is one.
+    fn attach_to_connector(
+        &mut self,
+        target: Option<&Self::ConnectorHandle>,
+    ) -> Result<(), InvalidHandle>;
 }
 
 /// Container for a visualization tree.
@@ -84,13 +112,22 @@ pub trait VisTree {
         Self: 'a;
 
     /// Type of references to connectors.
-    type ConnectorRef<'a>: VisConnector<Handle = Self::ElementHandle> + 'a
+    type ConnectorRef<'a>: VisConnector<Handle = Self::ElementHandle, ConnectorHandle = Self::ConnectorHandle>
+        + 'a
     where
         Self: 'a;
 
     /// Sets an element to be the root of the visualization tree.
     fn set_root(&mut self, handle: Option<&Self::ElementHandle>) -> Result<(), InvalidHandle>;
 
+    /// Sets elements to be the roots of the visualization tree.
+    ///
+    /// Each root is displayed separately, for example on its own canvas.
+    /// Trees that only support a single root display the first one.
+    fn set_roots(&mut self, handles: &[Self::ElementHandle]) -> Result<(), InvalidHandle> {
+        self.set_root(handles.first())
+    }
+
     /// Creates a new element.
     fn add_element(&mut self, tag_name: &str) -> Self::ElementHandle;
 
@@ -108,4 +145,24 @@ pub trait VisTree {
         &mut self,
         handle: &Self::ConnectorHandle,
     ) -> Result<Self::ConnectorRef<'_>, InvalidHandle>;
+
+    /// Notifies the tree that an element will not be used anymore.
+    ///
+    /// The caller detaches the element before removing it
+    /// and does not use its handle afterwards. Trees whose handles
+    /// are garbage-collected do not need to do anything.
+    fn remove_element(&mut self, handle: &Self::ElementHandle) -> Result<(), InvalidHandle> {
+        let _ = handle;
+        Ok(())
+    }
+
+    /// Notifies the tree that a connector will not be used anymore.
+    ///
+    /// The caller detaches the connector before removing it
+    /// and does not use its handle afterwards. Trees whose handles
+    /// are garbage-collected do not need to do anything.
+    fn remove_connector(&mut self, handle: &Self::ConnectorHandle) -> Result<(), InvalidHandle> {
+        let _ = handle;
+        Ok(())
+    }
 }
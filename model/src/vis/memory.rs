@@ -0,0 +1,768 @@
+//! Visualization tree that is kept in memory.
+//!
+//! [`MemoryVisTree`] is a complete [`VisTree`] that does not present
+//! the scene anywhere, but lets it be inspected instead.
+//! It is useful for running the rendering pipeline headlessly,
+//! for example in tests, and as a reference for the behavior
+//! that other implementations of [`VisTree`] should mimic.
+//!
+//! ```
+//! use aili_model::vis::{memory::*, *};
+//!
+//! let mut tree = MemoryVisTree::new();
+//! let root = tree.add_element("graph");
+//! let child = tree.add_element("cell");
+//! tree.get_element(&child).unwrap().insert_into(Some(&root)).unwrap();
+//! tree.get_element(&child).unwrap().set_attribute("value", Some("5"));
+//! tree.set_root(Some(&root)).unwrap();
+//!
+//! assert_eq!(tree.root(), Some(root));
+//! assert_eq!(tree.children(root).collect::<Vec<_>>(), [child]);
+//! let element = tree.element(child).unwrap();
+//! assert_eq!(element.tag_name(), "cell");
+//! assert_eq!(element.attribute("value"), Some("5"));
+//!
+//! // A cycle is rejected
+//! assert!(matches!(
+//!     tree.get_element(&root).unwrap().insert_into(Some(&child)),
+//!     Err(ParentAssignmentError::StructureViolation),
+//! ));
+//! ```
+
+use super::{
+    AttributeMap, InvalidHandle, ParentAssignmentError, VisConnector, VisElement, VisPin, VisTree,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+};
+
+/// Handle to an element or a connector of a [`MemoryVisTree`].
+///
+/// Handles are indices, so elements and connectors
+/// can be identified by their handles in assertions.
+/// Handles of removed entities are not reused.
+pub type MemoryHandle = usize;
+
+/// [`VisTree`] that keeps the scene in memory.
+///
+/// All operations are validated:
+/// - Handles of entities that do not exist or have been removed
+///   are rejected with [`InvalidHandle`].
+/// - Parent assignments that would create a cycle are rejected
+///   with [`ParentAssignmentError::StructureViolation`].
+///
+/// Removing an element detaches it from its parent, its children
+/// become detached, and pins attached to it are detached as well.
+/// Removing a connector does the same for pins attached to its midpoint.
+///
+/// ```
+/// use aili_model::vis::{memory::*, *};
+///
+/// let mut tree = MemoryVisTree::new();
+/// let element = tree.add_element("cell");
+/// let connector = tree.add_connector();
+/// tree.get_connector(&connector).unwrap().start_mut().attach_to(Some(&element)).unwrap();
+/// assert_eq!(
+///     tree.connector(connector).unwrap().start().target(),
+///     Some(PinTarget::Element(element)),
+/// );
+///
+/// tree.remove_element(&element).unwrap();
+/// assert_eq!(tree.connector(connector).unwrap().start().target(), None);
+/// assert!(tree.get_element(&element).is_err());
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct MemoryVisTree {
+    elements: Vec<Option<MemoryElement>>,
+    connectors: Vec<Option<MemoryConnector>>,
+    roots: Vec<MemoryHandle>,
+}
+
+impl MemoryVisTree {
+    /// Constructs an empty tree.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the first root of the tree, if any.
+    pub fn root(&self) -> Option<MemoryHandle> {
+        self.roots.first().copied()
+    }
+
+    /// Gets all roots of the tree.
+    pub fn roots(&self) -> &[MemoryHandle] {
+        &self.roots
+    }
+
+    /// Gets an element by its handle.
+    pub fn element(&self, handle: MemoryHandle) -> Option<&MemoryElement> {
+        self.elements.get(handle)?.as_ref()
+    }
+
+    /// Gets a connector by its handle.
+    pub fn connector(&self, handle: MemoryHandle) -> Option<&MemoryConnector> {
+        self.connectors.get(handle)?.as_ref()
+    }
+
+    /// Iterates over all elements that have not been removed,
+    /// in the order in which they were created.
+    pub fn elements(&self) -> impl Iterator<Item = (MemoryHandle, &MemoryElement)> {
+        self.elements
+            .iter()
+            .enumerate()
+            .filter_map(|(handle, element)| Some((handle, element.as_ref()?)))
+    }
+
+    /// Iterates over all connectors that have not been removed,
+    /// in the order in which they were created.
+    pub fn connectors(&self) -> impl Iterator<Item = (MemoryHandle, &MemoryConnector)> {
+        self.connectors
+            .iter()
+            .enumerate()
+            .filter_map(|(handle, connector)| Some((handle, connector.as_ref()?)))
+    }
+
+    /// Iterates over the children of an element,
+    /// in the order in which they were inserted.
+    pub fn children(&self, parent: MemoryHandle) -> impl Iterator<Item = MemoryHandle> + '_ {
+        self.element(parent)
+            .into_iter()
+            .flat_map(|element| element.children.iter().copied())
+    }
+
+    /// Decides whether an element is a root of the tree
+    /// or a descendant of one, and would therefore be displayed.
+    pub fn is_in_tree(&self, handle: MemoryHandle) -> bool {
+        let mut current = handle;
+        loop {
+            if self.roots.contains(&current) {
+                break true;
+            }
+            match self.element(current).and_then(MemoryElement::parent) {
+                Some(parent) => current = parent,
+                None => break false,
+            }
+        }
+    }
+
+    fn element_mut(&mut self, handle: MemoryHandle) -> Result<&mut MemoryElement, InvalidHandle> {
+        self.elements
+            .get_mut(handle)
+            .and_then(Option::as_mut)
+            .ok_or(InvalidHandle)
+    }
+
+    fn connector_mut(
+        &mut self,
+        handle: MemoryHandle,
+    ) -> Result<&mut MemoryConnector, InvalidHandle> {
+        self.connectors
+            .get_mut(handle)
+            .and_then(Option::as_mut)
+            .ok_or(InvalidHandle)
+    }
+
+    fn check_element(&self, handle: MemoryHandle) -> Result<(), InvalidHandle> {
+        self.element(handle).map(|_| ()).ok_or(InvalidHandle)
+    }
+
+    fn check_connector(&self, handle: MemoryHandle) -> Result<(), InvalidHandle> {
+        self.connector(handle).map(|_| ()).ok_or(InvalidHandle)
+    }
+
+    fn is_ancestor_of(&self, ancestor: MemoryHandle, descendant: MemoryHandle) -> bool {
+        let mut current = descendant;
+        loop {
+            if current == ancestor {
+                break true;
+            }
+            match self.element(current).and_then(MemoryElement::parent) {
+                Some(parent) => current = parent,
+                None => break false,
+            }
+        }
+    }
+
+    fn set_parent(
+        &mut self,
+        handle: MemoryHandle,
+        parent: Option<MemoryHandle>,
+    ) -> Result<(), ParentAssignmentError> {
+        self.check_element(handle)?;
+        if let Some(parent) = parent {
+            self.check_element(parent)?;
+            if self.is_ancestor_of(handle, parent) {
+                return Err(ParentAssignmentError::StructureViolation);
+            }
+        }
+        if let Some(old_parent) = self.elements[handle].as_ref().and_then(|e| e.parent) {
+            self.element_mut(old_parent)?
+                .children
+                .retain(|&child| child != handle);
+        }
+        if let Some(parent) = parent {
+            self.element_mut(parent)?.children.push(handle);
+        }
+        self.element_mut(handle)?.parent = parent;
+        Ok(())
+    }
+
+    /// Detaches all pins that target something.
+    fn detach_pins(&mut self, target: PinTarget) {
+        for connector in self.connectors.iter_mut().flatten() {
+            for pin in [&mut connector.start, &mut connector.end] {
+                if pin.target == Some(target) {
+                    pin.target = None;
+                }
+            }
+        }
+    }
+
+    fn pin_mut(&mut self, connector: MemoryHandle, end: PinEnd) -> &mut MemoryPin {
+        let connector = self.connectors[connector]
+            .as_mut()
+            .expect("Connector of a pin reference should exist");
+        match end {
+            PinEnd::Start => &mut connector.start,
+            PinEnd::End => &mut connector.end,
+        }
+    }
+}
+
+impl VisTree for MemoryVisTree {
+    type ElementHandle = MemoryHandle;
+    type ConnectorHandle = MemoryHandle;
+    type ElementRef<'a> = MemoryElementRef<'a>;
+    type ConnectorRef<'a> = MemoryConnectorRef<'a>;
+
+    fn set_root(&mut self, handle: Option<&Self::ElementHandle>) -> Result<(), InvalidHandle> {
+        self.set_roots(&Vec::from_iter(handle.copied()))
+    }
+
+    fn set_roots(&mut self, handles: &[Self::ElementHandle]) -> Result<(), InvalidHandle> {
+        for &handle in handles {
+            self.check_element(handle)?;
+        }
+        self.roots = handles.to_vec();
+        Ok(())
+    }
+
+    fn add_element(&mut self, tag_name: &str) -> Self::ElementHandle {
+        self.elements.push(Some(MemoryElement {
+            tag_name: tag_name.to_owned(),
+            attributes: BTreeMap::new(),
+            parent: None,
+            children: Vec::new(),
+            layer: 0,
+        }));
+        self.elements.len() - 1
+    }
+
+    fn add_connector(&mut self) -> Self::ConnectorHandle {
+        self.connectors.push(Some(MemoryConnector::default()));
+        self.connectors.len() - 1
+    }
+
+    fn get_element(
+        &mut self,
+        handle: &Self::ElementHandle,
+    ) -> Result<Self::ElementRef<'_>, InvalidHandle> {
+        self.check_element(*handle)?;
+        Ok(MemoryElementRef {
+            tree: self,
+            handle: *handle,
+        })
+    }
+
+    fn get_connector(
+        &mut self,
+        handle: &Self::ConnectorHandle,
+    ) -> Result<Self::ConnectorRef<'_>, InvalidHandle> {
+        self.check_connector(*handle)?;
+        Ok(MemoryConnectorRef {
+            tree: self,
+            handle: *handle,
+        })
+    }
+
+    fn remove_element(&mut self, handle: &Self::ElementHandle) -> Result<(), InvalidHandle> {
+        let handle = *handle;
+        self.set_parent(handle, None).map_err(|_| InvalidHandle)?;
+        let element = self.elements[handle]
+            .take()
+            .expect("Element should exist after it has been detached");
+        for child in element.children {
+            if let Some(child) = self.elements[child].as_mut() {
+                child.parent = None;
+            }
+        }
+        self.roots.retain(|&root| root != handle);
+        self.detach_pins(PinTarget::Element(handle));
+        Ok(())
+    }
+
+    fn remove_connector(&mut self, handle: &Self::ConnectorHandle) -> Result<(), InvalidHandle> {
+        self.check_connector(*handle)?;
+        self.connectors[*handle] = None;
+        self.detach_pins(PinTarget::Connector(*handle));
+        Ok(())
+    }
+}
+
+impl MemoryVisTree {
+    /// Describes the displayed part of the tree in a textual form
+    /// that does not depend on the order in which it was built.
+    ///
+    /// Two trees that display the same scene have the same description,
+    /// so descriptions can be compared against snapshots in tests.
+    ///
+    /// Each element is on its own line, indented below its parent,
+    /// with its attributes sorted by name. Children of an element
+    /// are sorted by their descriptions. Elements are numbered
+    /// in the order in which they are listed, so connectors can refer to them.
+    /// Connectors follow the elements, also sorted by their descriptions,
+    /// each with the targets of its pins, where `#n` is an element,
+    /// `~n` is the midpoint of another connector, `?` is an element
+    /// that is not displayed, and `-` means the pin is not attached.
+    /// Elements that are not displayed are left out, and so are
+    /// connectors that are not attached to anything that is displayed.
+    ///
+    /// ```
+    /// use aili_model::vis::{memory::*, *};
+    ///
+    /// let mut tree = MemoryVisTree::new();
+    /// let root = tree.add_element("graph");
+    /// for value in ["b", "a"] {
+    ///     let child = tree.add_element("cell");
+    ///     let mut child_ref = tree.get_element(&child).unwrap();
+    ///     child_ref.insert_into(Some(&root)).unwrap();
+    ///     child_ref.set_attribute("value", Some(value));
+    /// }
+    /// let connector = tree.add_connector();
+    /// let mut connector_ref = tree.get_connector(&connector).unwrap();
+    /// connector_ref.start_mut().attach_to(Some(&root)).unwrap();
+    /// connector_ref.end_mut().set_attribute("decoration", Some("arrow"));
+    /// tree.set_root(Some(&root)).unwrap();
+    ///
+    /// assert_eq!(tree.describe(), "\
+    /// #0 graph
+    ///   #1 cell value=\"a\"
+    ///   #2 cell value=\"b\"
+    /// connector #0 -> -
+    ///   end decoration=\"arrow\"
+    /// ");
+    /// ```
+    pub fn describe(&self) -> String {
+        let mut element_numbers = HashMap::new();
+        let mut output = String::new();
+        for &root in &self.roots {
+            self.describe_element(root, 0, &mut element_numbers, &mut output);
+        }
+        // Connectors are sorted without regard to which connectors
+        // they are attached to, so that they can be numbered first
+        let describe_connectors = |connector_numbers: &HashMap<MemoryHandle, usize>| {
+            let mut descriptions = self
+                .connectors()
+                .filter(|(_, connector)| {
+                    [&connector.start, &connector.end]
+                        .iter()
+                        .any(|pin| self.is_pin_displayed(pin))
+                })
+                .map(|(handle, connector)| {
+                    let description =
+                        self.describe_connector(connector, &element_numbers, connector_numbers);
+                    (description, handle)
+                })
+                .collect::<Vec<_>>();
+            descriptions.sort();
+            descriptions
+        };
+        let connector_numbers = describe_connectors(&HashMap::new())
+            .into_iter()
+            .enumerate()
+            .map(|(number, (_, handle))| (handle, number))
+            .collect();
+        for (description, _) in describe_connectors(&connector_numbers) {
+            output.push_str(&description);
+        }
+        output
+    }
+
+    /// Describes an element and its descendants and numbers them.
+    fn describe_element(
+        &self,
+        handle: MemoryHandle,
+        depth: usize,
+        element_numbers: &mut HashMap<MemoryHandle, usize>,
+        output: &mut String,
+    ) {
+        let Some(element) = self.element(handle) else {
+            return;
+        };
+        let number = element_numbers.len();
+        element_numbers.insert(handle, number);
+        let _ = write!(output, "{:indent$}#{number} ", "", indent = depth * 2);
+        output.push_str(&Self::describe_element_line(element));
+        output.push('\n');
+        for child in self.sorted_children(element) {
+            self.describe_element(child, depth + 1, element_numbers, output);
+        }
+    }
+
+    /// Describes an element and its descendants without numbering them,
+    /// which is used to sort siblings.
+    fn element_shape(&self, element: &MemoryElement, depth: usize, output: &mut String) {
+        let _ = write!(output, "{:indent$}", "", indent = depth * 2);
+        output.push_str(&Self::describe_element_line(element));
+        output.push('\n');
+        for child in self.sorted_children(element) {
+            if let Some(child) = self.element(child) {
+                self.element_shape(child, depth + 1, output);
+            }
+        }
+    }
+
+    /// Gets the children of an element, sorted by their shapes.
+    fn sorted_children(&self, element: &MemoryElement) -> Vec<MemoryHandle> {
+        let mut children = element
+            .children
+            .iter()
+            .filter_map(|&child| {
+                let mut shape = String::new();
+                self.element_shape(self.element(child)?, 0, &mut shape);
+                Some((shape, child))
+            })
+            .collect::<Vec<_>>();
+        children.sort();
+        children.into_iter().map(|(_, child)| child).collect()
+    }
+
+    /// Describes an element on its own.
+    fn describe_element_line(element: &MemoryElement) -> String {
+        let mut line = element.tag_name.clone();
+        if element.layer != 0 {
+            let _ = write!(line, " (layer {})", element.layer);
+        }
+        line.push_str(&describe_attributes(&element.attributes));
+        line
+    }
+
+    /// Describes a connector and its pins.
+    fn describe_connector(
+        &self,
+        connector: &MemoryConnector,
+        element_numbers: &HashMap<MemoryHandle, usize>,
+        connector_numbers: &HashMap<MemoryHandle, usize>,
+    ) -> String {
+        let describe_target = |pin: &MemoryPin| match pin.target {
+            Some(PinTarget::Element(handle)) => match element_numbers.get(&handle) {
+                Some(number) => format!("#{number}"),
+                None => "?".to_owned(),
+            },
+            Some(PinTarget::Connector(handle)) => match connector_numbers.get(&handle) {
+                Some(number) => format!("~{number}"),
+                None => "~".to_owned(),
+            },
+            None => "-".to_owned(),
+        };
+        let mut description = format!(
+            "connector {} -> {}{}\n",
+            describe_target(&connector.start),
+            describe_target(&connector.end),
+            describe_attributes(&connector.attributes),
+        );
+        for (name, pin) in [("start", &connector.start), ("end", &connector.end)] {
+            if !pin.attributes.is_empty() {
+                let attributes = describe_attributes(&pin.attributes);
+                let _ = writeln!(description, "  {name}{attributes}");
+            }
+        }
+        description
+    }
+
+    /// Decides whether a pin is attached to something that is displayed.
+    fn is_pin_displayed(&self, pin: &MemoryPin) -> bool {
+        match pin.target {
+            Some(PinTarget::Element(handle)) => self.is_in_tree(handle),
+            Some(PinTarget::Connector(handle)) => self.connector(handle).is_some_and(|connector| {
+                // Connectors attached to each other in a cycle
+                // are not displayed unless one of them reaches an element
+                [&connector.start, &connector.end].iter().any(|pin| {
+                    matches!(pin.target, Some(PinTarget::Element(handle)) if self.is_in_tree(handle))
+                })
+            }),
+            None => false,
+        }
+    }
+}
+
+/// Describes attributes, sorted by name, each preceded by a space.
+fn describe_attributes(attributes: &BTreeMap<String, String>) -> String {
+    attributes
+        .iter()
+        .map(|(name, value)| format!(" {name}={value:?}"))
+        .collect()
+}
+
+/// Element of a [`MemoryVisTree`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MemoryElement {
+    tag_name: String,
+    attributes: BTreeMap<String, String>,
+    parent: Option<MemoryHandle>,
+    children: Vec<MemoryHandle>,
+    layer: i32,
+}
+
+impl MemoryElement {
+    /// Gets the tag name of the element.
+    pub fn tag_name(&self) -> &str {
+        &self.tag_name
+    }
+
+    /// Gets the value of an attribute, if present.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    /// Gets all attributes of the element, ordered by name.
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+
+    /// Gets the parent of the element, if it has one.
+    pub fn parent(&self) -> Option<MemoryHandle> {
+        self.parent
+    }
+
+    /// Gets the layer the element is drawn in.
+    pub fn layer(&self) -> i32 {
+        self.layer
+    }
+}
+
+/// Connector of a [`MemoryVisTree`].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct MemoryConnector {
+    attributes: BTreeMap<String, String>,
+    start: MemoryPin,
+    end: MemoryPin,
+}
+
+impl MemoryConnector {
+    /// Gets the value of an attribute, if present.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    /// Gets all attributes of the connector, ordered by name.
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+
+    /// Gets the start pin.
+    pub fn start(&self) -> &MemoryPin {
+        &self.start
+    }
+
+    /// Gets the end pin.
+    pub fn end(&self) -> &MemoryPin {
+        &self.end
+    }
+}
+
+/// Pin of a [`MemoryConnector`].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct MemoryPin {
+    attributes: BTreeMap<String, String>,
+    target: Option<PinTarget>,
+}
+
+impl MemoryPin {
+    /// Gets the value of an attribute, if present.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    /// Gets all attributes of the pin, ordered by name.
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+
+    /// Gets what the pin is attached to, if anything.
+    pub fn target(&self) -> Option<PinTarget> {
+        self.target
+    }
+}
+
+/// What a [`MemoryPin`] is attached to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PinTarget {
+    /// Pin is attached to an element.
+    Element(MemoryHandle),
+
+    /// Pin is attached to the midpoint of a connector.
+    Connector(MemoryHandle),
+}
+
+/// Which pin of a connector a [`MemoryPinRef`] refers to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PinEnd {
+    Start,
+    End,
+}
+
+/// Reference to an element of a [`MemoryVisTree`].
+pub struct MemoryElementRef<'a> {
+    tree: &'a mut MemoryVisTree,
+    handle: MemoryHandle,
+}
+
+impl MemoryElementRef<'_> {
+    fn element(&self) -> &MemoryElement {
+        self.tree.elements[self.handle]
+            .as_ref()
+            .expect("Element of a reference should exist")
+    }
+
+    fn element_mut(&mut self) -> &mut MemoryElement {
+        self.tree.elements[self.handle]
+            .as_mut()
+            .expect("Element of a reference should exist")
+    }
+}
+
+impl AttributeMap for MemoryElementRef<'_> {
+    fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.element().attribute(name)
+    }
+
+    fn set_attribute(&mut self, name: &str, value: Option<&str>) {
+        set_attribute(&mut self.element_mut().attributes, name, value);
+    }
+}
+
+impl VisElement for MemoryElementRef<'_> {
+    type Handle = MemoryHandle;
+
+    fn insert_into(&mut self, parent: Option<&Self::Handle>) -> Result<(), ParentAssignmentError> {
+        self.tree.set_parent(self.handle, parent.copied())
+    }
+
+    fn set_layer(&mut self, layer: i32) {
+        self.element_mut().layer = layer;
+    }
+}
+
+/// Reference to a connector of a [`MemoryVisTree`].
+pub struct MemoryConnectorRef<'a> {
+    tree: &'a mut MemoryVisTree,
+    handle: MemoryHandle,
+}
+
+impl MemoryConnectorRef<'_> {
+    fn connector(&self) -> &MemoryConnector {
+        self.tree.connectors[self.handle]
+            .as_ref()
+            .expect("Connector of a reference should exist")
+    }
+
+    fn pin_ref(&mut self, end: PinEnd) -> MemoryPinRef<'_> {
+        MemoryPinRef {
+            tree: self.tree,
+            connector: self.handle,
+            end,
+        }
+    }
+}
+
+impl AttributeMap for MemoryConnectorRef<'_> {
+    fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.connector().attribute(name)
+    }
+
+    fn set_attribute(&mut self, name: &str, value: Option<&str>) {
+        let connector = self
+            .tree
+            .connector_mut(self.handle)
+            .expect("Connector of a reference should exist");
+        set_attribute(&mut connector.attributes, name, value);
+    }
+}
+
+impl VisConnector for MemoryConnectorRef<'_> {
+    type Handle = MemoryHandle;
+    type ConnectorHandle = MemoryHandle;
+    type PinRef<'a>
+        = MemoryPinRef<'a>
+    where
+        Self: 'a;
+
+    fn start_mut(&mut self) -> Self::PinRef<'_> {
+        self.pin_ref(PinEnd::Start)
+    }
+
+    fn end_mut(&mut self) -> Self::PinRef<'_> {
+        self.pin_ref(PinEnd::End)
+    }
+}
+
+/// Reference to a pin of a connector of a [`MemoryVisTree`].
+pub struct MemoryPinRef<'a> {
+    tree: &'a mut MemoryVisTree,
+    connector: MemoryHandle,
+    end: PinEnd,
+}
+
+impl AttributeMap for MemoryPinRef<'_> {
+    fn get_attribute(&self, name: &str) -> Option<&str> {
+        let connector = self.tree.connector(self.connector)?;
+        match self.end {
+            PinEnd::Start => connector.start.attribute(name),
+            PinEnd::End => connector.end.attribute(name),
+        }
+    }
+
+    fn set_attribute(&mut self, name: &str, value: Option<&str>) {
+        let pin = self.tree.pin_mut(self.connector, self.end);
+        set_attribute(&mut pin.attributes, name, value);
+    }
+}
+
+impl VisPin for MemoryPinRef<'_> {
+    type Handle = MemoryHandle;
+    type ConnectorHandle = MemoryHandle;
+
+    fn attach_to(&mut self, target: Option<&Self::Handle>) -> Result<(), InvalidHandle> {
+        if let Some(&target) = target {
+            self.tree.check_element(target)?;
+        }
+        self.tree.pin_mut(self.connector, self.end).target =
+            target.map(|&target| PinTarget::Element(target));
+        Ok(())
+    }
+
+    fn attach_to_connector(
+        &mut self,
+        target: Option<&Self::ConnectorHandle>,
+    ) -> Result<(), InvalidHandle> {
+        if let Some(&target) = target {
+            self.tree.check_connector(target)?;
+        }
+        self.tree.pin_mut(self.connector, self.end).target =
+            target.map(|&target| PinTarget::Connector(target));
+        Ok(())
+    }
+}
+
+fn set_attribute(attributes: &mut BTreeMap<String, String>, name: &str, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            attributes.insert(name.to_owned(), value.to_owned());
+        }
+        None => {
+            attributes.remove(name);
+        }
+    }
+}
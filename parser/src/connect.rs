@@ -16,11 +16,13 @@ impl<'a> From<LexOutputToken<'a>> for GrammarInputToken<'a> {
             Quoted(s) => Self::Quoted(s),
             Int(i) => Self::Int(i),
             RestrictMatcher => Self::If,
+            UnlessMatcher => Self::Unless,
             ManyMatcher => Self::Many,
             AltMatcher => Self::Alt,
             RootMatcher => Self::RootMatcher,
             EdgeMatcher => Self::EdgeMatcher,
             ExtraMatcher => Self::Extra,
+            ParentMatcher => Self::Parent,
             Plus => Self::Plus,
             Minus => Self::Minus,
             Not => Self::Not,
@@ -36,6 +38,10 @@ impl<'a> From<LexOutputToken<'a>> for GrammarInputToken<'a> {
             DoubleAnd => Self::And,
             DoubleOr => Self::Or,
             At => Self::At,
+            Requires => Self::Requires,
+            Function => Self::Function,
+            When => Self::When,
+            Rule => Self::Rule,
             Semicolon => Self::Semicolon,
             Comma => Self::Comma,
             Colon => Self::Colon,
@@ -0,0 +1,182 @@
+//! Machine-readable reports of problems in stylesheet sources.
+
+use crate::{
+    LexerError, ParseError, ParseErrorInfo, ParseFailure, ParseOptions, SyntaxError,
+    parse_stylesheet_with_positions,
+};
+use derive_more::Display;
+
+/// Problem found in a stylesheet source by [`lint_stylesheet`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    /// One-based number of the line where the problem occurred.
+    pub line_number: usize,
+
+    /// One-based number of the column where the problem occurred,
+    /// counted in characters.
+    pub column_number: usize,
+
+    /// How serious the problem is.
+    pub severity: Severity,
+
+    /// Stable identifier of the kind of problem,
+    /// such as `unexpected-token`.
+    pub code: &'static str,
+
+    /// Human-readable description of the problem.
+    pub message: String,
+}
+
+/// Seriousness of a [`Diagnostic`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+pub enum Severity {
+    /// Part of the source could not be understood
+    /// and has been left out of the stylesheet.
+    #[display("error")]
+    Error,
+
+    /// The source has been understood, but it is likely
+    /// not what the author intended.
+    #[display("warning")]
+    Warning,
+}
+
+/// Parses a stylesheet and collects all problems
+/// in the source, instead of the stylesheet itself.
+///
+/// Problems are collected the same way the error handler
+/// of [`parse_stylesheet_with_options`](crate::parse_stylesheet_with_options)
+/// receives them, so problems that are likely consequences of another
+/// problem are left out. If the parser fails irrecoverably,
+/// the failure is the last diagnostic.
+///
+/// ```
+/// use aili_parser::{ParseOptions, diagnostics::{Severity, lint_stylesheet}};
+///
+/// let diagnostics = lint_stylesheet(":: {\n  display: graph; ]\n}", ParseOptions::new());
+/// assert_eq!(diagnostics[0].line_number, 2);
+/// assert_eq!(diagnostics[0].column_number, 19);
+/// assert_eq!(diagnostics[0].severity, Severity::Error);
+/// assert_eq!(diagnostics[0].code, "unexpected-token");
+/// ```
+pub fn lint_stylesheet(source: &str, options: ParseOptions) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let result = parse_stylesheet_with_positions(source, options, |error, column_number| {
+        diagnostics.push(Diagnostic::from_parse_error(error, column_number));
+    });
+    if let Err((failure, line_number, column_number)) = result {
+        diagnostics.push(Diagnostic::from_parse_failure(
+            failure,
+            line_number,
+            column_number,
+        ));
+    }
+    diagnostics
+}
+
+impl Diagnostic {
+    /// Describes a recoverable error.
+    fn from_parse_error(error: ParseError, column_number: usize) -> Self {
+        let severity = match &error.error_data {
+            // The parser recovers by closing the rule,
+            // so nothing is lost
+            ParseErrorInfo::SyntaxError(SyntaxError::UnterminatedRule) => Severity::Warning,
+            _ => Severity::Error,
+        };
+        Self {
+            line_number: error.line_number,
+            column_number,
+            severity,
+            code: error_code(&error.error_data),
+            message: error.error_data.to_string(),
+        }
+    }
+
+    /// Describes an irrecoverable failure.
+    fn from_parse_failure(failure: ParseFailure, line_number: usize, column_number: usize) -> Self {
+        let code = match failure {
+            ParseFailure::Generic => "parser-failure",
+            ParseFailure::StackOverflow => "parser-stack-overflow",
+        };
+        Self {
+            line_number,
+            column_number,
+            severity: Severity::Error,
+            code,
+            message: failure.to_string(),
+        }
+    }
+}
+
+/// Gets the stable identifier of a kind of recoverable error.
+fn error_code(error: &ParseErrorInfo) -> &'static str {
+    match error {
+        ParseErrorInfo::LexerError(error) => match error {
+            LexerError::Generic => "invalid-token",
+            LexerError::ParseIntError(_) => "invalid-integer",
+            LexerError::InvalidUnquoted => "invalid-unquoted",
+            LexerError::AlphaCharacterInNumber => "alphabetic-in-number",
+            LexerError::UnterminatedQuoted => "unterminated-string",
+            LexerError::UnknownEscape(_) => "unknown-escape",
+            LexerError::InvalidUnicodeEscape => "invalid-unicode-escape",
+        },
+        ParseErrorInfo::SyntaxError(error) => match error {
+            SyntaxError::UnexpectedEnd => "unexpected-end",
+            SyntaxError::UnexpectedToken => "unexpected-token",
+            SyntaxError::InvalidEdgeLabel(_) => "unknown-edge-label",
+            SyntaxError::InvalidFunction(_) => "unknown-function",
+            SyntaxError::ArgumentCountMismatch(..) => "argument-count-mismatch",
+            SyntaxError::InvalidIntegerWidth(_) => "invalid-integer-width",
+            SyntaxError::InvalidPadding(_) => "invalid-padding",
+            SyntaxError::InvalidParameter(_) => "invalid-parameter",
+            SyntaxError::InvalidFunctionBody(_) => "invalid-function-body",
+            SyntaxError::InvalidUnquoted(_) => "invalid-literal",
+            SyntaxError::MissingElseBranch => "missing-else-branch",
+            SyntaxError::UnterminatedRule => "unterminated-rule",
+            SyntaxError::SyntaxLevelRequired(_) => "syntax-level-required",
+            SyntaxError::NestingTooDeep(_) => "nesting-too-deep",
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn valid_stylesheet_has_no_diagnostics() {
+        let diagnostics = lint_stylesheet(":: { display: graph; }", ParseOptions::new());
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn lexer_error_position() {
+        let diagnostics = lint_stylesheet(":: {\n    a: \"b;\n}", ParseOptions::new());
+        assert_eq!(
+            diagnostics.first(),
+            Some(&Diagnostic {
+                line_number: 2,
+                column_number: 8,
+                severity: Severity::Error,
+                code: "unterminated-string",
+                message: LexerError::UnterminatedQuoted.to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn columns_are_counted_in_characters() {
+        let diagnostics = lint_stylesheet("\"ř\" ]", ParseOptions::new());
+        assert_eq!(diagnostics[0].line_number, 1);
+        assert_eq!(diagnostics[0].column_number, 5);
+        assert_eq!(diagnostics[0].code, "unexpected-token");
+    }
+
+    #[test]
+    fn unterminated_rule_is_warning() {
+        let diagnostics = lint_stylesheet(":: { a: b", ParseOptions::new());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].code, "unterminated-rule");
+    }
+}
@@ -0,0 +1,34 @@
+//! Opt-in levels of syntax support.
+
+use derive_more::Display;
+
+/// Determines which syntactic constructs the parser accepts.
+///
+/// Constructs that are still experimental are only accepted
+/// if the parser is explicitly asked to accept them.
+/// Stylesheets that only use stable syntax parse the same way
+/// regardless of the level.
+///
+/// Levels are ordered so that each level accepts everything
+/// that lower levels accept.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Display, Default, Hash)]
+pub enum SyntaxLevel {
+    /// Only syntax that is considered stable is accepted.
+    #[default]
+    #[display("stable")]
+    Stable,
+
+    /// Experimental syntax is accepted in addition to stable syntax.
+    ///
+    /// Experimental constructs may change or be removed in the future.
+    #[display("experimental")]
+    Experimental,
+}
+
+impl SyntaxLevel {
+    /// Checks whether constructs that require a given level
+    /// are accepted at this level.
+    pub fn allows(self, required: SyntaxLevel) -> bool {
+        self >= required
+    }
+}
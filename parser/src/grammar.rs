@@ -3,11 +3,15 @@
 
 #![expect(clippy::let_unit_value, reason = "Clippy is triggered by macro output")]
 
-use crate::symbols::*;
+use crate::{features::SyntaxLevel, options::ParseOptions, symbols::*};
 use aili_model::state::EdgeLabel;
-use aili_style::stylesheet::{expression::*, selector::*, *};
+use aili_style::stylesheet::{
+    borrowed::*,
+    expression::{BinaryOperator, MagicVariableKey, UnaryOperator},
+};
 use derive_more::{Display, Error, From};
 use pomelo::pomelo;
+use std::{borrow::Cow, collections::HashMap};
 
 /// Error type returned by a parser when it irrecoverably fails.
 ///
@@ -21,6 +25,11 @@ pub enum ParseFailure {
     Generic,
 
     /// Parser's stack has overflown.
+    ///
+    /// This does not happen unless
+    /// [`ParseOptions::max_nesting_depth`] has been raised,
+    /// because expressions nested too deeply are discarded
+    /// before they reach the parser.
     #[display("parser stack overflow")]
     StackOverflow,
 }
@@ -47,42 +56,118 @@ pub enum SyntaxError {
     #[display("unknown function name {:?}", _0.0)]
     InvalidFunction(InvalidSymbol),
 
+    /// A function was called with a different number of arguments
+    /// than it accepts.
+    #[display("function {_0:?} expects {_1} arguments, but {_2} were provided")]
+    #[error(ignore)]
+    ArgumentCountMismatch(String, usize, usize),
+
+    /// The width argument of a function such as `as-signed`
+    /// is not an integer literal between 1 and 64.
+    #[display("function {_0:?} expects a width between 1 and 64 bits")]
+    #[error(ignore)]
+    InvalidIntegerWidth(String),
+
+    /// The width or fill argument of a function such as `pad-left`
+    /// is not an integer literal or a single-character string literal.
+    #[display("function {_0:?} expects an integer width and a single fill character")]
+    #[error(ignore)]
+    InvalidPadding(String),
+
+    /// A parameter of a user-defined function is not a variable name.
+    #[display("function parameter {:?} must be a variable name", _0.0)]
+    InvalidParameter(InvalidSymbol),
+
+    /// The body of a user-defined function is not a single `return` clause.
+    #[display("body of function {_0:?} must be a single return clause")]
+    #[error(ignore)]
+    InvalidFunctionBody(String),
+
     /// An invalid literal was used in an expression.
     #[display("token {:?} which is not a literal cannot appear in an expression", _0.0)]
     InvalidUnquoted(InvalidSymbol),
 
+    /// A conditional expression without an else branch
+    /// was assigned to something other than `display-if`.
+    #[display("only 'display-if' can be assigned a conditional expression without an else branch")]
+    MissingElseBranch,
+
     /// Missing closing brace at the end of input.
     #[display("last rule is missing a closing delimiter")]
     UnterminatedRule,
+
+    /// A construct was used that is not accepted at the parser's [`SyntaxLevel`].
+    #[display("this syntax requires {_0} syntax level to be enabled")]
+    #[error(ignore)]
+    SyntaxLevelRequired(SyntaxLevel),
+
+    /// An expression is nested more deeply than
+    /// [`ParseOptions::max_nesting_depth`] allows.
+    #[display("expression is nested more than {_0} levels deep")]
+    #[error(ignore)]
+    NestingTooDeep(usize),
 }
 
 /// Additional state object for a parser.
 ///
 /// This state object facilitates error reporting and recovery.
-pub struct ErrorManager<'a> {
+/// User-defined functions it collects borrow from the source text.
+pub struct ErrorManager<'e, 'a> {
     /// Handler that will be invoked when a recoverable syntax error
     /// is encountered.
-    error_handler: Box<dyn FnMut(SyntaxError) + 'a>,
+    error_handler: Box<dyn FnMut(SyntaxError) + 'e>,
     /// True when the parser is in error recovery state.
     is_recovering: bool,
+    /// Options that determine what input is accepted.
+    options: ParseOptions,
+    /// User-defined functions that have been defined so far.
+    functions: HashMap<Cow<'a, str>, UserFunction<'a>>,
 }
 
-impl Default for ErrorManager<'static> {
+impl Default for ErrorManager<'static, '_> {
     fn default() -> Self {
         Self {
             error_handler: Box::new(|_| {}),
             is_recovering: false,
+            options: ParseOptions::default(),
+            functions: HashMap::new(),
         }
     }
 }
 
-impl<'a> ErrorManager<'a> {
+impl<'e> ErrorManager<'e, '_> {
     /// Constructs an extra state object with the default initial state
     /// and a provided error handler.
-    pub fn new<F: FnMut(SyntaxError) + 'a>(error_handler: F) -> Self {
+    pub fn new<F: FnMut(SyntaxError) + 'e>(error_handler: F) -> Self {
         Self {
             error_handler: Box::new(error_handler),
             is_recovering: false,
+            options: ParseOptions::default(),
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Sets the options that determine what input is accepted.
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Verifies that a construct that requires a given syntax level
+    /// may be used.
+    ///
+    /// If it may not, reports an error and switches to recovery state,
+    /// which can later be resolved by [`ErrorManager::recover`].
+    ///
+    /// ## Return Value
+    /// True if the construct is accepted, false otherwise.
+    fn require_syntax_level(&mut self, required: SyntaxLevel) -> bool {
+        if self.options.syntax_level.allows(required) {
+            true
+        } else {
+            (self.error_handler)(SyntaxError::SyntaxLevelRequired(required));
+            self.is_recovering = true;
+            false
         }
     }
 
@@ -126,6 +211,23 @@ impl<'a> ErrorManager<'a> {
         self.is_recovering = true;
     }
 
+    /// Signals that a token has been matched explicitly
+    /// by a rule of the grammar that only matches malformed input.
+    ///
+    /// The parser does not enter recovery state.
+    fn unexpected_token(&mut self) {
+        (self.error_handler)(SyntaxError::UnexpectedToken);
+    }
+
+    /// Signals that a clause ended before it was complete.
+    ///
+    /// Switches to recovery state, so that the clause is discarded,
+    /// which is resolved by [`ErrorManager::recover`].
+    fn malformed_clause(&mut self) {
+        self.unexpected_token();
+        self.is_recovering = true;
+    }
+
     /// Signals that a rule was missing a closing delimiter.
     ///
     /// As this is an easily recoverable error (parser just imagines the closing
@@ -136,6 +238,16 @@ impl<'a> ErrorManager<'a> {
         (self.error_handler)(SyntaxError::UnterminatedRule);
     }
 
+    /// Signals that an expression nested too deeply has been discarded
+    /// before reaching the parser.
+    ///
+    /// Switches to recovery state, so that the clause
+    /// that contains the expression is discarded as well.
+    pub fn nesting_too_deep(&mut self, limit: usize) {
+        (self.error_handler)(SyntaxError::NestingTooDeep(limit));
+        self.is_recovering = true;
+    }
+
     /// Signals that the parser has reached a state where it can
     /// safely discard a part of input if it is errorneous.
     ///
@@ -176,7 +288,9 @@ pomelo! {
     }
 
     // Use extra state data to propagate errors
-    %extra_argument ErrorManager<'a>;
+    // The error handler does not need to live as long as the source
+    %parser pub struct Parser<'e, 'a> {};
+    %extra_argument ErrorManager<'e, 'a>;
 
     %token
     /// Type of tokens accepted by the syntactic parser.
@@ -184,38 +298,48 @@ pomelo! {
     pub enum Token<'a> {};
 
     // Underlying types of nonterminal symbols
-    %type stylesheet Stylesheet;
-    %type sheet_part Stylesheet;
-    %type rule       StyleRule;
-    %type body       Vec<StyleClause>;
-    %type proplist   Vec<StyleClause>;
-    %type proplist1  Vec<StyleClause>;
-    %type clause     StyleClause;
-    %type lvalue     StyleKey;
-    %type rvalue     Expression;
-    %type selector   Selector;
-    %type selector1  Selector;
-    %type selector2  Selector;
-    %type condition  Expression;
-    %type path       SelectorPath;
-    %type segment    SelectorSegment;
-    %type pathlist   Vec<SelectorPath>;
-    %type limsel     LimitedSelector;
-    %type limsel1    LimitedSelector;
-    %type limpath    Vec<LimitedEdgeMatcher>;
-    %type limseg     LimitedEdgeMatcher;
-    %type matcher    EdgeMatcher;
+    %type stylesheet Stylesheet<'a>;
+    %type sheet_part Stylesheet<'a>;
+    %type named      Option<(Option<Cow<'a, str>>, StyleRule<'a>)>;
+    %type rule       Option<StyleRule<'a>>;
+    %type head       Option<Selector<'a>>;
+    %type caplist    Vec<&'a str>;
+    %type function   UserFunction<'a>;
+    %type block      Vec<(Option<Cow<'a, str>>, StyleRule<'a>)>;
+    %type params     Vec<Cow<'a, str>>;
+    %type params1    Vec<Cow<'a, str>>;
+    %type body       Vec<StyleClause<'a>>;
+    %type proplist1  Vec<StyleClause<'a>>;
+    %type clause     StyleClause<'a>;
+    %type lvalue     StyleKey<'a>;
+    %type rvalue     Expression<'a>;
+    %type selector   Selector<'a>;
+    %type selector1  Selector<'a>;
+    %type selector2  Selector<'a>;
+    %type condition  Expression<'a>;
+    %type path       SelectorPath<'a>;
+    %type segment    SelectorSegment<'a>;
+    %type pathlist   Vec<SelectorPath<'a>>;
+    %type limsel     LimitedSelector<'a>;
+    %type limsel1    LimitedSelector<'a>;
+    %type limpath    Vec<LimitedEdgeMatcher<'a>>;
+    %type limseg     LimitedEdgeMatcher<'a>;
+    %type matcher    EdgeMatcher<'a>;
     %type exact      EdgeLabel;
-    %type extra      String;
-    %type index      Expression;
-    %type expr       Expression;
-    %type rexpr      Expression;
+    %type extra      Cow<'a, str>;
+    %type index      Expression<'a>;
+    %type expr       Expression<'a>;
+    %type rexpr      Expression<'a>;
+    %type cexpr      Expression<'a>;
+    %type branches   Vec<(Expression<'a>, Expression<'a>)>;
+    %type args       Vec<Expression<'a>>;
+    %type args1      Vec<Expression<'a>>;
     %type bop        BinaryOperator;
     %type uop        UnaryOperator;
 
     // Underlying types of terminal symbols
     %type Unquoted   &'a str;
-    %type Quoted     &'a str;
+    %type Quoted     Cow<'a, str>;
     %type Int        u64;
 
     %type
@@ -251,38 +375,100 @@ pomelo! {
 
     // Rules in the stylesheet
     sheet_part ::=                                     { Stylesheet::default() }
-    sheet_part ::= sheet_part(mut s) rule(r)           { if !extra.recover() { s.0.push(r) } s }
-    rule ::= selector(s) body(b)                       { StyleRule { selector: s, properties: b } }
-    rule ::= error                                     { extra.shift_error(); StyleRule::default() }
+    sheet_part ::= sheet_part(mut s) named(r)          { if !extra.recover() { push_rules(&mut s, r) } s }
+    named ::= rule(r)                                  { r.map(|r| (None, r)) }
+    named ::= Rule Quoted(n) rule(r)                   { extra.require_syntax_level(SyntaxLevel::Experimental);
+                                                         r.map(|r| (Some(n), r)) }
+    rule ::= head(h) body(b)                           { h.map(|s| StyleRule { selector: s, properties: b }) }
+    rule ::= error                                     { extra.shift_error(); None }
+    // Errors in the selector must be resolved before the body is parsed,
+    // so they are not mistaken for errors in its first clause
+    head ::= selector(s)                               { (!extra.recover()).then_some(s) }
+
+    // Directives in the stylesheet
+    sheet_part ::= sheet_part(mut s) Requires caplist(l) Semicolon { if !extra.recover() { s.requirements.extend(l) } s }
+    caplist ::= Unquoted(c)                            { vec![c] }
+    caplist ::= caplist(mut l) Comma Unquoted(c)       { l.push(c); l }
+
+    // Conditional blocks
+    sheet_part ::= sheet_part(mut s) When cexpr(c) OpenBrace block(b) CloseBrace
+                                                       { extra.require_syntax_level(SyntaxLevel::Experimental);
+                                                         if !extra.recover() {
+                                                             let start = s.rules.len();
+                                                             push_rules(&mut s, b);
+                                                             s.conditional_blocks.push(ConditionalBlock { condition: c, rules: start..s.rules.len() });
+                                                         }
+                                                         s }
+    block ::=                                          { Vec::new() }
+    block ::= block(mut b) named(r)                    { if !extra.recover() { b.extend(r) } b }
+
+    // Function definitions
+    sheet_part ::= sheet_part(s) function(f)           { if !extra.recover() { extra.functions.insert(f.name.clone(), f); } s }
+    function ::= Function Unquoted(n) OpenParen params(p) CloseParen OpenBrace Unquoted(k) Colon rvalue(r) Semicolon? CloseBrace
+                                                       { extra.require_syntax_level(SyntaxLevel::Experimental);
+                                                         let function = user_function(n, p, k, r);
+                                                         extra.try_or(function, UserFunction::new(Cow::default(), Vec::new(), Expression::Unset)) }
+    params ::=                                         { Vec::new() }
+    params ::= params1;
+    params1 ::= Unquoted(p)                            { vec![p.into()] }
+    params1 ::= params1(mut l) Comma Unquoted(p)       { l.push(p.into()); l }
 
     // Rule body (the part that is not a selector)
-    body ::= OpenBrace proplist CloseBrace;
-    body ::= OpenBrace proplist(l) End                 { extra.unterminated_rule(); l }
-    proplist ::= proplist1;
-    proplist ::= proplist1(mut l) clause(c)            { l.push(c); l }
+    // The closing brace is matched together with the last clause,
+    // so that a malformed clause cannot be mistaken for the end of the body
+    body ::= OpenBrace proplist1(l) CloseBrace         { l }
+    body ::= OpenBrace proplist1(mut l) clause(c) CloseBrace { if !extra.recover() { l.push(c) } l }
+    body ::= OpenBrace proplist1(l) End                { extra.unterminated_rule(); l }
+    body ::= OpenBrace proplist1(mut l) clause(c) End  { if !extra.recover() { l.push(c) }
+                                                         extra.unterminated_rule();
+                                                         l }
     proplist1 ::=                                      { Vec::new() }
-    proplist1 ::= proplist1(mut l) clause(c) Semicolon { l.push(c); l }
+    proplist1 ::= proplist1(mut l) clause(c) Semicolon { if !extra.recover() { l.push(c) } l }
+    proplist1 ::= proplist1(l) Semicolon               { extra.unexpected_token(); l }
     clause ::= lvalue(l) Colon rvalue(r)               { StyleClause { key: l, value: r } }
-    lvalue ::= Quoted(s)                               { StyleKey::Property(RawPropertyKey::QuotedProperty(s.to_owned())) }
+    clause ::= lvalue(l) Colon expr(c) Question Unquoted(t) { extra.require_syntax_level(SyntaxLevel::Experimental);
+                                                         let clause = conditional_display(l, c, t.into());
+                                                         extra.try_or(clause, StyleClause { key: StyleKey::Variable(Cow::default()), value: Expression::Unset }) }
+    clause ::= lvalue(l) Colon expr(c) Question Quoted(t) { extra.require_syntax_level(SyntaxLevel::Experimental);
+                                                         let clause = conditional_display(l, c, t);
+                                                         extra.try_or(clause, StyleClause { key: StyleKey::Variable(Cow::default()), value: Expression::Unset }) }
+    // Clauses that end early are matched explicitly, so that recovery
+    // never consumes the semicolon or closing brace that ends them
+    clause ::= lvalue Colon                            { extra.malformed_clause(); StyleClause { key: StyleKey::Variable(Cow::default()), value: Expression::Unset } }
+    clause ::= lvalue                                  { extra.malformed_clause(); StyleClause { key: StyleKey::Variable(Cow::default()), value: Expression::Unset } }
+    // Other malformed clauses are skipped up to the next semicolon or closing brace
+    clause ::= error                                   { extra.shift_error(); StyleClause { key: StyleKey::Variable(Cow::default()), value: Expression::Unset } }
+    lvalue ::= Quoted(s)                               { StyleKey::Property(RawPropertyKey::QuotedProperty(s)) }
     lvalue ::= Unquoted(s)                             { if is_variable_name(s) {
-                                                             StyleKey::Variable(s.to_owned())
+                                                             StyleKey::Variable(s.into())
                                                          } else {
-                                                             StyleKey::Property(RawPropertyKey::Property(s.to_owned()))
+                                                             StyleKey::Property(RawPropertyKey::Property(s.into()))
                                                        } }
-    lvalue ::= Unquoted(f) Slash Unquoted|Quoted(s)    { StyleKey::Property(RawPropertyKey::FragmentProperty(f.to_owned(), s.to_owned())) }
+    lvalue ::= Unquoted(f) Slash Unquoted(s)           { StyleKey::Property(RawPropertyKey::FragmentProperty(f.into(), s.into())) }
+    lvalue ::= Unquoted(f) Slash Quoted(s)             { StyleKey::Property(RawPropertyKey::FragmentProperty(f.into(), s)) }
     rvalue ::= rexpr;
-    rvalue ::= Unquoted(s)                             { resolve_unquoted_expression(s).unwrap_or_else(|InvalidSymbol(s)| Expression::String(s)) }
+    rvalue ::= branches(b) expr(f)                     { conditional_chain(b, f) }
+    rvalue ::= Unquoted(s)                             { resolve_unquoted(s).unwrap_or(Expression::String(s.into())) }
 
     // Selectors
     selector ::= selector1;
     selector ::= selector1(s) extra(e)                 { s.with_extra(e) }
+    selector ::= selector1(s) extra(e) Colon Unquoted(f) OpenParen Int(i) CloseParen
+                                                       { let index = extra.try_or(extra_index(f, i), 0);
+                                                         s.with_extra(e).with_extra_index(index) }
     selector1 ::= selector2;
     selector1 ::= selector2(s) EdgeMatcher             { s.selecting_edge() }
     selector2 ::= RootMatcher path(p)                  { Selector::from_path(p) }
     selector2 ::= path(p)                              { selector_from_not_root(p.0) }
-    condition ::= If OpenParen expr CloseParen;
-    condition ::= Colon Unquoted(s)                    { type_match_condition(s, true) }
+    condition ::= If OpenParen cexpr CloseParen;
+    condition ::= Unless OpenParen limsel(s) CloseParen { Uop(Not, Expression::Select(s.into()).into()) }
+    condition ::= Colon Unquoted(s)                    { if value_condition_by_name(s).is_ok() {
+                                                             extra.require_syntax_level(SyntaxLevel::Experimental);
+                                                         }
+                                                         type_match_condition(s.into(), true) }
     condition ::= Colon Quoted(s)                      { type_match_condition(s, false) }
+    condition ::= Colon Unquoted(f) OpenParen Unquoted(s) CloseParen { extra.try_or(custom_class_condition(f, s.into()), Expression::Unset) }
+    condition ::= Colon Unquoted(f) OpenParen Quoted(s) CloseParen { extra.try_or(custom_class_condition(f, s), Expression::Unset) }
     path ::=                                           { [].into() }
     path ::= path(mut p) segment(s)                    { p.0.push(s); p }
     path ::= path(mut p) index(e)                      { if let Expression::Int(i) = e {
@@ -303,32 +489,36 @@ pomelo! {
     limsel ::= limsel1;
     limsel ::= limsel1(s) extra(e)                     { s.with_extra(e) }
     limsel1 ::= limpath(p)                             { LimitedSelector::from_path(p) }
-    limsel1 ::= OpenParen expr(o) CloseParen limpath(p) { LimitedSelector::from_path(p).with_origin(o) }
+    limsel1 ::= OpenParen cexpr(o) CloseParen limpath(p) { LimitedSelector::from_path(p).with_origin(o) }
     limpath ::=                                        { Vec::new() }
     limpath ::= limpath(mut p) limseg(s)               { p.push(s); p }
     limseg ::= exact(e)                                { e.into() }
     limseg ::= index(e)                                { if let Expression::Int(i) = e { EdgeLabel::Index(i as usize).into() } else { LimitedEdgeMatcher::DynIndex(e) } }
-    limseg ::= Quoted(s)                               { EdgeLabel::Named(s.to_owned(), 0).into() }
+    limseg ::= Quoted(s)                               { EdgeLabel::Named((&*s).into(), 0).into() }
+    limseg ::= Parent                                  { LimitedEdgeMatcher::Parent }
 
     // Matchers in selectors (both full and limited)
     matcher ::= Asterisk                               { EdgeMatcher::Any }
     matcher ::= OpenBracket CloseBracket               { EdgeMatcher::AnyIndex }
-    matcher ::= Quoted(s)                              { EdgeMatcher::Named(s.to_owned()) }
+    matcher ::= Quoted(s)                              { EdgeMatcher::Named(s) }
     matcher ::= Percent                                { EdgeMatcher::AnyNamed }
     matcher ::= exact(e)                               { EdgeMatcher::Exact(e) }
-    exact ::= Quoted(s) Hash Int(i)                    { EdgeLabel::Named(s.to_owned(), i as usize) }
-    exact ::= Unquoted(s)                              { extra.try_or(edge_label_from_name(s).map_err(SyntaxError::InvalidEdgeLabel), EdgeLabel::Main) }
-    extra ::= Extra                                    { String::new() }
-    extra ::= Extra OpenParen Unquoted(s) CloseParen   { s.to_owned() }
-    index ::= OpenBracket expr CloseBracket;
+    exact ::= Quoted(s) Hash Int(i)                    { EdgeLabel::Named((&*s).into(), i as usize) }
+    exact ::= Unquoted(s)                              { let label = extra.options.edge_label_from_name(s);
+                                                         extra.try_or(label.map_err(SyntaxError::InvalidEdgeLabel), EdgeLabel::Main) }
+    extra ::= Extra                                    { Cow::default() }
+    extra ::= Extra OpenParen Unquoted(s) CloseParen   { s.into() }
+    index ::= OpenBracket cexpr CloseBracket;
 
     // Expressions
     expr ::= rexpr;
-    expr ::= Unquoted(s)                               { extra.try_or(resolve_unquoted_expression(s).map_err(SyntaxError::InvalidUnquoted), Expression::Unset) }
-    rexpr ::= OpenParen expr CloseParen;
-    rexpr ::= Quoted(s)                                { Expression::String(s.to_owned()) }
+    expr ::= Unquoted(s)                               { let expression = resolve_unquoted(s).ok_or_else(|| SyntaxError::InvalidUnquoted(InvalidSymbol(s.to_owned())));
+                                                         extra.try_or(expression, Expression::Unset) }
+    rexpr ::= OpenParen cexpr CloseParen;
+    rexpr ::= Quoted(s)                                { Expression::String(s) }
     rexpr ::= Int(i)                                   { Expression::Int(i) }
-    rexpr ::= Unquoted(s) OpenParen expr(e) CloseParen { Uop(extra.try_or(unary_function_by_name(s).map_err(SyntaxError::InvalidFunction), UnaryPlus), e.into()) }
+    rexpr ::= Unquoted(s) OpenParen args(a) CloseParen { let call = function_call(&extra.functions, s, a);
+                                                         extra.try_or(call, Expression::Unset) }
     rexpr ::= Plus expr(e) [Not]                       { Uop(UnaryPlus, e.into()) }
     rexpr ::= Minus expr(e) [Not]                      { Uop(UnaryMinus, e.into()) }
     rexpr ::= Not expr(e)                              { Uop(Not, e.into()) }
@@ -345,9 +535,131 @@ pomelo! {
     rexpr ::= expr(l) Ge expr(r)                       { Bop(l.into(), Ge, r.into()) }
     rexpr ::= expr(l) And expr(r)                      { Bop(l.into(), And, r.into()) }
     rexpr ::= expr(l) Or expr(r)                       { Bop(l.into(), Or, r.into()) }
-    rexpr ::= expr(c) Question expr(t) Colon expr(f)   { Expression::Conditional(c.into(), t.into(), f.into()) }
     rexpr ::= At                                       { Expression::Select(LimitedSelector::default().into()) }
     rexpr ::= At OpenParen limsel(s) CloseParen        { Expression::Select(s.into()) }
+    // Conditional expressions can only appear where any expression is expected,
+    // and chains of them are collected left-recursively,
+    // so that long chains do not grow the parser's stack
+    cexpr ::= expr;
+    cexpr ::= branches(b) expr(f)                      { conditional_chain(b, f) }
+    branches ::= expr(c) Question cexpr(t) Colon       { vec![(c, t)] }
+    branches ::= branches(mut b) expr(c) Question cexpr(t) Colon { b.push((c, t)); b }
+    args ::=                                           { Vec::new() }
+    args ::= args1;
+    args1 ::= cexpr(e)                                 { vec![e] }
+    args1 ::= args1(mut l) Comma cexpr(e)              { l.push(e); l }
+}
+
+/// Appends rules to a stylesheet, along with the names
+/// they have been given, if any.
+fn push_rules<'a>(
+    stylesheet: &mut Stylesheet<'a>,
+    rules: impl IntoIterator<Item = (Option<Cow<'a, str>>, StyleRule<'a>)>,
+) {
+    for (name, rule) in rules {
+        if let Some(name) = name {
+            stylesheet.rule_names.insert(stylesheet.rules.len(), name);
+        }
+        stylesheet.rules.push(rule);
+    }
+}
+
+/// Constructs a conditional expression from a chain of conditions
+/// with their branches, followed by the final alternative branch.
+///
+/// The chain is right-associative, so each condition
+/// is evaluated only if all conditions before it are false.
+fn conditional_chain<'a>(
+    branches: Vec<(Expression<'a>, Expression<'a>)>,
+    alternative: Expression<'a>,
+) -> Expression<'a> {
+    branches
+        .into_iter()
+        .rev()
+        .fold(alternative, |alternative, (condition, branch)| {
+            Expression::Conditional(condition.into(), branch.into(), alternative.into())
+        })
+}
+
+/// Constructs a user-defined function from its definition.
+///
+/// The body must be a single `return` clause
+/// and all parameters must be variable names.
+fn user_function<'a>(
+    name: &'a str,
+    parameters: Vec<Cow<'a, str>>,
+    body_key: &str,
+    body: Expression<'a>,
+) -> Result<UserFunction<'a>, SyntaxError> {
+    if body_key != "return" {
+        return Err(SyntaxError::InvalidFunctionBody(name.to_owned()));
+    }
+    if let Some(parameter) = parameters.iter().find(|p| !is_variable_name(p)) {
+        return Err(SyntaxError::InvalidParameter(InvalidSymbol(
+            parameter.clone().into_owned(),
+        )));
+    }
+    Ok(UserFunction::new(name.into(), parameters, body))
+}
+
+/// Constructs an expression that calls a function by its name.
+///
+/// User-defined functions shadow built-in functions of the same name.
+fn function_call<'a>(
+    functions: &HashMap<Cow<'a, str>, UserFunction<'a>>,
+    name: &str,
+    mut arguments: Vec<Expression<'a>>,
+) -> Result<Expression<'a>, SyntaxError> {
+    let verify_count = |expected_count: usize| {
+        if arguments.len() == expected_count {
+            Ok(())
+        } else {
+            Err(SyntaxError::ArgumentCountMismatch(
+                name.to_owned(),
+                expected_count,
+                arguments.len(),
+            ))
+        }
+    };
+    if let Some(function) = functions.get(name) {
+        verify_count(function.parameters.len())?;
+        return Ok(Expression::Call(function.clone().into(), arguments));
+    }
+    if let Ok(operator) = width_function_by_name(name) {
+        verify_count(2)?;
+        let width = match arguments.pop() {
+            Some(Expression::Int(width @ 1..=64)) => width as u32,
+            _ => return Err(SyntaxError::InvalidIntegerWidth(name.to_owned())),
+        };
+        let operand = arguments.pop().expect("Argument count has been verified");
+        return Ok(Expression::UnaryOperator(operator(width), operand.into()));
+    }
+    if let Ok(operator) = padding_function_by_name(name) {
+        verify_count(3)?;
+        let fill = match arguments.pop() {
+            Some(Expression::String(fill)) => {
+                let mut chars = fill.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(fill), None) => fill,
+                    _ => return Err(SyntaxError::InvalidPadding(name.to_owned())),
+                }
+            }
+            _ => return Err(SyntaxError::InvalidPadding(name.to_owned())),
+        };
+        let width = match arguments.pop() {
+            Some(Expression::Int(width)) => width as usize,
+            _ => return Err(SyntaxError::InvalidPadding(name.to_owned())),
+        };
+        let operand = arguments.pop().expect("Argument count has been verified");
+        return Ok(Expression::UnaryOperator(
+            operator(width, fill),
+            operand.into(),
+        ));
+    }
+    let operator = unary_function_by_name(name).map_err(SyntaxError::InvalidFunction)?;
+    verify_count(1)?;
+    let operand = arguments.pop().expect("Argument count has been verified");
+    Ok(Expression::UnaryOperator(operator, operand.into()))
 }
 
 /// Shorthand for constructing a selector from a path that does not
@@ -355,17 +667,55 @@ pomelo! {
 ///
 /// This is done by prepending a [`SelectorSegment::anything_any_number_of_times`]
 /// to the path.
-fn selector_from_not_root(segments: impl IntoIterator<Item = SelectorSegment>) -> Selector {
+fn selector_from_not_root<'a>(
+    segments: impl IntoIterator<Item = SelectorSegment<'a>>,
+) -> Selector<'a> {
     let starting_segment = SelectorSegment::anything_any_number_of_times();
     let segments = std::iter::once(starting_segment).chain(segments).collect();
     Selector::from_path(SelectorPath(segments))
 }
 
+/// Expands a `display-if: <condition> ? <tag>` clause
+/// into `display: <condition> ? "<tag>" : unset`,
+/// so that the entity is only displayed if the condition holds.
+fn conditional_display<'a>(
+    key: StyleKey<'a>,
+    condition: Expression<'a>,
+    tag: Cow<'a, str>,
+) -> Result<StyleClause<'a>, SyntaxError> {
+    match key {
+        StyleKey::Property(RawPropertyKey::Property(name)) if name == "display-if" => {
+            Ok(StyleClause {
+                key: StyleKey::Property(RawPropertyKey::Property("display".into())),
+                value: Expression::Conditional(
+                    condition.into(),
+                    Expression::String(tag).into(),
+                    Expression::Unset.into(),
+                ),
+            })
+        }
+        _ => Err(SyntaxError::MissingElseBranch),
+    }
+}
+
 /// Shorthand for constructing an expression that verifies
 /// the type name of a node.
-fn type_match_condition(type_name: &str, allow_special_names: bool) -> Expression {
+fn type_match_condition(type_name: Cow<'_, str>, allow_special_names: bool) -> Expression<'_> {
+    if allow_special_names && let Ok(lifetime) = entity_lifetime_by_name(&type_name) {
+        // --LIFETIME == --lifetime-name
+        return Expression::BinaryOperator(
+            Expression::MagicVariable(MagicVariableKey::Lifetime).into(),
+            BinaryOperator::Eq,
+            Expression::String(lifetime.name().into()).into(),
+        );
+    }
     let resolved_special_name = if allow_special_names {
-        node_type_class_by_name(type_name).ok()
+        node_type_class_by_name(&type_name).ok()
+    } else {
+        None
+    };
+    let resolved_value_condition = if allow_special_names {
+        value_condition_by_name(&type_name).ok()
     } else {
         None
     };
@@ -375,6 +725,8 @@ fn type_match_condition(type_name: &str, allow_special_names: bool) -> Expressio
             UnaryOperator::NodeIsA(type_class),
             Expression::Select(LimitedSelector::default().into()).into(),
         )
+    } else if let Some(condition) = resolved_value_condition {
+        condition.into()
     } else {
         // typename(@) == --type-name
         Expression::BinaryOperator(
@@ -384,13 +736,48 @@ fn type_match_condition(type_name: &str, allow_special_names: bool) -> Expressio
             )
             .into(),
             BinaryOperator::Eq,
-            Expression::String(type_name.to_owned()).into(),
+            Expression::String(type_name).into(),
+        )
+    }
+}
+
+/// Shorthand for constructing an expression that verifies
+/// the custom type class of a node.
+///
+/// Only the `class` function is supported in this position.
+fn custom_class_condition<'a>(
+    function: &str,
+    class_name: Cow<'a, str>,
+) -> Result<Expression<'a>, SyntaxError> {
+    if function != "class" {
+        return Err(SyntaxError::InvalidFunction(InvalidSymbol(
+            function.to_owned(),
+        )));
+    }
+    // classname(@) == --class-name
+    Ok(Expression::BinaryOperator(
+        Expression::UnaryOperator(
+            UnaryOperator::NodeCustomClass,
+            Expression::Select(LimitedSelector::default().into()).into(),
         )
+        .into(),
+        BinaryOperator::Eq,
+        Expression::String(class_name).into(),
+    ))
+}
+
+/// Shorthand for reading the index of an ordered extra
+fn extra_index(function: &str, index: u64) -> Result<usize, SyntaxError> {
+    if function != "index" {
+        return Err(SyntaxError::InvalidFunction(InvalidSymbol(
+            function.to_owned(),
+        )));
     }
+    Ok(index as usize)
 }
 
 /// Shorthand for constructing an expression that verifies the index of an edge
-fn index_match_condition(index: Expression) -> Expression {
+fn index_match_condition(index: Expression<'_>) -> Expression<'_> {
     Expression::BinaryOperator(
         Box::new(Expression::MagicVariable(MagicVariableKey::EdgeIndex)),
         BinaryOperator::Eq,
@@ -398,6 +785,21 @@ fn index_match_condition(index: Expression) -> Expression {
     )
 }
 
+/// Resolves an unquoted literal expression
+/// like [`resolve_unquoted_expression`] does,
+/// but borrows variable names from the source.
+fn resolve_unquoted(name: &str) -> Option<Expression<'_>> {
+    if is_variable_name(name) {
+        let variable = magic_variable(name).map_or_else(
+            || Expression::Variable(name.into()),
+            Expression::MagicVariable,
+        );
+        Some(variable)
+    } else {
+        literal_expression(name).map(Expression::from)
+    }
+}
+
 // Re-export types generated by Pomelo
 pub use parser::{Parser, Token};
 
@@ -469,16 +871,34 @@ mod test {
             .0;
         assert_eq!(
             stylesheet,
-            Stylesheet(vec![StyleRule {
+            Stylesheet::new(vec![StyleRule {
                 selector: Selector::default(),
                 properties: vec![StyleClause {
-                    key: StyleKey::Property(RawPropertyKey::Property("display".to_owned())),
+                    key: StyleKey::Property(RawPropertyKey::Property("display".into())),
                     value: Expression::Unset
                 }]
             }])
         );
     }
 
+    #[test]
+    fn experimental_syntax_at_stable_level() {
+        let mut extra = ErrorManager::new(
+            ExpectErrors::exact([SyntaxError::SyntaxLevelRequired(SyntaxLevel::Experimental)]).f(),
+        );
+        assert!(!extra.require_syntax_level(SyntaxLevel::Experimental));
+        assert!(extra.recover());
+    }
+
+    #[test]
+    fn experimental_syntax_at_experimental_level() {
+        let mut extra = ErrorManager::new(ExpectErrors::none().f())
+            .with_options(ParseOptions::new().with_syntax_level(SyntaxLevel::Experimental));
+        assert!(extra.require_syntax_level(SyntaxLevel::Stable));
+        assert!(extra.require_syntax_level(SyntaxLevel::Experimental));
+        assert!(!extra.recover());
+    }
+
     #[test]
     fn push_unexpected_token() {
         let mut parser = Parser::new(ErrorManager::new(ExpectErrors::some().f()));
@@ -2,6 +2,7 @@
 
 use derive_more::{Debug, Display, Error, From};
 use logos::Logos;
+use std::borrow::Cow;
 
 /// Error type emited by a lexer if a token fails to parse.
 #[derive(Clone, PartialEq, Eq, Debug, Display, From, Error, Default)]
@@ -26,6 +27,19 @@ pub enum LexerError {
     /// Quoted string literal did not end before the end of a line or the end of input.
     #[display("unterminated string literal")]
     UnterminatedQuoted,
+
+    /// Backslash in a string literal followed by a character
+    /// that does not form an escape sequence.
+    #[display("unknown escape sequence \\{_0}")]
+    #[error(ignore)]
+    #[from(ignore)]
+    UnknownEscape(char),
+
+    /// Unicode escape sequence in a string literal that is not
+    /// one to six hexadecimal digits enclosed in braces,
+    /// or that does not encode a valid character.
+    #[display("invalid unicode escape sequence")]
+    InvalidUnicodeEscape,
 }
 
 /// Additional data used by the lexer to track position in source.
@@ -45,7 +59,7 @@ pub struct SourceLocationInformation {
 /// Because it implements the [`Logos`] trait,
 /// it provides a [`Token::lexer`] function for constructing
 /// a lexer.
-#[derive(Logos, Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Logos, Clone, PartialEq, Eq, Debug)]
 #[logos(error = LexerError)]
 #[logos(extras = SourceLocationInformation)]
 #[logos(skip r"[ \r\t]|//[^\n]*")]
@@ -72,7 +86,8 @@ pub enum Token<'s> {
     ///
     /// Tokens consists of alphanumeric characters and dashes.
     /// Leading dashes are allowed, but at least one non-dash character must be present,
-    /// and the first one must be alphabetic.
+    /// and the first one must be alphabetic. Alphabetic characters
+    /// are not limited to ASCII.
     ///
     /// ## Examples
     /// Valid tokens:
@@ -83,6 +98,7 @@ pub enum Token<'s> {
     /// --variable-with-multiple-words
     /// --a123
     /// --a-123
+    /// název
     /// ```
     /// Invalid tokens:
     /// ```text
@@ -92,20 +108,33 @@ pub enum Token<'s> {
     /// --123abc
     /// --
     /// ```
-    #[regex(r"-*[a-zA-Z][a-zA-Z\d\-]*")]
+    #[regex(r"-*\p{Alphabetic}[\p{Alphabetic}\d\-]*")]
     #[regex(r"-{2,}", |_| Err(LexerError::InvalidUnquoted))]
     #[debug("{_0}")]
     Unquoted(&'s str),
 
     /// Double-quoted string literal.
-    #[regex(r#""[^"\n]*""#, |lex| &lex.slice()[1..(lex.slice().len() - 1)])]
-    #[regex(r#""[^"\n]*"#, |_| Err(LexerError::UnterminatedQuoted))]
+    ///
+    /// String literals cannot span multiple lines, but they can contain
+    /// the following escape sequences:
+    ///
+    /// | Sequence     | Meaning                                   |
+    /// |--------------|-------------------------------------------|
+    /// | `\n`         | Line feed                                 |
+    /// | `\t`         | Tab                                       |
+    /// | `\"`         | Double quote                              |
+    /// | `\\`         | Backslash                                 |
+    /// | `\u{...}`    | Unicode character with a hexadecimal code |
+    ///
+    /// Literals without escape sequences are borrowed from the source.
+    #[regex(r#""([^"\\\n]|\\[^\n])*""#, |lex| unescape(&lex.slice()[1..(lex.slice().len() - 1)]))]
+    #[regex(r#""([^"\\\n]|\\[^\n])*\\?"#, |_| Err(LexerError::UnterminatedQuoted))]
     #[debug("{_0:?}")]
-    Quoted(&'s str),
+    Quoted(Cow<'s, str>),
 
     /// Decimal integer literal.
     #[regex(r"\d+", |lex| lex.slice().parse())]
-    #[regex(r"\d+[a-zA-Z][a-zA-Z\d]*", |_| Err(LexerError::AlphaCharacterInNumber))]
+    #[regex(r"\d+\p{Alphabetic}[\p{Alphabetic}\d]*", |_| Err(LexerError::AlphaCharacterInNumber))]
     #[debug("{_0}")]
     Int(u64),
 
@@ -126,6 +155,19 @@ pub enum Token<'s> {
     #[debug(".if")]
     RestrictMatcher,
 
+    /// Selector matcher that specifies a negative restriction
+    /// on the existence of a path from the selected entity.
+    ///
+    /// ## Examples
+    /// ```text
+    /// ::.unless("next" ref) {
+    ///   property: value;
+    /// }
+    /// ```
+    #[token(".unless")]
+    #[debug(".unless")]
+    UnlessMatcher,
+
     /// Selector matcher that specifies an iteration.
     #[token(".many")]
     #[debug(".many")]
@@ -155,6 +197,19 @@ pub enum Token<'s> {
     #[debug("::extra")]
     ExtraMatcher,
 
+    /// Limited selector matcher that steps back to the preceding node.
+    /// Not allowed in full selectors.
+    ///
+    /// ## Examples
+    /// ```text
+    /// "data" {
+    ///   length: @(.. "count");
+    /// }
+    /// ```
+    #[token("..")]
+    #[debug("..")]
+    ParentMatcher,
+
     // =========================================
     //                OPERATORS
     // =========================================
@@ -250,6 +305,102 @@ pub enum Token<'s> {
     #[debug("@")]
     At,
 
+    // =========================================
+    //                DIRECTIVES
+    // =========================================
+    /// Directive that declares capabilities that a state graph
+    /// must provide for the stylesheet to apply to it.
+    ///
+    /// ## Syntax
+    /// ```text
+    /// @requires <capability>, <capability>, ... ;
+    /// ```
+    ///
+    /// ## Examples
+    /// ```text
+    /// @requires gdb, arrays, float-values;
+    /// ```
+    #[token("@requires")]
+    #[debug("@requires")]
+    Requires,
+
+    /// Directive that defines a function that can be called
+    /// from any expression that follows it.
+    ///
+    /// The body of the function only sees its own parameters,
+    /// not the variables of its caller.
+    ///
+    /// ## Syntax
+    /// ```text
+    /// @function <name> ( <parameter>, <parameter>, ... ) {
+    ///   return: <expression>;
+    /// }
+    /// ```
+    ///
+    /// ## Examples
+    /// ```text
+    /// @function heat(--value, --limit) {
+    ///   return: --value > --limit ? "red" : "green";
+    /// }
+    ///
+    /// :int {
+    ///   fill: heat(@, 100);
+    /// }
+    /// ```
+    #[token("@function")]
+    #[debug("@function")]
+    Function,
+
+    /// Directive that encloses rules that only apply
+    /// if a condition holds.
+    ///
+    /// The condition is evaluated once per application
+    /// of the stylesheet and only sees variables provided
+    /// by the embedder.
+    ///
+    /// ## Syntax
+    /// ```text
+    /// @when <expression> {
+    ///   <rule>
+    ///   <rule>
+    ///   ...
+    /// }
+    /// ```
+    ///
+    /// ## Examples
+    /// ```text
+    /// @when --THEME == "dark" {
+    ///   :: {
+    ///     background: black;
+    ///   }
+    /// }
+    /// ```
+    #[token("@when")]
+    #[debug("@when")]
+    When,
+
+    /// Directive that gives a name to the rule that follows it.
+    ///
+    /// Tools that report on rules, such as linters and profilers,
+    /// refer to named rules by their names instead of their indices.
+    ///
+    /// ## Syntax
+    /// ```text
+    /// @rule "<name>"
+    /// <rule>
+    /// ```
+    ///
+    /// ## Examples
+    /// ```text
+    /// @rule "highlight-head"
+    /// :: "head" {
+    ///   fill: yellow;
+    /// }
+    /// ```
+    #[token("@rule")]
+    #[debug("@rule")]
+    Rule,
+
     // =========================================
     //                DELIMITERS
     // =========================================
@@ -294,6 +445,52 @@ pub enum Token<'s> {
     Hash,
 }
 
+/// Resolves escape sequences in the contents of a string literal.
+fn unescape(contents: &str) -> Result<Cow<'_, str>, LexerError> {
+    if !contents.contains('\\') {
+        return Ok(Cow::Borrowed(contents));
+    }
+    let mut unescaped = String::with_capacity(contents.len());
+    let mut chars = contents.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        let escaped = match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('u') => unescape_unicode(&mut chars)?,
+            Some(c) => return Err(LexerError::UnknownEscape(c)),
+            // The lexer does not accept a backslash at the end of a literal
+            None => return Err(LexerError::Generic),
+        };
+        unescaped.push(escaped);
+    }
+    Ok(Cow::Owned(unescaped))
+}
+
+/// Reads the remainder of a unicode escape sequence,
+/// which follows the `\u` that starts it.
+fn unescape_unicode(chars: &mut std::str::Chars) -> Result<char, LexerError> {
+    let (code, rest) = chars
+        .as_str()
+        .strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .ok_or(LexerError::InvalidUnicodeEscape)?;
+    if code.is_empty() || code.len() > 6 || !code.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(LexerError::InvalidUnicodeEscape);
+    }
+    let c = u32::from_str_radix(code, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or(LexerError::InvalidUnicodeEscape)?;
+    *chars = rest.chars();
+    Ok(c)
+}
+
 #[cfg(test)]
 mod test {
     use super::{
@@ -345,7 +542,12 @@ mod test {
             .expect("Tokens should have parsed");
         assert_eq!(
             tokens,
-            vec![Quoted(""), Quoted(" "), Quoted("a"), Quoted("abc")]
+            vec![
+                Quoted("".into()),
+                Quoted(" ".into()),
+                Quoted("a".into()),
+                Quoted("abc".into())
+            ]
         );
     }
 
@@ -356,12 +558,69 @@ mod test {
             tokens,
             vec![
                 Err(UnterminatedQuoted),
-                Ok(Quoted("def")),
+                Ok(Quoted("def".into())),
                 Err(UnterminatedQuoted)
             ]
         );
     }
 
+    #[test]
+    fn escape_sequences() {
+        let tokens = Token::lexer(r#""a\nb" "\t" "\"\\" "\u{e1}\u{1F600}" "\\n""#)
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Tokens should have parsed");
+        assert_eq!(
+            tokens,
+            vec![
+                Quoted("a\nb".into()),
+                Quoted("\t".into()),
+                Quoted("\"\\".into()),
+                Quoted("á😀".into()),
+                Quoted("\\n".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_escape_sequences() {
+        let tokens =
+            Token::lexer(r#""\x" "\u41" "\u{}" "\u{1234567}" "\u{d800}" "\u{4g}" "\u{41" "ok""#)
+                .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                Err(UnknownEscape('x')),
+                Err(InvalidUnicodeEscape),
+                Err(InvalidUnicodeEscape),
+                Err(InvalidUnicodeEscape),
+                Err(InvalidUnicodeEscape),
+                Err(InvalidUnicodeEscape),
+                Err(InvalidUnicodeEscape),
+                Ok(Quoted("ok".into()))
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_quote_does_not_terminate_string() {
+        let tokens = Token::lexer("\"abc\\\" x\n\"def\"").collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![Err(UnterminatedQuoted), Ok(Quoted("def".into()))]
+        );
+    }
+
+    #[test]
+    fn non_ascii_unquoted_tokens() {
+        let tokens = Token::lexer("název --délka-2 日本")
+            .collect::<Result<Vec<_>, _>>()
+            .expect("Tokens should have parsed");
+        assert_eq!(
+            tokens,
+            vec![Unquoted("název"), Unquoted("--délka-2"), Unquoted("日本")]
+        );
+    }
+
     #[test]
     fn whitespace() {
         let tokens = Token::lexer("1  2 \n\t 3 \r\n4  \n")
@@ -1,21 +1,30 @@
 #![doc = include_str!("../README.md")]
 
 mod connect;
+pub mod diagnostics;
+mod features;
 mod grammar;
 mod lexer;
+pub mod lint;
 mod mock_error_handler;
+mod nesting;
+mod options;
 mod report;
 pub mod symbols;
+pub mod trivia;
 
-use aili_style::stylesheet::Stylesheet;
+use aili_style::stylesheet::{Stylesheet, borrowed};
 use derive_more::{Display, Error, From};
 use grammar::{ErrorManager, Parser};
 use lexer::Token;
 use logos::Logos;
+use nesting::{Nesting, NestingTracker};
 use report::FilteredErrorHandler;
 
+pub use features::SyntaxLevel;
 pub use grammar::{ParseFailure, SyntaxError};
 pub use lexer::LexerError;
+pub use options::ParseOptions;
 
 /// Error type that indicates recoverable lexer or parser input errors.
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
@@ -42,58 +51,150 @@ pub enum ParseErrorInfo {
 ///
 /// The parse function attempts error recovery by discarding unparsable
 /// tokens. The returned stylesheet is a parsable portion of the input.
+/// A malformed clause is discarded on its own, while the other clauses
+/// of its rule are retained. A malformed selector discards the whole rule.
 /// An error is only returned if the parser irrecoverably fails.
+///
+/// The [default](ParseOptions::default) options are used,
+/// so only [stable](SyntaxLevel::Stable) syntax is accepted.
+/// Use [`parse_stylesheet_with_options`] to change that.
 pub fn parse_stylesheet(
     source: &str,
     error_handler: impl FnMut(ParseError),
 ) -> Result<Stylesheet, ParseFailure> {
+    parse_stylesheet_with_options(source, ParseOptions::default(), error_handler)
+}
+
+/// Parses a [`Stylesheet`] using provided [`ParseOptions`].
+///
+/// Constructs that require a higher [`SyntaxLevel`] than the options
+/// permit are reported as recoverable errors and discarded.
+///
+/// See [`parse_stylesheet`] for details on error recovery.
+pub fn parse_stylesheet_with_options(
+    source: &str,
+    options: ParseOptions,
+    mut error_handler: impl FnMut(ParseError),
+) -> Result<Stylesheet, ParseFailure> {
+    parse_stylesheet_with_positions(source, options, |error, _| error_handler(error))
+        .map(borrowed::Stylesheet::into_owned)
+        .map_err(|(failure, _, _)| failure)
+}
+
+/// Parses a [`Stylesheet`] using provided [`ParseOptions`],
+/// without copying identifiers and string literals out of the source.
+///
+/// The returned [`borrowed::Stylesheet`] borrows names
+/// from the source wherever it can, which saves an allocation
+/// for each of them when large stylesheets are parsed.
+/// It can be converted to a [`Stylesheet`]
+/// with [`into_owned`](borrowed::Stylesheet::into_owned).
+///
+/// See [`parse_stylesheet`] for details on error recovery.
+pub fn parse_stylesheet_borrowed(
+    source: &str,
+    options: ParseOptions,
+    mut error_handler: impl FnMut(ParseError),
+) -> Result<borrowed::Stylesheet<'_>, ParseFailure> {
+    parse_stylesheet_with_positions(source, options, |error, _| error_handler(error))
+        .map_err(|(failure, _, _)| failure)
+}
+
+/// Parses a [`Stylesheet`] like [`parse_stylesheet_with_options`],
+/// but also reports the one-based column number of each error
+/// and the line and column numbers where an irrecoverable failure occurred.
+///
+/// Columns are counted in characters.
+fn parse_stylesheet_with_positions(
+    source: &str,
+    options: ParseOptions,
+    mut error_handler: impl FnMut(ParseError, usize),
+) -> Result<borrowed::Stylesheet<'_>, (ParseFailure, usize, usize)> {
     let lexer = Token::lexer(source);
+    let error_handler = FilteredErrorHandler::new(|(error, column_number): (ParseError, usize)| {
+        error_handler(error, column_number)
+    });
     // Wrap error handler and lexer in a RefCell so we can access it
     // from both parser and the main loop
-    let shared = std::cell::RefCell::new((lexer, FilteredErrorHandler::new(error_handler)));
+    let shared = std::cell::RefCell::new((lexer, error_handler));
+    // Line and column numbers of the token that has been read last
+    let position = || {
+        let lexer = &shared.borrow().0;
+        let line_start = lexer.extras.line_offset;
+        let column_index = source
+            .get(line_start..lexer.span().start)
+            .map_or(0, |line| line.chars().count());
+        (lexer.extras.line_index + 1, column_index + 1)
+    };
     let report_error = |error_data| {
-        let (lexer, error_handler) = &mut *shared.borrow_mut();
-        error_handler.handle_error(ParseError {
+        let (line_number, column_number) = position();
+        let error = ParseError {
             error_data,
-            line_number: lexer.extras.line_index + 1,
-        });
+            line_number,
+        };
+        shared.borrow_mut().1.handle_error((error, column_number));
+    };
+    let fail = |failure| {
+        let (line_number, column_number) = position();
+        (failure, line_number, column_number)
     };
     // Wrap this in a callback because otherwise the borrow
     // would not be dropped in time and error reporting would fail
     let next_token_from_lexer = || shared.borrow_mut().0.next();
+    let mut nesting = NestingTracker::new(options.max_nesting_depth);
     // Forward syntax errors to the handler
-    let parser_extra = ErrorManager::new(|err| report_error(err.into()));
+    let parser_extra = ErrorManager::new(|err| report_error(err.into())).with_options(options);
     let mut parser = Parser::new(parser_extra);
     while let Some(token) = next_token_from_lexer() {
         match token {
             Ok(token) => {
-                parser.parse(token.into())?;
+                // Expressions nested too deeply never reach the parser,
+                // so they cannot overflow its stack
+                match nesting.track(&token) {
+                    Nesting::Accept => {}
+                    Nesting::TooDeep => {
+                        parser.extra_mut().nesting_too_deep(nesting.limit());
+                        continue;
+                    }
+                    Nesting::Discard => continue,
+                }
+                parser.parse(token.into()).map_err(fail)?;
                 shared.borrow_mut().1.token_parsed();
             }
             Err(err) => report_error(err.into()),
         }
     }
     // Push end token so we get relevant error descriptions
-    parser.parse(grammar::Token::End)?;
-    Ok(parser.end_of_input()?.0)
+    parser.parse(grammar::Token::End).map_err(fail)?;
+    Ok(parser.end_of_input().map_err(fail)?.0)
 }
 
 #[cfg(test)]
 mod test {
     use super::{
-        ParseError,
+        ParseError, ParseOptions, SyntaxLevel,
         grammar::{self, SyntaxError},
         lexer::LexerError,
         mock_error_handler::ExpectErrors,
-        parse_stylesheet,
+        parse_stylesheet, parse_stylesheet_borrowed, parse_stylesheet_with_options,
+        symbols::InvalidSymbol,
     };
-    use aili_model::state::{EdgeLabel, NodeTypeClass};
-    use aili_style::stylesheet::{expression::*, selector::*, *};
+    use aili_model::{
+        capability::{self, CapabilitySet},
+        state::{EdgeLabel, NodeTypeClass},
+    };
+    use aili_style::stylesheet::{borrowed, expression::*, selector::*, *};
+    use std::borrow::Cow;
+
+    /// Options that accept experimental syntax.
+    fn experimental() -> ParseOptions {
+        ParseOptions::new().with_syntax_level(SyntaxLevel::Experimental)
+    }
 
     #[test]
     fn minimal_empty_rule() {
         let source = ":: { }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: Vec::new(),
         }]);
@@ -105,7 +206,7 @@ mod test {
     #[test]
     fn assign_unquoted_to_unquoted() {
         let source = ":: { abc:def }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Property(RawPropertyKey::Property("abc".to_owned())),
@@ -120,7 +221,7 @@ mod test {
     #[test]
     fn assign_single_letter_to_single_letter() {
         let source = ":: { a:b }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Property(RawPropertyKey::Property("a".to_owned())),
@@ -135,7 +236,7 @@ mod test {
     #[test]
     fn multiple_clauses_with_trailing_semicolon() {
         let source = ":: { a: 1; b: 2; }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![
                 StyleClause {
@@ -156,7 +257,7 @@ mod test {
     #[test]
     fn variable_invocations() {
         let source = ":: { --i: --j }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Variable("--i".to_owned()),
@@ -168,10 +269,35 @@ mod test {
         assert_eq!(expected_stylesheet, parsed_stylesheet);
     }
 
+    #[test]
+    fn traversal_position_magic_variables() {
+        let source = ":: { a: --DEPTH; b: --SIBLING-INDEX; c: --LAYER-INDEX }";
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![
+                StyleClause {
+                    key: StyleKey::Property(RawPropertyKey::Property("a".to_owned())),
+                    value: Expression::MagicVariable(MagicVariableKey::Depth),
+                },
+                StyleClause {
+                    key: StyleKey::Property(RawPropertyKey::Property("b".to_owned())),
+                    value: Expression::MagicVariable(MagicVariableKey::SiblingIndex),
+                },
+                StyleClause {
+                    key: StyleKey::Property(RawPropertyKey::Property("c".to_owned())),
+                    value: Expression::MagicVariable(MagicVariableKey::LayerIndex),
+                },
+            ],
+        }]);
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
     #[test]
     fn arihhmetic_operators() {
         let source = ":: { a: -1 - 3 * 2 + 4 / 2 % +5 }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Property(RawPropertyKey::Property("a".to_owned())),
@@ -218,7 +344,7 @@ mod test {
     #[test]
     fn empty_select_expression() {
         let source = ":: { value: @ }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Property(RawPropertyKey::Property("value".to_owned())),
@@ -233,7 +359,7 @@ mod test {
     #[test]
     fn logical_operators() {
         let source = ":: { value: @ || --a && !--b || --i == 0 }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Property(RawPropertyKey::Property("value".to_owned())),
@@ -271,13 +397,13 @@ mod test {
     #[test]
     fn select_expression_with_path() {
         let source = ":: { value: @(\"a\" [42]) }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Property(RawPropertyKey::Property("value".to_owned())),
                 value: Expression::Select(
                     LimitedSelector::from_path([
-                        EdgeLabel::Named("a".to_owned(), 0).into(),
+                        EdgeLabel::Named("a".into(), 0).into(),
                         EdgeLabel::Index(42).into(),
                     ])
                     .into(),
@@ -292,7 +418,7 @@ mod test {
     #[test]
     fn ternary_operator() {
         let source = ":: { value: --a && --b ? \"true\" : 1 + --a }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Property(RawPropertyKey::Property("value".to_owned())),
@@ -318,10 +444,58 @@ mod test {
         assert_eq!(expected_stylesheet, parsed_stylesheet);
     }
 
+    #[test]
+    fn conditional_display() {
+        let source = ":: { display-if: --a ? cell; }\n:: { display-if: --a > 1 ? \"kvt\" }";
+        let rule = |condition, tag: &str| StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(RawPropertyKey::Property("display".to_owned())),
+                value: Expression::Conditional(
+                    condition,
+                    Expression::String(tag.to_owned()).into(),
+                    Expression::Unset.into(),
+                ),
+            }],
+        };
+        let expected_stylesheet = Stylesheet::new(vec![
+            rule(Expression::Variable("--a".to_owned()).into(), "cell"),
+            rule(
+                Expression::BinaryOperator(
+                    Expression::Variable("--a".to_owned()).into(),
+                    BinaryOperator::Gt,
+                    Expression::Int(1).into(),
+                )
+                .into(),
+                "kvt",
+            ),
+        ]);
+        let parsed_stylesheet =
+            parse_stylesheet_with_options(source, experimental(), ExpectErrors::none().f())
+                .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn else_branch_is_required_outside_display_if() {
+        let source = ":: { value: --a ? cell; }";
+        let parsed_stylesheet = parse_stylesheet_with_options(
+            source,
+            experimental(),
+            ExpectErrors::exact([ParseError {
+                error_data: SyntaxError::MissingElseBranch.into(),
+                line_number: 1,
+            }])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+        assert!(parsed_stylesheet.rules[0].properties.is_empty());
+    }
+
     #[test]
     fn selector_edge_matchers() {
         let source = "main next ret ref len [] [42] \"a\" \"b\"#1 * % { }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::from_path(SelectorPath(
                 std::iter::once(SelectorSegment::anything_any_number_of_times())
                     .chain(
@@ -333,8 +507,8 @@ mod test {
                             EdgeLabel::Length.into(),
                             EdgeMatcher::AnyIndex,
                             EdgeLabel::Index(42).into(),
-                            EdgeMatcher::Named("a".to_owned()),
-                            EdgeLabel::Named("b".to_owned(), 1).into(),
+                            EdgeMatcher::Named("a".into()),
+                            EdgeLabel::Named("b".into(), 1).into(),
                             EdgeMatcher::Any,
                             EdgeMatcher::AnyNamed,
                         ]
@@ -354,7 +528,7 @@ mod test {
     fn selector_pseudo_elements() {
         let source =
             "::::edge { } ::::extra { } ::::extra(hello-world) { } :: main::edge::extra { }";
-        let expected_stylesheet = Stylesheet(vec![
+        let expected_stylesheet = Stylesheet::new(vec![
             StyleRule {
                 selector: Selector::default().selecting_edge(),
                 properties: Vec::new(),
@@ -381,10 +555,29 @@ mod test {
         assert_eq!(expected_stylesheet, parsed_stylesheet);
     }
 
+    #[test]
+    fn ordered_extras() {
+        let source = ":: main::extra(icon):index(0) { } :: main::extra(badge):index(2) { }";
+        let main = || Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into());
+        let expected_stylesheet = Stylesheet::new(vec![
+            StyleRule {
+                selector: main().with_extra("icon".to_owned()).with_extra_index(0),
+                properties: Vec::new(),
+            },
+            StyleRule {
+                selector: main().with_extra("badge".to_owned()).with_extra_index(2),
+                properties: Vec::new(),
+            },
+        ]);
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
     #[test]
     fn branched_selectors() {
         let source = ":: .many(.alt(next ret, .many(%))) { }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::from_path(
                 [SelectorSegment::AnyNumberOfTimes(
                     [SelectorSegment::Branch(vec![
@@ -412,7 +605,7 @@ mod test {
     #[test]
     fn special_property_keys() {
         let source = ":: { display: unset; \"display\": \"unset\"; parent: true; target: false; \"--i\": 1 }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![
                 StyleClause {
@@ -445,7 +638,7 @@ mod test {
     #[test]
     fn restricted_selector() {
         let source = ":: .many(*.if(--c)).if(--i == 0) { }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::from_path(
                 [
                     SelectorSegment::AnyNumberOfTimes(
@@ -470,10 +663,40 @@ mod test {
         assert_eq!(expected_stylesheet, parsed_stylesheet);
     }
 
+    #[test]
+    fn negative_restriction() {
+        let source = r#":: .many(*).unless("next" ref) { }"#;
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::AnyNumberOfTimes(
+                        [SelectorSegment::Match(EdgeMatcher::Any)].into(),
+                    ),
+                    SelectorSegment::Condition(Expression::UnaryOperator(
+                        expression::UnaryOperator::Not,
+                        Expression::Select(
+                            LimitedSelector::from_path([
+                                EdgeLabel::Named("next".into(), 0).into(),
+                                EdgeLabel::Deref.into(),
+                            ])
+                            .into(),
+                        )
+                        .into(),
+                    )),
+                ]
+                .into(),
+            ),
+            properties: Vec::new(),
+        }]);
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
     #[test]
     fn named_operators() {
         let source = ":: { a: isset(--i); b: is-root(@); c: typename(@); d: val(@); }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![
                 StyleClause {
@@ -511,10 +734,125 @@ mod test {
         assert_eq!(expected_stylesheet, parsed_stylesheet);
     }
 
+    #[test]
+    fn integer_width_operators() {
+        let source = ":: { a: hex(@); b: as-signed(@, 32); c: as-unsigned(@, 8); }";
+        let operator = |key: &str, operator| StyleClause {
+            key: StyleKey::Property(RawPropertyKey::Property(key.to_owned())),
+            value: Expression::UnaryOperator(
+                operator,
+                Expression::Select(LimitedSelector::default().into()).into(),
+            ),
+        };
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![
+                operator("a", expression::UnaryOperator::Hex),
+                operator("b", expression::UnaryOperator::AsSigned(32)),
+                operator("c", expression::UnaryOperator::AsUnsigned(8)),
+            ],
+        }]);
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn invalid_integer_width() {
+        let source = ":: { a: as-signed(@, 0); }\n:: { b: as-unsigned(@, --w); }";
+        parse_stylesheet(
+            source,
+            ExpectErrors::exact([
+                ParseError {
+                    error_data: SyntaxError::InvalidIntegerWidth("as-signed".to_owned()).into(),
+                    line_number: 1,
+                },
+                ParseError {
+                    error_data: SyntaxError::InvalidIntegerWidth("as-unsigned".to_owned()).into(),
+                    line_number: 2,
+                },
+            ])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+    }
+
+    #[test]
+    fn padding_operators() {
+        let source = r#":: { a: pad-left(@, 8, "0"); b: pad-right(@, 4, " "); }"#;
+        let operator = |key: &str, operator| StyleClause {
+            key: StyleKey::Property(RawPropertyKey::Property(key.to_owned())),
+            value: Expression::UnaryOperator(
+                operator,
+                Expression::Select(LimitedSelector::default().into()).into(),
+            ),
+        };
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![
+                operator("a", expression::UnaryOperator::PadLeft(8, '0')),
+                operator("b", expression::UnaryOperator::PadRight(4, ' ')),
+            ],
+        }]);
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn invalid_padding() {
+        let source = ":: { a: pad-left(@, 8, \"00\"); }\n:: { b: pad-right(@, --w, \" \"); }";
+        parse_stylesheet(
+            source,
+            ExpectErrors::exact([
+                ParseError {
+                    error_data: SyntaxError::InvalidPadding("pad-left".to_owned()).into(),
+                    line_number: 1,
+                },
+                ParseError {
+                    error_data: SyntaxError::InvalidPadding("pad-right".to_owned()).into(),
+                    line_number: 2,
+                },
+            ])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+    }
+
+    #[test]
+    fn source_location_operators() {
+        let source = r#":: { label: source-file(@) + ":" + source-line(@); }"#;
+        let location = |operator| {
+            Expression::UnaryOperator(
+                operator,
+                Expression::Select(LimitedSelector::default().into()).into(),
+            )
+        };
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(RawPropertyKey::Property("label".to_owned())),
+                value: Expression::BinaryOperator(
+                    Expression::BinaryOperator(
+                        location(expression::UnaryOperator::NodeSourceFile).into(),
+                        BinaryOperator::Plus,
+                        Expression::String(":".to_owned()).into(),
+                    )
+                    .into(),
+                    BinaryOperator::Plus,
+                    location(expression::UnaryOperator::NodeSourceLine).into(),
+                ),
+            }],
+        }]);
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
     #[test]
     fn conditional_operator_precedence() {
         let source = ":: { a: 1 ? --a && 2 ? 3 : 4 : --a && 5 }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Property(RawPropertyKey::Property("a".to_owned())),
@@ -549,7 +887,7 @@ mod test {
     fn invalid_selector() {
         // The affected rules should be discarded, but all others should be retained
         let source = ":: { } # { }  main > } { } }";
-        let expected_stylesheet = Stylesheet(vec![
+        let expected_stylesheet = Stylesheet::new(vec![
             StyleRule {
                 selector: Selector::default(),
                 properties: Vec::new(),
@@ -575,7 +913,7 @@ mod test {
     #[test]
     fn unclosed_rule_body() {
         let source = ":: % { } :: { --a: b ";
-        let expected_stylesheet = Stylesheet(vec![
+        let expected_stylesheet = Stylesheet::new(vec![
             StyleRule {
                 selector: Selector::from_path(
                     [SelectorSegment::Match(EdgeMatcher::AnyNamed)].into(),
@@ -602,12 +940,183 @@ mod test {
         assert_eq!(expected_stylesheet, parsed_stylesheet);
     }
 
+    #[test]
+    fn required_capabilities() {
+        let source = "@requires gdb, arrays;\n:: { }\n@requires float-values;";
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule::default()]).with_requirements(
+            CapabilitySet::from_iter([
+                capability::GDB,
+                capability::ARRAYS,
+                capability::FLOAT_VALUES,
+            ]),
+        );
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn user_defined_function() {
+        let source = "@function add(--a, --b) { return: --a + --b; }\n:: { value: add(1, --c); }";
+        let function = UserFunction::new(
+            "add".to_owned(),
+            vec!["--a".to_owned(), "--b".to_owned()],
+            Expression::BinaryOperator(
+                Expression::Variable("--a".to_owned()).into(),
+                BinaryOperator::Plus,
+                Expression::Variable("--b".to_owned()).into(),
+            ),
+        );
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(RawPropertyKey::Property("value".to_owned())),
+                value: Expression::Call(
+                    function.into(),
+                    vec![Expression::Int(1), Expression::Variable("--c".to_owned())],
+                ),
+            }],
+        }]);
+        let parsed_stylesheet =
+            parse_stylesheet_with_options(source, experimental(), ExpectErrors::none().f())
+                .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn user_defined_function_wrong_argument_count() {
+        let source =
+            "@function f(--a) { return: --a }\n:: { value: f(1, 2); }\n:: { value: val(); }";
+        parse_stylesheet_with_options(
+            source,
+            experimental(),
+            ExpectErrors::exact([
+                ParseError {
+                    error_data: SyntaxError::ArgumentCountMismatch("f".to_owned(), 1, 2).into(),
+                    line_number: 2,
+                },
+                ParseError {
+                    error_data: SyntaxError::ArgumentCountMismatch("val".to_owned(), 1, 0).into(),
+                    line_number: 3,
+                },
+            ])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+    }
+
+    #[test]
+    fn user_defined_function_must_be_defined_before_use() {
+        let source = ":: { value: f(1); }\n@function f(--a) { return: --a; }";
+        let parsed_stylesheet = parse_stylesheet_with_options(
+            source,
+            experimental(),
+            ExpectErrors::exact([ParseError {
+                error_data: SyntaxError::InvalidFunction(InvalidSymbol("f".to_owned())).into(),
+                line_number: 1,
+            }])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+        assert_eq!(parsed_stylesheet.rules.len(), 1);
+        assert!(parsed_stylesheet.rules[0].properties.is_empty());
+    }
+
+    #[test]
+    fn user_defined_function_invalid_parameter() {
+        let source = "@function f(a) { return: a; }";
+        parse_stylesheet_with_options(
+            source,
+            experimental(),
+            ExpectErrors::exact([ParseError {
+                error_data: SyntaxError::InvalidParameter(InvalidSymbol("a".to_owned())).into(),
+                line_number: 1,
+            }])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+    }
+
+    #[test]
+    fn conditional_block() {
+        let source = ":: { value: 1; }\n@when --THEME == \"dark\" {\n  :: { value: 2; }\n  :: { value: 3; }\n}\n:: { value: 4; }";
+        let rule = |value| StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(RawPropertyKey::Property("value".to_owned())),
+                value: Expression::Int(value),
+            }],
+        };
+        let condition = Expression::BinaryOperator(
+            Expression::Variable("--THEME".to_owned()).into(),
+            BinaryOperator::Eq,
+            Expression::String("dark".to_owned()).into(),
+        );
+        let expected_stylesheet = Stylesheet::new(vec![rule(1), rule(2), rule(3), rule(4)])
+            .with_conditional_block(condition, 1..3);
+        let parsed_stylesheet =
+            parse_stylesheet_with_options(source, experimental(), ExpectErrors::none().f())
+                .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn malformed_rule_in_conditional_block() {
+        let source = "@when --THEME {\n  :: { value: 1; }\n  thread { value: 2; }\n}";
+        let parsed_stylesheet = parse_stylesheet_with_options(
+            source,
+            experimental(),
+            ExpectErrors::exact([ParseError {
+                error_data: SyntaxError::InvalidEdgeLabel(InvalidSymbol("thread".to_owned()))
+                    .into(),
+                line_number: 3,
+            }])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+        assert_eq!(parsed_stylesheet.rules.len(), 1);
+        assert_eq!(parsed_stylesheet.conditional_blocks[0].rules, 0..1);
+    }
+
+    #[test]
+    fn named_rules() {
+        let source = "@rule \"a\"\n:: {}\n:: main {}\n@when --X {\n  @rule \"c\"\n  :: {}\n}";
+        let parsed_stylesheet =
+            parse_stylesheet_with_options(source, experimental(), ExpectErrors::none().f())
+                .expect("Stylesheet should have parsed");
+        assert_eq!(parsed_stylesheet.rules.len(), 3);
+        assert_eq!(parsed_stylesheet.rule_name(0), Some("a"));
+        assert_eq!(parsed_stylesheet.rule_name(1), None);
+        assert_eq!(parsed_stylesheet.rule_name(2), Some("c"));
+    }
+
+    #[test]
+    fn required_capabilities_missing_semicolon() {
+        let source = "@requires gdb, arrays :: { a: b }";
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::some().f())
+            .expect("Stylesheet should have parsed");
+        assert!(parsed_stylesheet.requirements.is_empty());
+    }
+
+    /// Expected result of parsing a rule with clauses `a: a` and `c: c`
+    /// and a malformed clause between them.
+    fn stylesheet_with_clauses_a_and_c() -> Stylesheet {
+        let clause = |name: &str| StyleClause {
+            key: StyleKey::Property(RawPropertyKey::Property(name.to_owned())),
+            value: Expression::String(name.to_owned()),
+        };
+        Stylesheet::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![clause("a"), clause("c")],
+        }])
+    }
+
     #[test]
     fn missing_semicolon() {
         let source = ":: { a: a; b: b /* missing semicolon */ x: x; c: c }";
         let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::some().f())
             .expect("Stylesheet should have parsed");
-        assert_eq!(Stylesheet::default(), parsed_stylesheet);
+        assert_eq!(stylesheet_with_clauses_a_and_c(), parsed_stylesheet);
     }
 
     #[test]
@@ -615,7 +1124,7 @@ mod test {
         let source = ":: { a: a; b: /* missing rhs */; c: c }";
         let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::some().f())
             .expect("Stylesheet should have parsed");
-        assert_eq!(Stylesheet::default(), parsed_stylesheet);
+        assert_eq!(stylesheet_with_clauses_a_and_c(), parsed_stylesheet);
     }
 
     #[test]
@@ -623,7 +1132,7 @@ mod test {
         let source = ":: { a: a; b /* missing colon */ b; c: c }";
         let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::some().f())
             .expect("Stylesheet should have parsed");
-        assert_eq!(Stylesheet::default(), parsed_stylesheet);
+        assert_eq!(stylesheet_with_clauses_a_and_c(), parsed_stylesheet);
     }
 
     #[test]
@@ -631,7 +1140,7 @@ mod test {
         let source = ":: { a: a; b; c: c }";
         let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::some().f())
             .expect("Stylesheet should have parsed");
-        assert_eq!(Stylesheet::default(), parsed_stylesheet);
+        assert_eq!(stylesheet_with_clauses_a_and_c(), parsed_stylesheet);
     }
 
     #[test]
@@ -639,7 +1148,7 @@ mod test {
         let source = ":: { a: a; b b: b; c: c }";
         let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::some().f())
             .expect("Stylesheet should have parsed");
-        assert_eq!(Stylesheet::default(), parsed_stylesheet);
+        assert_eq!(stylesheet_with_clauses_a_and_c(), parsed_stylesheet);
     }
 
     #[test]
@@ -647,7 +1156,52 @@ mod test {
         let source = ":: { a: a; 42: b; c: c }";
         let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::some().f())
             .expect("Stylesheet should have parsed");
-        assert_eq!(Stylesheet::default(), parsed_stylesheet);
+        assert_eq!(stylesheet_with_clauses_a_and_c(), parsed_stylesheet);
+    }
+
+    #[test]
+    fn malformed_clauses_are_reported_separately() {
+        let source = ":: {\n  a: a;\n  b: ;\n  c: c;\n  d d: d;\n  e: e;\n}";
+        let clause = |name: &str| StyleClause {
+            key: StyleKey::Property(RawPropertyKey::Property(name.to_owned())),
+            value: Expression::String(name.to_owned()),
+        };
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![clause("a"), clause("c"), clause("e")],
+        }]);
+        let parsed_stylesheet = parse_stylesheet(
+            source,
+            ExpectErrors::exact([
+                ParseError {
+                    error_data: SyntaxError::UnexpectedToken.into(),
+                    line_number: 3,
+                },
+                ParseError {
+                    error_data: SyntaxError::UnexpectedToken.into(),
+                    line_number: 5,
+                },
+            ])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn malformed_selector_discards_rule() {
+        let source = ":: { a: a; }\nthread { b: b; }\n:: { c: c; }";
+        let parsed_stylesheet = parse_stylesheet(
+            source,
+            ExpectErrors::exact([ParseError {
+                error_data: SyntaxError::InvalidEdgeLabel(InvalidSymbol("thread".to_owned()))
+                    .into(),
+                line_number: 2,
+            }])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+        assert_eq!(parsed_stylesheet.rules.len(), 2);
     }
 
     #[test]
@@ -655,17 +1209,17 @@ mod test {
         let source = ":: { a: a; ; c: c }";
         let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::some().f())
             .expect("Stylesheet should have parsed");
-        assert_eq!(Stylesheet::default(), parsed_stylesheet);
+        assert_eq!(stylesheet_with_clauses_a_and_c(), parsed_stylesheet);
     }
 
-    /// This test verifies that a parser stack overflow is handled
-    /// gracefully and the right error is returned.
+    /// This test verifies that expressions that are nested deeply enough
+    /// to overflow the parser's stack are rejected gracefully.
     ///
     /// Parser stack overflow can be triggered by deeply nesting
     /// right-associative expressions (either parenthesized, or right-associative
     /// by design.
     #[test]
-    fn stack_overflow() {
+    fn nesting_too_deep() {
         let source = r"*.if(
             0 ? 0 ? 0 ? 0 ? 0 ? 0 ? 0 ? 0 ? 0 ? 0 ? 0 ?
                 0 ? 0 ? 0 ? 0 ? 0 ? 0 ? 0 ? 0 ? 0 ? 0 ? 0 ?
@@ -679,14 +1233,81 @@ mod test {
                 0 : 0 : 0 : 0 : 0 : 0 : 0 : 0 : 0 : 0 : 0 :
             0 : 0 : 0 : 0 : 0 : 0 : 0 : 0 : 0 : 0 : 0 
         ) { }";
-        let result = parse_stylesheet(source, ExpectErrors::none().f());
+        let parsed_stylesheet = parse_stylesheet(
+            source,
+            ExpectErrors::exact([ParseError {
+                error_data: SyntaxError::NestingTooDeep(ParseOptions::DEFAULT_MAX_NESTING_DEPTH)
+                    .into(),
+                line_number: 3,
+            }])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+        assert_eq!(Stylesheet::default(), parsed_stylesheet);
+    }
+
+    #[test]
+    fn nesting_too_deep_discards_one_clause() {
+        let source = ":: {\n  a: 1;\n  b: ((1 ? (2) : 3));\n  c: ((1));\n}";
+        let clause = |name: &str| StyleClause {
+            key: StyleKey::Property(RawPropertyKey::Property(name.to_owned())),
+            value: Expression::Int(1),
+        };
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![clause("a"), clause("c")],
+        }]);
+        let parsed_stylesheet = parse_stylesheet_with_options(
+            source,
+            ParseOptions::new().with_max_nesting_depth(2),
+            ExpectErrors::exact([ParseError {
+                error_data: SyntaxError::NestingTooDeep(2).into(),
+                line_number: 3,
+            }])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn long_flat_conditional_chain() {
+        let length = 4 * ParseOptions::DEFAULT_MAX_NESTING_DEPTH;
+        let source = format!(":: {{ a: {}0; }}", "1 ? 2 : ".repeat(length));
+        let expected_value = (0..length).fold(Expression::Int(0), |alternative, _| {
+            Expression::Conditional(
+                Expression::Int(1).into(),
+                Expression::Int(2).into(),
+                alternative.into(),
+            )
+        });
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(RawPropertyKey::Property("a".to_owned())),
+                value: expected_value,
+            }],
+        }]);
+        let parsed_stylesheet = parse_stylesheet(&source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn stack_overflow() {
+        let source = format!(":: {{ a: {}0{}; }}", "(".repeat(200), ")".repeat(200));
+        let result = parse_stylesheet_with_options(
+            &source,
+            ParseOptions::new().with_max_nesting_depth(usize::MAX),
+            ExpectErrors::none().f(),
+        );
         assert_eq!(result, Err(grammar::ParseFailure::StackOverflow));
     }
 
     #[test]
     fn fragment_attributes() {
         let source = ":: { start/a: a; end/b: b }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![
                 StyleClause {
@@ -713,7 +1334,7 @@ mod test {
     #[test]
     fn type_assertions_in_selectors() {
         let source = ":: :struct :\"struct\" :vector :\"vector\" { }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::from_path(
                 [
                     SelectorSegment::Condition(Expression::UnaryOperator(
@@ -757,6 +1378,149 @@ mod test {
         assert_eq!(expected_stylesheet, parsed_stylesheet);
     }
 
+    #[test]
+    fn value_assertions_in_selectors() {
+        let source = ":: :zero :negative :null :\"zero\" { value: is-zero(@); }";
+        let value_is = |predicate| {
+            Expression::UnaryOperator(
+                UnaryOperator::ValueIs(predicate),
+                Expression::UnaryOperator(
+                    UnaryOperator::NodeValue,
+                    Expression::Select(LimitedSelector::default().into()).into(),
+                )
+                .into(),
+            )
+        };
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::Condition(value_is(ValuePredicate::Zero)),
+                    SelectorSegment::Condition(value_is(ValuePredicate::Negative)),
+                    SelectorSegment::Condition(Expression::BinaryOperator(
+                        Expression::UnaryOperator(
+                            UnaryOperator::NodeIsA(NodeTypeClass::Ref),
+                            Expression::Select(LimitedSelector::default().into()).into(),
+                        )
+                        .into(),
+                        BinaryOperator::And,
+                        value_is(ValuePredicate::Zero).into(),
+                    )),
+                    SelectorSegment::Condition(Expression::BinaryOperator(
+                        Expression::UnaryOperator(
+                            UnaryOperator::NodeTypeName,
+                            Expression::Select(LimitedSelector::default().into()).into(),
+                        )
+                        .into(),
+                        BinaryOperator::Eq,
+                        Expression::String("zero".to_owned()).into(),
+                    )),
+                ]
+                .into(),
+            ),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(RawPropertyKey::Property("value".to_owned())),
+                value: Expression::UnaryOperator(
+                    UnaryOperator::ValueIs(ValuePredicate::Zero),
+                    Expression::Select(LimitedSelector::default().into()).into(),
+                ),
+            }],
+        }]);
+        let parsed_stylesheet =
+            parse_stylesheet_with_options(source, experimental(), ExpectErrors::none().f())
+                .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn lifetime_assertions_in_selectors() {
+        let source = ":: :new :removed :\"new\" { }";
+        let lifetime_condition = |name: &str| {
+            SelectorSegment::Condition(Expression::BinaryOperator(
+                Expression::MagicVariable(MagicVariableKey::Lifetime).into(),
+                BinaryOperator::Eq,
+                Expression::String(name.to_owned()).into(),
+            ))
+        };
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::from_path(
+                [
+                    lifetime_condition("new"),
+                    lifetime_condition("removed"),
+                    SelectorSegment::Condition(Expression::BinaryOperator(
+                        Expression::UnaryOperator(
+                            UnaryOperator::NodeTypeName,
+                            Expression::Select(LimitedSelector::default().into()).into(),
+                        )
+                        .into(),
+                        BinaryOperator::Eq,
+                        Expression::String("new".to_owned()).into(),
+                    )),
+                ]
+                .into(),
+            ),
+            properties: Vec::new(),
+        }]);
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn custom_class_assertions_in_selectors() {
+        let source = ":: :custom :class(task) :class(\"task\") { value: is-custom(@); }";
+        let is_custom = || {
+            Expression::UnaryOperator(
+                UnaryOperator::NodeIsA(NodeTypeClass::Custom),
+                Expression::Select(LimitedSelector::default().into()).into(),
+            )
+        };
+        let class_condition = || {
+            SelectorSegment::Condition(Expression::BinaryOperator(
+                Expression::UnaryOperator(
+                    UnaryOperator::NodeCustomClass,
+                    Expression::Select(LimitedSelector::default().into()).into(),
+                )
+                .into(),
+                BinaryOperator::Eq,
+                Expression::String("task".to_owned()).into(),
+            ))
+        };
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::Condition(is_custom()),
+                    class_condition(),
+                    class_condition(),
+                ]
+                .into(),
+            ),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(RawPropertyKey::Property("value".to_owned())),
+                value: is_custom(),
+            }],
+        }]);
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn unknown_function_in_selector_condition() {
+        let source = ":: :kind(task) { }";
+        // Malformed selector discards the whole rule
+        let expected_stylesheet = Stylesheet::default();
+        let parsed_stylesheet = parse_stylesheet(
+            source,
+            ExpectErrors::exact([ParseError {
+                error_data: SyntaxError::InvalidFunction(InvalidSymbol("kind".to_owned())).into(),
+                line_number: 1,
+            }])
+            .f(),
+        )
+        .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
     #[test]
     fn dynamic_index_matcher_in_expression() {
         let source = ":: { parent: @([--len - 1][--i]) }";
@@ -765,7 +1529,7 @@ mod test {
             BinaryOperator::Minus,
             Expression::Int(1).into(),
         );
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Property(RawPropertyKey::Property("parent".to_owned())),
@@ -786,7 +1550,7 @@ mod test {
     #[test]
     fn dynamic_index_matcher_in_selector() {
         let source = ":: [--len - 1] [--i] { }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::from_path(
                 [
                     // Dynamic index matcher unrolls
@@ -817,10 +1581,31 @@ mod test {
         assert_eq!(expected_stylesheet, parsed_stylesheet);
     }
 
+    #[test]
+    fn parent_matcher_in_expression() {
+        let source = r#":: { parent: @(.. "count") }"#;
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(RawPropertyKey::Property("parent".to_owned())),
+                value: Expression::Select(
+                    LimitedSelector::from_path([
+                        LimitedEdgeMatcher::Parent,
+                        EdgeLabel::Named("count".into(), 0).into(),
+                    ])
+                    .into(),
+                ),
+            }],
+        }]);
+        let parsed_stylesheet = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
     #[test]
     fn select_origin_override() {
         let source = ":: { parent: @((@) main) }";
-        let expected_stylesheet = Stylesheet(vec![StyleRule {
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
                 key: StyleKey::Property(RawPropertyKey::Property("parent".to_owned())),
@@ -862,4 +1647,219 @@ mod test {
         parse_stylesheet(source, ExpectErrors::exact(expected_errors).f())
             .expect("Stylesheet should have parsed");
     }
+
+    #[test]
+    fn stable_syntax_at_experimental_level() {
+        let source = ":: { a: 1; b: --c; } * { --c: 2 }";
+        let stable = parse_stylesheet(source, ExpectErrors::none().f())
+            .expect("Stylesheet should have parsed");
+        let experimental = parse_stylesheet_with_options(
+            source,
+            ParseOptions::new().with_syntax_level(SyntaxLevel::Experimental),
+            ExpectErrors::none().f(),
+        )
+        .expect("Stylesheet should have parsed");
+        assert_eq!(stable, experimental);
+    }
+
+    #[test]
+    fn experimental_syntax_at_stable_level() {
+        let empty_rule = || StyleRule {
+            selector: Selector::default(),
+            properties: Vec::new(),
+        };
+        let cases = [
+            ("@rule \"a\" :: { }", Stylesheet::default()),
+            ("@when --X { :: { } }", Stylesheet::default()),
+            ("@function f(--a) { return: --a; }", Stylesheet::default()),
+            (":: :zero { }", Stylesheet::default()),
+            (
+                ":: { display-if: --X ? cell; }",
+                Stylesheet::new(vec![empty_rule()]),
+            ),
+        ];
+        for (source, expected_stylesheet) in cases {
+            let expected_errors = [ParseError {
+                error_data: SyntaxError::SyntaxLevelRequired(SyntaxLevel::Experimental).into(),
+                line_number: 1,
+            }];
+            let parsed_stylesheet =
+                parse_stylesheet(source, ExpectErrors::exact(expected_errors).f())
+                    .expect("Stylesheet should have parsed");
+            assert_eq!(expected_stylesheet, parsed_stylesheet);
+        }
+    }
+
+    #[test]
+    fn custom_edge_labels() {
+        let source = "thread .alt(channel, ref) { a: --NAME }";
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::anything_any_number_of_times(),
+                    SelectorSegment::Match(EdgeLabel::Custom("thread".to_owned()).into()),
+                    SelectorSegment::Branch(vec![
+                        [SelectorSegment::Match(
+                            EdgeLabel::Custom("channel".to_owned()).into(),
+                        )]
+                        .into(),
+                        [SelectorSegment::Match(EdgeLabel::Deref.into())].into(),
+                    ]),
+                ]
+                .into(),
+            ),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(RawPropertyKey::Property("a".to_owned())),
+                value: Expression::MagicVariable(MagicVariableKey::EdgeName),
+            }],
+        }]);
+        let options = ParseOptions::new()
+            .with_custom_edge_label("thread".to_owned())
+            .with_custom_edge_label("channel".to_owned());
+        let parsed_stylesheet =
+            parse_stylesheet_with_options(source, options, ExpectErrors::none().f())
+                .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet);
+    }
+
+    #[test]
+    fn unregistered_custom_edge_label() {
+        let source = "thread { }";
+        let expected_errors = [ParseError {
+            error_data: SyntaxError::InvalidEdgeLabel(InvalidSymbol("thread".to_owned())).into(),
+            line_number: 1,
+        }];
+        parse_stylesheet(source, ExpectErrors::exact(expected_errors).f())
+            .expect("Stylesheet should have parsed");
+    }
+
+    #[test]
+    fn borrowed_names_refer_to_source() {
+        let source = r#":: { display: graph; --v: "a"; "q": "b\n"; f/p: --v; }"#;
+        let parsed_stylesheet =
+            parse_stylesheet_borrowed(source, ParseOptions::new(), ExpectErrors::none().f())
+                .expect("Stylesheet should have parsed");
+        let expected_properties = vec![
+            borrowed::StyleClause {
+                key: borrowed::StyleKey::Property(borrowed::RawPropertyKey::Property(
+                    "display".into(),
+                )),
+                value: borrowed::Expression::String("graph".into()),
+            },
+            borrowed::StyleClause {
+                key: borrowed::StyleKey::Variable("--v".into()),
+                value: borrowed::Expression::String("a".into()),
+            },
+            borrowed::StyleClause {
+                key: borrowed::StyleKey::Property(borrowed::RawPropertyKey::QuotedProperty(
+                    "q".into(),
+                )),
+                value: borrowed::Expression::String("b\n".into()),
+            },
+            borrowed::StyleClause {
+                key: borrowed::StyleKey::Property(borrowed::RawPropertyKey::FragmentProperty(
+                    "f".into(),
+                    "p".into(),
+                )),
+                value: borrowed::Expression::Variable("--v".into()),
+            },
+        ];
+        assert_eq!(parsed_stylesheet.rules.len(), 1);
+        let properties = &parsed_stylesheet.rules[0].properties;
+        assert_eq!(properties, &expected_properties);
+        // Only the literal with an escape sequence cannot be borrowed
+        let borrowed_names = properties.iter().flat_map(|clause| {
+            let key = match &clause.key {
+                borrowed::StyleKey::Property(borrowed::RawPropertyKey::FragmentProperty(f, p)) => {
+                    vec![f, p]
+                }
+                borrowed::StyleKey::Property(
+                    borrowed::RawPropertyKey::Property(name)
+                    | borrowed::RawPropertyKey::QuotedProperty(name),
+                )
+                | borrowed::StyleKey::Variable(name) => vec![name],
+            };
+            let value = match &clause.value {
+                borrowed::Expression::String(value) | borrowed::Expression::Variable(value) => {
+                    Some(value)
+                }
+                _ => None,
+            };
+            key.into_iter().chain(value)
+        });
+        let owned_names: Vec<_> = borrowed_names
+            .filter(|name| matches!(name, Cow::Owned(_)))
+            .collect();
+        assert_eq!(owned_names, [&Cow::<str>::Owned("b\n".to_owned())]);
+    }
+
+    #[test]
+    fn borrowed_stylesheet_converts_to_owned() {
+        let source = "@requires gdb;\n@function f(--a) { return: --a + 1; }\n@rule \"r\"\n\"x\" :t .if(@(\"y\" [--i])) ::extra(e) { v: f(--b); display-if: --b == 1 ? \"s\"; }";
+        let function = UserFunction::new(
+            "f".to_owned(),
+            vec!["--a".to_owned()],
+            Expression::BinaryOperator(
+                Expression::Variable("--a".to_owned()).into(),
+                BinaryOperator::Plus,
+                Expression::Int(1).into(),
+            ),
+        );
+        let condition = Expression::Select(
+            LimitedSelector::from_path([
+                LimitedEdgeMatcher::Exact(EdgeLabel::Named("y".into(), 0)),
+                LimitedEdgeMatcher::DynIndex(Expression::Variable("--i".to_owned())),
+            ])
+            .into(),
+        );
+        let type_condition = Expression::BinaryOperator(
+            Expression::UnaryOperator(
+                UnaryOperator::NodeTypeName,
+                Expression::Select(LimitedSelector::default().into()).into(),
+            )
+            .into(),
+            BinaryOperator::Eq,
+            Expression::String("t".to_owned()).into(),
+        );
+        let expected_stylesheet = Stylesheet::new(vec![StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::anything_any_number_of_times(),
+                    SelectorSegment::Match(EdgeMatcher::Named("x".to_owned())),
+                    SelectorSegment::Condition(type_condition),
+                    SelectorSegment::Condition(condition),
+                ]
+                .into(),
+            )
+            .with_extra("e".to_owned()),
+            properties: vec![
+                StyleClause {
+                    key: StyleKey::Property(RawPropertyKey::Property("v".to_owned())),
+                    value: Expression::Call(
+                        function.into(),
+                        vec![Expression::Variable("--b".to_owned())],
+                    ),
+                },
+                StyleClause {
+                    key: StyleKey::Property(RawPropertyKey::Property("display".to_owned())),
+                    value: Expression::Conditional(
+                        Expression::BinaryOperator(
+                            Expression::Variable("--b".to_owned()).into(),
+                            BinaryOperator::Eq,
+                            Expression::Int(1).into(),
+                        )
+                        .into(),
+                        Expression::String("s".to_owned()).into(),
+                        Expression::Unset.into(),
+                    ),
+                },
+            ],
+        }])
+        .with_requirements(CapabilitySet::new().with("gdb"))
+        .with_rule_name(0, "r");
+        let parsed_stylesheet =
+            parse_stylesheet_borrowed(source, experimental(), ExpectErrors::none().f())
+                .expect("Stylesheet should have parsed");
+        assert_eq!(expected_stylesheet, parsed_stylesheet.into_owned());
+    }
 }
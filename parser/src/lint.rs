@@ -0,0 +1,851 @@
+//! Semantic analysis of parsed stylesheets.
+//!
+//! Unlike [`diagnostics`](crate::diagnostics), which reports
+//! problems that prevent the parser from understanding the source,
+//! this module reports constructs that are valid,
+//! but are unlikely to do what the author intended.
+
+use crate::{ParseFailure, ParseOptions, lexer::Token, parse_stylesheet_with_options};
+use aili_style::stylesheet::{
+    RawPropertyKey, StyleKey, StyleRule, Stylesheet,
+    expression::{Expression, LimitedEdgeMatcher, LimitedSelector, UnaryOperator},
+    selector::{Selector, SelectorPath, SelectorSegment},
+};
+use derive_more::Display;
+use logos::Logos;
+use std::{collections::HashSet, ops::Range};
+
+/// Kind of problem found by [`lint`].
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+pub enum LintKind {
+    /// A property is assigned that the renderer does not recognize.
+    ///
+    /// Quoted property names are not checked, as they are meant
+    /// to pass arbitrary attributes to the renderer.
+    #[display("unknown property '{_0}'")]
+    UnknownProperty(String),
+
+    /// A fragment property is assigned to a fragment that does not exist,
+    /// and its prefix is not a known namespace either.
+    #[display("unknown fragment '{_0}'")]
+    UnknownFragment(String),
+
+    /// A variable is read, but no rule assigns it before it is read.
+    #[display("variable '{_0}' is read before it is assigned")]
+    UndefinedVariable(String),
+
+    /// A rule has no effect because every property and variable
+    /// it assigns is assigned again by a later rule with an identical selector.
+    #[display("rule is shadowed by rule {_0}, which has an identical selector")]
+    ShadowedRule(usize),
+
+    /// A condition evaluates the same way for every entity.
+    #[display("condition does not depend on the selected entity")]
+    ConstantCondition,
+
+    /// The `target` property is assigned to an entity
+    /// that is not displayed as a connector.
+    #[display("'target' has no effect on entities that are not displayed as connectors")]
+    TargetWithoutConnector,
+}
+
+impl LintKind {
+    /// Gets a stable identifier of the kind of problem,
+    /// such as `unknown-property`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownProperty(_) => "unknown-property",
+            Self::UnknownFragment(_) => "unknown-fragment",
+            Self::UndefinedVariable(_) => "undefined-variable",
+            Self::ShadowedRule(_) => "shadowed-rule",
+            Self::ConstantCondition => "constant-condition",
+            Self::TargetWithoutConnector => "target-without-connector",
+        }
+    }
+}
+
+/// Problem found in a stylesheet by [`lint`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Lint {
+    /// What the problem is.
+    pub kind: LintKind,
+
+    /// Index of the rule where the problem occurred.
+    pub rule_index: usize,
+
+    /// Name of the rule where the problem occurred, if it has one.
+    pub rule_name: Option<String>,
+
+    /// Index of the clause of the rule where the problem occurred,
+    /// if the problem is in a clause and not in the selector.
+    pub clause_index: Option<usize>,
+
+    /// Part of the source where the problem occurred,
+    /// if the stylesheet was linted from its source.
+    pub span: Option<Span>,
+}
+
+/// Part of a stylesheet source.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    /// Byte offsets of the part in the source.
+    pub range: Range<usize>,
+
+    /// One-based number of the line where the part starts.
+    pub line_number: usize,
+
+    /// One-based number of the column where the part starts,
+    /// counted in characters.
+    pub column_number: usize,
+}
+
+/// Options that modify what [`lint`] reports.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LintOptions {
+    /// Names of properties that are recognized by the renderer.
+    ///
+    /// By default, these are the properties interpreted by the translator
+    /// and the attributes of the visual models supported by the frontend.
+    pub known_properties: HashSet<String>,
+
+    /// Names of fragment properties that are recognized by the renderer.
+    pub known_fragment_properties: HashSet<String>,
+
+    /// Namespaces of properties that are meant for specific renderers.
+    ///
+    /// Properties in these namespaces are not checked,
+    /// as each renderer recognizes different ones.
+    pub known_namespaces: HashSet<String>,
+}
+
+impl LintOptions {
+    /// Properties that the translator and the frontend recognize.
+    pub const DEFAULT_PROPERTIES: &[&str] = &[
+        "display",
+        "parent",
+        "target",
+        "stable-key",
+        "detach",
+        "layer",
+        "reverse",
+        "align-items",
+        "checked",
+        "color",
+        "direction",
+        "fill",
+        "gap",
+        "hat",
+        "horizontal-align",
+        "horizontal-justify",
+        "key",
+        "label",
+        "layout",
+        "order",
+        "order-children",
+        "padding",
+        "shape",
+        "size",
+        "stroke",
+        "stroke-style",
+        "stroke-width",
+        "title",
+        "value",
+        "vertical-align",
+        "vertical-justify",
+    ];
+
+    /// Fragment properties that the frontend recognizes.
+    pub const DEFAULT_FRAGMENT_PROPERTIES: &[&str] = &["anchor", "decoration", "label"];
+
+    /// Names of fragments of visual entities.
+    pub const FRAGMENTS: &[&str] = &["start", "end"];
+
+    /// Constructs the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the name of a property that the renderer recognizes.
+    pub fn with_known_property(mut self, name: String) -> Self {
+        self.known_properties.insert(name);
+        self
+    }
+
+    /// Registers the name of a fragment property that the renderer recognizes.
+    pub fn with_known_fragment_property(mut self, name: String) -> Self {
+        self.known_fragment_properties.insert(name);
+        self
+    }
+
+    /// Registers a namespace of properties meant for a specific renderer.
+    pub fn with_known_namespace(mut self, namespace: String) -> Self {
+        self.known_namespaces.insert(namespace);
+        self
+    }
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            known_properties: Self::DEFAULT_PROPERTIES
+                .iter()
+                .copied()
+                .map(str::to_owned)
+                .collect(),
+            known_fragment_properties: Self::DEFAULT_FRAGMENT_PROPERTIES
+                .iter()
+                .copied()
+                .map(str::to_owned)
+                .collect(),
+            known_namespaces: HashSet::new(),
+        }
+    }
+}
+
+/// Analyzes a parsed stylesheet for constructs that are likely mistakes.
+///
+/// Parsed stylesheets do not remember where they came from,
+/// so the problems are located by rule and clause indices only.
+/// Use [`lint_source`] to also locate them in the source.
+///
+/// ```
+/// use aili_parser::{
+///     lint::{LintKind, LintOptions, lint},
+///     parse_stylesheet,
+/// };
+///
+/// let stylesheet = parse_stylesheet(":: { dispaly: graph; }", |_| {}).unwrap();
+/// let lints = lint(&stylesheet, &LintOptions::new());
+/// assert_eq!(lints[0].kind, LintKind::UnknownProperty("dispaly".to_owned()));
+/// assert_eq!(lints[0].clause_index, Some(0));
+/// ```
+pub fn lint(stylesheet: &Stylesheet, options: &LintOptions) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let mut report = |kind, rule_index, clause_index| {
+        lints.push(Lint {
+            kind,
+            rule_index,
+            rule_name: stylesheet.rule_name(rule_index).map(str::to_owned),
+            clause_index,
+            span: None,
+        })
+    };
+    let assigned_variables = assigned_variables(stylesheet);
+    for (rule_index, rule) in stylesheet.rules.iter().enumerate() {
+        for_each_condition_in_path(&rule.selector.path, &mut |condition| {
+            if is_constant(condition) {
+                report(LintKind::ConstantCondition, rule_index, None);
+            } else {
+                for_each_conditional(condition, &mut |nested| {
+                    if is_constant(nested) {
+                        report(LintKind::ConstantCondition, rule_index, None);
+                    }
+                });
+            }
+            for_each_variable(condition, &mut |name| {
+                if !assigned_variables.contains(name) {
+                    report(
+                        LintKind::UndefinedVariable(name.to_owned()),
+                        rule_index,
+                        None,
+                    );
+                }
+            });
+        });
+        for (clause_index, clause) in rule.properties.iter().enumerate() {
+            if let Some(kind) = check_key(&clause.key, options) {
+                report(kind, rule_index, Some(clause_index));
+            }
+            for_each_conditional(&clause.value, &mut |condition| {
+                if is_constant(condition) {
+                    report(LintKind::ConstantCondition, rule_index, Some(clause_index));
+                }
+            });
+            for_each_variable(&clause.value, &mut |name| {
+                // Variables assigned by other rules may be inherited,
+                // but variables assigned by this rule are only
+                // visible to the clauses that come after the assignment
+                let assigned_elsewhere = stylesheet
+                    .rules
+                    .iter()
+                    .enumerate()
+                    .any(|(i, other)| i != rule_index && assigns_variable(other, name));
+                let assigned_before = rule.properties[..clause_index]
+                    .iter()
+                    .any(|previous| is_variable(&previous.key, name));
+                if !assigned_elsewhere && !assigned_before {
+                    report(
+                        LintKind::UndefinedVariable(name.to_owned()),
+                        rule_index,
+                        Some(clause_index),
+                    );
+                }
+            });
+            if is_property(&clause.key, "target")
+                && effective_display(stylesheet, &rule.selector)
+                    .is_some_and(|display| display != "connector")
+            {
+                report(
+                    LintKind::TargetWithoutConnector,
+                    rule_index,
+                    Some(clause_index),
+                );
+            }
+        }
+        if let Some(shadowing_rule) = shadowing_rule(stylesheet, rule_index) {
+            report(LintKind::ShadowedRule(shadowing_rule), rule_index, None);
+        }
+    }
+    lints
+}
+
+/// Parses a stylesheet and analyzes it like [`lint`],
+/// locating each problem in the source.
+///
+/// Problems in a clause are located at the clause,
+/// and problems in a selector are located at the selector.
+/// If the source contains syntax errors, rules that have been
+/// left out of the stylesheet would make the locations unreliable,
+/// so the problems are reported without them.
+/// Use [`lint_stylesheet`](crate::diagnostics::lint_stylesheet)
+/// to find the syntax errors.
+///
+/// ```
+/// use aili_parser::{
+///     ParseOptions,
+///     lint::{LintKind, LintOptions, lint_source},
+/// };
+///
+/// let source = ":: {\n  display: graph;\n  target: @;\n}";
+/// let lints = lint_source(source, ParseOptions::new(), &LintOptions::new()).unwrap();
+/// let span = lints[0].span.as_ref().unwrap();
+/// assert_eq!(lints[0].kind, LintKind::TargetWithoutConnector);
+/// assert_eq!(span.line_number, 3);
+/// assert_eq!(span.column_number, 3);
+/// assert_eq!(&source[span.range.clone()], "target: @");
+/// ```
+pub fn lint_source(
+    source: &str,
+    parse_options: ParseOptions,
+    options: &LintOptions,
+) -> Result<Vec<Lint>, ParseFailure> {
+    let mut has_errors = false;
+    let stylesheet = parse_stylesheet_with_options(source, parse_options, |_| has_errors = true)?;
+    let mut lints = lint(&stylesheet, options);
+    if has_errors {
+        return Ok(lints);
+    }
+    if let Some(rule_spans) = locate_parsed_rules(source, &stylesheet) {
+        for lint in &mut lints {
+            let rule_span = &rule_spans[lint.rule_index];
+            lint.span = Some(match lint.clause_index {
+                Some(clause_index) => rule_span.clauses[clause_index].clone(),
+                None => rule_span.selector.clone(),
+            });
+        }
+    }
+    Ok(lints)
+}
+
+/// Checks whether a property or variable name is valid.
+fn check_key(key: &StyleKey, options: &LintOptions) -> Option<LintKind> {
+    match key {
+        StyleKey::Property(RawPropertyKey::FragmentProperty(namespace, _))
+            if options.known_namespaces.contains(namespace) =>
+        {
+            None
+        }
+        StyleKey::Property(RawPropertyKey::Property(name))
+            if !options.known_properties.contains(name) =>
+        {
+            Some(LintKind::UnknownProperty(name.clone()))
+        }
+        StyleKey::Property(RawPropertyKey::FragmentProperty(fragment, _))
+            if !LintOptions::FRAGMENTS.contains(&fragment.as_str()) =>
+        {
+            Some(LintKind::UnknownFragment(fragment.clone()))
+        }
+        StyleKey::Property(RawPropertyKey::FragmentProperty(fragment, name))
+            if !options.known_fragment_properties.contains(name) =>
+        {
+            Some(LintKind::UnknownProperty(format!("{fragment}/{name}")))
+        }
+        _ => None,
+    }
+}
+
+/// Checks whether a key is a given unquoted property.
+fn is_property(key: &StyleKey, name: &str) -> bool {
+    matches!(key, StyleKey::Property(RawPropertyKey::Property(n)) if n == name)
+}
+
+/// Checks whether a key is a given variable.
+fn is_variable(key: &StyleKey, name: &str) -> bool {
+    matches!(key, StyleKey::Variable(n) if n == name)
+}
+
+/// Checks whether any clause of a rule assigns a variable.
+fn assigns_variable(rule: &StyleRule, name: &str) -> bool {
+    rule.properties
+        .iter()
+        .any(|clause| is_variable(&clause.key, name))
+}
+
+/// Collects the names of all variables that are assigned anywhere.
+fn assigned_variables(stylesheet: &Stylesheet) -> HashSet<&str> {
+    stylesheet
+        .rules
+        .iter()
+        .flat_map(|rule| &rule.properties)
+        .filter_map(|clause| match &clause.key {
+            StyleKey::Variable(name) => Some(name.as_str()),
+            StyleKey::Property(_) => None,
+        })
+        .collect()
+}
+
+/// Determines the value of `display` that entities selected
+/// by a selector end up with, if it is a literal.
+///
+/// Only rules with an identical selector are considered,
+/// so the result is [`None`] if the display mode is not known for sure.
+fn effective_display<'a>(stylesheet: &'a Stylesheet, selector: &Selector) -> Option<&'a str> {
+    let value = stylesheet
+        .rules
+        .iter()
+        .filter(|rule| rule.selector == *selector)
+        .flat_map(|rule| &rule.properties)
+        .filter(|clause| is_property(&clause.key, "display"))
+        .map(|clause| &clause.value)
+        .next_back()?;
+    match value {
+        Expression::String(display) => Some(display.as_str()),
+        Expression::Unset => Some("none"),
+        _ => None,
+    }
+}
+
+/// Finds a later rule with an identical selector that,
+/// together with the rules between them, reassigns everything
+/// that a rule assigns.
+fn shadowing_rule(stylesheet: &Stylesheet, rule_index: usize) -> Option<usize> {
+    let rule = &stylesheet.rules[rule_index];
+    let mut unassigned = rule
+        .properties
+        .iter()
+        .map(|clause| &clause.key)
+        .collect::<HashSet<_>>();
+    for (i, later) in stylesheet.rules.iter().enumerate().skip(rule_index + 1) {
+        if later.selector != rule.selector {
+            continue;
+        }
+        for clause in &later.properties {
+            unassigned.remove(&clause.key);
+        }
+        if unassigned.is_empty() {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Checks whether an expression evaluates the same way for every entity.
+fn is_constant(expression: &Expression) -> bool {
+    match expression {
+        Expression::Variable(_) | Expression::MagicVariable(_) | Expression::Select(_) => false,
+        Expression::Unset | Expression::Bool(_) | Expression::String(_) | Expression::Int(_) => {
+            true
+        }
+        Expression::UnaryOperator(_, operand) => is_constant(operand),
+        Expression::BinaryOperator(lhs, _, rhs) => is_constant(lhs) && is_constant(rhs),
+        Expression::Conditional(condition, if_true, if_false) => {
+            is_constant(condition) && is_constant(if_true) && is_constant(if_false)
+        }
+        // Function bodies read their parameters, which would
+        // require substituting the arguments to check, so calls
+        // are conservatively assumed to vary
+        Expression::Call(_, _) => false,
+    }
+}
+
+/// Calls a function on all condition segments of a selector path.
+fn for_each_condition_in_path(path: &SelectorPath, f: &mut impl FnMut(&Expression)) {
+    for segment in &path.0 {
+        match segment {
+            SelectorSegment::Match(_) => {}
+            SelectorSegment::AnyNumberOfTimes(path) => for_each_condition_in_path(path, f),
+            SelectorSegment::Branch(paths) => {
+                for path in paths {
+                    for_each_condition_in_path(path, f);
+                }
+            }
+            SelectorSegment::Condition(condition) => f(condition),
+        }
+    }
+}
+
+/// Calls a function on the conditions of all conditional
+/// expressions nested in an expression.
+fn for_each_conditional(expression: &Expression, f: &mut impl FnMut(&Expression)) {
+    for_each_subexpression(expression, &mut |expression| {
+        if let Expression::Conditional(condition, _, _) = expression {
+            f(condition);
+        }
+    });
+}
+
+/// Calls a function on the names of all variables read by an expression.
+///
+/// Variables that are only tested by `isset` are not included,
+/// since testing whether a variable is assigned is what `isset` is for.
+fn for_each_variable(expression: &Expression, f: &mut impl FnMut(&str)) {
+    match expression {
+        Expression::Variable(name) => f(name),
+        Expression::UnaryOperator(UnaryOperator::IsSet, operand)
+            if matches!(**operand, Expression::Variable(_)) => {}
+        Expression::UnaryOperator(_, operand) => for_each_variable(operand, f),
+        Expression::BinaryOperator(lhs, _, rhs) => {
+            for_each_variable(lhs, f);
+            for_each_variable(rhs, f);
+        }
+        Expression::Conditional(condition, if_true, if_false) => {
+            for_each_variable(condition, f);
+            for_each_variable(if_true, f);
+            for_each_variable(if_false, f);
+        }
+        // Function bodies only read their own parameters
+        Expression::Call(_, arguments) => {
+            for argument in arguments {
+                for_each_variable(argument, f);
+            }
+        }
+        Expression::Select(selector) => {
+            for_each_expression_in_selector(selector, &mut |e| for_each_variable(e, f))
+        }
+        Expression::MagicVariable(_)
+        | Expression::Unset
+        | Expression::Bool(_)
+        | Expression::String(_)
+        | Expression::Int(_) => {}
+    }
+}
+
+/// Calls a function on an expression and all expressions nested in it.
+fn for_each_subexpression(expression: &Expression, f: &mut impl FnMut(&Expression)) {
+    f(expression);
+    match expression {
+        Expression::UnaryOperator(_, operand) => for_each_subexpression(operand, f),
+        Expression::BinaryOperator(lhs, _, rhs) => {
+            for_each_subexpression(lhs, f);
+            for_each_subexpression(rhs, f);
+        }
+        Expression::Conditional(condition, if_true, if_false) => {
+            for_each_subexpression(condition, f);
+            for_each_subexpression(if_true, f);
+            for_each_subexpression(if_false, f);
+        }
+        Expression::Call(_, arguments) => {
+            for argument in arguments {
+                for_each_subexpression(argument, f);
+            }
+        }
+        Expression::Select(selector) => {
+            for_each_expression_in_selector(selector, &mut |e| for_each_subexpression(e, f))
+        }
+        Expression::Variable(_)
+        | Expression::MagicVariable(_)
+        | Expression::Unset
+        | Expression::Bool(_)
+        | Expression::String(_)
+        | Expression::Int(_) => {}
+    }
+}
+
+/// Calls a function on the expressions directly nested in a limited selector.
+fn for_each_expression_in_selector(selector: &LimitedSelector, f: &mut impl FnMut(&Expression)) {
+    if let Some(origin) = &selector.origin {
+        f(origin);
+    }
+    for matcher in &selector.path {
+        if let LimitedEdgeMatcher::DynIndex(index) = matcher {
+            f(index);
+        }
+    }
+}
+
+/// Location of a rule in a stylesheet source.
+pub(crate) struct RuleSpan {
+    /// Location of the selector.
+    pub selector: Span,
+
+    /// Locations of all clauses, without their terminating semicolons.
+    pub clauses: Vec<Span>,
+
+    /// Byte offset of the end of the rule's closing brace.
+    pub end: usize,
+}
+
+/// Finds the locations of all rules of a stylesheet
+/// and their clauses in the source it has been parsed from.
+///
+/// Fails if the rules found in the source do not match the stylesheet,
+/// which happens if some were left out because of syntax errors.
+pub(crate) fn locate_parsed_rules(source: &str, stylesheet: &Stylesheet) -> Option<Vec<RuleSpan>> {
+    let rule_spans = locate_rules(source);
+    let spans_match = rule_spans.len() == stylesheet.rules.len()
+        && rule_spans
+            .iter()
+            .zip(&stylesheet.rules)
+            .all(|(span, rule)| span.clauses.len() == rule.properties.len());
+    spans_match.then_some(rule_spans)
+}
+
+/// Finds the locations of all rules and their clauses in a source.
+///
+/// The source is expected to be free of syntax errors.
+fn locate_rules(source: &str) -> Vec<RuleSpan> {
+    let mut lexer = Token::lexer(source);
+    let mut rules = Vec::new();
+    let mut in_directive = false;
+    let mut in_function = false;
+    let mut in_block_condition = false;
+    let mut in_rule_name = false;
+    let mut selector: Option<Span> = None;
+    let mut clauses: Option<Vec<Span>> = None;
+    let mut clause: Option<Span> = None;
+    while let Some(token) = lexer.next() {
+        let range = lexer.span();
+        let line_start = lexer.extras.line_offset;
+        let token_span = Span {
+            line_number: lexer.extras.line_index + 1,
+            column_number: source[line_start..range.start].chars().count() + 1,
+            range,
+        };
+        let extend = |span: &mut Option<Span>| match span {
+            Some(span) => span.range.end = token_span.range.end,
+            None => *span = Some(token_span.clone()),
+        };
+        let in_body = clauses.is_some();
+        match (token, in_body) {
+            (Ok(Token::Requires), false) => in_directive = true,
+            (Ok(Token::Semicolon), false) if in_directive => in_directive = false,
+            (_, false) if in_directive => {}
+            (Ok(Token::Function), false) => in_function = true,
+            (Ok(Token::CloseBrace), false) if in_function => in_function = false,
+            (_, false) if in_function => {}
+            (Ok(Token::Rule), false) => in_rule_name = true,
+            // Name of the rule
+            (_, false) if in_rule_name => in_rule_name = false,
+            (Ok(Token::When), false) => in_block_condition = true,
+            (Ok(Token::OpenBrace), false) if in_block_condition => in_block_condition = false,
+            (_, false) if in_block_condition => {}
+            // Closing brace of a conditional block
+            (Ok(Token::CloseBrace), false) => {}
+            (Ok(Token::OpenBrace), false) => clauses = Some(Vec::new()),
+            (_, false) => extend(&mut selector),
+            (Ok(Token::CloseBrace), true) => {
+                let mut clauses = clauses.take().unwrap_or_default();
+                clauses.extend(clause.take());
+                let end = token_span.range.end;
+                rules.push(RuleSpan {
+                    selector: selector.take().unwrap_or(token_span),
+                    clauses,
+                    end,
+                });
+            }
+            (Ok(Token::Semicolon), true) => {
+                if let Some(clauses) = &mut clauses {
+                    clauses.extend(clause.take());
+                }
+            }
+            (_, true) => extend(&mut clause),
+        }
+    }
+    // The last rule may be missing its closing brace
+    if let Some(mut clauses) = clauses {
+        clauses.extend(clause);
+        if let Some(selector) = selector {
+            rules.push(RuleSpan {
+                selector,
+                clauses,
+                end: source.len(),
+            });
+        }
+    }
+    rules
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SyntaxLevel;
+
+    fn lint_kinds(source: &str) -> Vec<(LintKind, usize, Option<usize>)> {
+        let options = ParseOptions::new();
+        lint_source(source, options, &LintOptions::new())
+            .unwrap()
+            .into_iter()
+            .map(|lint| (lint.kind, lint.rule_index, lint.clause_index))
+            .collect()
+    }
+
+    #[test]
+    fn valid_stylesheet_has_no_lints() {
+        let source = ":: { display: graph; --x: 1; }\n:int { display: cell; value: --x; }";
+        assert!(lint_kinds(source).is_empty());
+    }
+
+    #[test]
+    fn unknown_properties() {
+        assert_eq!(
+            lint_kinds(r#":: { colour: red; "colour": red; middle/label: a; start/lable: a; }"#),
+            [
+                (LintKind::UnknownProperty("colour".to_owned()), 0, Some(0)),
+                (LintKind::UnknownFragment("middle".to_owned()), 0, Some(2)),
+                (
+                    LintKind::UnknownProperty("start/lable".to_owned()),
+                    0,
+                    Some(3)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn known_namespaces() {
+        let options = LintOptions::new().with_known_namespace("svg".to_owned());
+        let lints = lint_source(
+            ":: { svg/stroke-dasharray: a; debug/note: a; }",
+            ParseOptions::new(),
+            &options,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|lint| (lint.kind, lint.clause_index))
+        .collect::<Vec<_>>();
+        assert_eq!(
+            lints,
+            [(LintKind::UnknownFragment("debug".to_owned()), Some(1))]
+        );
+    }
+
+    #[test]
+    fn custom_known_property() {
+        let options = LintOptions::new().with_known_property("colour".to_owned());
+        let lints = lint_source(":: { colour: red; }", ParseOptions::new(), &options).unwrap();
+        assert!(lints.is_empty());
+    }
+
+    #[test]
+    fn variable_read_before_assignment() {
+        assert_eq!(
+            lint_kinds(":: { value: --x; --x: 1; label: isset(--y); title: --y; }"),
+            [
+                (LintKind::UndefinedVariable("--x".to_owned()), 0, Some(0)),
+                (LintKind::UndefinedVariable("--y".to_owned()), 0, Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn variable_inherited_from_other_rule() {
+        assert!(lint_kinds(":int { value: --x; }\n:: { --x: 1; }").is_empty());
+    }
+
+    #[test]
+    fn shadowed_rule() {
+        assert_eq!(
+            lint_kinds(":int { display: cell; }\n:: { display: graph; }\n:int { display: text; }"),
+            [(LintKind::ShadowedRule(2), 0, None)]
+        );
+    }
+
+    #[test]
+    fn partially_overridden_rule_is_not_shadowed() {
+        assert!(
+            lint_kinds(":int { display: cell; value: 1; }\n:int { display: text; }").is_empty()
+        );
+    }
+
+    #[test]
+    fn constant_conditions() {
+        assert_eq!(
+            lint_kinds(":int.if(1 == 1) { display: cell; value: true ? 1 : 2; }"),
+            [
+                (LintKind::ConstantCondition, 0, None),
+                (LintKind::ConstantCondition, 0, Some(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn target_without_connector() {
+        assert_eq!(
+            lint_kinds(
+                ":ref { display: cell; target: @(ref); }\n:ptr { display: connector; target: @(ref); }"
+            ),
+            [(LintKind::TargetWithoutConnector, 0, Some(1))]
+        );
+    }
+
+    #[test]
+    fn spans_of_selectors_and_clauses() {
+        let source = "@requires gdb;\n:int.if(true) {\n  colour: red;\n}";
+        let lints = lint_source(source, ParseOptions::new(), &LintOptions::new()).unwrap();
+        let spans = lints
+            .iter()
+            .map(|lint| {
+                let span = lint.span.as_ref().unwrap();
+                (
+                    &source[span.range.clone()],
+                    span.line_number,
+                    span.column_number,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(spans, [(":int.if(true)", 2, 1), ("colour: red", 3, 3)]);
+    }
+
+    #[test]
+    fn function_definitions_are_not_rules() {
+        let source = "@function f(--a) { return: --a + 1; }\n:int { colour: f(@); }";
+        let options = ParseOptions::new().with_syntax_level(SyntaxLevel::Experimental);
+        let lints = lint_source(source, options, &LintOptions::new()).unwrap();
+        let spans = lints
+            .iter()
+            .map(|lint| &source[lint.span.as_ref().unwrap().range.clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(spans, ["colour: f(@)"]);
+    }
+
+    #[test]
+    fn lints_refer_to_rule_names() {
+        let source = "@rule \"heads\"\n:int { colour: red; }\n@when --X { :: { colour: red; } }";
+        let options = ParseOptions::new().with_syntax_level(SyntaxLevel::Experimental);
+        let lints = lint_source(source, options, &LintOptions::new()).unwrap();
+        let names_and_spans = lints
+            .iter()
+            .map(|lint| {
+                (
+                    lint.rule_name.as_deref(),
+                    &source[lint.span.as_ref().unwrap().range.clone()],
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names_and_spans,
+            [(Some("heads"), "colour: red"), (None, "colour: red")]
+        );
+    }
+
+    #[test]
+    fn no_spans_with_syntax_errors() {
+        let lints = lint_source(
+            ":: { colour: red; }\n] :: { colour: blue; }",
+            ParseOptions::new(),
+            &LintOptions::new(),
+        )
+        .unwrap();
+        assert!(lints.iter().all(|lint| lint.span.is_none()));
+    }
+}
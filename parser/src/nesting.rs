@@ -0,0 +1,162 @@
+//! Limit on how deeply expressions can be nested.
+
+use crate::lexer::Token;
+
+/// Construct that has been opened and not yet closed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Open {
+    /// Parenthesized or bracketed group, which includes
+    /// arguments of function calls and indices.
+    Group,
+
+    /// Conditional expression whose alternative branch
+    /// has not started yet.
+    Condition,
+
+    /// Alternative branch of a conditional expression.
+    /// It ends together with the group that contains it.
+    Alternative,
+}
+
+/// What should be done with a token after it has been
+/// seen by a [`NestingTracker`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Nesting {
+    /// The token should be passed to the parser.
+    Accept,
+
+    /// The token opens a construct that is nested too deeply.
+    /// An error should be reported and the token discarded.
+    TooDeep,
+
+    /// The token is a part of a construct that is nested too deeply
+    /// and should be discarded.
+    Discard,
+}
+
+/// Tracks how deeply expressions are nested in the token stream,
+/// so that pathological expressions can be discarded before
+/// they overflow the parser's stack.
+///
+/// Parentheses, brackets, and conditional operators each count
+/// as one level of nesting, except that a conditional expression
+/// in the alternative branch of another one continues its chain
+/// at the same level, since the parser does not nest chained conditionals.
+/// Once a construct would exceed the limit,
+/// all tokens up to its end are discarded, while the rest of the input
+/// is parsed as usual. Rule and clause delimiters always end
+/// all constructs, so discarding never spills over into the next clause.
+pub struct NestingTracker {
+    /// Maximum number of constructs that can be open at once.
+    limit: usize,
+    /// Constructs that are open at the moment, innermost last.
+    open: Vec<Open>,
+    /// True when tokens of a construct nested too deeply
+    /// are being discarded.
+    ///
+    /// The discarded construct is always the one
+    /// at index [`NestingTracker::limit`] in [`NestingTracker::open`].
+    is_discarding: bool,
+}
+
+impl NestingTracker {
+    /// Constructs a tracker with the given limit on nesting depth.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            open: Vec::new(),
+            is_discarding: false,
+        }
+    }
+
+    /// Gets the maximum number of constructs that can be open at once.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Processes a token and decides what to do with it.
+    pub fn track(&mut self, token: &Token) -> Nesting {
+        match token {
+            Token::OpenParen | Token::OpenBracket => self.open(Open::Group),
+            Token::Question if self.open.last() == Some(&Open::Alternative) => {
+                // Chained conditional, replaces the one whose alternative it is
+                self.open.pop();
+                self.open.push(Open::Condition);
+                self.accept_unless_discarding()
+            }
+            Token::Question => self.open(Open::Condition),
+            Token::Colon => {
+                while self.open.last() == Some(&Open::Alternative) {
+                    self.open.pop();
+                }
+                if let Some(construct @ Open::Condition) = self.open.last_mut() {
+                    *construct = Open::Alternative;
+                }
+                self.end_discarding_below_limit();
+                self.accept_unless_discarding()
+            }
+            Token::CloseParen | Token::CloseBracket => {
+                self.close_conditions();
+                let closed_at = self.open.len().checked_sub(1);
+                self.open.pop();
+                // The closing delimiter of the discarded group is discarded with it,
+                // delimiters of the groups around it are not
+                let closes_discarded = closed_at.is_some_and(|depth| depth >= self.limit);
+                self.end_discarding_below_limit();
+                if closes_discarded {
+                    Nesting::Discard
+                } else {
+                    self.accept_unless_discarding()
+                }
+            }
+            Token::Comma => {
+                self.close_conditions();
+                self.end_discarding_below_limit();
+                self.accept_unless_discarding()
+            }
+            Token::Semicolon | Token::OpenBrace | Token::CloseBrace => {
+                self.open.clear();
+                self.is_discarding = false;
+                Nesting::Accept
+            }
+            _ => self.accept_unless_discarding(),
+        }
+    }
+
+    /// Opens a construct, checking the limit.
+    fn open(&mut self, construct: Open) -> Nesting {
+        let was_discarding = self.is_discarding;
+        let depth = self.open.len();
+        self.open.push(construct);
+        if was_discarding {
+            Nesting::Discard
+        } else if depth >= self.limit {
+            self.is_discarding = true;
+            Nesting::TooDeep
+        } else {
+            Nesting::Accept
+        }
+    }
+
+    /// Closes all conditional expressions in the innermost group.
+    fn close_conditions(&mut self) {
+        while matches!(self.open.last(), Some(Open::Condition | Open::Alternative)) {
+            self.open.pop();
+        }
+    }
+
+    /// Stops discarding tokens once the discarded construct has been closed.
+    fn end_discarding_below_limit(&mut self) {
+        if self.open.len() <= self.limit {
+            self.is_discarding = false;
+        }
+    }
+
+    fn accept_unless_discarding(&self) -> Nesting {
+        if self.is_discarding {
+            Nesting::Discard
+        } else {
+            Nesting::Accept
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Configuration of the parser.
+
+use crate::{
+    features::SyntaxLevel,
+    symbols::{InvalidSymbol, edge_label_from_name},
+};
+use aili_model::state::EdgeLabel;
+use std::collections::HashSet;
+
+/// Options that modify what input the parser accepts.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseOptions {
+    /// Determines which syntactic constructs are accepted.
+    pub syntax_level: SyntaxLevel,
+
+    /// Names that are recognized as [`EdgeLabel::Custom`]
+    /// edge labels in selectors.
+    ///
+    /// Built-in edge names (see [`edge_label_from_name`])
+    /// take precedence over custom names.
+    pub custom_edge_labels: HashSet<String>,
+
+    /// Maximum number of parentheses, brackets, and conditional
+    /// operators that can be nested in one another.
+    /// A chain of conditional operators, such as `a ? b : c ? d : e`,
+    /// counts as one level.
+    ///
+    /// Expressions that are nested more deeply are discarded
+    /// with a [`SyntaxError::NestingTooDeep`](crate::SyntaxError::NestingTooDeep) error.
+    /// Raising the limit too far may cause the parser to run out of stack
+    /// on deeply nested input and fail with [`ParseFailure::StackOverflow`](crate::ParseFailure::StackOverflow).
+    pub max_nesting_depth: usize,
+}
+
+impl ParseOptions {
+    /// Default value of [`ParseOptions::max_nesting_depth`].
+    pub const DEFAULT_MAX_NESTING_DEPTH: usize = 16;
+
+    /// Constructs the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the syntax level that determines which constructs are accepted.
+    pub fn with_syntax_level(mut self, syntax_level: SyntaxLevel) -> Self {
+        self.syntax_level = syntax_level;
+        self
+    }
+
+    /// Registers a name that should be recognized
+    /// as an [`EdgeLabel::Custom`] edge label.
+    pub fn with_custom_edge_label(mut self, name: String) -> Self {
+        self.custom_edge_labels.insert(name);
+        self
+    }
+
+    /// Sets the maximum depth to which expressions can be nested.
+    pub fn with_max_nesting_depth(mut self, max_nesting_depth: usize) -> Self {
+        self.max_nesting_depth = max_nesting_depth;
+        self
+    }
+
+    /// Resolves the name of an edge label, taking registered
+    /// custom edge labels into account.
+    pub fn edge_label_from_name(&self, name: &str) -> Result<EdgeLabel, InvalidSymbol> {
+        edge_label_from_name(name).or_else(|InvalidSymbol(name)| {
+            if self.custom_edge_labels.contains(&name) {
+                Ok(EdgeLabel::Custom(name))
+            } else {
+                Err(InvalidSymbol(name))
+            }
+        })
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            syntax_level: SyntaxLevel::default(),
+            custom_edge_labels: HashSet::new(),
+            max_nesting_depth: Self::DEFAULT_MAX_NESTING_DEPTH,
+        }
+    }
+}
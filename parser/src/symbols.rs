@@ -1,7 +1,13 @@
 //! Definitions of symbol names used by semantic analysis
 
 use aili_model::state::{EdgeLabel, NodeTypeClass};
-use aili_style::stylesheet::expression::{Expression, MagicVariableKey, UnaryOperator};
+use aili_style::{
+    eval::context::EntityLifetime,
+    stylesheet::expression::{
+        BinaryOperator, Expression, LimitedSelector, MagicVariableKey, UnaryOperator,
+        ValuePredicate,
+    },
+};
 use derive_more::{Display, Error};
 
 /// Error type returned by symbol name matchers
@@ -42,24 +48,41 @@ pub fn is_variable_name(key: &str) -> bool {
 /// Maps function-like [`UnaryOperator`]s to their names.
 ///
 /// ## Symbol Names
-/// | Symbol name                                        | Associated operator                           |
-/// |----------------------------------------------------|-----------------------------------------------|
-/// | `isset`                                            | [`IsSet`](UnaryOperator::IsSet)               |
-/// | `val`                                              | [`NodeValue`](UnaryOperator::NodeValue)       |
-/// | `typename`                                         | [`NodeTypeName`](UnaryOperator::NodeTypeName) |
-/// | `is-`[suffix matching [`node_type_class_by_name`]] | [`NodeIsA`](UnaryOperator::NodeIsA)           |
+/// | Symbol name                                        | Associated operator                                 |
+/// |----------------------------------------------------|-----------------------------------------------------|
+/// | `isset`                                            | [`IsSet`](UnaryOperator::IsSet)                     |
+/// | `iserror`                                          | [`IsError`](UnaryOperator::IsError)                 |
+/// | `hex`                                              | [`Hex`](UnaryOperator::Hex)                         |
+/// | `val`                                              | [`NodeValue`](UnaryOperator::NodeValue)             |
+/// | `typename`                                         | [`NodeTypeName`](UnaryOperator::NodeTypeName)       |
+/// | `classname`                                        | [`NodeCustomClass`](UnaryOperator::NodeCustomClass) |
+/// | `source-file`                                      | [`NodeSourceFile`](UnaryOperator::NodeSourceFile)   |
+/// | `source-line`                                      | [`NodeSourceLine`](UnaryOperator::NodeSourceLine)   |
+/// | `is-`[suffix matching [`node_type_class_by_name`]] | [`NodeIsA`](UnaryOperator::NodeIsA)                 |
+/// | `is-`[suffix matching [`value_predicate_by_name`]] | [`ValueIs`](UnaryOperator::ValueIs)                 |
 pub fn unary_function_by_name(name: &str) -> Result<UnaryOperator, InvalidSymbol> {
     match name {
         "isset" => Ok(UnaryOperator::IsSet),
+        "iserror" => Ok(UnaryOperator::IsError),
+        "hex" => Ok(UnaryOperator::Hex),
         "val" => Ok(UnaryOperator::NodeValue),
         "typename" => Ok(UnaryOperator::NodeTypeName),
+        "classname" => Ok(UnaryOperator::NodeCustomClass),
+        "source-file" => Ok(UnaryOperator::NodeSourceFile),
+        "source-line" => Ok(UnaryOperator::NodeSourceLine),
         _ => {
             let type_class_from_name = name
                 .strip_prefix("is-")
                 .map(node_type_class_by_name)
                 .and_then(Result::ok);
+            let value_predicate_from_name = name
+                .strip_prefix("is-")
+                .map(value_predicate_by_name)
+                .and_then(Result::ok);
             if let Some(type_class) = type_class_from_name {
                 Ok(UnaryOperator::NodeIsA(type_class))
+            } else if let Some(predicate) = value_predicate_from_name {
+                Ok(UnaryOperator::ValueIs(predicate))
             } else {
                 Err(InvalidSymbol(name.to_owned()))
             }
@@ -67,25 +90,128 @@ pub fn unary_function_by_name(name: &str) -> Result<UnaryOperator, InvalidSymbol
     }
 }
 
+/// Maps function-like [`UnaryOperator`]s that take
+/// an integer width as their second argument to their names.
+///
+/// ## Symbol Names
+/// | Symbol name   | Associated operator                       |
+/// |---------------|-------------------------------------------|
+/// | `as-signed`   | [`AsSigned`](UnaryOperator::AsSigned)     |
+/// | `as-unsigned` | [`AsUnsigned`](UnaryOperator::AsUnsigned) |
+pub fn width_function_by_name(name: &str) -> Result<fn(u32) -> UnaryOperator, InvalidSymbol> {
+    match name {
+        "as-signed" => Ok(UnaryOperator::AsSigned),
+        "as-unsigned" => Ok(UnaryOperator::AsUnsigned),
+        _ => Err(InvalidSymbol(name.to_owned())),
+    }
+}
+
+/// Maps function-like [`UnaryOperator`]s that take a width
+/// and a fill character as their second and third arguments to their names.
+///
+/// ## Symbol Names
+/// | Symbol name | Associated operator                   |
+/// |-------------|---------------------------------------|
+/// | `pad-left`  | [`PadLeft`](UnaryOperator::PadLeft)   |
+/// | `pad-right` | [`PadRight`](UnaryOperator::PadRight) |
+pub fn padding_function_by_name(
+    name: &str,
+) -> Result<fn(usize, char) -> UnaryOperator, InvalidSymbol> {
+    match name {
+        "pad-left" => Ok(UnaryOperator::PadLeft),
+        "pad-right" => Ok(UnaryOperator::PadRight),
+        _ => Err(InvalidSymbol(name.to_owned())),
+    }
+}
+
 /// Maps [`NodeTypeClass`]es to their names.
 ///
 /// ## Symbol Names
-/// | Symbol name | Associated type class             |
-/// |-------------|-----------------------------------|
-/// | `root`      | [`Root`](NodeTypeClass::Root)     |
-/// | `frame`     | [`Frame`](NodeTypeClass::Frame)   |
-/// | `val`       | [`Atom`](NodeTypeClass::Atom)     |
-/// | `struct`    | [`Struct`](NodeTypeClass::Struct) |
-/// | `arr`       | [`Array`](NodeTypeClass::Array)   |
-/// | `ref`       | [`Ref`](NodeTypeClass::Ref)       |
+/// | Symbol name | Associated type class                   |
+/// |-------------|-----------------------------------------|
+/// | `root`      | [`Root`](NodeTypeClass::Root)           |
+/// | `frame`     | [`Frame`](NodeTypeClass::Frame)         |
+/// | `val`       | [`Atom`](NodeTypeClass::Atom)           |
+/// | `struct`    | [`Struct`](NodeTypeClass::Struct)       |
+/// | `union`     | [`Union`](NodeTypeClass::Union)         |
+/// | `arr`       | [`Array`](NodeTypeClass::Array)         |
+/// | `ref`       | [`Ref`](NodeTypeClass::Ref)             |
+/// | `truncated` | [`Truncated`](NodeTypeClass::Truncated) |
+/// | `custom`    | [`Custom`](NodeTypeClass::Custom)       |
 pub fn node_type_class_by_name(name: &str) -> Result<NodeTypeClass, InvalidSymbol> {
     match name {
         "root" => Ok(NodeTypeClass::Root),
         "frame" => Ok(NodeTypeClass::Frame),
         "val" => Ok(NodeTypeClass::Atom),
         "struct" => Ok(NodeTypeClass::Struct),
+        "union" => Ok(NodeTypeClass::Union),
         "arr" => Ok(NodeTypeClass::Array),
         "ref" => Ok(NodeTypeClass::Ref),
+        "truncated" => Ok(NodeTypeClass::Truncated),
+        "custom" => Ok(NodeTypeClass::Custom),
+        _ => Err(InvalidSymbol(name.to_owned())),
+    }
+}
+
+/// Maps [`ValuePredicate`]s to their names.
+///
+/// ## Symbol Names
+/// | Symbol name | Associated predicate                   |
+/// |-------------|----------------------------------------|
+/// | `zero`      | [`Zero`](ValuePredicate::Zero)         |
+/// | `negative`  | [`Negative`](ValuePredicate::Negative) |
+pub fn value_predicate_by_name(name: &str) -> Result<ValuePredicate, InvalidSymbol> {
+    match name {
+        "zero" => Ok(ValuePredicate::Zero),
+        "negative" => Ok(ValuePredicate::Negative),
+        _ => Err(InvalidSymbol(name.to_owned())),
+    }
+}
+
+/// Maps value-testing pseudo-classes to the conditions they desugar to.
+///
+/// ## Symbol Names
+/// | Symbol name                                 | Associated condition           |
+/// |---------------------------------------------|--------------------------------|
+/// | Name matching [`value_predicate_by_name`]   | `is-xxx(val(@))`               |
+/// | `null` or `null-ptr`                        | `is-ref(@) && is-zero(val(@))` |
+pub fn value_condition_by_name(name: &str) -> Result<Expression, InvalidSymbol> {
+    let value_is = |predicate| {
+        // is-xxx(val(@))
+        Expression::UnaryOperator(
+            UnaryOperator::ValueIs(predicate),
+            Expression::UnaryOperator(
+                UnaryOperator::NodeValue,
+                Expression::Select(LimitedSelector::default().into()).into(),
+            )
+            .into(),
+        )
+    };
+    match name {
+        "null" | "null-ptr" => Ok(Expression::BinaryOperator(
+            Expression::UnaryOperator(
+                UnaryOperator::NodeIsA(NodeTypeClass::Ref),
+                Expression::Select(LimitedSelector::default().into()).into(),
+            )
+            .into(),
+            BinaryOperator::And,
+            value_is(ValuePredicate::Zero).into(),
+        )),
+        _ => value_predicate_by_name(name).map(value_is),
+    }
+}
+
+/// Maps [`EntityLifetime`]s to their names.
+///
+/// ## Symbol Names
+/// | Symbol name | Associated lifetime                  |
+/// |-------------|--------------------------------------|
+/// | `new`       | [`New`](EntityLifetime::New)         |
+/// | `removed`   | [`Removed`](EntityLifetime::Removed) |
+pub fn entity_lifetime_by_name(name: &str) -> Result<EntityLifetime, InvalidSymbol> {
+    match name {
+        "new" => Ok(EntityLifetime::New),
+        "removed" => Ok(EntityLifetime::Removed),
         _ => Err(InvalidSymbol(name.to_owned())),
     }
 }
@@ -99,12 +225,18 @@ pub fn node_type_class_by_name(name: &str) -> Result<NodeTypeClass, InvalidSymbo
 /// | `true`            | [`Bool`](Expression::Bool)`(true)`  |
 /// | `false`           | [`Bool`](Expression::Bool)`(false)` |
 pub fn literal_expression_by_name(name: &str) -> Result<Expression, InvalidSymbol> {
+    literal_expression(name).ok_or_else(|| InvalidSymbol(name.to_owned()))
+}
+
+/// Same as [`literal_expression_by_name`], but does not allocate
+/// an [`InvalidSymbol`] when the name is not recognized.
+pub(crate) fn literal_expression(name: &str) -> Option<Expression> {
     match name {
-        "unset" => Ok(Expression::Unset),
-        "none" => Ok(Expression::Unset),
-        "true" => Ok(Expression::Bool(true)),
-        "false" => Ok(Expression::Bool(false)),
-        _ => Err(InvalidSymbol(name.to_owned())),
+        "unset" => Some(Expression::Unset),
+        "none" => Some(Expression::Unset),
+        "true" => Some(Expression::Bool(true)),
+        "false" => Some(Expression::Bool(false)),
+        _ => None,
     }
 }
 
@@ -116,12 +248,34 @@ pub fn literal_expression_by_name(name: &str) -> Result<Expression, InvalidSymbo
 /// | `--INDEX`         | [`EdgeIndex`](MagicVariableKey::EdgeIndex)                 |
 /// | `--NAME`          | [`EdgeName`](MagicVariableKey::EdgeName)                   |
 /// | `--DISCRIMINATOR` | [`EdgeDiscriminator`](MagicVariableKey::EdgeDiscriminator) |
+/// | `--DEPTH`         | [`Depth`](MagicVariableKey::Depth)                         |
+/// | `--SIBLING-INDEX` | [`SiblingIndex`](MagicVariableKey::SiblingIndex)           |
+/// | `--LAYER-INDEX`   | [`LayerIndex`](MagicVariableKey::LayerIndex)               |
+/// | `--LIFETIME`      | [`Lifetime`](MagicVariableKey::Lifetime)                   |
+/// | `--ADDRESS`       | [`Address`](MagicVariableKey::Address)                     |
+/// | `--TYPE-CLASS`    | [`TypeClass`](MagicVariableKey::TypeClass)                 |
+/// | `--CHILD-COUNT`   | [`ChildCount`](MagicVariableKey::ChildCount)               |
+/// | `--SHARED`        | [`Shared`](MagicVariableKey::Shared)                       |
 pub fn magic_variable_by_name(name: &str) -> Result<MagicVariableKey, InvalidSymbol> {
+    magic_variable(name).ok_or_else(|| InvalidSymbol(name.to_owned()))
+}
+
+/// Same as [`magic_variable_by_name`], but does not allocate
+/// an [`InvalidSymbol`] when the name is not recognized.
+pub(crate) fn magic_variable(name: &str) -> Option<MagicVariableKey> {
     match name {
-        "--INDEX" => Ok(MagicVariableKey::EdgeIndex),
-        "--NAME" => Ok(MagicVariableKey::EdgeName),
-        "--DISCRIMINATOR" => Ok(MagicVariableKey::EdgeDiscriminator),
-        _ => Err(InvalidSymbol(name.to_owned())),
+        "--INDEX" => Some(MagicVariableKey::EdgeIndex),
+        "--NAME" => Some(MagicVariableKey::EdgeName),
+        "--DISCRIMINATOR" => Some(MagicVariableKey::EdgeDiscriminator),
+        "--DEPTH" => Some(MagicVariableKey::Depth),
+        "--SIBLING-INDEX" => Some(MagicVariableKey::SiblingIndex),
+        "--LAYER-INDEX" => Some(MagicVariableKey::LayerIndex),
+        "--LIFETIME" => Some(MagicVariableKey::Lifetime),
+        "--ADDRESS" => Some(MagicVariableKey::Address),
+        "--TYPE-CLASS" => Some(MagicVariableKey::TypeClass),
+        "--CHILD-COUNT" => Some(MagicVariableKey::ChildCount),
+        "--SHARED" => Some(MagicVariableKey::Shared),
+        _ => None,
     }
 }
 
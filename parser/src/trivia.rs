@@ -0,0 +1,306 @@
+//! Comments and whitespace that the parser otherwise discards.
+//!
+//! Tools that rewrite stylesheets, such as formatters, must not lose
+//! the comments the authors have written. [`parse_stylesheet_with_trivia`]
+//! parses a stylesheet and also collects all comments and whitespace
+//! into a [`TriviaTable`], attaching each piece to the rule or clause
+//! it belongs to.
+//!
+//! ```
+//! use aili_parser::{
+//!     ParseOptions,
+//!     trivia::{Attachment, Node, parse_stylesheet_with_trivia},
+//! };
+//!
+//! let source = "// Root scope\n:: {\n  display: graph; // Lay out as a graph\n}";
+//! let (stylesheet, trivia) =
+//!     parse_stylesheet_with_trivia(source, ParseOptions::new(), |_| {}).unwrap();
+//! let comments = trivia.comments().collect::<Vec<_>>();
+//! assert_eq!(comments[0].text, "// Root scope");
+//! assert_eq!(comments[0].attachment, Some(Attachment::Leading(Node::Rule(0))));
+//! assert_eq!(comments[1].text, "// Lay out as a graph");
+//! assert_eq!(comments[1].attachment, Some(Attachment::Trailing(Node::Clause(0, 0))));
+//! ```
+
+use crate::{
+    ParseError, ParseFailure, ParseOptions,
+    lexer::Token,
+    lint::{Span, locate_parsed_rules},
+    parse_stylesheet_with_options,
+};
+use aili_style::stylesheet::Stylesheet;
+use logos::Logos;
+
+/// Kind of a [`Trivia`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TriviaKind {
+    /// Consecutive whitespace characters, including line breaks.
+    Whitespace,
+
+    /// Comment that starts with `//` and ends at the end of the line.
+    LineComment,
+
+    /// Comment enclosed in `/*` and `*/`.
+    BlockComment,
+}
+
+/// Part of a stylesheet that can be attached to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Node {
+    /// Selector of the rule at an index.
+    Rule(usize),
+
+    /// Clause at an index in the rule at an index.
+    Clause(usize, usize),
+}
+
+/// Describes where in the stylesheet a [`Trivia`] belongs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Attachment {
+    /// Trivia precedes the node, after the end of the previous node.
+    Leading(Node),
+
+    /// Trivia is on the same line as the end of the node
+    /// and no other node starts between them.
+    ///
+    /// The end of a rule is its closing brace.
+    Trailing(Node),
+
+    /// Trivia is between the tokens of the node.
+    Inner(Node),
+
+    /// Trivia follows the last node in the stylesheet.
+    EndOfInput,
+}
+
+/// Comment or whitespace in a stylesheet source.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Trivia {
+    /// What the trivia is.
+    pub kind: TriviaKind,
+
+    /// Text of the trivia, including the comment delimiters.
+    pub text: String,
+
+    /// Location of the trivia in the source.
+    pub span: Span,
+
+    /// Where the trivia belongs in the stylesheet.
+    ///
+    /// Trivia is only attached if the stylesheet contains
+    /// no syntax errors, because rules that have been left out
+    /// of the stylesheet would make the attachment unreliable.
+    pub attachment: Option<Attachment>,
+}
+
+/// All trivia in a stylesheet source, in the order
+/// in which they appear.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct TriviaTable(pub Vec<Trivia>);
+
+impl TriviaTable {
+    /// Iterates over the comments, skipping whitespace.
+    pub fn comments(&self) -> impl Iterator<Item = &Trivia> {
+        self.0
+            .iter()
+            .filter(|trivia| trivia.kind != TriviaKind::Whitespace)
+    }
+
+    /// Iterates over all trivia with a given attachment.
+    pub fn attached_to(&self, attachment: Attachment) -> impl Iterator<Item = &Trivia> {
+        self.0
+            .iter()
+            .filter(move |trivia| trivia.attachment == Some(attachment))
+    }
+}
+
+/// Parses a [`Stylesheet`] like [`parse_stylesheet_with_options`]
+/// and collects its comments and whitespace.
+pub fn parse_stylesheet_with_trivia(
+    source: &str,
+    options: ParseOptions,
+    mut error_handler: impl FnMut(ParseError),
+) -> Result<(Stylesheet, TriviaTable), ParseFailure> {
+    let mut has_errors = false;
+    let stylesheet = parse_stylesheet_with_options(source, options, |error| {
+        has_errors = true;
+        error_handler(error);
+    })?;
+    let mut trivia = collect_trivia(source);
+    if !has_errors && let Some(rule_spans) = locate_parsed_rules(source, &stylesheet) {
+        let mut nodes = Vec::new();
+        for (rule_index, rule) in rule_spans.into_iter().enumerate() {
+            nodes.push((Node::Rule(rule_index), rule.selector));
+            for (clause_index, span) in rule.clauses.into_iter().enumerate() {
+                nodes.push((Node::Clause(rule_index, clause_index), span));
+            }
+            // Empty span at the closing brace, so that trivia
+            // on the same line can trail the rule
+            nodes.push((Node::Rule(rule_index), span_at(source, rule.end..rule.end)));
+        }
+        for trivia in &mut trivia {
+            trivia.attachment = Some(attach(source, &nodes, &trivia.span));
+        }
+    }
+    Ok((stylesheet, TriviaTable(trivia)))
+}
+
+/// Decides where a trivia belongs, given the locations of all nodes
+/// in the order in which they appear.
+fn attach(source: &str, nodes: &[(Node, Span)], span: &Span) -> Attachment {
+    let next_index =
+        nodes.partition_point(|(_, node_span)| node_span.range.start < span.range.start);
+    if let Some((node, node_span)) = next_index.checked_sub(1).map(|i| &nodes[i]) {
+        if span.range.end <= node_span.range.end {
+            return Attachment::Inner(*node);
+        }
+        let between = &source[node_span.range.end..span.range.start];
+        if !between.contains('\n') && !source[span.range.clone()].starts_with('\n') {
+            return Attachment::Trailing(*node);
+        }
+    }
+    match nodes.get(next_index) {
+        Some((node, _)) => Attachment::Leading(*node),
+        None => Attachment::EndOfInput,
+    }
+}
+
+/// Finds all comments and whitespace in a source.
+///
+/// Trivia is whatever the lexer skips between tokens.
+fn collect_trivia(source: &str) -> Vec<Trivia> {
+    let mut trivia = Vec::new();
+    let mut lexer = Token::lexer(source);
+    let mut gap_start = 0;
+    while lexer.next().is_some() {
+        split_trivia(source, gap_start..lexer.span().start, &mut trivia);
+        gap_start = lexer.span().end;
+    }
+    split_trivia(source, gap_start..source.len(), &mut trivia);
+    trivia
+}
+
+/// Splits the text between two tokens into individual trivia.
+fn split_trivia(source: &str, gap: std::ops::Range<usize>, trivia: &mut Vec<Trivia>) {
+    let mut start = gap.start;
+    while start < gap.end {
+        let rest = &source[start..gap.end];
+        let (kind, length) = if rest.starts_with("//") {
+            (
+                TriviaKind::LineComment,
+                rest.find('\n').unwrap_or(rest.len()),
+            )
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let length = comment.find("*/").map_or(rest.len(), |end| end + 4);
+            (TriviaKind::BlockComment, length)
+        } else {
+            let length = rest
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(rest.len());
+            if length == 0 {
+                // The lexer skips nothing else, so there is nothing to split
+                return;
+            }
+            (TriviaKind::Whitespace, length)
+        };
+        trivia.push(Trivia {
+            kind,
+            text: rest[..length].to_owned(),
+            span: span_at(source, start..start + length),
+            attachment: None,
+        });
+        start += length;
+    }
+}
+
+/// Computes the line and column where a part of a source starts.
+fn span_at(source: &str, range: std::ops::Range<usize>) -> Span {
+    let before = &source[..range.start];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Span {
+        line_number: before.matches('\n').count() + 1,
+        column_number: before[line_start..].chars().count() + 1,
+        range,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn attached_comments(source: &str) -> Vec<(String, Option<Attachment>)> {
+        let (_, trivia) = parse_stylesheet_with_trivia(source, ParseOptions::new(), |_| {})
+            .expect("Stylesheet should have parsed");
+        trivia
+            .comments()
+            .map(|trivia| (trivia.text.clone(), trivia.attachment))
+            .collect()
+    }
+
+    #[test]
+    fn trivia_covers_everything_but_tokens() {
+        let source = "/* a */ :: {\n\tdisplay: graph; // b\n}\n";
+        let (_, trivia) = parse_stylesheet_with_trivia(source, ParseOptions::new(), |_| {})
+            .expect("Stylesheet should have parsed");
+        let kinds = trivia
+            .0
+            .iter()
+            .map(|t| (t.kind, t.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                (TriviaKind::BlockComment, "/* a */"),
+                (TriviaKind::Whitespace, " "),
+                (TriviaKind::Whitespace, " "),
+                (TriviaKind::Whitespace, "\n\t"),
+                (TriviaKind::Whitespace, " "),
+                (TriviaKind::Whitespace, " "),
+                (TriviaKind::LineComment, "// b"),
+                (TriviaKind::Whitespace, "\n"),
+                (TriviaKind::Whitespace, "\n"),
+            ]
+        );
+        assert_eq!(trivia.0[6].span.line_number, 2);
+        assert_eq!(trivia.0[6].span.column_number, 18);
+    }
+
+    #[test]
+    fn comments_are_attached_to_nodes() {
+        let source = "
+            // leading rule
+            :: /* inner rule */ main {
+                // leading clause
+                display: /* inner clause */ graph; // trailing clause
+                /* leading next clause */ value: @;
+            } // trailing rule
+            // end
+        ";
+        assert_eq!(
+            attached_comments(source),
+            [
+                ("// leading rule", Attachment::Leading(Node::Rule(0))),
+                ("/* inner rule */", Attachment::Inner(Node::Rule(0))),
+                ("// leading clause", Attachment::Leading(Node::Clause(0, 0))),
+                ("/* inner clause */", Attachment::Inner(Node::Clause(0, 0))),
+                (
+                    "// trailing clause",
+                    Attachment::Trailing(Node::Clause(0, 0))
+                ),
+                (
+                    "/* leading next clause */",
+                    Attachment::Leading(Node::Clause(0, 1))
+                ),
+                ("// trailing rule", Attachment::Trailing(Node::Rule(0))),
+                ("// end", Attachment::EndOfInput),
+            ]
+            .map(|(text, attachment)| (text.to_owned(), Some(attachment)))
+        );
+    }
+
+    #[test]
+    fn comments_are_not_attached_with_syntax_errors() {
+        let source = "// a\n:: { display: ; }";
+        assert_eq!(attached_comments(source), [("// a".to_owned(), None)]);
+    }
+}
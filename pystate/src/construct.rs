@@ -0,0 +1,232 @@
+//! Construction of a [`PyStateGraph`] using a [`PdbSession`].
+
+use crate::{
+    dump::{ObjectDump, StateDump, ValueDump},
+    pdb::{PdbSession, Result},
+    state::*,
+};
+use aili_model::state::*;
+
+impl PyStateGraph {
+    /// Constructs a state graph that only consists of the root node
+    pub fn empty() -> Self {
+        let mut graph = Self::default();
+        graph.nodes.insert(
+            PyStateNodeId::Root,
+            PyStateNode::new(NodeTypeClass::Root, None),
+        );
+        graph
+    }
+
+    /// Constructs a new state graph using a provided pdb session.
+    ///
+    /// This function sends commands to pdb and awaits responses
+    /// asynchronously.
+    pub async fn new(pdb: &mut impl PdbSession) -> Result<Self> {
+        Ok(Self::from_dump(pdb.dump_state().await?))
+    }
+
+    /// Updates an existing state graph using a provided pdb session.
+    ///
+    /// This function sends commands to pdb and awaits responses
+    /// asynchronously.
+    pub async fn update(&mut self, pdb: &mut impl PdbSession) -> Result<()> {
+        *self = Self::new(pdb).await?;
+        Ok(())
+    }
+
+    /// Constructs a state graph from a snapshot of a program's state.
+    pub fn from_dump(dump: StateDump) -> Self {
+        let mut graph = Self::empty();
+        for (i, frame) in dump.frames.into_iter().enumerate() {
+            let frame_id = PyStateNodeId::Frame(i);
+            let previous_id = match i {
+                0 => PyStateNodeId::Root,
+                _ => PyStateNodeId::Frame(i - 1),
+            };
+            let edge = match i {
+                0 => EdgeLabel::Main,
+                _ => EdgeLabel::Next,
+            };
+            graph.add_successor(&previous_id, edge, frame_id.clone());
+            let mut frame_node = PyStateNode::new(NodeTypeClass::Frame, Some(frame.name));
+            frame_node.source_location = frame
+                .file
+                .zip(frame.line)
+                .map(|(file, line)| SourceLocation { file, line });
+            graph.nodes.insert(frame_id.clone(), frame_node);
+            for (name, value) in frame.variables {
+                graph.add_value(&frame_id, EdgeLabel::Named(name.into(), 0), value);
+            }
+        }
+        for (id, object) in dump.objects {
+            graph.add_object(id, object);
+        }
+        graph
+    }
+
+    /// Inserts a node that represents an object.
+    fn add_object(&mut self, id: u64, object: ObjectDump) {
+        let object_id = PyStateNodeId::Object(id);
+        let type_class = if object.items.is_some() {
+            NodeTypeClass::Array
+        } else {
+            NodeTypeClass::Struct
+        };
+        self.nodes.insert(
+            object_id.clone(),
+            PyStateNode::new(type_class, Some(object.type_name)),
+        );
+        if let Some(items) = object.items {
+            let mut length_node = PyStateNode::new(NodeTypeClass::Atom, None);
+            length_node.value = Some(NodeValue::Uint(items.len() as u64));
+            self.nodes.insert(PyStateNodeId::Length(id), length_node);
+            self.add_successor(&object_id, EdgeLabel::Length, PyStateNodeId::Length(id));
+            for (i, item) in items.into_iter().enumerate() {
+                self.add_value(&object_id, EdgeLabel::Index(i), item);
+            }
+        }
+        for (name, value) in object.fields.into_iter().flatten() {
+            // Keys that are not strings are named by their `repr`,
+            // so a key such as `1` can share its name with the key `'1'`
+            let discriminator = self.count_named_successors(&object_id, &name);
+            self.add_value(
+                &object_id,
+                EdgeLabel::Named(name.into(), discriminator),
+                value,
+            );
+        }
+    }
+
+    /// Counts the outgoing edges of a node that have a given name.
+    fn count_named_successors(&self, node: &PyStateNodeId, name: &str) -> usize {
+        self.nodes.get(node).map_or(0, |node| {
+            node.successors
+                .iter()
+                .filter(|(edge, _)| matches!(edge, EdgeLabel::Named(n, _) if n.as_str() == name))
+                .count()
+        })
+    }
+
+    /// Inserts a node that represents a value held by another node
+    /// and connects it to its holder.
+    fn add_value(&mut self, holder: &PyStateNodeId, edge: EdgeLabel, value: ValueDump) {
+        let value_id = PyStateNodeId::Value(holder.clone().into(), edge.clone());
+        let node = match value {
+            ValueDump::Atom { atom, value } => {
+                let mut node = PyStateNode::new(NodeTypeClass::Atom, Some(atom));
+                node.value = value.map(NodeValue::Int);
+                node
+            }
+            ValueDump::Ref { target } => {
+                let mut node = PyStateNode::new(NodeTypeClass::Ref, None);
+                node.successors
+                    .push((EdgeLabel::Deref, PyStateNodeId::Object(target)));
+                node
+            }
+        };
+        self.nodes.insert(value_id.clone(), node);
+        self.add_successor(holder, edge, value_id);
+    }
+
+    /// Adds an outgoing edge to an existing node.
+    fn add_successor(&mut self, node: &PyStateNodeId, edge: EdgeLabel, target: PyStateNodeId) {
+        if let Some(node) = self.nodes.get_mut(node) {
+            node.successors.push((edge, target));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dump::FrameDump;
+
+    #[test]
+    fn shared_object_is_represented_once() {
+        let dump = StateDump {
+            frames: vec![FrameDump {
+                name: "<module>".to_owned(),
+                file: None,
+                line: None,
+                variables: vec![
+                    ("a".to_owned(), ValueDump::Ref { target: 1 }),
+                    ("b".to_owned(), ValueDump::Ref { target: 1 }),
+                ],
+            }],
+            objects: vec![(
+                1,
+                ObjectDump {
+                    type_name: "list".to_owned(),
+                    items: Some(Vec::new()),
+                    fields: None,
+                },
+            )],
+        };
+        let graph = PyStateGraph::from_dump(dump);
+        let a = graph
+            .get_at_root(&[
+                EdgeLabel::Main,
+                EdgeLabel::Named("a".into(), 0),
+                EdgeLabel::Deref,
+            ])
+            .unwrap();
+        let b = graph
+            .get_at_root(&[
+                EdgeLabel::Main,
+                EdgeLabel::Named("b".into(), 0),
+                EdgeLabel::Deref,
+            ])
+            .unwrap();
+        assert!(std::ptr::eq(a, b));
+        assert_eq!(a.node_type_class(), NodeTypeClass::Array);
+    }
+
+    #[test]
+    fn fields_with_same_name_are_told_apart() {
+        let dump = StateDump {
+            frames: vec![FrameDump {
+                name: "<module>".to_owned(),
+                file: None,
+                line: None,
+                variables: vec![("d".to_owned(), ValueDump::Ref { target: 1 })],
+            }],
+            objects: vec![(
+                1,
+                ObjectDump {
+                    type_name: "dict".to_owned(),
+                    items: None,
+                    fields: Some(vec![
+                        (
+                            "1".to_owned(),
+                            ValueDump::Atom {
+                                atom: "int".to_owned(),
+                                value: Some(2),
+                            },
+                        ),
+                        (
+                            "1".to_owned(),
+                            ValueDump::Atom {
+                                atom: "int".to_owned(),
+                                value: Some(3),
+                            },
+                        ),
+                    ]),
+                },
+            )],
+        };
+        let graph = PyStateGraph::from_dump(dump);
+        let field = |discriminator| {
+            graph
+                .get_at_root(&[
+                    EdgeLabel::Main,
+                    EdgeLabel::Named("d".into(), 0),
+                    EdgeLabel::Deref,
+                    EdgeLabel::Named("1".into(), discriminator),
+                ])
+                .and_then(|node| node.value())
+        };
+        assert_eq!(field(0), Some(NodeValue::Int(2)));
+        assert_eq!(field(1), Some(NodeValue::Int(3)));
+    }
+}
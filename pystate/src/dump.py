@@ -0,0 +1,102 @@
+# Captures the state of the debugged program and prints it
+# as a single line of JSON, preceded by a marker.
+#
+# This script is executed by the debugger in the context
+# of the debugged program, so it must not leave any names behind.
+# It is always executed in a fresh namespace.
+
+import bdb, cmd, collections, json, pdb, sys, types
+
+SKIPPED_FILES = {bdb.__file__, cmd.__file__, pdb.__file__}
+HIDDEN_TYPES = (type, types.ModuleType, types.FunctionType, types.BuiltinFunctionType)
+SEQUENCE_TYPES = (list, tuple, set, frozenset, collections.deque)
+INT_RANGE = range(-(2**63), 2**63)
+
+
+def is_user_frame(frame):
+    file_name = frame.f_code.co_filename
+    return not file_name.startswith("<") and file_name not in SKIPPED_FILES
+
+
+def is_visible_variable(name, value):
+    return not name.startswith("__") and not isinstance(value, HIDDEN_TYPES)
+
+
+def mangle(name, cls):
+    # Private slots are stored under the same mangled names as private attributes
+    class_name = cls.__name__.lstrip("_")
+    if name.startswith("__") and not name.endswith("__") and class_name:
+        return "_" + class_name + name
+    return name
+
+
+def slot_names(value):
+    names = []
+    for cls in type(value).__mro__:
+        slots = cls.__dict__.get("__slots__", ())
+        slots = [slots] if isinstance(slots, str) else slots
+        names.extend(mangle(name, cls) for name in slots)
+    return [name for name in names if name not in ("__dict__", "__weakref__")]
+
+
+def dump_state():
+    frames = []
+    frame = sys._getframe()
+    while frame is not None:
+        if is_user_frame(frame):
+            frames.append(frame)
+        frame = frame.f_back
+    frames.reverse()
+
+    objects = {}
+    pending = []
+
+    def dump_value(value):
+        if isinstance(value, bool):
+            return {"atom": "bool", "value": int(value)}
+        if isinstance(value, int):
+            return {"atom": "int", "value": value if value in INT_RANGE else None}
+        if value is None or isinstance(value, (float, complex, str, bytes)):
+            return {"atom": type(value).__name__, "value": None}
+        key = id(value)
+        if key not in objects:
+            objects[key] = None
+            pending.append(value)
+        return {"ref": key}
+
+    def dump_object(value):
+        type_name = type(value).__name__
+        if isinstance(value, SEQUENCE_TYPES):
+            return {"type": type_name, "items": [dump_value(v) for v in value]}
+        if isinstance(value, dict):
+            fields = [[k if isinstance(k, str) else repr(k), dump_value(v)] for k, v in value.items()]
+            return {"type": type_name, "fields": fields}
+        fields = [
+            [k, dump_value(getattr(value, k))] for k in slot_names(value) if hasattr(value, k)
+        ]
+        attributes = getattr(value, "__dict__", None)
+        if isinstance(attributes, dict):
+            fields.extend([k, dump_value(v)] for k, v in attributes.items())
+        return {"type": type_name, "fields": fields}
+
+    dumped_frames = []
+    for frame in frames:
+        variables = [
+            [k, dump_value(v)] for k, v in frame.f_locals.items() if is_visible_variable(k, v)
+        ]
+        dumped_frames.append(
+            {
+                "name": frame.f_code.co_name,
+                "file": frame.f_code.co_filename,
+                "line": frame.f_lineno,
+                "variables": variables,
+            }
+        )
+    while pending:
+        value = pending.pop()
+        objects[id(value)] = dump_object(value)
+
+    return {"frames": dumped_frames, "objects": [[k, v] for k, v in objects.items()]}
+
+
+print(MARKER + json.dumps(dump_state()))
@@ -0,0 +1,87 @@
+//! Serialized snapshot of a Python program's state,
+//! as reported by the debugger.
+
+use serde::Deserialize;
+
+/// Snapshot of the state of a Python program.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize)]
+pub struct StateDump {
+    /// Stack frames of the program, starting with the outermost one.
+    pub frames: Vec<FrameDump>,
+
+    /// All objects that are reachable from the stack frames,
+    /// identified by their [`id`](https://docs.python.org/3/library/functions.html#id).
+    pub objects: Vec<(u64, ObjectDump)>,
+}
+
+/// Snapshot of a stack frame.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+pub struct FrameDump {
+    /// Name of the function whose call created the frame.
+    ///
+    /// The frame of the program's main module is called `<module>`.
+    pub name: String,
+
+    /// Name of the source file of the function.
+    #[serde(default)]
+    pub file: Option<String>,
+
+    /// Number of the line that is being executed in the frame.
+    #[serde(default)]
+    pub line: Option<u64>,
+
+    /// Local variables of the frame.
+    pub variables: Vec<(String, ValueDump)>,
+}
+
+/// Snapshot of an object stored in a variable, a container, or an attribute.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ValueDump {
+    /// Immutable elementary value, such as a number or a string.
+    ///
+    /// These are captured by value, as their identity
+    /// is generally not interesting.
+    Atom {
+        /// Name of the value's type.
+        atom: String,
+
+        /// Numeric value, if the value is an integer or a boolean
+        /// that fits into [`i64`].
+        value: Option<i64>,
+    },
+
+    /// Reference to an object that is captured separately.
+    Ref {
+        /// Identifier of the referenced object.
+        #[serde(rename = "ref")]
+        target: u64,
+    },
+}
+
+/// Snapshot of an object that may be referenced from multiple places.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize)]
+pub struct ObjectDump {
+    /// Name of the object's type.
+    #[serde(rename = "type")]
+    pub type_name: String,
+
+    /// Items of the object, if it is a sequence or a set.
+    pub items: Option<Vec<ValueDump>>,
+
+    /// Named members of the object, if it is a mapping
+    /// or an instance with attributes, including attributes
+    /// stored in [slots](https://docs.python.org/3/reference/datamodel.html#slots).
+    ///
+    /// Keys of mappings that are not strings are represented
+    /// by their `repr`, so several fields may share a name.
+    pub fields: Option<Vec<(String, ValueDump)>>,
+}
+
+/// Source code of the script that captures the state of a program
+/// in the debugger.
+///
+/// The script expects a global variable named `MARKER` to be defined.
+/// It prints the value of the marker immediately followed by
+/// the JSON representation of a [`StateDump`].
+pub const DUMP_SCRIPT: &str = include_str!("dump.py");
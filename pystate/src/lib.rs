@@ -0,0 +1,6 @@
+#![doc = include_str!("../README.md")]
+
+mod construct;
+pub mod dump;
+pub mod pdb;
+pub mod state;
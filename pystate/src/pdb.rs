@@ -0,0 +1,95 @@
+//! Interoperability with [pdb](https://docs.python.org/3/library/pdb.html).
+
+use crate::dump::{DUMP_SCRIPT, StateDump};
+use derive_more::{Display, Error, From};
+
+/// Low level interface to pdb that communicates using literal strings.
+pub trait PdbStream {
+    /// Sends a command to pdb.
+    ///
+    /// The command must be a single line
+    /// in the [pdb command syntax](https://docs.python.org/3/library/pdb.html#debugger-commands).
+    ///
+    /// The returned string is all output that pdb printed
+    /// in response to the command, up to the next prompt.
+    fn send_command(&mut self, command: &str) -> impl Future<Output = std::io::Result<String>>;
+}
+
+/// Exposes operations that inspect the debugged program.
+pub trait PdbSession {
+    /// Captures the state of the whole program.
+    fn dump_state(&mut self) -> impl Future<Output = Result<StateDump>>;
+}
+
+impl<T: PdbStream> PdbSession for T {
+    async fn dump_state(&mut self) -> Result<StateDump> {
+        let command = format!(
+            "!exec({}, {{'MARKER': {}}})",
+            python_string_literal(DUMP_SCRIPT),
+            python_string_literal(DUMP_MARKER),
+        );
+        let output = self.send_command(&command).await?;
+        let Some(json) = output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(DUMP_MARKER))
+        else {
+            return Err(Error::MissingDump(output));
+        };
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Text that precedes a state dump in the debugger's output.
+const DUMP_MARKER: &str = "@aili-dump:";
+
+/// Converts a string to a Python string literal that evaluates to it.
+fn python_string_literal(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            _ => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Result type for operations that involve communication with pdb.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Describes an error in communication with pdb.
+#[derive(Debug, Display, Error, From)]
+pub enum Error {
+    /// IO error.
+    #[display("io error: {_0}")]
+    IOError(std::io::Error),
+
+    /// Pdb did not respond with a state dump.
+    ///
+    /// The full response is included.
+    #[display("response did not contain a state dump: {_0:?}")]
+    #[from(ignore)]
+    #[error(ignore)]
+    MissingDump(String),
+
+    /// Pdb responded with a state dump that could not be parsed.
+    #[display("failed to parse state dump: {_0}")]
+    BadDump(serde_json::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn string_literal_escapes_special_characters() {
+        let literal = python_string_literal("a \"b\"\n\\c");
+        assert_eq!(literal, r#""a \"b\"\n\\c""#);
+    }
+}
@@ -0,0 +1,124 @@
+//! Implementation of [`ProgramStateGraph`] backed by a pdb session.
+
+use aili_model::{
+    capability::{self, CapabilitySet},
+    state::*,
+};
+use derive_more::Debug;
+use std::collections::HashMap;
+
+/// Identifiers of state nodes used by [`PyStateGraph`].
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum PyStateNodeId {
+    /// Identifier of the root node.
+    #[debug("::")]
+    Root,
+
+    /// Identifier of a stack frame node.
+    #[debug("frame({_0})")]
+    Frame(usize),
+
+    /// Identifier of a node that represents a Python object
+    /// with a given [`id`](https://docs.python.org/3/library/functions.html#id).
+    #[debug("obj({_0:#x})")]
+    Object(u64),
+
+    /// Identifier of the [`EdgeLabel::Length`] pseudo-node
+    /// associated with a [`PyStateNodeId::Object`] node.
+    #[debug("obj({_0:#x}) len")]
+    Length(u64),
+
+    /// Identifier of a node that represents a value
+    /// stored in a variable, attribute, or item of another node.
+    ///
+    /// Values are identified by the node that holds them
+    /// and the label of the edge that leads to them.
+    #[debug("{_0:?} -> {_1:?}")]
+    Value(Box<PyStateNodeId>, EdgeLabel),
+}
+
+/// Implementation of a [`ProgramStateGraph`] backed by a pdb session.
+///
+/// Python variables, items, and attributes that hold numbers,
+/// strings, and other immutable values are represented
+/// by [`NodeTypeClass::Atom`] nodes. All other values are represented
+/// by [`NodeTypeClass::Ref`] nodes that point to a shared node
+/// that represents the referenced object, so objects that are
+/// referenced from multiple places are only present once.
+#[derive(Debug, Default)]
+pub struct PyStateGraph {
+    pub(crate) nodes: HashMap<PyStateNodeId, PyStateNode>,
+}
+
+impl ProgramStateGraph for PyStateGraph {
+    type NodeId = PyStateNodeId;
+    type NodeRef<'a>
+        = &'a PyStateNode
+    where
+        Self: 'a;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        self.nodes.get(id)
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        CapabilitySet::from_iter([capability::PYTHON, capability::ARRAYS, capability::POINTERS])
+    }
+}
+
+impl RootedProgramStateGraph for PyStateGraph {
+    fn root(&self) -> Self::NodeId {
+        PyStateNodeId::Root
+    }
+}
+
+/// Node of a [`PyStateGraph`].
+#[derive(Debug)]
+pub struct PyStateNode {
+    pub(crate) type_class: NodeTypeClass,
+    pub(crate) type_name: Option<String>,
+    pub(crate) successors: Vec<(EdgeLabel, PyStateNodeId)>,
+    pub(crate) value: Option<NodeValue>,
+    pub(crate) source_location: Option<SourceLocation>,
+}
+
+impl PyStateNode {
+    /// Constructs a new node with no successors or value.
+    pub(crate) fn new(type_class: NodeTypeClass, type_name: Option<String>) -> Self {
+        Self {
+            type_class,
+            type_name,
+            successors: Vec::new(),
+            value: None,
+            source_location: None,
+        }
+    }
+}
+
+impl ProgramStateNode for &PyStateNode {
+    type NodeId = PyStateNodeId;
+    type NodeTypeId<'a>
+        = &'a str
+    where
+        Self: 'a;
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
+        self.successors
+            .iter()
+            .find(|(e, _)| *e == *edge)
+            .map(|(_, n)| n)
+            .cloned()
+    }
+    fn node_type_class(&self) -> NodeTypeClass {
+        self.type_class
+    }
+    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
+        self.type_name.as_deref()
+    }
+    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
+        self.successors.iter().map(|(e, n)| (e, n.clone()))
+    }
+    fn value(&self) -> Option<NodeValue> {
+        self.value.clone()
+    }
+    fn source_location(&self) -> Option<SourceLocation> {
+        self.source_location.clone()
+    }
+}
@@ -0,0 +1,222 @@
+mod utils;
+
+use aili_model::state::*;
+use aili_pystate::state::PyStateGraph;
+use utils::{future::ExpectReady as _, pdb_from_source};
+
+#[test]
+fn minimal_sample_program() {
+    let mut pdb = pdb_from_source("pass\n");
+    let state_graph = PyStateGraph::new(&mut pdb)
+        .expect_ready()
+        .expect("Could not construct state graph");
+    let module = state_graph
+        .get_at_root(&[EdgeLabel::Main])
+        .expect("Module frame should be present");
+    assert_eq!(module.node_type_class(), NodeTypeClass::Frame);
+    assert_eq!(module.node_type_id(), Some("<module>"));
+    assert_eq!(module.value(), None);
+}
+
+#[test]
+fn basic_global_variable() {
+    let mut pdb = pdb_from_source(
+        r"x = 42
+pass
+",
+    );
+    pdb.run_to_line(2).unwrap();
+    let state_graph = PyStateGraph::new(&mut pdb).expect_ready().unwrap();
+    let x = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("x".into(), 0)])
+        .unwrap();
+    assert_eq!(x.node_type_class(), NodeTypeClass::Atom);
+    assert_eq!(x.node_type_id(), Some("int"));
+    assert_eq!(x.value(), Some(NodeValue::Int(42)));
+}
+
+#[test]
+fn function_call_and_local_variable() {
+    let mut pdb = pdb_from_source(
+        r"def f(a):
+    b = a + 1
+    return b
+f(1)
+",
+    );
+    pdb.run_to_line(3).unwrap();
+    let state_graph = PyStateGraph::new(&mut pdb).expect_ready().unwrap();
+    let f = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Next])
+        .unwrap();
+    assert_eq!(f.node_type_class(), NodeTypeClass::Frame);
+    assert_eq!(f.node_type_id(), Some("f"));
+    let b = state_graph
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Next,
+            EdgeLabel::Named("b".into(), 0),
+        ])
+        .unwrap();
+    assert_eq!(b.value(), Some(NodeValue::Int(2)));
+    // Functions are not shown as variables
+    assert!(
+        state_graph
+            .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("f".into(), 0)])
+            .is_none()
+    );
+}
+
+#[test]
+fn shared_list_is_referenced() {
+    let mut pdb = pdb_from_source(
+        r"a = [1, 2, 3]
+b = a
+pass
+",
+    );
+    pdb.run_to_line(3).unwrap();
+    let state_graph = PyStateGraph::new(&mut pdb).expect_ready().unwrap();
+    let a_ref = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Named("a".into(), 0)])
+        .unwrap();
+    assert_eq!(a_ref.node_type_class(), NodeTypeClass::Ref);
+    let a_id = state_graph
+        .get_id_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("a".into(), 0),
+            EdgeLabel::Deref,
+        ])
+        .unwrap();
+    let b_id = state_graph
+        .get_id_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("b".into(), 0),
+            EdgeLabel::Deref,
+        ])
+        .unwrap();
+    assert_eq!(a_id, b_id);
+    let list = state_graph.get(&a_id).unwrap();
+    assert_eq!(list.node_type_class(), NodeTypeClass::Array);
+    assert_eq!(list.node_type_id(), Some("list"));
+    let length = state_graph
+        .get(&list.get_successor(&EdgeLabel::Length).unwrap())
+        .unwrap();
+    assert_eq!(length.value(), Some(NodeValue::Uint(3)));
+    let item = state_graph
+        .get(&list.get_successor(&EdgeLabel::Index(2)).unwrap())
+        .unwrap();
+    assert_eq!(item.value(), Some(NodeValue::Int(3)));
+}
+
+#[test]
+fn object_attributes() {
+    let mut pdb = pdb_from_source(
+        r"class Point:
+    def __init__(self, x):
+        self.x = x
+p = Point(5)
+pass
+",
+    );
+    pdb.run_to_line(5).unwrap();
+    let state_graph = PyStateGraph::new(&mut pdb).expect_ready().unwrap();
+    let point = state_graph
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("p".into(), 0),
+            EdgeLabel::Deref,
+        ])
+        .unwrap();
+    assert_eq!(point.node_type_class(), NodeTypeClass::Struct);
+    assert_eq!(point.node_type_id(), Some("Point"));
+    let x = state_graph
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("p".into(), 0),
+            EdgeLabel::Deref,
+            EdgeLabel::Named("x".into(), 0),
+        ])
+        .unwrap();
+    assert_eq!(x.value(), Some(NodeValue::Int(5)));
+}
+
+#[test]
+fn private_slots() {
+    let mut pdb = pdb_from_source(
+        r"class Point:
+    __slots__ = ('__x',)
+    def __init__(self, x):
+        self.__x = x
+p = Point(5)
+pass
+",
+    );
+    pdb.run_to_line(6).unwrap();
+    let state_graph = PyStateGraph::new(&mut pdb).expect_ready().unwrap();
+    let x = state_graph
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("p".into(), 0),
+            EdgeLabel::Deref,
+            EdgeLabel::Named("_Point__x".into(), 0),
+        ])
+        .unwrap();
+    assert_eq!(x.value(), Some(NodeValue::Int(5)));
+}
+
+#[test]
+fn frames_have_source_locations() {
+    let mut pdb = pdb_from_source(
+        r"def f():
+    pass
+f()
+",
+    );
+    pdb.run_to_line(2).unwrap();
+    let state_graph = PyStateGraph::new(&mut pdb).expect_ready().unwrap();
+    let module = state_graph.get_at_root(&[EdgeLabel::Main]).unwrap();
+    let f = state_graph
+        .get_at_root(&[EdgeLabel::Main, EdgeLabel::Next])
+        .unwrap();
+    assert_eq!(
+        module.source_location().map(|location| location.line),
+        Some(3)
+    );
+    assert_eq!(f.source_location().map(|location| location.line), Some(2));
+    assert!(f.source_location().unwrap().file.ends_with(".py"));
+}
+
+#[test]
+fn sets_and_slots() {
+    let mut pdb = pdb_from_source(
+        r"class Point:
+    __slots__ = ('x',)
+    def __init__(self, x):
+        self.x = x
+s = {7}
+p = Point(5)
+pass
+",
+    );
+    pdb.run_to_line(7).unwrap();
+    let state_graph = PyStateGraph::new(&mut pdb).expect_ready().unwrap();
+    let set = state_graph
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("s".into(), 0),
+            EdgeLabel::Deref,
+        ])
+        .unwrap();
+    assert_eq!(set.node_type_class(), NodeTypeClass::Array);
+    assert_eq!(set.node_type_id(), Some("set"));
+    let x = state_graph
+        .get_at_root(&[
+            EdgeLabel::Main,
+            EdgeLabel::Named("p".into(), 0),
+            EdgeLabel::Deref,
+            EdgeLabel::Named("x".into(), 0),
+        ])
+        .unwrap();
+    assert_eq!(x.value(), Some(NodeValue::Int(5)));
+}
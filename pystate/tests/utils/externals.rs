@@ -0,0 +1,59 @@
+//! Use of external processes in tests.
+
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    path::PathBuf,
+    sync::LazyLock,
+};
+
+/// Exposes a value that is loaded from an environment variable
+/// or a default value is used.
+macro_rules! lazy_env_or_default {
+    ( $( #[ $attr:meta ] )* $vis:vis $get:ident = $env:literal | $default:expr $(;)? ) => {
+        $( #[ $attr ] )*
+        $vis fn $get() -> &'static str {
+            fn construct() -> String {
+                std::env::var($env).unwrap_or_else(|_| $default.to_string())
+            }
+            static LAZY_VAL: LazyLock<String> = LazyLock::new(construct);
+            &LAZY_VAL
+        }
+    };
+}
+
+lazy_env_or_default! {
+    /// Path to the Python interpreter executable.
+    pub python_path = "PYTHON_PATH" | "python3";
+}
+
+/// Builds a hex string from the hash of a value.
+fn hex_hash<T: Hash + ?Sized>(x: &T) -> String {
+    let mut hasher = DefaultHasher::new();
+    Hash::hash(x, &mut hasher);
+    let hash = hasher.finish();
+    format!("{hash:x}")
+}
+
+/// Gets the path to a temorary directory for storing test artifacts
+/// and creates the directory if needed.
+fn temporary_directory() -> Result<PathBuf, std::io::Error> {
+    let tmp = std::env::temp_dir();
+    let pid = std::process::id();
+    let my_tmp_dir = tmp.join(pid.to_string());
+    if let Err(err) = std::fs::create_dir(&my_tmp_dir) {
+        // If the path already exists and is a directory, then it is actually fine
+        // Otherwise we fail with the returned error
+        if !my_tmp_dir.is_dir() {
+            return Err(err);
+        }
+    }
+    Ok(my_tmp_dir)
+}
+
+/// Saves a Python source to a script in a temporary directory
+/// and returns the path to the script.
+pub fn save_python(source: &str) -> Result<PathBuf, std::io::Error> {
+    let my_tmp_script = temporary_directory()?.join(hex_hash(&source) + ".py");
+    std::fs::write(&my_tmp_script, source)?;
+    Ok(my_tmp_script)
+}
@@ -0,0 +1,15 @@
+//! Common utilities for use in integration tests.
+
+pub mod externals;
+pub mod future;
+pub mod pdb;
+
+use externals::save_python;
+use pdb::TestPdb;
+
+/// Saves a Python source and starts a pdb session
+/// that debugs it.
+pub fn pdb_from_source(source: &str) -> TestPdb {
+    let script = save_python(source).expect("Could not save script");
+    TestPdb::new(script).expect("Could not start pdb")
+}
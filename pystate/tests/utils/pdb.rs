@@ -0,0 +1,87 @@
+//! Testing implementation of [`PdbStream`] that communicates
+//! with the debugger synchronously.
+
+use super::externals::python_path;
+use aili_pystate::pdb::PdbStream;
+use std::{
+    io::{BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+pub struct TestPdb {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl TestPdb {
+    pub fn new(script_path: impl AsRef<std::ffi::OsStr>) -> std::io::Result<Self> {
+        let mut pdb = Self::spawn_pdb(script_path)?;
+        let stdin = pdb
+            .stdin
+            .take()
+            .expect("Pdb was spawned with piped IO, so file descriptors should exist");
+        let stdout = pdb
+            .stdout
+            .take()
+            .expect("Pdb was spawned with piped IO, so file descriptors should exist");
+        let mut instance = Self {
+            stdin,
+            stdout: BufReader::new(stdout),
+        };
+        instance.read_output_section()?; // Pdb stops before the first line
+        Ok(instance)
+    }
+
+    fn spawn_pdb(script_path: impl AsRef<std::ffi::OsStr>) -> std::io::Result<Child> {
+        Command::new(python_path())
+            .arg("-m")
+            .arg("pdb")
+            .arg(script_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+    }
+
+    fn send_line(&mut self, command: &str) -> std::io::Result<()> {
+        self.stdin.write_all(command.as_bytes())?;
+        self.stdin.write_all(b"\n")?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn read_output_section(&mut self) -> std::io::Result<String> {
+        let mut output = Vec::new();
+        let mut byte = [0];
+        while !output.ends_with(Self::PROMPT.as_bytes()) {
+            if self.stdout.read(&mut byte)? == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            output.push(byte[0]);
+        }
+        output.truncate(output.len() - Self::PROMPT.len());
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    pub fn run_to_line(&mut self, line: usize) -> std::io::Result<()> {
+        self.send_line(&format!("tbreak {line}"))?;
+        self.read_output_section()?;
+        self.send_line("continue")?;
+        self.read_output_section()?;
+        Ok(())
+    }
+
+    const PROMPT: &str = "(Pdb) ";
+}
+
+impl PdbStream for TestPdb {
+    async fn send_command(&mut self, command: &str) -> std::io::Result<String> {
+        self.send_line(command)?;
+        self.read_output_section()
+    }
+}
+
+impl Drop for TestPdb {
+    fn drop(&mut self) {
+        let _ = self.send_line("quit");
+    }
+}
@@ -0,0 +1,97 @@
+//! [`StateProvider`] that debugs a program with GDB.
+
+#![cfg(feature = "gdbstate")]
+
+use crate::session::StateProvider;
+use aili_gdbstate::{
+    gdbmi::{result::Error, stream::GdbMiStream},
+    hints::PointerLengthHintKey,
+    state::GdbStateGraph,
+};
+use aili_style::cascade::CascadeStyle;
+use std::{
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake},
+    thread::Thread,
+};
+
+/// [`StateProvider`] that constructs a [`GdbStateGraph`]
+/// from a GDB/MI session.
+pub struct GdbStateProvider<T: GdbMiStream> {
+    gdb: T,
+    graph: GdbStateGraph,
+    pointer_hints: CascadeStyle<PointerLengthHintKey>,
+}
+
+impl<T: GdbMiStream> GdbStateProvider<T> {
+    /// Constructs a new provider and the initial state graph.
+    ///
+    /// The session is driven synchronously, so the stream
+    /// should not depend on an external executor to make progress.
+    pub fn new(
+        mut gdb: T,
+        pointer_hints: CascadeStyle<PointerLengthHintKey>,
+    ) -> Result<Self, Error> {
+        let graph = block_on(GdbStateGraph::new_with_hints(&mut gdb, &pointer_hints))?;
+        Ok(Self {
+            gdb,
+            graph,
+            pointer_hints,
+        })
+    }
+
+    /// Erases all variable objects created by the provider
+    /// and returns the underlying GDB/MI session.
+    pub fn into_session(mut self) -> Result<T, Error> {
+        block_on(self.graph.drop_variable_objects(&mut self.gdb))?;
+        Ok(self.gdb)
+    }
+}
+
+impl<T: GdbMiStream> StateProvider for GdbStateProvider<T> {
+    type Graph = GdbStateGraph;
+    type Error = Error;
+
+    fn graph(&self) -> &Self::Graph {
+        &self.graph
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        block_on(
+            self.graph
+                .update_with_hints(&mut self.gdb, &self.pointer_hints),
+        )
+    }
+
+    /// Sends a GDB/MI command to the session.
+    ///
+    /// Returns the class of the result record.
+    fn execute(&mut self, command: &str) -> Result<String, Self::Error> {
+        let record = block_on(self.gdb.send_command(command))?;
+        let result_class = record.result_class.to_string();
+        record.must_be_done_or_running()?;
+        Ok(result_class)
+    }
+}
+
+/// Runs a future to completion on the current thread.
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => break output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
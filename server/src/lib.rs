@@ -0,0 +1,8 @@
+#![doc = include_str!("../README.md")]
+
+pub mod gdb;
+pub use aili_translate::mutation;
+pub mod rpc;
+pub mod server;
+pub mod session;
+pub mod timeline;
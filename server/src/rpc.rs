@@ -0,0 +1,187 @@
+//! [JSON-RPC 2.0](https://www.jsonrpc.org/specification)
+//! interface to a [`Session`].
+//!
+//! The following methods are available:
+//!
+//! | Method          | Parameters                   | Result                                        |
+//! |-----------------|------------------------------|-----------------------------------------------|
+//! | `snapshot`      |                              | `{ mutations }` that build the visualization  |
+//! | `setStylesheet` | `{ stylesheet }`             | `{ mutations }` caused by the new stylesheet  |
+//! | `update`        |                              | `{ mutations }` caused by the update          |
+//! | `execute`       | `{ command }`                | `{ response, mutations }`                     |
+//! | `events`        |                              | `{ events }` recorded in the timeline         |
+//! | `replay`        | `{ count }`                  | `{ mutations }` that replay `count` events    |
+//!
+//! Stylesheets are passed in the serialized form
+//! of [`Stylesheet`](aili_style::stylesheet::Stylesheet).
+//! A stylesheet that requires capabilities the state graph
+//! does not provide is rejected with [`INCOMPATIBLE_STYLESHEET`].
+//! Mutations are serialized [`Mutation`](crate::mutation::Mutation)s
+//! and events are serialized [`SessionEvent`](crate::timeline::SessionEvent)s.
+//! Replaying events against a fresh visualization tree restores
+//! the visualization as it was after the last replayed event.
+
+use crate::session::{Session, StateProvider};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// Error code for messages that are not valid JSON.
+pub const PARSE_ERROR: i64 = -32700;
+
+/// Error code for messages that are not valid requests.
+pub const INVALID_REQUEST: i64 = -32600;
+
+/// Error code for requests of unknown methods.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Error code for requests with invalid parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// Error code for failures of the [`StateProvider`].
+pub const PROVIDER_ERROR: i64 = -32000;
+
+/// Error code for stylesheets that require capabilities
+/// that the state graph does not provide.
+pub const INCOMPATIBLE_STYLESHEET: i64 = -32001;
+
+/// Request sent by a client.
+#[derive(Deserialize)]
+struct Request {
+    /// Identifier of the request.
+    /// Notifications, which have no identifier, do not get a response.
+    id: Option<Value>,
+
+    /// Name of the invoked method.
+    method: String,
+
+    /// Parameters of the method.
+    #[serde(default)]
+    params: Value,
+}
+
+/// Response sent to a client.
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+/// Outcome of a request.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Outcome {
+    /// The request was successful.
+    Result(Value),
+
+    /// The request failed.
+    Error(ErrorObject),
+}
+
+/// Description of a failed request.
+#[derive(Serialize)]
+struct ErrorObject {
+    code: i64,
+    message: String,
+}
+
+impl Outcome {
+    fn error(code: i64, message: impl ToString) -> Self {
+        Self::Error(ErrorObject {
+            code,
+            message: message.to_string(),
+        })
+    }
+}
+
+/// Processes a single message received from a client.
+///
+/// Returns the serialized response that should be sent back,
+/// or [`None`] if the message was a notification.
+pub fn handle_message<P: StateProvider>(session: &mut Session<P>, message: &str) -> Option<String> {
+    let request = match serde_json::from_str::<Value>(message) {
+        Ok(value) => value,
+        Err(e) => {
+            return Some(serialize_response(
+                Value::Null,
+                Outcome::error(PARSE_ERROR, e),
+            ));
+        }
+    };
+    let request = match serde_json::from_value::<Request>(request) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(serialize_response(
+                Value::Null,
+                Outcome::error(INVALID_REQUEST, e),
+            ));
+        }
+    };
+    let outcome = dispatch(session, &request.method, request.params);
+    request.id.map(|id| serialize_response(id, outcome))
+}
+
+/// Invokes a method on the session.
+fn dispatch<P: StateProvider>(session: &mut Session<P>, method: &str, params: Value) -> Outcome {
+    match method {
+        "snapshot" => Outcome::Result(json!({ "mutations": session.snapshot() })),
+        "setStylesheet" => {
+            #[derive(Deserialize)]
+            struct Params {
+                stylesheet: aili_style::stylesheet::Stylesheet,
+            }
+            let params = match serde_json::from_value::<Params>(params) {
+                Ok(params) => params,
+                Err(e) => return Outcome::error(INVALID_PARAMS, e),
+            };
+            match session.set_stylesheet(params.stylesheet) {
+                Ok(mutations) => Outcome::Result(json!({ "mutations": mutations })),
+                Err(e) => Outcome::error(INCOMPATIBLE_STYLESHEET, e),
+            }
+        }
+        "update" => match session.update() {
+            Ok(mutations) => Outcome::Result(json!({ "mutations": mutations })),
+            Err(e) => Outcome::error(PROVIDER_ERROR, e),
+        },
+        "execute" => {
+            #[derive(Deserialize)]
+            struct Params {
+                command: String,
+            }
+            let params = match serde_json::from_value::<Params>(params) {
+                Ok(params) => params,
+                Err(e) => return Outcome::error(INVALID_PARAMS, e),
+            };
+            match session.execute(&params.command) {
+                Ok((response, mutations)) => {
+                    Outcome::Result(json!({ "response": response, "mutations": mutations }))
+                }
+                Err(e) => Outcome::error(PROVIDER_ERROR, e),
+            }
+        }
+        "events" => Outcome::Result(json!({ "events": session.timeline().events() })),
+        "replay" => {
+            #[derive(Deserialize)]
+            struct Params {
+                count: usize,
+            }
+            match serde_json::from_value::<Params>(params) {
+                Ok(params) => Outcome::Result(json!({
+                    "mutations": session.timeline().replay(params.count).collect::<Vec<_>>()
+                })),
+                Err(e) => Outcome::error(INVALID_PARAMS, e),
+            }
+        }
+        _ => Outcome::error(METHOD_NOT_FOUND, format!("unknown method {method:?}")),
+    }
+}
+
+fn serialize_response(id: Value, outcome: Outcome) -> String {
+    let response = Response {
+        jsonrpc: "2.0",
+        id,
+        outcome,
+    };
+    serde_json::to_string(&response).expect("Responses should always serialize")
+}
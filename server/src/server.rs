@@ -0,0 +1,74 @@
+//! WebSocket transport for the [JSON-RPC interface](crate::rpc).
+
+use crate::{
+    rpc::handle_message,
+    session::{Session, StateProvider},
+};
+use derive_more::{Display, Error, From};
+use std::net::{TcpListener, TcpStream};
+use tungstenite::{HandshakeError, Message};
+
+/// Result type of server operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error type of server operations.
+#[derive(Debug, Display, Error, From)]
+pub enum Error {
+    /// Error reported by the underlying socket.
+    #[display("io error: {_0}")]
+    IOError(std::io::Error),
+
+    /// Error in the WebSocket protocol.
+    #[display("websocket error: {_0}")]
+    WebSocket(Box<tungstenite::Error>),
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(value: tungstenite::Error) -> Self {
+        Box::new(value).into()
+    }
+}
+
+/// Serves a session to clients that connect to a listener.
+///
+/// Clients are served one at a time, in the order in which
+/// they connect. All clients share the same session, so a client
+/// that connects later sees the visualization as the previous
+/// client has left it, and can request it with the `snapshot` method.
+///
+/// This function only returns if accepting a connection fails.
+/// Errors in individual connections only terminate the connection.
+pub fn serve<P: StateProvider>(listener: &TcpListener, session: &mut Session<P>) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept()?;
+        // A broken connection should not take the server down
+        let _ = serve_connection(stream, session);
+    }
+}
+
+/// Serves a session to a single client until it disconnects.
+pub fn serve_connection<P: StateProvider>(
+    stream: TcpStream,
+    session: &mut Session<P>,
+) -> Result<()> {
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(HandshakeError::Failure(e)) => return Err(e.into()),
+        Err(HandshakeError::Interrupted(_)) => {
+            // Only happens with non-blocking streams
+            return Err(std::io::Error::from(std::io::ErrorKind::WouldBlock).into());
+        }
+    };
+    loop {
+        let message = match socket.read() {
+            Ok(message) => message,
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        if let Message::Text(text) = message
+            && let Some(response) = handle_message(session, &text)
+        {
+            socket.send(Message::Text(response))?;
+        }
+    }
+}
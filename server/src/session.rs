@@ -0,0 +1,223 @@
+//! Visualization session that ties a state graph,
+//! a stylesheet, and a recorded visualization tree together.
+
+use crate::{
+    mutation::{Mutation, MutationRecorder},
+    timeline::{SessionEventKind, Timeline},
+};
+use aili_model::state::{ProgramStateGraph, RootedProgramStateGraph};
+use aili_style::{
+    cascade::CascadeStyle,
+    selectable::Selectable,
+    stylesheet::{MissingCapabilities, Stylesheet},
+};
+use aili_translate::{
+    cascade::{
+        LifetimeTracker, apply_stylesheet_tracked_with_warnings,
+        reapply_stylesheet_tracked_with_warnings,
+    },
+    forward::VisTreeWriter,
+    property::{PropertyKey, typed::AttributeTypeMismatch},
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// Source of a program state graph that a [`Session`] visualizes.
+pub trait StateProvider {
+    /// Type of the state graph.
+    type Graph: RootedProgramStateGraph;
+
+    /// Error type of operations on the provider.
+    type Error: std::error::Error;
+
+    /// Gets the current state graph.
+    fn graph(&self) -> &Self::Graph;
+
+    /// Brings the state graph up to date with the debuggee.
+    fn update(&mut self) -> Result<(), Self::Error>;
+
+    /// Executes a provider-specific command, such as a step
+    /// in the debuggee. The state graph does not need to be
+    /// updated by this operation, [`StateProvider::update`]
+    /// is invoked separately.
+    ///
+    /// Returns a human-readable response to the command.
+    fn execute(&mut self, command: &str) -> Result<String, Self::Error>;
+}
+
+/// Visualization session hosted by a server.
+///
+/// The session renders the state graph of a [`StateProvider`]
+/// using a stylesheet. Changes to the visualization are reported
+/// as [`Mutation`]s that a frontend can apply to its own
+/// visualization tree.
+///
+/// Nodes are tracked between renders, so stylesheets can style
+/// nodes that have just been added or removed using the `:new`
+/// and `:removed` selectors. Replacing the stylesheet does not
+/// count as a change of the state graph, so nodes keep their lifetimes
+/// and visual elements whose display mode has not changed are kept.
+///
+/// All events of the session are recorded in a [`Timeline`].
+pub struct Session<P: StateProvider> {
+    provider: P,
+    stylesheet: CascadeStyle<PropertyKey>,
+    lifetimes: LifetimeTracker<<P::Graph as ProgramStateGraph>::NodeId>,
+    tracked: bool,
+    writer: VisTreeWriter<'static, <P::Graph as ProgramStateGraph>::NodeId, MutationRecorder>,
+    timeline: Timeline,
+    warnings: Rc<RefCell<Vec<String>>>,
+}
+
+impl<P: StateProvider> Session<P> {
+    /// Constructs a new session with an empty stylesheet.
+    pub fn new(provider: P) -> Self {
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let warning_sink = warnings.clone();
+        let writer = VisTreeWriter::new(MutationRecorder::new()).with_warning_handler(Box::new(
+            move |warning| warning_sink.borrow_mut().push(warning.to_string()),
+        ));
+        Self {
+            provider,
+            stylesheet: CascadeStyle::empty(),
+            lifetimes: LifetimeTracker::new(),
+            tracked: false,
+            writer,
+            timeline: Timeline::new(),
+            warnings,
+        }
+    }
+
+    /// Sets the stylesheet that the session starts with.
+    ///
+    /// Fails if the stylesheet requires capabilities
+    /// that the state graph does not provide.
+    pub fn with_stylesheet(mut self, stylesheet: Stylesheet) -> Result<Self, MissingCapabilities> {
+        self.stylesheet = self.compile_stylesheet(stylesheet)?;
+        Ok(self)
+    }
+
+    /// Gets the provider of the state graph.
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    /// Gets the provider of the state graph, mutably.
+    ///
+    /// Changes made through the provider are only reflected
+    /// in the visualization after [`Session::render`].
+    pub fn provider_mut(&mut self) -> &mut P {
+        &mut self.provider
+    }
+
+    /// Replaces the stylesheet and renders the state graph again.
+    ///
+    /// If the stylesheet requires capabilities that the state graph
+    /// does not provide, it is rejected and the current stylesheet is kept.
+    pub fn set_stylesheet(
+        &mut self,
+        stylesheet: Stylesheet,
+    ) -> Result<Vec<Mutation>, MissingCapabilities> {
+        self.stylesheet = self.compile_stylesheet(stylesheet)?;
+        Ok(self.render_and_record(SessionEventKind::StylesheetChange))
+    }
+
+    /// Updates the state graph and renders it.
+    pub fn update(&mut self) -> Result<Vec<Mutation>, P::Error> {
+        self.record_failure(|provider| provider.update())?;
+        Ok(self.render_and_record(SessionEventKind::Update))
+    }
+
+    /// Executes a command of the state provider,
+    /// then updates the state graph and renders it.
+    pub fn execute(&mut self, command: &str) -> Result<(String, Vec<Mutation>), P::Error> {
+        let response = self.record_failure(|provider| provider.execute(command))?;
+        self.record_failure(|provider| provider.update())?;
+        let mutations = self.render_and_record(SessionEventKind::Stop {
+            command: command.to_owned(),
+            response: response.clone(),
+        });
+        Ok((response, mutations))
+    }
+
+    /// Renders the current state graph using the current stylesheet.
+    ///
+    /// Returns the changes that have been made to the visualization.
+    pub fn render(&mut self) -> Vec<Mutation> {
+        self.render_and_record(SessionEventKind::Render)
+    }
+
+    /// Constructs a sequence of mutations that builds
+    /// the current visualization from scratch.
+    pub fn snapshot(&self) -> Vec<Mutation> {
+        self.writer.vis_tree().snapshot()
+    }
+
+    /// Gets the log of all events of the session.
+    pub fn timeline(&self) -> &Timeline {
+        &self.timeline
+    }
+
+    /// Renders the current state graph and records the changes
+    /// in the timeline, along with any warnings raised by the renderer.
+    ///
+    /// A change of the stylesheet is rendered with the lifetimes
+    /// from the previous render, since the state graph has not changed.
+    fn render_and_record(&mut self, kind: SessionEventKind) -> Vec<Mutation> {
+        let graph = self.provider.graph();
+        let warning_handler = |warning: AttributeTypeMismatch<_>| {
+            self.warnings.borrow_mut().push(warning.to_string())
+        };
+        let mapping = if self.tracked && kind == SessionEventKind::StylesheetChange {
+            reapply_stylesheet_tracked_with_warnings(
+                &self.stylesheet,
+                graph,
+                &self.lifetimes,
+                warning_handler,
+            )
+        } else {
+            self.tracked = true;
+            apply_stylesheet_tracked_with_warnings(
+                &self.stylesheet,
+                graph,
+                &mut self.lifetimes,
+                warning_handler,
+            )
+        };
+        self.writer
+            .update_roots(vec![Selectable::node(graph.root())]);
+        self.writer.update(mapping);
+        let mutations = self.writer.vis_tree_mut().take_mutations();
+        self.timeline.record(kind, mutations.clone());
+        for message in self.warnings.borrow_mut().drain(..) {
+            self.timeline
+                .record(SessionEventKind::Warning { message }, Vec::new());
+        }
+        mutations
+    }
+
+    /// Performs an operation on the provider and records
+    /// its failure in the timeline.
+    fn record_failure<T>(
+        &mut self,
+        operation: impl FnOnce(&mut P) -> Result<T, P::Error>,
+    ) -> Result<T, P::Error> {
+        operation(&mut self.provider).inspect_err(|e| {
+            self.timeline.record(
+                SessionEventKind::Error {
+                    message: e.to_string(),
+                },
+                Vec::new(),
+            )
+        })
+    }
+
+    /// Verifies that a stylesheet is compatible with the state graph
+    /// and prepares it for rendering.
+    fn compile_stylesheet(
+        &self,
+        stylesheet: Stylesheet,
+    ) -> Result<CascadeStyle<PropertyKey>, MissingCapabilities> {
+        stylesheet.check_requirements(&self.provider.graph().capabilities())?;
+        Ok(stylesheet.map_key().into())
+    }
+}
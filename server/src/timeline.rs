@@ -0,0 +1,134 @@
+//! Structured log of the events of a [`Session`](crate::session::Session)
+//! that can be replayed to reconstruct past visualizations.
+
+use crate::mutation::{Mutation, MutationPlayer};
+use aili_model::vis::{ParentAssignmentError, VisTree};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Kind of an event that has occurred in a session.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SessionEventKind {
+    /// A command of the state provider has been executed,
+    /// typically stopping the debuggee at a new location.
+    Stop {
+        /// The executed command.
+        command: String,
+
+        /// Response of the provider to the command.
+        response: String,
+    },
+
+    /// The state graph has been updated.
+    Update,
+
+    /// The stylesheet has been replaced.
+    StylesheetChange,
+
+    /// The state graph has been rendered
+    /// without being updated first.
+    Render,
+
+    /// An operation of the state provider has failed.
+    Error {
+        /// Description of the failure.
+        message: String,
+    },
+
+    /// The renderer has detected a likely error in the stylesheet.
+    Warning {
+        /// Description of the problem.
+        message: String,
+    },
+}
+
+/// Single entry in a [`Timeline`].
+#[derive(Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct SessionEvent {
+    /// Time since the start of the session.
+    #[serde(rename = "timestamp", serialize_with = "serialize_millis")]
+    pub timestamp: Duration,
+
+    /// What has happened.
+    #[serde(flatten)]
+    pub kind: SessionEventKind,
+
+    /// Changes made to the visualization as a result of the event.
+    pub mutations: Vec<Mutation>,
+}
+
+/// Chronological log of the events of a session.
+///
+/// Each event carries the changes it has made to the visualization,
+/// so the visualization as it was after any event can be reconstructed
+/// by replaying all preceding events against a fresh renderer.
+#[derive(Debug)]
+pub struct Timeline {
+    start: Instant,
+    events: Vec<SessionEvent>,
+}
+
+impl Timeline {
+    /// Constructs an empty timeline that starts now.
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Appends an event to the timeline.
+    pub fn record(&mut self, kind: SessionEventKind, mutations: Vec<Mutation>) {
+        self.events.push(SessionEvent {
+            timestamp: self.start.elapsed(),
+            kind,
+            mutations,
+        });
+    }
+
+    /// Gets all events recorded so far, in chronological order.
+    pub fn events(&self) -> &[SessionEvent] {
+        &self.events
+    }
+
+    /// Constructs a sequence of mutations that builds the visualization
+    /// as it was after a given number of events from scratch.
+    ///
+    /// If `event_count` exceeds the number of recorded events,
+    /// the current visualization is built.
+    pub fn replay(&self, event_count: usize) -> impl Iterator<Item = &Mutation> {
+        self.events
+            .iter()
+            .take(event_count)
+            .flat_map(|event| &event.mutations)
+    }
+
+    /// Builds the visualization as it was after a given number
+    /// of events in a fresh visualization tree.
+    ///
+    /// See [`Timeline::replay`].
+    pub fn replay_into<V: VisTree>(
+        &self,
+        vis_tree: V,
+        event_count: usize,
+    ) -> Result<V, ParentAssignmentError> {
+        let mut player = MutationPlayer::new(vis_tree);
+        player.apply_all(self.replay(event_count))?;
+        Ok(player.into_vis_tree())
+    }
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Serializes a duration as a number of milliseconds.
+fn serialize_millis<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis().try_into().unwrap_or(u64::MAX))
+}
@@ -0,0 +1,319 @@
+use aili_interp::{
+    interpreter::{Interpreter, RuntimeError},
+    parser::parse_program,
+};
+use aili_model::{
+    capability::{self, CapabilitySet},
+    state::EdgeLabel,
+};
+use aili_server::{
+    mutation::{Mutation, MutationRecorder},
+    rpc::{INCOMPATIBLE_STYLESHEET, METHOD_NOT_FOUND, PARSE_ERROR, PROVIDER_ERROR, handle_message},
+    server::serve_connection,
+    session::{Session, StateProvider},
+    timeline::SessionEventKind,
+};
+use aili_style::stylesheet::{expression::*, selector::*, *};
+use serde_json::{Value, json};
+use std::net::TcpListener;
+
+/// Provider that runs a program in the teaching language interpreter.
+struct InterpProvider(Interpreter);
+
+impl StateProvider for InterpProvider {
+    type Graph = Interpreter;
+    type Error = RuntimeError;
+
+    fn graph(&self) -> &Self::Graph {
+        &self.0
+    }
+
+    fn update(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn execute(&mut self, command: &str) -> Result<String, Self::Error> {
+        match command {
+            "step" => Ok(self.0.step()?.to_string()),
+            _ => Ok("unknown command".to_owned()),
+        }
+    }
+}
+
+fn session_from_source(source: &str) -> Session<InterpProvider> {
+    let program = parse_program(source).expect("Program should have parsed");
+    Session::new(InterpProvider(Interpreter::new(program)))
+}
+
+/// Stylesheet that displays the root and each variable
+/// in the main frame as a cell.
+///
+/// ```text
+/// :: {
+///   display: cell;
+/// }
+/// :: main % {
+///   display: cell;
+/// }
+/// ```
+fn variable_stylesheet() -> Stylesheet {
+    let display_cell = StyleClause {
+        key: StyleKey::Property(RawPropertyKey::Property("display".to_owned())),
+        value: Expression::String("cell".to_owned()),
+    };
+    Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![display_cell.clone()],
+        },
+        StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::Match(EdgeLabel::Main.into()),
+                    SelectorSegment::Match(EdgeMatcher::AnyNamed),
+                ]
+                .into(),
+            ),
+            properties: vec![display_cell],
+        },
+    ])
+}
+
+fn created_elements(mutations: &[Mutation]) -> usize {
+    mutations
+        .iter()
+        .filter(|m| matches!(m, Mutation::CreateElement { .. }))
+        .count()
+}
+
+#[test]
+fn stylesheet_renders_into_mutations() {
+    let mut session = session_from_source("let a = 1;");
+    let mutations = session.set_stylesheet(variable_stylesheet()).unwrap();
+    assert_eq!(created_elements(&mutations), 1);
+    assert!(mutations.contains(&Mutation::SetRoot { element: Some(0) }));
+}
+
+#[test]
+fn stylesheet_with_missing_capabilities_is_rejected() {
+    let mut session = session_from_source("let a = 1;");
+    session.set_stylesheet(variable_stylesheet()).unwrap();
+    let stylesheet = Stylesheet::new(Vec::new()).with_requirements(CapabilitySet::from_iter([
+        capability::ARRAYS,
+        capability::GDB,
+    ]));
+    let error = session.set_stylesheet(stylesheet).unwrap_err();
+    assert_eq!(error.0, [capability::GDB]);
+    // The previous stylesheet is still in use
+    assert_eq!(created_elements(&session.snapshot()), 1);
+}
+
+#[test]
+fn execution_renders_new_variables() {
+    let mut session = session_from_source("let a = 1;\nlet b = 2;");
+    session.set_stylesheet(variable_stylesheet()).unwrap();
+    let (response, mutations) = session.execute("step").unwrap();
+    assert_eq!(response, "true");
+    assert_eq!(created_elements(&mutations), 1);
+    let (_, mutations) = session.execute("step").unwrap();
+    assert_eq!(created_elements(&mutations), 1);
+    // Nothing changes if the program has finished
+    let (response, mutations) = session.execute("step").unwrap();
+    assert_eq!(response, "false");
+    assert!(mutations.is_empty());
+}
+
+#[test]
+fn stylesheet_change_keeps_elements_and_lifetimes() {
+    let mut session = session_from_source("let a = 1;");
+    session.set_stylesheet(variable_stylesheet()).unwrap();
+    session.execute("step").unwrap();
+    // :: main % {
+    //   lifetime: @lifetime;
+    // }
+    let mut stylesheet = variable_stylesheet();
+    stylesheet.rules[1].properties.push(StyleClause {
+        key: StyleKey::Property(RawPropertyKey::Property("lifetime".to_owned())),
+        value: Expression::MagicVariable(MagicVariableKey::Lifetime),
+    });
+    let mutations = session.set_stylesheet(stylesheet).unwrap();
+    assert_eq!(created_elements(&mutations), 0);
+    // The variable has been added by the last step, so it is still new
+    assert!(mutations.iter().any(|m| matches!(
+        m,
+        Mutation::SetElementAttribute { name, value, .. }
+            if name == "lifetime" && value.as_deref() == Some("new")
+    )));
+}
+
+#[test]
+fn snapshot_reproduces_visualization() {
+    let mut session = session_from_source("let a = 1;\nlet b = 2;");
+    session.set_stylesheet(variable_stylesheet()).unwrap();
+    session.execute("step").unwrap();
+    session.execute("step").unwrap();
+    let snapshot = session.snapshot();
+    assert_eq!(created_elements(&snapshot), 3);
+    assert_eq!(
+        snapshot.last(),
+        Some(&Mutation::SetRoot { element: Some(0) })
+    );
+}
+
+/// Checks that two sequences of mutations contain the same mutations,
+/// regardless of order.
+fn assert_same_mutations(left: &[Mutation], right: &[Mutation]) {
+    assert_eq!(left.len(), right.len());
+    assert!(left.iter().all(|m| right.contains(m)));
+}
+
+#[test]
+fn timeline_records_session_events() {
+    let mut session = session_from_source("let a = 1;");
+    session.set_stylesheet(variable_stylesheet()).unwrap();
+    session.execute("step").unwrap();
+    let kinds: Vec<_> = session
+        .timeline()
+        .events()
+        .iter()
+        .map(|event| &event.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            &SessionEventKind::StylesheetChange,
+            &SessionEventKind::Stop {
+                command: "step".to_owned(),
+                response: "true".to_owned(),
+            },
+        ]
+    );
+    assert_eq!(
+        created_elements(&session.timeline().events()[1].mutations),
+        1
+    );
+}
+
+#[test]
+fn timeline_records_provider_errors() {
+    let mut session = session_from_source("let a = 1 / 0;");
+    assert!(session.execute("step").is_err());
+    let events = session.timeline().events();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0].kind, SessionEventKind::Error { .. }));
+}
+
+#[test]
+fn timeline_replays_past_visualization() {
+    let mut session = session_from_source("let a = 1;\nlet b = 2;");
+    session.set_stylesheet(variable_stylesheet()).unwrap();
+    session.execute("step").unwrap();
+    let past_snapshot = session.snapshot();
+    let past_event_count = session.timeline().events().len();
+    session.execute("step").unwrap();
+    let replayed = session
+        .timeline()
+        .replay_into(MutationRecorder::new(), past_event_count)
+        .unwrap();
+    assert_same_mutations(&replayed.snapshot(), &past_snapshot);
+    // Replaying everything restores the current visualization
+    let replayed = session
+        .timeline()
+        .replay_into(MutationRecorder::new(), usize::MAX)
+        .unwrap();
+    assert_same_mutations(&replayed.snapshot(), &session.snapshot());
+}
+
+#[test]
+fn rpc_requests() {
+    let mut session = session_from_source("let a = 1;");
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "setStylesheet",
+        "params": { "stylesheet": variable_stylesheet() },
+    });
+    let response = handle_message(&mut session, &request.to_string()).unwrap();
+    let response: Value = serde_json::from_str(&response).unwrap();
+    assert_eq!(response["id"], 1);
+    assert_eq!(
+        response["result"]["mutations"],
+        json!([
+            { "type": "createElement", "id": 0, "tagName": "cell" },
+            { "type": "setRoot", "element": 0 },
+        ])
+    );
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": "step",
+        "method": "execute",
+        "params": { "command": "step" },
+    });
+    let response = handle_message(&mut session, &request.to_string()).unwrap();
+    let response: Value = serde_json::from_str(&response).unwrap();
+    assert_eq!(response["id"], "step");
+    assert_eq!(response["result"]["response"], "true");
+}
+
+#[test]
+fn rpc_errors() {
+    let mut session = session_from_source("let a = 1 / 0;");
+    let response = handle_message(&mut session, "{").unwrap();
+    let response: Value = serde_json::from_str(&response).unwrap();
+    assert_eq!(response["error"]["code"], PARSE_ERROR);
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "frobnicate" });
+    let response = handle_message(&mut session, &request.to_string()).unwrap();
+    let response: Value = serde_json::from_str(&response).unwrap();
+    assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "execute",
+        "params": { "command": "step" },
+    });
+    let response = handle_message(&mut session, &request.to_string()).unwrap();
+    let response: Value = serde_json::from_str(&response).unwrap();
+    assert_eq!(response["error"]["code"], PROVIDER_ERROR);
+    let stylesheet: Stylesheet = Stylesheet::new(Vec::new())
+        .with_requirements(CapabilitySet::new().with(capability::FLOAT_VALUES));
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "setStylesheet",
+        "params": { "stylesheet": stylesheet },
+    });
+    let response = handle_message(&mut session, &request.to_string()).unwrap();
+    let response: Value = serde_json::from_str(&response).unwrap();
+    assert_eq!(response["error"]["code"], INCOMPATIBLE_STYLESHEET);
+}
+
+#[test]
+fn rpc_notification_gets_no_response() {
+    let mut session = session_from_source("let a = 1;");
+    let request = json!({ "jsonrpc": "2.0", "method": "update" });
+    assert!(handle_message(&mut session, &request.to_string()).is_none());
+}
+
+#[test]
+fn websocket_round_trip() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let mut session = session_from_source("let a = 1;");
+        let (stream, _) = listener.accept().unwrap();
+        serve_connection(stream, &mut session).unwrap();
+    });
+    let (mut socket, _) = tungstenite::connect(format!("ws://{address}")).unwrap();
+    let request = json!({ "jsonrpc": "2.0", "id": 7, "method": "snapshot" });
+    socket
+        .send(tungstenite::Message::Text(request.to_string()))
+        .unwrap();
+    let response = socket.read().unwrap().into_text().unwrap();
+    let response: Value = serde_json::from_str(&response).unwrap();
+    assert_eq!(response["id"], 7);
+    assert_eq!(response["result"]["mutations"], json!([]));
+    socket.close(None).unwrap();
+    // Wait for the server to acknowledge the closing handshake
+    while socket.read().is_ok() {}
+    server.join().unwrap();
+}
@@ -0,0 +1,123 @@
+//! Compiled stylesheets that can be shared and persisted.
+
+use super::style::CascadeStyle;
+use crate::stylesheet::{MissingCapabilities, PropertyKey, RawPropertyKey, Stylesheet};
+use aili_model::capability::CapabilitySet;
+use std::{ops::Deref, sync::Arc};
+
+/// Stylesheet that has been compiled for evaluation.
+///
+/// Compiling a stylesheet flattens its selectors
+/// into a [`CascadeSelector`](super::CascadeSelector) automaton,
+/// which is what the cascade is evaluated with.
+/// The compiled form can be reused across any number
+/// of graphs, so a stylesheet only needs to be compiled once.
+///
+/// Cloning a compiled stylesheet is cheap, as all clones
+/// share the same automaton. With the `serde` feature,
+/// the compiled form can also be serialized, so it can be
+/// reused by other processes without compiling it again.
+///
+/// ```
+/// use aili_style::{
+///     cascade::CompiledStylesheet,
+///     stylesheet::{StyleRule, Stylesheet, selector::Selector},
+/// };
+///
+/// let stylesheet: Stylesheet = Stylesheet::new(vec![StyleRule {
+///     selector: Selector::default(),
+///     properties: Vec::new(),
+/// }]);
+/// let compiled = CompiledStylesheet::compile(stylesheet);
+/// let shared = compiled.clone();
+/// assert_eq!(shared.rule_at(0).properties.len(), 0);
+/// ```
+#[derive(PartialEq, Eq, Debug)]
+pub struct CompiledStylesheet<K: PropertyKey = RawPropertyKey>(Arc<CompiledStylesheetData<K>>);
+
+/// Shared contents of a [`CompiledStylesheet`].
+#[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct CompiledStylesheetData<K: PropertyKey> {
+    /// The compiled rules and selectors.
+    style: CascadeStyle<K>,
+
+    /// Capabilities that the stylesheet requires.
+    #[cfg_attr(feature = "serde", serde(default))]
+    requirements: CapabilitySet,
+}
+
+impl<K: PropertyKey> CompiledStylesheet<K> {
+    /// Compiles a stylesheet.
+    pub fn compile(stylesheet: Stylesheet<K>) -> Self {
+        let requirements = stylesheet.requirements.clone();
+        Self(Arc::new(CompiledStylesheetData {
+            style: stylesheet.into(),
+            requirements,
+        }))
+    }
+
+    /// Constructs a compiled stylesheet with no rules.
+    pub fn empty() -> Self {
+        Self::compile(Stylesheet::default())
+    }
+
+    /// Gets the compiled rules and selectors of the stylesheet.
+    pub fn cascade(&self) -> &CascadeStyle<K> {
+        &self.0.style
+    }
+
+    /// Gets the capabilities that the stylesheet requires.
+    pub fn requirements(&self) -> &CapabilitySet {
+        &self.0.requirements
+    }
+
+    /// Verifies that a state graph provides all capabilities
+    /// that the stylesheet requires.
+    pub fn check_requirements(&self, provided: &CapabilitySet) -> Result<(), MissingCapabilities> {
+        MissingCapabilities::check(self.requirements(), provided)
+    }
+}
+
+impl<K: PropertyKey> Clone for CompiledStylesheet<K> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<K: PropertyKey> Default for CompiledStylesheet<K> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<K: PropertyKey> Deref for CompiledStylesheet<K> {
+    type Target = CascadeStyle<K>;
+    fn deref(&self) -> &Self::Target {
+        self.cascade()
+    }
+}
+
+impl<K: PropertyKey> From<Stylesheet<K>> for CompiledStylesheet<K> {
+    fn from(value: Stylesheet<K>) -> Self {
+        Self::compile(value)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K: PropertyKey + serde::Serialize> serde::Serialize for CompiledStylesheet<K> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.as_ref().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K: PropertyKey + serde::Deserialize<'de>> serde::Deserialize<'de>
+    for CompiledStylesheet<K>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        CompiledStylesheetData::deserialize(deserializer)
+            .map(Arc::new)
+            .map(Self)
+    }
+}
@@ -0,0 +1,87 @@
+//! Variables provided by the embedder of a stylesheet.
+
+use super::CascadeStyle;
+use crate::{
+    eval::{
+        context::{EvaluationContext, Never},
+        evaluate,
+        variable_pool::VariablePool,
+    },
+    stylesheet::{PropertyKey, expression::Expression},
+};
+use aili_model::state::NodeValue;
+use std::collections::HashMap;
+
+/// Variables that the embedder of a stylesheet provides
+/// to parameterize it at runtime.
+///
+/// The variables are visible to all expressions in the stylesheet,
+/// including conditions of [conditional blocks](crate::stylesheet::ConditionalBlock).
+/// This lets one stylesheet serve multiple modes, such as light
+/// and dark themes, which the embedder switches between,
+/// or react to the state of the embedder, such as its zoom level.
+///
+/// ```
+/// use aili_style::{
+///     cascade::CascadeContext,
+///     stylesheet::expression::{BinaryOperator, Expression},
+/// };
+///
+/// let context = CascadeContext::new().with_variable("--THEME", "dark".to_owned());
+/// let condition = Expression::BinaryOperator(
+///     Expression::Variable("--THEME".to_owned()).into(),
+///     BinaryOperator::Eq,
+///     Expression::String("dark".to_owned()).into(),
+/// );
+/// assert!(context.evaluate_condition(&condition));
+/// assert!(!CascadeContext::new().evaluate_condition(&condition));
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct CascadeContext {
+    /// Values of the variables, by name.
+    pub variables: HashMap<String, NodeValue>,
+}
+
+impl CascadeContext {
+    /// Constructs a context with no variables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assigns a value to a variable.
+    pub fn with_variable(mut self, name: impl Into<String>, value: impl Into<NodeValue>) -> Self {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
+
+    /// Evaluates the condition of a conditional block.
+    ///
+    /// The condition can read the variables of the context,
+    /// but nothing else, as there is no state graph to select from.
+    /// Variables that the context does not provide are unset.
+    pub fn evaluate_condition(&self, condition: &Expression) -> bool {
+        let mut variable_pool = VariablePool::new();
+        for (name, value) in &self.variables {
+            variable_pool.insert(name.as_str(), value.clone().into());
+        }
+        let context = EvaluationContext::<Never>::stateless().with_variables(&variable_pool);
+        evaluate(condition, &context).is_truthy()
+    }
+
+    /// Determines which rules of a stylesheet apply in the context.
+    ///
+    /// ## Return Value
+    /// A flag for each rule, indexed by rule index,
+    /// that is set if the rule applies.
+    pub fn enabled_rules<K: PropertyKey>(&self, stylesheet: &CascadeStyle<K>) -> Vec<bool> {
+        (0..stylesheet.rule_count())
+            .map(|rule_index| {
+                stylesheet
+                    .rule_at(rule_index)
+                    .condition
+                    .as_ref()
+                    .is_none_or(|condition| self.evaluate_condition(condition))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,207 @@
+//! Detection of selectors that can never match.
+
+use crate::stylesheet::{expression::*, selector::*};
+use aili_model::state::{EdgeLabel, NodeTypeClass};
+use derive_more::Display;
+use std::collections::HashSet;
+
+/// Problem with a selector that has been found
+/// when a stylesheet was compiled.
+///
+/// Reported by [`CascadeStyle::compile_with_warnings`](super::CascadeStyle::compile_with_warnings).
+/// Selectors are checked against the edges that each
+/// [`NodeTypeClass`] permits, so they may still match
+/// graphs that do not adhere to the permitted edges.
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+pub enum SelectorWarning {
+    /// An edge matcher can never match, because no edge it matches
+    /// may start at the nodes that the preceding segments select.
+    #[display("{matcher:?} can never match in rule {rule}")]
+    UnmatchableEdge {
+        /// Index of the rule whose selector contains the matcher.
+        rule: usize,
+
+        /// The matcher that can never match.
+        matcher: EdgeMatcher,
+    },
+
+    /// A type class assertion can never match, because the preceding
+    /// segments can never select nodes of that type class.
+    #[display(":{type_class:?} can never match in rule {rule}")]
+    UnmatchableTypeClass {
+        /// Index of the rule whose selector contains the assertion.
+        rule: usize,
+
+        /// The type class that is asserted.
+        type_class: NodeTypeClass,
+    },
+
+    /// An [alternative](SelectorSegment::Branch) has no branches,
+    /// so it can never match.
+    #[display("empty alternative can never match in rule {rule}")]
+    EmptyAlternative {
+        /// Index of the rule whose selector contains the alternative.
+        rule: usize,
+    },
+}
+
+/// All type classes that a node may have.
+const ALL_TYPE_CLASSES: [NodeTypeClass; 9] = [
+    NodeTypeClass::Root,
+    NodeTypeClass::Frame,
+    NodeTypeClass::Atom,
+    NodeTypeClass::Struct,
+    NodeTypeClass::Union,
+    NodeTypeClass::Array,
+    NodeTypeClass::Ref,
+    NodeTypeClass::Custom,
+    NodeTypeClass::Truncated,
+];
+
+/// Checks the selector of a rule for segments that can never match.
+pub(super) fn check_selector(selector: &Selector, rule: usize) -> Vec<SelectorWarning> {
+    let mut checker = SelectorChecker {
+        rule,
+        warnings: Vec::new(),
+    };
+    // Selectors start at the root
+    checker.check_path(&selector.path, [NodeTypeClass::Root].into(), true);
+    checker.warnings
+}
+
+/// Helper that walks a selector and tracks the type classes
+/// of the nodes it may have reached.
+struct SelectorChecker {
+    rule: usize,
+    warnings: Vec<SelectorWarning>,
+}
+
+impl SelectorChecker {
+    /// Finds the type classes of the nodes that a path may reach,
+    /// starting at nodes of some type classes.
+    fn check_path(
+        &mut self,
+        path: &SelectorPath,
+        mut type_classes: HashSet<NodeTypeClass>,
+        report: bool,
+    ) -> HashSet<NodeTypeClass> {
+        for segment in &path.0 {
+            type_classes = self.check_segment(segment, type_classes, report);
+        }
+        type_classes
+    }
+
+    /// Finds the type classes of the nodes that a segment may reach,
+    /// starting at nodes of some type classes.
+    fn check_segment(
+        &mut self,
+        segment: &SelectorSegment,
+        type_classes: HashSet<NodeTypeClass>,
+        report: bool,
+    ) -> HashSet<NodeTypeClass> {
+        // Segments after one that can never match are not reported again
+        let report = report && !type_classes.is_empty();
+        match segment {
+            SelectorSegment::Match(matcher) => {
+                let targets = successor_type_classes(&type_classes, matcher);
+                if report && targets.is_empty() {
+                    self.warnings.push(SelectorWarning::UnmatchableEdge {
+                        rule: self.rule,
+                        matcher: matcher.clone(),
+                    });
+                }
+                targets
+            }
+            SelectorSegment::AnyNumberOfTimes(path) => {
+                // Repeat the path until it reaches no new type classes
+                let mut reachable = type_classes;
+                loop {
+                    let reached = self.check_path(path, reachable.clone(), false);
+                    if reached.is_subset(&reachable) {
+                        break;
+                    }
+                    reachable.extend(reached);
+                }
+                self.check_path(path, reachable.clone(), report);
+                reachable
+            }
+            SelectorSegment::Branch(paths) => {
+                if report && paths.is_empty() {
+                    self.warnings
+                        .push(SelectorWarning::EmptyAlternative { rule: self.rule });
+                }
+                paths
+                    .iter()
+                    .flat_map(|path| self.check_path(path, type_classes.clone(), report))
+                    .collect()
+            }
+            SelectorSegment::Condition(condition) => {
+                let Some(type_class) = asserted_type_class(condition) else {
+                    return type_classes;
+                };
+                if !type_classes.contains(&type_class) {
+                    if report {
+                        self.warnings.push(SelectorWarning::UnmatchableTypeClass {
+                            rule: self.rule,
+                            type_class,
+                        });
+                    }
+                    return HashSet::new();
+                }
+                [type_class].into()
+            }
+        }
+    }
+}
+
+/// Finds the type classes of the nodes that edges matched by a matcher
+/// may lead to from nodes of some type classes.
+fn successor_type_classes(
+    type_classes: &HashSet<NodeTypeClass>,
+    matcher: &EdgeMatcher,
+) -> HashSet<NodeTypeClass> {
+    // One label of each kind is enough, since all labels
+    // of the same kind permit the same type classes
+    let labels = [
+        EdgeLabel::Main,
+        EdgeLabel::Next,
+        EdgeLabel::Result,
+        EdgeLabel::Deref,
+        EdgeLabel::Index(0),
+        EdgeLabel::Named("".into(), 0),
+        EdgeLabel::Length,
+        EdgeLabel::Custom(String::new()),
+    ];
+    let matched_labels = match matcher {
+        EdgeMatcher::Exact(label) => vec![label.clone()],
+        EdgeMatcher::Any => labels.to_vec(),
+        EdgeMatcher::AnyIndex => vec![EdgeLabel::Index(0)],
+        EdgeMatcher::AnyNamed | EdgeMatcher::Named(_) => vec![EdgeLabel::Named("".into(), 0)],
+    };
+    matched_labels
+        .iter()
+        .filter(|label| {
+            type_classes
+                .iter()
+                .any(|type_class| label.permits_source(*type_class))
+        })
+        .flat_map(|label| {
+            ALL_TYPE_CLASSES
+                .into_iter()
+                .filter(|type_class| label.permits_target(*type_class))
+        })
+        .collect()
+}
+
+/// Recognizes conditions that assert the type class of the current node,
+/// such as `.if(is-arr(@))`, which is what `:arr` desugars to.
+fn asserted_type_class(condition: &Expression) -> Option<NodeTypeClass> {
+    match condition {
+        Expression::UnaryOperator(UnaryOperator::NodeIsA(type_class), operand)
+            if **operand == Expression::Select(LimitedSelector::default().into()) =>
+        {
+            Some(*type_class)
+        }
+        _ => None,
+    }
+}
@@ -1,7 +1,15 @@
 //! Utilities for stylesheet resolution.
 
+mod compiled;
+mod context;
+mod diagnostics;
 mod selector_resolver;
 mod style;
 
-pub use selector_resolver::{SelectionCaret, SelectorResolver};
-pub use style::{CascadeSelector, CascadeStyle, CascadeStyleRule};
+pub use compiled::CompiledStylesheet;
+pub use context::CascadeContext;
+pub use diagnostics::SelectorWarning;
+pub use selector_resolver::{ActiveStates, SelectionCaret, SelectorResolver};
+pub use style::{
+    CascadeSelector, CascadeStyle, CascadeStyleRule, FlatSelector, FlatSelectorSegment, RuleLabel,
+};
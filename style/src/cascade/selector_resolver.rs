@@ -3,7 +3,7 @@
 use super::style::{CascadeSelector, FlatSelectorSegment};
 use crate::eval::{context::EvaluationContext, evaluate};
 use aili_model::state::{EdgeLabel, NodeId, ProgramStateGraph};
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 /// Helper object for the resolution of stylesheets.
 #[derive(Clone)]
@@ -21,6 +21,28 @@ pub struct SelectorResolver<'a, T: NodeId> {
     /// transition in the state machine.
     matched_sequence_points: HashSet<(T, SelectorState)>,
 
+    /// Conditions whose outcome only depends on the node
+    /// they are evaluated at, so it can be reused.
+    ///
+    /// A condition is identified by the state
+    /// of its [`FlatSelectorSegment::Restrict`] transition.
+    cacheable_conditions: HashSet<SelectorState>,
+
+    /// Outcomes of [cacheable](Self::cacheable_conditions) conditions
+    /// that have already been evaluated at a node.
+    ///
+    /// Nodes that are reachable along many paths would otherwise
+    /// have the same conditions evaluated once per path.
+    condition_cache: HashMap<(T, SelectorState), bool>,
+
+    /// Number of conditions that have been evaluated,
+    /// not counting those whose outcomes were reused.
+    conditions_evaluated: usize,
+
+    /// Number of steps each selector has taken through its state machine,
+    /// indexed by rule index.
+    match_attempts: Vec<usize>,
+
     /// The resolution stack that tracks the current path to root.
     stack: Vec<ResolveFrame>,
 }
@@ -31,6 +53,10 @@ impl<'a, T: NodeId> SelectorResolver<'a, T> {
         Self {
             selectors,
             matched_sequence_points: HashSet::new(),
+            cacheable_conditions: selectors.cacheable_conditions(),
+            condition_cache: HashMap::new(),
+            conditions_evaluated: 0,
+            match_attempts: vec![0; selectors.0.len()],
             stack: vec![ResolveFrame {
                 active_states: selectors.all_starting_states(),
             }],
@@ -99,6 +125,7 @@ impl<'a, T: NodeId> SelectorResolver<'a, T> {
         // Make a transitive closure of selector states reachable at this node
         while let Some((state, target)) = open_states.pop() {
             let selector = &self.selectors.0[state.rule_index].path;
+            self.match_attempts[state.rule_index] += 1;
             if state.instruction_index >= selector.len() {
                 // We made it to the end of the selector
                 // That means it has matched the node
@@ -135,8 +162,19 @@ impl<'a, T: NodeId> SelectorResolver<'a, T> {
                     }
                 }
                 FlatSelectorSegment::Restrict(condition) => {
+                    let key = (node.clone(), state);
+                    let holds = if let Some(&holds) = self.condition_cache.get(&key) {
+                        holds
+                    } else {
+                        let holds = evaluate(condition, eval_context).is_truthy();
+                        self.conditions_evaluated += 1;
+                        if self.cacheable_conditions.contains(&state) {
+                            self.condition_cache.insert(key, holds);
+                        }
+                        holds
+                    };
                     // Proceed only if the condition holds
-                    if evaluate(condition, eval_context).is_truthy() {
+                    if holds {
                         // continue traversing the state machine linearly
                         open_states.push((state.advance(), target));
                     }
@@ -168,12 +206,45 @@ impl<'a, T: NodeId> SelectorResolver<'a, T> {
         !self.stack.last().unwrap().active_states.is_empty()
     }
 
+    /// Gets the number of selector conditions that have been evaluated so far.
+    ///
+    /// Conditions whose outcomes have been reused from an earlier
+    /// evaluation at the same node are not counted.
+    pub fn conditions_evaluated(&self) -> usize {
+        self.conditions_evaluated
+    }
+
+    /// Gets the number of steps each selector has taken
+    /// through its state machine so far, indexed by rule index.
+    ///
+    /// Selectors that take many steps without matching anything,
+    /// such as those that start with `.many(*)`, make resolution slow.
+    pub fn match_attempts(&self) -> &[usize] {
+        &self.match_attempts
+    }
+
+    /// Gets the states that the selectors are in at the moment,
+    /// in a form that can be compared and hashed.
+    ///
+    /// Traversals can use this to recognize that they have reached
+    /// a node in a way that they have already reached it before.
+    pub fn active_states(&self) -> ActiveStates {
+        let mut states = self.stack.last().unwrap().active_states.clone();
+        states.sort();
+        states.dedup();
+        ActiveStates(states)
+    }
+
     /// Creates a copy of the resolver that is frozen at current frame
     /// and cannot be popped past it.
     pub fn snapshot(&self) -> Self {
         Self {
             selectors: self.selectors,
             matched_sequence_points: self.matched_sequence_points.clone(),
+            cacheable_conditions: self.cacheable_conditions.clone(),
+            condition_cache: self.condition_cache.clone(),
+            conditions_evaluated: self.conditions_evaluated,
+            match_attempts: self.match_attempts.clone(),
             stack: vec![self.stack.last().unwrap().clone()],
         }
     }
@@ -190,6 +261,29 @@ impl CascadeSelector {
             })
             .collect()
     }
+
+    /// Retrieves the states of all conditions in all selectors
+    /// that do not read any variables.
+    fn cacheable_conditions(&self) -> HashSet<SelectorState> {
+        self.0
+            .iter()
+            .enumerate()
+            .flat_map(|(rule_index, selector)| {
+                selector
+                    .path
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, segment)| match segment {
+                        FlatSelectorSegment::Restrict(condition) => !condition.reads_variables(),
+                        _ => false,
+                    })
+                    .map(move |(instruction_index, _)| SelectorState {
+                        rule_index,
+                        instruction_index,
+                    })
+            })
+            .collect()
+    }
 }
 
 /// Indicates what kind of entity a selector has last passed.
@@ -202,6 +296,13 @@ pub enum SelectionCaret {
     PrecedingEdge,
 }
 
+/// States that the selectors of a [`SelectorResolver`]
+/// are in at some point of the traversal.
+///
+/// See [`SelectorResolver::active_states`].
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct ActiveStates(Vec<SelectorState>);
+
 /// Unique identifier of an instruction in a selector.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 struct SelectorState {
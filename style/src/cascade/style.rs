@@ -1,13 +1,24 @@
 //! Preprocessing of [`Stylesheet`]s to simplify matching.
 
-use crate::stylesheet::{expression::Expression, selector::*, *};
-use derive_more::Debug;
+use super::diagnostics::{SelectorWarning, check_selector};
+use crate::stylesheet::{
+    expression::{Expression, MagicVariableKey},
+    selector::*,
+    *,
+};
+use derive_more::{Debug, Display};
 
 /// Compiled stylesheet that can be used to evaluate the cascade.
-#[derive(Debug)]
+///
+/// See [`CompiledStylesheet`](super::CompiledStylesheet)
+/// for a compiled stylesheet that can be shared.
+#[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CascadeStyle<K: PropertyKey = RawPropertyKey> {
     selectors: CascadeSelector,
     rules: Vec<CascadeStyleRule<K>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    uses_layer_indices: bool,
 }
 
 impl<K: PropertyKey> CascadeStyle<K> {
@@ -16,9 +27,25 @@ impl<K: PropertyKey> CascadeStyle<K> {
         Self {
             selectors: CascadeSelector(Vec::new()),
             rules: Vec::new(),
+            uses_layer_indices: false,
         }
     }
 
+    /// Compiles a stylesheet and reports selector segments
+    /// that can never match.
+    ///
+    /// The stylesheet is compiled the same way as by [`From`],
+    /// the warnings do not change how it is applied.
+    pub fn compile_with_warnings(stylesheet: Stylesheet<K>) -> (Self, Vec<SelectorWarning>) {
+        let warnings = stylesheet
+            .rules
+            .iter()
+            .enumerate()
+            .flat_map(|(index, rule)| check_selector(&rule.selector, index))
+            .collect();
+        (stylesheet.into(), warnings)
+    }
+
     /// Gets the compiled selectors of the stalesheet.
     pub fn selector_machine(&self) -> &CascadeSelector {
         &self.selectors
@@ -31,6 +58,26 @@ impl<K: PropertyKey> CascadeStyle<K> {
     pub fn rule_at(&self, index: usize) -> &CascadeStyleRule<K> {
         &self.rules[index]
     }
+
+    /// Gets the number of rules in the stylesheet.
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Checks whether any rule refers to
+    /// [layer indices](MagicVariableKey::LayerIndex),
+    /// so they have to be computed when the stylesheet is applied.
+    pub fn uses_layer_indices(&self) -> bool {
+        self.uses_layer_indices
+    }
+
+    /// Gets a human-readable reference to a rule at a specified index.
+    pub fn rule_label(&self, index: usize) -> RuleLabel<'_> {
+        match &self.rules[index].name {
+            Some(name) => RuleLabel::Named(name),
+            None => RuleLabel::Index(index),
+        }
+    }
 }
 
 impl<K: PropertyKey> Default for CascadeStyle<K> {
@@ -41,14 +88,42 @@ impl<K: PropertyKey> Default for CascadeStyle<K> {
 
 impl<K: PropertyKey> From<Stylesheet<K>> for CascadeStyle<K> {
     fn from(value: Stylesheet<K>) -> Self {
+        let annotations = (0..value.rules.len())
+            .map(|rule_index| {
+                (
+                    value.condition_of(rule_index).cloned(),
+                    value.rule_name(rule_index).map(str::to_owned),
+                )
+            })
+            .collect::<Vec<_>>();
+        let uses_layer_indices = value.rules.iter().any(|rule| {
+            rule.selector
+                .path
+                .uses_magic_variable(MagicVariableKey::LayerIndex)
+                || rule.properties.iter().any(|property| {
+                    property
+                        .value
+                        .uses_magic_variable(MagicVariableKey::LayerIndex)
+                })
+        });
         let (selectors, rules) = value
-            .0
+            .rules
             .into_iter()
-            .map(|mut rule| {
+            .zip(annotations)
+            .map(|(mut rule, (condition, name))| {
                 let extra_label = rule.selector.extra.take();
+                let extra_index = rule.selector.extra_index.filter(|_| extra_label.is_some());
+                let selects_by_lifetime = rule
+                    .selector
+                    .path
+                    .uses_magic_variable(MagicVariableKey::Lifetime);
                 let selector = rule.selector.into();
                 let body = CascadeStyleRule {
                     extra_label,
+                    extra_index,
+                    selects_by_lifetime,
+                    condition,
+                    name,
                     properties: rule.properties,
                 };
                 (selector, body)
@@ -57,24 +132,73 @@ impl<K: PropertyKey> From<Stylesheet<K>> for CascadeStyle<K> {
         Self {
             selectors: CascadeSelector(selectors),
             rules,
+            uses_layer_indices,
         }
     }
 }
 
+/// Human-readable reference to a rule of a [`CascadeStyle`].
+///
+/// Rules are referred to by their names if they have one,
+/// and by their indices otherwise.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+pub enum RuleLabel<'a> {
+    /// Rule that has a name.
+    #[display("rule {_0:?}")]
+    Named(&'a str),
+
+    /// Rule that has no name, identified by its index.
+    #[display("rule {_0}")]
+    Index(usize),
+}
+
 /// Compiled bundle of selectors.
-#[derive(Debug)]
+#[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CascadeSelector(pub(super) Vec<FlatSelector>);
 
+impl CascadeSelector {
+    /// Gets the flattened selectors of all rules, in declaration order.
+    pub fn selectors(&self) -> &[FlatSelector] {
+        &self.0
+    }
+}
+
 /// Body of a single rule in a compiled [`CascadeStyle`].
 ///
 /// Contains the body of the rule and an optional extra label.
-#[derive(Debug)]
+#[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CascadeStyleRule<K: PropertyKey = RawPropertyKey> {
     /// Specifies whether the selector selects an extra element
     /// attached to the matched node or edge, instead of the node
     /// or edge directly.
     pub extra_label: Option<String>,
 
+    /// Position of the extra element among the extras
+    /// of the same owner, if the rule selects an ordered extra.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_index: Option<usize>,
+
+    /// Specifies whether the selector refers to the
+    /// [lifetime](crate::eval::context::EntityLifetime) of nodes.
+    ///
+    /// Only such rules apply to nodes that have been removed
+    /// from the graph, so that removed nodes do not linger
+    /// in stylesheets that do not style them explicitly.
+    pub selects_by_lifetime: bool,
+
+    /// Condition of the [conditional block](crate::stylesheet::ConditionalBlock)
+    /// that the rule is in, if any.
+    ///
+    /// See [`CascadeContext`](super::CascadeContext) for how conditions are evaluated.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub condition: Option<Expression>,
+
+    /// Name that the rule has been given in the stylesheet, if any.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub name: Option<String>,
+
     /// Properties in the body of the original rule.
     pub properties: Vec<StyleClause<K>>,
 }
@@ -87,6 +211,7 @@ pub struct CascadeStyleRule<K: PropertyKey = RawPropertyKey> {
 /// The input of the machine is program state nodes and edges
 /// in the order they appear in the state graph.
 #[derive(PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FlatSelector {
     /// State machine of the selector.
     ///
@@ -126,6 +251,7 @@ impl From<Selector> for FlatSelector {
 
 /// Transitions of the state machine.
 #[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FlatSelectorSegment {
     /// Transition that matches a program state edge.
     ///
@@ -222,6 +348,12 @@ fn flatten_selector_segment(segment: SelectorSegment, output: &mut Vec<FlatSelec
              *                                       +---------------------------------+
              */
 
+            // An empty alternative never matches
+            if branches.is_empty() {
+                output.push(FlatSelectorSegment::Restrict(Expression::Bool(false)));
+                return;
+            }
+
             // Save the index of the starting state
             // so we can correctly set up branch transitions later
             let starting_index = output.len();
@@ -265,6 +397,7 @@ fn flatten_selector_segment(segment: SelectorSegment, output: &mut Vec<FlatSelec
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::stylesheet::expression::{LimitedEdgeMatcher, LimitedSelector};
     use FlatSelectorSegment::*;
     use aili_model::state::EdgeLabel;
 
@@ -274,6 +407,7 @@ mod test {
             path: SelectorPath(vec![]),
             selects_edge: false,
             extra: None,
+            extra_index: None,
         };
         let expected_flat_selector = FlatSelector {
             path: vec![MatchNode],
@@ -293,6 +427,7 @@ mod test {
             ]),
             selects_edge: false,
             extra: None,
+            extra_index: None,
         };
         let expected_flat_selector = FlatSelector {
             path: vec![
@@ -322,6 +457,7 @@ mod test {
             ]),
             selects_edge: false,
             extra: None,
+            extra_index: None,
         };
         let expected_flat_selector = FlatSelector {
             path: vec![
@@ -353,7 +489,7 @@ mod test {
                     SelectorPath(vec![SelectorSegment::Match(EdgeMatcher::Any)]),
                     SelectorPath(vec![
                         SelectorSegment::Match(EdgeMatcher::AnyNamed),
-                        SelectorSegment::Match(EdgeMatcher::Named("hello".to_owned())),
+                        SelectorSegment::Match(EdgeMatcher::Named("hello".into())),
                     ]),
                     SelectorPath(vec![SelectorSegment::Match(EdgeMatcher::AnyIndex)]),
                 ]),
@@ -361,6 +497,7 @@ mod test {
             ]),
             selects_edge: false,
             extra: None,
+            extra_index: None,
         };
         let expected_flat_selector = FlatSelector {
             path: vec![
@@ -374,7 +511,7 @@ mod test {
                 /* 7 */ MatchNode,
                 MatchEdge(EdgeMatcher::AnyNamed),
                 MatchNode,
-                MatchEdge(EdgeMatcher::Named("hello".to_owned())),
+                MatchEdge(EdgeMatcher::Named("hello".into())),
                 Jump(14),
                 /* 12 */ MatchNode,
                 MatchEdge(EdgeMatcher::AnyIndex),
@@ -389,6 +526,23 @@ mod test {
         );
     }
 
+    #[test]
+    fn flatten_empty_branch() {
+        let original_selector = Selector {
+            path: SelectorPath(vec![SelectorSegment::Branch(vec![])]),
+            selects_edge: false,
+            extra: None,
+            extra_index: None,
+        };
+        let expected_flat_selector = FlatSelector {
+            path: vec![Restrict(Expression::Bool(false)), MatchNode],
+        };
+        assert_eq!(
+            FlatSelector::from(original_selector),
+            expected_flat_selector
+        );
+    }
+
     #[test]
     fn flatten_branched_and_repeated_selector() {
         let original_selector = Selector {
@@ -409,6 +563,7 @@ mod test {
             ]),
             selects_edge: false,
             extra: None,
+            extra_index: None,
         };
         let expected_flat_selector = FlatSelector {
             path: vec![
@@ -436,4 +591,38 @@ mod test {
             expected_flat_selector
         );
     }
+
+    #[test]
+    fn layer_index_in_select_index_is_detected() {
+        let selected_by_layer = Expression::Select(Box::new(LimitedSelector::from_path([
+            LimitedEdgeMatcher::DynIndex(Expression::MagicVariable(MagicVariableKey::LayerIndex)),
+        ])));
+        let stylesheet = Stylesheet::<RawPropertyKey>::new(vec![StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: StyleKey::Variable("--item".to_owned()),
+                value: selected_by_layer,
+            }],
+        }]);
+        assert!(CascadeStyle::from(stylesheet).uses_layer_indices());
+    }
+
+    #[test]
+    fn lifetime_in_select_origin_is_detected() {
+        let origin = Expression::Conditional(
+            Box::new(Expression::MagicVariable(MagicVariableKey::Lifetime)),
+            Box::new(Expression::Select(Box::default())),
+            Box::new(Expression::Unset),
+        );
+        let condition =
+            Expression::Select(Box::new(LimitedSelector::from_path([]).with_origin(origin)));
+        let stylesheet = Stylesheet::<RawPropertyKey>::new(vec![StyleRule {
+            selector: Selector::from_path(SelectorPath(vec![SelectorSegment::Condition(
+                condition,
+            )])),
+            properties: Vec::new(),
+        }]);
+        let style = CascadeStyle::from(stylesheet);
+        assert!(style.rules[0].selects_by_lifetime);
+    }
 }
@@ -1,7 +1,10 @@
 //! Contexts for expression evaluation.
 
 use super::variable_pool::VariablePool;
-use aili_model::state::{EdgeLabel, NodeTypeId, ProgramStateGraph, ProgramStateNode};
+use aili_model::state::{
+    EdgeLabel, NodeTypeId, ProgramStateGraph, ProgramStateNode, RootedProgramStateGraph,
+};
+use std::collections::{HashMap, VecDeque};
 
 /// Provides stateful context for expression evaluation.
 pub struct EvaluationContext<'a, T>
@@ -16,6 +19,11 @@ where
     /// [`Select`](crate::stylesheet::expression::Expression::Select) expressions.
     pub select_origin: Option<T::NodeId>,
 
+    /// Node from which the select origin was reached,
+    /// to which [`LimitedEdgeMatcher::Parent`](crate::stylesheet::expression::LimitedEdgeMatcher::Parent)
+    /// steps back.
+    pub parent: Option<T::NodeId>,
+
     /// Variable pool in which [`Variable`](crate::stylesheet::expression::Expression::Variable)
     /// expressions should be evaluated.
     pub variable_pool: Option<&'a VariablePool<&'a str, T::NodeId>>,
@@ -34,6 +42,95 @@ where
     /// [`MagicVariableKey::EdgeDiscriminator`](crate::stylesheet::expression::MagicVariableKey::EdgeDiscriminator)
     /// should resolve to.
     pub edge_discriminator: Option<usize>,
+
+    /// Values that
+    /// [`MagicVariableKey::Depth`](crate::stylesheet::expression::MagicVariableKey::Depth),
+    /// [`MagicVariableKey::SiblingIndex`](crate::stylesheet::expression::MagicVariableKey::SiblingIndex),
+    /// and [`MagicVariableKey::LayerIndex`](crate::stylesheet::expression::MagicVariableKey::LayerIndex)
+    /// should resolve to.
+    pub traversal_position: Option<TraversalPosition>,
+
+    /// Value that
+    /// [`MagicVariableKey::Lifetime`](crate::stylesheet::expression::MagicVariableKey::Lifetime)
+    /// should resolve to.
+    pub lifetime: Option<EntityLifetime>,
+}
+
+/// Position of a node in a traversal of a state graph.
+///
+/// Stylesheets can use the position to lay out elements
+/// without relying on the layout engine of a renderer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TraversalPosition {
+    /// Number of edges between the root and the node.
+    pub depth: usize,
+
+    /// Index of the edge that leads to the node
+    /// among the outgoing edges of the preceding node.
+    pub sibling_index: usize,
+
+    /// Index of the node among all nodes at the same distance
+    /// from the root, in breadth-first order.
+    ///
+    /// Unlike the other components, it only depends on the graph,
+    /// not on the path along which the node has been reached.
+    /// See [`layer_indices`].
+    pub layer_index: usize,
+}
+
+/// Assigns each node reachable from the root of a graph
+/// its [layer index](TraversalPosition::layer_index).
+///
+/// Nodes are ordered by a breadth-first search from the root
+/// that follows the outgoing edges of each node in order.
+/// Each node is counted once, at its shortest distance from the root.
+pub fn layer_indices<T: RootedProgramStateGraph>(graph: &T) -> HashMap<T::NodeId, usize> {
+    let root = graph.root();
+    let mut layer_counters = vec![1];
+    let mut layer_indices = HashMap::from([(root.clone(), 0)]);
+    let mut open = VecDeque::from([(root, 0)]);
+    while let Some((node, distance)) = open.pop_front() {
+        let Some(node_ref) = graph.get(&node) else {
+            continue;
+        };
+        for (_, successor) in node_ref.successors() {
+            if layer_indices.contains_key(&successor) {
+                continue;
+            }
+            if layer_counters.len() <= distance + 1 {
+                layer_counters.push(0);
+            }
+            layer_indices.insert(successor.clone(), layer_counters[distance + 1]);
+            layer_counters[distance + 1] += 1;
+            open.push_back((successor, distance + 1));
+        }
+    }
+    layer_indices
+}
+
+/// Change in the presence of a node in a state graph
+/// since a stylesheet was last applied to the graph.
+///
+/// Stylesheets can use the lifetime to give entities
+/// distinct appearances as they enter or leave the visualization.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum EntityLifetime {
+    /// The node was not present when the stylesheet was last applied.
+    New,
+
+    /// The node is no longer present in the graph.
+    /// It has only been retained so its removal can be visualized.
+    Removed,
+}
+
+impl EntityLifetime {
+    /// Gets the name by which stylesheets refer to the lifetime.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Removed => "removed",
+        }
+    }
 }
 
 impl<'a, T> EvaluationContext<'a, T>
@@ -52,10 +149,13 @@ where
         Self {
             graph: Some(graph),
             select_origin: Some(select_origin),
+            parent: None,
             variable_pool: None,
             edge_index: None,
             edge_discriminator: None,
             edge_name: None,
+            traversal_position: None,
+            lifetime: None,
         }
     }
 
@@ -67,6 +167,14 @@ where
         self
     }
 
+    /// Adds the node from which the select origin was reached
+    /// for evaluating selectors that step back with
+    /// [`LimitedEdgeMatcher::Parent`](crate::stylesheet::expression::LimitedEdgeMatcher::Parent).
+    pub fn with_optional_parent(mut self, parent: Option<T::NodeId>) -> Self {
+        self.parent = parent;
+        self
+    }
+
     /// Adds an edge index for evaluating the
     /// [`MagicVariableKey::EdgeIndex`](crate::stylesheet::expression::MagicVariableKey::EdgeIndex)
     /// magic variable.
@@ -91,6 +199,26 @@ where
         self
     }
 
+    /// Adds a traversal position for evaluating the
+    /// [`MagicVariableKey::Depth`](crate::stylesheet::expression::MagicVariableKey::Depth),
+    /// [`MagicVariableKey::SiblingIndex`](crate::stylesheet::expression::MagicVariableKey::SiblingIndex),
+    /// and [`MagicVariableKey::LayerIndex`](crate::stylesheet::expression::MagicVariableKey::LayerIndex)
+    /// magic variables.
+    pub fn with_traversal_position(mut self, position: TraversalPosition) -> Self {
+        self.traversal_position = Some(position);
+        self
+    }
+
+    /// Adds a lifetime for evaluating the
+    /// [`MagicVariableKey::Lifetime`](crate::stylesheet::expression::MagicVariableKey::Lifetime)
+    /// magic variable.
+    ///
+    /// Nodes that have neither been added nor removed have no lifetime.
+    pub fn with_lifetime(mut self, lifetime: Option<EntityLifetime>) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
     /// Adds edge parameters for evaluating magic variables
     /// based on the edge label of the preceding edge.
     pub fn with_preceding_edge(mut self, edge_label: &'a EdgeLabel) -> Self {
@@ -105,6 +233,9 @@ where
                 self.edge_name = Some(name);
                 self.edge_discriminator = Some(*discriminator);
             }
+            EdgeLabel::Custom(name) => {
+                self.edge_name = Some(name);
+            }
             _ => {}
         }
         self
@@ -129,10 +260,13 @@ where
         Self {
             graph: None,
             select_origin: None,
+            parent: None,
             variable_pool: None,
             edge_index: None,
             edge_discriminator: None,
             edge_name: None,
+            traversal_position: None,
+            lifetime: None,
         }
     }
 }
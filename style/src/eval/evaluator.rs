@@ -1,7 +1,14 @@
 //! Main implementation of expression evaluation.
 
-use super::context::EvaluationContext;
-use crate::{selectable::Selectable, stylesheet::expression::*, values::PropertyValue};
+use super::{
+    context::{EvaluationContext, TraversalPosition},
+    variable_pool::VariablePool,
+};
+use crate::{
+    selectable::Selectable,
+    stylesheet::expression::*,
+    values::{EvaluationError, PropertyValue},
+};
 use aili_model::state::*;
 
 /// Helper for evaluating expressions statefully.
@@ -29,6 +36,7 @@ impl<T: ProgramStateGraph> Evaluator<'_, T> {
                     self.evaluate(if_false)
                 }
             }
+            Call(function, arguments) => self.call(function, arguments),
             Variable(name) => self
                 .0
                 .variable_pool
@@ -63,37 +71,132 @@ impl<T: ProgramStateGraph> Evaluator<'_, T> {
                 .map(NodeValue::Uint)
                 .map(PropertyValue::Value)
                 .unwrap_or_default(),
+            MagicVariable(MagicVariableKey::Depth) => self.traversal_position_value(|p| p.depth),
+            MagicVariable(MagicVariableKey::SiblingIndex) => {
+                self.traversal_position_value(|p| p.sibling_index)
+            }
+            MagicVariable(MagicVariableKey::LayerIndex) => {
+                self.traversal_position_value(|p| p.layer_index)
+            }
+            MagicVariable(MagicVariableKey::Lifetime) => self
+                .0
+                .lifetime
+                .map(|lifetime| PropertyValue::String(lifetime.name().to_owned()))
+                .unwrap_or_default(),
+            MagicVariable(MagicVariableKey::Address) => self.current_node_value(|node| {
+                matches!(
+                    node.node_type_class(),
+                    NodeTypeClass::Frame | NodeTypeClass::Ref
+                )
+                .then(|| node.value())
+                .flatten()
+                .map(PropertyValue::Value)
+            }),
+            MagicVariable(MagicVariableKey::TypeClass) => self.current_node_value(|node| {
+                Some(type_class_name(node.node_type_class()).to_owned().into())
+            }),
+            MagicVariable(MagicVariableKey::ChildCount) => {
+                self.current_node_value(|node| Some((node.successors().count() as u64).into()))
+            }
+            MagicVariable(MagicVariableKey::Shared) => {
+                self.current_node_value(|node| Some(node.is_shared().into()))
+            }
         }
     }
 
+    /// Retrieves a property of the current node, if there is one.
+    fn current_node_value(
+        &self,
+        property: impl FnOnce(T::NodeRef<'_>) -> Option<PropertyValue<T::NodeId>>,
+    ) -> PropertyValue<T::NodeId> {
+        self.0
+            .select_origin
+            .as_ref()
+            .and_then(|node_id| self.0.graph.and_then(|graph| graph.get(node_id)))
+            .and_then(property)
+            .unwrap_or_default()
+    }
+
+    /// Evaluates a call to a user-defined function in the context.
+    ///
+    /// The body of the function is evaluated in its own scope,
+    /// where only its parameters are visible.
+    /// Everything else about the context is inherited from the caller.
+    fn call(&self, function: &UserFunction, arguments: &[Expression]) -> PropertyValue<T::NodeId> {
+        let mut variable_pool = VariablePool::new();
+        for (parameter, argument) in function.parameters.iter().zip(arguments) {
+            variable_pool.insert(parameter.as_str(), self.evaluate(argument));
+        }
+        let context = EvaluationContext {
+            graph: self.0.graph,
+            select_origin: self.0.select_origin.clone(),
+            parent: self.0.parent.clone(),
+            variable_pool: Some(&variable_pool),
+            edge_index: self.0.edge_index,
+            edge_name: self.0.edge_name,
+            edge_discriminator: self.0.edge_discriminator,
+            traversal_position: self.0.traversal_position,
+            lifetime: self.0.lifetime,
+        };
+        Evaluator(&context).evaluate(&function.body)
+    }
+
+    /// Retrieves a component of the traversal position, if available.
+    fn traversal_position_value(
+        &self,
+        component: impl FnOnce(&TraversalPosition) -> usize,
+    ) -> PropertyValue<T::NodeId> {
+        self.0
+            .traversal_position
+            .as_ref()
+            .map(component)
+            .map(|value| value as u64)
+            .map(NodeValue::Uint)
+            .map(PropertyValue::Value)
+            .unwrap_or_default()
+    }
+
     /// Evaluates a unary operator expression in the context.
     fn unary_operator(
         &self,
         operator: UnaryOperator,
         operand: PropertyValue<T::NodeId>,
     ) -> PropertyValue<T::NodeId> {
-        use self::NodeValue::*;
+        use self::NodeValue::{Bool, Float, Int, Uint};
         use PropertyValue::*;
         use UnaryOperator::*;
+        // Errors pass through all operators that produce values
+        if let Error(error) = operand
+            && !matches!(operator, Not | NodeIsA(_) | ValueIs(_) | IsSet | IsError)
+        {
+            return Error(error);
+        }
         match operator {
             Plus => match self.coerce_to_value(operand) {
                 Unset => Unset,
                 Value(Int(i)) => i.into(),
                 Value(Uint(u)) => u.into(),
                 Value(Bool(b)) => u64::from(b).into(),
-                String(s) => String(s),
-                Selection(_) => unreachable!(),
+                Value(Float(f)) => f.into(),
+                String(s) | Value(self::NodeValue::String(s)) => String(s),
+                Selection(_) | Error(_) => unreachable!(),
             },
             Minus => match self.coerce_to_value(operand) {
                 Unset => Unset,
-                Value(Int(i)) => i.checked_neg().map(Into::into).unwrap_or_default(),
+                Value(Int(i)) => i
+                    .checked_neg()
+                    .map(Into::into)
+                    .unwrap_or(EvaluationError::Overflow.into()),
                 Value(Uint(u)) => i64::try_from(u)
                     .map(std::ops::Neg::neg)
                     .map(Into::into)
-                    .unwrap_or_default(),
+                    .unwrap_or(EvaluationError::Overflow.into()),
                 Value(Bool(b)) => (-i64::from(b)).into(),
-                String(_) => Unset,
-                Selection(_) => unreachable!(),
+                Value(Float(f)) => (-f).into(),
+                String(_) | Value(self::NodeValue::String(_)) => {
+                    EvaluationError::TypeMismatch.into()
+                }
+                Selection(_) | Error(_) => unreachable!(),
             },
             Not => (!operand.is_truthy()).into(),
             NodeValue => self
@@ -106,12 +209,99 @@ impl<T: ProgramStateGraph> Evaluator<'_, T> {
                 .map(|node| node.node_type_class())
                 .is_some_and(|cls| cls == type_class)
                 .into(),
+            ValueIs(predicate) => NumericValue::try_from(self.coerce_to_value(operand))
+                .is_ok_and(|value| match predicate {
+                    ValuePredicate::Zero => value.as_float() == 0.0,
+                    ValuePredicate::Negative => value.as_float() < 0.0,
+                })
+                .into(),
             NodeTypeName => self
                 .coerce_to_node(operand)
                 .and_then(|node| node.node_type_id().map(|tid| tid.type_name().to_owned()))
                 .map(Into::into)
                 .unwrap_or_default(),
+            NodeCustomClass => self
+                .coerce_to_node(operand)
+                .and_then(|node| node.custom_type_class().map(str::to_owned))
+                .map(Into::into)
+                .unwrap_or_default(),
+            NodeSourceFile => self
+                .coerce_to_node(operand)
+                .and_then(|node| node.source_location())
+                .map(|location| location.file.into())
+                .unwrap_or_default(),
+            NodeSourceLine => self
+                .coerce_to_node(operand)
+                .and_then(|node| node.source_location())
+                .map(|location| location.line.into())
+                .unwrap_or_default(),
             IsSet => (!matches!(operand, PropertyValue::Unset)).into(),
+            IsError => matches!(operand, PropertyValue::Error(_)).into(),
+            Hex => match self.integer_bits(operand) {
+                Ok((bits, true)) => format!("-{:#x}", (bits as i64).unsigned_abs()).into(),
+                Ok((bits, false)) => format!("{bits:#x}").into(),
+                Err(value) => value,
+            },
+            AsSigned(width) => match self.integer_bits(operand) {
+                Ok((bits, _)) => {
+                    // Shift the sign bit of the narrow integer to the top and back
+                    let shift = 64 - width.clamp(1, 64);
+                    (((bits << shift) as i64) >> shift).into()
+                }
+                Err(value) => value,
+            },
+            AsUnsigned(width) => match self.integer_bits(operand) {
+                Ok((bits, _)) => match width.clamp(1, 64) {
+                    64 => bits.into(),
+                    width => (bits & ((1 << width) - 1)).into(),
+                },
+                Err(value) => value,
+            },
+            PadLeft(width, fill) => self
+                .padding(operand, width, fill)
+                .map(|(padding, value)| padding + &value)
+                .map(Into::into)
+                .unwrap_or_default(),
+            PadRight(width, fill) => self
+                .padding(operand, width, fill)
+                .map(|(padding, value)| value + &padding)
+                .map(Into::into)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Converts a value to string and constructs the fill characters
+    /// that need to be added to it to make it a given number of characters long.
+    ///
+    /// Returns [`None`] if the value is unset.
+    fn padding(
+        &self,
+        value: PropertyValue<T::NodeId>,
+        width: usize,
+        fill: char,
+    ) -> Option<(String, String)> {
+        let value = match self.coerce_to_value(value) {
+            PropertyValue::Unset => return None,
+            value => value.to_string(),
+        };
+        let padding =
+            std::iter::repeat_n(fill, width.saturating_sub(value.chars().count())).collect();
+        Some((padding, value))
+    }
+
+    /// Gets the 64-bit two's complement representation of an integer
+    /// and whether it is negative.
+    ///
+    /// Values that are not integers are rejected with the value
+    /// that an integer operator evaluates to when applied to them.
+    fn integer_bits(
+        &self,
+        value: PropertyValue<T::NodeId>,
+    ) -> Result<(u64, bool), PropertyValue<T::NodeId>> {
+        match NumericValue::try_from(self.coerce_to_value(value))? {
+            NumericValue::Int(i) => Ok((i as u64, i < 0)),
+            NumericValue::Uint(u) => Ok((u, false)),
+            NumericValue::Float(_) => Err(EvaluationError::TypeMismatch.into()),
         }
     }
 
@@ -133,6 +323,11 @@ impl<T: ProgramStateGraph> Evaluator<'_, T> {
         // For all other operators, extract values from selections
         let left = self.coerce_to_value(left);
         let right = self.coerce_to_value(right);
+        // Errors propagate through all other operators, the left one first
+        if let (PropertyValue::Error(error), _) | (_, PropertyValue::Error(error)) = (&left, &right)
+        {
+            return PropertyValue::Error(*error);
+        }
         match operator {
             Plus => {
                 // If either argument is a string, this is string concatenation.
@@ -143,63 +338,75 @@ impl<T: ProgramStateGraph> Evaluator<'_, T> {
                 }
                 // Try to coerce to numeric values
                 match (left, right).try_into() {
-                    Ok(NumericPair::Int(left, right)) => {
-                        left.checked_add(right).map(Into::into).unwrap_or_default()
-                    }
-                    Ok(NumericPair::Uint(left, right)) => {
-                        left.checked_add(right).map(Into::into).unwrap_or_default()
-                    }
-                    Err(_) => PropertyValue::Unset,
+                    Ok(NumericPair::Int(left, right)) => left
+                        .checked_add(right)
+                        .map(Into::into)
+                        .unwrap_or(EvaluationError::Overflow.into()),
+                    Ok(NumericPair::Uint(left, right)) => left
+                        .checked_add(right)
+                        .map(Into::into)
+                        .unwrap_or(EvaluationError::Overflow.into()),
+                    Ok(NumericPair::Float(left, right)) => (left + right).into(),
+                    Err(value) => value,
                 }
             }
             Minus => match (left, right).try_into() {
-                Ok(NumericPair::Int(left, right)) => {
-                    left.checked_sub(right).map(Into::into).unwrap_or_default()
-                }
+                Ok(NumericPair::Int(left, right)) => left
+                    .checked_sub(right)
+                    .map(Into::into)
+                    .unwrap_or(EvaluationError::Overflow.into()),
                 Ok(NumericPair::Uint(left, right)) => {
+                    // Unsigned subtraction goes below zero
+                    // when the right operand is larger
                     if left < right {
                         right
                             .checked_sub(left)
                             .and_then(|x| i64::try_from(x).ok())
                             .map(std::ops::Neg::neg)
                             .map(Into::into)
-                            .unwrap_or_default()
+                            .unwrap_or(EvaluationError::Overflow.into())
                     } else {
-                        left.checked_sub(right).map(Into::into).unwrap_or_default()
+                        (left - right).into()
                     }
                 }
-                Err(_) => PropertyValue::Unset,
+                Ok(NumericPair::Float(left, right)) => (left - right).into(),
+                Err(value) => value,
             },
             Mul => match (left, right).try_into() {
-                Ok(NumericPair::Int(left, right)) => {
-                    left.checked_mul(right).map(Into::into).unwrap_or_default()
-                }
-                Ok(NumericPair::Uint(left, right)) => {
-                    left.checked_mul(right).map(Into::into).unwrap_or_default()
-                }
-                Err(_) => PropertyValue::Unset,
-            },
-            Div => match (left, right).try_into() {
                 Ok(NumericPair::Int(left, right)) => left
-                    .checked_div_euclid(right)
+                    .checked_mul(right)
                     .map(Into::into)
-                    .unwrap_or_default(),
+                    .unwrap_or(EvaluationError::Overflow.into()),
                 Ok(NumericPair::Uint(left, right)) => left
+                    .checked_mul(right)
+                    .map(Into::into)
+                    .unwrap_or(EvaluationError::Overflow.into()),
+                Ok(NumericPair::Float(left, right)) => (left * right).into(),
+                Err(value) => value,
+            },
+            Div => match (left, right).try_into() {
+                Ok(
+                    NumericPair::Int(_, 0) | NumericPair::Uint(_, 0) | NumericPair::Float(_, 0.0),
+                ) => EvaluationError::DivisionByZero.into(),
+                Ok(NumericPair::Int(left, right)) => left
                     .checked_div_euclid(right)
                     .map(Into::into)
-                    .unwrap_or_default(),
-                Err(_) => PropertyValue::Unset,
+                    .unwrap_or(EvaluationError::Overflow.into()),
+                Ok(NumericPair::Uint(left, right)) => left.div_euclid(right).into(),
+                Ok(NumericPair::Float(left, right)) => (left / right).into(),
+                Err(value) => value,
             },
             Mod => match (left, right).try_into() {
+                Ok(
+                    NumericPair::Int(_, 0) | NumericPair::Uint(_, 0) | NumericPair::Float(_, 0.0),
+                ) => EvaluationError::DivisionByZero.into(),
                 Ok(NumericPair::Int(left, right)) => left
                     .checked_rem_euclid(right)
                     .map(Into::into)
-                    .unwrap_or_default(),
-                Ok(NumericPair::Uint(left, right)) => left
-                    .checked_rem_euclid(right)
-                    .map(Into::into)
-                    .unwrap_or_default(),
-                Err(_) => PropertyValue::Unset,
+                    .unwrap_or(EvaluationError::Overflow.into()),
+                Ok(NumericPair::Uint(left, right)) => left.rem_euclid(right).into(),
+                Ok(NumericPair::Float(left, right)) => left.rem_euclid(right).into(),
+                Err(value) => value,
             },
             Eq => (left == right).into(),
             Ne => (left != right).into(),
@@ -213,20 +420,29 @@ impl<T: ProgramStateGraph> Evaluator<'_, T> {
 
     /// Evaluates a select expression in the context.
     fn select(&self, selector: &LimitedSelector) -> Option<Selectable<T::NodeId>> {
-        let mut current_node = if let Some(explicit_origin) = &selector.origin {
-            Self::coerce_to_node_id(&self.evaluate(explicit_origin))?.clone()
+        let (mut current_node, mut parent) = if let Some(explicit_origin) = &selector.origin {
+            // The predecessor of an explicit origin is not known
+            let origin = Self::coerce_to_node_id(&self.evaluate(explicit_origin))?.clone();
+            (origin, None)
         } else {
-            self.0.select_origin.clone()?
+            (self.0.select_origin.clone()?, self.0.parent.clone())
         };
+        // Nodes the selector has passed through, so it can step back
+        let mut visited = Vec::new();
         for segment in &selector.path {
+            if let LimitedEdgeMatcher::Parent = segment {
+                current_node = visited.pop().or_else(|| parent.take())?;
+                continue;
+            }
             let edge_label = self.resolve_selector_segment(segment)?;
             // Find the edge specified (unambiguously) by the segmens
             // and move to the node at its end
-            current_node = self
+            let next_node = self
                 .0
                 .graph?
                 .get(&current_node)
                 .and_then(|node| node.get_successor(&edge_label))?;
+            visited.push(std::mem::replace(&mut current_node, next_node));
         }
         let mut selection = Selectable::node(current_node);
         selection.extra_label = selector.extra_label.clone();
@@ -249,6 +465,8 @@ impl<T: ProgramStateGraph> Evaluator<'_, T> {
                     _ => None,
                 }
             }
+            // Stepping back is not a move along an edge
+            LimitedEdgeMatcher::Parent => None,
         }
     }
 
@@ -299,6 +517,8 @@ enum NumericPair {
     Int(i64, i64),
     /// Two values coercible to unsigned integer.
     Uint(u64, u64),
+    /// Two values coercible to float, at least one of which is a float.
+    Float(f64, f64),
 }
 
 /// Helper for binary arithmetic operators.
@@ -307,29 +527,65 @@ enum NumericValue {
     Int(i64),
     /// Value coercible to unsigned integer.
     Uint(u64),
+    /// Floating point value.
+    Float(f64),
 }
 
+impl NumericValue {
+    /// Converts the value to a float, possibly losing precision.
+    fn as_float(&self) -> f64 {
+        match *self {
+            Self::Int(i) => i as f64,
+            Self::Uint(u) => u as f64,
+            Self::Float(f) => f,
+        }
+    }
+}
+
+/// Values that cannot be coerced to numbers are rejected
+/// with the value that an arithmetic operator evaluates to
+/// when applied to them.
 impl<T: NodeId> TryFrom<PropertyValue<T>> for NumericValue {
-    type Error = ();
+    type Error = PropertyValue<T>;
     fn try_from(value: PropertyValue<T>) -> Result<Self, Self::Error> {
         match value {
             PropertyValue::Value(NodeValue::Int(i)) => Ok(Self::Int(i)),
             PropertyValue::Value(NodeValue::Uint(u)) => Ok(Self::Uint(u)),
             PropertyValue::Value(NodeValue::Bool(b)) => Ok(Self::Uint(b.into())),
-            _ => Err(()),
+            PropertyValue::Value(NodeValue::Float(f)) => Ok(Self::Float(f)),
+            PropertyValue::Unset => Err(PropertyValue::Unset),
+            PropertyValue::Error(error) => Err(error.into()),
+            _ => Err(EvaluationError::TypeMismatch.into()),
         }
     }
 }
 
 impl<T: NodeId> TryFrom<(PropertyValue<T>, PropertyValue<T>)> for NumericPair {
-    type Error = ();
+    type Error = PropertyValue<T>;
     fn try_from(value: (PropertyValue<T>, PropertyValue<T>)) -> Result<Self, Self::Error> {
         use NumericValue::*;
+        let overflow = |_| PropertyValue::Error(EvaluationError::Overflow);
         match (value.0.try_into()?, value.1.try_into()?) {
+            (a @ Float(_), b) | (a, b @ Float(_)) => Ok(Self::Float(a.as_float(), b.as_float())),
             (Int(a), Int(b)) => Ok(Self::Int(a, b)),
-            (Uint(a), Int(b)) => Ok(Self::Int(a.try_into().map_err(|_| ())?, b)),
-            (Int(a), Uint(b)) => Ok(Self::Int(a, b.try_into().map_err(|_| ())?)),
+            (Uint(a), Int(b)) => Ok(Self::Int(a.try_into().map_err(overflow)?, b)),
+            (Int(a), Uint(b)) => Ok(Self::Int(a, b.try_into().map_err(overflow)?)),
             (Uint(a), Uint(b)) => Ok(Self::Uint(a, b)),
         }
     }
 }
+
+/// Gets the name by which selectors refer to a node type class.
+fn type_class_name(type_class: NodeTypeClass) -> &'static str {
+    match type_class {
+        NodeTypeClass::Root => "root",
+        NodeTypeClass::Frame => "frame",
+        NodeTypeClass::Atom => "val",
+        NodeTypeClass::Struct => "struct",
+        NodeTypeClass::Union => "union",
+        NodeTypeClass::Array => "arr",
+        NodeTypeClass::Ref => "ref",
+        NodeTypeClass::Custom => "custom",
+        NodeTypeClass::Truncated => "truncated",
+    }
+}
@@ -2,6 +2,7 @@
 
 pub mod cascade;
 pub mod eval;
+pub mod select;
 pub mod selectable;
 pub mod stylesheet;
 pub mod values;
@@ -0,0 +1,277 @@
+//! Ad-hoc selector queries against state graphs.
+//!
+//! Stylesheets match all of their selectors in a single traversal
+//! and assign properties to the matched entities.
+//! [`query`] only matches one selector and reports which entities
+//! it matches, which is useful for searching through a graph.
+//! [`CascadeStyle::rules_matching`] goes the other way and reports
+//! which rules of a stylesheet match one entity.
+
+use crate::{
+    cascade::{CascadeStyle, SelectionCaret, SelectorResolver},
+    eval::{
+        context::{EvaluationContext, TraversalPosition, layer_indices},
+        evaluate,
+        variable_pool::VariablePool,
+    },
+    selectable::Selectable,
+    stylesheet::{
+        PropertyKey, RawPropertyKey, StyleKey, StyleRule, Stylesheet, selector::Selector,
+    },
+};
+use aili_model::state::{EdgeLabel, ProgramStateNode, RootedProgramStateGraph};
+use std::collections::{HashMap, HashSet};
+
+/// Finds all entities in a graph that are matched by a selector.
+///
+/// Entities are listed in the order in which they are first
+/// reached by a depth-first traversal from the root,
+/// and each entity is listed once, even if the selector
+/// matches it along multiple paths. If the selector selects
+/// an extra entity, the extra label is included.
+///
+/// Selectors are evaluated the same way as in a stylesheet,
+/// except no variables are defined and entities have no
+/// [lifetime](crate::stylesheet::expression::MagicVariableKey::Lifetime).
+pub fn query<T: RootedProgramStateGraph>(
+    graph: &T,
+    selector: Selector,
+) -> impl Iterator<Item = Selectable<T::NodeId>> {
+    let style = CascadeStyle::<RawPropertyKey>::from(Stylesheet::new(vec![StyleRule {
+        selector,
+        properties: Vec::new(),
+    }]));
+    let mut traversal = Traversal::new(&style, graph, None);
+    traversal.run();
+    let mut seen = HashSet::new();
+    traversal
+        .matches
+        .into_iter()
+        .map(|(_, selected)| selected)
+        .filter(|selected| seen.insert(selected.clone()))
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+impl<K: PropertyKey> CascadeStyle<K> {
+    /// Finds the indices of all rules whose selectors
+    /// match an entity in a graph, in declaration order.
+    ///
+    /// Of the rules that match, later rules take precedence
+    /// over earlier ones when they assign the same property.
+    ///
+    /// Only the parts of the graph from which the entity
+    /// is reachable are traversed, so this is much cheaper
+    /// than applying the whole stylesheet when the entity
+    /// is close to the root. Entities have no
+    /// [lifetime](crate::stylesheet::expression::MagicVariableKey::Lifetime)
+    /// and nodes that are skipped are not counted towards the
+    /// [layer index](crate::stylesheet::expression::MagicVariableKey::LayerIndex).
+    pub fn rules_matching<T: RootedProgramStateGraph>(
+        &self,
+        graph: &T,
+        entity: &Selectable<T::NodeId>,
+    ) -> Vec<usize> {
+        let mut relevant_nodes = ancestors(graph, &entity.node_id);
+        if let Some(edge_label) = &entity.edge_label {
+            // Edges are selected at their target nodes
+            let successor = graph
+                .get(&entity.node_id)
+                .and_then(|node| node.get_successor(edge_label));
+            relevant_nodes.extend(successor);
+        }
+        let mut traversal = Traversal::new(self, graph, Some(relevant_nodes));
+        traversal.run();
+        let mut rules = traversal
+            .matches
+            .into_iter()
+            .filter(|(_, selected)| selected == entity)
+            .map(|(rule_index, _)| rule_index)
+            .collect::<Vec<_>>();
+        rules.sort();
+        rules.dedup();
+        rules
+    }
+}
+
+/// Finds all nodes from which a node is reachable,
+/// including the node itself, provided that it is reachable from the root.
+fn ancestors<T: RootedProgramStateGraph>(graph: &T, target: &T::NodeId) -> HashSet<T::NodeId> {
+    let mut predecessors = HashMap::<_, Vec<_>>::new();
+    let mut visited = HashSet::from([graph.root()]);
+    let mut open = vec![graph.root()];
+    while let Some(node) = open.pop() {
+        let Some(node_ref) = graph.get(&node) else {
+            continue;
+        };
+        for (_, successor) in node_ref.successors() {
+            predecessors
+                .entry(successor.clone())
+                .or_default()
+                .push(node.clone());
+            if visited.insert(successor.clone()) {
+                open.push(successor);
+            }
+        }
+    }
+    if !visited.contains(target) {
+        return HashSet::new();
+    }
+    let mut ancestors = HashSet::from([target.clone()]);
+    let mut open = vec![target.clone()];
+    while let Some(node) = open.pop() {
+        for predecessor in predecessors.get(&node).into_iter().flatten() {
+            if ancestors.insert(predecessor.clone()) {
+                open.push(predecessor.clone());
+            }
+        }
+    }
+    ancestors
+}
+
+/// Depth-first traversal of a graph that collects
+/// the entities matched by the rules of a stylesheet.
+///
+/// Variables are assigned by matched rules the same way
+/// as when the stylesheet is applied, so selectors that
+/// read them match the same entities.
+struct Traversal<'a, 'g, K: PropertyKey, T: RootedProgramStateGraph> {
+    /// The graph being traversed.
+    graph: &'g T,
+
+    /// The stylesheet whose rules are matched.
+    style: &'a CascadeStyle<K>,
+
+    /// Resolver that tracks the selectors.
+    resolver: SelectorResolver<'a, T::NodeId>,
+
+    /// Variables that are active at the moment.
+    variable_pool: VariablePool<&'a str, T::NodeId>,
+
+    /// Layer indices of all nodes in the graph,
+    /// if the stylesheet refers to them.
+    layer_indices: Option<HashMap<T::NodeId, usize>>,
+
+    /// Nodes that the traversal may enter, if it is restricted.
+    relevant_nodes: Option<HashSet<T::NodeId>>,
+
+    /// Indices of matched rules and the entities they matched,
+    /// in order of traversal.
+    matches: Vec<(usize, Selectable<T::NodeId>)>,
+}
+
+impl<'a, 'g, K: PropertyKey, T: RootedProgramStateGraph> Traversal<'a, 'g, K, T> {
+    fn new(
+        style: &'a CascadeStyle<K>,
+        graph: &'g T,
+        relevant_nodes: Option<HashSet<T::NodeId>>,
+    ) -> Self {
+        Self {
+            graph,
+            style,
+            resolver: SelectorResolver::new(style.selector_machine()),
+            variable_pool: VariablePool::new(),
+            layer_indices: style.uses_layer_indices().then(|| layer_indices(graph)),
+            relevant_nodes,
+            matches: Vec::new(),
+        }
+    }
+
+    fn run(&mut self) {
+        let root = self.graph.root();
+        if self.is_relevant(&root) {
+            self.run_from(root, None, 0, 0);
+        }
+    }
+
+    /// Checks whether the traversal may enter a node.
+    fn is_relevant(&self, node: &T::NodeId) -> bool {
+        self.relevant_nodes
+            .as_ref()
+            .is_none_or(|relevant_nodes| relevant_nodes.contains(node))
+    }
+
+    /// Traverses depth-first from a specified node and evaluates the selectors.
+    fn run_from(
+        &mut self,
+        node: T::NodeId,
+        previous: Option<(T::NodeId, &EdgeLabel)>,
+        depth: usize,
+        sibling_index: usize,
+    ) {
+        let position = TraversalPosition {
+            depth,
+            sibling_index,
+            layer_index: self
+                .layer_indices
+                .as_ref()
+                .and_then(|layer_indices| layer_indices.get(&node))
+                .copied()
+                .unwrap_or_default(),
+        };
+        let previous_edge = previous.as_ref().map(|(_, edge_label)| *edge_label);
+        let previous_node = previous.as_ref().map(|(node, _)| node.clone());
+
+        let context = EvaluationContext::from_graph(self.graph, node.clone())
+            .with_variables(&self.variable_pool)
+            .with_optional_parent(previous_node.clone())
+            .with_optional_preceding_edge(previous_edge)
+            .with_traversal_position(position);
+        let mut matched_rules = self.resolver.resolve_node(node.clone(), &context);
+
+        // Resolve rules in the same order as the cascade does
+        matched_rules.sort_by_cached_key(|&(rule_index, caret)| {
+            let has_extra = self.style.rule_at(rule_index).extra_label.is_some();
+            (caret == SelectionCaret::Node, has_extra, rule_index)
+        });
+        for (rule_index, caret) in matched_rules {
+            let mut selected = match (caret, &previous) {
+                (SelectionCaret::Node, _) => Selectable::node(node.clone()),
+                (SelectionCaret::PrecedingEdge, Some((previous_node, edge_label))) => {
+                    Selectable::edge(previous_node.clone(), (*edge_label).clone())
+                }
+                (SelectionCaret::PrecedingEdge, None) => continue,
+            };
+            let rule = self.style.rule_at(rule_index);
+            selected.extra_label = rule.extra_label.clone();
+            // Variables assigned by extras do not leak outside of them
+            if !selected.is_extra() {
+                for property in &rule.properties {
+                    if let StyleKey::Variable(name) = &property.key {
+                        let context = EvaluationContext::from_graph(self.graph, node.clone())
+                            .with_variables(&self.variable_pool)
+                            .with_optional_parent(previous_node.clone())
+                            .with_optional_preceding_edge(previous_edge)
+                            .with_traversal_position(position);
+                        let value = evaluate(&property.value, &context);
+                        self.variable_pool.insert(name, value);
+                    }
+                }
+            }
+            self.matches.push((rule_index, selected));
+        }
+
+        // We stop once there is nothing else to explore
+        if !self.resolver.has_edges_to_resolve() {
+            return;
+        }
+        let Some(node_ref) = self.graph.get(&node) else {
+            return;
+        };
+        for (sibling_index, (edge_label, successor)) in node_ref.successors().enumerate() {
+            if !self.is_relevant(&successor) {
+                continue;
+            }
+            self.variable_pool.push();
+            self.resolver.push_edge(edge_label);
+            self.run_from(
+                successor,
+                Some((node.clone(), edge_label)),
+                depth + 1,
+                sibling_index,
+            );
+            self.resolver.pop_edge();
+            self.variable_pool.pop();
+        }
+    }
+}
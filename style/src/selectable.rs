@@ -9,6 +9,7 @@ use aili_model::state::{EdgeLabel, NodeId};
 /// Additionally, "extra" entities can be attached to them,
 /// which allows each state entity to produce multiple visual elements.
 #[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Selectable<T: NodeId> {
     /// Identifier of the state node that has either been selected
     /// or is the starting point of the selected edge.
@@ -0,0 +1,597 @@
+//! Variant of the stylesheet syntax tree whose identifiers
+//! and string literals borrow from the source text.
+//!
+//! Parsers can build this tree without allocating a string
+//! for every name they encounter, which adds up for large stylesheets.
+//! Names that cannot be borrowed, such as string literals
+//! with escape sequences, are owned.
+//!
+//! Each type mirrors its counterpart in [`expression`], [`selector`],
+//! or [`stylesheet`](super), and can be converted to it with `into_owned`.
+
+use super::{expression, selector};
+use aili_model::{capability::CapabilitySet, state::EdgeLabel};
+use derive_more::From;
+use expression::{BinaryOperator, MagicVariableKey, UnaryOperator};
+use std::{borrow::Cow, collections::BTreeMap, ops::Range};
+
+/// Borrowed variant of [`Expression`](expression::Expression).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Expression<'src> {
+    /// Variable invoked by its name.
+    Variable(Cow<'src, str>),
+
+    /// Built-in magic variable-like value.
+    MagicVariable(MagicVariableKey),
+
+    /// The `unset` (null) literal.
+    Unset,
+
+    /// Boolean literal.
+    Bool(bool),
+
+    /// String literal.
+    String(Cow<'src, str>),
+
+    /// Integer literal.
+    Int(u64),
+
+    /// Selectable element refered to by its selector.
+    Select(Box<LimitedSelector<'src>>),
+
+    /// Unary operator expression.
+    UnaryOperator(UnaryOperator, Box<Expression<'src>>),
+
+    /// Binary operator expression.
+    BinaryOperator(Box<Expression<'src>>, BinaryOperator, Box<Expression<'src>>),
+
+    /// Conditional ternary operator expression.
+    Conditional(
+        Box<Expression<'src>>,
+        Box<Expression<'src>>,
+        Box<Expression<'src>>,
+    ),
+
+    /// Call to a user-defined function.
+    Call(Box<UserFunction<'src>>, Vec<Expression<'src>>),
+}
+
+impl Expression<'_> {
+    /// Converts the expression to an [`Expression`](expression::Expression)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> expression::Expression {
+        use expression::Expression as Owned;
+        match self {
+            Self::Variable(name) => Owned::Variable(name.into_owned()),
+            Self::MagicVariable(key) => Owned::MagicVariable(key),
+            Self::Unset => Owned::Unset,
+            Self::Bool(value) => Owned::Bool(value),
+            Self::String(value) => Owned::String(value.into_owned()),
+            Self::Int(value) => Owned::Int(value),
+            Self::Select(selector) => Owned::Select(selector.into_owned().into()),
+            Self::UnaryOperator(operator, operand) => {
+                Owned::UnaryOperator(operator, operand.into_owned().into())
+            }
+            Self::BinaryOperator(left, operator, right) => Owned::BinaryOperator(
+                left.into_owned().into(),
+                operator,
+                right.into_owned().into(),
+            ),
+            Self::Conditional(condition, if_true, if_false) => Owned::Conditional(
+                condition.into_owned().into(),
+                if_true.into_owned().into(),
+                if_false.into_owned().into(),
+            ),
+            Self::Call(function, arguments) => Owned::Call(
+                function.into_owned().into(),
+                arguments.into_iter().map(Expression::into_owned).collect(),
+            ),
+        }
+    }
+}
+
+impl From<expression::Expression> for Expression<'_> {
+    fn from(value: expression::Expression) -> Self {
+        use expression::Expression as Owned;
+        match value {
+            Owned::Variable(name) => Self::Variable(name.into()),
+            Owned::MagicVariable(key) => Self::MagicVariable(key),
+            Owned::Unset => Self::Unset,
+            Owned::Bool(value) => Self::Bool(value),
+            Owned::String(value) => Self::String(value.into()),
+            Owned::Int(value) => Self::Int(value),
+            Owned::Select(selector) => Self::Select(Box::new((*selector).into())),
+            Owned::UnaryOperator(operator, operand) => {
+                Self::UnaryOperator(operator, Box::new((*operand).into()))
+            }
+            Owned::BinaryOperator(left, operator, right) => Self::BinaryOperator(
+                Box::new((*left).into()),
+                operator,
+                Box::new((*right).into()),
+            ),
+            Owned::Conditional(condition, if_true, if_false) => Self::Conditional(
+                Box::new((*condition).into()),
+                Box::new((*if_true).into()),
+                Box::new((*if_false).into()),
+            ),
+            Owned::Call(function, arguments) => Self::Call(
+                Box::new((*function).into()),
+                arguments.into_iter().map(Into::into).collect(),
+            ),
+        }
+    }
+}
+
+/// Borrowed variant of [`UserFunction`](expression::UserFunction).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UserFunction<'src> {
+    /// Name of the function.
+    pub name: Cow<'src, str>,
+
+    /// Names of the variables that the arguments are assigned to.
+    pub parameters: Vec<Cow<'src, str>>,
+
+    /// Expression that evaluates to the function's return value.
+    pub body: Expression<'src>,
+}
+
+impl<'src> UserFunction<'src> {
+    /// Constructs a function from its name, parameters, and body.
+    pub fn new(
+        name: Cow<'src, str>,
+        parameters: Vec<Cow<'src, str>>,
+        body: Expression<'src>,
+    ) -> Self {
+        Self {
+            name,
+            parameters,
+            body,
+        }
+    }
+
+    /// Converts the function to a [`UserFunction`](expression::UserFunction)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> expression::UserFunction {
+        expression::UserFunction::new(
+            self.name.into_owned(),
+            self.parameters.into_iter().map(Cow::into_owned).collect(),
+            self.body.into_owned(),
+        )
+    }
+}
+
+impl From<expression::UserFunction> for UserFunction<'_> {
+    fn from(value: expression::UserFunction) -> Self {
+        Self::new(
+            value.name.into(),
+            value.parameters.into_iter().map(Into::into).collect(),
+            value.body.into(),
+        )
+    }
+}
+
+/// Borrowed variant of [`LimitedEdgeMatcher`](expression::LimitedEdgeMatcher).
+#[derive(Clone, PartialEq, Eq, From, Debug)]
+pub enum LimitedEdgeMatcher<'src> {
+    /// Matches a statically defined edge label.
+    Exact(EdgeLabel),
+
+    /// Matches an [`EdgeLabel::Index`] with the index
+    /// specified by an expression which is evaluated
+    /// dynamically.
+    DynIndex(Expression<'src>),
+
+    /// Steps back to the node that the selector came from.
+    Parent,
+}
+
+impl LimitedEdgeMatcher<'_> {
+    /// Converts the matcher to a [`LimitedEdgeMatcher`](expression::LimitedEdgeMatcher)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> expression::LimitedEdgeMatcher {
+        match self {
+            Self::Exact(label) => expression::LimitedEdgeMatcher::Exact(label),
+            Self::DynIndex(index) => expression::LimitedEdgeMatcher::DynIndex(index.into_owned()),
+            Self::Parent => expression::LimitedEdgeMatcher::Parent,
+        }
+    }
+}
+
+impl From<expression::LimitedEdgeMatcher> for LimitedEdgeMatcher<'_> {
+    fn from(value: expression::LimitedEdgeMatcher) -> Self {
+        match value {
+            expression::LimitedEdgeMatcher::Exact(label) => Self::Exact(label),
+            expression::LimitedEdgeMatcher::DynIndex(index) => Self::DynIndex(index.into()),
+            expression::LimitedEdgeMatcher::Parent => Self::Parent,
+        }
+    }
+}
+
+/// Borrowed variant of [`LimitedSelector`](expression::LimitedSelector).
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct LimitedSelector<'src> {
+    /// Path that must be matched in order to select something.
+    pub path: Vec<LimitedEdgeMatcher<'src>>,
+
+    /// Overrides the origin from where the selector should be evaluated.
+    pub origin: Option<Box<Expression<'src>>>,
+
+    /// Specifies whether the selector selects an extra element
+    /// attached to the matched node or edge, instead of the node
+    /// or edge directly.
+    pub extra_label: Option<Cow<'src, str>>,
+}
+
+impl<'src> LimitedSelector<'src> {
+    /// Shorthand for constructing a limited selector that matches a node.
+    pub fn from_path(path: impl IntoIterator<Item = LimitedEdgeMatcher<'src>>) -> Self {
+        Self {
+            path: Vec::from_iter(path),
+            origin: None,
+            extra_label: None,
+        }
+    }
+
+    /// Overrides the selection origin with an expression value.
+    pub fn with_origin(mut self, origin: Expression<'src>) -> Self {
+        self.origin = Some(Box::new(origin));
+        self
+    }
+
+    /// Adds an extra label to an existing selector.
+    pub fn with_extra(mut self, extra_label: Cow<'src, str>) -> Self {
+        self.extra_label = Some(extra_label);
+        self
+    }
+
+    /// Converts the selector to a [`LimitedSelector`](expression::LimitedSelector)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> expression::LimitedSelector {
+        expression::LimitedSelector {
+            path: self
+                .path
+                .into_iter()
+                .map(LimitedEdgeMatcher::into_owned)
+                .collect(),
+            origin: self.origin.map(|origin| origin.into_owned().into()),
+            extra_label: self.extra_label.map(Cow::into_owned),
+        }
+    }
+}
+
+impl From<expression::LimitedSelector> for LimitedSelector<'_> {
+    fn from(value: expression::LimitedSelector) -> Self {
+        Self {
+            path: value.path.into_iter().map(Into::into).collect(),
+            origin: value.origin.map(|origin| Box::new((*origin).into())),
+            extra_label: value.extra_label.map(Into::into),
+        }
+    }
+}
+
+/// Borrowed variant of [`EdgeMatcher`](selector::EdgeMatcher).
+#[derive(Clone, PartialEq, Eq, From, Debug)]
+pub enum EdgeMatcher<'src> {
+    /// Matches all edges.
+    Any,
+
+    /// Matches a particular edge label.
+    #[from]
+    Exact(EdgeLabel),
+
+    /// Matches all [`EdgeLabel::Index`] edges.
+    AnyIndex,
+
+    /// Matches all [`EdgeLabel::Named`] edges.
+    AnyNamed,
+
+    /// Matches all [`EdgeLabel::Named`] edges with a particular name,
+    /// but with any secondary index.
+    Named(Cow<'src, str>),
+}
+
+impl EdgeMatcher<'_> {
+    /// Converts the matcher to an [`EdgeMatcher`](selector::EdgeMatcher)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> selector::EdgeMatcher {
+        match self {
+            Self::Any => selector::EdgeMatcher::Any,
+            Self::Exact(label) => selector::EdgeMatcher::Exact(label),
+            Self::AnyIndex => selector::EdgeMatcher::AnyIndex,
+            Self::AnyNamed => selector::EdgeMatcher::AnyNamed,
+            Self::Named(name) => selector::EdgeMatcher::Named(name.into_owned()),
+        }
+    }
+}
+
+/// Borrowed variant of [`SelectorSegment`](selector::SelectorSegment).
+#[derive(PartialEq, Eq, Debug)]
+pub enum SelectorSegment<'src> {
+    /// Matches an edge.
+    Match(EdgeMatcher<'src>),
+
+    /// Matches a full selector path zero or more times.
+    AnyNumberOfTimes(SelectorPath<'src>),
+
+    /// Matches at least one of a set of selector paths.
+    Branch(Vec<SelectorPath<'src>>),
+
+    /// Matches if a condition evaluates to a [truthy](crate::values::PropertyValue::is_truthy)
+    /// value.
+    Condition(Expression<'src>),
+}
+
+impl SelectorSegment<'_> {
+    /// Shorthand for a completely unrestricted selector segment
+    /// that matches all edges to any depth.
+    pub fn anything_any_number_of_times() -> Self {
+        Self::AnyNumberOfTimes([SelectorSegment::Match(EdgeMatcher::Any)].into())
+    }
+
+    /// Converts the segment to a [`SelectorSegment`](selector::SelectorSegment)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> selector::SelectorSegment {
+        match self {
+            Self::Match(matcher) => selector::SelectorSegment::Match(matcher.into_owned()),
+            Self::AnyNumberOfTimes(path) => {
+                selector::SelectorSegment::AnyNumberOfTimes(path.into_owned())
+            }
+            Self::Branch(paths) => selector::SelectorSegment::Branch(
+                paths.into_iter().map(SelectorPath::into_owned).collect(),
+            ),
+            Self::Condition(condition) => {
+                selector::SelectorSegment::Condition(condition.into_owned())
+            }
+        }
+    }
+}
+
+/// Borrowed variant of [`SelectorPath`](selector::SelectorPath).
+#[derive(PartialEq, Eq, From, Default, Debug)]
+#[from(forward)]
+pub struct SelectorPath<'src>(pub Vec<SelectorSegment<'src>>);
+
+impl SelectorPath<'_> {
+    /// Converts the path to a [`SelectorPath`](selector::SelectorPath)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> selector::SelectorPath {
+        selector::SelectorPath(
+            self.0
+                .into_iter()
+                .map(SelectorSegment::into_owned)
+                .collect(),
+        )
+    }
+}
+
+/// Borrowed variant of [`Selector`](selector::Selector).
+#[derive(PartialEq, Eq, Default, Debug)]
+pub struct Selector<'src> {
+    /// Path that must match in order to select something.
+    pub path: SelectorPath<'src>,
+
+    /// Specifies whether the selector selects the last
+    /// edge it matched instead of the node at the end of that edge.
+    pub selects_edge: bool,
+
+    /// Specifies whether the selector selects an extra element
+    /// attached to the matched node or edge, instead of the node
+    /// or edge directly.
+    pub extra: Option<Cow<'src, str>>,
+
+    /// Position of the [`extra`](Selector::extra) element
+    /// among the extras of the same owner.
+    pub extra_index: Option<usize>,
+}
+
+impl<'src> Selector<'src> {
+    /// Shorthand for constructing a selector that matches a node.
+    pub fn from_path(path: SelectorPath<'src>) -> Self {
+        Self {
+            path,
+            selects_edge: false,
+            extra: None,
+            extra_index: None,
+        }
+    }
+
+    /// Shorthand for setting the [`Selector::selects_edge`] flag.
+    pub fn selecting_edge(self) -> Self {
+        Self {
+            selects_edge: true,
+            ..self
+        }
+    }
+
+    /// Shorthand for adding an [`Selector::extra`] tag.
+    pub fn with_extra(self, extra: Cow<'src, str>) -> Self {
+        Self {
+            extra: Some(extra),
+            ..self
+        }
+    }
+
+    /// Shorthand for adding an [`Selector::extra_index`].
+    pub fn with_extra_index(self, extra_index: usize) -> Self {
+        Self {
+            extra_index: Some(extra_index),
+            ..self
+        }
+    }
+
+    /// Converts the selector to a [`Selector`](selector::Selector)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> selector::Selector {
+        selector::Selector {
+            path: self.path.into_owned(),
+            selects_edge: self.selects_edge,
+            extra: self.extra.map(Cow::into_owned),
+            extra_index: self.extra_index,
+        }
+    }
+}
+
+/// Borrowed variant of [`RawPropertyKey`](super::RawPropertyKey).
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum RawPropertyKey<'src> {
+    /// Property identified by a name.
+    Property(Cow<'src, str>),
+
+    /// Property identified by a name with quoted name.
+    QuotedProperty(Cow<'src, str>),
+
+    /// Property whose name is prefixed with another name,
+    /// separated by a slash.
+    FragmentProperty(Cow<'src, str>, Cow<'src, str>),
+}
+
+impl RawPropertyKey<'_> {
+    /// Converts the key to a [`RawPropertyKey`](super::RawPropertyKey)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> super::RawPropertyKey {
+        match self {
+            Self::Property(name) => super::RawPropertyKey::Property(name.into_owned()),
+            Self::QuotedProperty(name) => super::RawPropertyKey::QuotedProperty(name.into_owned()),
+            Self::FragmentProperty(prefix, name) => {
+                super::RawPropertyKey::FragmentProperty(prefix.into_owned(), name.into_owned())
+            }
+        }
+    }
+}
+
+/// Borrowed variant of [`StyleKey`](super::StyleKey).
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum StyleKey<'src> {
+    /// Assigns value to a property of the selected entity.
+    Property(RawPropertyKey<'src>),
+
+    /// Assigns values to a cascade variable.
+    Variable(Cow<'src, str>),
+}
+
+impl StyleKey<'_> {
+    /// Converts the key to a [`StyleKey`](super::StyleKey)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> super::StyleKey {
+        match self {
+            Self::Property(key) => super::StyleKey::Property(key.into_owned()),
+            Self::Variable(name) => super::StyleKey::Variable(name.into_owned()),
+        }
+    }
+}
+
+/// Borrowed variant of [`StyleClause`](super::StyleClause).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StyleClause<'src> {
+    /// Name of the property or variable to assign.
+    pub key: StyleKey<'src>,
+
+    /// Expression that evaluates to the value that should
+    /// be assigned to the property.
+    pub value: Expression<'src>,
+}
+
+impl StyleClause<'_> {
+    /// Converts the clause to a [`StyleClause`](super::StyleClause)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> super::StyleClause {
+        super::StyleClause {
+            key: self.key.into_owned(),
+            value: self.value.into_owned(),
+        }
+    }
+}
+
+/// Borrowed variant of [`StyleRule`](super::StyleRule).
+#[derive(PartialEq, Eq, Default, Debug)]
+pub struct StyleRule<'src> {
+    /// Selector that determines what entities the rule applies to.
+    pub selector: Selector<'src>,
+
+    /// Properties assigned to each entity that matches.
+    pub properties: Vec<StyleClause<'src>>,
+}
+
+impl StyleRule<'_> {
+    /// Converts the rule to a [`StyleRule`](super::StyleRule)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> super::StyleRule {
+        super::StyleRule {
+            selector: self.selector.into_owned(),
+            properties: self
+                .properties
+                .into_iter()
+                .map(StyleClause::into_owned)
+                .collect(),
+        }
+    }
+}
+
+/// Borrowed variant of [`ConditionalBlock`](super::ConditionalBlock).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ConditionalBlock<'src> {
+    /// Condition that must be truthy for the rules to apply.
+    pub condition: Expression<'src>,
+
+    /// Indices of the rules in the block.
+    pub rules: Range<usize>,
+}
+
+impl ConditionalBlock<'_> {
+    /// Converts the block to a [`ConditionalBlock`](super::ConditionalBlock)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> super::ConditionalBlock {
+        super::ConditionalBlock {
+            condition: self.condition.into_owned(),
+            rules: self.rules,
+        }
+    }
+}
+
+/// Borrowed variant of [`Stylesheet`](super::Stylesheet).
+#[derive(PartialEq, Eq, Default, Debug)]
+pub struct Stylesheet<'src> {
+    /// Rules of the stylesheet, in declaration order.
+    pub rules: Vec<StyleRule<'src>>,
+
+    /// Capabilities that a state graph must provide
+    /// for the stylesheet to be applicable to it.
+    pub requirements: CapabilitySet,
+
+    /// Blocks of rules that only apply under a condition,
+    /// in declaration order.
+    pub conditional_blocks: Vec<ConditionalBlock<'src>>,
+
+    /// Names of the rules that have been given one, by rule index.
+    pub rule_names: BTreeMap<usize, Cow<'src, str>>,
+}
+
+impl<'src> Stylesheet<'src> {
+    /// Constructs a stylesheet with no requirements,
+    /// no conditional blocks, and no named rules.
+    pub fn new(rules: Vec<StyleRule<'src>>) -> Self {
+        Self {
+            rules,
+            ..Self::default()
+        }
+    }
+
+    /// Converts the stylesheet to a [`Stylesheet`](super::Stylesheet)
+    /// that does not borrow from the source.
+    pub fn into_owned(self) -> super::Stylesheet {
+        super::Stylesheet {
+            rules: self.rules.into_iter().map(StyleRule::into_owned).collect(),
+            requirements: self.requirements,
+            conditional_blocks: self
+                .conditional_blocks
+                .into_iter()
+                .map(ConditionalBlock::into_owned)
+                .collect(),
+            rule_names: self
+                .rule_names
+                .into_iter()
+                .map(|(index, name)| (index, name.into_owned()))
+                .collect(),
+        }
+    }
+}
@@ -10,11 +10,13 @@ use derive_more::{Debug, From};
 /// - Variable invocations
 /// - [`LimitedSelector`] queries
 /// - Compound operator expressions
+/// - Calls to [user-defined functions](UserFunction)
 ///
 /// All expressions are without side effects.
 /// Variable invocation and selector query expressions
 /// are however stateful.
 #[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expression {
     /// Variable invoked by its name.
     #[debug("{_0}")]
@@ -57,6 +59,131 @@ pub enum Expression {
     /// Otherwise resolves to its third argument.
     #[debug("({_0:?} ? {_1:?} : {_2:?})")]
     Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+
+    /// Call to a user-defined function.
+    ///
+    /// Arguments are evaluated in the caller's scope
+    /// and assigned to the function's parameters,
+    /// then the function's body is evaluated in a scope
+    /// that only contains the parameters.
+    #[debug("{}({})", _0.name, _1.iter().map(|arg| format!("{arg:?}")).collect::<Vec<_>>().join(", "))]
+    Call(Box<UserFunction>, Vec<Expression>),
+}
+
+impl Expression {
+    /// Checks whether the expression refers to a magic variable.
+    pub fn uses_magic_variable(&self, key: MagicVariableKey) -> bool {
+        match self {
+            Self::MagicVariable(variable) => *variable == key,
+            Self::Select(selector) => {
+                selector
+                    .origin
+                    .as_ref()
+                    .is_some_and(|origin| origin.uses_magic_variable(key))
+                    || selector.path.iter().any(|segment| match segment {
+                        LimitedEdgeMatcher::DynIndex(index) => index.uses_magic_variable(key),
+                        LimitedEdgeMatcher::Exact(_) | LimitedEdgeMatcher::Parent => false,
+                    })
+            }
+            Self::UnaryOperator(_, operand) => operand.uses_magic_variable(key),
+            Self::BinaryOperator(left, _, right) => {
+                left.uses_magic_variable(key) || right.uses_magic_variable(key)
+            }
+            Self::Conditional(condition, if_true, if_false) => {
+                condition.uses_magic_variable(key)
+                    || if_true.uses_magic_variable(key)
+                    || if_false.uses_magic_variable(key)
+            }
+            Self::Call(function, arguments) => {
+                function.body.uses_magic_variable(key)
+                    || arguments.iter().any(|arg| arg.uses_magic_variable(key))
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks whether the expression reads any user or magic variables.
+    ///
+    /// Expressions that do not read variables always evaluate
+    /// the same way when evaluated over the same graph
+    /// with the same select origin.
+    pub fn reads_variables(&self) -> bool {
+        self.reads_scope_variables(true)
+    }
+
+    /// Checks whether the expression reads any magic variables,
+    /// or user variables if they are in scope.
+    ///
+    /// Bodies of [user-defined functions](UserFunction) only see
+    /// their own parameters, which are determined by the arguments,
+    /// so user variables they read are not counted.
+    fn reads_scope_variables(&self, user_variables: bool) -> bool {
+        match self {
+            Self::Variable(_) => user_variables,
+            Self::MagicVariable(_) => true,
+            Self::Unset | Self::Bool(_) | Self::Int(_) | Self::String(_) => false,
+            Self::Select(selector) => {
+                selector
+                    .origin
+                    .as_ref()
+                    .is_some_and(|origin| origin.reads_scope_variables(user_variables))
+                    || selector.path.iter().any(|segment| match segment {
+                        LimitedEdgeMatcher::Exact(_) => false,
+                        LimitedEdgeMatcher::DynIndex(index) => {
+                            index.reads_scope_variables(user_variables)
+                        }
+                        // The predecessor depends on the traversal,
+                        // just like the magic variables do
+                        LimitedEdgeMatcher::Parent => true,
+                    })
+            }
+            Self::UnaryOperator(_, operand) => operand.reads_scope_variables(user_variables),
+            Self::BinaryOperator(left, _, right) => {
+                left.reads_scope_variables(user_variables)
+                    || right.reads_scope_variables(user_variables)
+            }
+            Self::Conditional(condition, if_true, if_false) => {
+                condition.reads_scope_variables(user_variables)
+                    || if_true.reads_scope_variables(user_variables)
+                    || if_false.reads_scope_variables(user_variables)
+            }
+            Self::Call(function, arguments) => {
+                function.body.reads_scope_variables(false)
+                    || arguments
+                        .iter()
+                        .any(|arg| arg.reads_scope_variables(user_variables))
+            }
+        }
+    }
+}
+
+/// Function defined by a stylesheet.
+///
+/// Functions are resolved when the stylesheet is parsed,
+/// so every [`Call`](Expression::Call) expression
+/// carries its own copy of the function it calls.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UserFunction {
+    /// Name of the function.
+    pub name: String,
+
+    /// Names of the variables that the arguments are assigned to.
+    pub parameters: Vec<String>,
+
+    /// Expression that evaluates to the function's return value.
+    pub body: Expression,
+}
+
+impl UserFunction {
+    /// Constructs a function from its name, parameters, and body.
+    pub fn new(name: String, parameters: Vec<String>, body: Expression) -> Self {
+        Self {
+            name,
+            parameters,
+            body,
+        }
+    }
 }
 
 /// Identifiers of variables that can be invoked within expressions.
@@ -71,22 +198,65 @@ pub enum VariableKey {
 
 /// Identifiers of built-in interpreter magic variables.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MagicVariableKey {
     /// If an [`EdgeLabel::Index`] edge has just been traversed,
     /// this variable contains the index associated with the edge.
     EdgeIndex,
 
-    /// If an [`EdgeLabel::Named`] edge has just been traversed,
+    /// If an [`EdgeLabel::Named`] or [`EdgeLabel::Custom`] edge has just been traversed,
     /// this variable contains the name associated with the edge.
     EdgeName,
 
     /// If an [`EdgeLabel::Named`] edge has just been traversed,
     /// this variable contains the discriminator associated with the edge.
     EdgeDiscriminator,
+
+    /// Number of edges that have been traversed from the root
+    /// to reach the current node.
+    Depth,
+
+    /// Index of the edge that has just been traversed
+    /// among the outgoing edges of its source node.
+    SiblingIndex,
+
+    /// Index of the current node among all nodes at the same
+    /// distance from the root, in breadth-first order of the graph.
+    ///
+    /// In trees, the distance is the same as [`Depth`](MagicVariableKey::Depth).
+    /// Unlike the depth, the layer index does not depend on the path
+    /// along which the node has been reached, or on which parts
+    /// of the graph the selectors have skipped.
+    LayerIndex,
+
+    /// Name of the [`EntityLifetime`](crate::eval::context::EntityLifetime)
+    /// of the current node, if it has been added to or removed from the graph
+    /// since the stylesheet was last applied.
+    Lifetime,
+
+    /// Address of the current node, if it is a [`Frame`](NodeTypeClass::Frame)
+    /// or a [`Ref`](NodeTypeClass::Ref) that has a value,
+    /// since the values of these nodes are addresses.
+    Address,
+
+    /// Name of the [`NodeTypeClass`] of the current node, as selectors refer to it, such as `"frame"` or `"arr"`.
+    TypeClass,
+
+    /// Number of outgoing edges of the current node.
+    ChildCount,
+
+    /// Whether the current node stands in for several identical objects,
+    /// as reported by [`ProgramStateNode::is_shared`](aili_model::state::ProgramStateNode::is_shared).
+    Shared,
 }
 
 /// Identifier of the operator in a [`UnaryOperator`](Expression::UnaryOperator) expression.
+///
+/// Operators that return [`Bool`](aili_model::state::NodeValue::Bool)s
+/// accept [`Error`](crate::values::PropertyValue::Error)s like any other value.
+/// All other operators return errors unchanged.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnaryOperator {
     /// Coerces a value to a number if possible.
     ///
@@ -99,6 +269,7 @@ pub enum UnaryOperator {
     /// | [`Uint`](aili_model::state::NodeValue::Uint)             | Argument is unchanged                                |
     /// | [`Bool`](aili_model::state::NodeValue::Bool)             | [`Uint`](aili_model::state::NodeValue::Uint), 0 or 1 |
     /// | [`Selection`](crate::values::PropertyValue::Selection) | Equivalent to `+val(x)`                              |
+    /// | [`Error`](crate::values::PropertyValue::Error)         | Argument is unchanged                                |
     #[debug("+")]
     Plus,
 
@@ -108,11 +279,12 @@ pub enum UnaryOperator {
     /// | Argument                                                 | Return value                                                                                                       |
     /// |----------------------------------------------------------|--------------------------------------------------------------------------------------------------------------------|
     /// | [`Unset`](crate::values::PropertyValue::Unset)         | [`Unset`](crate::values::PropertyValue::Unset)                                                                   |
-    /// | [`String`](crate::values::PropertyValue::String)       | [`Unset`](crate::values::PropertyValue::Unset),                                                                  |
-    /// | [`Int`](aili_model::state::NodeValue::Int)               | [`Int`](aili_model::state::NodeValue::Int) or [`Unset`](crate::values::PropertyValue::Unset) in case of overflow |
-    /// | [`Uint`](aili_model::state::NodeValue::Uint)             | [`Int`](aili_model::state::NodeValue::Int) or [`Unset`](crate::values::PropertyValue::Unset) in case of overflow |
+    /// | [`String`](crate::values::PropertyValue::String)       | [`TypeMismatch`](crate::values::EvaluationError::TypeMismatch)                                                     |
+    /// | [`Int`](aili_model::state::NodeValue::Int)               | [`Int`](aili_model::state::NodeValue::Int) or [`Overflow`](crate::values::EvaluationError::Overflow)               |
+    /// | [`Uint`](aili_model::state::NodeValue::Uint)             | [`Int`](aili_model::state::NodeValue::Int) or [`Overflow`](crate::values::EvaluationError::Overflow)               |
     /// | [`Bool`](aili_model::state::NodeValue::Bool)             | [`Int`](aili_model::state::NodeValue::Int), 0 or -1                                                                |
     /// | [`Selection`](crate::values::PropertyValue::Selection) | Equivalent to `-val(x)`                                                                                            |
+    /// | [`Error`](crate::values::PropertyValue::Error)         | Argument is unchanged                                                                                              |
     #[debug("-")]
     Minus,
 
@@ -143,6 +315,16 @@ pub enum UnaryOperator {
     #[debug("is-{_0:?}")]
     NodeIsA(NodeTypeClass),
 
+    /// Checks whether a value satisfies a given predicate.
+    ///
+    /// ## Return Values
+    /// [`Bool`](aili_model::state::NodeValue::Bool). True if the argument
+    /// can be coerced to a numeric value that satisfies the predicate,
+    /// false otherwise. If the argument is a [`Selection`](crate::values::PropertyValue::Selection),
+    /// it is first evaluated (equivalent to using the [`NodeValue`](UnaryOperator::NodeValue) operator).
+    #[debug("is-{_0:?}")]
+    ValueIs(ValuePredicate),
+
     /// Gets the name of state node's type.
     ///
     /// ## Return Values
@@ -153,6 +335,37 @@ pub enum UnaryOperator {
     #[debug("typename")]
     NodeTypeName,
 
+    /// Gets the name of state node's custom type class.
+    ///
+    /// ## Return Values
+    /// [`String`](crate::values::PropertyValue::String) containing the name of the
+    /// [custom type class](aili_model::state::ProgramStateNode::custom_type_class)
+    /// of the argument if it is a selection of a node
+    /// of type [`Custom`](aili_model::state::NodeTypeClass::Custom).
+    /// [`Unset`](crate::values::PropertyValue::Unset) otherwise.
+    #[debug("classname")]
+    NodeCustomClass,
+
+    /// Gets the name of the source file that a state node is located in.
+    ///
+    /// ## Return Values
+    /// [`String`](crate::values::PropertyValue::String) containing the file name of the
+    /// [source location](aili_model::state::ProgramStateNode::source_location)
+    /// of the argument if it is a selection of a node that has one.
+    /// [`Unset`](crate::values::PropertyValue::Unset) otherwise.
+    #[debug("source-file")]
+    NodeSourceFile,
+
+    /// Gets the number of the source line that a state node is located at.
+    ///
+    /// ## Return Values
+    /// [`Uint`](aili_model::state::NodeValue::Uint) containing the line number of the
+    /// [source location](aili_model::state::ProgramStateNode::source_location)
+    /// of the argument if it is a selection of a node that has one.
+    /// [`Unset`](crate::values::PropertyValue::Unset) otherwise.
+    #[debug("source-line")]
+    NodeSourceLine,
+
     /// Checks whether a value is defined.
     ///
     /// ## Return Values
@@ -160,10 +373,114 @@ pub enum UnaryOperator {
     /// is [`Unset`](crate::values::PropertyValue::Unset), true otherwise.
     #[debug("isset")]
     IsSet,
+
+    /// Checks whether a value could not be calculated.
+    ///
+    /// ## Return Values
+    /// [`Bool`](aili_model::state::NodeValue::Bool). True if the argument
+    /// is an [`Error`](crate::values::PropertyValue::Error), false otherwise.
+    #[debug("iserror")]
+    IsError,
+
+    /// Formats an integer in hexadecimal notation.
+    ///
+    /// ## Return Values
+    /// [`String`](crate::values::PropertyValue::String) with the value of the argument
+    /// in lowercase hexadecimal notation prefixed with `0x`, such as `0xff`.
+    /// Negative numbers are prefixed with `-0x` instead; use [`AsUnsigned`](UnaryOperator::AsUnsigned)
+    /// first to format them in two's complement.
+    ///
+    /// [`TypeMismatch`](crate::values::EvaluationError::TypeMismatch) if the argument
+    /// is not an integer or a [`Bool`](aili_model::state::NodeValue::Bool).
+    /// [`Unset`](crate::values::PropertyValue::Unset) if the argument is unset.
+    /// If the argument is a [`Selection`](crate::values::PropertyValue::Selection),
+    /// it is first evaluated (equivalent to using the [`NodeValue`](UnaryOperator::NodeValue) operator).
+    #[debug("hex")]
+    Hex,
+
+    /// Reinterprets the lowest bits of an integer as a signed integer
+    /// of a given width in bits, between 1 and 64.
+    ///
+    /// Values read from a debugger are signed or unsigned depending
+    /// on their type, so, for example, an `unsigned int` with all bits set
+    /// is not equal to `-1`, but converting it to a 32-bit signed integer is.
+    ///
+    /// ## Return Values
+    /// [`Int`](aili_model::state::NodeValue::Int) whose two's complement representation
+    /// is the sign-extended lowest bits of the argument.
+    ///
+    /// [`TypeMismatch`](crate::values::EvaluationError::TypeMismatch) if the argument
+    /// is not an integer or a [`Bool`](aili_model::state::NodeValue::Bool).
+    /// [`Unset`](crate::values::PropertyValue::Unset) if the argument is unset.
+    /// If the argument is a [`Selection`](crate::values::PropertyValue::Selection),
+    /// it is first evaluated (equivalent to using the [`NodeValue`](UnaryOperator::NodeValue) operator).
+    #[debug("as-signed<{_0}>")]
+    AsSigned(u32),
+
+    /// Reinterprets the lowest bits of an integer as an unsigned integer
+    /// of a given width in bits, between 1 and 64.
+    ///
+    /// ## Return Values
+    /// [`Uint`](aili_model::state::NodeValue::Uint) that is equal to the
+    /// lowest bits of the two's complement representation of the argument.
+    ///
+    /// [`TypeMismatch`](crate::values::EvaluationError::TypeMismatch) if the argument
+    /// is not an integer or a [`Bool`](aili_model::state::NodeValue::Bool).
+    /// [`Unset`](crate::values::PropertyValue::Unset) if the argument is unset.
+    /// If the argument is a [`Selection`](crate::values::PropertyValue::Selection),
+    /// it is first evaluated (equivalent to using the [`NodeValue`](UnaryOperator::NodeValue) operator).
+    #[debug("as-unsigned<{_0}>")]
+    AsUnsigned(u32),
+
+    /// Converts a value to string and pads it from the left
+    /// with a fill character to a given number of characters.
+    ///
+    /// ## Return Values
+    /// [`String`](crate::values::PropertyValue::String) that consists of the argument
+    /// converted to string, preceded by as many fill characters as needed
+    /// to make it at least as long as required. Numbers are always formatted
+    /// the same way, regardless of locale.
+    ///
+    /// [`Unset`](crate::values::PropertyValue::Unset) if the argument is unset.
+    /// If the argument is a [`Selection`](crate::values::PropertyValue::Selection),
+    /// it is first evaluated (equivalent to using the [`NodeValue`](UnaryOperator::NodeValue) operator).
+    #[debug("pad-left<{_0}, {_1:?}>")]
+    PadLeft(usize, char),
+
+    /// Converts a value to string and pads it from the right
+    /// with a fill character to a given number of characters.
+    ///
+    /// ## Return Values
+    /// Same as [`PadLeft`](UnaryOperator::PadLeft), except the fill
+    /// characters follow the value.
+    #[debug("pad-right<{_0}, {_1:?}>")]
+    PadRight(usize, char),
+}
+
+/// Predicate on a numeric value tested by the [`ValueIs`](UnaryOperator::ValueIs) operator.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ValuePredicate {
+    /// The value is equal to zero.
+    ///
+    /// Pointers are represented by their addresses,
+    /// so this is also true for null pointers.
+    #[debug("zero")]
+    Zero,
+
+    /// The value is less than zero.
+    #[debug("negative")]
+    Negative,
 }
 
 /// Identifier of the operator in a [`BinaryOperator`](Expression::BinaryOperator) expression.
+///
+/// If either argument of any operator except [`And`](BinaryOperator::And)
+/// and [`Or`](BinaryOperator::Or) is an [`Error`](crate::values::PropertyValue::Error),
+/// the operator returns it without looking at the other argument.
+/// If both are errors, the left one is returned.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinaryOperator {
     /// Arithmetic addition or string concatenation.
     ///
@@ -174,8 +491,10 @@ pub enum BinaryOperator {
     ///   to string and the arguments are concatenated. [`Unset`](crate::values::PropertyValue::Unset) converts
     ///   to empty string and [`Bool`](aili_model::state::NodeValue::Bool) values convert to `"true"` and `"false"`.
     /// - Otwerwise, if both arguments can be coerced to numeric types, they are added.
-    ///   [`Unset`](crate::values::PropertyValue::Unset) is returned in case of overflow.
-    /// - Otherwise, [`Unset`](crate::values::PropertyValue::Unset) is returned.
+    ///   [`Overflow`](crate::values::EvaluationError::Overflow) is returned in case of overflow.
+    ///   If either argument is [`Float`](aili_model::state::NodeValue::Float), so is the result.
+    /// - Otherwise, [`Unset`](crate::values::PropertyValue::Unset) is returned if either argument is unset
+    ///   and [`TypeMismatch`](crate::values::EvaluationError::TypeMismatch) is returned if it is not.
     #[debug("+")]
     Plus,
 
@@ -185,8 +504,10 @@ pub enum BinaryOperator {
     /// - If either argument is [`Selection`](crate::values::PropertyValue::Selection), it is first evaluated
     ///   (equivalent to using the [`NodeValue`](UnaryOperator::NodeValue) operator).
     /// - Then, if both arguments can be coerced to numeric types, they are subtracted.
-    ///   [`Unset`](crate::values::PropertyValue::Unset) is returned in case of overflow.
-    /// - Otherwise, [`Unset`](crate::values::PropertyValue::Unset) is returned.
+    ///   [`Overflow`](crate::values::EvaluationError::Overflow) is returned in case of overflow.
+    ///   If either argument is [`Float`](aili_model::state::NodeValue::Float), so is the result.
+    /// - Otherwise, [`Unset`](crate::values::PropertyValue::Unset) is returned if either argument is unset
+    ///   and [`TypeMismatch`](crate::values::EvaluationError::TypeMismatch) is returned if it is not.
     #[debug("-")]
     Minus,
 
@@ -196,8 +517,10 @@ pub enum BinaryOperator {
     /// - If either argument is [`Selection`](crate::values::PropertyValue::Selection), it is first evaluated
     ///   (equivalent to using the [`NodeValue`](UnaryOperator::NodeValue) operator).
     /// - Then, if both arguments can be coerced to numeric types, they are multiplied.
-    ///   [`Unset`](crate::values::PropertyValue::Unset) is returned in case of overflow.
-    /// - Otherwise, [`Unset`](crate::values::PropertyValue::Unset) is returned.
+    ///   [`Overflow`](crate::values::EvaluationError::Overflow) is returned in case of overflow.
+    ///   If either argument is [`Float`](aili_model::state::NodeValue::Float), so is the result.
+    /// - Otherwise, [`Unset`](crate::values::PropertyValue::Unset) is returned if either argument is unset
+    ///   and [`TypeMismatch`](crate::values::EvaluationError::TypeMismatch) is returned if it is not.
     #[debug("*")]
     Mul,
 
@@ -207,8 +530,12 @@ pub enum BinaryOperator {
     /// - If either argument is [`Selection`](crate::values::PropertyValue::Selection), it is first evaluated
     ///   (equivalent to using the [`NodeValue`](UnaryOperator::NodeValue) operator).
     /// - Then, if both arguments can be coerced to numeric types, they are divided.
-    ///   [`Unset`](crate::values::PropertyValue::Unset) is returned in case of overflow.
-    /// - Otherwise, [`Unset`](crate::values::PropertyValue::Unset) is returned.
+    ///   [`DivisionByZero`](crate::values::EvaluationError::DivisionByZero) is returned if the right argument is zero
+    ///   and [`Overflow`](crate::values::EvaluationError::Overflow) is returned in case of overflow.
+    ///   If either argument is [`Float`](aili_model::state::NodeValue::Float),
+    ///   floating point division is used instead.
+    /// - Otherwise, [`Unset`](crate::values::PropertyValue::Unset) is returned if either argument is unset
+    ///   and [`TypeMismatch`](crate::values::EvaluationError::TypeMismatch) is returned if it is not.
     #[debug("/")]
     Div,
 
@@ -218,8 +545,12 @@ pub enum BinaryOperator {
     /// - If either argument is [`Selection`](crate::values::PropertyValue::Selection), it is first evaluated
     ///   (equivalent to using the [`NodeValue`](UnaryOperator::NodeValue) operator).
     /// - Then, if both arguments can be coerced to numeric types, they are divided.
-    ///   [`Unset`](crate::values::PropertyValue::Unset) is returned in case of overflow.
-    /// - Otherwise, [`Unset`](crate::values::PropertyValue::Unset) is returned.
+    ///   [`DivisionByZero`](crate::values::EvaluationError::DivisionByZero) is returned if the right argument is zero
+    ///   and [`Overflow`](crate::values::EvaluationError::Overflow) is returned in case of overflow.
+    ///   If either argument is [`Float`](aili_model::state::NodeValue::Float),
+    ///   the Euclidean remainder of floating point division is returned.
+    /// - Otherwise, [`Unset`](crate::values::PropertyValue::Unset) is returned if either argument is unset
+    ///   and [`TypeMismatch`](crate::values::EvaluationError::TypeMismatch) is returned if it is not.
     #[debug("%")]
     Mod,
 
@@ -307,6 +638,7 @@ pub enum BinaryOperator {
 
 /// Edge matcher that can be used with a limited selector.
 #[derive(Clone, PartialEq, Eq, From, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LimitedEdgeMatcher {
     /// Matches a statically defined edge label.
     #[debug("{_0:?}")]
@@ -320,6 +652,16 @@ pub enum LimitedEdgeMatcher {
     /// it rejects all edges.
     #[debug("[({_0:?})]")]
     DynIndex(Expression),
+
+    /// Steps back to the node that the selector came from,
+    /// or to the predecessor of the select origin
+    /// if used before any other matchers.
+    ///
+    /// The predecessor is the node from which the origin
+    /// was reached when the graph was traversed. It is only known
+    /// if the origin has not been overridden.
+    #[debug("..")]
+    Parent,
 }
 
 /// Selector that is limited to a single path
@@ -328,6 +670,7 @@ pub enum LimitedEdgeMatcher {
 ///
 /// These selectors can always unambiguously select at most one entity.
 #[derive(Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LimitedSelector {
     /// Path that must be matched in order to select something.
     pub path: Vec<LimitedEdgeMatcher>,
@@ -1,12 +1,15 @@
 //! Stylesheets that assign properties to state entities
 //! based on the structure of their surroundings.
 
+pub mod borrowed;
 pub mod expression;
 pub mod selector;
 
-use derive_more::Debug;
+use aili_model::capability::CapabilitySet;
+use derive_more::{Debug, Display, Error};
 use expression::Expression;
 use selector::Selector;
+use std::{collections::BTreeMap, ops::Range};
 
 /// Types that can be used as a key for style properties
 pub trait PropertyKey: Clone + Eq + std::fmt::Debug + std::hash::Hash {}
@@ -16,6 +19,7 @@ impl<T> PropertyKey for T where T: Clone + Eq + std::fmt::Debug + std::hash::Has
 /// [`PropertyKey`] that consists of raw string keys
 /// for properties and fragment properties.
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RawPropertyKey {
     /// Property identified by a name.
     Property(String),
@@ -23,13 +27,18 @@ pub enum RawPropertyKey {
     /// Property identified by a name with quoted name.
     QuotedProperty(String),
 
-    /// Property of a fragment.
+    /// Property whose name is prefixed with another name,
+    /// separated by a slash.
+    ///
+    /// The prefix is either the name of a fragment,
+    /// or a namespace of the property.
     FragmentProperty(String, String),
 }
 
 /// Single stylesheet rule that assignes a series
 /// of property and variable values to a selector.
 #[derive(PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StyleRule<K: PropertyKey = RawPropertyKey> {
     /// Selector that determines what entities the rule applies to.
     pub selector: Selector,
@@ -82,6 +91,7 @@ impl<K: PropertyKey> std::fmt::Debug for StyleRule<K> {
 /// Single property or variable assignment entry.
 #[derive(Clone, PartialEq, Eq, Debug)]
 #[debug("{key:?}: ({value:?})")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StyleClause<K: PropertyKey = RawPropertyKey> {
     /// Name of the property or variable to assign.
     ///
@@ -124,6 +134,7 @@ impl<K: PropertyKey> StyleClause<K> {
 
 /// A key that values can be assigned to in a style rule.
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StyleKey<K: PropertyKey = RawPropertyKey> {
     /// Assigns value to a property of the selected entity.
     Property(K),
@@ -160,9 +171,80 @@ impl<K: PropertyKey> StyleKey<K> {
 
 /// Full stylesheet, a sequence of style rules.
 #[derive(PartialEq, Eq, Debug)]
-pub struct Stylesheet<K: PropertyKey = RawPropertyKey>(pub Vec<StyleRule<K>>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stylesheet<K: PropertyKey = RawPropertyKey> {
+    /// Rules of the stylesheet, in declaration order.
+    pub rules: Vec<StyleRule<K>>,
+
+    /// Capabilities that a state graph must provide
+    /// for the stylesheet to be applicable to it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub requirements: CapabilitySet,
+
+    /// Blocks of rules that only apply under a condition,
+    /// in declaration order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub conditional_blocks: Vec<ConditionalBlock>,
+
+    /// Names of the rules that have been given one, by rule index.
+    ///
+    /// Names let tools refer to rules in a way that does not change
+    /// when other rules are added or removed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rule_names: BTreeMap<usize, String>,
+}
 
 impl<K: PropertyKey> Stylesheet<K> {
+    /// Constructs a stylesheet with no requirements,
+    /// no conditional blocks, and no named rules.
+    pub fn new(rules: Vec<StyleRule<K>>) -> Self {
+        Self {
+            rules,
+            requirements: CapabilitySet::new(),
+            conditional_blocks: Vec::new(),
+            rule_names: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the capabilities that the stylesheet requires.
+    pub fn with_requirements(mut self, requirements: CapabilitySet) -> Self {
+        self.requirements = requirements;
+        self
+    }
+
+    /// Makes a range of rules conditional.
+    pub fn with_conditional_block(mut self, condition: Expression, rules: Range<usize>) -> Self {
+        self.conditional_blocks
+            .push(ConditionalBlock { condition, rules });
+        self
+    }
+
+    /// Gets the condition under which the rule at an index applies,
+    /// if it is in a conditional block.
+    pub fn condition_of(&self, rule_index: usize) -> Option<&Expression> {
+        self.conditional_blocks
+            .iter()
+            .find(|block| block.rules.contains(&rule_index))
+            .map(|block| &block.condition)
+    }
+
+    /// Gives a name to the rule at an index.
+    pub fn with_rule_name(mut self, rule_index: usize, name: impl Into<String>) -> Self {
+        self.rule_names.insert(rule_index, name.into());
+        self
+    }
+
+    /// Gets the name of the rule at an index, if it has one.
+    pub fn rule_name(&self, rule_index: usize) -> Option<&str> {
+        self.rule_names.get(&rule_index).map(String::as_str)
+    }
+
+    /// Verifies that a state graph provides all capabilities
+    /// that the stylesheet requires.
+    pub fn check_requirements(&self, provided: &CapabilitySet) -> Result<(), MissingCapabilities> {
+        MissingCapabilities::check(&self.requirements, provided)
+    }
+
     /// Converts a stylesheet to a different [`PropertyKey`].
     ///
     /// All properties whose keys cannot be converted are removed.
@@ -171,12 +253,55 @@ impl<K: PropertyKey> Stylesheet<K> {
         L: PropertyKey,
         K: TryInto<L>,
     {
-        Stylesheet(self.0.into_iter().map(StyleRule::map_key).collect())
+        Stylesheet {
+            rules: self.rules.into_iter().map(StyleRule::map_key).collect(),
+            requirements: self.requirements,
+            conditional_blocks: self.conditional_blocks,
+            rule_names: self.rule_names,
+        }
     }
 }
 
 impl<K: PropertyKey> Default for Stylesheet<K> {
     fn default() -> Self {
-        Self(Vec::new())
+        Self::new(Vec::new())
+    }
+}
+
+/// Consecutive rules of a [`Stylesheet`] that only apply
+/// when a condition holds.
+///
+/// Conditions are evaluated once for each application
+/// of the stylesheet, before any entities are selected,
+/// so they can only refer to variables that are provided
+/// by the embedder, not to the state graph.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConditionalBlock {
+    /// Condition that must be truthy for the rules to apply.
+    pub condition: Expression,
+
+    /// Indices of the rules in the block.
+    pub rules: Range<usize>,
+}
+
+/// Error type that indicates that a stylesheet requires
+/// capabilities that a state graph does not provide.
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display("stylesheet requires capabilities that are not available: {}", _0.join(", "))]
+pub struct MissingCapabilities(#[error(not(source))] pub Vec<String>);
+
+impl MissingCapabilities {
+    /// Verifies that all required capabilities are provided.
+    ///
+    /// ## Return Value
+    /// An error that lists the missing capabilities, if any.
+    pub fn check(required: &CapabilitySet, provided: &CapabilitySet) -> Result<(), Self> {
+        let missing: Vec<_> = required.missing_from(provided).map(str::to_owned).collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(Self(missing))
+        }
     }
 }
@@ -1,12 +1,13 @@
 //! Selectors identify selectable entities by the paths
 //! that lead to them.
 
-use super::expression::Expression;
+use super::expression::{Expression, MagicVariableKey};
 use aili_model::state::EdgeLabel;
 use derive_more::{Debug, From};
 
 /// Pattern against which an [`EdgeLabel`] can be matched.
-#[derive(PartialEq, Eq, From, Debug)]
+#[derive(Clone, PartialEq, Eq, From, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EdgeMatcher {
     /// Matches all edges.
     #[debug("*")]
@@ -49,6 +50,7 @@ impl EdgeMatcher {
 /// Unrestricted segment of a selector path.
 /// Can be an edge matcher or a control flow construct.
 #[derive(PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SelectorSegment {
     /// Matches an edge.
     #[debug("{_0:?}")]
@@ -80,8 +82,24 @@ impl SelectorSegment {
 /// in order to pass.
 #[derive(PartialEq, Eq, From, Default)]
 #[from(forward)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SelectorPath(pub Vec<SelectorSegment>);
 
+impl SelectorPath {
+    /// Checks whether any condition in the path
+    /// refers to a magic variable.
+    pub fn uses_magic_variable(&self, key: MagicVariableKey) -> bool {
+        self.0.iter().any(|segment| match segment {
+            SelectorSegment::Match(_) => false,
+            SelectorSegment::AnyNumberOfTimes(path) => path.uses_magic_variable(key),
+            SelectorSegment::Branch(paths) => {
+                paths.iter().any(|path| path.uses_magic_variable(key))
+            }
+            SelectorSegment::Condition(condition) => condition.uses_magic_variable(key),
+        })
+    }
+}
+
 impl std::fmt::Debug for SelectorPath {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for (i, segment) in self.0.iter().enumerate() {
@@ -98,6 +116,7 @@ impl std::fmt::Debug for SelectorPath {
 /// and tail decorators that specify which selectable element
 /// was exactly selected.
 #[derive(PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Selector {
     /// Path that must match in order to select something.
     pub path: SelectorPath,
@@ -110,6 +129,15 @@ pub struct Selector {
     /// attached to the matched node or edge, instead of the node
     /// or edge directly.
     pub extra: Option<String>,
+
+    /// Position of the [`extra`](Selector::extra) element
+    /// among the extras of the same owner.
+    ///
+    /// Extras with lower indices are placed before extras
+    /// with higher indices. Ignored if the selector
+    /// does not select an extra.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extra_index: Option<usize>,
 }
 
 impl Selector {
@@ -119,24 +147,31 @@ impl Selector {
             path,
             selects_edge: false,
             extra: None,
+            extra_index: None,
         }
     }
 
     /// Shorthand for setting the [`Selector::selects_edge`] flag.
     pub fn selecting_edge(self) -> Self {
         Self {
-            path: self.path,
             selects_edge: true,
-            extra: self.extra,
+            ..self
         }
     }
 
     /// Shorthand for adding an [`Selector::extra`] tag.
     pub fn with_extra(self, extra: String) -> Self {
         Self {
-            path: self.path,
-            selects_edge: self.selects_edge,
             extra: Some(extra),
+            ..self
+        }
+    }
+
+    /// Shorthand for adding an [`Selector::extra_index`].
+    pub fn with_extra_index(self, extra_index: usize) -> Self {
+        Self {
+            extra_index: Some(extra_index),
+            ..self
         }
     }
 }
@@ -153,6 +188,9 @@ impl std::fmt::Debug for Selector {
             } else {
                 write!(f, "::extra({extra:?})")?;
             }
+            if let Some(extra_index) = self.extra_index {
+                write!(f, ":index({extra_index})")?;
+            }
         }
         Ok(())
     }
@@ -21,15 +21,56 @@ pub enum PropertyValue<T: NodeId> {
 
     /// The property's value is a program value extracted from state
     /// or calculated with arithmetics.
+    ///
+    /// [`NodeValue::String`] values extracted from state
+    /// are converted to [`String`](PropertyValue::String)s instead.
     #[debug("({_0:?})")]
     #[display("{_0:?}")]
-    #[from(NodeValue, bool, u64, i64)]
+    #[from(bool, u64, i64, f64)]
     Value(NodeValue),
 
     /// The property's value is a string literal or compound string.
     #[debug("{_0:?}")]
     #[from]
     String(String),
+
+    /// The property's value could not be calculated.
+    ///
+    /// Errors propagate through arithmetic and comparison operators,
+    /// so an expression that depends on an erroneous value is erroneous
+    /// as well. They are falsy and cannot be assigned to properties.
+    #[debug("error({_0})")]
+    #[display("")]
+    #[from]
+    Error(EvaluationError),
+}
+
+/// Reason why an expression has evaluated
+/// to a [`PropertyValue::Error`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+pub enum EvaluationError {
+    /// The result of an arithmetic operation
+    /// does not fit into the range of its type.
+    #[display("overflow")]
+    Overflow,
+
+    /// An integer or a float has been divided by zero.
+    #[display("division-by-zero")]
+    DivisionByZero,
+
+    /// An arithmetic operator has been applied
+    /// to a value that is not a number.
+    #[display("type-mismatch")]
+    TypeMismatch,
+}
+
+impl<T: NodeId> From<NodeValue> for PropertyValue<T> {
+    fn from(value: NodeValue) -> Self {
+        match value {
+            NodeValue::String(s) => Self::String(s),
+            _ => Self::Value(value),
+        }
+    }
 }
 
 impl<T: NodeId> PropertyValue<T> {
@@ -40,7 +81,8 @@ impl<T: NodeId> PropertyValue<T> {
     /// - [`Unset`](PropertyValue::Unset)
     /// - Empty [`String`](PropertyValue::String)
     /// - False [`Bool`](NodeValue::Bool)
-    /// - Zero [`Int`](NodeValue::Int) and [`Uint`](NodeValue::Uint)
+    /// - Zero [`Int`](NodeValue::Int), [`Uint`](NodeValue::Uint), and [`Float`](NodeValue::Float)
+    /// - [`Error`](PropertyValue::Error)
     ///
     /// Note that, in particular, all [`Selection`](PropertyValue::Selection)s
     /// are truthy. `!!select(...)` is a shorthand for verifying
@@ -48,12 +90,13 @@ impl<T: NodeId> PropertyValue<T> {
     /// no value, its value is false, or it is not a node.
     pub fn is_truthy(&self) -> bool {
         match self {
-            Self::Unset => false,
-            Self::String(s) => !s.is_empty(),
+            Self::Unset | Self::Error(_) => false,
+            Self::String(s) | Self::Value(NodeValue::String(s)) => !s.is_empty(),
             Self::Selection(_) => true,
             Self::Value(NodeValue::Bool(b)) => *b,
             Self::Value(NodeValue::Int(i)) => *i != 0,
             Self::Value(NodeValue::Uint(u)) => *u != 0,
+            Self::Value(NodeValue::Float(f)) => *f != 0.0,
         }
     }
 }
@@ -71,10 +114,20 @@ impl<T: NodeId> PartialOrd for PropertyValue<T> {
     ///   Otherwise they are unordered.
     /// - Two values of type [`Value`](PropertyValue::Value) are totally
     ///   ordered by their numeric values. `true == 1` and `false == 0`.
+    ///   See [`NodeValue`]'s implementation of [`Ord`] for details.
+    /// - Two values of type [`Error`](PropertyValue::Error) are equal
+    ///   if they have the same reason. Otherwise they are unordered.
     /// - Any other pair of values is unordered.
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match (self, other) {
             (Self::Unset, Self::Unset) => Some(std::cmp::Ordering::Equal),
+            (Self::Error(left), Self::Error(right)) => {
+                if left == right {
+                    Some(std::cmp::Ordering::Equal)
+                } else {
+                    None
+                }
+            }
             (Self::String(left), Self::String(right)) => {
                 if left == right {
                     Some(std::cmp::Ordering::Equal)
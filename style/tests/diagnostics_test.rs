@@ -0,0 +1,106 @@
+//! Tests for [`CascadeStyle::compile_with_warnings`].
+
+use aili_model::state::{EdgeLabel, NodeTypeClass};
+use aili_style::{
+    cascade::{CascadeStyle, SelectorWarning},
+    stylesheet::{expression::*, selector::*, *},
+};
+
+fn rule(path: impl Into<SelectorPath>) -> StyleRule<RawPropertyKey> {
+    StyleRule {
+        selector: Selector::from_path(path.into()),
+        properties: Vec::new(),
+    }
+}
+
+fn warnings_of(rules: Vec<StyleRule<RawPropertyKey>>) -> Vec<SelectorWarning> {
+    CascadeStyle::compile_with_warnings(Stylesheet::new(rules)).1
+}
+
+fn is_a(type_class: NodeTypeClass) -> SelectorSegment {
+    SelectorSegment::Condition(Expression::UnaryOperator(
+        UnaryOperator::NodeIsA(type_class),
+        Expression::Select(LimitedSelector::default().into()).into(),
+    ))
+}
+
+#[test]
+fn permitted_paths_have_no_warnings() {
+    let warnings = warnings_of(vec![
+        // :: main .many(next) "a" ref []
+        rule([
+            SelectorSegment::Match(EdgeLabel::Main.into()),
+            SelectorSegment::AnyNumberOfTimes(
+                [SelectorSegment::Match(EdgeLabel::Next.into())].into(),
+            ),
+            SelectorSegment::Match(EdgeMatcher::Named("a".into())),
+            SelectorSegment::Match(EdgeLabel::Deref.into()),
+            SelectorSegment::Match(EdgeMatcher::AnyIndex),
+        ]),
+        // .many(*) :arr len
+        rule([
+            SelectorSegment::anything_any_number_of_times(),
+            is_a(NodeTypeClass::Array),
+            SelectorSegment::Match(EdgeLabel::Length.into()),
+        ]),
+    ]);
+    assert_eq!(warnings, []);
+}
+
+#[test]
+fn edges_that_cannot_start_at_the_selected_nodes() {
+    let warnings = warnings_of(vec![
+        // :: [] main
+        rule([
+            SelectorSegment::Match(EdgeMatcher::AnyIndex),
+            SelectorSegment::Match(EdgeLabel::Main.into()),
+        ]),
+        // :: main len "a"
+        rule([
+            SelectorSegment::Match(EdgeLabel::Main.into()),
+            SelectorSegment::Match(EdgeLabel::Length.into()),
+            SelectorSegment::Match(EdgeMatcher::Named("a".into())),
+        ]),
+    ]);
+    assert_eq!(
+        warnings,
+        [
+            SelectorWarning::UnmatchableEdge {
+                rule: 0,
+                matcher: EdgeMatcher::AnyIndex,
+            },
+            // Segments after the first one that cannot match are not reported
+            SelectorWarning::UnmatchableEdge {
+                rule: 1,
+                matcher: EdgeLabel::Length.into(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn type_assertions_that_cannot_hold() {
+    // :: main :arr
+    let warnings = warnings_of(vec![rule([
+        SelectorSegment::Match(EdgeLabel::Main.into()),
+        is_a(NodeTypeClass::Array),
+    ])]);
+    assert_eq!(
+        warnings,
+        [SelectorWarning::UnmatchableTypeClass {
+            rule: 0,
+            type_class: NodeTypeClass::Array,
+        }]
+    );
+}
+
+#[test]
+fn empty_alternatives() {
+    // :: main .alt() "a"
+    let warnings = warnings_of(vec![rule([
+        SelectorSegment::Match(EdgeLabel::Main.into()),
+        SelectorSegment::Branch(Vec::new()),
+        SelectorSegment::Match(EdgeMatcher::Named("a".into())),
+    ])]);
+    assert_eq!(warnings, [SelectorWarning::EmptyAlternative { rule: 0 }]);
+}
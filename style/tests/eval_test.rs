@@ -2,15 +2,19 @@
 
 mod test_graph;
 
-use aili_model::state::RootedProgramStateGraph as _;
+use aili_model::{
+    builder::StateGraphBuilder,
+    state::{EdgeLabel, NodeTypeClass, NodeValue, RootedProgramStateGraph as _},
+};
 use aili_style::{
-    eval::{context::EvaluationContext, evaluate},
+    eval::{context::EvaluationContext, evaluate, variable_pool::VariablePool},
     stylesheet::expression::{
         BinaryOperator as BinaryOp,
         Expression::{self, *},
-        UnaryOperator as UnaryOp,
+        LimitedEdgeMatcher, LimitedSelector, MagicVariableKey, UnaryOperator as UnaryOp,
+        UserFunction, ValuePredicate,
     },
-    values::PropertyValue,
+    values::{EvaluationError, PropertyValue},
 };
 use test_graph::TestGraph;
 
@@ -82,9 +86,12 @@ fn unary_plus_on_missing_node_returns_unset() {
 }
 
 #[test]
-fn unary_minus_on_string_returns_unset() {
+fn unary_minus_on_string_is_type_mismatch() {
     let expr = UnaryOperator(UnaryOp::Minus, String("hello".to_owned()).into());
-    assert_eq!(eval_on_default_graph(&expr), PropertyValue::Unset);
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        EvaluationError::TypeMismatch.into()
+    );
 }
 
 #[test]
@@ -94,9 +101,12 @@ fn unary_minus_negates_int() {
 }
 
 #[test]
-fn unary_minus_returns_unset_on_overflow() {
+fn unary_minus_overflows() {
     let expr = UnaryOperator(UnaryOp::Minus, Int(u64::MAX).into());
-    assert_eq!(eval_on_default_graph(&expr), PropertyValue::Unset);
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        EvaluationError::Overflow.into()
+    );
 }
 
 #[test]
@@ -186,6 +196,28 @@ fn value_extracts_value_from_node() {
     );
 }
 
+#[test]
+fn value_extracts_string_from_node() {
+    let expr = UnaryOperator(
+        UnaryOp::NodeValue,
+        Select(TestGraph::string_node_selector().into()).into(),
+    );
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        TestGraph::STRING_NODE_VALUE.to_owned().into()
+    );
+}
+
+#[test]
+fn string_node_equals_string_with_same_value() {
+    let expr = BinaryOperator(
+        Select(TestGraph::string_node_selector().into()).into(),
+        BinaryOp::Eq,
+        String(TestGraph::STRING_NODE_VALUE.to_owned()).into(),
+    );
+    assert_eq!(eval_on_default_graph(&expr), true.into());
+}
+
 #[test]
 fn value_of_valueless_node_is_unset() {
     let expr = UnaryOperator(
@@ -249,6 +281,53 @@ fn isset_missing_node_is_false() {
     assert_eq!(eval_on_default_graph(&expr), false.into());
 }
 
+#[test]
+fn classname_of_custom_node() {
+    let expr = UnaryOperator(
+        UnaryOp::NodeCustomClass,
+        Select(TestGraph::custom_node_selector().into()).into(),
+    );
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        PropertyValue::String(TestGraph::CUSTOM_NODE_CLASS.to_owned())
+    );
+}
+
+#[test]
+fn classname_of_builtin_node_is_unset() {
+    let expr = UnaryOperator(
+        UnaryOp::NodeCustomClass,
+        Select(TestGraph::numeric_node_selector().into()).into(),
+    );
+    assert_eq!(eval_on_default_graph(&expr), PropertyValue::Unset);
+}
+
+#[test]
+fn source_location_of_node() {
+    let file = UnaryOperator(
+        UnaryOp::NodeSourceFile,
+        Select(TestGraph::located_node_selector().into()).into(),
+    );
+    let line = UnaryOperator(
+        UnaryOp::NodeSourceLine,
+        Select(TestGraph::located_node_selector().into()).into(),
+    );
+    assert_eq!(
+        eval_on_default_graph(&file),
+        PropertyValue::String(TestGraph::SOURCE_FILE.to_owned())
+    );
+    assert_eq!(eval_on_default_graph(&line), TestGraph::SOURCE_LINE.into());
+}
+
+#[test]
+fn source_location_of_unlocated_node_is_unset() {
+    let expr = UnaryOperator(
+        UnaryOp::NodeSourceLine,
+        Select(TestGraph::numeric_node_selector().into()).into(),
+    );
+    assert_eq!(eval_on_default_graph(&expr), PropertyValue::Unset);
+}
+
 #[test]
 fn binary_plus_concatenates_strings() {
     let expr = BinaryOperator(
@@ -308,13 +387,16 @@ fn bool_minus_uint_coerces_bool_to_int() {
 }
 
 #[test]
-fn string_minus_int_is_unset() {
+fn string_minus_int_is_type_mismatch() {
     let expr = BinaryOperator(
         String("hello".to_owned()).into(),
         BinaryOp::Minus,
         Int(42).into(),
     );
-    assert_eq!(eval_on_default_graph(&expr), PropertyValue::Unset);
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        EvaluationError::TypeMismatch.into()
+    );
 }
 
 #[test]
@@ -359,9 +441,12 @@ fn int_times_bool_coerces_bool_to_int() {
 }
 
 #[test]
-fn uint_times_uint_returns_unset_on_overflow() {
+fn uint_times_uint_overflows() {
     let expr = BinaryOperator(Int(u64::MAX).into(), BinaryOp::Mul, Int(2).into());
-    assert_eq!(eval_on_default_graph(&expr), PropertyValue::Unset);
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        EvaluationError::Overflow.into()
+    );
 }
 
 #[test]
@@ -381,9 +466,12 @@ fn negative_dividend_is_divided_the_euclidean_way() {
 }
 
 #[test]
-fn zero_division_is_unset() {
+fn zero_division_is_error() {
     let expr = BinaryOperator(Int(42).into(), BinaryOp::Div, Int(0).into());
-    assert_eq!(eval_on_default_graph(&expr), PropertyValue::Unset);
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        EvaluationError::DivisionByZero.into()
+    );
 }
 
 #[test]
@@ -403,9 +491,12 @@ fn modulo_with_negative_dividend_is_calculated_the_euclidean_way() {
 }
 
 #[test]
-fn zero_modulo_is_unset() {
+fn zero_modulo_is_error() {
     let expr = BinaryOperator(Int(42).into(), BinaryOp::Mod, Int(0).into());
-    assert_eq!(eval_on_default_graph(&expr), PropertyValue::Unset);
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        EvaluationError::DivisionByZero.into()
+    );
 }
 
 #[test]
@@ -785,3 +876,430 @@ fn conditional_returns_third_argument_if_first_is_falsy() {
     );
     assert_eq!(eval_on_default_graph(&expr), 42u64.into());
 }
+
+#[test]
+fn function_call_assigns_arguments_to_parameters() {
+    let function = UserFunction::new(
+        "sub".to_owned(),
+        vec!["--a".to_owned(), "--b".to_owned()],
+        BinaryOperator(
+            Variable("--a".to_owned()).into(),
+            BinaryOp::Minus,
+            Variable("--b".to_owned()).into(),
+        ),
+    );
+    let expr = Call(function.into(), vec![Int(5), Int(3)]);
+    assert_eq!(eval_on_default_graph(&expr), 2u64.into());
+}
+
+#[test]
+fn function_body_does_not_see_caller_variables() {
+    let function = UserFunction::new(
+        "f".to_owned(),
+        vec!["--a".to_owned()],
+        BinaryOperator(
+            Variable("--a".to_owned()).into(),
+            BinaryOp::Plus,
+            Variable("--b".to_owned()).into(),
+        ),
+    );
+    let expr = Call(function.into(), vec![Variable("--b".to_owned())]);
+    let mut variable_pool = VariablePool::new();
+    variable_pool.insert("--b", 1u64.into());
+    let graph = TestGraph::default_graph();
+    let context =
+        EvaluationContext::from_graph(&graph, graph.root()).with_variables(&variable_pool);
+    // --b is unset inside the function, so the sum is unset
+    assert_eq!(evaluate(&expr, &context), PropertyValue::Unset);
+}
+
+#[test]
+fn function_body_selects_relative_to_caller() {
+    let function = UserFunction::new(
+        "value-of-float".to_owned(),
+        Vec::new(),
+        UnaryOperator(
+            UnaryOp::NodeValue,
+            Select(TestGraph::float_node_selector().into()).into(),
+        ),
+    );
+    let expr = Call(function.into(), Vec::new());
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        TestGraph::FLOAT_NODE_VALUE.into()
+    );
+}
+
+#[test]
+fn parent_matcher_steps_back_along_the_path() {
+    let selector = LimitedSelector::from_path([
+        EdgeLabel::Named("a".into(), 0).into(),
+        LimitedEdgeMatcher::Parent,
+        EdgeLabel::Named("s".into(), 0).into(),
+    ]);
+    let expr = UnaryOperator(UnaryOp::NodeValue, Select(selector.into()).into());
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        TestGraph::STRING_NODE_VALUE.to_owned().into()
+    );
+}
+
+#[test]
+fn parent_matcher_steps_back_to_predecessor_of_origin() {
+    let graph = TestGraph::default_graph();
+    // Node 1 is the numeric node, a successor of the root
+    let context = EvaluationContext::from_graph(&graph, 1).with_optional_parent(Some(graph.root()));
+    let selector = LimitedSelector::from_path([
+        LimitedEdgeMatcher::Parent,
+        EdgeLabel::Named("s".into(), 0).into(),
+    ]);
+    let expr = UnaryOperator(UnaryOp::NodeValue, Select(selector.into()).into());
+    assert_eq!(
+        evaluate(&expr, &context),
+        TestGraph::STRING_NODE_VALUE.to_owned().into()
+    );
+}
+
+#[test]
+fn parent_matcher_without_predecessor_selects_nothing() {
+    let selector = LimitedSelector::from_path([LimitedEdgeMatcher::Parent]);
+    assert_eq!(
+        eval_on_default_graph(&Select(selector.into())),
+        PropertyValue::Unset
+    );
+}
+
+#[test]
+fn value_is_zero() {
+    let is_zero = |operand: Expression| {
+        eval_on_default_graph(&UnaryOperator(
+            UnaryOp::ValueIs(ValuePredicate::Zero),
+            operand.into(),
+        ))
+    };
+    assert_eq!(is_zero(Int(0)), true.into());
+    assert_eq!(is_zero(Int(1)), false.into());
+    assert_eq!(is_zero(Bool(false)), true.into());
+    assert_eq!(is_zero(String("0".to_owned())), false.into());
+    assert_eq!(is_zero(Unset), false.into());
+}
+
+#[test]
+fn value_is_negative() {
+    let is_negative = |operand: Expression| {
+        eval_on_default_graph(&UnaryOperator(
+            UnaryOp::ValueIs(ValuePredicate::Negative),
+            operand.into(),
+        ))
+    };
+    assert_eq!(
+        is_negative(UnaryOperator(UnaryOp::Minus, Int(3).into())),
+        true.into()
+    );
+    assert_eq!(is_negative(Int(0)), false.into());
+    assert_eq!(
+        is_negative(UnaryOperator(
+            UnaryOp::Minus,
+            Select(TestGraph::float_node_selector().into()).into(),
+        )),
+        (TestGraph::FLOAT_NODE_VALUE > 0.0).into()
+    );
+}
+
+#[test]
+fn unary_minus_negates_float() {
+    let expr = UnaryOperator(
+        UnaryOp::Minus,
+        Select(TestGraph::float_node_selector().into()).into(),
+    );
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        (-TestGraph::FLOAT_NODE_VALUE).into()
+    );
+}
+
+#[test]
+fn float_plus_int_is_float() {
+    let expr = BinaryOperator(
+        Select(TestGraph::float_node_selector().into()).into(),
+        BinaryOp::Plus,
+        Int(1).into(),
+    );
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        (TestGraph::FLOAT_NODE_VALUE + 1.0).into()
+    );
+}
+
+#[test]
+fn int_over_float_divides_values() {
+    let expr = BinaryOperator(
+        Int(5).into(),
+        BinaryOp::Div,
+        Select(TestGraph::float_node_selector().into()).into(),
+    );
+    assert_eq!(eval_on_default_graph(&expr), 2.0.into());
+}
+
+#[test]
+fn float_zero_division_is_error() {
+    let expr = BinaryOperator(
+        Select(TestGraph::float_node_selector().into()).into(),
+        BinaryOp::Div,
+        Int(0).into(),
+    );
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        EvaluationError::DivisionByZero.into()
+    );
+}
+
+#[test]
+fn float_modulo_calculates_remainder() {
+    let expr = BinaryOperator(
+        Select(TestGraph::float_node_selector().into()).into(),
+        BinaryOp::Mod,
+        Int(2).into(),
+    );
+    assert_eq!(eval_on_default_graph(&expr), 0.5.into());
+}
+
+#[test]
+fn float_equals_int_with_same_value() {
+    let expr = BinaryOperator(
+        BinaryOperator(
+            Select(TestGraph::float_node_selector().into()).into(),
+            BinaryOp::Mul,
+            Int(2).into(),
+        )
+        .into(),
+        BinaryOp::Eq,
+        Int(5).into(),
+    );
+    assert_eq!(eval_on_default_graph(&expr), true.into());
+}
+
+#[test]
+fn float_is_less_than_larger_int() {
+    let expr = BinaryOperator(
+        Select(TestGraph::float_node_selector().into()).into(),
+        BinaryOp::Lt,
+        Int(3).into(),
+    );
+    assert_eq!(eval_on_default_graph(&expr), true.into());
+}
+
+#[test]
+fn int_is_compared_with_float_exactly() {
+    // Both integers round to the same float
+    let float = BinaryOperator(
+        BinaryOperator(
+            Select(TestGraph::float_node_selector().into()).into(),
+            BinaryOp::Minus,
+            Select(TestGraph::float_node_selector().into()).into(),
+        )
+        .into(),
+        BinaryOp::Plus,
+        Int(1 << 53).into(),
+    );
+    let expr = BinaryOperator(
+        Int((1 << 53) + 1).into(),
+        BinaryOp::Gt,
+        float.clone().into(),
+    );
+    assert_eq!(eval_on_default_graph(&expr), true.into());
+    let expr = BinaryOperator(Int(1 << 53).into(), BinaryOp::Eq, float.into());
+    assert_eq!(eval_on_default_graph(&expr), true.into());
+}
+
+#[test]
+fn zero_minus_one_is_negative() {
+    let expr = BinaryOperator(Int(0).into(), BinaryOp::Minus, Int(1).into());
+    assert_eq!(eval_on_default_graph(&expr), (-1i64).into());
+}
+
+#[test]
+fn errors_propagate_through_operators() {
+    let zero_division = BinaryOperator(Int(1).into(), BinaryOp::Div, Int(0).into());
+    let expr = BinaryOperator(
+        UnaryOperator(UnaryOp::Minus, zero_division.into()).into(),
+        BinaryOp::Lt,
+        Int(42).into(),
+    );
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        EvaluationError::DivisionByZero.into()
+    );
+}
+
+#[test]
+fn left_error_takes_precedence() {
+    let expr = BinaryOperator(
+        BinaryOperator(Int(u64::MAX).into(), BinaryOp::Plus, Int(1).into()).into(),
+        BinaryOp::Plus,
+        BinaryOperator(Int(1).into(), BinaryOp::Mod, Int(0).into()).into(),
+    );
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        EvaluationError::Overflow.into()
+    );
+}
+
+#[test]
+fn error_is_falsy() {
+    let error = BinaryOperator(Int(1).into(), BinaryOp::Div, Int(0).into());
+    let expr = Conditional(error.into(), Int(1).into(), Int(2).into());
+    assert_eq!(eval_on_default_graph(&expr), 2u64.into());
+}
+
+#[test]
+fn iserror_recognizes_errors() {
+    let iserror =
+        |expr: Expression| eval_on_default_graph(&UnaryOperator(UnaryOp::IsError, expr.into()));
+    let error = BinaryOperator(Int(1).into(), BinaryOp::Div, Int(0).into());
+    assert_eq!(iserror(error), true.into());
+    assert_eq!(iserror(Unset), false.into());
+    assert_eq!(iserror(Int(0)), false.into());
+}
+
+#[test]
+fn hex_formats_integers() {
+    let hex = |expr: Expression| eval_on_default_graph(&UnaryOperator(UnaryOp::Hex, expr.into()));
+    assert_eq!(hex(Int(255)), "0xff".to_owned().into());
+    assert_eq!(
+        hex(UnaryOperator(UnaryOp::Minus, Int(16).into())),
+        "-0x10".to_owned().into()
+    );
+    assert_eq!(
+        hex(String("ff".to_owned())),
+        EvaluationError::TypeMismatch.into()
+    );
+    assert_eq!(hex(Unset), PropertyValue::Unset);
+}
+
+#[test]
+fn as_signed_sign_extends_narrow_integers() {
+    let as_signed = |width, expr: Expression| {
+        eval_on_default_graph(&UnaryOperator(UnaryOp::AsSigned(width), expr.into()))
+    };
+    assert_eq!(as_signed(32, Int(0xffff_ffff)), (-1i64).into());
+    assert_eq!(as_signed(32, Int(0x7fff_ffff)), 0x7fff_ffffi64.into());
+    assert_eq!(as_signed(8, Int(0x180)), (-128i64).into());
+    assert_eq!(as_signed(64, Int(u64::MAX)), (-1i64).into());
+}
+
+#[test]
+fn as_unsigned_truncates_integers() {
+    let as_unsigned = |width, expr: Expression| {
+        eval_on_default_graph(&UnaryOperator(UnaryOp::AsUnsigned(width), expr.into()))
+    };
+    let minus_one = UnaryOperator(UnaryOp::Minus, Int(1).into());
+    assert_eq!(as_unsigned(32, minus_one.clone()), 0xffff_ffffu64.into());
+    assert_eq!(as_unsigned(64, minus_one), u64::MAX.into());
+    assert_eq!(as_unsigned(8, Int(0x1ff)), 0xffu64.into());
+}
+
+#[test]
+fn reinterpreted_integer_equals_literal() {
+    // as-signed(4294967295, 32) == -1
+    let expr = BinaryOperator(
+        UnaryOperator(UnaryOp::AsSigned(32), Int(0xffff_ffff).into()).into(),
+        BinaryOp::Eq,
+        UnaryOperator(UnaryOp::Minus, Int(1).into()).into(),
+    );
+    assert_eq!(eval_on_default_graph(&expr), true.into());
+}
+
+#[test]
+fn pad_left_fills_to_width() {
+    let pad_left = |expr: Expression| {
+        eval_on_default_graph(&UnaryOperator(UnaryOp::PadLeft(4, '0'), expr.into()))
+    };
+    assert_eq!(pad_left(Int(42)), "0042".to_owned().into());
+    assert_eq!(pad_left(Int(123456)), "123456".to_owned().into());
+    assert_eq!(
+        pad_left(UnaryOperator(UnaryOp::Hex, Int(255).into())),
+        "0xff".to_owned().into()
+    );
+    assert_eq!(pad_left(Unset), PropertyValue::Unset);
+}
+
+#[test]
+fn pad_right_fills_to_width() {
+    let expr = UnaryOperator(UnaryOp::PadRight(5, '.'), String("ab".to_owned()).into());
+    assert_eq!(eval_on_default_graph(&expr), "ab...".to_owned().into());
+}
+
+#[test]
+fn pad_left_formats_node_value() {
+    let expr = UnaryOperator(
+        UnaryOp::PadLeft(4, ' '),
+        Select(TestGraph::numeric_node_selector().into()).into(),
+    );
+    assert_eq!(
+        eval_on_default_graph(&expr),
+        format!("{:>4}", TestGraph::NUMERIC_NODE_VALUE).into()
+    );
+}
+
+#[test]
+fn node_metadata_of_root() {
+    assert_eq!(
+        eval_on_default_graph(&MagicVariable(MagicVariableKey::TypeClass)),
+        "root".to_owned().into()
+    );
+    assert_eq!(
+        eval_on_default_graph(&MagicVariable(MagicVariableKey::ChildCount)),
+        5u64.into()
+    );
+    assert_eq!(
+        eval_on_default_graph(&MagicVariable(MagicVariableKey::Address)),
+        PropertyValue::Unset
+    );
+}
+
+#[test]
+fn nodes_are_not_shared_by_default() {
+    assert_eq!(
+        eval_on_default_graph(&MagicVariable(MagicVariableKey::Shared)),
+        false.into()
+    );
+}
+
+#[test]
+fn address_of_frame_and_reference() {
+    let mut builder = StateGraphBuilder::new();
+    let frame = builder.add_node(NodeTypeClass::Frame, Some(NodeValue::Uint(0x7ff0)));
+    let reference = builder.add_node(NodeTypeClass::Ref, Some(NodeValue::Uint(0x1000)));
+    let atom = builder.add_node(NodeTypeClass::Atom, Some(NodeValue::Uint(42)));
+    builder
+        .add_edge(builder.root(), EdgeLabel::Main, frame)
+        .unwrap();
+    builder
+        .add_edge(frame, EdgeLabel::Named("p".into(), 0), reference)
+        .unwrap();
+    builder
+        .add_edge(frame, EdgeLabel::Named("x".into(), 0), atom)
+        .unwrap();
+    let graph = builder.build();
+    let eval_at = |node, key| {
+        evaluate(
+            &MagicVariable(key),
+            &EvaluationContext::from_graph(&graph, node),
+        )
+    };
+    assert_eq!(eval_at(frame, MagicVariableKey::Address), 0x7ff0u64.into());
+    assert_eq!(
+        eval_at(reference, MagicVariableKey::Address),
+        0x1000u64.into()
+    );
+    assert_eq!(
+        eval_at(atom, MagicVariableKey::Address),
+        PropertyValue::Unset
+    );
+    assert_eq!(
+        eval_at(frame, MagicVariableKey::TypeClass),
+        "frame".to_owned().into()
+    );
+    assert_eq!(eval_at(frame, MagicVariableKey::ChildCount), 2u64.into());
+}
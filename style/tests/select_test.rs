@@ -0,0 +1,218 @@
+//! Tests for [`query`] and [`CascadeStyle::rules_matching`].
+
+use aili_model::{graph, state::EdgeLabel};
+use aili_style::{
+    cascade::CascadeStyle,
+    select::query,
+    selectable::Selectable,
+    stylesheet::{expression::*, selector::*, *},
+};
+
+#[test]
+fn query_lists_each_node_once_in_traversal_order() {
+    let graph = graph! {
+        0 -"a"-> 1;
+        0 -"b"-> 2;
+        1 -"a"-> 3;
+        2 -"a"-> 3;
+    };
+    // iter(*) "a"
+    let selector = Selector::from_path(
+        [
+            SelectorSegment::anything_any_number_of_times(),
+            SelectorSegment::Match(EdgeMatcher::Named("a".into())),
+        ]
+        .into(),
+    );
+    let matched = query(&graph, selector).collect::<Vec<_>>();
+    assert_eq!(matched, [Selectable::node(1), Selectable::node(3)]);
+}
+
+#[test]
+fn query_edges() {
+    let graph = graph! {
+        0 -main-> 1;
+        1 -ref-> 2;
+        1 -next-> 3;
+        3 -ref-> 2;
+    };
+    // iter(*) ref::edge
+    let selector = Selector::from_path(
+        [
+            SelectorSegment::anything_any_number_of_times(),
+            SelectorSegment::Match(EdgeLabel::Deref.into()),
+        ]
+        .into(),
+    )
+    .selecting_edge();
+    let matched = query(&graph, selector).collect::<Vec<_>>();
+    assert_eq!(
+        matched,
+        [
+            Selectable::edge(1, EdgeLabel::Deref),
+            Selectable::edge(3, EdgeLabel::Deref),
+        ]
+    );
+}
+
+#[test]
+fn query_extras() {
+    let graph = graph! {
+        0 -main-> 1;
+    };
+    // main::extra(x)
+    let selector = Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into())
+        .with_extra("x".to_owned());
+    let matched = query(&graph, selector).collect::<Vec<_>>();
+    assert_eq!(matched, [Selectable::node(1).with_extra("x".to_owned())]);
+}
+
+#[test]
+fn query_with_condition() {
+    let graph = graph! {
+        0 -main-> 1 [val = 0];
+        1 -next-> 2 [val = 5];
+        2 -next-> 3 [val = 0];
+    };
+    // main iter(next).if(is-zero(@depth - 1))
+    let selector = Selector::from_path(
+        [
+            SelectorSegment::Match(EdgeLabel::Main.into()),
+            SelectorSegment::AnyNumberOfTimes(
+                [SelectorSegment::Match(EdgeLabel::Next.into())].into(),
+            ),
+            SelectorSegment::Condition(Expression::UnaryOperator(
+                UnaryOperator::ValueIs(ValuePredicate::Zero),
+                Expression::BinaryOperator(
+                    Expression::MagicVariable(MagicVariableKey::Depth).into(),
+                    BinaryOperator::Minus,
+                    Expression::Int(1).into(),
+                )
+                .into(),
+            )),
+        ]
+        .into(),
+    );
+    let matched = query(&graph, selector).collect::<Vec<_>>();
+    assert_eq!(matched, [Selectable::node(1)]);
+}
+
+#[test]
+fn query_nothing() {
+    let graph = graph! {
+        0 -main-> 1;
+    };
+    // next
+    let selector = Selector::from_path([SelectorSegment::Match(EdgeLabel::Next.into())].into());
+    assert_eq!(query(&graph, selector).count(), 0);
+}
+
+/// Constructs a stylesheet for testing [`CascadeStyle::rules_matching`].
+///
+/// ```text
+/// /* 0 */ :: {
+///   --x: 1;
+/// }
+/// /* 1 */ main {}
+/// /* 2 */ iter(*).if(--x) {}
+/// /* 3 */ iter(*) ref {}
+/// /* 4 */ iter(*) ref::edge {}
+/// /* 5 */ main::extra(x) {}
+/// ```
+fn inspected_stylesheet() -> CascadeStyle {
+    Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: StyleKey::Variable("--x".to_owned()),
+                value: Expression::Int(1),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
+            properties: Vec::new(),
+        },
+        StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::anything_any_number_of_times(),
+                    SelectorSegment::Condition(Expression::Variable("--x".to_owned())),
+                ]
+                .into(),
+            ),
+            properties: Vec::new(),
+        },
+        StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::anything_any_number_of_times(),
+                    SelectorSegment::Match(EdgeLabel::Deref.into()),
+                ]
+                .into(),
+            ),
+            properties: Vec::new(),
+        },
+        StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::anything_any_number_of_times(),
+                    SelectorSegment::Match(EdgeLabel::Deref.into()),
+                ]
+                .into(),
+            )
+            .selecting_edge(),
+            properties: Vec::new(),
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into())
+                .with_extra("x".to_owned()),
+            properties: Vec::new(),
+        },
+    ])
+    .into()
+}
+
+#[test]
+fn rules_matching_node() {
+    let graph = graph! {
+        0 -main-> 1;
+        1 -ref-> 2;
+        0 -"a"-> 2;
+    };
+    let style = inspected_stylesheet();
+    // The variable is only assigned after the root is matched
+    assert_eq!(style.rules_matching(&graph, &Selectable::node(0)), [0]);
+    assert_eq!(style.rules_matching(&graph, &Selectable::node(1)), [1, 2]);
+    assert_eq!(style.rules_matching(&graph, &Selectable::node(2)), [2, 3]);
+}
+
+#[test]
+fn rules_matching_edge_and_extra() {
+    let graph = graph! {
+        0 -main-> 1;
+        1 -ref-> 2;
+    };
+    let style = inspected_stylesheet();
+    assert_eq!(
+        style.rules_matching(&graph, &Selectable::edge(1, EdgeLabel::Deref)),
+        [4]
+    );
+    assert_eq!(
+        style.rules_matching(&graph, &Selectable::node(1).with_extra("x".to_owned())),
+        [5]
+    );
+}
+
+#[test]
+fn rules_matching_unreachable_node() {
+    let graph = graph! {
+        0 -main-> 1;
+        2 -ref-> 1;
+    };
+    let style = inspected_stylesheet();
+    assert!(
+        style
+            .rules_matching(&graph, &Selectable::node(2))
+            .is_empty()
+    );
+}
@@ -0,0 +1,99 @@
+//! Tests for serialization of stylesheets.
+
+#![cfg(feature = "serde")]
+
+use aili_model::{
+    capability::CapabilitySet,
+    state::{EdgeLabel, NodeTypeClass},
+};
+use aili_style::{
+    cascade::CompiledStylesheet,
+    stylesheet::{expression::*, selector::*, *},
+};
+
+#[test]
+fn stylesheet_round_trip() {
+    let stylesheet = Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::Match(EdgeLabel::Main.into()),
+                    SelectorSegment::AnyNumberOfTimes(
+                        [SelectorSegment::Match(EdgeLabel::Next.into())].into(),
+                    ),
+                    SelectorSegment::Branch(vec![
+                        [SelectorSegment::Match(EdgeMatcher::Named("a".into()))].into(),
+                        [SelectorSegment::Match(
+                            EdgeLabel::Named("b".into(), 1).into(),
+                        )]
+                        .into(),
+                    ]),
+                    SelectorSegment::Condition(Expression::UnaryOperator(
+                        UnaryOperator::NodeIsA(NodeTypeClass::Ref),
+                        Expression::Select(LimitedSelector::default().into()).into(),
+                    )),
+                ]
+                .into(),
+            )
+            .selecting_edge()
+            .with_extra("label".to_owned()),
+            properties: vec![
+                StyleClause {
+                    key: StyleKey::Property(RawPropertyKey::Property("display".to_owned())),
+                    value: Expression::String("cell".to_owned()),
+                },
+                StyleClause {
+                    key: StyleKey::Variable("--i".to_owned()),
+                    value: Expression::BinaryOperator(
+                        Expression::MagicVariable(MagicVariableKey::EdgeIndex).into(),
+                        BinaryOperator::Plus,
+                        Expression::Int(1).into(),
+                    ),
+                },
+            ],
+        },
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: StyleKey::Property(RawPropertyKey::FragmentProperty(
+                    "start".to_owned(),
+                    "x".to_owned(),
+                )),
+                value: Expression::Select(
+                    LimitedSelector::from_path(vec![
+                        EdgeLabel::Deref.into(),
+                        LimitedEdgeMatcher::DynIndex(Expression::Variable("--i".to_owned())),
+                    ])
+                    .into(),
+                ),
+            }],
+        },
+    ]);
+    let json = serde_json::to_string(&stylesheet).expect("Stylesheet should serialize");
+    let deserialized: Stylesheet =
+        serde_json::from_str(&json).expect("Stylesheet should deserialize");
+    assert_eq!(deserialized, stylesheet);
+}
+
+#[test]
+fn compiled_stylesheet_round_trip() {
+    let stylesheet = Stylesheet::new(vec![StyleRule {
+        selector: Selector::from_path(
+            [
+                SelectorSegment::anything_any_number_of_times(),
+                SelectorSegment::Condition(Expression::Select(LimitedSelector::default().into())),
+            ]
+            .into(),
+        ),
+        properties: vec![StyleClause {
+            key: StyleKey::Property(RawPropertyKey::Property("display".to_owned())),
+            value: Expression::String("cell".to_owned()),
+        }],
+    }])
+    .with_requirements(CapabilitySet::new().with("gdb"));
+    let compiled = CompiledStylesheet::compile(stylesheet);
+    let serialized = serde_json::to_string(&compiled).unwrap();
+    let deserialized: CompiledStylesheet = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, compiled);
+    assert!(deserialized.requirements().contains("gdb"));
+}
@@ -1,22 +1,61 @@
 //! Stub program state graphs for testing.
 
-use aili_model::state::*;
+use aili_model::{
+    builder::{StateGraph, StateGraphBuilder},
+    state::*,
+};
 use aili_style::stylesheet::expression::LimitedSelector;
-use std::collections::HashMap;
 
 /// Stub graph for testing graph-based code.
-pub struct TestGraph(Vec<TestNode>);
+pub struct TestGraph(StateGraph);
 
 impl TestGraph {
     /// Shorthand for a pre-constructed graph for running tests.
     pub fn default_graph() -> Self {
-        use EdgeLabel::*;
-        Self(vec![
-            // 0 - root and valueless node
-            TestNode([(Named("a".to_owned(), 0), 1)].into(), None),
-            // 1 - numeric node
-            TestNode([].into(), Some(NodeValue::Uint(Self::NUMERIC_NODE_VALUE))),
-        ])
+        let mut builder = StateGraphBuilder::new();
+        // 1 - numeric node
+        let numeric = builder.add_node(
+            NodeTypeClass::Atom,
+            Some(NodeValue::Uint(Self::NUMERIC_NODE_VALUE)),
+        );
+        // 2 - node of a custom class
+        let custom = builder.add_node(NodeTypeClass::Custom, None);
+        builder
+            .node_mut(custom)
+            .unwrap()
+            .set_custom_type_class(Self::CUSTOM_NODE_CLASS);
+        // 3 - string node
+        let string = builder.add_node(
+            NodeTypeClass::Atom,
+            Some(NodeValue::String(Self::STRING_NODE_VALUE.to_owned())),
+        );
+        // 4 - floating point node
+        let float = builder.add_node(
+            NodeTypeClass::Atom,
+            Some(NodeValue::Float(Self::FLOAT_NODE_VALUE)),
+        );
+        // 5 - node with a source location
+        let located = builder.add_node(NodeTypeClass::Atom, None);
+        builder
+            .node_mut(located)
+            .unwrap()
+            .set_source_location(Some(SourceLocation {
+                file: Self::SOURCE_FILE.to_owned(),
+                line: Self::SOURCE_LINE,
+            }));
+        // 0 - root and valueless node
+        for (name, node) in [
+            ("a", numeric),
+            ("c", custom),
+            ("s", string),
+            ("f", float),
+            ("l", located),
+        ] {
+            builder
+                .add_edge(builder.root(), EdgeLabel::Named(name.into(), 0), node)
+                .unwrap();
+        }
+        Self(builder.build())
     }
 
     /// Constructs a selector that selects a valueless node
@@ -31,6 +70,30 @@ impl TestGraph {
         LimitedSelector::from_path([EdgeLabel::Named("a".into(), 0).into()])
     }
 
+    /// Constructs a selector that matches a node of a custom type class
+    /// in the [`default_graph`](TestGraph::default_graph).
+    pub fn custom_node_selector() -> LimitedSelector {
+        LimitedSelector::from_path([EdgeLabel::Named("c".into(), 0).into()])
+    }
+
+    /// Constructs a selector that matches a string-valued node
+    /// in the [`default_graph`](TestGraph::default_graph).
+    pub fn string_node_selector() -> LimitedSelector {
+        LimitedSelector::from_path([EdgeLabel::Named("s".into(), 0).into()])
+    }
+
+    /// Constructs a selector that matches a float-valued node
+    /// in the [`default_graph`](TestGraph::default_graph).
+    pub fn float_node_selector() -> LimitedSelector {
+        LimitedSelector::from_path([EdgeLabel::Named("f".into(), 0).into()])
+    }
+
+    /// Constructs a selector that matches a node with a source location
+    /// in the [`default_graph`](TestGraph::default_graph).
+    pub fn located_node_selector() -> LimitedSelector {
+        LimitedSelector::from_path([EdgeLabel::Named("l".into(), 0).into()])
+    }
+
     /// Constructs a selector that does not match a node
     /// in the [`default_graph`](TestGraph::default_graph).
     pub fn missing_node_selector() -> LimitedSelector {
@@ -41,44 +104,43 @@ impl TestGraph {
     /// [`numeric_node_selector`](TestGraph::numeric_node_selector)
     /// in the [`default_graph`](TestGraph::default_graph)
     pub const NUMERIC_NODE_VALUE: u64 = 37;
+
+    /// Value of the node selected by
+    /// [`string_node_selector`](TestGraph::string_node_selector)
+    /// in the [`default_graph`](TestGraph::default_graph)
+    pub const STRING_NODE_VALUE: &str = "text";
+
+    /// Value of the node selected by
+    /// [`float_node_selector`](TestGraph::float_node_selector)
+    /// in the [`default_graph`](TestGraph::default_graph)
+    pub const FLOAT_NODE_VALUE: f64 = 2.5;
+
+    /// Name of the custom type class of the node selected by
+    /// [`custom_node_selector`](TestGraph::custom_node_selector)
+    /// in the [`default_graph`](TestGraph::default_graph)
+    pub const CUSTOM_NODE_CLASS: &str = "task";
+
+    /// Source file of the node selected by
+    /// [`located_node_selector`](TestGraph::located_node_selector)
+    /// in the [`default_graph`](TestGraph::default_graph)
+    pub const SOURCE_FILE: &str = "main.c";
+
+    /// Source line of the node selected by
+    /// [`located_node_selector`](TestGraph::located_node_selector)
+    /// in the [`default_graph`](TestGraph::default_graph)
+    pub const SOURCE_LINE: u64 = 42;
 }
 
 impl ProgramStateGraph for TestGraph {
     type NodeId = usize;
-    type NodeRef<'a> = &'a TestNode;
+    type NodeRef<'a> = <StateGraph as ProgramStateGraph>::NodeRef<'a>;
     fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
-        self.0.get(*id)
+        self.0.get(id)
     }
 }
 
 impl RootedProgramStateGraph for TestGraph {
     fn root(&self) -> Self::NodeId {
-        0
-    }
-}
-
-/// Node of [`TestGraph`].
-pub struct TestNode(HashMap<EdgeLabel, usize>, Option<NodeValue>);
-
-impl ProgramStateNode for &TestNode {
-    type NodeId = usize;
-    type NodeTypeId<'a>
-        = &'a str
-    where
-        Self: 'a;
-    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
-        self.0.get(edge).copied()
-    }
-    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
-        self.0.iter().map(|(k, v)| (k, *v))
-    }
-    fn node_type_class(&self) -> NodeTypeClass {
-        NodeTypeClass::Root
-    }
-    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
-        None
-    }
-    fn value(&self) -> Option<NodeValue> {
-        self.1
+        self.0.root()
     }
 }
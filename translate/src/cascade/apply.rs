@@ -1,25 +1,221 @@
 //! Evaluation of an entire stylesheet.
 
-use super::mapping_builder::PropertyMappingBuilder;
-use crate::property::{EntityPropertyMapping, PropertyKey};
+use super::{
+    budget::{PartialMapping, TraversalBudget},
+    lifetime::{LifetimeGraph, LifetimeTracker},
+    mapping_builder::PropertyMappingBuilder,
+    stats::CascadeStats,
+    traversal::TraversalMode,
+};
+use crate::property::{EntityPropertyMapping, PropertyKey, typed::AttributeTypeMismatch};
 use aili_model::state::{EdgeLabel, ProgramStateNode, RootedProgramStateGraph};
 use aili_style::{
-    cascade::{CascadeStyle, SelectionCaret, SelectorResolver},
-    eval::{context::EvaluationContext, evaluate, variable_pool::VariablePool},
+    cascade::{ActiveStates, CascadeContext, CascadeStyle, SelectionCaret, SelectorResolver},
+    eval::{
+        context::{EntityLifetime, EvaluationContext, TraversalPosition, layer_indices},
+        evaluate,
+        variable_pool::VariablePool,
+    },
     selectable::Selectable,
     stylesheet::StyleKey,
 };
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
 
 /// Applies a stylesheet to a graph.
+///
+/// Values of well-known attributes that do not have the required type
+/// are silently left out. Use [`apply_stylesheet_with_warnings`]
+/// to be notified about them.
 pub fn apply_stylesheet<T: RootedProgramStateGraph>(
     stylesheet: &CascadeStyle<PropertyKey>,
     graph: &T,
+) -> EntityPropertyMapping<T::NodeId> {
+    apply_stylesheet_with_warnings(stylesheet, graph, |_| {})
+}
+
+/// Applies a stylesheet to a graph and reports values
+/// of well-known attributes that do not have the required type.
+///
+/// See [`typed`](crate::property::typed) for the types of attributes.
+pub fn apply_stylesheet_with_warnings<T: RootedProgramStateGraph>(
+    stylesheet: &CascadeStyle<PropertyKey>,
+    graph: &T,
+    warning_handler: impl FnMut(AttributeTypeMismatch<T::NodeId>),
+) -> EntityPropertyMapping<T::NodeId> {
+    let mut helper = ApplyStylesheet::new(stylesheet, graph);
+    helper.run();
+    helper.result(warning_handler)
+}
+
+/// Applies a stylesheet to a graph in a context provided by the embedder.
+///
+/// Variables of the context are visible to all expressions
+/// in the stylesheet, unless a rule assigns them another value.
+/// Rules in [conditional blocks](aili_style::stylesheet::ConditionalBlock)
+/// only apply if their conditions hold in the context. Other applications
+/// use an empty context, so the conditions only see unset variables.
+///
+/// Values of well-known attributes that do not have the required type
+/// are silently left out, like in [`apply_stylesheet`].
+pub fn apply_stylesheet_with_context<T: RootedProgramStateGraph>(
+    stylesheet: &CascadeStyle<PropertyKey>,
+    graph: &T,
+    context: &CascadeContext,
 ) -> EntityPropertyMapping<T::NodeId> {
     let mut helper = ApplyStylesheet::new(stylesheet, graph);
+    helper.enter_context(context);
     helper.run();
-    helper.result()
+    helper.result(|_| {})
 }
 
+/// Applies a stylesheet to a graph and counts the work it takes.
+///
+/// The counters of this application are added to `stats`,
+/// so counters of multiple applications can be accumulated.
+/// Rules are timed with [`Instant`], so this must not be used
+/// on platforms that do not have a clock, such as `wasm32-unknown-unknown`.
+///
+/// Values of well-known attributes that do not have the required type
+/// are silently left out, like in [`apply_stylesheet`].
+pub fn apply_stylesheet_with_stats<T: RootedProgramStateGraph>(
+    stylesheet: &CascadeStyle<PropertyKey>,
+    graph: &T,
+    stats: &mut CascadeStats,
+) -> EntityPropertyMapping<T::NodeId> {
+    let mut helper = ApplyStylesheet::new(stylesheet, graph);
+    helper.start_stats();
+    helper.run();
+    helper.finish_stats(stats);
+    helper.result(|_| {})
+}
+
+/// Applies a stylesheet to a graph, but stops traversing it
+/// once a budget has been exhausted.
+///
+/// The properties assigned before the traversal stopped
+/// are kept, so the result is a partial mapping, which is flagged
+/// as [truncated](PartialMapping::truncated). This keeps embedders
+/// responsive when a graph is too large to be styled in time.
+///
+/// Values of well-known attributes that do not have the required type
+/// are silently left out, like in [`apply_stylesheet`].
+pub fn apply_stylesheet_with_budget<T: RootedProgramStateGraph>(
+    stylesheet: &CascadeStyle<PropertyKey>,
+    graph: &T,
+    budget: TraversalBudget,
+) -> PartialMapping<T::NodeId> {
+    let mut helper = ApplyStylesheet::new(stylesheet, graph);
+    helper.budget = Some(budget);
+    helper.run();
+    let truncated = helper.truncated;
+    PartialMapping {
+        mapping: helper.result(|_| {}),
+        truncated,
+    }
+}
+
+/// Applies a stylesheet to a graph, traversing it in a specified way.
+///
+/// See [`TraversalMode`] for how the modes differ.
+///
+/// Values of well-known attributes that do not have the required type
+/// are silently left out, like in [`apply_stylesheet`].
+pub fn apply_stylesheet_with_mode<T: RootedProgramStateGraph>(
+    stylesheet: &CascadeStyle<PropertyKey>,
+    graph: &T,
+    mode: TraversalMode,
+) -> EntityPropertyMapping<T::NodeId> {
+    let mut helper = ApplyStylesheet::new(stylesheet, graph);
+    if mode == TraversalMode::VisitOnce {
+        helper.visited = Some(HashSet::new());
+    }
+    helper.run();
+    helper.result(|_| {})
+}
+
+/// Applies a stylesheet to a graph and tags nodes that have been
+/// added or removed since the stylesheet was last applied
+/// with the same tracker.
+///
+/// Removed nodes are included in the resulting mapping
+/// for one more application, so stylesheets can style them
+/// as they leave the visualization. See [`LifetimeTracker::track`].
+pub fn apply_stylesheet_tracked<T: RootedProgramStateGraph>(
+    stylesheet: &CascadeStyle<PropertyKey>,
+    graph: &T,
+    tracker: &mut LifetimeTracker<T::NodeId>,
+) -> EntityPropertyMapping<T::NodeId> {
+    apply_stylesheet_tracked_with_warnings(stylesheet, graph, tracker, |_| {})
+}
+
+/// Applies a stylesheet to a graph like [`apply_stylesheet_tracked`]
+/// and reports values of well-known attributes that do not have
+/// the required type, like [`apply_stylesheet_with_warnings`].
+pub fn apply_stylesheet_tracked_with_warnings<T: RootedProgramStateGraph>(
+    stylesheet: &CascadeStyle<PropertyKey>,
+    graph: &T,
+    tracker: &mut LifetimeTracker<T::NodeId>,
+    warning_handler: impl FnMut(AttributeTypeMismatch<T::NodeId>),
+) -> EntityPropertyMapping<T::NodeId> {
+    apply_stylesheet_to_lifetimes(stylesheet, &tracker.track(graph), warning_handler, None)
+}
+
+/// Applies a stylesheet to a graph that has not changed
+/// since it was last tracked by a tracker.
+///
+/// Nodes have the same lifetimes as they had in the last
+/// [tracked application](apply_stylesheet_tracked), so replacing
+/// the stylesheet does not make new or removed nodes look like
+/// they have been there all along. See [`LifetimeTracker::view`].
+pub fn reapply_stylesheet_tracked<T: RootedProgramStateGraph>(
+    stylesheet: &CascadeStyle<PropertyKey>,
+    graph: &T,
+    tracker: &LifetimeTracker<T::NodeId>,
+) -> EntityPropertyMapping<T::NodeId> {
+    reapply_stylesheet_tracked_with_warnings(stylesheet, graph, tracker, |_| {})
+}
+
+/// Applies a stylesheet to a graph like [`reapply_stylesheet_tracked`]
+/// and reports values of well-known attributes that do not have
+/// the required type, like [`apply_stylesheet_with_warnings`].
+pub fn reapply_stylesheet_tracked_with_warnings<T: RootedProgramStateGraph>(
+    stylesheet: &CascadeStyle<PropertyKey>,
+    graph: &T,
+    tracker: &LifetimeTracker<T::NodeId>,
+    warning_handler: impl FnMut(AttributeTypeMismatch<T::NodeId>),
+) -> EntityPropertyMapping<T::NodeId> {
+    apply_stylesheet_to_lifetimes(stylesheet, &tracker.view(graph), warning_handler, None)
+}
+
+/// Applies a stylesheet to a graph whose nodes are tagged with lifetimes.
+///
+/// If `stats` are provided, the counters of this application
+/// are added to them, like in [`apply_stylesheet_with_stats`].
+pub(crate) fn apply_stylesheet_to_lifetimes<T: RootedProgramStateGraph>(
+    stylesheet: &CascadeStyle<PropertyKey>,
+    graph: &LifetimeGraph<T>,
+    warning_handler: impl FnMut(AttributeTypeMismatch<T::NodeId>),
+    stats: Option<&mut CascadeStats>,
+) -> EntityPropertyMapping<T::NodeId> {
+    let lifetime_of = |id: &T::NodeId| graph.lifetime_of(id);
+    let mut helper = ApplyStylesheet::new(stylesheet, graph);
+    helper.lifetime_of = Some(&lifetime_of);
+    if stats.is_some() {
+        helper.start_stats();
+    }
+    helper.run();
+    if let Some(stats) = stats {
+        helper.finish_stats(stats);
+    }
+    helper.result(warning_handler)
+}
+
+/// Function that gets the lifetime of a node.
+type LifetimeLookup<'g, T> = dyn Fn(&T) -> Option<EntityLifetime> + 'g;
+
 /// Helper for stylesheet applications.
 struct ApplyStylesheet<'a, 'g, T: RootedProgramStateGraph> {
     /// The graph being traversed.
@@ -36,6 +232,35 @@ struct ApplyStylesheet<'a, 'g, T: RootedProgramStateGraph> {
 
     /// Variables that are active at the moment
     variable_pool: VariablePool<&'a str, T::NodeId>,
+
+    /// Layer indices of all nodes in the graph,
+    /// if the stylesheet refers to them.
+    layer_indices: Option<HashMap<T::NodeId, usize>>,
+
+    /// Flags that indicate which rules apply in the context
+    /// of the application, indexed by rule index.
+    enabled_rules: Vec<bool>,
+
+    /// Lookup of the lifetimes of nodes, if they are tracked.
+    lifetime_of: Option<&'g LifetimeLookup<'g, T::NodeId>>,
+
+    /// Performance counters, if they are collected.
+    stats: Option<CascadeStats>,
+
+    /// Limits on the traversal, if it is limited.
+    budget: Option<TraversalBudget>,
+
+    /// Number of nodes that have been visited so far.
+    nodes_visited: usize,
+
+    /// Indicates that the traversal has been stopped
+    /// because it has run out of budget.
+    truncated: bool,
+
+    /// Nodes that have been entered and the selector states
+    /// they were entered with, if each combination
+    /// should only be entered once.
+    visited: Option<HashSet<(T::NodeId, ActiveStates)>>,
 }
 
 impl<'a, 'g, T: RootedProgramStateGraph> ApplyStylesheet<'a, 'g, T> {
@@ -46,15 +271,57 @@ impl<'a, 'g, T: RootedProgramStateGraph> ApplyStylesheet<'a, 'g, T> {
             resolver: SelectorResolver::new(stylesheet.selector_machine()),
             mapping: PropertyMappingBuilder::new(),
             variable_pool: VariablePool::new(),
+            layer_indices: stylesheet
+                .uses_layer_indices()
+                .then(|| layer_indices(graph)),
+            enabled_rules: CascadeContext::new().enabled_rules(stylesheet),
+            lifetime_of: None,
+            stats: None,
+            budget: None,
+            nodes_visited: 0,
+            truncated: false,
+            visited: None,
+        }
+    }
+
+    /// Makes the variables of a context visible to the stylesheet
+    /// and enables the rules that apply in it.
+    fn enter_context(&mut self, context: &'a CascadeContext) {
+        self.enabled_rules = context.enabled_rules(self.stylesheet);
+        // The bottom frame is never popped,
+        // so the variables are visible everywhere
+        for (name, value) in &context.variables {
+            self.variable_pool.insert(name, value.clone().into());
+        }
+    }
+
+    /// Starts counting the work the application takes.
+    fn start_stats(&mut self) {
+        self.stats = Some(CascadeStats {
+            rule_times: vec![Duration::ZERO; self.stylesheet.rule_count()],
+            rule_matches: vec![0; self.stylesheet.rule_count()],
+            ..CascadeStats::default()
+        });
+    }
+
+    /// Adds the counted work to accumulated counters.
+    fn finish_stats(&mut self, stats: &mut CascadeStats) {
+        if let Some(mut helper_stats) = self.stats.take() {
+            helper_stats.conditions_evaluated = self.resolver.conditions_evaluated();
+            helper_stats.rule_match_attempts = self.resolver.match_attempts().to_vec();
+            stats.merge(&helper_stats);
         }
     }
 
-    fn result(self) -> EntityPropertyMapping<T::NodeId> {
-        self.mapping.build(self.graph)
+    fn result(
+        self,
+        warning_handler: impl FnMut(AttributeTypeMismatch<T::NodeId>),
+    ) -> EntityPropertyMapping<T::NodeId> {
+        self.mapping.build(self.graph, warning_handler)
     }
 
     fn run(&mut self) {
-        self.run_from(self.graph.root(), None, None);
+        self.run_from(self.graph.root(), None, None, 0, 0);
     }
 
     /// Traverses depth-first from a specified node and evaluates the selector.
@@ -63,18 +330,48 @@ impl<'a, 'g, T: RootedProgramStateGraph> ApplyStylesheet<'a, 'g, T> {
         node: T::NodeId,
         previous_node: Option<T::NodeId>,
         previous_edge: Option<&EdgeLabel>,
+        depth: usize,
+        sibling_index: usize,
     ) {
-        let matched_rules = self.resolve_node(node.clone(), previous_edge);
+        if self.truncated
+            || self
+                .budget
+                .is_some_and(|budget| !budget.allows_visit(self.nodes_visited))
+        {
+            self.truncated = true;
+            return;
+        }
+        self.nodes_visited += 1;
+        let position = TraversalPosition {
+            depth,
+            sibling_index,
+            layer_index: self.layer_index(&node),
+        };
+        if let Some(stats) = &mut self.stats {
+            stats.nodes_visited += 1;
+        }
+
+        let matched_rules = self.resolve_node(
+            node.clone(),
+            previous_node.as_ref(),
+            previous_edge,
+            position,
+        );
+        if let Some(stats) = &mut self.stats {
+            for (rule_index, _) in &matched_rules {
+                stats.rule_matches[*rule_index] += 1;
+            }
+        }
 
         self.mapping.push();
 
-        self.resolve_matched_rules(&node, previous_node, previous_edge, matched_rules);
+        self.resolve_matched_rules(&node, previous_node, previous_edge, position, matched_rules);
 
         // This is our termination condition:
         // We stop once there is nothing else to explore
         if self.resolver.has_edges_to_resolve() {
             // Traverse down the tree through all edges
-            self.traverse_outgoing_edges(node);
+            self.traverse_outgoing_edges(node, depth);
         }
 
         self.mapping.pop();
@@ -85,6 +382,7 @@ impl<'a, 'g, T: RootedProgramStateGraph> ApplyStylesheet<'a, 'g, T> {
         node: &T::NodeId,
         previous_node: Option<T::NodeId>,
         previous_edge: Option<&EdgeLabel>,
+        position: TraversalPosition,
         mut matched_rules: Vec<(usize, SelectionCaret)>,
     ) {
         // Resolve rules in correct order
@@ -96,8 +394,17 @@ impl<'a, 'g, T: RootedProgramStateGraph> ApplyStylesheet<'a, 'g, T> {
             (caret == SelectionCaret::Node, has_extra, rule_index)
         });
 
+        // Removed nodes are only styled by rules that expect them
+        let is_removed = self.lifetime(node) == Some(EntityLifetime::Removed);
+
         // Resolve all entities that matched
         for (rule_index, caret) in matched_rules {
+            if !self.enabled_rules[rule_index] {
+                continue;
+            }
+            if is_removed && !self.stylesheet.rule_at(rule_index).selects_by_lifetime {
+                continue;
+            }
             let mut selected = if caret == SelectionCaret::Node {
                 Selectable::node(node.clone())
             } else if let Some(selected) = previous_node.clone().and_then(|node| {
@@ -109,8 +416,19 @@ impl<'a, 'g, T: RootedProgramStateGraph> ApplyStylesheet<'a, 'g, T> {
             } else {
                 continue;
             };
-            selected.extra_label = self.stylesheet.rule_at(rule_index).extra_label.clone();
-            self.selected_entity(&selected, node, rule_index, previous_edge);
+            let rule = self.stylesheet.rule_at(rule_index);
+            selected.extra_label = rule.extra_label.clone();
+            if let Some(extra_index) = rule.extra_index {
+                self.mapping.order_extra(&selected, extra_index, rule_index);
+            }
+            self.selected_entity(
+                &selected,
+                node,
+                rule_index,
+                previous_node.as_ref(),
+                previous_edge,
+                position,
+            );
         }
     }
 
@@ -118,28 +436,63 @@ impl<'a, 'g, T: RootedProgramStateGraph> ApplyStylesheet<'a, 'g, T> {
     fn resolve_node(
         &mut self,
         node: T::NodeId,
+        previous_node: Option<&T::NodeId>,
         previous_edge: Option<&EdgeLabel>,
+        position: TraversalPosition,
     ) -> Vec<(usize, SelectionCaret)> {
         let context = EvaluationContext::from_graph(self.graph, node.clone())
             .with_variables(&self.variable_pool)
-            .with_optional_preceding_edge(previous_edge);
+            .with_optional_parent(previous_node.cloned())
+            .with_optional_preceding_edge(previous_edge)
+            .with_traversal_position(position)
+            .with_lifetime(self.lifetime(&node));
         self.resolver.resolve_node(node, &context)
     }
 
+    /// Gets the lifetime of a node, if lifetimes are tracked.
+    fn lifetime(&self, node: &T::NodeId) -> Option<EntityLifetime> {
+        self.lifetime_of.and_then(|lifetime_of| lifetime_of(node))
+    }
+
+    /// Gets the layer index of a node, if the stylesheet refers to it.
+    fn layer_index(&self, node: &T::NodeId) -> usize {
+        self.layer_indices
+            .as_ref()
+            .and_then(|layer_indices| layer_indices.get(node))
+            .copied()
+            .unwrap_or_default()
+    }
+
     /// Traverses depth-first through all outgoing edges of a node.
-    fn traverse_outgoing_edges(&mut self, starting_node: T::NodeId) {
+    fn traverse_outgoing_edges(&mut self, starting_node: T::NodeId, depth: usize) {
         let Some(node) = self.graph.get(&starting_node) else {
             return;
         };
-        for (edge_label, successor_node) in node.successors() {
+        for (sibling_index, (edge_label, successor_node)) in node.successors().enumerate() {
+            if self.truncated {
+                break;
+            }
+            if let Some(stats) = &mut self.stats {
+                stats.edges_traversed += 1;
+            }
             // Push a state so we can pop it later
             self.variable_pool.push();
             self.resolver.push_edge(edge_label);
+            if let Some(visited) = &mut self.visited {
+                // Skip the node if it has been entered the same way before
+                if !visited.insert((successor_node.clone(), self.resolver.active_states())) {
+                    self.resolver.pop_edge();
+                    self.variable_pool.pop();
+                    continue;
+                }
+            }
             // Resolve the following edge and node
             self.run_from(
                 successor_node,
                 Some(starting_node.clone()),
                 Some(edge_label),
+                depth + 1,
+                sibling_index,
             );
             // Discard all variables that were created here
             self.resolver.pop_edge();
@@ -152,8 +505,18 @@ impl<'a, 'g, T: RootedProgramStateGraph> ApplyStylesheet<'a, 'g, T> {
         target: &Selectable<T::NodeId>,
         select_origin: &T::NodeId,
         rule_index: usize,
+        previous_node: Option<&T::NodeId>,
         previous_edge: Option<&EdgeLabel>,
+        position: TraversalPosition,
     ) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!(
+            "rule",
+            index = rule_index,
+            name = self.stylesheet.rule_at(rule_index).name.as_deref()
+        )
+        .entered();
+        let start_time = self.stats.is_some().then(Instant::now);
         // Adjust the mapping to the new entity
         self.mapping
             .selected_entity(target, select_origin, rule_index);
@@ -166,7 +529,10 @@ impl<'a, 'g, T: RootedProgramStateGraph> ApplyStylesheet<'a, 'g, T> {
         for property in properties {
             let context = EvaluationContext::from_graph(self.graph, select_origin.clone())
                 .with_variables(&self.variable_pool)
-                .with_optional_preceding_edge(previous_edge);
+                .with_optional_parent(previous_node.cloned())
+                .with_optional_preceding_edge(previous_edge)
+                .with_traversal_position(position)
+                .with_lifetime(self.lifetime(select_origin));
             let value = evaluate(&property.value, &context);
             match &property.key {
                 StyleKey::Property(key) => {
@@ -180,5 +546,9 @@ impl<'a, 'g, T: RootedProgramStateGraph> ApplyStylesheet<'a, 'g, T> {
         if target.is_extra() {
             self.variable_pool.pop();
         }
+        if let (Some(stats), Some(start_time)) = (&mut self.stats, start_time) {
+            stats.rules_evaluated += 1;
+            stats.rule_times[rule_index] += start_time.elapsed();
+        }
     }
 }
@@ -0,0 +1,69 @@
+//! Limits on how much work an application of a stylesheet may do.
+
+use crate::property::EntityPropertyMapping;
+use aili_model::state::NodeId;
+use std::time::Instant;
+
+/// Limits on the traversal of a graph during
+/// [`apply_stylesheet_with_budget`](super::apply_stylesheet_with_budget).
+///
+/// Once any of the limits is exceeded, the traversal stops
+/// and the mapping is built from what has been evaluated so far.
+/// A budget with no limits does not stop the traversal at all.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TraversalBudget {
+    /// Maximum number of times a node may be reached by the traversal.
+    ///
+    /// Nodes that are reachable along multiple paths
+    /// count once for each path they are reached by.
+    pub max_nodes: Option<usize>,
+
+    /// Point in time after which no more nodes are visited.
+    ///
+    /// Deadlines are checked with [`Instant`], so they must not be used
+    /// on platforms that do not have a clock, such as `wasm32-unknown-unknown`.
+    pub deadline: Option<Instant>,
+}
+
+impl TraversalBudget {
+    /// Constructs a budget with no limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the number of nodes that may be visited.
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Limits the time that the traversal may take.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Checks whether another node may be visited
+    /// after a number of nodes have been visited already.
+    pub(super) fn allows_visit(&self, nodes_visited: usize) -> bool {
+        self.max_nodes
+            .is_none_or(|max_nodes| nodes_visited < max_nodes)
+            && self
+                .deadline
+                .is_none_or(|deadline| Instant::now() < deadline)
+    }
+}
+
+/// Result of an application of a stylesheet that may have been cut short.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PartialMapping<T: NodeId> {
+    /// The resulting mapping.
+    ///
+    /// If the traversal has been [truncated](PartialMapping::truncated),
+    /// it only contains the properties assigned before it stopped.
+    pub mapping: EntityPropertyMapping<T>,
+
+    /// Indicates that the traversal has run out of budget
+    /// before it has visited all nodes it would have.
+    pub truncated: bool,
+}
@@ -0,0 +1,374 @@
+//! Tracking of nodes that enter or leave a state graph
+//! between applications of a stylesheet.
+
+use aili_model::{capability::CapabilitySet, state::*};
+use aili_style::eval::context::EntityLifetime;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+/// Remembers the nodes of a state graph between applications
+/// of a stylesheet, so each application can tell which nodes
+/// have been added or removed since the previous one.
+#[derive(Debug)]
+pub struct LifetimeTracker<T: NodeId> {
+    /// Copy of all nodes that were reachable in the graph
+    /// when it was last tracked.
+    snapshot: HashMap<T, NodeSnapshot<T>>,
+
+    /// Changes found by the last comparison.
+    changes: TrackedChanges<T>,
+}
+
+/// Differences between two consecutive graphs
+/// seen by a [`LifetimeTracker`].
+#[derive(Debug)]
+struct TrackedChanges<T: NodeId> {
+    /// Nodes that have been added.
+    new_nodes: HashSet<T>,
+
+    /// Copies of nodes that have been removed.
+    removed_nodes: HashMap<T, NodeSnapshot<T>>,
+
+    /// Edges from present nodes to removed nodes
+    /// that are kept so the removed nodes stay reachable.
+    retained_edges: HashMap<T, Vec<(EdgeLabel, T)>>,
+}
+
+impl<T: NodeId> Default for TrackedChanges<T> {
+    fn default() -> Self {
+        Self {
+            new_nodes: HashSet::new(),
+            removed_nodes: HashMap::new(),
+            retained_edges: HashMap::new(),
+        }
+    }
+}
+
+impl<T: NodeId> LifetimeTracker<T> {
+    /// Constructs a tracker that has not seen any graph yet.
+    ///
+    /// All nodes of the first tracked graph are considered
+    /// [`New`](EntityLifetime::New).
+    pub fn new() -> Self {
+        Self {
+            snapshot: HashMap::new(),
+            changes: TrackedChanges::default(),
+        }
+    }
+
+    /// Compares a graph to the graph that was tracked previously
+    /// and remembers it for the next comparison.
+    ///
+    /// Returns a view of the graph in which the nodes that have been
+    /// removed since the previous comparison are still present,
+    /// so that their removal can be visualized.
+    pub fn track<'a, G>(&'a mut self, graph: &'a G) -> LifetimeGraph<'a, G>
+    where
+        G: RootedProgramStateGraph<NodeId = T>,
+    {
+        let current = snapshot_graph(graph);
+        let previous = std::mem::replace(&mut self.snapshot, current);
+        let current = &self.snapshot;
+        let new_nodes = current
+            .keys()
+            .filter(|id| !previous.contains_key(id))
+            .cloned()
+            .collect();
+        let mut retained_edges = HashMap::new();
+        for (id, previous_node) in &previous {
+            let Some(current_node) = current.get(id) else {
+                continue;
+            };
+            // Removed successors stay where they were,
+            // unless their edge has been taken over by another node
+            let edges = previous_node
+                .successors
+                .iter()
+                .filter(|(edge, successor)| {
+                    !current.contains_key(successor) && current_node.get_successor(edge).is_none()
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if !edges.is_empty() {
+                retained_edges.insert(id.clone(), edges);
+            }
+        }
+        let removed_nodes = previous
+            .into_iter()
+            .filter(|(id, _)| !current.contains_key(id))
+            .collect();
+        self.changes = TrackedChanges {
+            new_nodes,
+            removed_nodes,
+            retained_edges,
+        };
+        self.view(graph)
+    }
+
+    /// Returns the same view of a graph as the last call
+    /// to [`track`](LifetimeTracker::track), without comparing
+    /// the graph again.
+    ///
+    /// This is meant for applying a different stylesheet
+    /// to a graph that has not changed since it was last tracked,
+    /// so nodes keep their lifetimes instead of all becoming
+    /// neither new nor removed.
+    pub fn view<'a, G>(&'a self, graph: &'a G) -> LifetimeGraph<'a, G>
+    where
+        G: RootedProgramStateGraph<NodeId = T>,
+    {
+        LifetimeGraph {
+            graph,
+            changes: &self.changes,
+        }
+    }
+}
+
+impl<T: NodeId> Default for LifetimeTracker<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// View of a state graph that also includes the nodes
+/// that have been removed from it since it was last tracked
+/// by a [`LifetimeTracker`].
+///
+/// Removed nodes are attached to the nodes they were attached to
+/// before they were removed, as long as those nodes are still present
+/// and the same edge does not lead to another node now.
+pub struct LifetimeGraph<'a, G: ProgramStateGraph> {
+    graph: &'a G,
+    changes: &'a TrackedChanges<G::NodeId>,
+}
+
+impl<G: ProgramStateGraph> LifetimeGraph<'_, G> {
+    /// Gets the lifetime of a node.
+    ///
+    /// Returns [`None`] for nodes that have neither
+    /// been added nor removed, and for nodes that do not exist.
+    pub fn lifetime_of(&self, id: &G::NodeId) -> Option<EntityLifetime> {
+        if self.changes.removed_nodes.contains_key(id) {
+            Some(EntityLifetime::Removed)
+        } else if self.changes.new_nodes.contains(id) {
+            Some(EntityLifetime::New)
+        } else {
+            None
+        }
+    }
+}
+
+impl<G: ProgramStateGraph> ProgramStateGraph for LifetimeGraph<'_, G> {
+    type NodeId = G::NodeId;
+    type NodeRef<'b>
+        = LifetimeNodeRef<'b, G>
+    where
+        Self: 'b;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        // The graph may still hold nodes that are no longer reachable
+        if let Some(node) = self.changes.removed_nodes.get(id) {
+            return Some(LifetimeNodeRef(NodeRefInner::Removed(node)));
+        }
+        let node = self.graph.get(id)?;
+        let retained_edges = self
+            .changes
+            .retained_edges
+            .get(id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        Some(LifetimeNodeRef(NodeRefInner::Present(node, retained_edges)))
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        self.graph.capabilities()
+    }
+}
+
+impl<G: RootedProgramStateGraph> RootedProgramStateGraph for LifetimeGraph<'_, G> {
+    fn root(&self) -> Self::NodeId {
+        self.graph.root()
+    }
+}
+
+/// Reference to a node of a [`LifetimeGraph`].
+pub struct LifetimeNodeRef<'a, G: ProgramStateGraph + 'a>(NodeRefInner<'a, G>);
+
+enum NodeRefInner<'a, G: ProgramStateGraph + 'a> {
+    /// Node that is present in the tracked graph,
+    /// along with edges to removed nodes that it retains.
+    Present(G::NodeRef<'a>, &'a [(EdgeLabel, G::NodeId)]),
+
+    /// Node that has been removed from the tracked graph.
+    Removed(&'a NodeSnapshot<G::NodeId>),
+}
+
+impl<G: ProgramStateGraph> ProgramStateNode for LifetimeNodeRef<'_, G> {
+    type NodeId = G::NodeId;
+    type NodeTypeId<'b>
+        = Cow<'b, str>
+    where
+        Self: 'b;
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
+        match &self.0 {
+            NodeRefInner::Present(node, retained_edges) => node
+                .get_successor(edge)
+                .or_else(|| find_successor(retained_edges, edge)),
+            NodeRefInner::Removed(node) => node.get_successor(edge),
+        }
+    }
+    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
+        let successors: Box<dyn Iterator<Item = _>> = match &self.0 {
+            NodeRefInner::Present(node, retained_edges) => Box::new(
+                node.successors().chain(
+                    retained_edges
+                        .iter()
+                        .map(|(edge, successor)| (edge, successor.clone())),
+                ),
+            ),
+            NodeRefInner::Removed(node) => Box::new(
+                node.successors
+                    .iter()
+                    .map(|(edge, successor)| (edge, successor.clone())),
+            ),
+        };
+        successors
+    }
+    fn node_type_class(&self) -> NodeTypeClass {
+        match &self.0 {
+            NodeRefInner::Present(node, _) => node.node_type_class(),
+            NodeRefInner::Removed(node) => node.node_type_class,
+        }
+    }
+    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
+        match &self.0 {
+            NodeRefInner::Present(node, _) => node
+                .node_type_id()
+                .map(|id| Cow::Owned(id.type_name().to_owned())),
+            NodeRefInner::Removed(node) => node.type_name.as_deref().map(Cow::Borrowed),
+        }
+    }
+    fn custom_type_class(&self) -> Option<&str> {
+        match &self.0 {
+            NodeRefInner::Present(node, _) => node.custom_type_class(),
+            NodeRefInner::Removed(node) => node.custom_type_class.as_deref(),
+        }
+    }
+    fn value(&self) -> Option<NodeValue> {
+        match &self.0 {
+            NodeRefInner::Present(node, _) => node.value(),
+            NodeRefInner::Removed(node) => node.value.clone(),
+        }
+    }
+    fn source_location(&self) -> Option<SourceLocation> {
+        match &self.0 {
+            NodeRefInner::Present(node, _) => node.source_location(),
+            NodeRefInner::Removed(node) => node.source_location.clone(),
+        }
+    }
+    fn is_shared(&self) -> bool {
+        match &self.0 {
+            NodeRefInner::Present(node, _) => node.is_shared(),
+            NodeRefInner::Removed(node) => node.is_shared,
+        }
+    }
+}
+
+/// Copy of a state node that outlives the graph it was taken from.
+#[derive(PartialEq, Debug)]
+pub struct NodeSnapshot<T: NodeId> {
+    successors: Vec<(EdgeLabel, T)>,
+    node_type_class: NodeTypeClass,
+    type_name: Option<String>,
+    custom_type_class: Option<String>,
+    value: Option<NodeValue>,
+    source_location: Option<SourceLocation>,
+    is_shared: bool,
+}
+
+impl<T: NodeId> NodeSnapshot<T> {
+    pub(crate) fn new(node: &impl ProgramStateNode<NodeId = T>) -> Self {
+        Self {
+            successors: node
+                .successors()
+                .map(|(edge, successor)| (edge.clone(), successor))
+                .collect(),
+            node_type_class: node.node_type_class(),
+            type_name: node.node_type_id().map(|id| id.type_name().to_owned()),
+            custom_type_class: node.custom_type_class().map(str::to_owned),
+            value: node.value(),
+            source_location: node.source_location(),
+            is_shared: node.is_shared(),
+        }
+    }
+
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<T> {
+        find_successor(&self.successors, edge)
+    }
+}
+
+/// Finds the node that an edge leads to in a list of edges.
+fn find_successor<T: NodeId>(edges: &[(EdgeLabel, T)], edge: &EdgeLabel) -> Option<T> {
+    edges
+        .iter()
+        .find(|(label, _)| label == edge)
+        .map(|(_, successor)| successor.clone())
+}
+
+/// Takes snapshots of all nodes that are reachable from the root of a graph.
+pub(crate) fn snapshot_graph<G: RootedProgramStateGraph>(
+    graph: &G,
+) -> HashMap<G::NodeId, NodeSnapshot<G::NodeId>> {
+    let mut snapshot = HashMap::new();
+    let mut pending = vec![graph.root()];
+    while let Some(id) = pending.pop() {
+        if snapshot.contains_key(&id) {
+            continue;
+        }
+        let Some(node) = graph.get(&id) else {
+            continue;
+        };
+        let node = NodeSnapshot::new(&node);
+        pending.extend(
+            node.successors
+                .iter()
+                .map(|(_, successor)| successor.clone()),
+        );
+        snapshot.insert(id, node);
+    }
+    snapshot
+}
+
+impl<T: NodeId> ProgramStateNode for &NodeSnapshot<T> {
+    type NodeId = T;
+    type NodeTypeId<'a>
+        = &'a str
+    where
+        Self: 'a;
+    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
+        find_successor(&self.successors, edge)
+    }
+    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
+        self.successors
+            .iter()
+            .map(|(edge, successor)| (edge, successor.clone()))
+    }
+    fn node_type_class(&self) -> NodeTypeClass {
+        self.node_type_class
+    }
+    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
+        self.type_name.as_deref()
+    }
+    fn custom_type_class(&self) -> Option<&str> {
+        self.custom_type_class.as_deref()
+    }
+    fn value(&self) -> Option<NodeValue> {
+        self.value.clone()
+    }
+    fn source_location(&self) -> Option<SourceLocation> {
+        self.source_location.clone()
+    }
+    fn is_shared(&self) -> bool {
+        self.is_shared
+    }
+}
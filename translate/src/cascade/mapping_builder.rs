@@ -1,6 +1,9 @@
 //! Helper for construction of [`EntityPropertyMapping`]s.
 
-use crate::property::{DisplayMode, EntityPropertyMapping, PropertyKey};
+use crate::property::{
+    DisplayMode, EntityPropertyMapping, PropertyKey,
+    typed::{AttributeType, AttributeTypeMismatch, attribute_type, fragment_attribute_type},
+};
 use aili_model::state::{NodeId, ProgramStateGraph, ProgramStateNode};
 use aili_style::{
     eval::{context::EvaluationContext, unwrap_node_value},
@@ -55,6 +58,10 @@ pub struct PropertyMappingBuilder<T: NodeId> {
 
     /// Stack that tracks the information necessary to assign auto-defaults.
     auto_stack: Vec<AutoAssignmentContext<T>>,
+
+    /// Indices of ordered extras and the precedences
+    /// of the rules that assigned them.
+    extra_indices: HashMap<Selectable<T>, (usize, usize)>,
 }
 
 impl<T: NodeId> PropertyMappingBuilder<T> {
@@ -63,6 +70,7 @@ impl<T: NodeId> PropertyMappingBuilder<T> {
         Self {
             properties: HashMap::new(),
             auto_stack: vec![AutoAssignmentContext::default()],
+            extra_indices: HashMap::new(),
         }
     }
 
@@ -80,33 +88,85 @@ impl<T: NodeId> PropertyMappingBuilder<T> {
     }
 
     /// Finalizes the property mapping.
-    pub fn build(mut self, graph: &impl ProgramStateGraph<NodeId = T>) -> EntityPropertyMapping<T> {
+    ///
+    /// Values of well-known attributes are normalized.
+    /// Values that do not have the type the attribute requires
+    /// are left out and reported to the warning handler.
+    pub fn build(
+        mut self,
+        graph: &impl ProgramStateGraph<NodeId = T>,
+        mut warning_handler: impl FnMut(AttributeTypeMismatch<T>),
+    ) -> EntityPropertyMapping<T> {
         let mut mapping = EntityPropertyMapping::new();
+        let mut reversed = Vec::new();
         for (EntityPropertyKey(entity, property), RulePropertyValue { value, .. }) in
             std::mem::take(&mut self.properties)
         {
-            // Insert the property map lazily
-            let entity_properties = || mapping.0.entry(entity).or_default();
+            // Values that could not be calculated erase the property, same as unset
+            let value = match value {
+                PropertyValue::Error(_) => PropertyValue::Unset,
+                value => value,
+            };
             match property {
                 PropertyKey::Attribute(name) => {
                     let value = Self::to_true_value(value, graph);
                     // If value if Unset, the attribute should not be saved at all
-                    if value != PropertyValue::Unset {
-                        entity_properties()
-                            .attributes
-                            .insert(name, value.to_string());
+                    if value == PropertyValue::Unset {
+                        continue;
                     }
+                    let normalized = match attribute_type(&name) {
+                        None => value.to_string(),
+                        Some(expected) => match expected.normalize(&value) {
+                            Some(normalized) => normalized,
+                            None => {
+                                warning_handler(AttributeTypeMismatch {
+                                    entity,
+                                    fragment: None,
+                                    attribute: name.into(),
+                                    expected,
+                                    value: value.to_string(),
+                                });
+                                continue;
+                            }
+                        },
+                    };
+                    mapping
+                        .0
+                        .entry(entity)
+                        .or_default()
+                        .attributes
+                        .insert(name, normalized);
                 }
                 PropertyKey::FragmentAttribute(fragment, name) => {
                     let value = Self::to_true_value(value, graph);
                     // If value is Unset, the attribute should not be saved at all
-                    if value != PropertyValue::Unset {
-                        entity_properties()
-                            .fragment_attributes
-                            .entry(fragment)
-                            .or_default()
-                            .insert(name, value.to_string());
+                    if value == PropertyValue::Unset {
+                        continue;
                     }
+                    let normalized = match fragment_attribute_type(&name) {
+                        None => value.to_string(),
+                        Some(expected) => match expected.normalize(&value) {
+                            Some(normalized) => normalized,
+                            None => {
+                                warning_handler(AttributeTypeMismatch {
+                                    entity,
+                                    fragment: Some(fragment),
+                                    attribute: name.into(),
+                                    expected,
+                                    value: value.to_string(),
+                                });
+                                continue;
+                            }
+                        },
+                    };
+                    mapping
+                        .0
+                        .entry(entity)
+                        .or_default()
+                        .fragment_attributes
+                        .entry(fragment)
+                        .or_default()
+                        .insert(name, normalized);
                 }
                 PropertyKey::Display => {
                     let display_mode = match &value {
@@ -127,20 +187,73 @@ impl<T: NodeId> PropertyMappingBuilder<T> {
                         _ => Some(DisplayMode::from_name(value.to_string())),
                     };
                     if display_mode.is_some() {
-                        entity_properties().display = display_mode;
+                        mapping.0.entry(entity).or_default().display = display_mode;
                     }
                 }
                 PropertyKey::Parent => {
                     if let PropertyValue::Selection(sel) = value {
-                        entity_properties().parent = Some(*sel);
+                        mapping.0.entry(entity).or_default().parent = Some(*sel);
                     }
                 }
                 PropertyKey::Target => {
                     if let PropertyValue::Selection(sel) = value {
-                        entity_properties().target = Some(*sel);
+                        mapping.0.entry(entity).or_default().target = Some(*sel);
+                    }
+                }
+                PropertyKey::StableKey => {
+                    let value = Self::to_true_value(value, graph);
+                    if value != PropertyValue::Unset {
+                        mapping.0.entry(entity).or_default().stable_key = Some(value.to_string());
                     }
                 }
-                PropertyKey::Detach => {}
+                PropertyKey::Detach => {
+                    if Self::to_true_value(value, graph).is_truthy() {
+                        mapping.0.entry(entity).or_default().detached = true;
+                    }
+                }
+                PropertyKey::Layer => {
+                    let value = Self::to_true_value(value, graph);
+                    if value == PropertyValue::Unset {
+                        continue;
+                    }
+                    let layer = AttributeType::Integer
+                        .normalize(&value)
+                        .and_then(|layer| layer.parse().ok());
+                    match layer {
+                        Some(layer) => mapping.0.entry(entity).or_default().layer = Some(layer),
+                        None => warning_handler(AttributeTypeMismatch {
+                            entity,
+                            fragment: None,
+                            attribute: "layer".to_owned(),
+                            expected: AttributeType::Integer,
+                            value: value.to_string(),
+                        }),
+                    }
+                }
+                PropertyKey::Reverse => {
+                    if Self::to_true_value(value, graph).is_truthy() {
+                        reversed.push(entity);
+                    }
+                }
+            }
+        }
+        // Connectors are reversed once both of their ends are known
+        for entity in reversed {
+            if let Some(properties) = mapping.0.get_mut(&entity)
+                && properties.display == Some(DisplayMode::Connector)
+            {
+                std::mem::swap(&mut properties.parent, &mut properties.target);
+            }
+        }
+        for (entity, (_, extra_index)) in self.extra_indices {
+            if let Some(properties) = mapping.0.get_mut(&entity) {
+                properties.extra_index = Some(extra_index);
+                // Containers that order their children
+                // place ordered extras by their indices by default
+                properties
+                    .attributes
+                    .entry("order".into())
+                    .or_insert_with(|| extra_index.to_string());
             }
         }
         mapping
@@ -183,6 +296,29 @@ impl<T: NodeId> PropertyMappingBuilder<T> {
         }
     }
 
+    /// Notifies the builder that an extra entity has been selected
+    /// by a rule that places it at an index among the extras of its owner.
+    ///
+    /// If multiple rules assign an index, the one
+    /// with the greatest precedence is used.
+    pub fn order_extra(
+        &mut self,
+        target: &Selectable<T>,
+        extra_index: usize,
+        static_precedence: usize,
+    ) {
+        match self.extra_indices.entry(target.clone()) {
+            Entry::Occupied(mut existing) => {
+                if static_precedence >= existing.get().0 {
+                    existing.insert((static_precedence, extra_index));
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((static_precedence, extra_index));
+            }
+        }
+    }
+
     /// Assigns a value to a property key of a given entity.
     pub fn assign(
         &mut self,
@@ -2,6 +2,21 @@
 //! of [state graphs](aili_model::state).
 
 mod apply;
+mod budget;
+mod lifetime;
 mod mapping_builder;
+mod stats;
+mod traversal;
 
-pub use apply::apply_stylesheet;
+pub(crate) use apply::apply_stylesheet_to_lifetimes;
+pub use apply::{
+    apply_stylesheet, apply_stylesheet_tracked, apply_stylesheet_tracked_with_warnings,
+    apply_stylesheet_with_budget, apply_stylesheet_with_context, apply_stylesheet_with_mode,
+    apply_stylesheet_with_stats, apply_stylesheet_with_warnings, reapply_stylesheet_tracked,
+    reapply_stylesheet_tracked_with_warnings,
+};
+pub use budget::{PartialMapping, TraversalBudget};
+pub(crate) use lifetime::snapshot_graph;
+pub use lifetime::{LifetimeGraph, LifetimeNodeRef, LifetimeTracker, NodeSnapshot};
+pub use stats::{CascadeStats, RuleProfile};
+pub use traversal::TraversalMode;
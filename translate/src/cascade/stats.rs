@@ -0,0 +1,146 @@
+//! Performance counters of stylesheet applications.
+
+use aili_style::{
+    cascade::{CascadeStyle, RuleLabel},
+    stylesheet::PropertyKey,
+};
+use std::{ops::AddAssign, time::Duration};
+
+/// Counters that describe how much work
+/// an application of a stylesheet has done.
+///
+/// Collected by [`apply_stylesheet_with_stats`](super::apply_stylesheet_with_stats).
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct CascadeStats {
+    /// Number of times a node has been reached by the traversal.
+    ///
+    /// Nodes that are reachable along multiple paths
+    /// are counted once for each path they are reached by.
+    pub nodes_visited: usize,
+
+    /// Number of edges that have been traversed.
+    pub edges_traversed: usize,
+
+    /// Number of times the properties of a rule
+    /// have been evaluated for an entity it selected.
+    pub rules_evaluated: usize,
+
+    /// Number of selector conditions that have been evaluated.
+    ///
+    /// Conditions whose outcomes have been reused from an earlier
+    /// evaluation at the same node are not counted.
+    pub conditions_evaluated: usize,
+
+    /// Time spent evaluating the properties of each rule,
+    /// indexed by the rule's position in the stylesheet.
+    pub rule_times: Vec<Duration>,
+
+    /// Number of steps the selector of each rule has taken
+    /// through its state machine, indexed by the rule's position
+    /// in the stylesheet.
+    ///
+    /// This is the work it takes to find out whether
+    /// the selector matches, whether it does or not.
+    pub rule_match_attempts: Vec<usize>,
+
+    /// Number of times the selector of each rule has matched,
+    /// indexed by the rule's position in the stylesheet.
+    pub rule_matches: Vec<usize>,
+}
+
+/// Counters of one rule, as reported by [`CascadeStats::hot_rules`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RuleProfile<'a> {
+    /// Reference to the rule.
+    pub label: RuleLabel<'a>,
+
+    /// Number of steps the selector of the rule has taken.
+    ///
+    /// See [`CascadeStats::rule_match_attempts`].
+    pub match_attempts: usize,
+
+    /// Number of times the selector of the rule has matched.
+    pub matches: usize,
+
+    /// Time spent evaluating the properties of the rule.
+    pub time: Duration,
+}
+
+impl CascadeStats {
+    /// Constructs counters with nothing counted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the total time spent evaluating the properties of all rules.
+    pub fn total_rule_time(&self) -> Duration {
+        self.rule_times.iter().sum()
+    }
+
+    /// Pairs the time spent evaluating each rule with a reference
+    /// to the rule, so that rules with names are reported by them.
+    pub fn labeled_rule_times<'s, K: PropertyKey>(
+        &self,
+        stylesheet: &'s CascadeStyle<K>,
+    ) -> impl Iterator<Item = (RuleLabel<'s>, Duration)> {
+        self.rule_times
+            .iter()
+            .take(stylesheet.rule_count())
+            .enumerate()
+            .map(|(rule_index, time)| (stylesheet.rule_label(rule_index), *time))
+    }
+
+    /// Finds the rules whose selectors have taken the most steps,
+    /// at most `limit` of them, most expensive first.
+    ///
+    /// Rules that have taken the same number of steps
+    /// are ordered by the time spent evaluating their properties.
+    pub fn hot_rules<'s, K: PropertyKey>(
+        &self,
+        stylesheet: &'s CascadeStyle<K>,
+        limit: usize,
+    ) -> Vec<RuleProfile<'s>> {
+        let mut profiles = (0..stylesheet.rule_count())
+            .map(|rule_index| RuleProfile {
+                label: stylesheet.rule_label(rule_index),
+                match_attempts: rule_counter(&self.rule_match_attempts, rule_index),
+                matches: rule_counter(&self.rule_matches, rule_index),
+                time: rule_counter(&self.rule_times, rule_index),
+            })
+            .collect::<Vec<_>>();
+        profiles.sort_by(|a, b| {
+            b.match_attempts
+                .cmp(&a.match_attempts)
+                .then(b.time.cmp(&a.time))
+        });
+        profiles.truncate(limit);
+        profiles
+    }
+
+    /// Adds the counters of another application to these counters.
+    pub fn merge(&mut self, other: &CascadeStats) {
+        self.nodes_visited += other.nodes_visited;
+        self.edges_traversed += other.edges_traversed;
+        self.rules_evaluated += other.rules_evaluated;
+        self.conditions_evaluated += other.conditions_evaluated;
+        merge_rule_counters(&mut self.rule_times, &other.rule_times);
+        merge_rule_counters(&mut self.rule_match_attempts, &other.rule_match_attempts);
+        merge_rule_counters(&mut self.rule_matches, &other.rule_matches);
+    }
+}
+
+/// Gets a counter indexed by rule index,
+/// or zero if the rule has not been counted.
+fn rule_counter<T: Copy + Default>(counters: &[T], rule_index: usize) -> T {
+    counters.get(rule_index).copied().unwrap_or_default()
+}
+
+/// Adds counters indexed by rule index to other such counters.
+fn merge_rule_counters<T: AddAssign + Copy + Default>(counters: &mut Vec<T>, other: &[T]) {
+    if counters.len() < other.len() {
+        counters.resize(other.len(), T::default());
+    }
+    for (counter, other_counter) in counters.iter_mut().zip(other) {
+        *counter += *other_counter;
+    }
+}
@@ -0,0 +1,27 @@
+//! Strategies of traversing graphs during the cascade.
+
+/// Decides how many times the cascade may enter a node.
+///
+/// See [`apply_stylesheet_with_mode`](super::apply_stylesheet_with_mode).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TraversalMode {
+    /// A node is entered along every path that some selector
+    /// may still match, until the selectors stop matching.
+    ///
+    /// Each selector may only scan a node once, but selectors that
+    /// read variables can have different outcomes along different paths.
+    #[default]
+    AllPaths,
+
+    /// A node is entered at most once for each combination
+    /// of selector states that are waiting to match it.
+    ///
+    /// When a node is reached again while the selectors are in the same
+    /// states as before, it is skipped, along with everything behind it,
+    /// even if the values of variables are different.
+    ///
+    /// This bounds the traversal by the size of the graph, so cyclic graphs,
+    /// such as doubly-linked lists, are traversed in linear time
+    /// regardless of how the stylesheet is written.
+    VisitOnce,
+}
@@ -0,0 +1,563 @@
+//! Static snapshots of visualizations.
+//!
+//! A snapshot renders the current state of a visualization tree
+//! as a standalone SVG image or HTML page, so that stills of a program
+//! state can be saved without the frontend. [`VisTree`](aili_model::vis::VisTree)s
+//! cannot be read back, so snapshots are built from the [`Mutation`]s
+//! that construct the tree, such as those produced by
+//! [`MutationRecorder::snapshot`](crate::mutation::MutationRecorder::snapshot).
+//!
+//! Snapshots do not use the layout engines of the frontend.
+//! Elements are drawn as nested boxes, whose children are stacked
+//! in a column, or in a row if the element's `direction` is `row`,
+//! and connectors are drawn as straight lines between the centers
+//! of the elements they connect. Layers are not taken into account.
+//!
+//! ```
+//! use aili_model::vis::{AttributeMap, VisTree};
+//! use aili_translate::{export::export_svg, mutation::MutationRecorder};
+//!
+//! let mut tree = MutationRecorder::new();
+//! let root = tree.add_element("cell");
+//! tree.get_element(&root).unwrap().set_attribute("value", Some("42"));
+//! tree.set_root(Some(&root)).unwrap();
+//! let svg = export_svg(&tree.snapshot());
+//! assert!(svg.starts_with("<svg"));
+//! assert!(svg.contains(">42</text>"));
+//! ```
+
+use crate::mutation::{Mutation, PinSide};
+use std::{collections::HashMap, fmt::Write};
+
+/// Space between the border of an element and its contents.
+const PADDING: f64 = 8.0;
+
+/// Space between adjacent children of an element.
+const GAP: f64 = 8.0;
+
+/// Height of a line of text.
+const LINE_HEIGHT: f64 = 16.0;
+
+/// Estimated width of a character of text.
+const CHAR_WIDTH: f64 = 8.0;
+
+/// Smallest width and height of an element.
+const MIN_SIZE: f64 = 24.0;
+
+/// Renders a visualization tree as a standalone SVG image.
+///
+/// The tree is constructed by applying the mutations in order.
+/// Mutations that refer to entities that do not exist are ignored.
+pub fn export_svg(mutations: &[Mutation]) -> String {
+    let scene = Scene::from_mutations(mutations);
+    let layout = Layout::of(&scene);
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        layout.width, layout.height
+    )
+    .unwrap();
+    svg.push_str(concat!(
+        "<defs>",
+        r#"<marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8" orient="auto-start-reverse">"#,
+        r#"<path d="M 0 0 L 10 5 L 0 10 z"/>"#,
+        "</marker>",
+        "</defs>\n",
+    ));
+    for &root in &scene.roots {
+        write_element(&mut svg, &scene, &layout, root);
+    }
+    for (id, connector) in scene.connectors.iter().enumerate() {
+        if let Some(line) = layout.connectors.get(&id) {
+            write_connector(&mut svg, connector, line);
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Renders a visualization tree as a standalone HTML page
+/// that contains the image produced by [`export_svg`].
+pub fn export_html(mutations: &[Mutation]) -> String {
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n",
+            "<html>\n",
+            "<head>\n",
+            "<meta charset=\"utf-8\">\n",
+            "<title>Aili Snapshot</title>\n",
+            "</head>\n",
+            "<body>\n",
+            "{}",
+            "</body>\n",
+            "</html>\n",
+        ),
+        export_svg(mutations)
+    )
+}
+
+/// Visualization tree reconstructed from mutations.
+#[derive(Default)]
+struct Scene {
+    elements: Vec<SceneElement>,
+    connectors: Vec<SceneConnector>,
+    roots: Vec<usize>,
+}
+
+#[derive(Default)]
+struct SceneElement {
+    tag_name: String,
+    attributes: HashMap<String, String>,
+    children: Vec<usize>,
+    parent: Option<usize>,
+}
+
+#[derive(Default)]
+struct SceneConnector {
+    attributes: HashMap<String, String>,
+    start: ScenePin,
+    end: ScenePin,
+}
+
+#[derive(Default)]
+struct ScenePin {
+    attributes: HashMap<String, String>,
+    target: Option<PinTarget>,
+}
+
+/// Entity that a connector pin is attached to.
+#[derive(Clone, Copy)]
+enum PinTarget {
+    Element(usize),
+    Connector(usize),
+}
+
+impl Scene {
+    fn from_mutations(mutations: &[Mutation]) -> Self {
+        let mut scene = Self::default();
+        // Removed entities keep their identifiers, so they are
+        // kept in place and skipped when the scene is drawn
+        let mut removed_elements = Vec::new();
+        let mut removed_connectors = Vec::new();
+        for mutation in mutations {
+            match mutation {
+                Mutation::CreateElement { tag_name, .. } => {
+                    scene.elements.push(SceneElement {
+                        tag_name: tag_name.clone(),
+                        ..SceneElement::default()
+                    });
+                    removed_elements.push(false);
+                }
+                Mutation::CreateConnector { .. } => {
+                    scene.connectors.push(SceneConnector::default());
+                    removed_connectors.push(false);
+                }
+                Mutation::SetRoot { element } => scene.roots = element.iter().copied().collect(),
+                Mutation::SetRoots { elements } => scene.roots = elements.clone(),
+                Mutation::SetParent { element, parent } => scene.set_parent(*element, *parent),
+                Mutation::SetLayer { .. } => {}
+                Mutation::SetElementAttribute {
+                    element,
+                    name,
+                    value,
+                } => {
+                    if let Some(element) = scene.elements.get_mut(*element) {
+                        set_attribute(&mut element.attributes, name, value);
+                    }
+                }
+                Mutation::SetConnectorAttribute {
+                    connector,
+                    name,
+                    value,
+                } => {
+                    if let Some(connector) = scene.connectors.get_mut(*connector) {
+                        set_attribute(&mut connector.attributes, name, value);
+                    }
+                }
+                Mutation::SetPinAttribute {
+                    connector,
+                    pin,
+                    name,
+                    value,
+                } => {
+                    if let Some(pin) = scene.pin_mut(*connector, *pin) {
+                        set_attribute(&mut pin.attributes, name, value);
+                    }
+                }
+                Mutation::SetPinTarget {
+                    connector,
+                    pin,
+                    target,
+                } => {
+                    if let Some(pin) = scene.pin_mut(*connector, *pin) {
+                        pin.target = target.map(PinTarget::Element);
+                    }
+                }
+                Mutation::SetPinTargetConnector {
+                    connector,
+                    pin,
+                    target_connector,
+                } => {
+                    if let Some(pin) = scene.pin_mut(*connector, *pin) {
+                        pin.target = Some(PinTarget::Connector(*target_connector));
+                    }
+                }
+                Mutation::RemoveElement { element } => {
+                    if let Some(removed) = removed_elements.get_mut(*element) {
+                        *removed = true;
+                    }
+                    scene.roots.retain(|root| root != element);
+                }
+                Mutation::RemoveConnector { connector } => {
+                    if let Some(removed) = removed_connectors.get_mut(*connector) {
+                        *removed = true;
+                    }
+                }
+            }
+        }
+        // Detach everything from removed entities
+        for (id, _) in removed_elements.iter().enumerate().filter(|(_, r)| **r) {
+            scene.set_parent(id, None);
+            for child in std::mem::take(&mut scene.elements[id].children) {
+                scene.elements[child].parent = None;
+            }
+        }
+        for connector in &mut scene.connectors {
+            for pin in [&mut connector.start, &mut connector.end] {
+                let is_removed = match pin.target {
+                    Some(PinTarget::Element(id)) => removed_elements.get(id) != Some(&false),
+                    Some(PinTarget::Connector(id)) => removed_connectors.get(id) != Some(&false),
+                    None => false,
+                };
+                if is_removed {
+                    pin.target = None;
+                }
+            }
+        }
+        scene
+            .roots
+            .retain(|root| removed_elements.get(*root) == Some(&false));
+        scene
+    }
+
+    fn set_parent(&mut self, element: usize, parent: Option<usize>) {
+        if element >= self.elements.len() || parent.is_some_and(|p| p >= self.elements.len()) {
+            return;
+        }
+        // Assignments that would create a cycle are ignored,
+        // like a tree would reject them
+        if parent.is_some_and(|parent| self.is_ancestor_of(element, parent)) {
+            return;
+        }
+        if let Some(old_parent) = self.elements[element].parent.take() {
+            self.elements[old_parent]
+                .children
+                .retain(|child| *child != element);
+        }
+        if let Some(parent) = parent {
+            self.elements[parent].children.push(element);
+            self.elements[element].parent = Some(parent);
+        }
+    }
+
+    fn is_ancestor_of(&self, ancestor: usize, mut descendant: usize) -> bool {
+        loop {
+            if descendant == ancestor {
+                break true;
+            }
+            match self.elements[descendant].parent {
+                Some(parent) => descendant = parent,
+                None => break false,
+            }
+        }
+    }
+
+    fn pin_mut(&mut self, connector: usize, side: PinSide) -> Option<&mut ScenePin> {
+        let connector = self.connectors.get_mut(connector)?;
+        Some(match side {
+            PinSide::Start => &mut connector.start,
+            PinSide::End => &mut connector.end,
+        })
+    }
+}
+
+fn set_attribute(attributes: &mut HashMap<String, String>, name: &str, value: &Option<String>) {
+    match value {
+        Some(value) => attributes.insert(name.to_owned(), value.clone()),
+        None => attributes.remove(name),
+    };
+}
+
+/// Coordinates of a point in the image.
+type Point = (f64, f64);
+
+/// Rectangle occupied by an element.
+#[derive(Clone, Copy, Default)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Rect {
+    fn center(&self) -> Point {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+}
+
+/// Positions of all entities of a [`Scene`].
+struct Layout {
+    width: f64,
+    height: f64,
+    elements: HashMap<usize, Rect>,
+    connectors: HashMap<usize, (Point, Point)>,
+}
+
+impl Layout {
+    fn of(scene: &Scene) -> Self {
+        let mut layout = Self {
+            width: GAP,
+            height: GAP,
+            elements: HashMap::new(),
+            connectors: HashMap::new(),
+        };
+        // Roots are placed side by side
+        for &root in &scene.roots {
+            let (width, height) = measure(scene, root);
+            layout.place(scene, root, layout.width, GAP);
+            layout.width += width + GAP;
+            layout.height = layout.height.max(height + 2.0 * GAP);
+        }
+        let mut visiting = vec![false; scene.connectors.len()];
+        for id in 0..scene.connectors.len() {
+            layout.place_connector(scene, id, &mut visiting);
+        }
+        layout
+    }
+
+    fn place(&mut self, scene: &Scene, id: usize, x: f64, y: f64) {
+        let (width, height) = measure(scene, id);
+        self.elements.insert(
+            id,
+            Rect {
+                x,
+                y,
+                width,
+                height,
+            },
+        );
+        let element = &scene.elements[id];
+        let mut offset = 0.0;
+        let top = if text_of(element).is_some() {
+            PADDING + LINE_HEIGHT + GAP
+        } else {
+            PADDING
+        };
+        for &child in &element.children {
+            let (child_width, child_height) = measure(scene, child);
+            if is_row(element) {
+                self.place(scene, child, x + PADDING + offset, y + top);
+                offset += child_width + GAP;
+            } else {
+                self.place(scene, child, x + PADDING, y + top + offset);
+                offset += child_height + GAP;
+            }
+        }
+    }
+
+    /// Finds the endpoints of a connector, if both of its pins
+    /// are attached to something that has been placed.
+    fn place_connector(
+        &mut self,
+        scene: &Scene,
+        id: usize,
+        visiting: &mut [bool],
+    ) -> Option<Point> {
+        if let Some((start, end)) = self.connectors.get(&id) {
+            return Some(midpoint(*start, *end));
+        }
+        // Connectors attached to each other in a cycle cannot be placed
+        if std::mem::replace(&mut visiting[id], true) {
+            return None;
+        }
+        let connector = &scene.connectors[id];
+        let start = self.pin_position(scene, &connector.start, visiting)?;
+        let end = self.pin_position(scene, &connector.end, visiting)?;
+        self.connectors.insert(id, (start, end));
+        Some(midpoint(start, end))
+    }
+
+    fn pin_position(
+        &mut self,
+        scene: &Scene,
+        pin: &ScenePin,
+        visiting: &mut [bool],
+    ) -> Option<Point> {
+        match pin.target? {
+            PinTarget::Element(id) => self.elements.get(&id).map(Rect::center),
+            PinTarget::Connector(id) => self.place_connector(scene, id, visiting),
+        }
+    }
+}
+
+/// Computes the size of an element and all its descendants.
+fn measure(scene: &Scene, id: usize) -> (f64, f64) {
+    let element = &scene.elements[id];
+    let (text_width, text_height) = match text_of(element) {
+        Some(text) => (text.chars().count() as f64 * CHAR_WIDTH, LINE_HEIGHT),
+        None => (0.0, 0.0),
+    };
+    let child_sizes = element
+        .children
+        .iter()
+        .map(|&child| measure(scene, child))
+        .collect::<Vec<_>>();
+    let gaps = child_sizes.len().saturating_sub(1) as f64 * GAP;
+    let (children_width, children_height) = if is_row(element) {
+        (
+            child_sizes.iter().map(|(w, _)| w).sum::<f64>() + gaps,
+            child_sizes.iter().map(|(_, h)| *h).fold(0.0, f64::max),
+        )
+    } else {
+        (
+            child_sizes.iter().map(|(w, _)| *w).fold(0.0, f64::max),
+            child_sizes.iter().map(|(_, h)| h).sum::<f64>() + gaps,
+        )
+    };
+    let separator = if text_height > 0.0 && !child_sizes.is_empty() {
+        GAP
+    } else {
+        0.0
+    };
+    (
+        (text_width.max(children_width) + 2.0 * PADDING).max(MIN_SIZE),
+        (text_height + separator + children_height + 2.0 * PADDING).max(MIN_SIZE),
+    )
+}
+
+/// Gets the text that is displayed in an element.
+fn text_of(element: &SceneElement) -> Option<&str> {
+    element
+        .attributes
+        .get("value")
+        .map(String::as_str)
+        .filter(|text| !text.is_empty())
+}
+
+/// Decides whether children of an element are laid out in a row.
+fn is_row(element: &SceneElement) -> bool {
+    match element.attributes.get("direction").map(String::as_str) {
+        Some(direction) => matches!(direction, "row" | "east" | "west"),
+        None => matches!(element.tag_name.as_str(), "row" | "graph"),
+    }
+}
+
+fn midpoint(start: Point, end: Point) -> Point {
+    ((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0)
+}
+
+/// Writes an element and all its descendants as a group.
+fn write_element(svg: &mut String, scene: &Scene, layout: &Layout, id: usize) {
+    let element = &scene.elements[id];
+    let Some(rect) = layout.elements.get(&id) else {
+        return;
+    };
+    let attribute = |name: &str, default: &'static str| {
+        element
+            .attributes
+            .get(name)
+            .map_or(default.to_owned(), |value| escape(value))
+    };
+    writeln!(
+        svg,
+        r#"<g class="{}" data-id="{id}">"#,
+        escape(&element.tag_name)
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="{}" stroke-width="{}"{}/>"#,
+        rect.x,
+        rect.y,
+        rect.width,
+        rect.height,
+        attribute("fill", "white"),
+        attribute("stroke", "black"),
+        attribute("stroke-width", "1"),
+        dash_array(&element.attributes),
+    )
+    .unwrap();
+    if let Some(text) = text_of(element) {
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" fill="{}" font-family="monospace" font-size="14">{}</text>"#,
+            rect.x + PADDING,
+            rect.y + PADDING + LINE_HEIGHT * 0.8,
+            attribute("color", "black"),
+            escape(text),
+        )
+        .unwrap();
+    }
+    for &child in &element.children {
+        write_element(svg, scene, layout, child);
+    }
+    svg.push_str("</g>\n");
+}
+
+/// Writes a connector as a path between its endpoints.
+fn write_connector(
+    svg: &mut String,
+    connector: &SceneConnector,
+    ((x1, y1), (x2, y2)): &(Point, Point),
+) {
+    let marker = |pin: &ScenePin, name: &str| {
+        if pin.attributes.get("decoration").map(String::as_str) == Some("arrow") {
+            format!(r#" {name}="url(#arrow)""#)
+        } else {
+            String::new()
+        }
+    };
+    writeln!(
+        svg,
+        r#"<path class="connector" d="M {x1} {y1} L {x2} {y2}" fill="none" stroke="{}" stroke-width="{}"{}{}{}/>"#,
+        connector
+            .attributes
+            .get("stroke")
+            .map_or("black".to_owned(), |value| escape(value)),
+        connector
+            .attributes
+            .get("stroke-width")
+            .map_or("1".to_owned(), |value| escape(value)),
+        dash_array(&connector.attributes),
+        marker(&connector.start, "marker-start"),
+        marker(&connector.end, "marker-end"),
+    )
+    .unwrap();
+}
+
+/// Translates the `stroke-style` attribute to an SVG attribute.
+fn dash_array(attributes: &HashMap<String, String>) -> &'static str {
+    match attributes.get("stroke-style").map(String::as_str) {
+        Some("dashed") => r#" stroke-dasharray="6 4""#,
+        Some("dotted") => r#" stroke-dasharray="2 3""#,
+        _ => "",
+    }
+}
+
+/// Escapes text so it can be used in XML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
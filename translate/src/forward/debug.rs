@@ -24,7 +24,7 @@ where
 {
     /// Formats the state of a writer in a way fit for inline debug printing.
     fn debug_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{{ root: {:?}, mapping: {{ ", self.current_root)?;
+        write!(f, "{{ roots: {:?}, mapping: {{ ", self.current_roots)?;
         for (key, rendering) in &self.current_mappping {
             write!(f, "[{key:?}]: {:?}, ", rendering.properties)?;
         }
@@ -62,6 +62,12 @@ where
                 if let Some(target) = &rendering.properties.target {
                     mapping_printouts.push(("target".to_owned(), format!("{target:?}")));
                 }
+                if rendering.properties.detached {
+                    mapping_printouts.push(("detach".to_owned(), "true".to_owned()));
+                }
+                if let Some(layer) = &rendering.properties.layer {
+                    mapping_printouts.push(("layer".to_owned(), layer.to_string()));
+                }
                 for (name, value) in &rendering.properties.attributes {
                     mapping_printouts.push((format!("{name:?}"), format!("{value:?}")));
                 }
@@ -77,11 +83,11 @@ where
         // Prepare the root property as well
         // This is a property of the renderer as a whole, rather than
         // a particular entity
-        let root_key = "root";
-        let root_value = if let Some(root) = &self.current_root {
-            format!("{root:?}")
-        } else {
+        let root_key = "roots";
+        let root_value = if self.current_roots.is_empty() {
             "[none]".to_owned()
+        } else {
+            format!("{:?}", self.current_roots)
         };
         // Widths of display columns are given by the longest
         let width_1 = property_maps
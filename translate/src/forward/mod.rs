@@ -2,22 +2,16 @@
 //! to the [visualization tree](aili_model::vis).
 
 mod debug;
+mod validate;
+mod warning;
 
 use crate::property::{DisplayMode, EntityPropertyMapping, FragmentKey, PropertyMap};
-use aili_model::{state::NodeId, vis::*};
+use aili_model::{name::Name, state::NodeId, vis::*};
 use aili_style::selectable::Selectable;
-use derive_more::Display;
 use std::collections::HashMap;
 
-/// Describes an occurrence in a [`VisTreeWriter`]
-/// that should not arise when using it as intended
-/// and is likely indicative of an error in the input.
-#[derive(Debug, Display)]
-pub enum VisTreeWriterWarning<T: NodeId> {
-    /// The resolved stylesheet has caused a cycle in the visualization tree.
-    #[display("detected loop in vis tree near {_0:?}")]
-    VisStructureViolation(Selectable<T>),
-}
+pub use validate::{MappingViolation, validate_mapping};
+pub use warning::{VisTreeWriterWarning, WarningPolicy, WarningSeverity};
 
 /// Updates the structure of a [`VisTree`] to reflect
 /// changes in stylesheet resolution.
@@ -25,9 +19,9 @@ pub struct VisTreeWriter<'w, T: NodeId, V: VisTree> {
     /// The target visualization tree.
     vis_tree: V,
 
-    /// Selectable entity whose associated visual element
-    /// is currently at the root of the visualization tree.
-    current_root: Option<Selectable<T>>,
+    /// Selectable entities whose associated visual elements
+    /// are currently the roots of the visualization tree.
+    current_roots: Vec<Selectable<T>>,
 
     /// Associated visual elements and current properties
     /// of all visualized entities.
@@ -35,6 +29,13 @@ pub struct VisTreeWriter<'w, T: NodeId, V: VisTree> {
 
     /// Handler that processes warnings emited by the writer.
     warning_handler: Option<Box<dyn FnMut(VisTreeWriterWarning<T>) + 'w>>,
+
+    /// Decides how violations in mappings are handled.
+    warning_policy: WarningPolicy,
+
+    /// Warnings raised during the current update
+    /// that have not been passed to the handler yet.
+    pending_warnings: Vec<VisTreeWriterWarning<T>>,
 }
 
 impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
@@ -42,9 +43,11 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
     pub fn new(vis_tree: V) -> Self {
         Self {
             vis_tree,
-            current_root: None,
+            current_roots: Vec::new(),
             current_mappping: HashMap::new(),
             warning_handler: None,
+            warning_policy: WarningPolicy::default(),
+            pending_warnings: Vec::new(),
         }
     }
 
@@ -65,15 +68,47 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
         self
     }
 
+    /// Sets how the writer handles mappings that violate
+    /// the structure of the visualization tree.
+    pub fn set_warning_policy(&mut self, warning_policy: WarningPolicy) {
+        self.warning_policy = warning_policy;
+    }
+
+    /// Sets how the writer handles mappings that violate
+    /// the structure of the visualization tree.
+    pub fn with_warning_policy(mut self, warning_policy: WarningPolicy) -> Self {
+        self.set_warning_policy(warning_policy);
+        self
+    }
+
+    /// Gets how the writer handles mappings that violate
+    /// the structure of the visualization tree.
+    pub fn warning_policy(&self) -> WarningPolicy {
+        self.warning_policy
+    }
+
     /// Consumes self and returns the [`VisTree`] that was passed
     /// to the constructor.
     pub fn reclaim_vis_tree(self) -> V {
         self.vis_tree
     }
 
-    /// Gets the current root element, if any.
-    pub fn get_root(&self) -> Option<&Selectable<T>> {
-        self.current_root.as_ref()
+    /// Gets the [`VisTree`] that the writer renders into.
+    pub fn vis_tree(&self) -> &V {
+        &self.vis_tree
+    }
+
+    /// Gets the [`VisTree`] that the writer renders into, mutably.
+    ///
+    /// Modifying the structure of the tree directly can cause
+    /// the writer to make incorrect assumptions about it.
+    pub fn vis_tree_mut(&mut self) -> &mut V {
+        &mut self.vis_tree
+    }
+
+    /// Gets the entities whose elements are the current roots.
+    pub fn get_roots(&self) -> &[Selectable<T>] {
+        &self.current_roots
     }
 
     /// Iterates through the current visualized entities and their properties.
@@ -83,23 +118,64 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
             .map(|(k, rendering)| (k, &rendering.properties))
     }
 
-    /// Changes the root element.
-    pub fn update_root(&mut self, new_root: Option<Selectable<T>>) {
-        // Do nothing if the root is up-to-date
-        if new_root == self.current_root {
+    /// Checks a property mapping for structure violations
+    /// that [`update`](VisTreeWriter::update) would run into,
+    /// without modifying the [`VisTree`].
+    ///
+    /// See [`validate_mapping`] for details.
+    pub fn validate(&self, mapping: &EntityPropertyMapping<T>) -> Vec<MappingViolation<T>> {
+        validate_mapping(mapping)
+    }
+
+    /// Changes the root elements.
+    ///
+    /// Entities that are not displayed as elements are skipped,
+    /// but they become roots as soon as they are.
+    pub fn update_roots(&mut self, new_roots: Vec<Selectable<T>>) {
+        // Do nothing if the roots are up-to-date
+        if new_roots == self.current_roots {
             return;
         }
-        self.current_root = new_root;
+        self.current_roots = new_roots;
         // Propagate the update to the vis tree
-        self.forward_update_root();
+        self.forward_update_roots();
     }
 
     /// Updates the properties of all visual elements.
+    ///
+    /// Entities whose display mode has not changed keep their
+    /// visual elements and connectors, only their attributes
+    /// and relations are updated. Attributes that have not been
+    /// set by the writer are left untouched.
+    ///
+    /// Entities with a [stable key](PropertyMap::stable_key) are matched
+    /// to the visual entity that had the same key, rather than
+    /// to the one that belonged to the same [`Selectable`].
+    ///
+    /// Violations in the mapping are handled according to the
+    /// [warning policy](VisTreeWriter::set_warning_policy).
+    /// Warnings are passed to the handler once the update is finished,
+    /// and warnings of the same kind are
+    /// [coalesced](VisTreeWriterWarning::Coalesced).
     pub fn update(&mut self, mut new_mapping: EntityPropertyMapping<T>) {
+        if !self.apply_warning_policy(&mut new_mapping) {
+            self.flush_warnings();
+            return;
+        }
         let mut updated_mapping = HashMap::new();
+        let mut keyed_renderings = self
+            .current_mappping
+            .iter()
+            .filter_map(|(key, rendering)| {
+                Some((rendering.properties.stable_key.clone()?, key.clone()))
+            })
+            .collect();
         // Create renderings for entities that are not yet rendered and update those that are
         for (key, new_properties) in new_mapping.0.drain() {
-            if let Some(new_entity_mapping) = self.update_or_create_rendering(&key, new_properties)
+            let old_mapping =
+                self.take_previous_rendering(&key, &new_properties, &mut keyed_renderings);
+            if let Some(new_entity_mapping) =
+                self.update_or_create_rendering(old_mapping, new_properties)
             {
                 updated_mapping.insert(key, new_entity_mapping);
             }
@@ -114,13 +190,51 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
         // Inter-entity relationships should only be updated now,
         // after all entity recreating is completed
         self.update_inter_entity_relations();
-        // Root element may have changed or its rendering may have been recreated
-        self.forward_update_root();
+        // Root elements may have changed or their renderings may have been recreated
+        self.forward_update_roots();
+        self.flush_warnings();
+    }
+
+    /// Validates a mapping if the warning policy requires it
+    /// and removes entities that should be skipped.
+    ///
+    /// Returns `false` if the update should be aborted.
+    fn apply_warning_policy(&mut self, mapping: &mut EntityPropertyMapping<T>) -> bool {
+        match self.warning_policy {
+            WarningPolicy::BestEffort => true,
+            WarningPolicy::SkipEntity => {
+                for violation in validate_mapping(mapping) {
+                    mapping.0.remove(violation.entity());
+                    self.pending_warnings
+                        .push(VisTreeWriterWarning::SkippedEntity(violation));
+                }
+                true
+            }
+            WarningPolicy::Abort => {
+                let violations = validate_mapping(mapping);
+                let is_valid = violations.is_empty();
+                self.pending_warnings.extend(
+                    violations
+                        .into_iter()
+                        .map(VisTreeWriterWarning::RejectedUpdate),
+                );
+                is_valid
+            }
+        }
+    }
+
+    /// Passes all pending warnings to the handler.
+    fn flush_warnings(&mut self) {
+        let warnings = warning::coalesce_warnings(self.pending_warnings.drain(..));
+        if let Some(warning_handler) = &mut self.warning_handler {
+            warnings.into_iter().for_each(warning_handler);
+        }
     }
 
     /// Updates the parent-child and pin-target relationships of all active visual entities.
     fn update_inter_entity_relations(&mut self) {
         let mut retry_element_insertions = Vec::new();
+        let mut target_cycles = Vec::new();
         for (selectable, mapping) in &self.current_mappping {
             match &mapping.vis_handle {
                 EitherVisHandle::Element(handle) => {
@@ -130,8 +244,7 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
                         .expect("The handle should remain valid");
                     let parent_handle = mapping
                         .properties
-                        .parent
-                        .as_ref()
+                        .effective_parent()
                         .and_then(|key| self.current_mappping.get(key))
                         .and_then(|mapping| mapping.vis_handle.element());
                     match element.insert_into(parent_handle) {
@@ -169,15 +282,34 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
                         .target
                         .as_ref()
                         .and_then(|key| self.current_mappping.get(key))
-                        .and_then(|mapping| mapping.vis_handle.element());
+                        .map(|mapping| &mapping.vis_handle);
                     connector
                         .start_mut()
                         .attach_to(start_handle)
                         .expect("The handle should remain valid");
-                    connector
-                        .end_mut()
-                        .attach_to(end_handle)
-                        .expect("The handle should remain valid");
+                    let mut end = connector.end_mut();
+                    match end_handle {
+                        Some(EitherVisHandle::Connector(end_handle)) => {
+                            let target_of = |key: &Selectable<T>| {
+                                let mapping = self.current_mappping.get(key)?;
+                                mapping.vis_handle.connector()?;
+                                mapping.properties.target.as_ref()
+                            };
+                            if validate::is_on_target_cycle(selectable, target_of) {
+                                // Connectors attached to each other in a loop
+                                // have no point to attach to, so we leave them loose
+                                end.attach_to(None).expect("Detachment should never fail");
+                                target_cycles.push(selectable);
+                            } else {
+                                end.attach_to_connector(Some(end_handle))
+                                    .expect("The handle should remain valid");
+                            }
+                        }
+                        Some(EitherVisHandle::Element(end_handle)) => end
+                            .attach_to(Some(end_handle))
+                            .expect("The handle should remain valid"),
+                        None => end.attach_to(None).expect("Detachment should never fail"),
+                    }
                 }
             }
         }
@@ -197,25 +329,61 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
                     panic!("The handle should remain valid")
                 }
                 Err(ParentAssignmentError::StructureViolation) => {
-                    if let Some(warning_handler) = &mut self.warning_handler {
-                        warning_handler(VisTreeWriterWarning::VisStructureViolation(
+                    self.pending_warnings
+                        .push(VisTreeWriterWarning::VisStructureViolation(
                             selectable.clone(),
                         ));
-                    }
                 }
             }
         }
+        self.pending_warnings.extend(
+            target_cycles
+                .into_iter()
+                .cloned()
+                .map(VisTreeWriterWarning::VisStructureViolation),
+        );
+    }
+
+    /// Removes the rendering that should be reused for an entity
+    /// from the current mapping, if there is one.
+    ///
+    /// Entities with a stable key take over the rendering that had
+    /// the same key. Other entities take over their own rendering,
+    /// unless it had a stable key.
+    fn take_previous_rendering(
+        &mut self,
+        key: &Selectable<T>,
+        new_properties: &PropertyMap<T>,
+        keyed_renderings: &mut HashMap<String, Selectable<T>>,
+    ) -> Option<EntityRendering<T, V>> {
+        match &new_properties.stable_key {
+            Some(stable_key) => {
+                let previous_owner = keyed_renderings.remove(stable_key)?;
+                self.current_mappping.remove(&previous_owner)
+            }
+            None => {
+                if self
+                    .current_mappping
+                    .get(key)?
+                    .properties
+                    .stable_key
+                    .is_some()
+                {
+                    return None;
+                }
+                self.current_mappping.remove(key)
+            }
+        }
     }
 
     /// Updates the existing rendering for an entity if possible,
     /// or creates a new one
     fn update_or_create_rendering(
         &mut self,
-        key: &Selectable<T>,
+        old_mapping: Option<EntityRendering<T, V>>,
         new_properties: PropertyMap<T>,
     ) -> Option<EntityRendering<T, V>> {
-        // Get the existing mapping for the entity and remove it from the container
-        if let Some(mut old_mapping) = self.current_mappping.remove(key) {
+        if let Some(mut old_mapping) = old_mapping {
             if old_mapping.properties.display == new_properties.display {
                 // The entity is already displayed and its display mode has not changed,
                 // so we update the existing rendering instead of creating a new one
@@ -243,6 +411,8 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
                         .insert_into(None)
                         .expect("Detachment should never fail");
                 }
+                // The handle is about to be dropped
+                let _ = self.vis_tree.remove_element(&handle);
             }
             EitherVisHandle::Connector(handle) => {
                 // Remove the connector from both its endpoints
@@ -256,6 +426,7 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
                         .attach_to(None)
                         .expect("Detachment should never fail");
                 }
+                let _ = self.vis_tree.remove_connector(&handle);
             }
         }
     }
@@ -280,6 +451,9 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
                         .iter()
                         .map(|(k, v)| (k.as_str(), v.as_str())),
                 );
+                if let Some(layer) = properties.layer {
+                    element.set_layer(layer);
+                }
                 EitherVisHandle::Element(handle)
             }
             Some(DisplayMode::Connector) => {
@@ -339,6 +513,9 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
                         .iter()
                         .map(|(k, v)| (k.as_str(), v.as_str())),
                 );
+                if mapping.properties.layer != properties.layer {
+                    element.set_layer(properties.layer.unwrap_or_default());
+                }
                 mapping.properties = properties;
             }
             EitherVisHandle::Connector(handle) => {
@@ -400,7 +577,7 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
     /// Updates attributes of a visual entity.
     fn update_attribute_map<'a>(
         target: &mut impl AttributeMap,
-        mut old_values: HashMap<String, String>,
+        mut old_values: HashMap<Name, String>,
         values: impl IntoIterator<Item = (&'a str, &'a str)>,
     ) {
         for (key, value) in values {
@@ -412,18 +589,20 @@ impl<'w, T: NodeId, V: VisTree> VisTreeWriter<'w, T, V> {
         }
     }
 
-    /// Updates the root element in the visualization tree.
+    /// Updates the root elements in the visualization tree.
     ///
-    /// This can be called in reaction to an external change of the root entity,
-    /// or because the root entity's visualization has been recreated.
-    fn forward_update_root(&mut self) {
-        let root_handle = self
-            .current_root
-            .as_ref()
-            .and_then(|key| self.current_mappping.get(key))
-            .and_then(|mapping| mapping.vis_handle.element());
+    /// This can be called in reaction to an external change of the root entities,
+    /// or because the root entities' visualizations have been recreated.
+    fn forward_update_roots(&mut self) {
+        let root_handles = self
+            .current_roots
+            .iter()
+            .filter_map(|key| self.current_mappping.get(key))
+            .filter_map(|mapping| mapping.vis_handle.element())
+            .cloned()
+            .collect::<Vec<_>>();
         self.vis_tree
-            .set_root(root_handle)
+            .set_roots(&root_handles)
             .expect("The handle should remain valid");
     }
 }
@@ -453,4 +632,11 @@ impl<E: VisHandle, C: VisHandle> EitherVisHandle<E, C> {
             Self::Connector(_) => None,
         }
     }
+
+    fn connector(&self) -> Option<&C> {
+        match self {
+            Self::Element(_) => None,
+            Self::Connector(h) => Some(h),
+        }
+    }
 }
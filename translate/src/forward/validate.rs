@@ -0,0 +1,215 @@
+//! Dry-run validation of [`EntityPropertyMapping`]s.
+
+use crate::property::{DisplayMode, EntityPropertyMapping, PropertyMap};
+use aili_model::state::NodeId;
+use aili_style::selectable::Selectable;
+use derive_more::Display;
+use std::collections::{HashMap, HashSet};
+
+/// Describes a structural problem in an [`EntityPropertyMapping`]
+/// that would prevent it from being faithfully rendered
+/// into a [`VisTree`](aili_model::vis::VisTree).
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+pub enum MappingViolation<T: NodeId> {
+    /// The entity is an element whose chain of parents loops back to itself.
+    #[display("element {_0:?} is its own ancestor")]
+    ParentCycle(Selectable<T>),
+
+    /// The entity's parent (or start point, if the entity is a connector)
+    /// refers to an entity that is not displayed.
+    #[display("parent of {_0:?} is not displayed")]
+    MissingParent(Selectable<T>),
+
+    /// The entity is a connector whose target refers
+    /// to an entity that is not displayed.
+    #[display("target of {_0:?} is not displayed")]
+    MissingTarget(Selectable<T>),
+
+    /// The entity's parent (or start point, if the entity is a connector)
+    /// refers to an entity that is displayed as a connector.
+    #[display("parent of {_0:?} is a connector")]
+    ConnectorParent(Selectable<T>),
+
+    /// The entity is a connector whose target is a connector
+    /// whose chain of targets loops back to the entity.
+    #[display("connector {_0:?} is its own target")]
+    TargetCycle(Selectable<T>),
+
+    /// The entity has the same stable key as another displayed entity,
+    /// so at most one of them can reuse the visualization with that key.
+    #[display("stable key of {_0:?} is not unique")]
+    DuplicateStableKey(Selectable<T>),
+}
+
+impl<T: NodeId> MappingViolation<T> {
+    /// Gets the entity that violates the structure.
+    pub fn entity(&self) -> &Selectable<T> {
+        match self {
+            Self::ParentCycle(entity)
+            | Self::MissingParent(entity)
+            | Self::MissingTarget(entity)
+            | Self::ConnectorParent(entity)
+            | Self::TargetCycle(entity)
+            | Self::DuplicateStableKey(entity) => entity,
+        }
+    }
+}
+
+/// Checks an [`EntityPropertyMapping`] for structure violations
+/// without rendering it.
+///
+/// All violations are collected and returned, in no particular order.
+/// Entities that are not displayed are not checked.
+/// Every entity that lies on a parent or target cycle is reported,
+/// as is every entity that shares its stable key with another.
+/// Connectors may target other connectors, as long as the targets
+/// do not form a cycle.
+pub fn validate_mapping<T: NodeId>(mapping: &EntityPropertyMapping<T>) -> Vec<MappingViolation<T>> {
+    let mut violations = Vec::new();
+    for (selectable, properties) in &mapping.0 {
+        let Some(display) = &properties.display else {
+            continue;
+        };
+        if let Some(parent) = properties.effective_parent() {
+            match display_of(mapping, parent) {
+                None => violations.push(MappingViolation::MissingParent(selectable.clone())),
+                Some(DisplayMode::Connector) => {
+                    violations.push(MappingViolation::ConnectorParent(selectable.clone()))
+                }
+                Some(DisplayMode::ElementTag(_)) => {}
+            }
+        }
+        // Targets are only meaningful for connectors
+        if let (DisplayMode::Connector, Some(target)) = (display, &properties.target) {
+            match display_of(mapping, target) {
+                None => violations.push(MappingViolation::MissingTarget(selectable.clone())),
+                Some(DisplayMode::Connector) => {
+                    if is_on_target_cycle(selectable, |key| connector_target(mapping, key)) {
+                        violations.push(MappingViolation::TargetCycle(selectable.clone()))
+                    }
+                }
+                Some(DisplayMode::ElementTag(_)) => {}
+            }
+        }
+    }
+    violations.extend(
+        find_parent_cycles(mapping)
+            .into_iter()
+            .map(MappingViolation::ParentCycle),
+    );
+    violations.extend(
+        find_duplicate_stable_keys(mapping)
+            .into_iter()
+            .map(MappingViolation::DuplicateStableKey),
+    );
+    violations
+}
+
+/// Gets the display mode of an entity, if it is displayed.
+fn display_of<'a, T: NodeId>(
+    mapping: &'a EntityPropertyMapping<T>,
+    selectable: &Selectable<T>,
+) -> Option<&'a DisplayMode> {
+    mapping.0.get(selectable)?.display.as_ref()
+}
+
+/// Gets the parent of an entity if both the entity
+/// and its parent are displayed as elements.
+fn element_parent<'a, T: NodeId>(
+    mapping: &'a EntityPropertyMapping<T>,
+    properties: &'a PropertyMap<T>,
+) -> Option<&'a Selectable<T>> {
+    let parent = properties.effective_parent()?;
+    match display_of(mapping, parent) {
+        Some(DisplayMode::ElementTag(_)) => Some(parent),
+        _ => None,
+    }
+}
+
+/// Gets the target of an entity if it is displayed as a connector.
+fn connector_target<'a, T: NodeId>(
+    mapping: &'a EntityPropertyMapping<T>,
+    selectable: &Selectable<T>,
+) -> Option<&'a Selectable<T>> {
+    let properties = mapping.0.get(selectable)?;
+    match properties.display {
+        Some(DisplayMode::Connector) => properties.target.as_ref(),
+        _ => None,
+    }
+}
+
+/// Checks whether following the targets of connectors,
+/// starting at a connector, leads back to that connector.
+pub(super) fn is_on_target_cycle<'a, T: NodeId + 'a>(
+    connector: &Selectable<T>,
+    connector_target: impl Fn(&Selectable<T>) -> Option<&'a Selectable<T>>,
+) -> bool {
+    let mut visited = HashSet::new();
+    let mut current = connector_target(connector);
+    while let Some(key) = current {
+        if key == connector {
+            return true;
+        }
+        if !visited.insert(key) {
+            // We have found a cycle that does not include the connector
+            return false;
+        }
+        current = connector_target(key);
+    }
+    false
+}
+
+/// Finds all elements that are their own ancestors.
+fn find_parent_cycles<T: NodeId>(mapping: &EntityPropertyMapping<T>) -> Vec<Selectable<T>> {
+    // Elements whose ancestry has already been fully explored
+    let mut finished = HashSet::new();
+    let mut cycles = Vec::new();
+    for (selectable, properties) in &mapping.0 {
+        if !matches!(properties.display, Some(DisplayMode::ElementTag(_)))
+            || finished.contains(selectable)
+        {
+            continue;
+        }
+        // Walk up the ancestry, remembering where on the path each element lies
+        let mut path = Vec::new();
+        let mut path_indices = HashMap::new();
+        let mut current = Some((selectable, properties));
+        while let Some((key, properties)) = current {
+            if finished.contains(key) {
+                break;
+            }
+            if let Some(&cycle_start) = path_indices.get(key) {
+                // We have come back to an element on the current path,
+                // so everything from there onwards is a cycle
+                cycles.extend(
+                    path[cycle_start..]
+                        .iter()
+                        .map(|k: &&Selectable<T>| (*k).clone()),
+                );
+                break;
+            }
+            path_indices.insert(key, path.len());
+            path.push(key);
+            current = element_parent(mapping, properties)
+                .and_then(|parent| mapping.0.get_key_value(parent));
+        }
+        finished.extend(path);
+    }
+    cycles
+}
+
+/// Finds all displayed entities whose stable keys are not unique.
+fn find_duplicate_stable_keys<T: NodeId>(mapping: &EntityPropertyMapping<T>) -> Vec<Selectable<T>> {
+    let mut owners = HashMap::<_, Vec<_>>::new();
+    for (selectable, properties) in &mapping.0 {
+        if let (Some(_), Some(stable_key)) = (&properties.display, &properties.stable_key) {
+            owners.entry(stable_key).or_default().push(selectable);
+        }
+    }
+    owners
+        .into_values()
+        .filter(|owners| owners.len() > 1)
+        .flatten()
+        .cloned()
+        .collect()
+}
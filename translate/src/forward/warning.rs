@@ -0,0 +1,141 @@
+//! Warnings emitted by [`VisTreeWriter`](super::VisTreeWriter)
+//! and policies that decide how they are handled.
+
+use super::MappingViolation;
+use aili_model::state::NodeId;
+use aili_style::selectable::Selectable;
+use derive_more::Display;
+
+/// Describes an occurrence in a [`VisTreeWriter`](super::VisTreeWriter)
+/// that should not arise when using it as intended
+/// and is likely indicative of an error in the input.
+#[derive(Debug, Display)]
+pub enum VisTreeWriterWarning<T: NodeId> {
+    /// The resolved stylesheet has caused a cycle in the visualization tree.
+    #[display("detected loop in vis tree near {_0:?}")]
+    VisStructureViolation(Selectable<T>),
+
+    /// An entity has been left out of an update
+    /// because of a violation in the mapping.
+    ///
+    /// Only emitted with [`WarningPolicy::SkipEntity`].
+    #[display("skipped entity: {_0}")]
+    SkippedEntity(MappingViolation<T>),
+
+    /// An update has not been applied at all
+    /// because of a violation in the mapping.
+    ///
+    /// Only emitted with [`WarningPolicy::Abort`].
+    #[display("update rejected: {_0}")]
+    RejectedUpdate(MappingViolation<T>),
+
+    /// Several warnings of the same kind that have been raised
+    /// during a single update, reported together.
+    ///
+    /// The vector always contains at least two warnings,
+    /// none of which are coalesced themselves.
+    #[display("{} {} in this update, first: {}", _0.len(), _0[0].plural_name(), _0[0])]
+    Coalesced(Vec<Self>),
+}
+
+impl<T: NodeId> VisTreeWriterWarning<T> {
+    /// Gets a stable identifier of the kind of warning,
+    /// such as `structure-violation`.
+    ///
+    /// Coalesced warnings have the code of the warnings they contain.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::VisStructureViolation(_) => "structure-violation",
+            Self::SkippedEntity(_) => "skipped-entity",
+            Self::RejectedUpdate(_) => "rejected-update",
+            Self::Coalesced(warnings) => warnings[0].code(),
+        }
+    }
+
+    /// Gets how serious the warning is.
+    ///
+    /// Coalesced warnings have the severity of the warnings they contain.
+    pub fn severity(&self) -> WarningSeverity {
+        match self {
+            Self::VisStructureViolation(_) | Self::SkippedEntity(_) => WarningSeverity::Warning,
+            Self::RejectedUpdate(_) => WarningSeverity::Error,
+            Self::Coalesced(warnings) => warnings[0].severity(),
+        }
+    }
+
+    /// Describes multiple warnings of the same kind.
+    fn plural_name(&self) -> &'static str {
+        match self {
+            Self::VisStructureViolation(_) => "structure violations",
+            Self::SkippedEntity(_) => "skipped entities",
+            Self::RejectedUpdate(_) => "reasons to reject the update",
+            Self::Coalesced(warnings) => warnings[0].plural_name(),
+        }
+    }
+}
+
+/// Seriousness of a [`VisTreeWriterWarning`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Display)]
+pub enum WarningSeverity {
+    /// Something has been rendered differently than the stylesheet asked for,
+    /// but the rest of the visualization is unaffected.
+    #[display("warning")]
+    Warning,
+
+    /// An update has not been applied at all.
+    #[display("error")]
+    Error,
+}
+
+/// Decides what a [`VisTreeWriter`](super::VisTreeWriter) does
+/// with a mapping that violates the structure of the visualization tree.
+///
+/// See [`validate_mapping`](super::validate_mapping)
+/// for the kinds of violations.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum WarningPolicy {
+    /// The mapping is rendered as faithfully as possible.
+    /// Elements that cannot be inserted into their parents
+    /// are left detached and reported as
+    /// [`VisStructureViolation`](VisTreeWriterWarning::VisStructureViolation)s.
+    #[default]
+    BestEffort,
+
+    /// The mapping is validated before it is rendered
+    /// and entities with violations are left out,
+    /// as if they were not displayed at all.
+    SkipEntity,
+
+    /// The mapping is validated before it is rendered
+    /// and if it has any violations, it is not rendered at all,
+    /// so the visualization tree is left as it was.
+    Abort,
+}
+
+/// Groups warnings by their [codes](VisTreeWriterWarning::code),
+/// in the order in which each code first occurs,
+/// and coalesces groups that have more than one warning.
+pub(super) fn coalesce_warnings<T: NodeId>(
+    warnings: impl IntoIterator<Item = VisTreeWriterWarning<T>>,
+) -> Vec<VisTreeWriterWarning<T>> {
+    let mut groups = Vec::<Vec<VisTreeWriterWarning<T>>>::new();
+    for warning in warnings {
+        match groups
+            .iter_mut()
+            .find(|group| group[0].code() == warning.code())
+        {
+            Some(group) => group.push(warning),
+            None => groups.push(vec![warning]),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|mut group| {
+            if group.len() == 1 {
+                group.pop().expect("Group is not empty")
+            } else {
+                VisTreeWriterWarning::Coalesced(group)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,287 @@
+//! Recording of state graphs and their mappings, step by step.
+//!
+//! A [`History`] keeps a copy of the state graph and the resolved
+//! [`EntityPropertyMapping`] for each step of a debugging session,
+//! so the session can be scrubbed through after the debuggee is gone.
+//! Nodes that have not changed between steps are shared between
+//! the copies, so long sessions of small steps stay small.
+//!
+//! ```
+//! use aili_model::{graph, state::*};
+//! use aili_translate::{history::History, property::EntityPropertyMapping};
+//!
+//! let mut history = History::new();
+//! history.record(&graph! { 0 -main-> 1 [val = 1]; }, EntityPropertyMapping::new());
+//! history.record(&graph! { 0 -main-> 1 [val = 2]; }, EntityPropertyMapping::new());
+//! assert_eq!(history.position(), Some(1));
+//!
+//! let previous = history.step_backward().unwrap();
+//! let frame = previous.graph.get(&1).unwrap();
+//! assert_eq!(frame.value(), Some(NodeValue::Int(1)));
+//!
+//! let diff = history.diff(0, 1).unwrap();
+//! assert!(diff.changed_nodes.contains(&1));
+//! assert!(diff.added_nodes.is_empty());
+//! ```
+
+use crate::{
+    cascade::{NodeSnapshot, snapshot_graph},
+    property::EntityPropertyMapping,
+};
+use aili_model::{
+    capability::CapabilitySet,
+    state::{NodeId, ProgramStateGraph, RootedProgramStateGraph},
+};
+use aili_style::selectable::Selectable;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Copy of a state graph that outlives the graph it was taken from.
+///
+/// Only the nodes that are reachable from the root are copied.
+/// The copy is itself a state graph, so a past step can be
+/// rendered again like any other graph.
+#[derive(Debug)]
+pub struct GraphSnapshot<T: NodeId> {
+    root: T,
+    nodes: HashMap<T, Arc<NodeSnapshot<T>>>,
+    capabilities: CapabilitySet,
+}
+
+impl<T: NodeId> GraphSnapshot<T> {
+    /// Copies a state graph.
+    pub fn new<G: RootedProgramStateGraph<NodeId = T>>(graph: &G) -> Self {
+        Self::sharing_with(graph, None)
+    }
+
+    /// Copies a state graph, reusing the nodes of a previous copy
+    /// that are the same in both graphs.
+    fn sharing_with<G: RootedProgramStateGraph<NodeId = T>>(
+        graph: &G,
+        previous: Option<&Self>,
+    ) -> Self {
+        let nodes = snapshot_graph(graph)
+            .into_iter()
+            .map(|(id, node)| {
+                let node = previous
+                    .and_then(|previous| previous.nodes.get(&id))
+                    .filter(|previous_node| ***previous_node == node)
+                    .cloned()
+                    .unwrap_or_else(|| Arc::new(node));
+                (id, node)
+            })
+            .collect();
+        Self {
+            root: graph.root(),
+            nodes,
+            capabilities: graph.capabilities(),
+        }
+    }
+
+    /// Gets the number of nodes in the copy.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl<T: NodeId> ProgramStateGraph for GraphSnapshot<T> {
+    type NodeId = T;
+    type NodeRef<'a>
+        = &'a NodeSnapshot<T>
+    where
+        Self: 'a;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        self.nodes.get(id).map(Arc::as_ref)
+    }
+    fn capabilities(&self) -> CapabilitySet {
+        self.capabilities.clone()
+    }
+}
+
+impl<T: NodeId> RootedProgramStateGraph for GraphSnapshot<T> {
+    fn root(&self) -> Self::NodeId {
+        self.root.clone()
+    }
+}
+
+/// State of a debugging session at one step.
+#[derive(Debug)]
+pub struct HistoryStep<T: NodeId> {
+    /// Copy of the state graph.
+    pub graph: GraphSnapshot<T>,
+
+    /// The mapping that the state graph was resolved to.
+    pub mapping: EntityPropertyMapping<T>,
+}
+
+/// Differences between two steps of a [`History`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HistoryDiff<T: NodeId> {
+    /// Nodes that are only present in the later step.
+    pub added_nodes: HashSet<T>,
+
+    /// Nodes that are only present in the earlier step.
+    pub removed_nodes: HashSet<T>,
+
+    /// Nodes that are present in both steps, but differ
+    /// in their values, types, or outgoing edges.
+    pub changed_nodes: HashSet<T>,
+
+    /// Entities that only have properties in the later step.
+    pub added_entities: HashSet<Selectable<T>>,
+
+    /// Entities that only have properties in the earlier step.
+    pub removed_entities: HashSet<Selectable<T>>,
+
+    /// Entities that have different properties in each step.
+    pub changed_entities: HashSet<Selectable<T>>,
+}
+
+impl<T: NodeId> HistoryDiff<T> {
+    /// Checks whether the steps are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.added_entities.is_empty()
+            && self.removed_entities.is_empty()
+            && self.changed_entities.is_empty()
+    }
+}
+
+/// Recording of a debugging session.
+///
+/// Steps are recorded one after another and can then be
+/// visited in any order. The history remembers which step
+/// is being visited, so a viewer can seek backward and forward.
+/// Recording a step always appends it at the end and visits it.
+#[derive(Debug)]
+pub struct History<T: NodeId> {
+    steps: Vec<HistoryStep<T>>,
+    position: usize,
+}
+
+impl<T: NodeId> History<T> {
+    /// Constructs a history with no steps.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the state graph and the resolved mapping of a step.
+    pub fn record<G: RootedProgramStateGraph<NodeId = T>>(
+        &mut self,
+        graph: &G,
+        mapping: EntityPropertyMapping<T>,
+    ) {
+        let graph = GraphSnapshot::sharing_with(graph, self.steps.last().map(|step| &step.graph));
+        self.steps.push(HistoryStep { graph, mapping });
+        self.position = self.steps.len() - 1;
+    }
+
+    /// Gets the number of recorded steps.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Checks whether no steps have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Gets a step by its index.
+    pub fn get(&self, index: usize) -> Option<&HistoryStep<T>> {
+        self.steps.get(index)
+    }
+
+    /// Gets the index of the step that is being visited,
+    /// or [`None`] if no steps have been recorded.
+    pub fn position(&self) -> Option<usize> {
+        (!self.steps.is_empty()).then_some(self.position)
+    }
+
+    /// Gets the step that is being visited.
+    pub fn current(&self) -> Option<&HistoryStep<T>> {
+        self.steps.get(self.position)
+    }
+
+    /// Visits the step at an index.
+    ///
+    /// Returns [`None`] and stays at the current step
+    /// if there is no such step.
+    pub fn seek(&mut self, index: usize) -> Option<&HistoryStep<T>> {
+        if index >= self.steps.len() {
+            return None;
+        }
+        self.position = index;
+        self.current()
+    }
+
+    /// Visits the step after the current one, if there is one.
+    pub fn step_forward(&mut self) -> Option<&HistoryStep<T>> {
+        self.seek(self.position + 1)
+    }
+
+    /// Visits the step before the current one, if there is one.
+    pub fn step_backward(&mut self) -> Option<&HistoryStep<T>> {
+        self.seek(self.position.checked_sub(1)?)
+    }
+
+    /// Compares two steps.
+    ///
+    /// Returns [`None`] if either of the steps does not exist.
+    pub fn diff(&self, from: usize, to: usize) -> Option<HistoryDiff<T>> {
+        let from = self.steps.get(from)?;
+        let to = self.steps.get(to)?;
+        let from_nodes = &from.graph.nodes;
+        let to_nodes = &to.graph.nodes;
+        let from_entities = &from.mapping.0;
+        let to_entities = &to.mapping.0;
+        Some(HistoryDiff {
+            added_nodes: keys_missing_from(to_nodes, from_nodes),
+            removed_nodes: keys_missing_from(from_nodes, to_nodes),
+            changed_nodes: from_nodes
+                .iter()
+                .filter(|(id, node)| {
+                    to_nodes.get(id).is_some_and(|other| {
+                        // Shared nodes are the same without comparing them
+                        !Arc::ptr_eq(node, other) && node != &other
+                    })
+                })
+                .map(|(id, _)| id.clone())
+                .collect(),
+            added_entities: keys_missing_from(to_entities, from_entities),
+            removed_entities: keys_missing_from(from_entities, to_entities),
+            changed_entities: from_entities
+                .iter()
+                .filter(|(entity, properties)| {
+                    to_entities
+                        .get(entity)
+                        .is_some_and(|other| *properties != other)
+                })
+                .map(|(entity, _)| entity.clone())
+                .collect(),
+        })
+    }
+}
+
+impl<T: NodeId> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            steps: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+/// Collects the keys of one map that are not in another.
+fn keys_missing_from<K: Clone + Eq + std::hash::Hash, V, W>(
+    map: &HashMap<K, V>,
+    other: &HashMap<K, W>,
+) -> HashSet<K> {
+    map.keys()
+        .filter(|key| !other.contains_key(key))
+        .cloned()
+        .collect()
+}
@@ -1,5 +1,11 @@
 #![doc = include_str!("../README.md")]
 
 pub mod cascade;
+pub mod export;
 pub mod forward;
+pub mod history;
+pub mod mutation;
+pub mod pipeline;
 pub mod property;
+#[cfg(feature = "testing")]
+pub mod testing;
@@ -0,0 +1,820 @@
+//! [`VisTree`] that records changes to its structure
+//! as a stream of serializable mutations.
+
+use aili_model::vis::*;
+use std::collections::HashMap;
+
+/// Identifies one of the two pins of a connector.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum PinSide {
+    /// The start pin.
+    Start,
+
+    /// The end pin.
+    End,
+}
+
+/// Single change to a visualization tree.
+///
+/// Elements and connectors are identified by numbers
+/// that are assigned in order of creation. Elements and connectors
+/// have separate sequences of identifiers.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "camelCase"))]
+pub enum Mutation {
+    /// A new element has been created.
+    CreateElement {
+        /// Identifier of the new element.
+        id: usize,
+
+        /// Tag name of the new element.
+        #[cfg_attr(feature = "serde", serde(rename = "tagName"))]
+        tag_name: String,
+    },
+
+    /// A new connector has been created.
+    CreateConnector {
+        /// Identifier of the new connector.
+        id: usize,
+    },
+
+    /// The root element of the tree has changed.
+    ///
+    /// This is recorded when the tree has at most one root,
+    /// so clients that only support a single root are not affected
+    /// by multiple roots unless they are actually used.
+    SetRoot {
+        /// The new root element, if any.
+        element: Option<usize>,
+    },
+
+    /// The root elements of the tree have changed
+    /// and there is more than one of them.
+    SetRoots {
+        /// The new root elements.
+        elements: Vec<usize>,
+    },
+
+    /// The parent of an element has changed.
+    SetParent {
+        /// The element whose parent has changed.
+        element: usize,
+
+        /// The new parent element, if any.
+        parent: Option<usize>,
+    },
+
+    /// The layer of an element has changed.
+    SetLayer {
+        /// The element whose layer has changed.
+        element: usize,
+
+        /// The new layer of the element.
+        layer: i32,
+    },
+
+    /// An attribute of an element has changed.
+    SetElementAttribute {
+        /// The element whose attribute has changed.
+        element: usize,
+
+        /// Name of the attribute.
+        name: String,
+
+        /// The new value of the attribute, if any.
+        value: Option<String>,
+    },
+
+    /// An attribute of a connector has changed.
+    SetConnectorAttribute {
+        /// The connector whose attribute has changed.
+        connector: usize,
+
+        /// Name of the attribute.
+        name: String,
+
+        /// The new value of the attribute, if any.
+        value: Option<String>,
+    },
+
+    /// An attribute of a connector pin has changed.
+    SetPinAttribute {
+        /// The connector whose pin has changed.
+        connector: usize,
+
+        /// The pin that has changed.
+        pin: PinSide,
+
+        /// Name of the attribute.
+        name: String,
+
+        /// The new value of the attribute, if any.
+        value: Option<String>,
+    },
+
+    /// The element that a connector pin is attached to has changed.
+    ///
+    /// If the pin was attached to a connector, it no longer is.
+    SetPinTarget {
+        /// The connector whose pin has changed.
+        connector: usize,
+
+        /// The pin that has changed.
+        pin: PinSide,
+
+        /// The new target element, if any.
+        target: Option<usize>,
+    },
+
+    /// A connector pin has been attached to the midpoint of another connector.
+    ///
+    /// If the pin was attached to an element, it no longer is.
+    /// Pins are detached from connectors by [`Mutation::SetPinTarget`].
+    SetPinTargetConnector {
+        /// The connector whose pin has changed.
+        connector: usize,
+
+        /// The pin that has changed.
+        pin: PinSide,
+
+        /// The new target connector.
+        #[cfg_attr(feature = "serde", serde(rename = "targetConnector"))]
+        target_connector: usize,
+    },
+
+    /// An element has been removed from the tree
+    /// and its identifier will not be used again.
+    ///
+    /// The element has no parent at this point. Elements
+    /// that are still inserted into it and pins that are still
+    /// attached to it are detached, and if it is a root,
+    /// it is no longer one.
+    RemoveElement {
+        /// The removed element.
+        element: usize,
+    },
+
+    /// A connector has been removed from the tree
+    /// and its identifier will not be used again.
+    ///
+    /// Pins that are still attached to it are detached.
+    RemoveConnector {
+        /// The removed connector.
+        connector: usize,
+    },
+}
+
+/// [`VisTree`] that does not render anything, but instead records
+/// all changes as [`Mutation`]s, so they can be replayed elsewhere.
+/// Operations that do not change anything are not recorded.
+///
+/// The recorder also keeps track of the current structure of the tree,
+/// so that a client that was not present from the start
+/// can be brought up to date using [`MutationRecorder::snapshot`].
+#[derive(Debug, Default)]
+pub struct MutationRecorder {
+    elements: Vec<RecordedElement>,
+    connectors: Vec<RecordedConnector>,
+    roots: Vec<usize>,
+    mutations: Vec<Mutation>,
+}
+
+#[derive(Debug)]
+struct RecordedElement {
+    tag_name: String,
+    attributes: HashMap<String, String>,
+    parent: Option<usize>,
+    layer: i32,
+    removed: bool,
+}
+
+#[derive(Debug, Default)]
+struct RecordedConnector {
+    attributes: HashMap<String, String>,
+    start: RecordedPin,
+    end: RecordedPin,
+    removed: bool,
+}
+
+#[derive(Debug, Default)]
+struct RecordedPin {
+    attributes: HashMap<String, String>,
+    target: Option<usize>,
+    target_connector: Option<usize>,
+}
+
+impl MutationRecorder {
+    /// Constructs an empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes and returns all mutations recorded since the last call.
+    pub fn take_mutations(&mut self) -> Vec<Mutation> {
+        std::mem::take(&mut self.mutations)
+    }
+
+    /// Constructs a sequence of mutations that builds
+    /// the current state of the tree from scratch.
+    pub fn snapshot(&self) -> Vec<Mutation> {
+        let mut mutations = Vec::new();
+        // Removed entities keep their identifiers,
+        // so the client's sequence of identifiers stays in sync
+        for (id, element) in self.elements.iter().enumerate() {
+            mutations.push(Mutation::CreateElement {
+                id,
+                tag_name: element.tag_name.clone(),
+            });
+            if element.removed {
+                mutations.push(Mutation::RemoveElement { element: id });
+                continue;
+            }
+            mutations.extend(element.attributes.iter().map(|(name, value)| {
+                Mutation::SetElementAttribute {
+                    element: id,
+                    name: name.clone(),
+                    value: Some(value.clone()),
+                }
+            }));
+            if element.layer != 0 {
+                mutations.push(Mutation::SetLayer {
+                    element: id,
+                    layer: element.layer,
+                });
+            }
+        }
+        // Parents are only assigned once all elements exist.
+        // The final structure has no cycles,
+        // so neither does any part of it
+        mutations.extend(
+            self.elements
+                .iter()
+                .enumerate()
+                .filter(|(_, element)| element.parent.is_some())
+                .map(|(id, element)| Mutation::SetParent {
+                    element: id,
+                    parent: element.parent,
+                }),
+        );
+        for (id, connector) in self.connectors.iter().enumerate() {
+            mutations.push(Mutation::CreateConnector { id });
+            if connector.removed {
+                mutations.push(Mutation::RemoveConnector { connector: id });
+                continue;
+            }
+            mutations.extend(connector.attributes.iter().map(|(name, value)| {
+                Mutation::SetConnectorAttribute {
+                    connector: id,
+                    name: name.clone(),
+                    value: Some(value.clone()),
+                }
+            }));
+            for (pin, side) in [
+                (&connector.start, PinSide::Start),
+                (&connector.end, PinSide::End),
+            ] {
+                mutations.extend(pin.attributes.iter().map(|(name, value)| {
+                    Mutation::SetPinAttribute {
+                        connector: id,
+                        pin: side,
+                        name: name.clone(),
+                        value: Some(value.clone()),
+                    }
+                }));
+                if pin.target.is_some() {
+                    mutations.push(Mutation::SetPinTarget {
+                        connector: id,
+                        pin: side,
+                        target: pin.target,
+                    });
+                }
+            }
+        }
+        // Pins are only attached to connectors once all connectors exist
+        for (id, connector) in self.connectors.iter().enumerate() {
+            if connector.removed {
+                continue;
+            }
+            for (pin, side) in [
+                (&connector.start, PinSide::Start),
+                (&connector.end, PinSide::End),
+            ] {
+                if let Some(target_connector) = pin.target_connector {
+                    mutations.push(Mutation::SetPinTargetConnector {
+                        connector: id,
+                        pin: side,
+                        target_connector,
+                    });
+                }
+            }
+        }
+        if !self.roots.is_empty() {
+            mutations.push(self.roots_mutation());
+        }
+        mutations
+    }
+
+    /// Constructs a mutation that sets the current roots.
+    fn roots_mutation(&self) -> Mutation {
+        match self.roots.as_slice() {
+            [] => Mutation::SetRoot { element: None },
+            [root] => Mutation::SetRoot {
+                element: Some(*root),
+            },
+            roots => Mutation::SetRoots {
+                elements: roots.to_vec(),
+            },
+        }
+    }
+
+    fn is_ancestor_of(&self, ancestor: usize, mut descendant: usize) -> bool {
+        loop {
+            if descendant == ancestor {
+                break true;
+            }
+            match self.elements[descendant].parent {
+                Some(parent) => descendant = parent,
+                None => break false,
+            }
+        }
+    }
+
+    fn validate_element(&self, handle: Option<&usize>) -> Result<(), InvalidHandle> {
+        match handle {
+            Some(&id) if self.elements.get(id).is_none_or(|e| e.removed) => Err(InvalidHandle),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_connector(&self, handle: usize) -> Result<(), InvalidHandle> {
+        match self.connectors.get(handle) {
+            Some(connector) if !connector.removed => Ok(()),
+            _ => Err(InvalidHandle),
+        }
+    }
+}
+
+impl VisTree for MutationRecorder {
+    type ElementHandle = usize;
+    type ConnectorHandle = usize;
+    type ElementRef<'a> = RecordedElementRef<'a>;
+    type ConnectorRef<'a> = RecordedConnectorRef<'a>;
+
+    fn set_root(&mut self, handle: Option<&Self::ElementHandle>) -> Result<(), InvalidHandle> {
+        self.set_roots(handle.map(std::slice::from_ref).unwrap_or_default())
+    }
+
+    fn set_roots(&mut self, handles: &[Self::ElementHandle]) -> Result<(), InvalidHandle> {
+        for handle in handles {
+            self.validate_element(Some(handle))?;
+        }
+        if self.roots == handles {
+            return Ok(());
+        }
+        self.roots = handles.to_vec();
+        self.mutations.push(self.roots_mutation());
+        Ok(())
+    }
+
+    fn add_element(&mut self, tag_name: &str) -> Self::ElementHandle {
+        let id = self.elements.len();
+        self.elements.push(RecordedElement {
+            tag_name: tag_name.to_owned(),
+            attributes: HashMap::new(),
+            parent: None,
+            layer: 0,
+            removed: false,
+        });
+        self.mutations.push(Mutation::CreateElement {
+            id,
+            tag_name: tag_name.to_owned(),
+        });
+        id
+    }
+
+    fn add_connector(&mut self) -> Self::ConnectorHandle {
+        let id = self.connectors.len();
+        self.connectors.push(RecordedConnector::default());
+        self.mutations.push(Mutation::CreateConnector { id });
+        id
+    }
+
+    fn get_element(
+        &mut self,
+        handle: &Self::ElementHandle,
+    ) -> Result<Self::ElementRef<'_>, InvalidHandle> {
+        self.validate_element(Some(handle))?;
+        Ok(RecordedElementRef(self, *handle))
+    }
+
+    fn get_connector(
+        &mut self,
+        handle: &Self::ConnectorHandle,
+    ) -> Result<Self::ConnectorRef<'_>, InvalidHandle> {
+        self.validate_connector(*handle)?;
+        Ok(RecordedConnectorRef(self, *handle))
+    }
+
+    fn remove_element(&mut self, handle: &Self::ElementHandle) -> Result<(), InvalidHandle> {
+        self.validate_element(Some(handle))?;
+        // Mirror what the client does when it removes the element,
+        // without recording it as separate mutations
+        let removed = Some(*handle);
+        self.elements[*handle].removed = true;
+        self.elements[*handle].parent = None;
+        for element in &mut self.elements {
+            if element.parent == removed {
+                element.parent = None;
+            }
+        }
+        for connector in &mut self.connectors {
+            for pin in [&mut connector.start, &mut connector.end] {
+                if pin.target == removed {
+                    pin.target = None;
+                }
+            }
+        }
+        self.roots.retain(|root| Some(*root) != removed);
+        self.mutations
+            .push(Mutation::RemoveElement { element: *handle });
+        Ok(())
+    }
+
+    fn remove_connector(&mut self, handle: &Self::ConnectorHandle) -> Result<(), InvalidHandle> {
+        self.validate_connector(*handle)?;
+        self.connectors[*handle].removed = true;
+        for connector in &mut self.connectors {
+            for pin in [&mut connector.start, &mut connector.end] {
+                if pin.target_connector == Some(*handle) {
+                    pin.target_connector = None;
+                }
+            }
+        }
+        self.mutations
+            .push(Mutation::RemoveConnector { connector: *handle });
+        Ok(())
+    }
+}
+
+/// Reference to an element of a [`MutationRecorder`].
+pub struct RecordedElementRef<'a>(&'a mut MutationRecorder, usize);
+
+/// Reference to a connector of a [`MutationRecorder`].
+pub struct RecordedConnectorRef<'a>(&'a mut MutationRecorder, usize);
+
+/// Reference to a connector pin of a [`MutationRecorder`].
+pub struct RecordedPinRef<'a>(&'a mut MutationRecorder, usize, PinSide);
+
+impl AttributeMap for RecordedElementRef<'_> {
+    fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.0.elements[self.1]
+            .attributes
+            .get(name)
+            .map(String::as_str)
+    }
+
+    fn set_attribute(&mut self, name: &str, value: Option<&str>) {
+        if !update_attribute(&mut self.0.elements[self.1].attributes, name, value) {
+            return;
+        }
+        self.0.mutations.push(Mutation::SetElementAttribute {
+            element: self.1,
+            name: name.to_owned(),
+            value: value.map(str::to_owned),
+        });
+    }
+}
+
+impl VisElement for RecordedElementRef<'_> {
+    type Handle = usize;
+
+    fn insert_into(&mut self, parent: Option<&Self::Handle>) -> Result<(), ParentAssignmentError> {
+        self.0.validate_element(parent)?;
+        if parent.is_some_and(|p| self.0.is_ancestor_of(self.1, *p)) {
+            return Err(ParentAssignmentError::StructureViolation);
+        }
+        if self.0.elements[self.1].parent == parent.copied() {
+            return Ok(());
+        }
+        self.0.elements[self.1].parent = parent.copied();
+        self.0.mutations.push(Mutation::SetParent {
+            element: self.1,
+            parent: parent.copied(),
+        });
+        Ok(())
+    }
+
+    fn set_layer(&mut self, layer: i32) {
+        if self.0.elements[self.1].layer == layer {
+            return;
+        }
+        self.0.elements[self.1].layer = layer;
+        self.0.mutations.push(Mutation::SetLayer {
+            element: self.1,
+            layer,
+        });
+    }
+}
+
+impl AttributeMap for RecordedConnectorRef<'_> {
+    fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.0.connectors[self.1]
+            .attributes
+            .get(name)
+            .map(String::as_str)
+    }
+
+    fn set_attribute(&mut self, name: &str, value: Option<&str>) {
+        if !update_attribute(&mut self.0.connectors[self.1].attributes, name, value) {
+            return;
+        }
+        self.0.mutations.push(Mutation::SetConnectorAttribute {
+            connector: self.1,
+            name: name.to_owned(),
+            value: value.map(str::to_owned),
+        });
+    }
+}
+
+impl VisConnector for RecordedConnectorRef<'_> {
+    type Handle = usize;
+    type ConnectorHandle = usize;
+    type PinRef<'a>
+        = RecordedPinRef<'a>
+    where
+        Self: 'a;
+
+    fn start_mut(&mut self) -> Self::PinRef<'_> {
+        RecordedPinRef(self.0, self.1, PinSide::Start)
+    }
+
+    fn end_mut(&mut self) -> Self::PinRef<'_> {
+        RecordedPinRef(self.0, self.1, PinSide::End)
+    }
+}
+
+impl RecordedPinRef<'_> {
+    fn pin(&self) -> &RecordedPin {
+        let connector = &self.0.connectors[self.1];
+        match self.2 {
+            PinSide::Start => &connector.start,
+            PinSide::End => &connector.end,
+        }
+    }
+
+    fn pin_mut(&mut self) -> &mut RecordedPin {
+        let connector = &mut self.0.connectors[self.1];
+        match self.2 {
+            PinSide::Start => &mut connector.start,
+            PinSide::End => &mut connector.end,
+        }
+    }
+}
+
+impl AttributeMap for RecordedPinRef<'_> {
+    fn get_attribute(&self, name: &str) -> Option<&str> {
+        self.pin().attributes.get(name).map(String::as_str)
+    }
+
+    fn set_attribute(&mut self, name: &str, value: Option<&str>) {
+        if !update_attribute(&mut self.pin_mut().attributes, name, value) {
+            return;
+        }
+        self.0.mutations.push(Mutation::SetPinAttribute {
+            connector: self.1,
+            pin: self.2,
+            name: name.to_owned(),
+            value: value.map(str::to_owned),
+        });
+    }
+}
+
+impl VisPin for RecordedPinRef<'_> {
+    type Handle = usize;
+    type ConnectorHandle = usize;
+
+    fn attach_to(&mut self, target: Option<&Self::Handle>) -> Result<(), InvalidHandle> {
+        self.0.validate_element(target)?;
+        if self.pin().target == target.copied() && self.pin().target_connector.is_none() {
+            return Ok(());
+        }
+        self.pin_mut().target = target.copied();
+        self.pin_mut().target_connector = None;
+        self.0.mutations.push(Mutation::SetPinTarget {
+            connector: self.1,
+            pin: self.2,
+            target: target.copied(),
+        });
+        Ok(())
+    }
+
+    fn attach_to_connector(
+        &mut self,
+        target: Option<&Self::ConnectorHandle>,
+    ) -> Result<(), InvalidHandle> {
+        let Some(&target_connector) = target else {
+            return self.attach_to(None);
+        };
+        self.0.validate_connector(target_connector)?;
+        if self.pin().target_connector == Some(target_connector) {
+            return Ok(());
+        }
+        self.pin_mut().target = None;
+        self.pin_mut().target_connector = Some(target_connector);
+        self.0.mutations.push(Mutation::SetPinTargetConnector {
+            connector: self.1,
+            pin: self.2,
+            target_connector,
+        });
+        Ok(())
+    }
+}
+
+/// Applies recorded [`Mutation`]s to another [`VisTree`].
+///
+/// The player maps the identifiers used by mutations
+/// to the handles of the target tree, so it must see all mutations
+/// from the moment the identifiers were assigned, typically
+/// from the creation of a [`MutationRecorder`].
+pub struct MutationPlayer<V: VisTree> {
+    vis_tree: V,
+    elements: Vec<V::ElementHandle>,
+    connectors: Vec<V::ConnectorHandle>,
+}
+
+impl<V: VisTree> MutationPlayer<V> {
+    /// Constructs a player that applies mutations to a tree.
+    pub fn new(vis_tree: V) -> Self {
+        Self {
+            vis_tree,
+            elements: Vec::new(),
+            connectors: Vec::new(),
+        }
+    }
+
+    /// Gets the target tree.
+    pub fn vis_tree(&self) -> &V {
+        &self.vis_tree
+    }
+
+    /// Gets the target tree, mutably.
+    pub fn vis_tree_mut(&mut self) -> &mut V {
+        &mut self.vis_tree
+    }
+
+    /// Consumes the player and returns the target tree.
+    pub fn into_vis_tree(self) -> V {
+        self.vis_tree
+    }
+
+    /// Applies a single mutation to the target tree.
+    pub fn apply(&mut self, mutation: &Mutation) -> Result<(), ParentAssignmentError> {
+        match mutation {
+            Mutation::CreateElement { id, tag_name } => {
+                if *id != self.elements.len() {
+                    return Err(InvalidHandle.into());
+                }
+                self.elements.push(self.vis_tree.add_element(tag_name));
+            }
+            Mutation::CreateConnector { id } => {
+                if *id != self.connectors.len() {
+                    return Err(InvalidHandle.into());
+                }
+                self.connectors.push(self.vis_tree.add_connector());
+            }
+            Mutation::SetRoot { element } => {
+                let element = self.element_handle(*element)?;
+                self.vis_tree.set_root(element.as_ref())?;
+            }
+            Mutation::SetRoots { elements } => {
+                let elements = elements
+                    .iter()
+                    .map(|element| self.element_handle(Some(*element)).map(Option::unwrap))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.vis_tree.set_roots(&elements)?;
+            }
+            Mutation::SetParent { element, parent } => {
+                let parent = self.element_handle(*parent)?;
+                let element = self.element_handle(Some(*element))?.unwrap();
+                self.vis_tree
+                    .get_element(&element)?
+                    .insert_into(parent.as_ref())?;
+            }
+            Mutation::SetLayer { element, layer } => {
+                let element = self.element_handle(Some(*element))?.unwrap();
+                self.vis_tree.get_element(&element)?.set_layer(*layer);
+            }
+            Mutation::SetElementAttribute {
+                element,
+                name,
+                value,
+            } => {
+                let element = self.element_handle(Some(*element))?.unwrap();
+                self.vis_tree
+                    .get_element(&element)?
+                    .set_attribute(name, value.as_deref());
+            }
+            Mutation::SetConnectorAttribute {
+                connector,
+                name,
+                value,
+            } => {
+                let connector = self.connector_handle(*connector)?;
+                self.vis_tree
+                    .get_connector(&connector)?
+                    .set_attribute(name, value.as_deref());
+            }
+            Mutation::SetPinAttribute {
+                connector,
+                pin,
+                name,
+                value,
+            } => {
+                let connector = self.connector_handle(*connector)?;
+                let mut connector = self.vis_tree.get_connector(&connector)?;
+                match pin {
+                    PinSide::Start => connector.start_mut().set_attribute(name, value.as_deref()),
+                    PinSide::End => connector.end_mut().set_attribute(name, value.as_deref()),
+                }
+            }
+            Mutation::SetPinTarget {
+                connector,
+                pin,
+                target,
+            } => {
+                let target = self.element_handle(*target)?;
+                let connector = self.connector_handle(*connector)?;
+                let mut connector = self.vis_tree.get_connector(&connector)?;
+                match pin {
+                    PinSide::Start => connector.start_mut().attach_to(target.as_ref())?,
+                    PinSide::End => connector.end_mut().attach_to(target.as_ref())?,
+                }
+            }
+            Mutation::SetPinTargetConnector {
+                connector,
+                pin,
+                target_connector,
+            } => {
+                let target = self.connector_handle(*target_connector)?;
+                let connector = self.connector_handle(*connector)?;
+                let mut connector = self.vis_tree.get_connector(&connector)?;
+                match pin {
+                    PinSide::Start => connector.start_mut().attach_to_connector(Some(&target))?,
+                    PinSide::End => connector.end_mut().attach_to_connector(Some(&target))?,
+                }
+            }
+            Mutation::RemoveElement { element } => {
+                let element = self.element_handle(Some(*element))?.unwrap();
+                self.vis_tree.remove_element(&element)?;
+            }
+            Mutation::RemoveConnector { connector } => {
+                let connector = self.connector_handle(*connector)?;
+                self.vis_tree.remove_connector(&connector)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies a sequence of mutations to the target tree, in order.
+    ///
+    /// Stops at the first mutation that fails to apply.
+    pub fn apply_all<'a>(
+        &mut self,
+        mutations: impl IntoIterator<Item = &'a Mutation>,
+    ) -> Result<(), ParentAssignmentError> {
+        mutations
+            .into_iter()
+            .try_for_each(|mutation| self.apply(mutation))
+    }
+
+    fn element_handle(&self, id: Option<usize>) -> Result<Option<V::ElementHandle>, InvalidHandle> {
+        id.map(|id| self.elements.get(id).cloned().ok_or(InvalidHandle))
+            .transpose()
+    }
+
+    fn connector_handle(&self, id: usize) -> Result<V::ConnectorHandle, InvalidHandle> {
+        self.connectors.get(id).cloned().ok_or(InvalidHandle)
+    }
+}
+
+/// Inserts or removes an attribute in an attribute map.
+///
+/// Returns `true` if the map has changed.
+fn update_attribute(
+    attributes: &mut HashMap<String, String>,
+    name: &str,
+    value: Option<&str>,
+) -> bool {
+    if attributes.get(name).map(String::as_str) == value {
+        return false;
+    }
+    if let Some(value) = value {
+        attributes.insert(name.to_owned(), value.to_owned());
+    } else {
+        attributes.remove(name);
+    }
+    true
+}
@@ -0,0 +1,304 @@
+//! Convenience type that ties a state graph, a stylesheet,
+//! and a renderer together.
+
+use crate::{
+    cascade::{CascadeStats, LifetimeGraph, LifetimeTracker, apply_stylesheet_to_lifetimes},
+    forward::{VisTreeWriter, VisTreeWriterWarning},
+    history::History,
+    property::{EntityPropertyMapping, PropertyKey, typed::AttributeTypeMismatch},
+};
+use aili_model::{state::RootedProgramStateGraph, vis::VisTree};
+use aili_style::{
+    cascade::CompiledStylesheet, selectable::Selectable, stylesheet::MissingCapabilities,
+};
+
+/// Owns a state graph, a stylesheet, and a [`VisTreeWriter`],
+/// and keeps the visualization up to date as either of them changes.
+///
+/// The stylesheet can be swapped at any time, which is useful
+/// for reloading it as it is being edited. Visual elements
+/// of entities whose display mode is the same under both stylesheets
+/// are kept, so any state that the visualization attaches to them,
+/// such as positions of elements that the user has moved, survives.
+/// Entities also keep their [lifetimes](crate::cascade::LifetimeTracker),
+/// so nodes that have just been added or removed are still styled as such.
+///
+/// Embedders that step through a program can update the graph
+/// and render it in one call with [`Pipeline::step`].
+/// Warnings of both the stylesheet application and the renderer
+/// can be observed through handlers, and the work that each render
+/// takes can be [counted](Pipeline::set_stats_enabled).
+/// Renders can also be [recorded](Pipeline::set_recording_enabled)
+/// into a [`History`] that can be scrubbed through later.
+///
+/// ```
+/// use aili_model::{state::RootedProgramStateGraph, vis::VisTree};
+/// use aili_style::cascade::CompiledStylesheet;
+/// use aili_translate::{pipeline::Pipeline, property::PropertyKey};
+///
+/// fn reload<G: RootedProgramStateGraph, V: VisTree>(
+///     pipeline: &mut Pipeline<G, V>,
+///     stylesheet: CompiledStylesheet<PropertyKey>,
+/// ) {
+///     if let Err(missing) = pipeline.set_stylesheet(stylesheet) {
+///         eprintln!("{missing}");
+///     }
+/// }
+/// ```
+pub struct Pipeline<'w, G: RootedProgramStateGraph, V: VisTree> {
+    /// The visualized state graph.
+    graph: G,
+
+    /// The stylesheet that the graph is visualized with.
+    stylesheet: CompiledStylesheet<PropertyKey>,
+
+    /// Tracker of nodes that enter or leave the graph between renders.
+    lifetimes: LifetimeTracker<G::NodeId>,
+
+    /// Whether the graph has been tracked by [`Pipeline::lifetimes`] yet.
+    tracked: bool,
+
+    /// Renderer that writes into the visualization tree.
+    writer: VisTreeWriter<'w, G::NodeId, V>,
+
+    /// Handler of values of well-known attributes
+    /// that do not have the required type.
+    cascade_warning_handler: Option<CascadeWarningHandler<'w, G::NodeId>>,
+
+    /// Counters of the last render, if they are being collected.
+    stats: Option<CascadeStats>,
+
+    /// Recording of the renders, if they are being recorded.
+    history: Option<History<G::NodeId>>,
+}
+
+/// Handler of warnings raised while a [`Pipeline`] applies its stylesheet.
+pub type CascadeWarningHandler<'w, T> = Box<dyn FnMut(AttributeTypeMismatch<T>) + 'w>;
+
+impl<'w, G: RootedProgramStateGraph, V: VisTree> Pipeline<'w, G, V> {
+    /// Constructs a pipeline with an empty stylesheet
+    /// that renders a graph into a visualization tree.
+    ///
+    /// Nothing is rendered until [`Pipeline::render`]
+    /// or [`Pipeline::set_stylesheet`] is called.
+    pub fn new(graph: G, vis_tree: V) -> Self {
+        Self {
+            graph,
+            stylesheet: CompiledStylesheet::empty(),
+            lifetimes: LifetimeTracker::new(),
+            tracked: false,
+            writer: VisTreeWriter::new(vis_tree),
+            cascade_warning_handler: None,
+            stats: None,
+            history: None,
+        }
+    }
+
+    /// Adds a handler of values of well-known attributes
+    /// that do not have the required type.
+    ///
+    /// Such values are silently left out without a handler.
+    pub fn set_cascade_warning_handler(
+        &mut self,
+        warning_handler: Option<CascadeWarningHandler<'w, G::NodeId>>,
+    ) {
+        self.cascade_warning_handler = warning_handler;
+    }
+
+    /// Adds a handler of values of well-known attributes
+    /// that do not have the required type.
+    pub fn with_cascade_warning_handler(
+        mut self,
+        warning_handler: CascadeWarningHandler<'w, G::NodeId>,
+    ) -> Self {
+        self.set_cascade_warning_handler(Some(warning_handler));
+        self
+    }
+
+    /// Adds a handler of warnings raised by the renderer.
+    ///
+    /// Shorthand for [`VisTreeWriter::with_warning_handler`].
+    pub fn with_warning_handler(
+        mut self,
+        warning_handler: Box<dyn FnMut(VisTreeWriterWarning<G::NodeId>) + 'w>,
+    ) -> Self {
+        self.writer.set_warning_handler(Some(warning_handler));
+        self
+    }
+
+    /// Enables or disables counting of the work that renders take.
+    ///
+    /// Rules are timed with [`Instant`](std::time::Instant),
+    /// so this must not be enabled on platforms that do not have a clock,
+    /// such as `wasm32-unknown-unknown`.
+    pub fn set_stats_enabled(&mut self, enabled: bool) {
+        self.stats = enabled.then(CascadeStats::new);
+    }
+
+    /// Gets the counters of the last render,
+    /// if [counting](Pipeline::set_stats_enabled) is enabled.
+    pub fn stats(&self) -> Option<&CascadeStats> {
+        self.stats.as_ref()
+    }
+
+    /// Starts or stops recording renders into a [`History`].
+    ///
+    /// Each [render](Pipeline::render) records the graph and the mapping
+    /// it has been resolved to as one step. Swapping the stylesheet
+    /// does not record a step. Starting a recording discards
+    /// the previous one, so it should be [taken](Pipeline::take_history) first.
+    pub fn set_recording_enabled(&mut self, enabled: bool) {
+        self.history = enabled.then(History::new);
+    }
+
+    /// Gets the recording of the renders, if they are being recorded.
+    pub fn history(&self) -> Option<&History<G::NodeId>> {
+        self.history.as_ref()
+    }
+
+    /// Gets the recording of the renders, if they are being recorded, mutably.
+    ///
+    /// This can be used to seek in the recording.
+    pub fn history_mut(&mut self) -> Option<&mut History<G::NodeId>> {
+        self.history.as_mut()
+    }
+
+    /// Stops recording renders and returns the recording.
+    pub fn take_history(&mut self) -> Option<History<G::NodeId>> {
+        self.history.take()
+    }
+
+    /// Gets the visualized state graph.
+    pub fn graph(&self) -> &G {
+        &self.graph
+    }
+
+    /// Gets the visualized state graph, mutably.
+    ///
+    /// Changes made to the graph are only reflected
+    /// in the visualization after [`Pipeline::render`].
+    pub fn graph_mut(&mut self) -> &mut G {
+        &mut self.graph
+    }
+
+    /// Replaces the visualized state graph and renders it.
+    pub fn set_graph(&mut self, graph: G) {
+        self.graph = graph;
+        self.render();
+    }
+
+    /// Gets the stylesheet that the graph is visualized with.
+    pub fn stylesheet(&self) -> &CompiledStylesheet<PropertyKey> {
+        &self.stylesheet
+    }
+
+    /// Replaces the stylesheet and renders the graph again.
+    ///
+    /// The graph is assumed not to have changed since it was last
+    /// rendered, so nodes keep the lifetimes they had then.
+    /// If the graph has not been rendered yet, it is rendered
+    /// like by [`Pipeline::render`].
+    /// Use [`Pipeline::render`] to pick up changes to the graph.
+    ///
+    /// If the stylesheet requires capabilities that the graph
+    /// does not provide, it is rejected and the current stylesheet is kept.
+    pub fn set_stylesheet(
+        &mut self,
+        stylesheet: CompiledStylesheet<PropertyKey>,
+    ) -> Result<(), MissingCapabilities> {
+        stylesheet.check_requirements(&self.graph.capabilities())?;
+        self.stylesheet = stylesheet;
+        if self.tracked {
+            let mapping = resolve(
+                &self.stylesheet,
+                &self.lifetimes.view(&self.graph),
+                &mut self.cascade_warning_handler,
+                &mut self.stats,
+            );
+            self.forward(mapping);
+        } else {
+            self.render();
+        }
+        Ok(())
+    }
+
+    /// Renders the current state graph using the current stylesheet.
+    ///
+    /// Nodes that have been added or removed since the last render
+    /// are tagged with their lifetimes.
+    pub fn render(&mut self) {
+        let mapping = resolve(
+            &self.stylesheet,
+            &self.lifetimes.track(&self.graph),
+            &mut self.cascade_warning_handler,
+            &mut self.stats,
+        );
+        self.tracked = true;
+        if let Some(history) = &mut self.history {
+            history.record(&self.graph, mapping.clone());
+        }
+        self.forward(mapping);
+    }
+
+    /// Updates the state graph and renders it.
+    ///
+    /// This is meant to be called each time the debuggee
+    /// is stopped, such as after it has taken a step, with a function
+    /// that brings the graph up to date with the debuggee.
+    pub fn step(&mut self, update: impl FnOnce(&mut G)) {
+        update(&mut self.graph);
+        self.render();
+    }
+
+    /// Gets the renderer that writes into the visualization tree.
+    pub fn writer(&self) -> &VisTreeWriter<'w, G::NodeId, V> {
+        &self.writer
+    }
+
+    /// Gets the renderer that writes into the visualization tree, mutably.
+    ///
+    /// This can be used to access the visualization tree
+    /// or to set a warning handler.
+    pub fn writer_mut(&mut self) -> &mut VisTreeWriter<'w, G::NodeId, V> {
+        &mut self.writer
+    }
+
+    /// Consumes the pipeline and returns the visualized state graph
+    /// and the visualization tree.
+    pub fn into_parts(self) -> (G, V) {
+        (self.graph, self.writer.reclaim_vis_tree())
+    }
+
+    /// Forwards a property mapping of the current graph to the renderer.
+    fn forward(&mut self, mapping: EntityPropertyMapping<G::NodeId>) {
+        self.writer
+            .update_roots(vec![Selectable::node(self.graph.root())]);
+        self.writer.update(mapping);
+    }
+}
+
+/// Applies a stylesheet to a graph tagged with lifetimes
+/// on behalf of a [`Pipeline`].
+///
+/// Counters, if they are being collected, are reset first,
+/// so they only describe this application.
+fn resolve<G: RootedProgramStateGraph>(
+    stylesheet: &CompiledStylesheet<PropertyKey>,
+    graph: &LifetimeGraph<G>,
+    warning_handler: &mut Option<CascadeWarningHandler<'_, G::NodeId>>,
+    stats: &mut Option<CascadeStats>,
+) -> EntityPropertyMapping<G::NodeId> {
+    if let Some(stats) = stats {
+        *stats = CascadeStats::new();
+    }
+    apply_stylesheet_to_lifetimes(
+        stylesheet,
+        graph,
+        |warning| {
+            if let Some(warning_handler) = warning_handler {
+                warning_handler(warning);
+            }
+        },
+        stats.as_mut(),
+    )
+}
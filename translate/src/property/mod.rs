@@ -1,20 +1,27 @@
 //! Keys and values of properties of visualizable entities.
 
 pub mod symbols;
+pub mod typed;
 
-use aili_model::state::NodeId;
+use aili_model::{name::Name, state::NodeId};
 use aili_style::selectable::Selectable;
 use derive_more::{Debug, From};
 use std::collections::HashMap;
 
 /// A key that values can be assigned to on a selectable entity.
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PropertyKey {
     /// Assigns value to an attribute of the selected entity.
-    Attribute(String),
+    ///
+    /// The name may start with a namespace, such as `svg/stroke`,
+    /// so that attributes meant for a specific renderer
+    /// can be told apart from the others.
+    /// See [`split_namespace`].
+    Attribute(Name),
 
     /// Assigns value to an attribute of a fragment of the selected entity.
-    FragmentAttribute(FragmentKey, String),
+    FragmentAttribute(FragmentKey, Name),
 
     /// Assigns value
     /// Modifies the display mode of the selected entity.
@@ -26,19 +33,58 @@ pub enum PropertyKey {
     /// Modifies the connector target reference of the selected entity.
     Target,
 
-    /// Modifies the detachment mode of the selected entity.
+    /// Modifies whether the selected entity's visualization
+    /// is detached from its parent.
     Detach,
+
+    /// Modifies the stable key of the selected entity.
+    StableKey,
+
+    /// Modifies the layer that the selected entity's visualization is drawn in.
+    Layer,
+
+    /// Modifies whether the selected connector's start and end are swapped,
+    /// so that it starts at its [target](PropertyKey::Target)
+    /// and ends at its [parent](PropertyKey::Parent).
+    Reverse,
+}
+
+/// Separator between the namespace of an attribute and its name,
+/// as in `svg/stroke`.
+pub const NAMESPACE_SEPARATOR: char = '/';
+
+/// Splits the name of an attribute into its namespace, if it has one,
+/// and the rest of the name.
+///
+/// ```
+/// use aili_translate::property::split_namespace;
+///
+/// assert_eq!(split_namespace("svg/stroke"), (Some("svg"), "stroke"));
+/// assert_eq!(split_namespace("stroke"), (None, "stroke"));
+/// ```
+pub fn split_namespace(attribute_name: &str) -> (Option<&str>, &str) {
+    match attribute_name.split_once(NAMESPACE_SEPARATOR) {
+        Some((namespace, name)) => (Some(namespace), name),
+        None => (None, attribute_name),
+    }
+}
+
+/// Joins a namespace and the name of an attribute
+/// into a name of a namespaced attribute.
+pub fn namespaced_name(namespace: &str, attribute_name: &str) -> Name {
+    format!("{namespace}{NAMESPACE_SEPARATOR}{attribute_name}").into()
 }
 
 /// Properties of a visual element, pre-processed to the required form.
 #[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PropertyMap<T: NodeId> {
     /// Attributes with string values.
-    pub attributes: HashMap<String, String>,
+    pub attributes: HashMap<Name, String>,
 
     /// Attributes with string values that belong to fragments
     /// of the visual element, rather than the element as a whole.
-    pub fragment_attributes: HashMap<FragmentKey, HashMap<String, String>>,
+    pub fragment_attributes: HashMap<FragmentKey, HashMap<Name, String>>,
 
     /// Display mode of the entity.
     pub display: Option<DisplayMode>,
@@ -53,6 +99,38 @@ pub struct PropertyMap<T: NodeId> {
     /// of this entity's visualization if [`display`](PropertyMap::display)
     /// is [`Connector`](DisplayMode::Connector).
     pub target: Option<Selectable<T>>,
+
+    /// Key that identifies the entity's visualization across updates.
+    ///
+    /// When set, the renderer reuses the visualization that had
+    /// the same key in the previous update, even if it belonged
+    /// to a different entity. This lets the visualization follow
+    /// a node that has moved elsewhere in the graph.
+    pub stable_key: Option<String>,
+
+    /// Position of an extra entity's visualization
+    /// among the visualizations of extras of the same owner.
+    ///
+    /// Set for extras selected with an index, such as
+    /// `::extra(icon):index(0)`. Extras with lower indices
+    /// are placed before extras with higher indices.
+    pub extra_index: Option<usize>,
+
+    /// Specifies that the entity's visualization should be kept
+    /// out of the visualization tree, even if it has a [parent](PropertyMap::parent).
+    ///
+    /// Detached elements are still rendered, along with their children,
+    /// but they float outside of the tree structure.
+    /// Connectors cannot be detached.
+    pub detached: bool,
+
+    /// Layer that the entity's visualization is drawn in.
+    ///
+    /// Visualizations in higher layers are drawn above those
+    /// in lower layers, regardless of where they are in the tree.
+    /// Visualizations without a layer are in layer zero.
+    /// Only elements have layers, connectors ignore them.
+    pub layer: Option<i32>,
 }
 
 impl<T: NodeId> PropertyMap<T> {
@@ -79,9 +157,49 @@ impl<T: NodeId> PropertyMap<T> {
         self
     }
 
+    /// Adds a stable key to the property map.
+    pub fn with_stable_key(mut self, stable_key: String) -> Self {
+        self.stable_key = Some(stable_key);
+        self
+    }
+
+    /// Adds an extra index to the property map.
+    pub fn with_extra_index(mut self, extra_index: usize) -> Self {
+        self.extra_index = Some(extra_index);
+        self
+    }
+
+    /// Marks the entity as detached.
+    pub fn with_detached(mut self) -> Self {
+        self.detached = true;
+        self
+    }
+
+    /// Adds a layer to the property map.
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = Some(layer);
+        self
+    }
+
+    /// Gets the entity whose visualization the entity's visualization
+    /// should be inserted into or start at, with [detachment](PropertyMap::detached)
+    /// taken into account.
+    pub fn effective_parent(&self) -> Option<&Selectable<T>> {
+        if self.detached && self.display != Some(DisplayMode::Connector) {
+            None
+        } else {
+            self.parent.as_ref()
+        }
+    }
+
     /// Adds an attribute value to the property map.
-    pub fn with_attribute(mut self, attribute_name: String, attribute_value: String) -> Self {
-        self.attributes.insert(attribute_name, attribute_value);
+    pub fn with_attribute(
+        mut self,
+        attribute_name: impl Into<Name>,
+        attribute_value: String,
+    ) -> Self {
+        self.attributes
+            .insert(attribute_name.into(), attribute_value);
         self
     }
 
@@ -89,15 +207,46 @@ impl<T: NodeId> PropertyMap<T> {
     pub fn with_fragment_attribute(
         mut self,
         fragment: FragmentKey,
-        attribute_name: String,
+        attribute_name: impl Into<Name>,
         attribute_value: String,
     ) -> Self {
         self.fragment_attributes
             .entry(fragment)
             .or_default()
-            .insert(attribute_name, attribute_value);
+            .insert(attribute_name.into(), attribute_value);
         self
     }
+
+    /// Gets the attributes in a namespace,
+    /// with the namespace stripped from their names.
+    pub fn namespaced_attributes(&self, namespace: &str) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes
+            .iter()
+            .filter_map(move |(name, value)| match split_namespace(name) {
+                (Some(n), name) if n == namespace => Some((name, value.as_str())),
+                _ => None,
+            })
+    }
+
+    /// Removes all attributes, including those of fragments,
+    /// whose namespaces are not listed.
+    ///
+    /// Attributes without a namespace are kept,
+    /// so that a renderer can drop the attributes meant
+    /// for other renderers, but keep the common ones.
+    pub fn retain_namespaces(&mut self, namespaces: &[&str]) {
+        let is_retained = |name: &Name| {
+            split_namespace(name)
+                .0
+                .is_none_or(|namespace| namespaces.contains(&namespace))
+        };
+        self.attributes.retain(|name, _| is_retained(name));
+        for attributes in self.fragment_attributes.values_mut() {
+            attributes.retain(|name, _| is_retained(name));
+        }
+        self.fragment_attributes
+            .retain(|_, attributes| !attributes.is_empty());
+    }
 }
 
 impl<T: NodeId> Default for PropertyMap<T> {
@@ -108,6 +257,10 @@ impl<T: NodeId> Default for PropertyMap<T> {
             display: None,
             parent: None,
             target: None,
+            stable_key: None,
+            extra_index: None,
+            detached: false,
+            layer: None,
         }
     }
 }
@@ -124,6 +277,18 @@ impl<T: NodeId> std::fmt::Debug for PropertyMap<T> {
         if let Some(target) = &self.target {
             write!(f, "target: {target:?}; ")?;
         }
+        if let Some(stable_key) = &self.stable_key {
+            write!(f, "stable-key: {stable_key:?}; ")?;
+        }
+        if let Some(extra_index) = &self.extra_index {
+            write!(f, "extra-index: {extra_index}; ")?;
+        }
+        if self.detached {
+            write!(f, "detach; ")?;
+        }
+        if let Some(layer) = &self.layer {
+            write!(f, "layer: {layer}; ")?;
+        }
         for (key, value) in &self.attributes {
             write!(f, "{key:?}: {value:?}; ")?;
         }
@@ -139,6 +304,7 @@ impl<T: NodeId> std::fmt::Debug for PropertyMap<T> {
 
 /// Ways to visualize an entity.
 #[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DisplayMode {
     /// Entity is displayed as a connector.
     #[debug("connector")]
@@ -154,6 +320,7 @@ pub enum DisplayMode {
 /// [`PropertyMap`]s can assign attributes not just to whole entities,
 /// but to their fragments as well. These keys identify the known fragment types.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FragmentKey {
     /// The start endpoint of an entity with [`DisplayMode::Connector`].
     ///
@@ -181,6 +348,63 @@ impl<T: NodeId> EntityPropertyMapping<T> {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Counts the entities in the mapping and how they are displayed.
+    pub fn stats(&self) -> MappingStats {
+        let mut stats = MappingStats::default();
+        for (entity, properties) in &self.0 {
+            if entity.is_node() {
+                stats.nodes += 1;
+            } else if entity.is_edge() {
+                stats.edges += 1;
+            } else {
+                stats.extras += 1;
+            }
+            match properties.display {
+                Some(DisplayMode::ElementTag(_)) => stats.elements += 1,
+                Some(DisplayMode::Connector) => stats.connectors += 1,
+                None => {}
+            }
+        }
+        stats
+    }
+
+    /// Removes attributes whose namespaces are not listed
+    /// from all entities in the mapping.
+    ///
+    /// See [`PropertyMap::retain_namespaces`].
+    pub fn retain_namespaces(&mut self, namespaces: &[&str]) {
+        for properties in self.0.values_mut() {
+            properties.retain_namespaces(namespaces);
+        }
+    }
+}
+
+/// Counts of entities in an [`EntityPropertyMapping`],
+/// as reported by [`EntityPropertyMapping::stats`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct MappingStats {
+    /// Number of nodes that have properties.
+    pub nodes: usize,
+
+    /// Number of edges that have properties.
+    pub edges: usize,
+
+    /// Number of extra entities that have properties.
+    pub extras: usize,
+
+    /// Number of entities that are displayed as elements.
+    pub elements: usize,
+
+    /// Number of entities that are displayed as connectors.
+    pub connectors: usize,
+}
+
+impl MappingStats {
+    /// Gets the total number of entities that have properties.
+    pub fn entity_count(&self) -> usize {
+        self.nodes + self.edges + self.extras
+    }
 }
 
 impl<T: NodeId> Default for EntityPropertyMapping<T> {
@@ -188,3 +412,22 @@ impl<T: NodeId> Default for EntityPropertyMapping<T> {
         Self(HashMap::new())
     }
 }
+
+/// Entity property mappings are serialized as sequences of key-value pairs,
+/// since [`Selectable`]s cannot be used as keys in most formats.
+#[cfg(feature = "serde")]
+impl<T: NodeId + serde::Serialize> serde::Serialize for EntityPropertyMapping<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: NodeId + serde::Deserialize<'de>> serde::Deserialize<'de>
+    for EntityPropertyMapping<T>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = Vec::<(Selectable<T>, PropertyMap<T>)>::deserialize(deserializer)?;
+        Ok(Self(entries.into_iter().collect()))
+    }
+}
@@ -1,6 +1,6 @@
 //! Definitions of conversions from [`RawPropertyKey`] to [`PropertyKey`].
 
-use super::{FragmentKey, PropertyKey};
+use super::{FragmentKey, PropertyKey, namespaced_name};
 use aili_style::stylesheet::RawPropertyKey;
 use derive_more::{Display, Error};
 
@@ -21,13 +21,21 @@ pub enum InvalidSymbol {
 /// | `display`                             | [`Display`](PropertyKey::Display)     |
 /// | `parent`                              | [`Parent`](PropertyKey::Parent)       |
 /// | `target`                              | [`Target`](PropertyKey::Target)       |
+/// | `stable-key`                          | [`StableKey`](PropertyKey::StableKey) |
+/// | `detach`                              | [`Detach`](PropertyKey::Detach)       |
+/// | `layer`                               | [`Layer`](PropertyKey::Layer)         |
+/// | `reverse`                             | [`Reverse`](PropertyKey::Reverse)     |
 /// | Other                                 | [`Attribute`](PropertyKey::Attribute) |
 pub fn unquoted_style_key(key: &str) -> PropertyKey {
     match key {
         "display" => PropertyKey::Display,
         "parent" => PropertyKey::Parent,
         "target" => PropertyKey::Target,
-        _ => PropertyKey::Attribute(key.to_owned()),
+        "stable-key" => PropertyKey::StableKey,
+        "detach" => PropertyKey::Detach,
+        "layer" => PropertyKey::Layer,
+        "reverse" => PropertyKey::Reverse,
+        _ => PropertyKey::Attribute(key.into()),
     }
 }
 
@@ -51,10 +59,12 @@ impl TryFrom<RawPropertyKey> for PropertyKey {
     fn try_from(value: RawPropertyKey) -> Result<Self, Self::Error> {
         match value {
             RawPropertyKey::Property(p) => Ok(unquoted_style_key(&p)),
-            RawPropertyKey::QuotedProperty(p) => Ok(PropertyKey::Attribute(p)),
-            RawPropertyKey::FragmentProperty(f, p) => {
-                Ok(PropertyKey::FragmentAttribute(fragment_key(&f)?, p))
-            }
+            RawPropertyKey::QuotedProperty(p) => Ok(PropertyKey::Attribute(p.into())),
+            // Prefixes that are not fragment names are namespaces
+            RawPropertyKey::FragmentProperty(f, p) => match fragment_key(&f) {
+                Ok(fragment) => Ok(PropertyKey::FragmentAttribute(fragment, p.into())),
+                Err(_) => Ok(PropertyKey::Attribute(namespaced_name(&f, &p))),
+            },
         }
     }
 }
@@ -0,0 +1,226 @@
+//! Types of values of well-known attributes.
+//!
+//! Attributes are passed to the renderer as strings.
+//! Attributes that the renderer interprets in a specific way,
+//! such as sizes or colors, are validated and normalized
+//! during the cascade, so that every renderer receives them
+//! in the same canonical form.
+
+use super::FragmentKey;
+use aili_model::state::{NodeId, NodeValue};
+use aili_style::{selectable::Selectable, values::PropertyValue};
+use derive_more::Display;
+
+/// Kind of values that a well-known attribute accepts.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+pub enum AttributeType {
+    /// Non-negative number, such as a width or a size.
+    ///
+    /// Normalized to its shortest decimal representation.
+    #[display("a non-negative number")]
+    Length,
+
+    /// Integer, such as the order of an element among its siblings.
+    #[display("an integer")]
+    Integer,
+
+    /// Boolean flag, normalized to `true` or `false`.
+    #[display("a boolean")]
+    Bool,
+
+    /// Color, either a named color, a hexadecimal code,
+    /// or a color function such as `rgb(...)`.
+    ///
+    /// Normalized to lowercase.
+    #[display("a color")]
+    Color,
+
+    /// One of a fixed set of keywords.
+    #[display("one of {}", _0.join(", "))]
+    Keyword(&'static [&'static str]),
+}
+
+/// Gets the type of a well-known attribute of an entity.
+///
+/// Attributes that are not well-known accept any value.
+///
+/// ## Well-Known Attributes
+/// | Attribute name                                                                   | Type                                  |
+/// |----------------------------------------------------------------------------------|---------------------------------------|
+/// | `stroke-width`, `size`, `padding`, `gap`                                         | [`Length`](AttributeType::Length)     |
+/// | `order`                                                                          | [`Integer`](AttributeType::Integer)   |
+/// | `checked`, `order-children`                                                      | [`Bool`](AttributeType::Bool)         |
+/// | `color`, `fill`, `stroke`                                                        | [`Color`](AttributeType::Color)       |
+/// | `stroke-style`, `shape`, `direction`, `align-items`, `layout`, `hat`, alignments | [`Keyword`](AttributeType::Keyword)   |
+pub fn attribute_type(name: &str) -> Option<AttributeType> {
+    use AttributeType::*;
+    match name {
+        "stroke-width" | "size" | "padding" | "gap" => Some(Length),
+        "order" => Some(Integer),
+        "checked" | "order-children" => Some(Bool),
+        "color" | "fill" | "stroke" => Some(Color),
+        "stroke-style" => Some(Keyword(&["solid", "dashed", "dotted"])),
+        "shape" => Some(Keyword(&[
+            "square",
+            "circle",
+            "rounded",
+            "straight",
+            "quadratic",
+            "cubic",
+        ])),
+        "direction" => Some(Keyword(&[
+            "row", "column", "north", "east", "south", "west",
+        ])),
+        "align-items" | "vertical-justify" | "horizontal-justify" => {
+            Some(Keyword(&["start", "center", "end"]))
+        }
+        "vertical-align" | "horizontal-align" => Some(Keyword(&["inside", "outside", "middle"])),
+        "hat" => Some(Keyword(&["none", "north", "east", "south", "west"])),
+        "layout" => Some(Keyword(&[
+            "unoriented",
+            "layered",
+            "gv-dot",
+            "gv-neato",
+            "gv-circo",
+            "gv-twopi",
+            "gv-fdp",
+        ])),
+        _ => None,
+    }
+}
+
+/// Gets the type of a well-known attribute of a fragment.
+///
+/// Attributes that are not well-known accept any value.
+///
+/// ## Well-Known Attributes
+/// | Attribute name | Type                                |
+/// |----------------|-------------------------------------|
+/// | `anchor`       | [`Keyword`](AttributeType::Keyword) |
+/// | `decoration`   | [`Keyword`](AttributeType::Keyword) |
+pub fn fragment_attribute_type(name: &str) -> Option<AttributeType> {
+    use AttributeType::*;
+    match name {
+        "anchor" => Some(Keyword(&[
+            "auto",
+            "north",
+            "south",
+            "east",
+            "west",
+            "northeast",
+            "northwest",
+            "southeast",
+            "southwest",
+        ])),
+        "decoration" => Some(Keyword(&["none", "square", "circle", "arrow"])),
+        _ => None,
+    }
+}
+
+impl AttributeType {
+    /// Validates a value and converts it to its canonical form.
+    ///
+    /// Returns [`None`] if the value does not have the type.
+    /// [Selections](PropertyValue::Selection) never have any type,
+    /// they must be resolved to the values of the selected nodes first.
+    pub fn normalize<T: NodeId>(self, value: &PropertyValue<T>) -> Option<String> {
+        match self {
+            Self::Length => as_number(value)
+                .filter(|number| *number >= 0.0)
+                .map(format_number),
+            Self::Integer => match value {
+                PropertyValue::Value(NodeValue::Int(i)) => Some(i.to_string()),
+                PropertyValue::Value(NodeValue::Uint(u)) => Some(u.to_string()),
+                PropertyValue::Value(NodeValue::Float(f)) if f.is_finite() && f.fract() == 0.0 => {
+                    Some(format_number(*f))
+                }
+                PropertyValue::String(s) => s.trim().parse::<i64>().ok().map(|i| i.to_string()),
+                _ => None,
+            },
+            Self::Bool => match value {
+                PropertyValue::Value(NodeValue::Bool(b)) => Some(b.to_string()),
+                PropertyValue::String(s) => match s.trim() {
+                    s @ ("true" | "false") => Some(s.to_owned()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            Self::Color => match value {
+                PropertyValue::String(s) => {
+                    Some(s.trim().to_ascii_lowercase()).filter(|s| is_color(s))
+                }
+                _ => None,
+            },
+            Self::Keyword(keywords) => match value {
+                PropertyValue::String(s) => keywords
+                    .iter()
+                    .find(|keyword| **keyword == s.trim())
+                    .map(|keyword| (*keyword).to_owned()),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Describes a value assigned to a well-known attribute
+/// that does not have the type the attribute requires.
+///
+/// Such values are left out of the resulting
+/// [`EntityPropertyMapping`](super::EntityPropertyMapping).
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display(
+    "attribute '{}{attribute}' of {entity:?} must be {expected}, but it is '{value}'",
+    fragment.map(|fragment| format!("{fragment:?}/")).unwrap_or_default()
+)]
+pub struct AttributeTypeMismatch<T: NodeId> {
+    /// The entity whose attribute was assigned.
+    pub entity: Selectable<T>,
+
+    /// The fragment whose attribute was assigned,
+    /// if the attribute belongs to a fragment.
+    pub fragment: Option<FragmentKey>,
+
+    /// Name of the attribute.
+    pub attribute: String,
+
+    /// Type of values the attribute accepts.
+    pub expected: AttributeType,
+
+    /// The value that was assigned, converted to a string.
+    pub value: String,
+}
+
+/// Converts a value to a finite number, if it represents one.
+fn as_number<T: NodeId>(value: &PropertyValue<T>) -> Option<f64> {
+    match value {
+        PropertyValue::Value(NodeValue::Int(i)) => Some(*i as f64),
+        PropertyValue::Value(NodeValue::Uint(u)) => Some(*u as f64),
+        PropertyValue::Value(NodeValue::Float(f)) => Some(*f),
+        PropertyValue::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+    .filter(|number: &f64| number.is_finite())
+}
+
+/// Formats a number in its shortest decimal representation.
+fn format_number(number: f64) -> String {
+    // Negative zero would otherwise keep its sign
+    if number == 0.0 {
+        "0".to_owned()
+    } else {
+        number.to_string()
+    }
+}
+
+/// Checks whether a lowercase string is a color.
+fn is_color(value: &str) -> bool {
+    if let Some(hex) = value.strip_prefix('#') {
+        matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    } else if let Some((function, arguments)) = value.split_once('(') {
+        !function.is_empty()
+            && function.chars().all(|c| c.is_ascii_alphabetic())
+            && arguments.ends_with(')')
+    } else {
+        !value.is_empty() && value.chars().all(|c| c.is_ascii_alphabetic())
+    }
+}
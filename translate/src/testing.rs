@@ -0,0 +1,467 @@
+//! Utilities for testing stylesheets.
+//!
+//! Instead of constructing whole [`EntityPropertyMapping`]s by hand,
+//! tests can list only the properties they are interested in
+//! using [`assert_styles!`](crate::assert_styles). Mappings that do not
+//! match the expectations are reported as readable [`MappingDiff`]s.
+//!
+//! Properties are named the way stylesheets name them.
+//! `display`, `parent`, `target`, `stable-key`, `extra-index`, `detach`, and `layer`
+//! refer to the respective
+//! properties, attributes of fragments are prefixed with the name
+//! of the fragment, such as `start/label`, and everything else
+//! refers to an attribute. Display modes are written as their names
+//! and entities are written as their [`Debug`] representations.
+//!
+//! ```
+//! use aili_model::state::RootedProgramStateGraph;
+//! use aili_style::cascade::CascadeStyle;
+//! use aili_translate::{assert_styles, property::PropertyKey};
+//!
+//! fn root_is_a_cell(
+//!     stylesheet: &CascadeStyle<PropertyKey>,
+//!     graph: &impl RootedProgramStateGraph<NodeId = usize>,
+//! ) {
+//!     assert_styles!(stylesheet, *graph, {
+//!         node(0) => { "display" == "cell", "value" == 42 },
+//!     });
+//! }
+//! ```
+//!
+//! What the renderer makes of the styles can be tested with a [`RenderHarness`],
+//! which renders a graph into a [`MemoryVisTree`] and compares
+//! its [description](MemoryVisTree::describe) to a snapshot.
+//! Snapshots stay valid as long as the rendered scene stays the same,
+//! regardless of the order in which the renderer has built it.
+//!
+//! ```
+//! use aili_model::graph;
+//! use aili_style::{
+//!     cascade::CompiledStylesheet,
+//!     stylesheet::{StyleKey::*, expression::*, selector::*, *},
+//! };
+//! use aili_translate::{property::PropertyKey::*, testing::RenderHarness};
+//!
+//! // :: { display: cell; value: 42; }
+//! let stylesheet = CompiledStylesheet::compile(Stylesheet::new(vec![StyleRule {
+//!     selector: Selector::default(),
+//!     properties: vec![
+//!         StyleClause {
+//!             key: Property(Display),
+//!             value: Expression::String("cell".to_owned()),
+//!         },
+//!         StyleClause {
+//!             key: Property(Attribute("value".into())),
+//!             value: Expression::Int(42),
+//!         },
+//!     ],
+//! }]));
+//! let harness = RenderHarness::new(graph! {}, stylesheet);
+//! harness.assert_snapshot(r#"
+//!     #0 cell value="42"
+//! "#);
+//! ```
+
+use crate::{
+    pipeline::Pipeline,
+    property::{DisplayMode, EntityPropertyMapping, PropertyKey, PropertyMap},
+};
+use aili_model::{
+    state::{EdgeLabel, NodeId, RootedProgramStateGraph},
+    vis::memory::MemoryVisTree,
+};
+use aili_style::{cascade::CompiledStylesheet, selectable::Selectable};
+use derive_more::Display;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Shorthand for [`Selectable::node`].
+pub fn node<T: NodeId>(node_id: T) -> Selectable<T> {
+    Selectable::node(node_id)
+}
+
+/// Shorthand for [`Selectable::edge`].
+pub fn edge<T: NodeId>(node_id: T, edge_label: EdgeLabel) -> Selectable<T> {
+    Selectable::edge(node_id, edge_label)
+}
+
+/// Value that a property is expected to have.
+pub trait ExpectedValue {
+    /// Converts the value to the form in which it appears
+    /// in a flattened property map.
+    fn to_expected_string(&self) -> String;
+}
+
+macro_rules! expected_value_by_display {
+    ( $( $ty:ty ),* ) => {
+        $(
+            impl ExpectedValue for $ty {
+                fn to_expected_string(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+expected_value_by_display!(str, String, bool, i32, i64, u32, u64, usize, f64);
+
+impl<T: NodeId> ExpectedValue for Selectable<T> {
+    fn to_expected_string(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+impl<V: ExpectedValue + ?Sized> ExpectedValue for &V {
+    fn to_expected_string(&self) -> String {
+        (**self).to_expected_string()
+    }
+}
+
+/// Single property whose value is different than expected.
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display(
+    "{entity:?} {property}: expected {}, found {}",
+    describe_value(expected),
+    describe_value(actual)
+)]
+pub struct PropertyDifference<T: NodeId> {
+    /// The entity whose property is different.
+    pub entity: Selectable<T>,
+
+    /// Name of the property.
+    pub property: String,
+
+    /// The expected value, if the property is expected to be set.
+    pub expected: Option<String>,
+
+    /// The actual value, if the property is set.
+    pub actual: Option<String>,
+}
+
+/// Differences between the expected and actual styles of entities.
+///
+/// Differences are ordered by entity and property name,
+/// so they are printed in a stable order.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MappingDiff<T: NodeId>(pub Vec<PropertyDifference<T>>);
+
+impl<T: NodeId> MappingDiff<T> {
+    /// Checks whether there are no differences.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Sorts differences into their stable order.
+    fn sorted(mut self) -> Self {
+        self.0.sort_by_cached_key(|difference| {
+            (
+                format!("{:?}", difference.entity),
+                difference.property.clone(),
+            )
+        });
+        self
+    }
+}
+
+impl<T: NodeId> std::fmt::Display for MappingDiff<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for difference in &self.0 {
+            writeln!(f, "{difference}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Expected values of selected properties of selected entities.
+///
+/// Properties and entities that are not mentioned
+/// are not checked.
+pub struct ExpectedStyles<T: NodeId>(HashMap<Selectable<T>, BTreeMap<String, String>>);
+
+impl<T: NodeId> ExpectedStyles<T> {
+    /// Constructs an empty set of expectations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an expected value of a property of an entity.
+    pub fn with_property(
+        mut self,
+        entity: Selectable<T>,
+        property: &str,
+        value: impl ExpectedValue,
+    ) -> Self {
+        self.0
+            .entry(entity)
+            .or_default()
+            .insert(property.to_owned(), value.to_expected_string());
+        self
+    }
+
+    /// Compares the expectations to a mapping.
+    pub fn diff(&self, actual: &EntityPropertyMapping<T>) -> MappingDiff<T> {
+        let mut differences = Vec::new();
+        for (entity, expected_properties) in &self.0 {
+            let actual_properties = actual
+                .0
+                .get(entity)
+                .map(flatten_properties)
+                .unwrap_or_default();
+            for (property, expected) in expected_properties {
+                let actual = actual_properties.get(property);
+                if actual != Some(expected) {
+                    differences.push(PropertyDifference {
+                        entity: entity.clone(),
+                        property: property.clone(),
+                        expected: Some(expected.clone()),
+                        actual: actual.cloned(),
+                    });
+                }
+            }
+        }
+        MappingDiff(differences).sorted()
+    }
+
+    /// Panics with a readable diff if a mapping
+    /// does not match the expectations.
+    #[track_caller]
+    pub fn assert_matches(&self, actual: &EntityPropertyMapping<T>) {
+        let diff = self.diff(actual);
+        assert!(diff.is_empty(), "styles do not match expectations:\n{diff}");
+    }
+}
+
+impl<T: NodeId> Default for ExpectedStyles<T> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+/// Compares two mappings property by property.
+pub fn diff_mappings<T: NodeId>(
+    expected: &EntityPropertyMapping<T>,
+    actual: &EntityPropertyMapping<T>,
+) -> MappingDiff<T> {
+    let mut differences = Vec::new();
+    let entities = expected.0.keys().chain(actual.0.keys());
+    for entity in entities.collect::<HashSet<_>>() {
+        let expected = expected
+            .0
+            .get(entity)
+            .map(flatten_properties)
+            .unwrap_or_default();
+        let actual = actual
+            .0
+            .get(entity)
+            .map(flatten_properties)
+            .unwrap_or_default();
+        let properties = expected.keys().chain(actual.keys());
+        for property in properties.collect::<HashSet<_>>() {
+            if expected.get(property) != actual.get(property) {
+                differences.push(PropertyDifference {
+                    entity: entity.clone(),
+                    property: property.clone(),
+                    expected: expected.get(property).cloned(),
+                    actual: actual.get(property).cloned(),
+                });
+            }
+        }
+    }
+    MappingDiff(differences).sorted()
+}
+
+/// Flattens a property map into pairs of property names and values.
+///
+/// See the [module-level documentation](self) for how properties are named.
+pub fn flatten_properties<T: NodeId>(properties: &PropertyMap<T>) -> BTreeMap<String, String> {
+    let mut flat = BTreeMap::new();
+    if let Some(display) = &properties.display {
+        let name = match display {
+            DisplayMode::Connector => "connector".to_owned(),
+            DisplayMode::ElementTag(tag_name) => tag_name.clone(),
+        };
+        flat.insert("display".to_owned(), name);
+    }
+    if let Some(parent) = &properties.parent {
+        flat.insert("parent".to_owned(), format!("{parent:?}"));
+    }
+    if let Some(target) = &properties.target {
+        flat.insert("target".to_owned(), format!("{target:?}"));
+    }
+    if let Some(stable_key) = &properties.stable_key {
+        flat.insert("stable-key".to_owned(), stable_key.clone());
+    }
+    if let Some(extra_index) = &properties.extra_index {
+        flat.insert("extra-index".to_owned(), extra_index.to_string());
+    }
+    if properties.detached {
+        flat.insert("detach".to_owned(), "true".to_owned());
+    }
+    if let Some(layer) = &properties.layer {
+        flat.insert("layer".to_owned(), layer.to_string());
+    }
+    flat.extend(
+        properties
+            .attributes
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone())),
+    );
+    for (fragment, attributes) in &properties.fragment_attributes {
+        flat.extend(
+            attributes
+                .iter()
+                .map(|(name, value)| (format!("{fragment:?}/{name}"), value.clone())),
+        );
+    }
+    flat
+}
+
+/// Renders a state graph into a [`MemoryVisTree`]
+/// and checks the result against snapshots.
+///
+/// The graph and the stylesheet can be changed between snapshots,
+/// which exercises the renderer's updates of an existing scene,
+/// such as moving elements to other parents and retargeting connectors.
+///
+/// See the [module-level documentation](self) for an example.
+pub struct RenderHarness<G: RootedProgramStateGraph> {
+    pipeline: Pipeline<'static, G, MemoryVisTree>,
+}
+
+impl<G: RootedProgramStateGraph> RenderHarness<G> {
+    /// Renders a graph with a stylesheet.
+    ///
+    /// ## Panics
+    /// Panics if the stylesheet requires capabilities
+    /// that the graph does not provide.
+    #[track_caller]
+    pub fn new(graph: G, stylesheet: CompiledStylesheet<PropertyKey>) -> Self {
+        let mut harness = Self {
+            pipeline: Pipeline::new(graph, MemoryVisTree::new()),
+        };
+        harness.set_stylesheet(stylesheet);
+        harness
+    }
+
+    /// Replaces the graph and renders it.
+    pub fn set_graph(&mut self, graph: G) {
+        self.pipeline.set_graph(graph);
+    }
+
+    /// Replaces the stylesheet and renders the graph again.
+    ///
+    /// ## Panics
+    /// Panics if the stylesheet requires capabilities
+    /// that the graph does not provide.
+    #[track_caller]
+    pub fn set_stylesheet(&mut self, stylesheet: CompiledStylesheet<PropertyKey>) {
+        if let Err(missing) = self.pipeline.set_stylesheet(stylesheet) {
+            panic!("stylesheet cannot be rendered: {missing}");
+        }
+    }
+
+    /// Gets the pipeline that renders the graph,
+    /// which gives access to the graph and the renderer.
+    pub fn pipeline_mut(&mut self) -> &mut Pipeline<'static, G, MemoryVisTree> {
+        &mut self.pipeline
+    }
+
+    /// Gets the visualization tree that the graph has been rendered into.
+    pub fn vis_tree(&self) -> &MemoryVisTree {
+        self.pipeline.writer().vis_tree()
+    }
+
+    /// Describes the visualization tree for comparison with a snapshot.
+    pub fn snapshot(&self) -> String {
+        self.vis_tree().describe()
+    }
+
+    /// Panics with both descriptions if the visualization tree
+    /// does not match a snapshot.
+    ///
+    /// The snapshot is [normalized](normalize_snapshot) first,
+    /// so it can be indented to match the surrounding code.
+    #[track_caller]
+    pub fn assert_snapshot(&self, expected: &str) {
+        let expected = normalize_snapshot(expected);
+        let actual = self.snapshot();
+        assert!(
+            expected == actual,
+            "visualization tree does not match snapshot:\n\
+            --- expected\n{expected}\
+            --- actual\n{actual}"
+        );
+    }
+}
+
+/// Removes the indentation that all lines of a snapshot share,
+/// along with blank lines at its start and end,
+/// so the snapshot takes the form of a [`MemoryVisTree::describe`] description.
+pub fn normalize_snapshot(snapshot: &str) -> String {
+    let lines = snapshot
+        .lines()
+        .map(str::trim_end)
+        .skip_while(|line| line.is_empty())
+        .collect::<Vec<_>>();
+    let end = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .map_or(0, |i| i + 1);
+    let lines = &lines[..end];
+    let indent = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| format!("{}\n", line.get(indent..).unwrap_or_default()))
+        .collect()
+}
+
+/// Describes a value of a property in a [`PropertyDifference`].
+fn describe_value(value: &Option<String>) -> String {
+    match value {
+        Some(value) => format!("{value:?}"),
+        None => "nothing".to_owned(),
+    }
+}
+
+/// Applies a stylesheet to a graph and checks selected properties
+/// of selected entities in the result.
+///
+/// Entities are listed as expressions that evaluate to [`Selectable`]s.
+/// The [`node`] and [`edge`] shorthands can be used without importing them.
+/// Each entity lists names of properties and their expected values,
+/// which may be anything that implements [`ExpectedValue`].
+///
+/// The stylesheet is passed by reference
+/// and the graph is borrowed by the macro.
+///
+/// See the [module-level documentation](self) for an example.
+#[macro_export]
+macro_rules! assert_styles {
+    (
+        $stylesheet:expr, $graph:expr, {
+            $( $entity:expr => { $( $property:literal == $value:expr ),* $(,)? } ),* $(,)?
+        } $(,)?
+    ) => {{
+        #[allow(unused_imports)]
+        use $crate::testing::{edge, node};
+        let mapping = $crate::cascade::apply_stylesheet($stylesheet, &$graph);
+        $crate::testing::ExpectedStyles::new()
+            $( $( .with_property($entity, $property, $value) )* )*
+            .assert_matches(&mapping);
+    }};
+}
+
+/// Checks that two mappings are equal,
+/// and panics with a readable diff if they are not.
+#[macro_export]
+macro_rules! assert_mappings_eq {
+    ( $expected:expr, $actual:expr $(,)? ) => {{
+        let diff = $crate::testing::diff_mappings(&$expected, &$actual);
+        assert!(diff.is_empty(), "mappings are not equal:\n{diff}");
+    }};
+}
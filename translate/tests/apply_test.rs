@@ -2,16 +2,24 @@
 
 mod test_graph;
 
-use aili_model::state::EdgeLabel;
+use aili_model::{
+    erased::{DynNodeId, DynStateGraph},
+    state::EdgeLabel,
+};
 use aili_style::{
-    cascade::CascadeStyle,
+    cascade::{CascadeContext, CascadeStyle},
     selectable::Selectable,
     stylesheet::{StyleKey::*, expression::*, selector::*, *},
 };
 use aili_translate::{
-    cascade::apply_stylesheet,
-    property::{PropertyKey::*, *},
+    cascade::{
+        CascadeStats, LifetimeTracker, TraversalBudget, TraversalMode, apply_stylesheet,
+        apply_stylesheet_tracked, apply_stylesheet_with_budget, apply_stylesheet_with_context,
+        apply_stylesheet_with_mode, apply_stylesheet_with_stats, apply_stylesheet_with_warnings,
+    },
+    property::{PropertyKey::*, typed::*, *},
 };
+use std::collections::HashMap;
 use test_graph::TestGraph;
 
 #[test]
@@ -19,16 +27,16 @@ fn apply_stylesheet_with_one_rule() {
     // .many(*) "a" {
     //   value: 42;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::from_path(
             [
                 SelectorSegment::anything_any_number_of_times(),
-                SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
+                SelectorSegment::Match(EdgeMatcher::Named("a".into())),
             ]
             .into(),
         ),
         properties: vec![StyleClause {
-            key: Property(Attribute("value".to_owned())),
+            key: Property(Attribute("value".into())),
             value: Expression::Int(42),
         }],
     }]));
@@ -56,7 +64,7 @@ fn apply_stylesheet_with_multiple_rules() {
     //   value: "kvt";
     //   title: 42;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::from_path(
                 [
@@ -66,7 +74,7 @@ fn apply_stylesheet_with_multiple_rules() {
                 .into(),
             ),
             properties: vec![StyleClause {
-                key: Property(Attribute("value".to_owned())),
+                key: Property(Attribute("value".into())),
                 value: Expression::String("cell".to_owned()),
             }],
         },
@@ -82,11 +90,11 @@ fn apply_stylesheet_with_multiple_rules() {
             ),
             properties: vec![
                 StyleClause {
-                    key: Property(Attribute("value".to_owned())),
+                    key: Property(Attribute("value".into())),
                     value: Expression::String("kvt".to_owned()),
                 },
                 StyleClause {
-                    key: Property(Attribute("title".to_owned())),
+                    key: Property(Attribute("title".into())),
                     value: Expression::Int(42),
                 },
             ],
@@ -120,7 +128,7 @@ fn select_extra_entity() {
     // :: main next::extra(abc) {
     //   display: "kvt";
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into())
                 .with_extra("".to_owned()),
@@ -168,12 +176,12 @@ fn select_extra_entity() {
 #[test]
 fn select_edge() {
     // .many(*).if(@("a"#0))::edge { }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::from_path(
             [
                 SelectorSegment::anything_any_number_of_times(),
                 SelectorSegment::Condition(Expression::Select(
-                    LimitedSelector::from_path([EdgeLabel::Named("a".to_owned(), 0).into()]).into(),
+                    LimitedSelector::from_path([EdgeLabel::Named("a".into(), 0).into()]).into(),
                 )),
             ]
             .into(),
@@ -193,14 +201,14 @@ fn select_edge() {
                 .with_target(Selectable::node(1)),
         ),
         (
-            Selectable::edge(0, EdgeLabel::Named("a".to_owned(), 0)),
+            Selectable::edge(0, EdgeLabel::Named("a".into(), 0)),
             PropertyMap::new()
                 .with_display(DisplayMode::Connector)
                 .with_parent(Selectable::node(0))
                 .with_target(Selectable::node(5)),
         ),
         (
-            Selectable::edge(1, EdgeLabel::Named("a".to_owned(), 0)),
+            Selectable::edge(1, EdgeLabel::Named("a".into(), 0)),
             PropertyMap::new()
                 .with_display(DisplayMode::Connector)
                 .with_parent(Selectable::node(1))
@@ -214,7 +222,7 @@ fn select_edge() {
                 .with_target(Selectable::node(3)),
         ),
         (
-            Selectable::edge(5, EdgeLabel::Named("a".to_owned(), 0)),
+            Selectable::edge(5, EdgeLabel::Named("a".into(), 0)),
             PropertyMap::new()
                 .with_display(DisplayMode::Connector)
                 .with_parent(Selectable::node(5))
@@ -258,7 +266,7 @@ fn coerce_values() {
     //   value: @;
     //   display: @([0]);
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::default(),
             properties: vec![
@@ -276,11 +284,11 @@ fn coerce_values() {
         },
         StyleRule {
             selector: Selector::from_path(
-                [SelectorSegment::Match(EdgeMatcher::Named("a".to_owned()))].into(),
+                [SelectorSegment::Match(EdgeMatcher::Named("a".into()))].into(),
             ),
             properties: vec![
                 StyleClause {
-                    key: Property(Attribute("value".to_owned())),
+                    key: Property(Attribute("value".into())),
                     value: Expression::Select(LimitedSelector::default().into()),
                 },
                 StyleClause {
@@ -328,7 +336,7 @@ fn save_variable_at_root() {
     // :: main {
     //   parent: --root;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
@@ -369,7 +377,7 @@ fn variable_assignment_sequential_consistency() {
     //   --i: --i + 2;
     //   c: --i;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::default(),
         properties: vec![
             StyleClause {
@@ -377,7 +385,7 @@ fn variable_assignment_sequential_consistency() {
                 value: Expression::Int(0),
             },
             StyleClause {
-                key: Property(Attribute("a".to_owned())),
+                key: Property(Attribute("a".into())),
                 value: Expression::Variable("--i".to_owned()),
             },
             StyleClause {
@@ -389,7 +397,7 @@ fn variable_assignment_sequential_consistency() {
                 ),
             },
             StyleClause {
-                key: Property(Attribute("b".to_owned())),
+                key: Property(Attribute("b".into())),
                 value: Expression::Variable("--i".to_owned()),
             },
             StyleClause {
@@ -401,7 +409,7 @@ fn variable_assignment_sequential_consistency() {
                 ),
             },
             StyleClause {
-                key: Property(Attribute("c".to_owned())),
+                key: Property(Attribute("c".into())),
                 value: Expression::Variable("--i".to_owned()),
             },
         ],
@@ -442,7 +450,7 @@ fn max_depth_using_variables() {
     //   value: --depth;
     //   --depth: --depth + 1;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
@@ -470,7 +478,7 @@ fn max_depth_using_variables() {
             ),
             properties: vec![
                 StyleClause {
-                    key: Property(Attribute("value".to_owned())),
+                    key: Property(Attribute("value".into())),
                     value: Expression::Variable("--depth".to_owned()),
                 },
                 StyleClause {
@@ -508,7 +516,7 @@ fn index_edge_magic_variables() {
     // .many(*).if(isset(--INDEX)) {
     //   value: --INDEX;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::from_path(
             [
                 SelectorSegment::anything_any_number_of_times(),
@@ -520,7 +528,7 @@ fn index_edge_magic_variables() {
             .into(),
         ),
         properties: vec![StyleClause {
-            key: Property(Attribute("value".to_owned())),
+            key: Property(Attribute("value".into())),
             value: Expression::MagicVariable(MagicVariableKey::EdgeIndex),
         }],
     }]));
@@ -548,7 +556,7 @@ fn named_edge_magic_variables() {
     // .many(*).if((isset(--NAME) && --NAME) != "a" || --DISCRIMINATOR) {
     //   value: --NAME + --DISCRIMINATOR;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::from_path(
             [
                 SelectorSegment::anything_any_number_of_times(),
@@ -575,7 +583,7 @@ fn named_edge_magic_variables() {
             .into(),
         ),
         properties: vec![StyleClause {
-            key: Property(Attribute("value".to_owned())),
+            key: Property(Attribute("value".into())),
             value: Expression::BinaryOperator(
                 Expression::MagicVariable(MagicVariableKey::EdgeName).into(),
                 BinaryOperator::Plus,
@@ -614,7 +622,7 @@ fn select_expressions_in_edge_rule() {
     //   parent: --root;
     //   target: @(next);
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
@@ -665,7 +673,7 @@ fn select_expressions_in_extra_rule() {
     // :: main::edge::extra {
     //   parent: @;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::default().with_extra("".to_owned()),
             properties: vec![StyleClause {
@@ -715,18 +723,18 @@ fn rule_precedence_in_declaration_order() {
     // .many(*) "b" {
     //   value: graph;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::from_path(
                 [
-                    SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
+                    SelectorSegment::Match(EdgeMatcher::Named("a".into())),
                     SelectorSegment::anything_any_number_of_times(),
                     SelectorSegment::Match(EdgeLabel::Deref.into()),
                 ]
                 .into(),
             ),
             properties: vec![StyleClause {
-                key: Property(Attribute("value".to_owned())),
+                key: Property(Attribute("value".into())),
                 value: Expression::String("cell".to_owned()),
             }],
         },
@@ -737,12 +745,12 @@ fn rule_precedence_in_declaration_order() {
                     SelectorSegment::AnyNumberOfTimes(
                         [SelectorSegment::Match(EdgeLabel::Next.into())].into(),
                     ),
-                    SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
+                    SelectorSegment::Match(EdgeMatcher::Named("a".into())),
                 ]
                 .into(),
             ),
             properties: vec![StyleClause {
-                key: Property(Attribute("value".to_owned())),
+                key: Property(Attribute("value".into())),
                 value: Expression::String("kvt".to_owned()),
             }],
         },
@@ -750,12 +758,12 @@ fn rule_precedence_in_declaration_order() {
             selector: Selector::from_path(
                 [
                     SelectorSegment::anything_any_number_of_times(),
-                    SelectorSegment::Match(EdgeMatcher::Named("b".to_owned())),
+                    SelectorSegment::Match(EdgeMatcher::Named("b".into())),
                 ]
                 .into(),
             ),
             properties: vec![StyleClause {
-                key: Property(Attribute("value".to_owned())),
+                key: Property(Attribute("value".into())),
                 value: Expression::String("graph".to_owned()),
             }],
         },
@@ -803,7 +811,7 @@ fn variable_sequential_consistency_across_rules() {
     // :: {
     //   --b: b;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
@@ -890,7 +898,7 @@ fn variable_scopes_with_edge_and_extra_matchers() {
     //   --a: a;
     // }
     let value_assignment = StyleClause {
-        key: Property(Attribute("value".to_owned())),
+        key: Property(Attribute("value".into())),
         value: Expression::BinaryOperator(
             Expression::BinaryOperator(
                 Expression::BinaryOperator(
@@ -912,7 +920,7 @@ fn variable_scopes_with_edge_and_extra_matchers() {
             Expression::Variable("--e".to_owned()).into(),
         ),
     };
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
             properties: vec![value_assignment.clone()],
@@ -1013,11 +1021,11 @@ fn assigning_unset_erases_property() {
     //   display: none;
     //   parent: none;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::default(),
         properties: vec![
             StyleClause {
-                key: Property(Attribute("value".to_owned())),
+                key: Property(Attribute("value".into())),
                 value: Expression::Unset.to_owned(),
             },
             StyleClause {
@@ -1047,7 +1055,7 @@ fn latter_property_assignments_take_priority() {
     //   display: connector;
     //   display: none;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::default(),
         properties: vec![
             StyleClause {
@@ -1075,7 +1083,7 @@ fn automatic_node_parent_assignment() {
     // :: .alt(main, main "a", "a", "a" ref "a") {
     //   display: cell;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
@@ -1089,14 +1097,14 @@ fn automatic_node_parent_assignment() {
                     [SelectorSegment::Match(EdgeLabel::Main.into())].into(),
                     [
                         SelectorSegment::Match(EdgeLabel::Main.into()),
-                        SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
+                        SelectorSegment::Match(EdgeMatcher::Named("a".into())),
                     ]
                     .into(),
-                    [SelectorSegment::Match(EdgeMatcher::Named("a".to_owned()))].into(),
+                    [SelectorSegment::Match(EdgeMatcher::Named("a".into()))].into(),
                     [
-                        SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
+                        SelectorSegment::Match(EdgeMatcher::Named("a".into())),
                         SelectorSegment::Match(EdgeLabel::Deref.into()),
-                        SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
+                        SelectorSegment::Match(EdgeMatcher::Named("a".into())),
                     ]
                     .into(),
                 ])]
@@ -1160,15 +1168,15 @@ fn fragment_attributes() {
     //   start/value: 42;
     //   end/key: abc;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![StyleRule {
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
         selector: Selector::default(),
         properties: vec![
             StyleClause {
-                key: Property(FragmentAttribute(FragmentKey::Start, "value".to_owned())),
+                key: Property(FragmentAttribute(FragmentKey::Start, "value".into())),
                 value: Expression::Int(42),
             },
             StyleClause {
-                key: Property(FragmentAttribute(FragmentKey::End, "key".to_owned())),
+                key: Property(FragmentAttribute(FragmentKey::End, "key".into())),
                 value: Expression::String("abc".to_owned()),
             },
         ],
@@ -1184,6 +1192,336 @@ fn fragment_attributes() {
     assert_eq!(resolved, expected_mapping);
 }
 
+#[test]
+fn stable_key() {
+    // :: {
+    //   stable-key: 42;
+    // }
+    // :: main {
+    //   stable-key: unset;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: Property(StableKey),
+                value: Expression::Int(42),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
+            properties: vec![StyleClause {
+                key: Property(StableKey),
+                value: Expression::Unset,
+            }],
+        },
+    ]));
+    let expected_mapping = [(
+        Selectable::node(0),
+        PropertyMap::new().with_stable_key("42".to_owned()),
+    )]
+    .into();
+    let resolved = apply_stylesheet(&stylesheet, &TestGraph::default_graph());
+    assert_eq!(resolved, expected_mapping);
+}
+
+#[test]
+fn detach() {
+    // :: {
+    //   display: graph;
+    // }
+    // :: main {
+    //   display: cell;
+    //   detach: true;
+    // }
+    // :: "a" {
+    //   display: cell;
+    //   detach: false;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("graph".to_owned()),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
+            properties: vec![
+                StyleClause {
+                    key: Property(Display),
+                    value: Expression::String("cell".to_owned()),
+                },
+                StyleClause {
+                    key: Property(Detach),
+                    value: Expression::Bool(true),
+                },
+            ],
+        },
+        StyleRule {
+            selector: Selector::from_path(
+                [SelectorSegment::Match(
+                    EdgeLabel::Named("a".into(), 0).into(),
+                )]
+                .into(),
+            ),
+            properties: vec![
+                StyleClause {
+                    key: Property(Display),
+                    value: Expression::String("cell".to_owned()),
+                },
+                StyleClause {
+                    key: Property(Detach),
+                    value: Expression::Bool(false),
+                },
+            ],
+        },
+    ]));
+    let cell = || PropertyMap::new().with_display(DisplayMode::ElementTag("cell".to_owned()));
+    let expected_mapping = [
+        (
+            Selectable::node(0),
+            PropertyMap::new().with_display(DisplayMode::ElementTag("graph".to_owned())),
+        ),
+        (
+            Selectable::node(1),
+            cell().with_parent(Selectable::node(0)).with_detached(),
+        ),
+        (Selectable::node(5), cell().with_parent(Selectable::node(0))),
+    ]
+    .into();
+    let resolved = apply_stylesheet(&stylesheet, &TestGraph::default_graph());
+    assert_eq!(resolved, expected_mapping);
+}
+
+#[test]
+fn reverse() {
+    // :: {
+    //   display: graph;
+    // }
+    // :: main::edge {
+    //   reverse: true;
+    // }
+    // :: "a"::edge {
+    //   reverse: false;
+    // }
+    // :: main {
+    //   display: cell;
+    //   reverse: true;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("graph".to_owned()),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into())
+                .selecting_edge(),
+            properties: vec![StyleClause {
+                key: Property(Reverse),
+                value: Expression::Bool(true),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path(
+                [SelectorSegment::Match(
+                    EdgeLabel::Named("a".into(), 0).into(),
+                )]
+                .into(),
+            )
+            .selecting_edge(),
+            properties: vec![StyleClause {
+                key: Property(Reverse),
+                value: Expression::Bool(false),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
+            properties: vec![
+                StyleClause {
+                    key: Property(Display),
+                    value: Expression::String("cell".to_owned()),
+                },
+                StyleClause {
+                    key: Property(Reverse),
+                    value: Expression::Bool(true),
+                },
+            ],
+        },
+    ]));
+    let expected_mapping = [
+        (
+            Selectable::node(0),
+            PropertyMap::new().with_display(DisplayMode::ElementTag("graph".to_owned())),
+        ),
+        (
+            Selectable::edge(0, EdgeLabel::Main),
+            PropertyMap::new()
+                .with_display(DisplayMode::Connector)
+                .with_parent(Selectable::node(1))
+                .with_target(Selectable::node(0)),
+        ),
+        (
+            Selectable::edge(0, EdgeLabel::Named("a".into(), 0)),
+            PropertyMap::new()
+                .with_display(DisplayMode::Connector)
+                .with_parent(Selectable::node(0))
+                .with_target(Selectable::node(5)),
+        ),
+        // Only connectors can be reversed
+        (
+            Selectable::node(1),
+            PropertyMap::new()
+                .with_display(DisplayMode::ElementTag("cell".to_owned()))
+                .with_parent(Selectable::node(0)),
+        ),
+    ]
+    .into();
+    let resolved = apply_stylesheet(&stylesheet, &TestGraph::default_graph());
+    assert_eq!(resolved, expected_mapping);
+}
+
+#[test]
+fn layer() {
+    // :: {
+    //   display: graph;
+    //   layer: 2;
+    // }
+    // :: main {
+    //   display: cell;
+    //   layer: top;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![
+                StyleClause {
+                    key: Property(Display),
+                    value: Expression::String("graph".to_owned()),
+                },
+                StyleClause {
+                    key: Property(Layer),
+                    value: Expression::Int(2),
+                },
+            ],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
+            properties: vec![
+                StyleClause {
+                    key: Property(Display),
+                    value: Expression::String("cell".to_owned()),
+                },
+                StyleClause {
+                    key: Property(Layer),
+                    value: Expression::String("top".to_owned()),
+                },
+            ],
+        },
+    ]));
+    let mut warnings = Vec::new();
+    let resolved =
+        apply_stylesheet_with_warnings(&stylesheet, &TestGraph::default_graph(), |warning| {
+            warnings.push(warning)
+        });
+    let expected_mapping = [
+        (
+            Selectable::node(0),
+            PropertyMap::new()
+                .with_display(DisplayMode::ElementTag("graph".to_owned()))
+                .with_layer(2),
+        ),
+        (
+            Selectable::node(1),
+            PropertyMap::new()
+                .with_display(DisplayMode::ElementTag("cell".to_owned()))
+                .with_parent(Selectable::node(0)),
+        ),
+    ]
+    .into();
+    assert_eq!(resolved, expected_mapping);
+    assert_eq!(
+        warnings,
+        [AttributeTypeMismatch {
+            entity: Selectable::node(1),
+            fragment: None,
+            attribute: "layer".to_owned(),
+            expected: AttributeType::Integer,
+            value: "top".to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn ordered_extras() {
+    // :: ::extra(icon):index(0) {
+    //   display: cell;
+    // }
+    // :: ::extra(badge):index(2) {
+    //   display: cell;
+    //   order: 5;
+    // }
+    // :: ::extra(label):index(1) {}
+    // :: ::extra(label):index(3) {
+    //   display: cell;
+    // }
+    let display_cell = || StyleClause {
+        key: Property(Display),
+        value: Expression::String("cell".to_owned()),
+    };
+    let ordered_extra = |extra: &str, index| {
+        Selector::default()
+            .with_extra(extra.to_owned())
+            .with_extra_index(index)
+    };
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
+        StyleRule {
+            selector: ordered_extra("icon", 0),
+            properties: vec![display_cell()],
+        },
+        StyleRule {
+            selector: ordered_extra("badge", 2),
+            properties: vec![
+                display_cell(),
+                StyleClause {
+                    key: Property(Attribute("order".into())),
+                    value: Expression::Int(5),
+                },
+            ],
+        },
+        StyleRule {
+            selector: ordered_extra("label", 1),
+            properties: Vec::new(),
+        },
+        StyleRule {
+            selector: ordered_extra("label", 3),
+            properties: vec![display_cell()],
+        },
+    ]));
+    let extra = |extra: &str, index: usize, order: &str| {
+        (
+            Selectable::node(0).with_extra(extra.to_owned()),
+            PropertyMap::new()
+                .with_display(DisplayMode::ElementTag("cell".to_owned()))
+                .with_parent(Selectable::node(0))
+                .with_extra_index(index)
+                .with_attribute("order".to_owned(), order.to_owned()),
+        )
+    };
+    let expected_mapping = [
+        extra("icon", 0, "0"),
+        extra("badge", 2, "5"),
+        extra("label", 3, "3"),
+    ]
+    .into();
+    let resolved = apply_stylesheet(&stylesheet, &TestGraph::default_graph());
+    assert_eq!(resolved, expected_mapping);
+}
+
 #[test]
 fn dynamic_index_matcher() {
     // :: {
@@ -1193,7 +1531,7 @@ fn dynamic_index_matcher() {
     // .if(@([--i])) {
     //   value: abc;
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
@@ -1215,7 +1553,7 @@ fn dynamic_index_matcher() {
                 .into(),
             ),
             properties: vec![StyleClause {
-                key: Property(Attribute("value".to_owned())),
+                key: Property(Attribute("value".into())),
                 value: Expression::String("abc".to_owned()),
             }],
         },
@@ -1238,7 +1576,7 @@ fn select_origin_override() {
     // :: main {
     //   value: @((--root) "a");
     // }
-    let stylesheet = CascadeStyle::from(Stylesheet(vec![
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
         StyleRule {
             selector: Selector::default(),
             properties: vec![StyleClause {
@@ -1249,9 +1587,9 @@ fn select_origin_override() {
         StyleRule {
             selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
             properties: vec![StyleClause {
-                key: Property(Attribute("value".to_owned())),
+                key: Property(Attribute("value".into())),
                 value: Expression::Select(
-                    LimitedSelector::from_path([EdgeLabel::Named("a".to_owned(), 0).into()])
+                    LimitedSelector::from_path([EdgeLabel::Named("a".into(), 0).into()])
                         .with_origin(Expression::Variable("--root".to_owned()))
                         .into(),
                 ),
@@ -1269,3 +1607,708 @@ fn select_origin_override() {
     let resolved = apply_stylesheet(&stylesheet, &TestGraph::default_graph());
     assert_eq!(resolved, expected_mapping);
 }
+
+#[test]
+fn traversal_position_magic_variables() {
+    // * { ... }
+    // "a" * { ... }
+    // "a" "a" * { ... }
+    // "a" [] * { ... }
+    //
+    // where { ... } stands for
+    // {
+    //   depth: --DEPTH;
+    //   sibling: --SIBLING-INDEX;
+    //   layer: --LAYER-INDEX;
+    // }
+    let properties = vec![
+        StyleClause {
+            key: Property(Attribute("depth".into())),
+            value: Expression::MagicVariable(MagicVariableKey::Depth),
+        },
+        StyleClause {
+            key: Property(Attribute("sibling".into())),
+            value: Expression::MagicVariable(MagicVariableKey::SiblingIndex),
+        },
+        StyleClause {
+            key: Property(Attribute("layer".into())),
+            value: Expression::MagicVariable(MagicVariableKey::LayerIndex),
+        },
+    ];
+    let paths = [
+        vec![EdgeMatcher::Any],
+        vec![EdgeMatcher::Named("a".into()), EdgeMatcher::Any],
+        vec![
+            EdgeMatcher::Named("a".into()),
+            EdgeMatcher::Named("a".into()),
+            EdgeMatcher::Any,
+        ],
+        vec![
+            EdgeMatcher::Named("a".into()),
+            EdgeMatcher::AnyIndex,
+            EdgeMatcher::Any,
+        ],
+    ];
+    let stylesheet = CascadeStyle::from(Stylesheet::new(
+        paths
+            .into_iter()
+            .map(|path| StyleRule {
+                selector: Selector::from_path(
+                    path.into_iter()
+                        .map(SelectorSegment::Match)
+                        .collect::<Vec<_>>()
+                        .into(),
+                ),
+                properties: properties.clone(),
+            })
+            .collect(),
+    ));
+    let position = |depth: usize, sibling: usize, layer: usize| {
+        PropertyMap::new()
+            .with_attribute("depth".to_owned(), depth.to_string())
+            .with_attribute("sibling".to_owned(), sibling.to_string())
+            .with_attribute("layer".to_owned(), layer.to_string())
+    };
+    let expected_mapping = [
+        (Selectable::node(1), position(1, 0, 0)),
+        (Selectable::node(5), position(1, 1, 1)),
+        (Selectable::node(6), position(2, 0, 2)),
+        (Selectable::node(8), position(2, 1, 3)),
+        (Selectable::node(10), position(2, 2, 1)),
+        (Selectable::node(11), position(3, 0, 1)),
+        (Selectable::node(7), position(3, 1, 3)),
+        (Selectable::node(9), position(3, 0, 4)),
+    ]
+    .into();
+    let resolved = apply_stylesheet(&stylesheet, &TestGraph::default_graph());
+    assert_eq!(resolved, expected_mapping);
+}
+
+#[test]
+fn layer_index_does_not_depend_on_traversal() {
+    // "a" "a" * {
+    //   layer: --LAYER-INDEX;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::from_path(
+            [
+                SelectorSegment::Match(EdgeMatcher::Named("a".into())),
+                SelectorSegment::Match(EdgeMatcher::Named("a".into())),
+                SelectorSegment::Match(EdgeMatcher::Any),
+            ]
+            .into(),
+        ),
+        properties: vec![StyleClause {
+            key: Property(Attribute("layer".into())),
+            value: Expression::MagicVariable(MagicVariableKey::LayerIndex),
+        }],
+    }]));
+    let layer =
+        |layer: usize| PropertyMap::new().with_attribute("layer".to_owned(), layer.to_string());
+    // Nodes that the selector does not reach are counted as well
+    let expected_mapping = [
+        (Selectable::node(11), layer(1)),
+        (Selectable::node(7), layer(3)),
+    ]
+    .into();
+    let resolved = apply_stylesheet(&stylesheet, &TestGraph::default_graph());
+    assert_eq!(resolved, expected_mapping);
+}
+
+#[test]
+fn apply_stylesheet_to_type_erased_graph() {
+    // .many(*) "a" {
+    //   value: 42;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::from_path(
+            [
+                SelectorSegment::anything_any_number_of_times(),
+                SelectorSegment::Match(EdgeMatcher::Named("a".into())),
+            ]
+            .into(),
+        ),
+        properties: vec![StyleClause {
+            key: Property(Attribute("value".into())),
+            value: Expression::Int(42),
+        }],
+    }]));
+    let expected_properties =
+        PropertyMap::new().with_attribute("value".to_owned(), "42".to_owned());
+    let expected_mapping = [5, 6, 7, 10, 11, 12]
+        .map(|id: usize| {
+            (
+                Selectable::node(DynNodeId::new(id)),
+                expected_properties.clone(),
+            )
+        })
+        .into();
+    let graph = TestGraph::default_graph();
+    let resolved = apply_stylesheet(&stylesheet, &DynStateGraph::from_ref(&graph));
+    assert_eq!(resolved, expected_mapping);
+}
+
+#[test]
+fn select_by_lifetime() {
+    // .many(*):new {
+    //   value: "new";
+    // }
+    // .many(*):removed {
+    //   value: "removed";
+    // }
+    let lifetime_rule = |lifetime: &str| StyleRule {
+        selector: Selector::from_path(
+            [
+                SelectorSegment::anything_any_number_of_times(),
+                SelectorSegment::Condition(Expression::BinaryOperator(
+                    Expression::MagicVariable(MagicVariableKey::Lifetime).into(),
+                    BinaryOperator::Eq,
+                    Expression::String(lifetime.to_owned()).into(),
+                )),
+            ]
+            .into(),
+        ),
+        properties: vec![StyleClause {
+            key: Property(Attribute("value".into())),
+            value: Expression::MagicVariable(MagicVariableKey::Lifetime),
+        }],
+    };
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
+        lifetime_rule("new"),
+        lifetime_rule("removed"),
+    ]));
+    let tagged = |ids: &[usize], lifetime: &str| {
+        let properties = PropertyMap::new().with_attribute("value".to_owned(), lifetime.to_owned());
+        ids.iter()
+            .map(|&id| (Selectable::node(id), properties.clone()))
+            .collect::<HashMap<_, _>>()
+    };
+    let mut tracker = LifetimeTracker::new();
+    let graph = TestGraph::default_graph();
+    let all_nodes = Vec::from_iter(0..14);
+    let resolved = apply_stylesheet_tracked(&stylesheet, &graph, &mut tracker);
+    assert_eq!(resolved, tagged(&all_nodes, "new").into());
+    let resolved = apply_stylesheet_tracked(&stylesheet, &graph, &mut tracker);
+    assert_eq!(resolved, EntityPropertyMapping::new());
+    // Nodes 1 to 4 are only reachable through the main edge
+    let graph = TestGraph::default_graph().without_edge(0, &EdgeLabel::Main);
+    let resolved = apply_stylesheet_tracked(&stylesheet, &graph, &mut tracker);
+    assert_eq!(resolved, tagged(&[1, 2, 3, 4], "removed").into());
+    // Removed nodes are only retained once
+    let resolved = apply_stylesheet_tracked(&stylesheet, &graph, &mut tracker);
+    assert_eq!(resolved, EntityPropertyMapping::new());
+    let graph = TestGraph::default_graph();
+    let resolved = apply_stylesheet_tracked(&stylesheet, &graph, &mut tracker);
+    assert_eq!(resolved, tagged(&[1, 2, 3, 4], "new").into());
+}
+
+#[test]
+fn removed_nodes_are_ignored_by_other_rules() {
+    // .many(*) {
+    //   value: 42;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::from_path([SelectorSegment::anything_any_number_of_times()].into()),
+        properties: vec![StyleClause {
+            key: Property(Attribute("value".into())),
+            value: Expression::Int(42),
+        }],
+    }]));
+    let mut tracker = LifetimeTracker::new();
+    apply_stylesheet_tracked(&stylesheet, &TestGraph::default_graph(), &mut tracker);
+    let graph = TestGraph::default_graph().without_edge(0, &EdgeLabel::Main);
+    let resolved = apply_stylesheet_tracked(&stylesheet, &graph, &mut tracker);
+    assert_eq!(resolved, apply_stylesheet(&stylesheet, &graph));
+}
+
+#[test]
+fn well_known_attributes_are_normalized() {
+    // :: {
+    //   stroke-width: "1.50";
+    //   checked: true;
+    //   fill: " Red ";
+    //   end/decoration: arrow;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::default(),
+        properties: vec![
+            StyleClause {
+                key: Property(Attribute("stroke-width".into())),
+                value: Expression::String("1.50".to_owned()),
+            },
+            StyleClause {
+                key: Property(Attribute("checked".into())),
+                value: Expression::Bool(true),
+            },
+            StyleClause {
+                key: Property(Attribute("fill".into())),
+                value: Expression::String(" Red ".to_owned()),
+            },
+            StyleClause {
+                key: Property(FragmentAttribute(FragmentKey::End, "decoration".into())),
+                value: Expression::String("arrow".to_owned()),
+            },
+        ],
+    }]));
+    let expected_mapping = [(
+        Selectable::node(0),
+        PropertyMap::new()
+            .with_attribute("stroke-width".to_owned(), "1.5".to_owned())
+            .with_attribute("checked".to_owned(), "true".to_owned())
+            .with_attribute("fill".to_owned(), "red".to_owned())
+            .with_fragment_attribute(
+                FragmentKey::End,
+                "decoration".to_owned(),
+                "arrow".to_owned(),
+            ),
+    )]
+    .into();
+    let resolved = apply_stylesheet(&stylesheet, &TestGraph::default_graph());
+    assert_eq!(resolved, expected_mapping);
+}
+
+#[test]
+fn mistyped_attributes_are_reported() {
+    // :: {
+    //   size: -1;
+    //   stroke-style: wavy;
+    //   value: wavy;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::default(),
+        properties: vec![
+            StyleClause {
+                key: Property(Attribute("size".into())),
+                value: Expression::UnaryOperator(UnaryOperator::Minus, Expression::Int(1).into()),
+            },
+            StyleClause {
+                key: Property(Attribute("stroke-style".into())),
+                value: Expression::String("wavy".to_owned()),
+            },
+            StyleClause {
+                key: Property(Attribute("value".into())),
+                value: Expression::String("wavy".to_owned()),
+            },
+        ],
+    }]));
+    let mut warnings = Vec::new();
+    let resolved =
+        apply_stylesheet_with_warnings(&stylesheet, &TestGraph::default_graph(), |warning| {
+            warnings.push(warning)
+        });
+    let expected_mapping = [(
+        Selectable::node(0),
+        PropertyMap::new().with_attribute("value".to_owned(), "wavy".to_owned()),
+    )]
+    .into();
+    assert_eq!(resolved, expected_mapping);
+    warnings.sort_by(|a, b| a.attribute.cmp(&b.attribute));
+    assert_eq!(
+        warnings,
+        [
+            AttributeTypeMismatch {
+                entity: Selectable::node(0),
+                fragment: None,
+                attribute: "size".to_owned(),
+                expected: AttributeType::Length,
+                value: "-1".to_owned(),
+            },
+            AttributeTypeMismatch {
+                entity: Selectable::node(0),
+                fragment: None,
+                attribute: "stroke-style".to_owned(),
+                expected: AttributeType::Keyword(&["solid", "dashed", "dotted"]),
+                value: "wavy".to_owned(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn stats_are_collected() {
+    // :: {
+    //   display: graph;
+    // }
+    // :: main {
+    //   display: cell;
+    // }
+    // :: main.if(true) {}
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("graph".to_owned()),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("cell".to_owned()),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path(
+                [
+                    SelectorSegment::Match(EdgeLabel::Main.into()),
+                    SelectorSegment::Condition(Expression::Bool(true)),
+                ]
+                .into(),
+            ),
+            properties: Vec::new(),
+        },
+    ]));
+    let mut stats = CascadeStats::new();
+    let resolved =
+        apply_stylesheet_with_stats(&stylesheet, &TestGraph::default_graph(), &mut stats);
+    assert_eq!(
+        resolved,
+        apply_stylesheet(&stylesheet, &TestGraph::default_graph())
+    );
+    // The root and both of its successors are visited,
+    // but the selectors end there
+    assert_eq!(stats.nodes_visited, 3);
+    assert_eq!(stats.edges_traversed, 2);
+    assert_eq!(stats.rules_evaluated, 3);
+    assert_eq!(stats.conditions_evaluated, 1);
+    assert_eq!(stats.rule_times.len(), 3);
+    // Counters of further applications are accumulated
+    apply_stylesheet_with_stats(&stylesheet, &TestGraph::default_graph(), &mut stats);
+    assert_eq!(stats.nodes_visited, 6);
+    assert_eq!(stats.rules_evaluated, 6);
+}
+
+#[test]
+fn stats_refer_to_named_rules() {
+    // @rule "root-graph"
+    // :: {
+    //   display: graph;
+    // }
+    // :: main {}
+    let stylesheet = CascadeStyle::from(
+        Stylesheet::new(vec![
+            StyleRule {
+                selector: Selector::default(),
+                properties: vec![StyleClause {
+                    key: Property(Display),
+                    value: Expression::String("graph".to_owned()),
+                }],
+            },
+            StyleRule {
+                selector: Selector::from_path(
+                    [SelectorSegment::Match(EdgeLabel::Main.into())].into(),
+                ),
+                properties: Vec::new(),
+            },
+        ])
+        .with_rule_name(0, "root-graph"),
+    );
+    let mut stats = CascadeStats::new();
+    apply_stylesheet_with_stats(&stylesheet, &TestGraph::default_graph(), &mut stats);
+    let labels = stats
+        .labeled_rule_times(&stylesheet)
+        .map(|(label, _)| label.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(labels, ["rule \"root-graph\"", "rule 1"]);
+}
+
+#[test]
+fn stats_report_hot_rules() {
+    // :: main {}
+    // @rule "everything"
+    // :: .many(*) "nothing" {}
+    let stylesheet = CascadeStyle::from(
+        Stylesheet::new(vec![
+            StyleRule {
+                selector: Selector::from_path(
+                    [SelectorSegment::Match(EdgeLabel::Main.into())].into(),
+                ),
+                properties: Vec::new(),
+            },
+            StyleRule {
+                selector: Selector::from_path(
+                    [
+                        SelectorSegment::anything_any_number_of_times(),
+                        SelectorSegment::Match(EdgeMatcher::Named("nothing".into())),
+                    ]
+                    .into(),
+                ),
+                properties: Vec::new(),
+            },
+        ])
+        .with_rule_name(1, "everything"),
+    );
+    let mut stats = CascadeStats::new();
+    apply_stylesheet_with_stats(&stylesheet, &TestGraph::default_graph(), &mut stats);
+    assert_eq!(stats.rule_matches, [1, 0]);
+    let hot_rules = stats.hot_rules(&stylesheet, 10);
+    assert_eq!(hot_rules.len(), 2);
+    assert_eq!(hot_rules[0].label.to_string(), "rule \"everything\"");
+    assert_eq!(hot_rules[0].matches, 0);
+    assert!(hot_rules[0].match_attempts > hot_rules[1].match_attempts);
+    assert_eq!(hot_rules[1].label.to_string(), "rule 0");
+    assert_eq!(hot_rules[1].matches, 1);
+    assert_eq!(stats.hot_rules(&stylesheet, 1).len(), 1);
+}
+
+#[test]
+fn traversal_budget() {
+    // :: {
+    //   display: graph;
+    // }
+    // :: main {
+    //   display: cell;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("graph".to_owned()),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("cell".to_owned()),
+            }],
+        },
+    ]));
+    let graph = TestGraph::default_graph();
+    // The root and both of its successors fit in the budget
+    let full = apply_stylesheet_with_budget(
+        &stylesheet,
+        &graph,
+        TraversalBudget::new().with_max_nodes(3),
+    );
+    assert!(!full.truncated);
+    assert_eq!(full.mapping, apply_stylesheet(&stylesheet, &graph));
+    // Only the root fits in the budget
+    let partial = apply_stylesheet_with_budget(
+        &stylesheet,
+        &graph,
+        TraversalBudget::new().with_max_nodes(1),
+    );
+    assert!(partial.truncated);
+    assert_eq!(
+        partial.mapping,
+        [(
+            Selectable::node(0),
+            PropertyMap::new().with_display(DisplayMode::ElementTag("graph".to_owned())),
+        )]
+        .into()
+    );
+    // Nothing fits before a deadline that has passed
+    let expired = apply_stylesheet_with_budget(
+        &stylesheet,
+        &graph,
+        TraversalBudget::new().with_deadline(std::time::Instant::now()),
+    );
+    assert!(expired.truncated);
+    assert_eq!(expired.mapping, EntityPropertyMapping::new());
+}
+
+#[test]
+fn visit_once_traversal() {
+    // :: .many(*) .if(@depth == 3) {
+    //   value: @depth;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::from_path(
+            [
+                SelectorSegment::AnyNumberOfTimes(
+                    [SelectorSegment::Match(EdgeMatcher::Any)].into(),
+                ),
+                SelectorSegment::Condition(Expression::BinaryOperator(
+                    Expression::MagicVariable(MagicVariableKey::Depth).into(),
+                    BinaryOperator::Eq,
+                    Expression::Int(3).into(),
+                )),
+            ]
+            .into(),
+        ),
+        properties: vec![StyleClause {
+            key: Property(Attribute("value".into())),
+            value: Expression::MagicVariable(MagicVariableKey::Depth),
+        }],
+    }]));
+    let graph = TestGraph::cyclic_list();
+    // Node 1 is reached at depth 3 by going back and forth
+    let expected_mapping = [(
+        Selectable::node(1),
+        PropertyMap::new().with_attribute("value".to_owned(), "3".to_owned()),
+    )]
+    .into();
+    assert_eq!(apply_stylesheet(&stylesheet, &graph), expected_mapping);
+    assert_eq!(
+        apply_stylesheet_with_mode(&stylesheet, &graph, TraversalMode::AllPaths),
+        expected_mapping
+    );
+    // Node 1 has already been entered at depth 1 in the same state,
+    // so it is not entered again
+    assert_eq!(
+        apply_stylesheet_with_mode(&stylesheet, &graph, TraversalMode::VisitOnce),
+        EntityPropertyMapping::new()
+    );
+}
+
+#[test]
+fn conditional_block_depends_on_context() {
+    // :: {
+    //   value: "light";
+    // }
+    // @when --THEME == "dark" {
+    //   :: {
+    //     value: "dark";
+    //   }
+    // }
+    let rule = |value: &str| StyleRule {
+        selector: Selector::default(),
+        properties: vec![StyleClause {
+            key: Property(Attribute("value".into())),
+            value: Expression::String(value.to_owned()),
+        }],
+    };
+    let stylesheet = CascadeStyle::from(
+        Stylesheet::new(vec![rule("light"), rule("dark")]).with_conditional_block(
+            Expression::BinaryOperator(
+                Expression::Variable("--THEME".to_owned()).into(),
+                BinaryOperator::Eq,
+                Expression::String("dark".to_owned()).into(),
+            ),
+            1..2,
+        ),
+    );
+    let graph = TestGraph::default_graph();
+    let mapping_with_value = |value: &str| {
+        [(
+            Selectable::node(0),
+            PropertyMap::new().with_attribute("value".to_owned(), value.to_owned()),
+        )]
+        .into()
+    };
+    assert_eq!(
+        apply_stylesheet(&stylesheet, &graph),
+        mapping_with_value("light")
+    );
+    let dark = CascadeContext::new().with_variable("--THEME", "dark".to_owned());
+    assert_eq!(
+        apply_stylesheet_with_context(&stylesheet, &graph, &dark),
+        mapping_with_value("dark")
+    );
+    let light = CascadeContext::new().with_variable("--THEME", "light".to_owned());
+    assert_eq!(
+        apply_stylesheet_with_context(&stylesheet, &graph, &light),
+        mapping_with_value("light")
+    );
+}
+
+#[test]
+fn context_variables_are_visible_everywhere() {
+    // :: .many(*) "a" {
+    //   value: --STEP-NUMBER;
+    // }
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::from_path(
+            [
+                SelectorSegment::anything_any_number_of_times(),
+                SelectorSegment::Match(EdgeMatcher::Named("a".into())),
+            ]
+            .into(),
+        ),
+        properties: vec![StyleClause {
+            key: Property(Attribute("value".into())),
+            value: Expression::Variable("--STEP-NUMBER".to_owned()),
+        }],
+    }]));
+    let context = CascadeContext::new().with_variable("--STEP-NUMBER", 7u64);
+    let expected_properties = PropertyMap::new().with_attribute("value".to_owned(), "7".to_owned());
+    let expected_mapping = [5, 6, 7, 10, 11, 12]
+        .map(|node| (Selectable::node(node), expected_properties.clone()))
+        .into();
+    let resolved =
+        apply_stylesheet_with_context(&stylesheet, &TestGraph::default_graph(), &context);
+    assert_eq!(resolved, expected_mapping);
+}
+
+#[test]
+fn mapping_stats() {
+    let mapping = EntityPropertyMapping::<usize>::from(HashMap::from([
+        (
+            Selectable::node(0),
+            PropertyMap::new().with_display(DisplayMode::ElementTag("cell".to_owned())),
+        ),
+        (
+            Selectable::edge(0, EdgeLabel::Main),
+            PropertyMap::new().with_display(DisplayMode::Connector),
+        ),
+        (
+            Selectable::node(0).with_extra("label".to_owned()),
+            PropertyMap::new().with_display(DisplayMode::ElementTag("text".to_owned())),
+        ),
+        (Selectable::node(1), PropertyMap::new()),
+    ]));
+    let stats = mapping.stats();
+    assert_eq!(stats.nodes, 2);
+    assert_eq!(stats.edges, 1);
+    assert_eq!(stats.extras, 1);
+    assert_eq!(stats.elements, 2);
+    assert_eq!(stats.connectors, 1);
+    assert_eq!(stats.entity_count(), 4);
+}
+
+#[test]
+fn namespaced_attributes() {
+    // :: {
+    //   stroke: red;
+    //   svg/stroke: blue;
+    //   debug/note: root;
+    //   start/label: a;
+    //   start/"svg/label": b;
+    // }
+    let clause = |key, value: &str| {
+        StyleClause {
+            key: Property(key),
+            value: Expression::String(value.to_owned()),
+        }
+        .try_map_key::<aili_translate::property::PropertyKey>()
+        .unwrap()
+    };
+    let stylesheet = CascadeStyle::from(Stylesheet::new(vec![StyleRule {
+        selector: Selector::default(),
+        properties: vec![
+            clause(RawPropertyKey::Property("stroke".to_owned()), "red"),
+            clause(
+                RawPropertyKey::FragmentProperty("svg".to_owned(), "stroke".to_owned()),
+                "blue",
+            ),
+            clause(
+                RawPropertyKey::FragmentProperty("debug".to_owned(), "note".to_owned()),
+                "root",
+            ),
+            clause(
+                RawPropertyKey::FragmentProperty("start".to_owned(), "label".to_owned()),
+                "a",
+            ),
+            clause(
+                RawPropertyKey::FragmentProperty("start".to_owned(), "svg/label".to_owned()),
+                "b",
+            ),
+        ],
+    }]));
+    let mut resolved = apply_stylesheet(&stylesheet, &TestGraph::default_graph());
+    let root = &resolved.0[&Selectable::node(0)];
+    assert_eq!(root.attributes.len(), 3);
+    assert_eq!(
+        root.namespaced_attributes("svg").collect::<Vec<_>>(),
+        [("stroke", "blue")]
+    );
+    resolved.retain_namespaces(&["debug"]);
+    let expected_root = PropertyMap::new()
+        .with_attribute("stroke", "red".to_owned())
+        .with_attribute("debug/note", "root".to_owned())
+        .with_fragment_attribute(FragmentKey::Start, "label", "a".to_owned());
+    assert_eq!(resolved.0[&Selectable::node(0)], expected_root);
+}
@@ -0,0 +1,109 @@
+//! Tests for [`export_svg`] and [`export_html`].
+
+use aili_model::vis::{AttributeMap, VisConnector, VisElement, VisPin, VisTree};
+use aili_translate::{
+    export::{export_html, export_svg},
+    mutation::MutationRecorder,
+};
+
+#[test]
+fn empty_tree_is_an_empty_image() {
+    let svg = export_svg(&[]);
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.ends_with("</svg>\n"));
+    assert!(!svg.contains("<rect"));
+}
+
+#[test]
+fn elements_are_nested_groups() {
+    let mut tree = MutationRecorder::new();
+    let root = tree.add_element("graph");
+    let child = tree.add_element("cell");
+    tree.get_element(&child)
+        .unwrap()
+        .set_attribute("value", Some("a < b"));
+    tree.get_element(&child)
+        .unwrap()
+        .set_attribute("fill", Some("red"));
+    tree.get_element(&child)
+        .unwrap()
+        .insert_into(Some(&root))
+        .unwrap();
+    tree.set_root(Some(&root)).unwrap();
+    let svg = export_svg(&tree.snapshot());
+    let graph_start = svg.find(r#"<g class="graph""#).unwrap();
+    let cell_start = svg.find(r#"<g class="cell""#).unwrap();
+    let graph_end = svg.rfind("</g>").unwrap();
+    assert!(graph_start < cell_start && cell_start < graph_end);
+    assert!(svg.contains(r#"fill="red""#));
+    // Text is escaped
+    assert!(svg.contains(">a &lt; b</text>"));
+}
+
+#[test]
+fn elements_outside_the_tree_are_not_drawn() {
+    let mut tree = MutationRecorder::new();
+    let root = tree.add_element("cell");
+    // Element that is not inserted into anything
+    tree.add_element("text");
+    tree.set_root(Some(&root)).unwrap();
+    let removed = tree.add_element("label");
+    tree.get_element(&removed)
+        .unwrap()
+        .insert_into(Some(&root))
+        .unwrap();
+    tree.get_element(&removed)
+        .unwrap()
+        .insert_into(None)
+        .unwrap();
+    tree.remove_element(&removed).unwrap();
+    let svg = export_svg(&tree.snapshot());
+    assert!(svg.contains(r#"<g class="cell""#));
+    assert!(!svg.contains(r#"<g class="text""#));
+    assert!(!svg.contains(r#"<g class="label""#));
+}
+
+#[test]
+fn connectors_are_paths() {
+    let mut tree = MutationRecorder::new();
+    let root = tree.add_element("graph");
+    let a = tree.add_element("cell");
+    let b = tree.add_element("cell");
+    for element in [&a, &b] {
+        tree.get_element(element)
+            .unwrap()
+            .insert_into(Some(&root))
+            .unwrap();
+    }
+    let connected = tree.add_connector();
+    let dangling = tree.add_connector();
+    {
+        let mut connector = tree.get_connector(&connected).unwrap();
+        connector.set_attribute("stroke-style", Some("dashed"));
+        connector.start_mut().attach_to(Some(&a)).unwrap();
+        let mut end = connector.end_mut();
+        end.attach_to(Some(&b)).unwrap();
+        end.set_attribute("decoration", Some("arrow"));
+    }
+    tree.get_connector(&dangling)
+        .unwrap()
+        .start_mut()
+        .attach_to(Some(&a))
+        .unwrap();
+    tree.set_root(Some(&root)).unwrap();
+    let svg = export_svg(&tree.snapshot());
+    assert_eq!(svg.matches(r#"<path class="connector""#).count(), 1);
+    assert!(svg.contains(r#"stroke-dasharray="6 4""#));
+    assert!(svg.contains(r#"marker-end="url(#arrow)""#));
+}
+
+#[test]
+fn html_contains_the_image() {
+    let mut tree = MutationRecorder::new();
+    let root = tree.add_element("cell");
+    tree.set_root(Some(&root)).unwrap();
+    let mutations = tree.snapshot();
+    let html = export_html(&mutations);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains(&export_svg(&mutations)));
+}
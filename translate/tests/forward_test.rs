@@ -2,10 +2,13 @@
 
 mod test_vis;
 
+use aili_model::name::Name;
 use aili_style::selectable::Selectable;
 use aili_translate::{
-    forward::{VisTreeWriter, VisTreeWriterWarning},
-    property::{DisplayMode, FragmentKey, PropertyMap},
+    forward::{
+        MappingViolation, VisTreeWriter, VisTreeWriterWarning, WarningPolicy, WarningSeverity,
+    },
+    property::{DisplayMode, EntityPropertyMapping, FragmentKey, PropertyMap},
 };
 use std::collections::HashMap;
 use test_vis::*;
@@ -19,6 +22,15 @@ macro_rules! construct {
     };
 }
 
+/// Converts attributes of an expected visual
+/// to the form in which a [`PropertyMap`] holds them.
+fn property_attributes(attributes: &HashMap<String, String>) -> HashMap<Name, String> {
+    attributes
+        .iter()
+        .map(|(name, value)| (name.into(), value.clone()))
+        .collect()
+}
+
 /// Shorthand for cunstructing the argument of [`VisTreeWriter::update`].
 macro_rules! mapping {
     ( $( $id:expr => { $($fill:tt)* } ),* $(,)? ) => {
@@ -112,7 +124,7 @@ fn create_element_with_attributes() {
     renderer.update(mapping![
         0 => {
             display: Some(DisplayMode::ElementTag("cell".to_owned())),
-            attributes: attributes.clone(),
+            attributes: property_attributes(&attributes),
         },
     ]);
     let vis_tree = renderer.reclaim_vis_tree();
@@ -136,13 +148,13 @@ fn update_element_attributes() {
     renderer.update(mapping![
         0 => {
             display: Some(DisplayMode::ElementTag("cell".to_owned())),
-            attributes,
+            attributes: property_attributes(&attributes),
         },
     ]);
     renderer.update(mapping![
         0 => {
             display: Some(DisplayMode::ElementTag("cell".to_owned())),
-            attributes: updated_attributes.clone(),
+            attributes: property_attributes(&updated_attributes),
         },
     ]);
     // The element should not be recreated, only its attributes should be updated.
@@ -154,6 +166,29 @@ fn update_element_attributes() {
     );
 }
 
+#[test]
+fn update_element_layer() {
+    let mut renderer = VisTreeWriter::new(TestVisTree::default());
+    renderer.update(mapping![
+        0 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            layer: Some(3),
+        },
+    ]);
+    assert_eq!(
+        renderer.vis_tree().elements,
+        expect_elements![{ tag_name: "cell".to_owned(), layer: 3 }]
+    );
+    // Elements that no longer have a layer return to layer zero
+    renderer.update(mapping![
+        0 => { display: Some(DisplayMode::ElementTag("cell".to_owned())) },
+    ]);
+    assert_eq!(
+        renderer.vis_tree().elements,
+        expect_elements![{ tag_name: "cell".to_owned() }]
+    );
+}
+
 #[test]
 fn create_element_with_parent() {
     let mut renderer = VisTreeWriter::new(TestVisTree::default());
@@ -306,12 +341,74 @@ fn create_connector_with_pins() {
     assert_eq!(
         vis_tree.connectors,
         expect_connectors![{
-            start: TestVisPin { target_index: Some(index_of_first), attributes: [].into() },
-            end: TestVisPin { target_index: Some(index_of_second), attributes: [].into() },
+            start: TestVisPin { target_index: Some(index_of_first), target_connector_index: None, attributes: [].into() },
+            end: TestVisPin { target_index: Some(index_of_second), target_connector_index: None, attributes: [].into() },
         }]
     );
 }
 
+#[test]
+fn connector_targets_connector() {
+    let mut renderer = VisTreeWriter::new(TestVisTree::default());
+    renderer.update(mapping![
+        0 => { display: Some(DisplayMode::ElementTag("cell".to_owned())) },
+        1 => {
+            display: Some(DisplayMode::Connector),
+            parent: Some(Selectable::node(0)),
+            target: Some(Selectable::node(0)),
+        },
+        2 => {
+            display: Some(DisplayMode::Connector),
+            attributes: [("kind".into(), "note".to_owned())].into(),
+            parent: Some(Selectable::node(0)),
+            target: Some(Selectable::node(1)),
+        },
+    ]);
+    let vis_tree = renderer.reclaim_vis_tree();
+    let index_of_pointer = vis_tree
+        .connectors
+        .iter()
+        .position(|c| c.attributes.is_empty())
+        .expect("Connector without attributes should be present");
+    let note = vis_tree
+        .connectors
+        .iter()
+        .find(|c| !c.attributes.is_empty())
+        .expect("Connector with attributes should be present");
+    assert_eq!(note.end.target_index, None);
+    assert_eq!(note.end.target_connector_index, Some(index_of_pointer));
+}
+
+#[test]
+fn connector_target_cycle() {
+    let mut warning_was_emited = false;
+    let mut renderer =
+        VisTreeWriter::new(TestVisTree::default()).with_warning_handler(Box::new(|warning| {
+            // Both connectors are reported, so the warnings are coalesced
+            if warning.code() == "structure-violation" {
+                warning_was_emited = true;
+            }
+        }));
+    renderer.update(mapping![
+        0 => {
+            display: Some(DisplayMode::Connector),
+            target: Some(Selectable::node(1)),
+        },
+        1 => {
+            display: Some(DisplayMode::Connector),
+            target: Some(Selectable::node(0)),
+        },
+    ]);
+    let vis_tree = renderer.reclaim_vis_tree();
+    assert!(warning_was_emited);
+    assert!(
+        vis_tree
+            .connectors
+            .iter()
+            .all(|c| c.end.target_connector_index.is_none())
+    );
+}
+
 #[test]
 fn change_element_into_connector() {
     let mut renderer = VisTreeWriter::new(TestVisTree::default());
@@ -466,8 +563,8 @@ fn set_connector_fragment_attributes() {
         0 => {
             display: Some(DisplayMode::Connector),
             fragment_attributes: [
-                (FragmentKey::Start, [("key".to_owned(), "a".to_owned())].into()),
-                (FragmentKey::End, [("value".to_owned(), "b".to_owned())].into()),
+                (FragmentKey::Start, [("key".into(), "a".to_owned())].into()),
+                (FragmentKey::End, [("value".into(), "b".to_owned())].into()),
             ]
             .into(),
         },
@@ -478,10 +575,12 @@ fn set_connector_fragment_attributes() {
         expect_connectors![{
             start: TestVisPin {
                 target_index: None,
+                target_connector_index: None,
                 attributes: [("key".to_owned(), "a".to_owned())].into(),
             },
             end: TestVisPin {
                 target_index: None,
+                target_connector_index: None,
                 attributes: [("value".to_owned(), "b".to_owned())].into(),
             },
         }],
@@ -498,8 +597,8 @@ fn update_connector_fragment_attributes() {
                 (
                     FragmentKey::Start,
                     [
-                        ("a".to_owned(), "a".to_owned()),
-                        ("b".to_owned(), "b".to_owned()),
+                        ("a".into(), "a".to_owned()),
+                        ("b".into(), "b".to_owned()),
                     ]
                     .into()),
             ]
@@ -513,8 +612,8 @@ fn update_connector_fragment_attributes() {
                 (
                     FragmentKey::Start,
                     [
-                        ("b".to_owned(), "d".to_owned()),
-                        ("c".to_owned(), "c".to_owned()),
+                        ("b".into(), "d".to_owned()),
+                        ("c".into(), "c".to_owned()),
                     ]
                     .into()),
             ]
@@ -527,13 +626,14 @@ fn update_connector_fragment_attributes() {
         expect_connectors![{
             start: TestVisPin {
                 target_index: None,
+                target_connector_index: None,
                 attributes: [
                     ("b".to_owned(), "d".to_owned()),
                     ("c".to_owned(), "c".to_owned()),
                 ]
                 .into(),
             },
-            end: TestVisPin { target_index: None, attributes: [].into() },
+            end: TestVisPin { target_index: None, target_connector_index: None, attributes: [].into() },
         }],
     );
 }
@@ -543,8 +643,8 @@ fn create_loop_in_vis_tree() {
     let mut warning_was_emited = false;
     let mut renderer =
         VisTreeWriter::new(TestVisTree::default()).with_warning_handler(Box::new(|warning| {
-            match warning {
-                VisTreeWriterWarning::VisStructureViolation(_) => warning_was_emited = true,
+            if let VisTreeWriterWarning::VisStructureViolation(_) = warning {
+                warning_was_emited = true;
             }
         }));
     renderer.update(mapping![
@@ -560,3 +660,334 @@ fn create_loop_in_vis_tree() {
     drop(renderer);
     assert!(warning_was_emited);
 }
+
+#[test]
+fn multiple_roots() {
+    let mut renderer = VisTreeWriter::new(TestVisTree::default());
+    renderer.update_roots(vec![
+        Selectable::node(0),
+        Selectable::node(1),
+        Selectable::node(2),
+    ]);
+    renderer.update(mapping![
+        0 => { display: Some(DisplayMode::ElementTag("graph".to_owned())) },
+        1 => { display: Some(DisplayMode::ElementTag("cell".to_owned())) },
+    ]);
+    assert_eq!(renderer.vis_tree().root_indices.len(), 2);
+    // The third root becomes a root as soon as it is displayed
+    renderer.update(mapping![
+        0 => { display: Some(DisplayMode::ElementTag("graph".to_owned())) },
+        1 => { display: Some(DisplayMode::ElementTag("cell".to_owned())) },
+        2 => { display: Some(DisplayMode::ElementTag("text".to_owned())) },
+    ]);
+    let vis_tree = renderer.reclaim_vis_tree();
+    let roots = vis_tree
+        .root_indices
+        .iter()
+        .map(|&index| vis_tree.elements[index].tag_name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(roots, ["graph", "cell", "text"]);
+}
+
+#[test]
+fn stable_key_follows_moved_entity() {
+    let mut renderer = VisTreeWriter::new(TestVisTree::default());
+    renderer.update(mapping![
+        0 => { display: Some(DisplayMode::ElementTag("graph".to_owned())) },
+        1 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(0)),
+            stable_key: Some("a".to_owned()),
+        },
+    ]);
+    renderer.update(mapping![
+        0 => { display: Some(DisplayMode::ElementTag("graph".to_owned())) },
+        2 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(0)),
+            stable_key: Some("a".to_owned()),
+        },
+    ]);
+    // The element of the first entity has been reused by the second one
+    let vis_tree = renderer.reclaim_vis_tree();
+    assert_eq!(vis_tree.elements.len(), 2);
+    expect_one_parent_and_child(&vis_tree);
+}
+
+#[test]
+fn stable_key_overrides_entity_identity() {
+    let named = |name: &str| HashMap::from_iter([("name".to_owned(), name.to_owned())]);
+    let mut renderer = VisTreeWriter::new(TestVisTree::default());
+    renderer.update(mapping![
+        0 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            stable_key: Some("a".to_owned()),
+        },
+    ]);
+    renderer.update(mapping![
+        0 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            attributes: property_attributes(&named("zero")),
+        },
+        1 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            attributes: property_attributes(&named("one")),
+            stable_key: Some("a".to_owned()),
+        },
+    ]);
+    // The keyed element has moved to the other entity,
+    // so the entity that lost its key gets a new element
+    let vis_tree = renderer.reclaim_vis_tree();
+    assert_eq!(
+        vis_tree.elements,
+        expect_elements![
+            { tag_name: "cell".to_owned(), attributes: named("one") },
+            { tag_name: "cell".to_owned(), attributes: named("zero") },
+        ]
+    );
+}
+
+#[test]
+fn detached_element_floats() {
+    let mut renderer = VisTreeWriter::new(TestVisTree::default());
+    renderer.update_roots(vec![Selectable::node(0)]);
+    renderer.update(mapping![
+        0 => { display: Some(DisplayMode::ElementTag("graph".to_owned())) },
+        1 => {
+            display: Some(DisplayMode::ElementTag("legend".to_owned())),
+            parent: Some(Selectable::node(0)),
+            detached: true,
+        },
+        2 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(1)),
+        },
+    ]);
+    let vis_tree = renderer.vis_tree();
+    let legend = vis_tree.expect_find_element(|element| element.tag_name == "legend");
+    let cell = vis_tree.expect_find_element(|element| element.tag_name == "cell");
+    assert_eq!(vis_tree.elements[legend].parent_index, None);
+    assert_ne!(vis_tree.root_index, Some(legend));
+    // Children of the detached element stay with it
+    assert_eq!(vis_tree.elements[cell].parent_index, Some(legend));
+    // The element is reattached once it is no longer detached
+    renderer.update(mapping![
+        0 => { display: Some(DisplayMode::ElementTag("graph".to_owned())) },
+        1 => {
+            display: Some(DisplayMode::ElementTag("legend".to_owned())),
+            parent: Some(Selectable::node(0)),
+        },
+    ]);
+    let vis_tree = renderer.reclaim_vis_tree();
+    let graph = vis_tree.expect_find_element(|element| element.tag_name == "graph");
+    assert_eq!(vis_tree.elements[legend].parent_index, Some(graph));
+}
+
+#[test]
+fn validate_ignores_parents_of_detached_elements() {
+    let renderer = VisTreeWriter::new(TestVisTree::default());
+    let violations = renderer.validate(&mapping![
+        0 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(1)),
+            detached: true,
+        },
+        1 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(0)),
+        },
+        2 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(3)),
+            detached: true,
+        },
+    ]);
+    assert_eq!(violations, []);
+}
+
+/// Mapping with a number of two-element loops.
+fn mapping_with_loops(loop_count: usize) -> EntityPropertyMapping<usize> {
+    (0..loop_count * 2)
+        .map(|i| {
+            let properties = PropertyMap::new()
+                .with_display(DisplayMode::ElementTag("cell".to_owned()))
+                .with_parent(Selectable::node(i ^ 1));
+            (Selectable::node(i), properties)
+        })
+        .collect::<HashMap<_, _>>()
+        .into()
+}
+
+#[test]
+fn warnings_are_coalesced() {
+    let mut warnings = Vec::new();
+    let mut renderer = VisTreeWriter::new(TestVisTree::default())
+        .with_warning_handler(Box::new(|warning| warnings.push(warning)));
+    renderer.update(mapping_with_loops(3));
+    drop(renderer);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code(), "structure-violation");
+    assert_eq!(warnings[0].severity(), WarningSeverity::Warning);
+    match &warnings[0] {
+        VisTreeWriterWarning::Coalesced(coalesced) => assert_eq!(coalesced.len(), 3),
+        _ => panic!("Warnings should have been coalesced"),
+    }
+    assert!(
+        warnings[0]
+            .to_string()
+            .starts_with("3 structure violations")
+    );
+}
+
+#[test]
+fn warning_policy_skip_entity() {
+    let mut warnings = Vec::new();
+    let mut renderer = VisTreeWriter::new(TestVisTree::default())
+        .with_warning_policy(WarningPolicy::SkipEntity)
+        .with_warning_handler(Box::new(|warning| warnings.push(warning)));
+    let mut mapping = mapping_with_loops(1);
+    mapping.0.insert(
+        Selectable::node(2),
+        PropertyMap::new().with_display(DisplayMode::ElementTag("text".to_owned())),
+    );
+    renderer.update(mapping);
+    let vis_tree = renderer.reclaim_vis_tree();
+    assert_eq!(
+        vis_tree.elements,
+        expect_elements![{ tag_name: "text".to_owned() }]
+    );
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code(), "skipped-entity");
+}
+
+#[test]
+fn warning_policy_abort() {
+    let mut warnings = Vec::new();
+    let mut renderer = VisTreeWriter::new(TestVisTree::default())
+        .with_warning_policy(WarningPolicy::Abort)
+        .with_warning_handler(Box::new(|warning| warnings.push(warning)));
+    renderer.update(mapping![0 => { display: Some(DisplayMode::ElementTag("cell".to_owned())) }]);
+    renderer.update(mapping_with_loops(1));
+    let vis_tree = renderer.reclaim_vis_tree();
+    // The invalid update has not touched the tree
+    assert_eq!(
+        vis_tree.elements,
+        expect_elements![{ tag_name: "cell".to_owned() }]
+    );
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code(), "rejected-update");
+    assert_eq!(warnings[0].severity(), WarningSeverity::Error);
+}
+
+#[test]
+fn validate_valid_mapping() {
+    let renderer = VisTreeWriter::new(TestVisTree::default());
+    let violations = renderer.validate(&mapping![
+        0 => { display: Some(DisplayMode::ElementTag("cell".to_owned())) },
+        1 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(0)),
+        },
+        2 => {
+            display: Some(DisplayMode::Connector),
+            parent: Some(Selectable::node(0)),
+            target: Some(Selectable::node(1)),
+        },
+    ]);
+    assert_eq!(violations, []);
+}
+
+#[test]
+fn validate_loop_in_vis_tree() {
+    let renderer = VisTreeWriter::new(TestVisTree::default());
+    let mut violations = renderer.validate(&mapping![
+        0 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(1)),
+        },
+        1 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(0)),
+        },
+        2 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(0)),
+        },
+    ]);
+    violations.sort_by_key(|v| format!("{v}"));
+    assert_eq!(
+        violations,
+        [
+            MappingViolation::ParentCycle(Selectable::node(0)),
+            MappingViolation::ParentCycle(Selectable::node(1)),
+        ]
+    );
+    // Validation must not touch the tree
+    let vis_tree = renderer.reclaim_vis_tree();
+    assert!(vis_tree.elements.is_empty());
+}
+
+#[test]
+fn validate_duplicate_stable_keys() {
+    let renderer = VisTreeWriter::new(TestVisTree::default());
+    let mut violations = renderer.validate(&mapping![
+        0 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            stable_key: Some("a".to_owned()),
+        },
+        1 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            stable_key: Some("a".to_owned()),
+        },
+        2 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            stable_key: Some("b".to_owned()),
+        },
+        3 => { stable_key: Some("b".to_owned()) },
+    ]);
+    violations.sort_by_key(|v| format!("{v}"));
+    assert_eq!(
+        violations,
+        [
+            MappingViolation::DuplicateStableKey(Selectable::node(0)),
+            MappingViolation::DuplicateStableKey(Selectable::node(1)),
+        ]
+    );
+}
+
+#[test]
+fn validate_reports_all_violations() {
+    let renderer = VisTreeWriter::new(TestVisTree::default());
+    let mut violations = renderer.validate(&mapping![
+        0 => {
+            display: Some(DisplayMode::Connector),
+            target: Some(Selectable::node(2)),
+        },
+        1 => {
+            display: Some(DisplayMode::ElementTag("cell".to_owned())),
+            parent: Some(Selectable::node(0)),
+        },
+        2 => {
+            display: Some(DisplayMode::Connector),
+            parent: Some(Selectable::node(5)),
+            target: Some(Selectable::node(0)),
+        },
+        3 => {
+            display: Some(DisplayMode::Connector),
+            parent: Some(Selectable::node(1)),
+            target: Some(Selectable::node(4)),
+        },
+        4 => { display: None },
+    ]);
+    violations.sort_by_key(|v| format!("{v}"));
+    assert_eq!(
+        violations,
+        [
+            MappingViolation::TargetCycle(Selectable::node(0)),
+            MappingViolation::TargetCycle(Selectable::node(2)),
+            MappingViolation::ConnectorParent(Selectable::node(1)),
+            MappingViolation::MissingParent(Selectable::node(2)),
+            MappingViolation::MissingTarget(Selectable::node(3)),
+        ]
+    );
+}
@@ -0,0 +1,162 @@
+//! Tests for [`History`].
+
+use aili_model::{
+    fixture::FixtureGraph,
+    graph,
+    state::{EdgeLabel, NodeValue, ProgramStateGraph, ProgramStateNode},
+    vis::memory::MemoryVisTree,
+};
+use aili_style::{
+    cascade::{CascadeStyle, CompiledStylesheet},
+    selectable::Selectable,
+    stylesheet::{StyleKey::*, expression::*, selector::*, *},
+};
+use aili_translate::{
+    cascade::apply_stylesheet,
+    history::History,
+    pipeline::Pipeline,
+    property::{PropertyKey, PropertyKey::*},
+};
+
+/// Stylesheet that displays the root and its main successor.
+///
+/// ```text
+/// :: {
+///   display: graph;
+/// }
+/// :: main {
+///   display: cell;
+///   value: @;
+/// }
+/// ```
+fn stylesheet() -> Stylesheet<PropertyKey> {
+    Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("graph".to_owned()),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
+            properties: vec![
+                StyleClause {
+                    key: Property(Display),
+                    value: Expression::String("cell".to_owned()),
+                },
+                StyleClause {
+                    key: Property(Attribute("value".into())),
+                    value: Expression::Select(LimitedSelector::default().into()),
+                },
+            ],
+        },
+    ])
+}
+
+fn record(history: &mut History<usize>, graph: &FixtureGraph) {
+    let stylesheet = CascadeStyle::<PropertyKey>::from(stylesheet());
+    history.record(graph, apply_stylesheet(&stylesheet, graph));
+}
+
+#[test]
+fn empty_history() {
+    let mut history = History::<usize>::new();
+    assert!(history.is_empty());
+    assert_eq!(history.position(), None);
+    assert!(history.current().is_none());
+    assert!(history.step_forward().is_none());
+    assert!(history.step_backward().is_none());
+    assert!(history.diff(0, 0).is_none());
+}
+
+#[test]
+fn seek_through_steps() {
+    let mut history = History::new();
+    for value in 0i64..3 {
+        record(&mut history, &graph! { 0 -main-> 1 [val = { value }]; });
+    }
+    assert_eq!(history.len(), 3);
+    assert_eq!(history.position(), Some(2));
+    assert!(history.step_forward().is_none());
+    assert_eq!(history.position(), Some(2));
+    let value_at = |history: &History<usize>| {
+        history
+            .current()
+            .unwrap()
+            .graph
+            .get(&1)
+            .and_then(|node| node.value())
+    };
+    history.step_backward().unwrap();
+    assert_eq!(value_at(&history), Some(NodeValue::Int(1)));
+    history.seek(0).unwrap();
+    assert_eq!(value_at(&history), Some(NodeValue::Int(0)));
+    assert!(history.step_backward().is_none());
+    assert!(history.seek(3).is_none());
+    assert_eq!(history.position(), Some(0));
+    history.step_forward().unwrap();
+    assert_eq!(value_at(&history), Some(NodeValue::Int(1)));
+}
+
+#[test]
+fn recorded_graph_resolves_like_the_original() {
+    let graph = graph! {
+        0 -main-> 1 [val = 5];
+        1 -"a"-> 2 [val = 6];
+    };
+    let mut history = History::new();
+    record(&mut history, &graph);
+    let step = history.current().unwrap();
+    assert_eq!(step.graph.node_count(), 3);
+    let stylesheet = CascadeStyle::<PropertyKey>::from(stylesheet());
+    assert_eq!(apply_stylesheet(&stylesheet, &step.graph), step.mapping);
+}
+
+#[test]
+fn diff_between_steps() {
+    let mut history = History::new();
+    record(
+        &mut history,
+        &graph! {
+            0 -main-> 1 [val = 1];
+            1 -"a"-> 2;
+        },
+    );
+    record(
+        &mut history,
+        &graph! {
+            0 -main-> 1 [val = 2];
+            1 -"b"-> 3;
+        },
+    );
+    let diff = history.diff(0, 1).unwrap();
+    assert_eq!(diff.added_nodes, [3].into());
+    assert_eq!(diff.removed_nodes, [2].into());
+    assert_eq!(diff.changed_nodes, [1].into());
+    assert!(diff.added_entities.is_empty());
+    assert!(diff.removed_entities.is_empty());
+    assert_eq!(diff.changed_entities, [Selectable::node(1)].into());
+    assert!(history.diff(1, 1).unwrap().is_empty());
+}
+
+#[test]
+fn pipeline_records_renders() {
+    let mut pipeline = Pipeline::new(FixtureGraph::new(), MemoryVisTree::new());
+    pipeline.set_recording_enabled(true);
+    pipeline
+        .set_stylesheet(CompiledStylesheet::compile(stylesheet()))
+        .unwrap();
+    pipeline.step(|graph| graph.add_edge(0, EdgeLabel::Main, 1));
+    // Swapping the stylesheet does not record a step
+    pipeline
+        .set_stylesheet(CompiledStylesheet::compile(stylesheet()))
+        .unwrap();
+    let history = pipeline.take_history().unwrap();
+    assert_eq!(history.len(), 2);
+    let diff = history.diff(0, 1).unwrap();
+    assert_eq!(diff.added_nodes, [1].into());
+    assert_eq!(diff.added_entities, [Selectable::node(1)].into());
+    assert_eq!(history.get(0).unwrap().mapping.0.len(), 1);
+    assert!(pipeline.history().is_none());
+}
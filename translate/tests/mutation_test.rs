@@ -0,0 +1,184 @@
+//! Tests for [`MutationRecorder`] and [`MutationPlayer`].
+
+mod test_vis;
+
+use aili_style::selectable::Selectable;
+use aili_translate::{
+    forward::VisTreeWriter,
+    mutation::{Mutation, MutationPlayer, MutationRecorder},
+    property::{DisplayMode, PropertyMap},
+};
+use test_vis::*;
+
+fn element(tag_name: &str, parent: Option<usize>) -> PropertyMap<usize> {
+    PropertyMap {
+        display: Some(DisplayMode::ElementTag(tag_name.to_owned())),
+        parent: parent.map(Selectable::node),
+        ..PropertyMap::default()
+    }
+}
+
+#[test]
+fn removed_rendering_is_recorded() {
+    let mut writer = VisTreeWriter::new(MutationRecorder::new());
+    writer.update(
+        [
+            (Selectable::node(0), element("graph", None)),
+            (Selectable::node(1), element("cell", Some(0))),
+        ]
+        .into(),
+    );
+    let created = writer.vis_tree_mut().take_mutations();
+    let child = created
+        .iter()
+        .find_map(|mutation| match mutation {
+            Mutation::CreateElement { id, tag_name } if tag_name == "cell" => Some(*id),
+            _ => None,
+        })
+        .expect("Child element should have been created");
+    writer.update([(Selectable::node(0), element("graph", None))].into());
+    assert_eq!(
+        writer.vis_tree_mut().take_mutations(),
+        [
+            Mutation::SetParent {
+                element: child,
+                parent: None,
+            },
+            Mutation::RemoveElement { element: child },
+        ]
+    );
+}
+
+#[test]
+fn removed_element_cannot_be_used() {
+    use aili_model::vis::VisTree;
+    let mut recorder = MutationRecorder::new();
+    let element = recorder.add_element("cell");
+    recorder.remove_element(&element).unwrap();
+    assert!(recorder.get_element(&element).is_err());
+    assert!(recorder.set_root(Some(&element)).is_err());
+    assert!(recorder.remove_element(&element).is_err());
+}
+
+#[test]
+fn snapshot_skips_removed_elements() {
+    let mut writer = VisTreeWriter::new(MutationRecorder::new());
+    writer.update_roots(vec![Selectable::node(0)]);
+    writer.update(
+        [
+            (Selectable::node(0), element("graph", None)),
+            (Selectable::node(1), element("cell", Some(0))),
+        ]
+        .into(),
+    );
+    writer.update(
+        [
+            (Selectable::node(0), element("graph", None)),
+            (Selectable::node(2), element("text", Some(0))),
+        ]
+        .into(),
+    );
+    let mut player = MutationPlayer::new(TestVisTree::default());
+    player.apply_all(&writer.vis_tree().snapshot()).unwrap();
+    let tree = player.into_vis_tree();
+    let root = tree.root_index.expect("Root should be set");
+    let text = tree.expect_find_element(|element| element.tag_name == "text");
+    assert_eq!(tree.elements[text].parent_index, Some(root));
+    let cell = tree.expect_find_element(|element| element.tag_name == "cell");
+    assert_eq!(tree.elements[cell].parent_index, None);
+}
+
+#[test]
+fn multiple_roots_are_recorded_and_replayed() {
+    let mut writer = VisTreeWriter::new(MutationRecorder::new());
+    writer.update_roots(vec![Selectable::node(0), Selectable::node(1)]);
+    writer.update(
+        [
+            (Selectable::node(0), element("graph", None)),
+            (Selectable::node(1), element("cell", None)),
+        ]
+        .into(),
+    );
+    let mutations = writer.vis_tree_mut().take_mutations();
+    let created = |tag: &str| {
+        mutations
+            .iter()
+            .find_map(|mutation| match mutation {
+                Mutation::CreateElement { id, tag_name } if tag_name == tag => Some(*id),
+                _ => None,
+            })
+            .expect("Element should have been created")
+    };
+    let (graph, cell) = (created("graph"), created("cell"));
+    assert_eq!(
+        mutations.last(),
+        Some(&Mutation::SetRoots {
+            elements: vec![graph, cell]
+        })
+    );
+    // Dropping back to a single root is recorded the usual way
+    writer.update_roots(vec![Selectable::node(1)]);
+    assert_eq!(
+        writer.vis_tree_mut().take_mutations(),
+        [Mutation::SetRoot {
+            element: Some(cell)
+        }]
+    );
+    writer.update_roots(vec![Selectable::node(1), Selectable::node(0)]);
+    let mut player = MutationPlayer::new(TestVisTree::default());
+    player.apply_all(&writer.vis_tree().snapshot()).unwrap();
+    let tree = player.into_vis_tree();
+    let graph = tree.expect_find_element(|element| element.tag_name == "graph");
+    let cell = tree.expect_find_element(|element| element.tag_name == "cell");
+    assert_eq!(tree.root_indices, [cell, graph]);
+}
+
+#[test]
+fn pin_attached_to_connector_is_replayed() {
+    let mut writer = VisTreeWriter::new(MutationRecorder::new());
+    let connector = |target: usize| PropertyMap {
+        display: Some(DisplayMode::Connector),
+        parent: Some(Selectable::node(0)),
+        target: Some(Selectable::node(target)),
+        ..PropertyMap::default()
+    };
+    writer.update(
+        [
+            (Selectable::node(0), element("cell", None)),
+            (Selectable::node(1), connector(0)),
+            (Selectable::node(2), connector(1)),
+        ]
+        .into(),
+    );
+    let mutations = writer.vis_tree_mut().take_mutations();
+    let attached = mutations
+        .iter()
+        .filter(|mutation| matches!(mutation, Mutation::SetPinTargetConnector { .. }))
+        .count();
+    assert_eq!(attached, 1);
+    let mut player = MutationPlayer::new(TestVisTree::default());
+    player.apply_all(&writer.vis_tree().snapshot()).unwrap();
+    let tree = player.into_vis_tree();
+    let attached_pins = tree
+        .connectors
+        .iter()
+        .filter_map(|connector| connector.end.target_connector_index)
+        .collect::<Vec<_>>();
+    assert_eq!(attached_pins.len(), 1);
+    assert!(tree.connectors[attached_pins[0]].end.target_index.is_some());
+}
+
+#[test]
+fn layer_is_recorded_and_replayed() {
+    let mut writer = VisTreeWriter::new(MutationRecorder::new());
+    writer.update([(Selectable::node(0), element("cell", None).with_layer(1))].into());
+    let mutations = writer.vis_tree_mut().take_mutations();
+    assert!(mutations.contains(&Mutation::SetLayer {
+        element: 0,
+        layer: 1
+    }));
+    let mut player = MutationPlayer::new(TestVisTree::default());
+    player.apply_all(&writer.vis_tree().snapshot()).unwrap();
+    let tree = player.into_vis_tree();
+    assert_eq!(tree.elements[0].layer, 1);
+}
@@ -0,0 +1,190 @@
+//! Tests for [`Pipeline`].
+
+mod test_graph;
+mod test_vis;
+
+use aili_model::{
+    fixture::FixtureGraph,
+    state::EdgeLabel,
+    vis::{AttributeMap, VisTree},
+};
+use aili_style::{
+    cascade::CompiledStylesheet,
+    stylesheet::{StyleKey::*, expression::*, selector::*, *},
+};
+use aili_translate::{pipeline::Pipeline, property::PropertyKey::*};
+use test_graph::TestGraph;
+use test_vis::TestVisTree;
+
+/// Constructs a stylesheet that displays the root
+/// as an element with an attribute that holds its lifetime.
+fn root_stylesheet(
+    tag_name: &str,
+    attribute: &str,
+) -> CompiledStylesheet<aili_translate::property::PropertyKey> {
+    // :: {
+    //   display: <tag_name>;
+    //   <attribute>: <lifetime>;
+    // }
+    CompiledStylesheet::compile(Stylesheet::new(vec![StyleRule {
+        selector: Selector::default(),
+        properties: vec![
+            StyleClause {
+                key: Property(Display),
+                value: Expression::String(tag_name.to_owned()),
+            },
+            StyleClause {
+                key: Property(Attribute(attribute.into())),
+                value: Expression::MagicVariable(MagicVariableKey::Lifetime),
+            },
+        ],
+    }]))
+}
+
+/// Constructs a stylesheet that displays the root
+/// and its main successor, which has an invalid stroke width.
+fn main_stylesheet() -> CompiledStylesheet<aili_translate::property::PropertyKey> {
+    // :: {
+    //   display: graph;
+    // }
+    // :: main {
+    //   display: cell;
+    //   stroke-width: "wide";
+    // }
+    CompiledStylesheet::compile(Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("graph".to_owned()),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
+            properties: vec![
+                StyleClause {
+                    key: Property(Display),
+                    value: Expression::String("cell".to_owned()),
+                },
+                StyleClause {
+                    key: Property(Attribute("stroke-width".into())),
+                    value: Expression::String("wide".to_owned()),
+                },
+            ],
+        },
+    ]))
+}
+
+#[test]
+fn stylesheet_swap_keeps_elements() {
+    let mut pipeline = Pipeline::new(TestGraph::default_graph(), TestVisTree::default());
+    pipeline
+        .set_stylesheet(root_stylesheet("cell", "a"))
+        .unwrap();
+    let root = pipeline
+        .writer()
+        .vis_tree()
+        .root_index
+        .expect("Root should be set");
+    pipeline
+        .writer_mut()
+        .vis_tree_mut()
+        .get_element(&root)
+        .unwrap()
+        .set_attribute("x", Some("10"));
+    pipeline
+        .set_stylesheet(root_stylesheet("cell", "b"))
+        .unwrap();
+    let vis_tree = pipeline.writer().vis_tree();
+    assert_eq!(vis_tree.elements.len(), 1);
+    assert_eq!(vis_tree.root_index, Some(root));
+    let attributes = &vis_tree.elements[root].attributes;
+    assert_eq!(attributes.get("x").map(String::as_str), Some("10"));
+    assert_eq!(attributes.get("a"), None);
+    assert_eq!(attributes.get("b").map(String::as_str), Some("new"));
+}
+
+#[test]
+fn stylesheet_swap_recreates_elements_with_changed_display() {
+    let mut pipeline = Pipeline::new(TestGraph::default_graph(), TestVisTree::default());
+    pipeline
+        .set_stylesheet(root_stylesheet("cell", "a"))
+        .unwrap();
+    pipeline
+        .set_stylesheet(root_stylesheet("kvt", "a"))
+        .unwrap();
+    let vis_tree = pipeline.writer().vis_tree();
+    let kvt = vis_tree.expect_find_element(|element| element.tag_name == "kvt");
+    assert_eq!(vis_tree.root_index, Some(kvt));
+}
+
+#[test]
+fn stylesheet_swap_keeps_lifetimes() {
+    let mut pipeline = Pipeline::new(TestGraph::default_graph(), TestVisTree::default());
+    pipeline.render();
+    pipeline
+        .set_stylesheet(root_stylesheet("cell", "a"))
+        .unwrap();
+    let vis_tree = pipeline.writer().vis_tree();
+    let root = vis_tree.root_index.expect("Root should be set");
+    assert_eq!(
+        vis_tree.elements[root]
+            .attributes
+            .get("a")
+            .map(String::as_str),
+        Some("new")
+    );
+    pipeline.render();
+    let vis_tree = pipeline.writer().vis_tree();
+    assert_eq!(
+        vis_tree.elements[root]
+            .attributes
+            .get("a")
+            .map(String::as_str),
+        None
+    );
+}
+
+#[test]
+fn step_updates_and_renders_graph() {
+    let mut pipeline = Pipeline::new(FixtureGraph::new(), TestVisTree::default());
+    pipeline.set_stylesheet(main_stylesheet()).unwrap();
+    assert_eq!(pipeline.writer().vis_tree().elements.len(), 1);
+    pipeline.step(|graph| graph.add_edge(0, EdgeLabel::Main, 1));
+    let vis_tree = pipeline.writer().vis_tree();
+    let cell = vis_tree.expect_find_element(|element| element.tag_name == "cell");
+    let graph = vis_tree.expect_find_element(|element| element.tag_name == "graph");
+    assert_eq!(vis_tree.elements[cell].parent_index, Some(graph));
+}
+
+#[test]
+fn cascade_warnings_are_reported() {
+    let mut attributes = Vec::new();
+    let mut pipeline = Pipeline::new(
+        FixtureGraph::new().with_edge(0, EdgeLabel::Main, 1),
+        TestVisTree::default(),
+    )
+    .with_cascade_warning_handler(Box::new(|warning| attributes.push(warning.attribute)));
+    pipeline.set_stylesheet(main_stylesheet()).unwrap();
+    drop(pipeline);
+    assert_eq!(attributes, ["stroke-width"]);
+}
+
+#[test]
+fn stats_describe_last_render() {
+    let mut pipeline = Pipeline::new(
+        FixtureGraph::new().with_edge(0, EdgeLabel::Main, 1),
+        TestVisTree::default(),
+    );
+    pipeline.set_stylesheet(main_stylesheet()).unwrap();
+    assert_eq!(pipeline.stats(), None);
+    pipeline.set_stats_enabled(true);
+    pipeline.render();
+    assert_eq!(pipeline.stats().unwrap().rules_evaluated, 2);
+    // Counters do not accumulate across renders
+    pipeline.render();
+    assert_eq!(pipeline.stats().unwrap().rules_evaluated, 2);
+    pipeline.set_stats_enabled(false);
+    pipeline.render();
+    assert_eq!(pipeline.stats(), None);
+}
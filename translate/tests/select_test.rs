@@ -2,18 +2,21 @@
 
 mod test_graph;
 
-use aili_model::state::{EdgeLabel, ProgramStateNode, RootedProgramStateGraph};
+use aili_model::state::{EdgeLabel, ProgramStateGraph, ProgramStateNode, RootedProgramStateGraph};
 use aili_style::{
     cascade::{CascadeSelector, CascadeStyle, SelectorResolver},
     eval::context::EvaluationContext,
     stylesheet::{StyleRule, Stylesheet, expression::*, selector::*},
 };
-use std::collections::HashSet;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
 use test_graph::TestGraph;
 
 /// Constructs a compiled stylesheet from one selector
 fn construct_style(selector: Selector) -> CascadeStyle {
-    Stylesheet(vec![StyleRule {
+    Stylesheet::new(vec![StyleRule {
         selector,
         properties: Vec::new(),
     }])
@@ -98,6 +101,30 @@ impl<'a, T: RootedProgramStateGraph> GetSelectorMatches<'a, T> {
     }
 }
 
+/// Graph that counts how many times each of its nodes has been looked up.
+struct CountingGraph {
+    /// The graph whose nodes are counted.
+    graph: TestGraph,
+
+    /// Number of lookups of each node.
+    lookups: RefCell<HashMap<usize, usize>>,
+}
+
+impl ProgramStateGraph for CountingGraph {
+    type NodeId = usize;
+    type NodeRef<'a> = <TestGraph as ProgramStateGraph>::NodeRef<'a>;
+    fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
+        *self.lookups.borrow_mut().entry(*id).or_default() += 1;
+        self.graph.get(id)
+    }
+}
+
+impl RootedProgramStateGraph for CountingGraph {
+    fn root(&self) -> Self::NodeId {
+        self.graph.root()
+    }
+}
+
 #[test]
 fn select_main_and_any_number_of_named() {
     // main iter(named)
@@ -136,7 +163,7 @@ fn select_named_anywhere() {
     let style = construct_style(Selector::from_path(
         [
             SelectorSegment::anything_any_number_of_times(),
-            SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
+            SelectorSegment::Match(EdgeMatcher::Named("a".into())),
         ]
         .into(),
     ));
@@ -150,8 +177,8 @@ fn select_named_successor_of_named_anywhere() {
     let style = construct_style(Selector::from_path(
         [
             SelectorSegment::anything_any_number_of_times(),
-            SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
-            SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
+            SelectorSegment::Match(EdgeMatcher::Named("a".into())),
+            SelectorSegment::Match(EdgeMatcher::Named("a".into())),
         ]
         .into(),
     ));
@@ -164,8 +191,8 @@ fn select_dereference_anywhere_after_double_named() {
     // "a" "a" iter(*) deref
     let style = construct_style(Selector::from_path(
         [
-            SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
-            SelectorSegment::Match(EdgeMatcher::Named("a".to_owned())),
+            SelectorSegment::Match(EdgeMatcher::Named("a".into())),
+            SelectorSegment::Match(EdgeMatcher::Named("a".into())),
             SelectorSegment::anything_any_number_of_times(),
             SelectorSegment::Match(EdgeLabel::Deref.into()),
         ]
@@ -199,7 +226,7 @@ fn select_next_frame_or_named() {
             SelectorSegment::AnyNumberOfTimes(
                 [SelectorSegment::Branch(vec![
                     [SelectorSegment::Match(EdgeLabel::Next.into())].into(),
-                    [SelectorSegment::Match(EdgeMatcher::Named("a".to_owned()))].into(),
+                    [SelectorSegment::Match(EdgeMatcher::Named("a".into()))].into(),
                 ])]
                 .into(),
             ),
@@ -273,3 +300,29 @@ fn select_stack_top_node() {
     let matched = get_selector_matches(style.selector_machine(), &TestGraph::default_graph());
     assert_eq!(matched, [4].into());
 }
+
+#[test]
+fn conditions_are_evaluated_once_per_node() {
+    // iter(*).if(@(deref))
+    let style = construct_style(Selector::from_path(
+        [
+            SelectorSegment::anything_any_number_of_times(),
+            SelectorSegment::Condition(Expression::Select(
+                LimitedSelector::from_path([EdgeLabel::Deref.into()]).into(),
+            )),
+        ]
+        .into(),
+    ));
+    let graph = CountingGraph {
+        graph: TestGraph::default_graph(),
+        lookups: RefCell::default(),
+    };
+    let matched = get_selector_matches(style.selector_machine(), &graph);
+    assert_eq!(matched, [5, 7, 8, 12, 13].into());
+    // Each node is looked up once when its edges are traversed
+    // and once when the condition is evaluated,
+    // even though some nodes are reachable along many paths
+    let lookups = graph.lookups.into_inner();
+    assert_eq!(lookups.len(), 14);
+    assert!(lookups.values().all(|&count| count == 2));
+}
@@ -0,0 +1,36 @@
+//! Tests for serialization of property mappings.
+
+#![cfg(feature = "serde")]
+
+use aili_model::state::EdgeLabel;
+use aili_style::selectable::Selectable;
+use aili_translate::property::{DisplayMode, EntityPropertyMapping, FragmentKey, PropertyMap};
+
+#[test]
+fn property_mapping_round_trip() {
+    let mapping: EntityPropertyMapping<usize> = [
+        (
+            Selectable::node(0),
+            PropertyMap::new()
+                .with_display(DisplayMode::ElementTag("cell".to_owned()))
+                .with_attribute("value".to_owned(), "42".to_owned()),
+        ),
+        (
+            Selectable::edge(0, EdgeLabel::Named("a".into(), 0)),
+            PropertyMap::new()
+                .with_display(DisplayMode::Connector)
+                .with_parent(Selectable::node(0))
+                .with_target(Selectable::node(1).with_extra("label".to_owned()))
+                .with_fragment_attribute(FragmentKey::End, "style".to_owned(), "dashed".to_owned()),
+        ),
+        (
+            Selectable::node(1).with_extra("label".to_owned()),
+            PropertyMap::new(),
+        ),
+    ]
+    .into();
+    let json = serde_json::to_string(&mapping).expect("Mapping should serialize");
+    let deserialized: EntityPropertyMapping<usize> =
+        serde_json::from_str(&json).expect("Mapping should deserialize");
+    assert_eq!(deserialized, mapping);
+}
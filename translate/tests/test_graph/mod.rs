@@ -1,10 +1,9 @@
 //! Stub program state graphs for testing.
 
-use aili_model::state::*;
-use std::collections::HashMap;
+use aili_model::{fixture::FixtureGraph, graph, state::*};
 
 /// Stub graph for testing graph-based code.
-pub struct TestGraph(Vec<TestNode>);
+pub struct TestGraph(FixtureGraph);
 
 impl TestGraph {
     /// Shorthand for a pre-constructed graph for running tests.
@@ -28,46 +27,54 @@ impl TestGraph {
          *      ([9])                |             /
          *                           +------------+
          */
-        use EdgeLabel::*;
-        Self(vec![
-            /* 0 */
-            TestNode([(Main, 1), (Named("a".to_owned(), 0), 5)].into(), None),
-            /* 1 */
-            TestNode([(Next, 2), (Named("a".to_owned(), 0), 10)].into(), None),
-            /* 2 */ TestNode([(Next, 3)].into(), None),
-            /* 3 */
-            TestNode([(Next, 4), (Named("a".to_owned(), 0), 7)].into(), None),
-            /* 4 */ TestNode([(Result, 13)].into(), None),
-            /* 5 */
-            TestNode(
-                [(Named("a".to_owned(), 0), 6), (Index(0), 8), (Deref, 10)].into(),
-                Some(Self::NUMERIC_NODE_VALUE.into()),
-            ),
-            /* 6 */
-            TestNode(
-                [
-                    (Named("a".to_owned(), 0), 11),
-                    (Named("b".to_owned(), 0), 7),
-                ]
-                .into(),
-                Some(3u64.into()),
-            ),
-            /* 7 */ TestNode([(Deref, 5)].into(), None),
-            /* 8 */ TestNode([(Deref, 9)].into(), None),
-            /* 9 */ TestNode([].into(), None),
-            /* 10 */
-            TestNode(
-                [
-                    (Named("a".to_owned(), 0), 11),
-                    (Named("a".to_owned(), 1), 12),
-                ]
-                .into(),
-                None,
-            ),
-            /* 11 */ TestNode([(Index(0), 13), (Index(1), 12)].into(), None),
-            /* 12 */ TestNode([(Deref, 10)].into(), None),
-            /* 13 */ TestNode([(Deref, 12)].into(), None),
-        ])
+        Self(graph! {
+            0 -main-> 1;
+            0 -"a"-> 5 [val = { Self::NUMERIC_NODE_VALUE }];
+            1 -next-> 2;
+            1 -"a"-> 10;
+            2 -next-> 3;
+            3 -next-> 4;
+            3 -"a"-> 7;
+            4 -ret-> 13;
+            5 -"a"-> 6 [val = 3u64];
+            5 -[0]-> 8;
+            5 -ref-> 10;
+            6 -"a"-> 11;
+            6 -"b"-> 7;
+            7 -ref-> 5;
+            8 -ref-> 9;
+            10 -"a"-> 11;
+            10 -"a"#1-> 12;
+            11 -[0]-> 13;
+            11 -[1]-> 12;
+            12 -ref-> 10;
+            13 -ref-> 12;
+        })
+    }
+
+    /// Doubly-linked list of three nodes, which has cycles.
+    #[allow(dead_code, reason = "Not every test suite needs cycles")]
+    pub fn cyclic_list() -> Self {
+        /*         next         next
+         *  ([0])-------->([1])-------->([2])
+         *       <--------     <--------
+         *         "prev"        "prev"
+         */
+        Self(graph! {
+            0 -next-> 1;
+            1 -next-> 2;
+            1 -"prev"-> 0;
+            2 -"prev"-> 1;
+        })
+    }
+
+    /// Removes an edge from the graph.
+    ///
+    /// Nodes that were only reachable through the edge
+    /// stay in the graph, but they become unreachable.
+    #[allow(dead_code, reason = "Not every test suite modifies graphs")]
+    pub fn without_edge(self, node: usize, edge: &EdgeLabel) -> Self {
+        Self(self.0.without_edge(node, edge))
     }
 
     /// Value of the node selected by
@@ -78,40 +85,14 @@ impl TestGraph {
 
 impl ProgramStateGraph for TestGraph {
     type NodeId = usize;
-    type NodeRef<'a> = &'a TestNode;
+    type NodeRef<'a> = <FixtureGraph as ProgramStateGraph>::NodeRef<'a>;
     fn get(&self, id: &Self::NodeId) -> Option<Self::NodeRef<'_>> {
-        self.0.get(*id)
+        self.0.get(id)
     }
 }
 
 impl RootedProgramStateGraph for TestGraph {
     fn root(&self) -> Self::NodeId {
-        0
-    }
-}
-
-/// Node of [`TestGraph`].
-pub struct TestNode(HashMap<EdgeLabel, usize>, Option<NodeValue>);
-
-impl ProgramStateNode for &TestNode {
-    type NodeId = usize;
-    type NodeTypeId<'a>
-        = &'a str
-    where
-        Self: 'a;
-    fn get_successor(&self, edge: &EdgeLabel) -> Option<Self::NodeId> {
-        self.0.get(edge).copied()
-    }
-    fn successors(&self) -> impl Iterator<Item = (&EdgeLabel, Self::NodeId)> {
-        self.0.iter().map(|(k, v)| (k, *v))
-    }
-    fn node_type_class(&self) -> NodeTypeClass {
-        NodeTypeClass::Root
-    }
-    fn node_type_id(&self) -> Option<Self::NodeTypeId<'_>> {
-        None
-    }
-    fn value(&self) -> Option<NodeValue> {
-        self.1
+        self.0.root()
     }
 }
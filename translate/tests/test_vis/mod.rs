@@ -8,6 +8,7 @@ pub struct TestVisTree {
     pub elements: Vec<TestVisElement>,
     pub connectors: Vec<TestVisConnector>,
     pub root_index: Option<usize>,
+    pub root_indices: Vec<usize>,
 }
 
 #[derive(PartialEq, Eq, Debug, Default)]
@@ -15,6 +16,7 @@ pub struct TestVisElement {
     pub tag_name: String,
     pub attributes: HashMap<String, String>,
     pub parent_index: Option<usize>,
+    pub layer: i32,
 }
 
 pub struct TestVisElementRef<'a>(&'a mut TestVisTree, usize);
@@ -30,6 +32,7 @@ pub struct TestVisConnector {
 pub struct TestVisPin {
     pub attributes: HashMap<String, String>,
     pub target_index: Option<usize>,
+    pub target_connector_index: Option<usize>,
 }
 
 impl VisTree for TestVisTree {
@@ -67,6 +70,13 @@ impl VisTree for TestVisTree {
 
     fn set_root(&mut self, handle: Option<&Self::ElementHandle>) -> Result<(), InvalidHandle> {
         self.root_index = handle.copied();
+        self.root_indices = Vec::from_iter(handle.copied());
+        Ok(())
+    }
+
+    fn set_roots(&mut self, handles: &[Self::ElementHandle]) -> Result<(), InvalidHandle> {
+        self.root_index = handles.first().copied();
+        self.root_indices = handles.to_vec();
         Ok(())
     }
 }
@@ -98,6 +108,10 @@ impl VisElement for TestVisElementRef<'_> {
             Ok(())
         }
     }
+
+    fn set_layer(&mut self, layer: i32) {
+        self.element_mut().layer = layer;
+    }
 }
 
 impl AttributeMap for &mut TestVisConnector {
@@ -116,6 +130,7 @@ impl AttributeMap for &mut TestVisConnector {
 
 impl VisConnector for &mut TestVisConnector {
     type Handle = usize;
+    type ConnectorHandle = usize;
     type PinRef<'a>
         = &'a mut TestVisPin
     where
@@ -146,9 +161,20 @@ impl AttributeMap for &mut TestVisPin {
 
 impl VisPin for &mut TestVisPin {
     type Handle = usize;
+    type ConnectorHandle = usize;
 
     fn attach_to(&mut self, target: Option<&Self::Handle>) -> Result<(), InvalidHandle> {
         self.target_index = target.copied();
+        self.target_connector_index = None;
+        Ok(())
+    }
+
+    fn attach_to_connector(
+        &mut self,
+        target: Option<&Self::ConnectorHandle>,
+    ) -> Result<(), InvalidHandle> {
+        self.target_index = None;
+        self.target_connector_index = target.copied();
         Ok(())
     }
 }
@@ -0,0 +1,277 @@
+//! Tests for the [`testing`](aili_translate::testing) utilities.
+
+#![cfg(feature = "testing")]
+
+mod test_graph;
+
+use aili_model::{graph, state::EdgeLabel};
+use aili_style::{
+    cascade::{CascadeStyle, CompiledStylesheet},
+    selectable::Selectable,
+    stylesheet::{StyleKey::*, expression::*, selector::*, *},
+};
+use aili_translate::{
+    assert_mappings_eq, assert_styles,
+    property::{
+        DisplayMode, EntityPropertyMapping, FragmentKey, PropertyKey, PropertyKey::*, PropertyMap,
+    },
+    testing::*,
+};
+use test_graph::TestGraph;
+
+/// Stylesheet that displays the root
+/// and its main successor.
+///
+/// ```text
+/// :: {
+///   display: graph;
+/// }
+/// :: main {
+///   display: cell;
+///   value: 42;
+/// }
+/// ```
+fn stylesheet() -> CascadeStyle<PropertyKey> {
+    CascadeStyle::from(Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("graph".to_owned()),
+            }],
+        },
+        StyleRule {
+            selector: Selector::from_path([SelectorSegment::Match(EdgeLabel::Main.into())].into()),
+            properties: vec![
+                StyleClause {
+                    key: Property(Display),
+                    value: Expression::String("cell".to_owned()),
+                },
+                StyleClause {
+                    key: Property(Attribute("value".into())),
+                    value: Expression::Int(42),
+                },
+            ],
+        },
+    ]))
+}
+
+#[test]
+fn matching_styles() {
+    assert_styles!(&stylesheet(), TestGraph::default_graph(), {
+        node(0) => { "display" == "graph" },
+        node(1) => { "display" == "cell", "value" == 42, "parent" == node(0) },
+    });
+}
+
+#[test]
+#[should_panic(expected = "1 value: expected \"43\", found \"42\"")]
+fn mismatched_styles() {
+    assert_styles!(&stylesheet(), TestGraph::default_graph(), {
+        node(1) => { "value" == 43 },
+    });
+}
+
+#[test]
+fn diff_lists_differences_in_order() {
+    let expected = [
+        (
+            Selectable::node(0),
+            PropertyMap::new().with_attribute("a".to_owned(), "1".to_owned()),
+        ),
+        (
+            Selectable::node(1),
+            PropertyMap::new()
+                .with_display(DisplayMode::Connector)
+                .with_fragment_attribute(FragmentKey::End, "b".to_owned(), "2".to_owned()),
+        ),
+    ]
+    .into();
+    let actual = [(
+        Selectable::node(0),
+        PropertyMap::new()
+            .with_attribute("a".to_owned(), "2".to_owned())
+            .with_stable_key("x".to_owned()),
+    )]
+    .into();
+    let diff = diff_mappings(&expected, &actual);
+    assert_eq!(
+        diff.to_string(),
+        [
+            "0 a: expected \"1\", found \"2\"",
+            "0 stable-key: expected nothing, found \"x\"",
+            "1 display: expected \"connector\", found nothing",
+            "1 end/b: expected \"2\", found nothing",
+            "",
+        ]
+        .join("\n")
+    );
+    assert!(diff_mappings(&expected, &expected).is_empty());
+}
+
+#[test]
+fn equal_mappings() {
+    let mapping: EntityPropertyMapping<usize> = [(
+        Selectable::node(0),
+        PropertyMap::new().with_display(DisplayMode::ElementTag("cell".to_owned())),
+    )]
+    .into();
+    assert_mappings_eq!(mapping, mapping.clone());
+}
+
+/// Stylesheet that displays the root as a graph
+/// and its `"a"` and `"b"` successors as cells,
+/// with `"b"` inside the `p` successor of itself
+/// and a connector for the `"a"` edge.
+///
+/// ```text
+/// :: {
+///   display: graph;
+/// }
+/// :: "a" {
+///   display: cell;
+///   value: @;
+/// }
+/// :: "b" {
+///   display: cell;
+///   value: @;
+///   parent: @(p);
+/// }
+/// :: "a"::edge {
+///   display: connector;
+/// }
+/// ```
+fn render_stylesheet() -> CompiledStylesheet<PropertyKey> {
+    let cell_clauses = || {
+        vec![
+            StyleClause {
+                key: Property(Display),
+                value: Expression::String("cell".to_owned()),
+            },
+            StyleClause {
+                key: Property(Attribute("value".into())),
+                value: Expression::Select(LimitedSelector::default().into()),
+            },
+        ]
+    };
+    let named = |name: &str| {
+        Selector::from_path([SelectorSegment::Match(EdgeMatcher::Named(name.into()))].into())
+    };
+    let mut b_clauses = cell_clauses();
+    b_clauses.push(StyleClause {
+        key: Property(Parent),
+        value: Expression::Select(
+            LimitedSelector::from_path([EdgeLabel::Named("p".into(), 0).into()]).into(),
+        ),
+    });
+    CompiledStylesheet::compile(Stylesheet::new(vec![
+        StyleRule {
+            selector: Selector::default(),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("graph".to_owned()),
+            }],
+        },
+        StyleRule {
+            selector: named("a"),
+            properties: cell_clauses(),
+        },
+        StyleRule {
+            selector: named("b"),
+            properties: b_clauses,
+        },
+        StyleRule {
+            selector: named("a").selecting_edge(),
+            properties: vec![StyleClause {
+                key: Property(Display),
+                value: Expression::String("connector".to_owned()),
+            }],
+        },
+    ]))
+}
+
+#[test]
+fn render_snapshot() {
+    let harness = RenderHarness::new(
+        graph! {
+            0 -"a"-> 1 [val = 1];
+            0 -"b"-> 2 [val = 2];
+            2 -"p"-> 0;
+        },
+        render_stylesheet(),
+    );
+    harness.assert_snapshot(
+        r#"
+        #0 graph
+          #1 cell value="1"
+          #2 cell value="2"
+        connector #0 -> #1
+        "#,
+    );
+}
+
+#[test]
+fn snapshot_follows_reparenting() {
+    let mut harness = RenderHarness::new(
+        graph! {
+            0 -"a"-> 1 [val = 1];
+            0 -"b"-> 2 [val = 2];
+            2 -"p"-> 0;
+        },
+        render_stylesheet(),
+    );
+    harness.set_graph(graph! {
+        0 -"a"-> 1 [val = 1];
+        0 -"b"-> 2 [val = 2];
+        2 -"p"-> 1;
+    });
+    harness.assert_snapshot(
+        r#"
+        #0 graph
+          #1 cell value="1"
+            #2 cell value="2"
+        connector #0 -> #1
+        "#,
+    );
+}
+
+#[test]
+fn snapshot_follows_connector_retargeting() {
+    let mut harness = RenderHarness::new(
+        graph! {
+            0 -"a"-> 1 [val = 1];
+            0 -"b"-> 2 [val = 2];
+            2 -"p"-> 0;
+        },
+        render_stylesheet(),
+    );
+    harness.set_graph(graph! {
+        0 -"a"-> 2 [val = 2];
+        0 -"b"-> 1 [val = 1];
+        1 -"p"-> 0;
+    });
+    harness.assert_snapshot(
+        r#"
+        #0 graph
+          #1 cell value="1"
+          #2 cell value="2"
+        connector #0 -> #2
+        "#,
+    );
+}
+
+#[test]
+#[should_panic(expected = "visualization tree does not match snapshot")]
+fn mismatched_snapshot() {
+    let harness = RenderHarness::new(graph! {}, render_stylesheet());
+    harness.assert_snapshot("#0 cell");
+}
+
+#[test]
+fn snapshots_are_normalized() {
+    assert_eq!(
+        normalize_snapshot("\n    #0 graph\n      #1 cell  \n\n  "),
+        "#0 graph\n  #1 cell\n",
+    );
+    assert_eq!(normalize_snapshot(""), "");
+}
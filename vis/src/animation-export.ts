@@ -0,0 +1,88 @@
+/**
+ * Export of a recorded sequence of visualization steps
+ * into frames of an animation.
+ *
+ * @module
+ */
+
+import { exportStaticHtml, StaticExportOptions } from './static-export';
+
+/**
+ * A single recorded step of a trace.
+ *
+ * When invoked, the step brings the visualization to the state
+ * that it had at the moment the step was recorded, typically
+ * by applying changes to the visualization tree.
+ */
+export type TraceStep = () => void | Promise<void>;
+
+/**
+ * Converts a rendered step into an image frame.
+ *
+ * Rasterization depends on the environment, so it is left to the caller.
+ * In a browser, the document can, for example, be drawn onto a canvas
+ * through an SVG `foreignObject`, and the frames can then be passed
+ * to a GIF or video encoder.
+ *
+ * @typeParam T Type of the produced frames.
+ * @param document Standalone HTML document that shows the step,
+ *        as produced by {@link exportStaticHtml}.
+ * @param index Zero-based index of the step in the trace.
+ * @returns The frame that represents the step.
+ */
+export type FrameRasterizer<T> = (document: string, index: number) => T | Promise<T>;
+
+/**
+ * Options for {@link exportAnimationFrames}.
+ */
+export interface AnimationExportOptions extends StaticExportOptions {
+    /**
+     * Waits until the layout of the visualization has settled after a step.
+     *
+     * @defaultValue Waits for two animation frames,
+     *               so that deferred layout updates can take place.
+     */
+    settle?: () => Promise<void>;
+}
+
+/**
+ * Renders each step of a recorded trace into an image frame.
+ *
+ * Steps are applied one by one. After each step, the content
+ * is exported with {@link exportStaticHtml} and the result
+ * is passed to the rasterizer.
+ *
+ * @typeParam T Type of the produced frames.
+ * @param content The DOM element that displays the visualization,
+ *        typically a container of a {@link Viewport}.
+ * @param steps The recorded steps, in the order in which they should be played.
+ * @param rasterize Converts the rendered steps into frames.
+ * @param options Additional options for the export.
+ * @returns The frames, one for each step, in order.
+ */
+export async function exportAnimationFrames<T>(
+    content: Element,
+    steps: Iterable<TraceStep> | AsyncIterable<TraceStep>,
+    rasterize: FrameRasterizer<T>,
+    options: AnimationExportOptions = {},
+): Promise<T[]> {
+    const settle = options.settle ?? waitForLayout;
+    const frames: T[] = [];
+    for await (const step of steps) {
+        await step();
+        await settle();
+        frames.push(await rasterize(exportStaticHtml(content, options), frames.length));
+    }
+    return frames;
+}
+
+/**
+ * Waits until layout updates scheduled for the next animation frame take place.
+ */
+async function waitForLayout(): Promise<void> {
+    // Layout is scheduled in an animation frame, wait for one more
+    // so that changes made by the layout itself are rendered
+    for (let i = 0; i < 2; ++i) {
+        await new Promise(resolve => requestAnimationFrame(resolve));
+    }
+}
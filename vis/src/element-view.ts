@@ -4,6 +4,7 @@
  * @module
  */
 
+import { ObserverHandle } from 'aili-hooligan';
 import { ReadonlyVisElement, VisElement } from './tree';
 import { ViewModel, ViewLayoutMode } from './model';
 import { ElementViewSlot } from './slots';
@@ -95,6 +96,10 @@ class ElementViewImpl implements ElementView {
     constructor(element: ReadonlyVisElement, model: ViewModel) {
         this.element = element;
         this.model = model;
+        if (element.layer !== 0) {
+            model.useLayer?.(element.layer);
+        }
+        this.layerObserver = element.onLayerChanged.hook(layer => model.useLayer?.(layer));
     }
     useEmbedding(embedding: ViewEmbedding): void {
         const slotIsUpToDate = !!embedding.slot && embedding.slot === this.slot;
@@ -129,6 +134,7 @@ class ElementViewImpl implements ElementView {
         // Switch the view to unembedded
         // Slot switch handler should perform the necessary cleanup
         this.useEmbedding({});
+        this.layerObserver.unhook();
         // Drop the view model, nobody else should own it
         this.model.destroy();
     }
@@ -155,4 +161,5 @@ class ElementViewImpl implements ElementView {
     readonly model: ViewModel;
     private slot?: ElementViewSlot;
     private _hasExplicitEmbedding: boolean = false;
+    private readonly layerObserver: ObserverHandle;
 }
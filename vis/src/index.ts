@@ -3,12 +3,14 @@
  * @mergeModuleWith <project>
  */
 
+export * from './animation-export';
 export * as binds from './attribute-binds';
 export * from './attributes';
 export * from './model-factory';
 export * from './model';
 export * from './models';
 export * from './slots';
+export * from './static-export';
 export * from './tree';
 export * from './viewport';
 export * from './viewport-dom';
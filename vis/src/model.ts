@@ -78,6 +78,15 @@ export interface ViewModel {
      *        should unrender itself.
      */
     useSlot(populator: ViewSlotPopulator | undefined): void;
+    /**
+     * Moves the model's rendering to a different layer.
+     *
+     * Models that do not implement this draw all elements
+     * in the same layer.
+     *
+     * @param layer The new layer, see {@link ReadonlyVisElement.layer}.
+     */
+    useLayer?(layer: number): void;
     /**
      * Performs cleanup before the view model expires.
      *
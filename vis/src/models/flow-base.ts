@@ -8,6 +8,12 @@ import { ObserverHandle } from 'aili-hooligan';
 import { ViewLayoutMode, ViewModel } from '../model';
 import { NULL_SLOT, ElementViewSlot, ViewSlotPopulator } from '../slots';
 import { ReadonlyVisElement } from '../tree';
+import './flow-base.css';
+
+/**
+ * CSS class for the root element of a view in a layer other than zero.
+ */
+export const CLASS_LAYERED: string = 'aili-layered';
 
 /**
  * Base class for common view models that consist of a single
@@ -46,6 +52,10 @@ export abstract class FlowViewModel implements ViewModel {
             this.html.remove();
         }
     }
+    useLayer(layer: number): void {
+        this.html.classList.toggle(CLASS_LAYERED, layer !== 0);
+        this.html.style.zIndex = layer === 0 ? '' : String(layer);
+    }
     createInlineSlot(child: ReadonlyVisElement, childModel: ViewModel): ElementViewSlot {
         // The default view model does not allow inline children,
         // but implementations can override this
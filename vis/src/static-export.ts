@@ -0,0 +1,168 @@
+/**
+ * Export of a rendered visualization into a standalone HTML document.
+ *
+ * @module
+ */
+
+/**
+ * Options for {@link exportStaticHtml}.
+ */
+export interface StaticExportOptions {
+    /**
+     * Title of the exported document.
+     *
+     * @defaultValue `'Aili Visualization'`
+     */
+    title?: string;
+}
+
+/**
+ * Exports a snapshot of rendered DOM content into a standalone HTML document.
+ *
+ * All styles that apply to the content, including the positions of connectors
+ * that have been computed by the layout engine, are inlined into the elements,
+ * so the document does not need any stylesheets or scripts to display
+ * the snapshot as it appears at the moment of the export.
+ *
+ * The content must be attached to a document, otherwise its styles
+ * cannot be computed.
+ *
+ * @param content The DOM element whose content should be exported,
+ *        typically a container of a {@link Viewport}.
+ * @param options Additional options for the export.
+ * @returns Source of the HTML document.
+ */
+export function exportStaticHtml(content: Element, options: StaticExportOptions = {}): string {
+    const title = options.title ?? DEFAULT_TITLE;
+    const snapshot = content.cloneNode(true) as Element;
+    const defaults = new DefaultStyleCache(content.ownerDocument);
+    try {
+        inlineComputedStyles(content, snapshot, defaults);
+    } finally {
+        defaults.destroy();
+    }
+    removeScripts(snapshot);
+    return [
+        '<!DOCTYPE html>',
+        '<html>',
+        '<head>',
+        '<meta charset="utf-8">',
+        `<title>${escapeHtml(title)}</title>`,
+        '</head>',
+        '<body>',
+        snapshot.outerHTML,
+        '</body>',
+        '</html>',
+        '',
+    ].join('\n');
+}
+
+const DEFAULT_TITLE: string = 'Aili Visualization';
+
+/**
+ * Copies computed styles of a source element and its descendants
+ * into the style attributes of their clones.
+ *
+ * Only properties whose values differ from the defaults
+ * for the given element type are copied, to keep the output small.
+ *
+ * @param source The original element.
+ * @param target Clone of the original element.
+ * @param defaults Default styles of elements.
+ */
+function inlineComputedStyles(
+    source: Element,
+    target: Element,
+    defaults: DefaultStyleCache,
+): void {
+    const view = source.ownerDocument.defaultView;
+    if (view) {
+        const computed = view.getComputedStyle(source);
+        const reference = defaults.get(source);
+        const declarations: string[] = [];
+        for (const property of Array.from(computed)) {
+            const value = computed.getPropertyValue(property);
+            if (value !== reference.get(property)) {
+                declarations.push(`${property}: ${value};`);
+            }
+        }
+        if (declarations.length > 0) {
+            target.setAttribute('style', declarations.join(' '));
+        } else {
+            target.removeAttribute('style');
+        }
+    }
+    // Clone has the same structure, so children can be matched by index
+    for (let i = 0; i < source.children.length; ++i) {
+        inlineComputedStyles(source.children[i], target.children[i], defaults);
+    }
+}
+
+/**
+ * Removes all scripts from a DOM subtree.
+ *
+ * @param root Root of the subtree.
+ */
+function removeScripts(root: Element): void {
+    root.querySelectorAll('script').forEach(script => script.remove());
+}
+
+/**
+ * Escapes special characters in text that is inserted into HTML.
+ *
+ * @param text The text to escape.
+ * @returns The escaped text.
+ */
+function escapeHtml(text: string): string {
+    return text
+        .replace(/&/g, '&amp;')
+        .replace(/</g, '&lt;')
+        .replace(/>/g, '&gt;')
+        .replace(/"/g, '&quot;');
+}
+
+/**
+ * Provides computed styles of unstyled elements of each type.
+ */
+class DefaultStyleCache {
+    constructor(document: Document) {
+        this.container = document.createElement('div');
+        this.container.style.display = 'none';
+        document.body.append(this.container);
+    }
+    /**
+     * Gets the computed style of an unstyled element
+     * of the same type as a provided element.
+     *
+     * @param element Element whose type should be considered.
+     * @returns Values of all style properties of the default style.
+     */
+    get(element: Element): ReadonlyMap<string, string> {
+        const key = `${element.namespaceURI} ${element.localName}`;
+        let style = this.styles.get(key);
+        if (!style) {
+            const document = this.container.ownerDocument;
+            const reference = document.createElementNS(element.namespaceURI, element.localName);
+            this.container.append(reference);
+            const view = document.defaultView!;
+            // Copy the values, the live declaration would change when the reference is removed
+            const computed = view.getComputedStyle(reference);
+            style = new Map(
+                Array.from(computed).map(property => [
+                    property,
+                    computed.getPropertyValue(property),
+                ]),
+            );
+            this.styles.set(key, style);
+        }
+        return style;
+    }
+    /**
+     * Removes the helper elements from the document.
+     */
+    destroy(): void {
+        this.container.remove();
+    }
+    private readonly container: HTMLElement;
+    private readonly styles: Map<string, ReadonlyMap<string, string>> = new Map();
+}
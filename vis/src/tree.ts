@@ -48,6 +48,14 @@ export interface ReadonlyVisElement {
      * Mirrors the {@link ReadonlyVisConnector.projectedParent} property.
      */
     readonly projectedConnectors: Iterable<ReadonlyVisConnector>;
+    /**
+     * Layer that the element is drawn in. Elements in higher layers
+     * are drawn above elements in lower layers, regardless
+     * of their position in the tree.
+     *
+     * Elements are in layer zero unless specified otherwise.
+     */
+    readonly layer: number;
     /**
      * Triggers when a pin is added to {@link pins}.
      * The added pin is also passed as the argument to the observers.
@@ -99,6 +107,14 @@ export interface ReadonlyVisElement {
     readonly onParentChanged: Hookable<
         [ReadonlyVisElement | undefined, ReadonlyVisElement | undefined]
     >;
+    /**
+     * Triggers when the {@link layer} changes.
+     * The new and previous layers are also passed as arguments
+     * to the observers.
+     *
+     * @event
+     */
+    readonly onLayerChanged: Hookable<[number, number]>;
 }
 
 /**
@@ -131,6 +147,19 @@ export interface ReadonlyVisConnector {
      * Mirrored by the {@link ReadonlyVisElement.projectedConnectors} property.
      */
     readonly projectedParent: ReadonlyVisElement | undefined;
+    /**
+     * Pins of other connectors that are attached to the midpoint of this connector.
+     *
+     * Mirrors the {@link ReadonlyVisPin.targetConnector} property.
+     */
+    readonly pins: Iterable<ReadonlyVisPin>;
+    /**
+     * Triggers when a pin is added to {@link pins}.
+     * The added pin is also passed as the argument to the observers.
+     *
+     * @event
+     */
+    readonly onAddPin: Hookable<[ReadonlyVisPin]>;
     /**
      * Triggers when the {@link projectedParent} property changes.
      * The new and previous projected parent elements are also passed as arguments
@@ -169,6 +198,16 @@ export interface ReadonlyVisPin {
      * Mirrored by the {@link ReadonlyVisElement.pins} property.
      */
     readonly target: ReadonlyVisElement | undefined;
+    /**
+     * The connector whose midpoint the pin is attached to,
+     * if it is attached to a connector instead of an element.
+     * At most one of {@link target} and {@link targetConnector} is set.
+     *
+     * Pins attached to connectors have no projection.
+     *
+     * Mirrored by the {@link ReadonlyVisConnector.pins} property.
+     */
+    readonly targetConnector: ReadonlyVisConnector | undefined;
     /**
      * The element that the pin's projection is attached to.
      *
@@ -194,6 +233,19 @@ export interface ReadonlyVisPin {
     readonly onTargetChanged: Hookable<
         [ReadonlyVisElement | undefined, ReadonlyVisElement | undefined]
     >;
+    /**
+     * Triggers when the {@link targetConnector} property changes.
+     * The new and previous target connectors are also passed as arguments
+     * to the observers.
+     *
+     * The hook is triggered before the matching call to {@link ReadonlyVisConnector.onAddPin}
+     * of the new target connector.
+     *
+     * @event
+     */
+    readonly onTargetConnectorChanged: Hookable<
+        [ReadonlyVisConnector | undefined, ReadonlyVisConnector | undefined]
+    >;
     /**
      * Triggers when the {@link projectedTarget} property changes.
      * The new and previous projected target elements are also passed
@@ -227,6 +279,7 @@ export class VisElement implements ReadonlyVisElement {
         this.onAddProjectedConnector = new Hook();
         this.onAddChild = new Hook();
         this.onParentChanged = new Hook();
+        this.onLayerChanged = new Hook();
     }
     readonly tagName: string;
     readonly attributes: AttributeMap;
@@ -239,6 +292,7 @@ export class VisElement implements ReadonlyVisElement {
     readonly onAddProjectedConnector: Hook<[VisConnector]>;
     readonly onAddChild: Hook<[VisElement]>;
     readonly onParentChanged: Hook<[VisElement | undefined, VisElement | undefined]>;
+    readonly onLayerChanged: Hook<[number, number]>;
     get parent(): VisElement | undefined {
         return this._parent;
     }
@@ -265,6 +319,21 @@ export class VisElement implements ReadonlyVisElement {
         parent?.onAddChild?.trigger(this);
         this.updateConnectorProjectionsRecursive();
     }
+    get layer(): number {
+        return this._layer;
+    }
+    /**
+     * Move the element to a different layer.
+     */
+    set layer(layer: number) {
+        if (layer === this._layer) {
+            // No-op
+            return;
+        }
+        const previousLayer = this._layer;
+        this._layer = layer;
+        this.onLayerChanged.trigger(layer, previousLayer);
+    }
     private isSameOrAncestorOf(other: VisElement | undefined): boolean {
         while (other) {
             if (this === other) {
@@ -296,6 +365,7 @@ export class VisElement implements ReadonlyVisElement {
         return path;
     }
     private _parent: VisElement | undefined = undefined;
+    private _layer: number = 0;
 }
 
 /**
@@ -315,6 +385,7 @@ export class VisPin implements ReadonlyVisPin {
         this.attributes = new AttributeMap();
         this.connector = connector;
         this.onTargetChanged = new Hook();
+        this.onTargetConnectorChanged = new Hook();
         this.onProjectedTargetChanged = new Hook();
     }
     readonly attributes: AttributeMap;
@@ -322,15 +393,30 @@ export class VisPin implements ReadonlyVisPin {
     get target(): VisElement | undefined {
         return this._target;
     }
+    get targetConnector(): VisConnector | undefined {
+        return this._targetConnector;
+    }
     get projectedTarget(): VisElement | undefined {
         return this._projectedTarget;
     }
     readonly onTargetChanged: Hook<[VisElement | undefined, VisElement | undefined]>;
+    readonly onTargetConnectorChanged: Hook<[VisConnector | undefined, VisConnector | undefined]>;
     readonly onProjectedTargetChanged: Hook<[VisElement | undefined, VisElement | undefined]>;
     /**
-     * Attach the pin to a new target or detach it from its current parent.
+     * Attach the pin to a new target element or to the midpoint of a connector,
+     * or detach it from its current target.
      */
-    set target(target: VisElement | undefined) {
+    set target(target: VisElement | VisConnector | undefined) {
+        // The pin lets go of its previous target before it attaches to the new one
+        if (target instanceof VisConnector) {
+            this.setTargetElement(undefined);
+            this.setTargetConnector(target);
+        } else {
+            this.setTargetConnector(undefined);
+            this.setTargetElement(target);
+        }
+    }
+    private setTargetElement(target: VisElement | undefined): void {
         if (target === this._target) {
             // No-op
             return;
@@ -345,10 +431,25 @@ export class VisPin implements ReadonlyVisPin {
         target?.onAddPin?.trigger(this);
         this.connector._updateProjection();
     }
+    private setTargetConnector(target: VisConnector | undefined): void {
+        if (target === this._targetConnector) {
+            // No-op
+            return;
+        } else if (this._targetConnector) {
+            // Original target must let go first
+            this._targetConnector.pins.delete(this);
+        }
+        const previousTarget = this._targetConnector;
+        this._targetConnector = target;
+        target?.pins?.add(this);
+        this.onTargetConnectorChanged.trigger(target, previousTarget);
+        target?.onAddPin?.trigger(this);
+    }
     /**
      * @internal
      */
     _target: VisElement | undefined = undefined;
+    private _targetConnector: VisConnector | undefined = undefined;
     /**
      * @internal
      */
@@ -363,11 +464,15 @@ export class VisConnector implements ReadonlyVisConnector {
         this.attributes = new AttributeMap();
         this.start = new VisPin(this);
         this.end = new VisPin(this);
+        this.pins = new Set();
+        this.onAddPin = new Hook();
         this.onProjectedParentChanged = new Hook();
     }
     readonly attributes: AttributeMap;
     readonly start: VisPin;
     readonly end: VisPin;
+    readonly pins: Set<VisPin>;
+    readonly onAddPin: Hook<[VisPin]>;
     get projectedParent(): VisElement | undefined {
         return this._projectedParent;
     }
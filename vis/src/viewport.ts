@@ -10,6 +10,13 @@ import { ConnectorViewContainer } from './connector-view';
 import { TreeView } from './tree-view';
 import { ViewportDOMRoot } from './viewport-dom';
 import { ContextFreeViewModelFactory, ViewModelFactory } from './model-factory';
+import { exportStaticHtml, StaticExportOptions } from './static-export';
+import {
+    AnimationExportOptions,
+    exportAnimationFrames,
+    FrameRasterizer,
+    TraceStep,
+} from './animation-export';
 
 /**
  * The main rendering class that can be attached to the DOM
@@ -32,6 +39,7 @@ export class Viewport {
         const view = new TreeView(elementViews, connectorViews);
         this.treeView = view;
         this.rootDom = root;
+        this.container = container;
     }
     /**
      * Sets the element that is at the root of the viewport.
@@ -59,7 +67,37 @@ export class Viewport {
     get root(): ReadonlyVisElement | undefined {
         return this.currentRoot;
     }
+    /**
+     * Exports the current rendering of the viewport
+     * into a standalone HTML document.
+     *
+     * @param options Additional options for the export.
+     * @returns Source of the HTML document.
+     * @see {@link exportStaticHtml}
+     */
+    exportStaticHtml(options?: StaticExportOptions): string {
+        return exportStaticHtml(this.container, options);
+    }
+    /**
+     * Renders each step of a recorded trace in the viewport
+     * and exports it into an image frame.
+     *
+     * @typeParam T Type of the produced frames.
+     * @param steps The recorded steps, in the order in which they should be played.
+     * @param rasterize Converts the rendered steps into frames.
+     * @param options Additional options for the export.
+     * @returns The frames, one for each step, in order.
+     * @see {@link exportAnimationFrames}
+     */
+    exportAnimationFrames<T>(
+        steps: Iterable<TraceStep> | AsyncIterable<TraceStep>,
+        rasterize: FrameRasterizer<T>,
+        options?: AnimationExportOptions,
+    ): Promise<T[]> {
+        return exportAnimationFrames(this.container, steps, rasterize, options);
+    }
     private treeView: TreeView;
+    private container: HTMLElement;
     private rootDom: ViewportDOMRoot;
     private currentRoot: ReadonlyVisElement | undefined;
 }
@@ -0,0 +1,69 @@
+/**
+ * @jest-environment jsdom
+ */
+
+import { afterEach, beforeEach, describe, expect, it } from '@jest/globals';
+import { exportAnimationFrames } from '../../src/animation-export';
+
+describe(exportAnimationFrames, () => {
+    let container: HTMLElement;
+
+    beforeEach(() => {
+        container = document.createElement('div');
+        document.body.append(container);
+    });
+
+    afterEach(() => {
+        container.remove();
+    });
+
+    const settle = () => Promise.resolve();
+
+    it('produces one frame per step', async () => {
+        const steps = ['first', 'second', 'third'].map(text => () => {
+            container.textContent = text;
+        });
+        const frames = await exportAnimationFrames(
+            container,
+            steps,
+            (html, index) => ({ html, index }),
+            { settle },
+        );
+        expect(frames).toHaveLength(3);
+        expect(frames.map(frame => frame.index)).toEqual([0, 1, 2]);
+        expect(frames[0].html).toContain('first');
+        expect(frames[1].html).toContain('second');
+        expect(frames[2].html).toContain('third');
+    });
+
+    it('waits for asynchronous steps and rasterizers', async () => {
+        async function* steps() {
+            yield async () => {
+                await Promise.resolve();
+                container.textContent = 'done';
+            };
+        }
+        const frames = await exportAnimationFrames(
+            container,
+            steps(),
+            async html => html.includes('done'),
+            { settle },
+        );
+        expect(frames).toEqual([true]);
+    });
+
+    it('waits for the layout to settle before each frame', async () => {
+        const events: string[] = [];
+        await exportAnimationFrames(
+            container,
+            [() => void events.push('step'), () => void events.push('step')],
+            () => events.push('frame'),
+            {
+                settle: async () => {
+                    events.push('settle');
+                },
+            },
+        );
+        expect(events).toEqual(['step', 'settle', 'frame', 'step', 'settle', 'frame']);
+    });
+});
@@ -0,0 +1,57 @@
+/**
+ * @jest-environment jsdom
+ */
+
+import { afterEach, beforeEach, describe, expect, it } from '@jest/globals';
+import { exportStaticHtml } from '../../src/static-export';
+
+describe(exportStaticHtml, () => {
+    let container: HTMLElement;
+    let style: HTMLStyleElement;
+
+    beforeEach(() => {
+        style = document.createElement('style');
+        style.textContent = '.highlight { color: red; }';
+        document.head.append(style);
+        container = document.createElement('div');
+        document.body.append(container);
+    });
+
+    afterEach(() => {
+        container.remove();
+        style.remove();
+    });
+
+    it('produces a standalone document', () => {
+        container.textContent = 'Hello';
+        const html = exportStaticHtml(container, { title: 'A < B' });
+        expect(html).toMatch(/^<!DOCTYPE html>/);
+        expect(html).toContain('<title>A &lt; B</title>');
+        expect(html).toContain('Hello');
+    });
+
+    it('inlines styles from stylesheets', () => {
+        const element = document.createElement('span');
+        element.className = 'highlight';
+        container.append(element);
+        const html = exportStaticHtml(container);
+        expect(html).toMatch(/<span class="highlight" style="[^"]*color: (red|rgb\(255, 0, 0\));/);
+    });
+
+    it('removes scripts', () => {
+        const script = document.createElement('script');
+        script.textContent = 'void 0;';
+        container.append(script);
+        const html = exportStaticHtml(container);
+        expect(html).not.toContain('<script');
+    });
+
+    it('does not modify the exported content', () => {
+        const element = document.createElement('span');
+        element.className = 'highlight';
+        container.append(element);
+        exportStaticHtml(container);
+        expect(element.hasAttribute('style')).toBe(false);
+        expect(document.body.children).toHaveLength(1);
+    });
+});
@@ -40,6 +40,10 @@ describe(VisElement, () => {
         it('has no projected connectors', () => {
             expect(element.projectedConnectors).not.toContainEqual(expect.anything());
         });
+
+        it('is in layer zero', () => {
+            expect(element.layer).toBe(0);
+        });
     });
 
     describe('Element inserted into a parent', () => {
@@ -158,6 +162,36 @@ describe(VisElement, () => {
         });
     });
 
+    describe('Layer change observer', () => {
+        const mockObserver = jest.fn().mockName('layerChangedObserver');
+
+        beforeEach(() => {
+            mockObserver.mockClear();
+        });
+
+        it('triggers when an element is moved to another layer', () => {
+            element.onLayerChanged.hook(mockObserver);
+            element.layer = 2;
+            expect(mockObserver).toHaveBeenCalledWith(2, 0);
+        });
+
+        it('does not trigger when an element is moved to its current layer', () => {
+            element.layer = 2;
+            element.onLayerChanged.hook(mockObserver);
+            element.layer = 2;
+            expect(mockObserver).not.toHaveBeenCalled();
+        });
+
+        it('triggers after relevant properties have been updated', () => {
+            mockObserver.mockImplementationOnce(() => {
+                expect(element.layer).toBe(2);
+            });
+            element.onLayerChanged.hook(mockObserver);
+            element.layer = 2;
+            expect(mockObserver).toHaveBeenCalled();
+        });
+    });
+
     describe('Child insertion observer', () => {
         const mockObserver = jest.fn().mockName('addChildObserver');
         let child: VisElement;
@@ -563,6 +597,73 @@ describe(VisConnector, () => {
         });
     });
 
+    describe('Pin attached to another connector', () => {
+        let target: VisConnector;
+
+        beforeEach(() => {
+            target = new VisConnector();
+            connector.start.target = target;
+        });
+
+        it('has the connector set as its target connector', () => {
+            expect(connector.start.targetConnector).toBe(target);
+        });
+
+        it('has no target element', () => {
+            expect(connector.start.target).toBeUndefined();
+        });
+
+        it('is a pin of the target connector', () => {
+            expect(target.pins).toContain(connector.start);
+        });
+
+        it('has no projection', () => {
+            expect(connector.start.projectedTarget).toBeUndefined();
+        });
+    });
+
+    describe('Pin moved from an element to another connector', () => {
+        let element: VisElement;
+        let target: VisConnector;
+
+        beforeEach(() => {
+            element = new VisElement(ELEMENT_TAG_NAME);
+            target = new VisConnector();
+            connector.start.target = element;
+            connector.start.target = target;
+        });
+
+        it('has the connector set as its target connector', () => {
+            expect(connector.start.targetConnector).toBe(target);
+        });
+
+        it('is not a pin of its former target', () => {
+            expect(connector.start.target).toBeUndefined();
+            expect(element.pins).not.toContain(connector.start);
+        });
+    });
+
+    describe('Pin moved from another connector to an element', () => {
+        let element: VisElement;
+        let target: VisConnector;
+
+        beforeEach(() => {
+            element = new VisElement(ELEMENT_TAG_NAME);
+            target = new VisConnector();
+            connector.start.target = target;
+            connector.start.target = element;
+        });
+
+        it('has the element set as its target', () => {
+            expect(connector.start.target).toBe(element);
+        });
+
+        it('is not a pin of its former target connector', () => {
+            expect(connector.start.targetConnector).toBeUndefined();
+            expect(target.pins).not.toContain(connector.start);
+        });
+    });
+
     describe('Connector attached to sibling elements', () => {
         let parent: VisElement;
         let left: VisElement;
@@ -941,6 +1042,47 @@ describe(VisPin, () => {
         });
     });
 
+    describe('Target connector change observer', () => {
+        const mockObserver = jest.fn().mockName('targetConnectorChangedObserver');
+        let target: VisConnector;
+
+        beforeEach(() => {
+            mockObserver.mockClear();
+            target = new VisConnector();
+        });
+
+        it('triggers when a pin is attached to a connector', () => {
+            connector.start.onTargetConnectorChanged.hook(mockObserver);
+            connector.start.target = target;
+            expect(mockObserver).toHaveBeenCalledWith(target, undefined);
+        });
+
+        it('triggers when a pin is detached from a connector', () => {
+            connector.start.target = target;
+            connector.start.onTargetConnectorChanged.hook(mockObserver);
+            connector.start.target = undefined;
+            expect(mockObserver).toHaveBeenCalledWith(undefined, target);
+        });
+
+        it('does not trigger when a pin is moved to its current target connector', () => {
+            connector.start.target = target;
+            connector.start.onTargetConnectorChanged.hook(mockObserver);
+            connector.start.target = target;
+            expect(mockObserver).not.toHaveBeenCalled();
+        });
+
+        it('triggers before the pin insertion observer of the target connector', () => {
+            const mockAddPinObserver = jest.fn().mockName('addPinObserver');
+            connector.start.onTargetConnectorChanged.hook(mockObserver);
+            target.onAddPin.hook(mockAddPinObserver);
+            connector.start.target = target;
+            expect(mockAddPinObserver).toHaveBeenCalledWith(connector.start);
+            expect(mockAddPinObserver.mock.invocationCallOrder[0]).toBeGreaterThan(
+                mockObserver.mock.invocationCallOrder[0],
+            );
+        });
+    });
+
     describe('Projected target change observer', () => {
         const mockStartObserver = jest.fn().mockName('projectedTargetChangedStartObserver');
         const mockEndObserver = jest.fn().mockName('projectedTargetChangedEndObserver');